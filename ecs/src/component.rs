@@ -1,4 +1,5 @@
-use std::{any::Any, fmt::Debug};
+use alloc::boxed::Box;
+use core::{any::Any, fmt::Debug};
 
 pub trait Component: Any + Debug {}
 
@@ -9,4 +10,7 @@ impl dyn Component {
     pub(crate) fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
         (self as &mut dyn Any).downcast_mut()
     }
+    pub(crate) fn downcast<T: Any>(self: Box<Self>) -> Option<Box<T>> {
+        (self as Box<dyn Any>).downcast().ok()
+    }
 }
@@ -0,0 +1,94 @@
+use core::fmt;
+
+use crate::{component::Component, error::EcsError, world::World};
+
+// despawned slots are reused, the generation tells a reused slot's entity apart
+// from the one that had it before
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    pub(crate) index: usize,
+    pub(crate) generation: u32,
+}
+
+impl Entity {
+    // the entity's slot in the component storage, for packing into gpu ids and the like.
+    // `World::entity_at` turns it back into the entity living there
+    pub fn index(self) -> usize {
+        self.index
+    }
+
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Debug for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Entity({}v{})", self.index, self.generation)
+    }
+}
+
+// for components that refer to other entities, so the references follow them
+// when entities move to another world. registered with `World::register_entity_refs`
+pub trait MapEntities {
+    // `map` gives an entity's id after the move, None when it stays behind or
+    // isn't moving. returning false drops the component
+    fn map_entities(&mut self, map: &dyn Fn(Entity) -> Option<Entity>) -> bool;
+}
+
+pub struct EntityWorld<'a> {
+    pub(crate) world: &'a mut World,
+    pub(crate) entity: Entity,
+}
+
+impl EntityWorld<'_> {
+    pub fn insert<T: Component + 'static>(self, component: T) -> Self {
+        self.world.add_component(self.entity, component);
+        self
+    }
+
+    pub fn remove<T: Component + 'static>(self) -> Self {
+        self.world.remove_component::<T>(self.entity);
+        self
+    }
+
+    pub fn id(self) -> Entity {
+        self.entity
+    }
+
+    pub fn despawn(self) {
+        self.world.despawn(self.entity);
+    }
+
+    pub fn get_component<T: Component + 'static>(&self) -> Option<&T> {
+        self.world.get_component::<T>(self.entity)
+    }
+
+    pub fn get_component_mut<T: Component + 'static>(&mut self) -> Option<&mut T> {
+        self.world.get_component_mut::<T>(self.entity)
+    }
+
+    pub fn try_component<T: Component + 'static>(&self) -> Result<&T, EcsError> {
+        self.world.try_component::<T>(self.entity)
+    }
+
+    pub fn try_component_mut<T: Component + 'static>(&mut self) -> Result<&mut T, EcsError> {
+        self.world.try_component_mut::<T>(self.entity)
+    }
+
+    #[track_caller]
+    pub fn component<T: Component + 'static>(&self) -> &T {
+        self.try_component::<T>().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    #[track_caller]
+    pub fn component_mut<T: Component + 'static>(&mut self) -> &mut T {
+        self.try_component_mut::<T>()
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn print_components(&self) {
+        self.world.print_components(self.entity);
+    }
+}
@@ -0,0 +1,33 @@
+use core::fmt;
+
+use crate::entity::Entity;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcsError {
+    ComponentMissing {
+        entity: Entity,
+        component: &'static str,
+    },
+    ResourceMissing(&'static str),
+    // `single` with none or more than one of the component around
+    NotSingle {
+        component: &'static str,
+        count: usize,
+    },
+}
+
+impl fmt::Display for EcsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ComponentMissing { entity, component } => {
+                write!(f, "{entity:?} has no {component}")
+            }
+            Self::ResourceMissing(resource) => write!(f, "Resource not found: {resource}"),
+            Self::NotSingle { component, count } => {
+                write!(f, "Expected exactly one {component}, found {count}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for EcsError {}
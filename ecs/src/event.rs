@@ -0,0 +1,47 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::{component::Component, world::World};
+
+// events live from when they're sent until the end of the frame, so systems
+// that run before the sender only see them if they came in between frames
+#[derive(Debug)]
+pub struct Events<T> {
+    events: Vec<T>,
+}
+
+impl<T: Debug + 'static> Component for Events<T> {}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+pub(crate) fn clear_events<T: Debug + 'static>(world: &mut World) {
+    if let Some(events) = world.get_resource_mut::<Events<T>>() {
+        events.clear();
+    }
+}
@@ -0,0 +1,17 @@
+// ECS - the naive way
+// Only needs alloc, so servers and tools can use it without the renderer.
+// Without the default "std" feature it builds for no_std targets.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod component;
+pub mod entity;
+pub mod error;
+pub mod event;
+pub mod schedule;
+pub mod system;
+pub mod world;
+
+pub use error::EcsError;
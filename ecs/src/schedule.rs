@@ -0,0 +1,76 @@
+// Schedule labels. A label is usually a unit struct, or an enum with a name
+// for each variant, so a typo is a compile error instead of a schedule that
+// never runs. Schedules are found by the label's type and then its value. The
+// name is only read when a label is first seen, the engine's own labels keep
+// the names the string api used so both reach the same schedule while code
+// moves over.
+
+// a schedule interned by the world it was first used in, for running it
+// without looking the label up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScheduleId(pub(crate) usize);
+
+// equal labels of a type are one schedule, so an enum's variants are separate ones
+pub trait ScheduleLabel: PartialEq + 'static {
+    // for the string api and for printing, unique per schedule. there's no
+    // default, the type's name would be the same for every variant
+    fn name(&self) -> &'static str;
+}
+
+// once, before the first frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Startup;
+
+impl ScheduleLabel for Startup {
+    fn name(&self) -> &'static str {
+        "startup"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Update;
+
+impl ScheduleLabel for Update {
+    fn name(&self) -> &'static str {
+        "update"
+    }
+}
+
+// as many times a frame as fixed steps are due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedUpdate;
+
+impl ScheduleLabel for FixedUpdate {
+    fn name(&self) -> &'static str {
+        "fixed_update"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ui;
+
+impl ScheduleLabel for Ui {
+    fn name(&self) -> &'static str {
+        "ui"
+    }
+}
+
+// after everything else, events are cleared here
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Last;
+
+impl ScheduleLabel for Last {
+    fn name(&self) -> &'static str {
+        "last"
+    }
+}
+
+// once, when the app is about to stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shutdown;
+
+impl ScheduleLabel for Shutdown {
+    fn name(&self) -> &'static str {
+        "shutdown"
+    }
+}
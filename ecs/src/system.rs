@@ -0,0 +1,54 @@
+// Systems are anything that runs on the world: plain functions, or closures
+// that capture their own settings and state. Both turn into a System with
+// `IntoSystem`, which is what schedules store.
+
+use alloc::boxed::Box;
+use core::fmt;
+
+use crate::world::World;
+
+pub struct System {
+    name: &'static str,
+    run: Box<dyn FnMut(&mut World)>,
+}
+
+impl System {
+    // for closures, whose type names all end in `{{closure}}`
+    pub fn named(name: &'static str, run: impl FnMut(&mut World) + 'static) -> Self {
+        Self {
+            name,
+            run: Box::new(run),
+        }
+    }
+
+    // the function's path, or the name it was given
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        (self.run)(world);
+    }
+}
+
+impl fmt::Debug for System {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("System").field("name", &self.name).finish()
+    }
+}
+
+pub trait IntoSystem {
+    fn into_system(self) -> System;
+}
+
+impl<F: FnMut(&mut World) + 'static> IntoSystem for F {
+    fn into_system(self) -> System {
+        System::named(core::any::type_name::<F>(), self)
+    }
+}
+
+impl IntoSystem for System {
+    fn into_system(self) -> System {
+        self
+    }
+}
@@ -0,0 +1,1017 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::any::{Any, TypeId};
+
+use hashbrown::HashMap;
+
+use crate::{
+    component::Component,
+    entity::{Entity, EntityWorld, MapEntities},
+    error::EcsError,
+    event::{Events, clear_events},
+    schedule::{Last, ScheduleId, ScheduleLabel},
+    system::{IntoSystem, System},
+};
+
+type EntityComponents = Option<Box<dyn Component>>;
+// a label of one type, boxed, and the schedule it interned
+type KnownLabel = (Box<dyn Any>, ScheduleId);
+// false when the component should be dropped
+type EntityMapper = fn(&mut dyn Component, &dyn Fn(Entity) -> Option<Entity>) -> bool;
+
+fn map_component<T: Component + MapEntities + 'static>(
+    component: &mut dyn Component,
+    map: &dyn Fn(Entity) -> Option<Entity>,
+) -> bool {
+    component
+        .downcast_mut::<T>()
+        .is_none_or(|component| component.map_entities(map))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EntitySlot {
+    generation: u32,
+    alive: bool,
+}
+// runs a schedule's systems, in order. replaced to wrap every system run, like
+// the engine's profiler does
+pub type ScheduleRunner = fn(&mut World, &'static str, &mut [System]);
+
+struct Schedule {
+    name: &'static str,
+    systems: Vec<System>,
+}
+
+pub struct World {
+    components: HashMap<TypeId, Vec<EntityComponents>>,
+    // only for printing
+    component_names: HashMap<TypeId, &'static str>,
+    // one per slot in the component storage, despawned ones are in `free`
+    entities: Vec<EntitySlot>,
+    free: Vec<usize>,
+    entity_mappers: HashMap<TypeId, EntityMapper>,
+    resources: HashMap<TypeId, Box<dyn Component>>,
+    // labels by type, then compared by value, so finding one doesn't hash its name
+    schedule_labels: HashMap<TypeId, Vec<KnownLabel>>,
+    // for the deprecated string api, every label's name is in here too so both
+    // reach the same schedule
+    schedule_names: HashMap<&'static str, ScheduleId>,
+    schedules: Vec<Schedule>,
+    schedule_runner: ScheduleRunner,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self {
+            components: HashMap::new(),
+            component_names: HashMap::new(),
+            entities: Vec::new(),
+            free: Vec::new(),
+            entity_mappers: HashMap::new(),
+            resources: HashMap::new(),
+            schedule_labels: HashMap::new(),
+            schedule_names: HashMap::new(),
+            schedules: Vec::new(),
+            schedule_runner: run_systems,
+        }
+    }
+}
+
+// the default ScheduleRunner
+pub fn run_systems(world: &mut World, _schedule: &'static str, systems: &mut [System]) {
+    for system in systems {
+        system.run(world);
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // sized for every entity there is, registering again keeps what's stored
+    pub fn register_component<T: Component + 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        let len = self.entities.len();
+        self.components
+            .entry(type_id)
+            .or_insert_with(|| (0..len).map(|_| None).collect());
+        self.component_names
+            .insert(type_id, core::any::type_name::<T>());
+    }
+
+    // lets `move_entities` fix up the entities T refers to
+    pub fn register_entity_refs<T: Component + MapEntities + 'static>(&mut self) {
+        self.entity_mappers
+            .insert(TypeId::of::<T>(), map_component::<T>);
+    }
+
+    pub fn init_resource<T: Component + Default + 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        self.resources.insert(type_id, Box::new(T::default()));
+    }
+
+    pub fn insert_resource<T: Component + 'static>(&mut self, resource: T) {
+        let type_id = TypeId::of::<T>();
+        self.resources.insert(type_id, Box::new(resource));
+    }
+
+    pub fn remove_resource<T: Component + 'static>(&mut self) -> Option<T> {
+        let type_id = TypeId::of::<T>();
+        self.resources
+            .remove(&type_id)?
+            .downcast::<T>()
+            .map(|resource| *resource)
+    }
+
+    pub fn get_resource<T: Component + 'static>(&self) -> Option<&T> {
+        let type_id = TypeId::of::<T>();
+        self.resources.get(&type_id)?.as_ref().downcast_ref::<T>()
+    }
+
+    pub fn get_resource_mut<T: Component + 'static>(&mut self) -> Option<&mut T> {
+        let type_id = TypeId::of::<T>();
+        self.resources
+            .get_mut(&type_id)?
+            .as_mut()
+            .downcast_mut::<T>()
+    }
+
+    pub fn try_resource<T: Component + 'static>(&self) -> Result<&T, EcsError> {
+        self.get_resource::<T>()
+            .ok_or(EcsError::ResourceMissing(core::any::type_name::<T>()))
+    }
+
+    pub fn try_resource_mut<T: Component + 'static>(&mut self) -> Result<&mut T, EcsError> {
+        self.get_resource_mut::<T>()
+            .ok_or(EcsError::ResourceMissing(core::any::type_name::<T>()))
+    }
+
+    #[track_caller]
+    pub fn resource<T: Component + 'static>(&self) -> &T {
+        self.try_resource::<T>().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    #[track_caller]
+    pub fn resource_mut<T: Component + 'static>(&mut self) -> &mut T {
+        self.try_resource_mut::<T>()
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // the resource is taken out while `f` runs, so it can be used alongside the
+    // rest of the world. one inserted by `f` is replaced when it's put back
+    pub fn try_resource_scope<T: Component + 'static, R>(
+        &mut self,
+        f: impl FnOnce(&mut World, &mut T) -> R,
+    ) -> Result<R, EcsError> {
+        let type_id = TypeId::of::<T>();
+        let missing = EcsError::ResourceMissing(core::any::type_name::<T>());
+        let mut resource = self.resources.remove(&type_id).ok_or(missing.clone())?;
+        let result = resource
+            .downcast_mut::<T>()
+            .map(|resource| f(self, resource));
+        self.resources.insert(type_id, resource);
+        result.ok_or(missing)
+    }
+
+    #[track_caller]
+    pub fn resource_scope<T: Component + 'static, R>(
+        &mut self,
+        f: impl FnOnce(&mut World, &mut T) -> R,
+    ) -> R {
+        self.try_resource_scope(f).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn print_resources(&self) {
+        for resource in self.resources.values() {
+            println!("Resource: {:?}", resource);
+        }
+    }
+
+    // reuses a despawned slot if there is one, so the storage only grows with
+    // the most entities alive at once
+    pub fn spawn(&'_ mut self) -> EntityWorld<'_> {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.entities[index].alive = true;
+                index
+            }
+            None => {
+                self.entities.push(EntitySlot {
+                    generation: 0,
+                    alive: true,
+                });
+                for components in self.components.values_mut() {
+                    components.push(None);
+                }
+                self.entities.len() - 1
+            }
+        };
+        let entity = Entity {
+            index,
+            generation: self.entities[index].generation,
+        };
+        EntityWorld {
+            world: self,
+            entity,
+        }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        for components in self.components.values_mut() {
+            components[entity.index] = None;
+        }
+        let slot = &mut self.entities[entity.index];
+        slot.alive = false;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(entity.index);
+    }
+
+    // false once it's despawned, even if its slot went to another entity since
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities
+            .get(entity.index)
+            .is_some_and(|slot| slot.alive && slot.generation == entity.generation)
+    }
+
+    // the entity alive in a slot, for indices that came from `Entity::index`
+    pub fn entity_at(&self, index: usize) -> Option<Entity> {
+        let slot = self.entities.get(index)?;
+        slot.alive.then_some(Entity {
+            index,
+            generation: slot.generation,
+        })
+    }
+
+    // entities alive right now
+    pub fn entity_count(&self) -> usize {
+        self.entities.len() - self.free.len()
+    }
+
+    // moves every component off the entity, for despawns that may be undone. the
+    // entity stays alive, so its slot isn't reused before they're restored
+    pub fn take_components(&mut self, entity: Entity) -> Vec<(TypeId, Box<dyn Component>)> {
+        if !self.is_alive(entity) {
+            return Vec::new();
+        }
+        self.components
+            .iter_mut()
+            .filter_map(|(type_id, components)| {
+                let component = components[entity.index].take()?;
+                Some((*type_id, component))
+            })
+            .collect()
+    }
+
+    pub fn restore_components(
+        &mut self,
+        entity: Entity,
+        components: Vec<(TypeId, Box<dyn Component>)>,
+    ) {
+        if !self.is_alive(entity) {
+            log::warn!("Components restored to despawned {:?}", entity);
+            return;
+        }
+        for (type_id, component) in components {
+            if let Some(slot) = self
+                .components
+                .get_mut(&type_id)
+                .and_then(|components| components.get_mut(entity.index))
+            {
+                *slot = Some(component);
+            }
+        }
+    }
+
+    // moves the entity and all its components to `other`, and returns its id
+    // there. None if it was already despawned
+    pub fn move_entity(&mut self, entity: Entity, other: &mut World) -> Option<Entity> {
+        self.move_entities(&[entity], other)[0]
+    }
+
+    // moves the entities together, references between them are kept and the ones
+    // to entities left behind are dropped, on both sides. only components
+    // registered with `register_entity_refs` are looked into
+    pub fn move_entities(&mut self, entities: &[Entity], other: &mut World) -> Vec<Option<Entity>> {
+        let mut moved: HashMap<Entity, Entity> = HashMap::new();
+        let ids: Vec<Option<Entity>> = entities
+            .iter()
+            .map(|&entity| {
+                if !self.is_alive(entity) {
+                    return None;
+                }
+                Some(*moved.entry(entity).or_insert_with(|| other.spawn().id()))
+            })
+            .collect();
+        let map = |entity: Entity| moved.get(&entity).copied();
+        let stays = |entity: Entity| (!moved.contains_key(&entity)).then_some(entity);
+
+        for (&entity, &new) in &moved {
+            for (type_id, mut component) in self.take_components(entity) {
+                if let Some(mapper) = self.entity_mappers.get(&type_id)
+                    && !mapper(component.as_mut(), &map)
+                {
+                    continue;
+                }
+                let len = other.entities.len();
+                other
+                    .components
+                    .entry(type_id)
+                    .or_insert_with(|| (0..len).map(|_| None).collect())[new.index] =
+                    Some(component);
+                other
+                    .component_names
+                    .insert(type_id, self.component_names[&type_id]);
+            }
+            self.despawn(entity);
+        }
+        for (type_id, mapper) in &self.entity_mappers {
+            other.entity_mappers.insert(*type_id, *mapper);
+            let Some(components) = self.components.get_mut(type_id) else {
+                continue;
+            };
+            for slot in components.iter_mut() {
+                if let Some(component) = slot
+                    && !mapper(component.as_mut(), &stays)
+                {
+                    *slot = None;
+                }
+            }
+        }
+        ids
+    }
+
+    #[cfg(feature = "std")]
+    pub fn print_entities(&self) {
+        for (type_id, components) in &self.components {
+            let type_name = self.component_names[type_id];
+            for (index, component) in components.iter().enumerate() {
+                if let Some(component) = component.as_ref() {
+                    println!(
+                        "Entity {} has component {}: {:?}",
+                        index, type_name, component
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn add_component<T: Component + 'static>(&mut self, entity: Entity, component: T) {
+        if !self.is_alive(entity) {
+            log::warn!(
+                "{} added to despawned {:?}",
+                core::any::type_name::<T>(),
+                entity
+            );
+            return;
+        }
+        let type_id = TypeId::of::<T>();
+        if let Some(components) = self.components.get_mut(&type_id) {
+            components[entity.index] = Some(Box::new(component));
+        } else {
+            self.register_component::<T>();
+            self.add_component(entity, component);
+        }
+    }
+
+    pub fn remove_component<T: Component + 'static>(&mut self, entity: Entity) {
+        let type_id = TypeId::of::<T>();
+        if !self.is_alive(entity) {
+            return;
+        }
+        if let Some(components) = self.components.get_mut(&type_id) {
+            components[entity.index] = None;
+        } else {
+            self.register_component::<T>();
+        }
+    }
+
+    pub fn get_component<T: Component + 'static>(&self, entity: Entity) -> Option<&T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        let type_id = TypeId::of::<T>();
+        self.components
+            .get(&type_id)?
+            .get(entity.index)?
+            .as_ref()?
+            .downcast_ref::<T>()
+    }
+
+    pub fn get_component_mut<T: Component + 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        let type_id = TypeId::of::<T>();
+        self.components
+            .get_mut(&type_id)?
+            .get_mut(entity.index)?
+            .as_mut()?
+            .downcast_mut::<T>()
+    }
+
+    // the same component on several entities at once, None if any of them is
+    // missing it or an entity is given twice
+    pub fn get_disjoint_mut<T: Component + 'static, const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> Option<[&mut T; N]> {
+        if !entities.iter().all(|entity| self.is_alive(*entity)) {
+            return None;
+        }
+        let components = self.components.get_mut(&TypeId::of::<T>())?;
+        let slots = components
+            .get_disjoint_mut(entities.map(|entity| entity.index))
+            .ok()?;
+        let mut components = slots.map(|slot| slot.as_mut()?.downcast_mut::<T>());
+        if components.iter().any(Option::is_none) {
+            return None;
+        }
+        Some(
+            components
+                .each_mut()
+                .map(|component| component.take().unwrap()),
+        )
+    }
+
+    // two components at once, on the same entity or two different ones. None if
+    // either is missing, or it's the same component on the same entity
+    pub fn get_pair_mut<A: Component + 'static, B: Component + 'static>(
+        &mut self,
+        a: Entity,
+        b: Entity,
+    ) -> Option<(&mut A, &mut B)> {
+        if !self.is_alive(a) || !self.is_alive(b) {
+            return None;
+        }
+        let (a_id, b_id) = (TypeId::of::<A>(), TypeId::of::<B>());
+        let (a, b) = if a_id == b_id {
+            let [a, b] = self
+                .components
+                .get_mut(&a_id)?
+                .get_disjoint_mut([a.index, b.index])
+                .ok()?;
+            (a, b)
+        } else {
+            let [a_components, b_components] = self.components.get_disjoint_mut([&a_id, &b_id]);
+            (&mut a_components?[a.index], &mut b_components?[b.index])
+        };
+        Some((
+            a.as_mut()?.downcast_mut::<A>()?,
+            b.as_mut()?.downcast_mut::<B>()?,
+        ))
+    }
+
+    pub fn try_component<T: Component + 'static>(&self, entity: Entity) -> Result<&T, EcsError> {
+        self.get_component::<T>(entity)
+            .ok_or(EcsError::ComponentMissing {
+                entity,
+                component: core::any::type_name::<T>(),
+            })
+    }
+
+    pub fn try_component_mut<T: Component + 'static>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<&mut T, EcsError> {
+        self.get_component_mut::<T>(entity)
+            .ok_or(EcsError::ComponentMissing {
+                entity,
+                component: core::any::type_name::<T>(),
+            })
+    }
+
+    // everything on a live entity with its type name, sorted by name
+    pub fn components(&self, entity: Entity) -> Vec<(&'static str, &dyn Component)> {
+        if !self.is_alive(entity) {
+            return Vec::new();
+        }
+        let mut components: Vec<(&'static str, &dyn Component)> = self
+            .components
+            .iter()
+            .filter_map(|(type_id, components)| {
+                let component = components.get(entity.index)?.as_deref()?;
+                Some((self.component_names[type_id], component))
+            })
+            .collect();
+        components.sort_by_key(|(name, _)| *name);
+        components
+    }
+
+    #[cfg(feature = "std")]
+    pub fn print_components(&self, entity: Entity) {
+        for (name, component) in self.components(entity) {
+            println!("{:?} has component {}: {:?}", entity, name, component);
+        }
+    }
+
+    pub fn query<T: Component + 'static>(&self) -> Vec<(Entity, &T)> {
+        let type_id = TypeId::of::<T>();
+        let entities = &self.entities;
+        if let Some(components) = self.components.get(&type_id) {
+            components
+                .iter()
+                .enumerate()
+                .filter_map(|(index, component)| {
+                    component
+                        .as_ref()
+                        .and_then(|c| c.downcast_ref::<T>())
+                        .map(|c| {
+                            let generation = entities[index].generation;
+                            (Entity { index, generation }, c)
+                        })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn query_mut<T: Component + 'static>(&mut self) -> Vec<(Entity, &mut T)> {
+        let type_id = TypeId::of::<T>();
+        let entities = &self.entities;
+        if let Some(components) = self.components.get_mut(&type_id) {
+            components
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(index, component)| {
+                    component
+                        .as_mut()
+                        .and_then(|c| c.downcast_mut::<T>())
+                        .map(|c| {
+                            let generation = entities[index].generation;
+                            (Entity { index, generation }, c)
+                        })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    // every entity with both components, borrowed together. empty when A and B
+    // are the same component, which would be borrowed twice
+    pub fn query_pair_mut<A: Component + 'static, B: Component + 'static>(
+        &mut self,
+    ) -> Vec<(Entity, &mut A, &mut B)> {
+        let (a_id, b_id) = (TypeId::of::<A>(), TypeId::of::<B>());
+        if a_id == b_id {
+            return Vec::new();
+        }
+        let entities = &self.entities;
+        let [Some(a_components), Some(b_components)] =
+            self.components.get_disjoint_mut([&a_id, &b_id])
+        else {
+            return Vec::new();
+        };
+        a_components
+            .iter_mut()
+            .zip(b_components.iter_mut())
+            .enumerate()
+            .filter_map(|(index, (a, b))| {
+                let a = a.as_mut()?.downcast_mut::<A>()?;
+                let b = b.as_mut()?.downcast_mut::<B>()?;
+                let generation = entities[index].generation;
+                Some((Entity { index, generation }, a, b))
+            })
+            .collect()
+    }
+
+    pub fn try_single<T: Component + 'static>(&self) -> Result<&T, EcsError> {
+        let mut components = self
+            .components
+            .get(&TypeId::of::<T>())
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_ref()?.downcast_ref::<T>());
+        match (components.next(), components.count()) {
+            (Some(component), 0) => Ok(component),
+            (first, rest) => Err(EcsError::NotSingle {
+                component: core::any::type_name::<T>(),
+                count: first.map_or(0, |_| rest + 1),
+            }),
+        }
+    }
+
+    pub fn try_single_mut<T: Component + 'static>(&mut self) -> Result<&mut T, EcsError> {
+        let mut components = self
+            .components
+            .get_mut(&TypeId::of::<T>())
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_mut()?.downcast_mut::<T>());
+        match (components.next(), components.count()) {
+            (Some(component), 0) => Ok(component),
+            (first, rest) => Err(EcsError::NotSingle {
+                component: core::any::type_name::<T>(),
+                count: first.map_or(0, |_| rest + 1),
+            }),
+        }
+    }
+
+    pub fn get_single<T: Component + 'static>(&self) -> Option<&T> {
+        self.try_single::<T>().ok()
+    }
+
+    pub fn get_single_mut<T: Component + 'static>(&mut self) -> Option<&mut T> {
+        self.try_single_mut::<T>().ok()
+    }
+
+    #[track_caller]
+    pub fn single<T: Component + 'static>(&self) -> &T {
+        self.try_single::<T>().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    #[track_caller]
+    pub fn single_mut<T: Component + 'static>(&mut self) -> &mut T {
+        self.try_single_mut::<T>().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // events are cleared by the Last schedule at the end of every frame
+    pub fn add_event<T: core::fmt::Debug + 'static>(&mut self) {
+        if self.get_resource::<Events<T>>().is_some() {
+            return;
+        }
+        self.init_resource::<Events<T>>();
+        self.add_system_to(Last, clear_events::<T>);
+    }
+
+    pub fn send_event<T: core::fmt::Debug + 'static>(&mut self, event: T) {
+        match self.get_resource_mut::<Events<T>>() {
+            Some(events) => events.send(event),
+            None => log::warn!(
+                "Event {} sent before add_event",
+                core::any::type_name::<T>()
+            ),
+        }
+    }
+
+    fn intern_schedule(&mut self, name: &'static str) -> ScheduleId {
+        if let Some(id) = self.schedule_names.get(name) {
+            return *id;
+        }
+        let id = ScheduleId(self.schedules.len());
+        self.schedules.push(Schedule {
+            name,
+            systems: Vec::new(),
+        });
+        self.schedule_names.insert(name, id);
+        id
+    }
+
+    fn find_label<L: ScheduleLabel>(&self, label: &L) -> Option<ScheduleId> {
+        self.schedule_labels
+            .get(&TypeId::of::<L>())?
+            .iter()
+            .find(|(known, _)| known.downcast_ref::<L>() == Some(label))
+            .map(|(_, id)| *id)
+    }
+
+    fn intern_label<L: ScheduleLabel>(&mut self, label: L) -> ScheduleId {
+        if let Some(id) = self.find_label(&label) {
+            return id;
+        }
+        // the name only matters the first time, to find a schedule the string api made
+        let id = self.intern_schedule(label.name());
+        self.schedule_labels
+            .entry(TypeId::of::<L>())
+            .or_default()
+            .push((Box::new(label), id));
+        id
+    }
+
+    // schedules are also added the first time a system is, this is for ones
+    // that are run before anything is added to them
+    pub fn add_schedule(&mut self, label: impl ScheduleLabel) -> ScheduleId {
+        self.intern_label(label)
+    }
+
+    pub fn schedule_id(&self, label: impl ScheduleLabel) -> Option<ScheduleId> {
+        self.find_label(&label)
+    }
+
+    pub fn add_system_to(&mut self, label: impl ScheduleLabel, system: impl IntoSystem) {
+        let id = self.intern_label(label);
+        self.schedules[id.0].systems.push(system.into_system());
+    }
+
+    // starts the schedule over empty if it was registered already
+    #[deprecated = "schedules are added with their first system, or with add_schedule"]
+    pub fn register_schedule(&mut self, name: &'static str) -> ScheduleId {
+        let id = self.intern_schedule(name);
+        self.schedules[id.0].systems.clear();
+        id
+    }
+
+    #[deprecated = "use add_system_to with a ScheduleLabel"]
+    pub fn add_system(&mut self, schedule_name: &'static str, system: impl IntoSystem) {
+        let id = self.intern_schedule(schedule_name);
+        self.schedules[id.0].systems.push(system.into_system());
+    }
+
+    pub fn run_system(&mut self, mut system: impl FnMut(&mut World)) {
+        system(self);
+    }
+
+    // does nothing for schedules that were never added
+    pub fn run(&mut self, label: impl ScheduleLabel) {
+        if let Some(id) = self.schedule_id(label) {
+            self.run_schedule_id(id);
+        }
+    }
+
+    #[deprecated = "use run with a ScheduleLabel"]
+    pub fn run_schedule(&mut self, schedule_name: &'static str) {
+        if let Some(id) = self.schedule_names.get(schedule_name).copied() {
+            self.run_schedule_id(id);
+        }
+    }
+
+    pub fn run_schedule_id(&mut self, schedule: ScheduleId) {
+        // taken out while they run, so they can have the world to themselves
+        let name = self.schedules[schedule.0].name;
+        let mut systems = core::mem::take(&mut self.schedules[schedule.0].systems);
+        (self.schedule_runner)(self, name, &mut systems);
+        // systems added to this schedule during the run go after its own
+        let added = core::mem::replace(&mut self.schedules[schedule.0].systems, systems);
+        self.schedules[schedule.0].systems.extend(added);
+    }
+
+    pub fn set_schedule_runner(&mut self, runner: ScheduleRunner) {
+        self.schedule_runner = runner;
+    }
+
+    #[cfg(feature = "std")]
+    pub fn print_schedules(&self) {
+        for Schedule { name, systems } in &self.schedules {
+            println!("Schedule: {}, Systems: {}", name, systems.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{schedule::Update, system::System};
+
+    #[derive(Debug, Default)]
+    struct Counter(Vec<&'static str>);
+
+    impl Component for Counter {}
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Health(u32);
+
+    impl Component for Health {}
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Follows(Entity);
+
+    impl Component for Follows {}
+
+    impl MapEntities for Follows {
+        fn map_entities(&mut self, map: &dyn Fn(Entity) -> Option<Entity>) -> bool {
+            map(self.0).map(|entity| self.0 = entity).is_some()
+        }
+    }
+
+    #[test]
+    fn add_component_registers_the_type() {
+        let mut world = World::new();
+        let entity = world.spawn().id();
+        world.add_component(entity, Health(3));
+        assert_eq!(world.get_component::<Health>(entity), Some(&Health(3)));
+        assert_eq!(world.query::<Health>().len(), 1);
+    }
+
+    #[test]
+    fn register_component_sizes_for_existing_entities() {
+        let mut world = World::new();
+        let first = world.spawn().id();
+        let second = world.spawn().id();
+        assert_ne!(
+            first, second,
+            "entities without components are still counted"
+        );
+        world.register_component::<Health>();
+        assert_eq!(world.get_component::<Health>(first), None);
+        world.add_component(second, Health(1));
+        // again, after components were added, keeps them
+        world.register_component::<Health>();
+        assert_eq!(world.get_component::<Health>(second), Some(&Health(1)));
+        let third = world.spawn().insert(Health(2)).id();
+        assert_eq!(world.get_component::<Health>(third), Some(&Health(2)));
+    }
+
+    #[test]
+    fn components_of_an_entity() {
+        let mut world = World::new();
+        let entity = world
+            .spawn()
+            .insert(Health(4))
+            .insert(Counter(Vec::new()))
+            .id();
+        let other = world.spawn().insert(Health(1)).id();
+        let components = world.components(entity);
+        let names: Vec<&str> = components.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            [
+                core::any::type_name::<Counter>(),
+                core::any::type_name::<Health>()
+            ]
+        );
+        assert_eq!(components[1].1.downcast_ref::<Health>(), Some(&Health(4)));
+        world.despawn(other);
+        assert!(world.components(other).is_empty());
+    }
+
+    #[test]
+    fn despawned_slots_are_reused_with_a_new_generation() {
+        let mut world = World::new();
+        let old = world.spawn().insert(Health(1)).id();
+        world.spawn();
+        world.despawn(old);
+        assert_eq!(world.entity_count(), 1);
+        assert!(!world.is_alive(old));
+
+        let new = world.spawn().id();
+        assert_eq!(new.index(), old.index());
+        assert_ne!(new.generation(), old.generation());
+        assert_eq!(world.entity_count(), 2);
+        // the old id doesn't reach the new entity or its components
+        assert_eq!(world.get_component::<Health>(new), None);
+        world.add_component(old, Health(9));
+        assert_eq!(world.get_component::<Health>(new), None);
+        assert_eq!(world.entity_at(old.index()), Some(new));
+    }
+
+    #[test]
+    fn closures_and_functions_are_systems() {
+        fn push_fn(world: &mut World) {
+            world.resource_mut::<Counter>().0.push("fn");
+        }
+        let mut world = World::new();
+        world.init_resource::<Counter>();
+        let mut runs = 0;
+        world.add_system_to(Update, push_fn);
+        world.add_system_to(Update, move |world: &mut World| {
+            runs += 1;
+            if runs == 2 {
+                world.resource_mut::<Counter>().0.push("second run");
+            }
+        });
+        world.add_system_to(
+            Update,
+            System::named("named", |world: &mut World| {
+                world.resource_mut::<Counter>().0.push("named")
+            }),
+        );
+        world.run(Update);
+        world.run(Update);
+        assert_eq!(
+            world.resource::<Counter>().0,
+            ["fn", "named", "fn", "second run", "named"]
+        );
+        assert_eq!(System::named("named", |_: &mut World| {}).name(), "named");
+    }
+
+    #[test]
+    fn moved_entities_keep_references_between_them() {
+        let (mut from, mut to) = (World::new(), World::new());
+        from.register_entity_refs::<Follows>();
+        to.spawn();
+        let leader = from.spawn().insert(Health(5)).id();
+        let follower = from.spawn().insert(Follows(leader)).id();
+        let left_behind = from.spawn().id();
+        let watcher = from.spawn().insert(Follows(leader)).id();
+        from.add_component(leader, Follows(left_behind));
+
+        let moved = from.move_entities(&[leader, follower], &mut to);
+        let (Some(new_leader), Some(new_follower)) = (moved[0], moved[1]) else {
+            panic!("both should have moved: {moved:?}");
+        };
+        assert!(!from.is_alive(leader) && !from.is_alive(follower));
+        assert_eq!(to.get_component::<Health>(new_leader), Some(&Health(5)));
+        assert_eq!(
+            to.get_component::<Follows>(new_follower),
+            Some(&Follows(new_leader))
+        );
+        // references across the worlds are dropped on both sides
+        assert_eq!(to.get_component::<Follows>(new_leader), None);
+        assert_eq!(from.get_component::<Follows>(watcher), None);
+        assert_eq!(from.move_entity(leader, &mut to), None);
+        assert_eq!(to.entity_count(), 3);
+    }
+
+    #[test]
+    fn resource_scopes_lend_the_rest_of_the_world() {
+        let mut world = World::new();
+        world.init_resource::<Counter>();
+        let entity = world.spawn().insert(Health(2)).id();
+        let health = world.resource_scope(|world, counter: &mut Counter| {
+            counter.0.push("scoped");
+            // it's out of the world while the scope runs
+            assert!(world.get_resource::<Counter>().is_none());
+            world.insert_resource(Counter(vec!["replaced"]));
+            world.get_component::<Health>(entity).copied()
+        });
+        assert_eq!(health, Some(Health(2)));
+        assert_eq!(world.resource::<Counter>().0, ["scoped"]);
+        assert!(matches!(
+            world.try_resource_scope(|_, _: &mut Health| ()),
+            Err(EcsError::ResourceMissing(_))
+        ));
+    }
+
+    #[test]
+    fn disjoint_borrows_of_components() {
+        let mut world = World::new();
+        let a = world
+            .spawn()
+            .insert(Health(1))
+            .insert(Counter::default())
+            .id();
+        let b = world.spawn().insert(Health(2)).id();
+        let bare = world.spawn().id();
+
+        let [first, second] = world.get_disjoint_mut::<Health, 2>([a, b]).unwrap();
+        core::mem::swap(first, second);
+        assert_eq!(world.get_component::<Health>(a), Some(&Health(2)));
+        assert!(world.get_disjoint_mut::<Health, 2>([a, a]).is_none());
+        assert!(world.get_disjoint_mut::<Health, 2>([a, bare]).is_none());
+
+        let (health, counter) = world.get_pair_mut::<Health, Counter>(a, a).unwrap();
+        counter.0.push("paired");
+        health.0 += 1;
+        assert_eq!(world.get_component::<Health>(a), Some(&Health(3)));
+        let (from, to) = world.get_pair_mut::<Health, Health>(a, b).unwrap();
+        to.0 += from.0;
+        assert_eq!(world.get_component::<Health>(b), Some(&Health(4)));
+        assert!(world.get_pair_mut::<Health, Health>(a, a).is_none());
+        assert!(world.get_pair_mut::<Health, Counter>(b, b).is_none());
+
+        world.despawn(b);
+        assert!(world.get_disjoint_mut::<Health, 1>([b]).is_none());
+        let pairs = world.query_pair_mut::<Health, Counter>();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].0, *pairs[0].1), (a, Health(3)));
+        assert!(world.query_pair_mut::<Health, Health>().is_empty());
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Phase {
+        A,
+        B,
+    }
+
+    impl ScheduleLabel for Phase {
+        fn name(&self) -> &'static str {
+            match self {
+                Phase::A => "phase_a",
+                Phase::B => "phase_b",
+            }
+        }
+    }
+
+    #[test]
+    fn enum_label_variants_are_separate_schedules() {
+        let mut world = World::new();
+        world.init_resource::<Counter>();
+        world.add_system_to(Phase::A, |world: &mut World| {
+            world.resource_mut::<Counter>().0.push("a")
+        });
+        world.add_system_to(Phase::B, |world: &mut World| {
+            world.resource_mut::<Counter>().0.push("b")
+        });
+        world.run(Phase::A);
+        assert_eq!(world.resource::<Counter>().0, ["a"]);
+        world.run(Phase::B);
+        world.run(Update);
+        assert_eq!(world.resource::<Counter>().0, ["a", "b"]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn string_names_reach_label_schedules() {
+        let mut world = World::new();
+        world.init_resource::<Counter>();
+        world.add_system("phase_a", |world: &mut World| {
+            world.resource_mut::<Counter>().0.push("by name")
+        });
+        world.add_system_to(Phase::A, |world: &mut World| {
+            world.resource_mut::<Counter>().0.push("by label")
+        });
+        assert!(world.schedule_id(Phase::A).is_some());
+        assert_eq!(world.schedule_id(Phase::B), None);
+        world.run(Phase::A);
+        world.run_schedule("phase_a");
+        assert_eq!(
+            world.resource::<Counter>().0,
+            ["by name", "by label", "by name", "by label"]
+        );
+    }
+}
@@ -0,0 +1,17 @@
+#!/bin/sh
+# What a change has to pass before it goes in: the build, clippy with the
+# default features, none, each one on its own and all of them, the tests with
+# the default and all features, and formatting. Run from anywhere in the repo.
+set -e
+cd "$(dirname "$0")/.."
+
+cargo build --workspace
+cargo clippy --workspace --all-targets -- -D warnings
+cargo clippy --workspace --all-targets --no-default-features -- -D warnings
+for feature in render2d render3d audio physics ui gltf obj; do
+    cargo clippy --workspace --all-targets --no-default-features --features "$feature" -- -D warnings
+done
+cargo clippy --workspace --all-targets --all-features -- -D warnings
+cargo test --workspace
+cargo test --workspace --all-features
+cargo fmt --all --check
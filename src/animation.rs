@@ -0,0 +1,476 @@
+// Keyframed animation. A MorphAnimation plays a track of morph weight
+// keyframes into the entity's MorphWeights and a TransformAnimation plays
+// transform keyframes into its Transform, both blending linearly between
+// keys. Named markers on either send an AnimationEvent as the playhead
+// crosses them, and a TransformAnimation can hand its movement to another
+// entity as root motion.
+
+use glam::Vec3;
+
+use crate::{
+    ecs::{
+        component::Component,
+        entity::{Entity, MapEntities},
+        world::World,
+    },
+    importance,
+    mesh::MorphWeights,
+    time::Time,
+    transform::Transform,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe<T> {
+    // seconds from the start of the track
+    pub time: f32,
+    pub value: T,
+}
+
+// a named point in time, like a footstep or the frame an attack connects
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub time: f32,
+    pub name: String,
+}
+
+impl Marker {
+    pub fn new(time: f32, name: impl Into<String>) -> Self {
+        Self {
+            time,
+            name: name.into(),
+        }
+    }
+}
+
+// sent for every marker the playhead passed this frame, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playback {
+    // negative plays backwards
+    pub speed: f32,
+    pub looping: bool,
+    pub paused: bool,
+    // playhead in seconds
+    pub time: f32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            looping: true,
+            paused: false,
+            time: 0.0,
+        }
+    }
+}
+
+// part of the track the playhead went over, `from` is only inclusive when
+// leaving the start of the track
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    from: f32,
+    to: f32,
+    inclusive: bool,
+}
+
+impl Span {
+    fn contains(&self, time: f32) -> bool {
+        if self.from <= self.to {
+            (time > self.from || self.inclusive && time == self.from) && time <= self.to
+        } else {
+            (time < self.from || self.inclusive && time == self.from) && time >= self.to
+        }
+    }
+}
+
+impl Playback {
+    // moves the playhead and returns what it went over, in order
+    fn advance(&mut self, delta: f32, duration: f32) -> Vec<Span> {
+        if self.paused || self.speed == 0.0 || delta <= 0.0 {
+            return Vec::new();
+        }
+        let from = self.time;
+        let to = from + delta * self.speed;
+        let (start, end) = if self.speed > 0.0 {
+            (0.0, duration)
+        } else {
+            (duration, 0.0)
+        };
+        let inclusive = from == start;
+        if !self.looping || duration <= 0.0 {
+            self.time = to.clamp(0.0, duration.max(0.0));
+            return vec![Span {
+                from,
+                to: self.time,
+                inclusive,
+            }];
+        }
+
+        // one wrap per frame at most, anything longer is skipped over
+        self.time = to.rem_euclid(duration);
+        if (self.speed > 0.0 && to < duration) || (self.speed < 0.0 && to > 0.0) {
+            vec![Span {
+                from,
+                to,
+                inclusive,
+            }]
+        } else {
+            vec![
+                Span {
+                    from,
+                    to: end,
+                    inclusive,
+                },
+                Span {
+                    from: start,
+                    to: self.time,
+                    inclusive: true,
+                },
+            ]
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MorphAnimation {
+    // sorted by time, each value holds a weight per morph target
+    pub keyframes: Vec<Keyframe<Vec<f32>>>,
+    pub markers: Vec<Marker>,
+    pub playback: Playback,
+}
+
+impl Component for MorphAnimation {}
+
+impl MorphAnimation {
+    pub fn new(keyframes: Vec<Keyframe<Vec<f32>>>) -> Self {
+        Self {
+            keyframes,
+            markers: Vec::new(),
+            playback: Playback::default(),
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        duration(&self.keyframes)
+    }
+
+    // the weights at `time`
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        sample(&self.keyframes, time, |from, to, t| {
+            let len = from.len().max(to.len());
+            (0..len)
+                .map(|i| {
+                    let a = from.get(i).copied().unwrap_or(0.0);
+                    let b = to.get(i).copied().unwrap_or(0.0);
+                    a + (b - a) * t
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformAnimation {
+    // sorted by time
+    pub keyframes: Vec<Keyframe<Transform>>,
+    pub markers: Vec<Marker>,
+    pub playback: Playback,
+    // moves this entity by the track's translation instead, keeping the
+    // animated entity at the first key's position. for a character's root
+    // bone this is the character itself
+    pub root_motion: Option<Entity>,
+}
+
+impl Component for TransformAnimation {}
+
+impl MapEntities for TransformAnimation {
+    fn map_entities(&mut self, map: &dyn Fn(Entity) -> Option<Entity>) -> bool {
+        self.root_motion = self.root_motion.and_then(map);
+        true
+    }
+}
+
+impl TransformAnimation {
+    pub fn new(keyframes: Vec<Keyframe<Transform>>) -> Self {
+        Self {
+            keyframes,
+            markers: Vec::new(),
+            playback: Playback::default(),
+            root_motion: None,
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        duration(&self.keyframes)
+    }
+
+    pub fn sample(&self, time: f32) -> Transform {
+        sample(&self.keyframes, time, |from, to, t| Transform {
+            translation: from.translation.lerp(to.translation, t),
+            rotation: from.rotation.slerp(to.rotation, t),
+            scale: from.scale.lerp(to.scale, t),
+        })
+        .unwrap_or_default()
+    }
+}
+
+fn duration<T>(keyframes: &[Keyframe<T>]) -> f32 {
+    keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+}
+
+fn sample<T: Clone>(
+    keyframes: &[Keyframe<T>],
+    time: f32,
+    blend: impl Fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    let index = keyframes.partition_point(|keyframe| keyframe.time <= time);
+    match (keyframes.get(index.wrapping_sub(1)), keyframes.get(index)) {
+        (Some(from), Some(to)) => {
+            let span = to.time - from.time;
+            let t = if span > 0.0 {
+                (time - from.time) / span
+            } else {
+                1.0
+            };
+            Some(blend(&from.value, &to.value, t))
+        }
+        (Some(keyframe), None) | (None, Some(keyframe)) => Some(keyframe.value.clone()),
+        (None, None) => None,
+    }
+}
+
+fn send_markers(world: &mut World, entity: Entity, markers: &[Marker], spans: &[Span]) {
+    for span in spans {
+        let mut crossed: Vec<&Marker> = markers
+            .iter()
+            .filter(|marker| span.contains(marker.time))
+            .collect();
+        if span.from > span.to {
+            crossed.sort_by(|a, b| b.time.total_cmp(&a.time));
+        } else {
+            crossed.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        for marker in crossed {
+            world.send_event(AnimationEvent {
+                entity,
+                name: marker.name.clone(),
+            });
+        }
+    }
+}
+
+pub fn animate_morph_weights(world: &mut World) {
+    let delta = world.get_resource::<Time>().map_or(0.0, Time::delta);
+    let animations: Vec<Entity> = world
+        .query::<MorphAnimation>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for entity in animations {
+        // less important ones catch up every few frames
+        let Some(delta) = importance::frame_delta(world, entity, delta) else {
+            continue;
+        };
+        let Some(animation) = world.get_component_mut::<MorphAnimation>(entity) else {
+            continue;
+        };
+        let duration = animation.duration();
+        let spans = animation.playback.advance(delta, duration);
+        let weights = animation.sample(animation.playback.time);
+        let markers = (!spans.is_empty()).then(|| animation.markers.clone());
+
+        if let Some(markers) = markers {
+            send_markers(world, entity, &markers, &spans);
+        }
+        match world.get_component_mut::<MorphWeights>(entity) {
+            Some(current) => current.0 = weights,
+            None => world.add_component(entity, MorphWeights(weights)),
+        }
+    }
+}
+
+pub fn animate_transforms(world: &mut World) {
+    let delta = world.get_resource::<Time>().map_or(0.0, Time::delta);
+    let animations: Vec<Entity> = world
+        .query::<TransformAnimation>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for entity in animations {
+        let Some(delta) = importance::frame_delta(world, entity, delta) else {
+            continue;
+        };
+        let Some(animation) = world.get_component_mut::<TransformAnimation>(entity) else {
+            continue;
+        };
+        let duration = animation.duration();
+        let spans = animation.playback.advance(delta, duration);
+        let mut pose = animation.sample(animation.playback.time);
+        let markers = (!spans.is_empty()).then(|| animation.markers.clone());
+
+        let root_motion = animation.root_motion.map(|root| {
+            let moved: Vec3 = spans
+                .iter()
+                .map(|span| {
+                    animation.sample(span.to).translation - animation.sample(span.from).translation
+                })
+                .sum();
+            (root, moved)
+        });
+        if root_motion.is_some() {
+            let start = animation.sample(0.0).translation;
+            pose.translation = start;
+        }
+
+        if let Some(markers) = markers {
+            send_markers(world, entity, &markers, &spans);
+        }
+        if let Some((root, moved)) = root_motion
+            && let Some(transform) = world.get_component_mut::<Transform>(root)
+        {
+            // the track's movement is in the root's own frame
+            transform.translation += transform.rotation * moved;
+        }
+        match world.get_component_mut::<Transform>(entity) {
+            Some(transform) => *transform = pose,
+            None => world.add_component(entity, pose),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ecs::schedule::Update, test_utils::TestWorld};
+
+    fn key<T>(time: f32, value: T) -> Keyframe<T> {
+        Keyframe { time, value }
+    }
+
+    fn markers_test(speed: f32) -> (TestWorld, Entity) {
+        let mut test = TestWorld::empty()
+            .with_delta(0.75)
+            .with_system(Update, animate_morph_weights);
+        test.capture_events::<AnimationEvent>();
+        let mut animation = MorphAnimation::new(vec![key(0.0, vec![0.0]), key(2.0, vec![1.0])]);
+        animation.markers = vec![
+            Marker::new(0.0, "start"),
+            Marker::new(0.5, "left"),
+            Marker::new(1.5, "right"),
+        ];
+        animation.playback.speed = speed;
+        let entity = test.spawn().insert(animation).id();
+        (test, entity)
+    }
+
+    fn names(test: &TestWorld) -> Vec<&str> {
+        test.events::<AnimationEvent>()
+            .iter()
+            .map(|event| event.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keyframes_blend_and_hold_at_the_ends() {
+        let animation = TransformAnimation::new(vec![
+            key(1.0, Transform::from_translation(Vec3::X)),
+            key(3.0, Transform::from_translation(Vec3::X * 5.0)),
+        ]);
+        assert_eq!(animation.duration(), 3.0);
+        assert_eq!(animation.sample(0.0).translation, Vec3::X);
+        assert_eq!(animation.sample(2.0).translation, Vec3::X * 3.0);
+        assert_eq!(animation.sample(4.0).translation, Vec3::X * 5.0);
+
+        // missing weights blend from zero
+        let morph = MorphAnimation::new(vec![key(0.0, vec![1.0]), key(1.0, vec![0.0, 1.0])]);
+        assert_eq!(morph.sample(0.5), [0.5, 0.5]);
+        assert!(MorphAnimation::new(Vec::new()).sample(1.0).is_empty());
+    }
+
+    #[test]
+    fn markers_fire_once_in_order_across_a_loop() {
+        let (mut test, entity) = markers_test(1.0);
+        test.tick();
+        assert_eq!(names(&test), ["start", "left"]);
+        assert_eq!(test.component::<MorphWeights>(entity).0, [0.375]);
+        test.ticks(2);
+        // 2.25 wraps to 0.25, passing the start again
+        assert_eq!(names(&test), ["start", "left", "right", "start"]);
+        assert_eq!(test.component::<MorphAnimation>(entity).playback.time, 0.25);
+        assert!(
+            test.events::<AnimationEvent>()
+                .iter()
+                .all(|event| event.entity == entity)
+        );
+    }
+
+    #[test]
+    fn backwards_and_stopped_playback() {
+        let (mut test, entity) = markers_test(-1.0);
+        test.tick();
+        // from 0 backwards wraps straight to the end, without leaving the
+        // marker it was sitting on
+        assert_eq!(names(&test), ["right"]);
+        assert_eq!(test.component::<MorphAnimation>(entity).playback.time, 1.25);
+
+        test.clear_events::<AnimationEvent>();
+        let playback = &mut test
+            .world
+            .get_component_mut::<MorphAnimation>(entity)
+            .unwrap()
+            .playback;
+        playback.looping = false;
+        playback.speed = 4.0;
+        test.ticks(2);
+        assert_eq!(names(&test), ["right"]);
+        assert_eq!(test.component::<MorphAnimation>(entity).playback.time, 2.0);
+        assert_eq!(test.component::<MorphWeights>(entity).0, [1.0]);
+        test.world
+            .get_component_mut::<MorphAnimation>(entity)
+            .unwrap()
+            .playback
+            .paused = true;
+        test.tick();
+        assert_eq!(names(&test), ["right"]);
+        assert_eq!(test.component::<MorphAnimation>(entity).playback.time, 2.0);
+    }
+
+    #[test]
+    fn root_motion_moves_the_root_instead() {
+        let mut test = TestWorld::empty()
+            .with_delta(0.5)
+            .with_system(Update, animate_transforms);
+        let root = test
+            .spawn()
+            .insert(Transform {
+                rotation: glam::Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+                ..Transform::IDENTITY
+            })
+            .id();
+        let mut animation = TransformAnimation::new(vec![
+            key(0.0, Transform::from_translation(Vec3::Y)),
+            key(1.0, Transform::from_translation(Vec3::new(0.0, 1.0, -2.0))),
+        ]);
+        animation.root_motion = Some(root);
+        let bone = test.spawn().insert(animation).id();
+
+        test.ticks(3);
+        assert_eq!(test.component::<Transform>(bone).translation, Vec3::Y);
+        // three half seconds at 2 units a second along the root's -z, which
+        // the root's turn points down -x. the wrap doesn't jump it back
+        let moved = test.component::<Transform>(root).translation;
+        assert!(
+            moved.abs_diff_eq(Vec3::new(-3.0, 0.0, 0.0), 1e-5),
+            "{moved}"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+// One file holding a whole assets directory, for shipping a game without a
+// tree of loose files next to it, or fetching it in one request on the web.
+// `whirlwind-cli pack` writes them. The layout is the magic, the entry count,
+// then each entry's path (relative, `/` separated), its length and its bytes,
+// the numbers little endian. Nothing's compressed, the images and sounds in
+// there are already.
+
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::error::WhirlwindError;
+
+const MAGIC: &[u8; 8] = b"WWPAK\0\0\x01";
+
+#[derive(Debug, Default)]
+pub struct AssetArchive {
+    data: Vec<u8>,
+    entries: HashMap<String, Range<usize>>,
+}
+
+impl AssetArchive {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => WhirlwindError::AssetNotFound(path.into()).into(),
+            _ => anyhow::Error::from(e),
+        })?;
+        Self::from_bytes(data)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> anyhow::Result<Self> {
+        anyhow::ensure!(data.starts_with(MAGIC), "Not an asset archive");
+        let mut cursor = MAGIC.len();
+        let mut take = |len: usize| -> anyhow::Result<Range<usize>> {
+            let range = cursor..cursor + len;
+            anyhow::ensure!(range.end <= data.len(), "Asset archive is truncated");
+            cursor = range.end;
+            Ok(range)
+        };
+        let count = u32::from_le_bytes(data[take(4)?].try_into()?);
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let len = u32::from_le_bytes(data[take(4)?].try_into()?) as usize;
+            let name = std::str::from_utf8(&data[take(len)?])?.to_owned();
+            let len = u64::from_le_bytes(data[take(8)?].try_into()?) as usize;
+            entries.insert(name, take(len)?);
+        }
+        Ok(Self { data, entries })
+    }
+
+    // `path` relative to the packed directory, "textures/grass.png"
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.entries
+            .get(path)
+            .map(|range| &self.data[range.clone()])
+    }
+
+    pub fn try_get(&self, path: &str) -> Result<&[u8], WhirlwindError> {
+        self.get(path)
+            .ok_or_else(|| WhirlwindError::AssetNotFound(path.into()))
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // every file under `directory`, in path order so packing twice gives the same bytes
+    pub fn pack(directory: impl AsRef<Path>) -> anyhow::Result<Vec<u8>> {
+        let directory = directory.as_ref();
+        let mut files = Vec::new();
+        collect_files(directory, &mut files)?;
+        let mut entries = Vec::with_capacity(files.len());
+        for file in files {
+            let name = file
+                .strip_prefix(directory)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            entries.push((name, file));
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(&u32::try_from(entries.len())?.to_le_bytes());
+        for (name, file) in entries {
+            let data = std::fs::read(&file)?;
+            out.extend_from_slice(&u32::try_from(name.len())?.to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            out.extend_from_slice(&data);
+        }
+        Ok(out)
+    }
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
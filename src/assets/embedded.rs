@@ -0,0 +1,100 @@
+// Assets compiled into the binary, so an empty project renders something with
+// no files next to the executable. The solid textures and primitives are made
+// on the spot, the cube is the one from assets/ embedded whole. They're added
+// at startup once there's a render device, before any of the app's systems.
+
+use crate::{
+    assets::{Assets, Handle},
+    ecs::{component::Component, world::World},
+    material::Material,
+    mesh::Mesh,
+    render::RenderDevice,
+    texture::{Texture, TextureUsage},
+};
+
+pub const CUBE_OBJ: &str = include_str!("../../assets/cube.obj");
+pub const CUBE_PNG: &[u8] = include_bytes!("../../assets/cube.png");
+
+#[derive(Debug, Clone, Copy)]
+pub struct DefaultAssets {
+    pub white: Handle<Texture>,
+    pub black: Handle<Texture>,
+    // a flat tangent space normal
+    pub normal: Handle<Texture>,
+    pub cube_texture: Handle<Texture>,
+    // the textured cube's mesh, its uvs fit `cube_texture`
+    pub cube: Handle<Mesh>,
+    // a plain 1x1x1 cube, a 1x1 plane and a sphere 1 across
+    pub unit_cube: Handle<Mesh>,
+    pub plane: Handle<Mesh>,
+    pub sphere: Handle<Mesh>,
+    // white, untextured
+    pub material: Handle<Material>,
+    pub cube_material: Handle<Material>,
+}
+
+impl Component for DefaultAssets {}
+
+fn solid(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    color: [u8; 4],
+    usage: TextureUsage,
+) -> anyhow::Result<Texture> {
+    Texture::checkerboard(device, queue, [color; 2], 1, usage)
+}
+
+pub(crate) fn insert_default_assets(world: &mut World) {
+    // headless worlds without a renderer don't get any
+    let Some(RenderDevice { device, queue, .. }) = world.get_resource::<RenderDevice>().cloned()
+    else {
+        return;
+    };
+    let textures = (|| {
+        anyhow::Ok([
+            solid(&device, &queue, [255; 4], TextureUsage::Color)?,
+            solid(&device, &queue, [0, 0, 0, 255], TextureUsage::Color)?,
+            solid(
+                &device,
+                &queue,
+                [128, 128, 255, 255],
+                TextureUsage::NormalMap,
+            )?,
+            Texture::from_bytes(&device, &queue, CUBE_PNG, TextureUsage::Color, "cube.png")?,
+        ])
+    })();
+    let cube = Mesh::from_obj_source(CUBE_OBJ);
+    let (textures, cube) = match (textures, cube) {
+        (Ok(textures), Ok(cube)) => (textures, cube),
+        (Err(e), _) | (_, Err(e)) => {
+            log::error!("Unable to create the default assets: {}", e);
+            return;
+        }
+    };
+
+    let [white, black, normal, cube_texture] =
+        textures.map(|texture| world.resource_mut::<Assets<Texture>>().add(texture));
+    let meshes = world.resource_mut::<Assets<Mesh>>();
+    let cube = meshes.add(cube);
+    let unit_cube = meshes.add(Mesh::cube(1.0));
+    let plane = meshes.add(Mesh::plane(1.0, 0));
+    let sphere = meshes.add(Mesh::sphere(0.5, 32, 16));
+    let materials = world.resource_mut::<Assets<Material>>();
+    let material = materials.add(Material::default());
+    let cube_material = materials.add(Material {
+        base_color_texture: Some(cube_texture),
+        ..Default::default()
+    });
+    world.insert_resource(DefaultAssets {
+        white,
+        black,
+        normal,
+        cube_texture,
+        cube,
+        unit_cube,
+        plane,
+        sphere,
+        material,
+        cube_material,
+    });
+}
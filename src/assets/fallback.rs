@@ -0,0 +1,142 @@
+// Stand-ins for assets that fail to load. With `load_texture` and `load_mesh`
+// a missing or broken file gets a loud magenta checkerboard or a cube in its
+// place and an AssetLoadFailed event, so a typo in a path shows up on screen
+// instead of stopping the game at startup.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    assets::{Assets, Handle},
+    color::Color,
+    ecs::{component::Component, world::World},
+    material::Material,
+    mesh::Mesh,
+    render::RenderDevice,
+    texture::{Texture, TextureUsage},
+};
+
+const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+#[derive(Debug, Clone)]
+pub struct AssetLoadFailed {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+// created the first time each is needed
+#[derive(Debug, Default)]
+pub struct FallbackAssets {
+    textures: Vec<(TextureUsage, Handle<Texture>)>,
+    mesh: Option<Handle<Mesh>>,
+    material: Option<Handle<Material>>,
+}
+
+impl Component for FallbackAssets {}
+
+// a magenta checkerboard, or a flat normal for normal maps so lighting stays sane
+pub fn fallback_texture(world: &mut World, usage: TextureUsage) -> anyhow::Result<Handle<Texture>> {
+    if let Some((_, handle)) = world
+        .resource::<FallbackAssets>()
+        .textures
+        .iter()
+        .find(|(texture_usage, _)| *texture_usage == usage)
+    {
+        return Ok(*handle);
+    }
+    let RenderDevice { device, queue, .. } = world
+        .get_resource::<RenderDevice>()
+        .ok_or(anyhow::anyhow!("Render device is not ready"))?
+        .clone();
+    let texture = match usage {
+        TextureUsage::NormalMap => {
+            Texture::checkerboard(&device, &queue, [[128, 128, 255, 255]; 2], 1, usage)?
+        }
+        TextureUsage::Color | TextureUsage::Data => {
+            Texture::checkerboard(&device, &queue, [MAGENTA, BLACK], 8, usage)?
+        }
+    };
+    let handle = world.resource_mut::<Assets<Texture>>().add(texture);
+    world
+        .resource_mut::<FallbackAssets>()
+        .textures
+        .push((usage, handle));
+    Ok(handle)
+}
+
+// a unit cube
+pub fn fallback_mesh(world: &mut World) -> Handle<Mesh> {
+    if let Some(handle) = world.resource::<FallbackAssets>().mesh {
+        return handle;
+    }
+    let handle = world.resource_mut::<Assets<Mesh>>().add(Mesh::cube(1.0));
+    world.resource_mut::<FallbackAssets>().mesh = Some(handle);
+    handle
+}
+
+// plain magenta, for materials that couldn't be built
+pub fn fallback_material(world: &mut World) -> Handle<Material> {
+    if let Some(handle) = world.resource::<FallbackAssets>().material {
+        return handle;
+    }
+    let handle = world.resource_mut::<Assets<Material>>().add(Material {
+        base_color: Color::MAGENTA,
+        ..Default::default()
+    });
+    world.resource_mut::<FallbackAssets>().material = Some(handle);
+    handle
+}
+
+fn report(world: &mut World, path: &Path, error: &anyhow::Error) {
+    log::warn!(
+        "Unable to load {}, using a fallback: {}",
+        path.display(),
+        error
+    );
+    world.send_event(AssetLoadFailed {
+        path: path.to_path_buf(),
+        error: error.to_string(),
+    });
+}
+
+// errors only when there's no render device to create even the fallback with
+pub fn load_texture(
+    world: &mut World,
+    path: impl AsRef<Path>,
+    usage: TextureUsage,
+) -> anyhow::Result<Handle<Texture>> {
+    let path = path.as_ref();
+    let RenderDevice { device, queue, .. } = world
+        .get_resource::<RenderDevice>()
+        .ok_or(anyhow::anyhow!("Render device is not ready"))?
+        .clone();
+    match Texture::from_path(&device, &queue, path, usage) {
+        Ok(texture) => Ok(world.resource_mut::<Assets<Texture>>().add(texture)),
+        Err(e) => {
+            report(world, path, &e);
+            fallback_texture(world, usage)
+        }
+    }
+}
+
+pub fn load_mesh(world: &mut World, path: impl AsRef<Path>) -> Handle<Mesh> {
+    let path = path.as_ref();
+    match read_mesh(path) {
+        Ok(mesh) => world.resource_mut::<Assets<Mesh>>().add(mesh),
+        Err(e) => {
+            report(world, path, &e);
+            fallback_mesh(world)
+        }
+    }
+}
+
+// picked by extension, anything that isn't glTF is read as obj
+fn read_mesh(path: &Path) -> anyhow::Result<Mesh> {
+    #[cfg(feature = "gltf")]
+    if path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb")
+    }) {
+        return Mesh::from_gltf(&path.to_string_lossy());
+    }
+    Mesh::from_obj(&path.to_string_lossy())
+}
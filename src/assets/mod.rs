@@ -0,0 +1,113 @@
+pub mod archive;
+pub mod embedded;
+pub mod fallback;
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use crate::{ecs::component::Component, error::WhirlwindError};
+
+pub struct Handle<T> {
+    id: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    // a handle no Assets gave out, for the tilemap tests that never look it up
+    #[cfg(all(test, feature = "render2d"))]
+    pub(crate) fn dangling(id: usize) -> Self {
+        Self {
+            id,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id)
+    }
+}
+
+impl<T: 'static> Component for Handle<T> {}
+
+#[derive(Debug)]
+pub struct Assets<T> {
+    items: Vec<Option<T>>,
+}
+
+impl<T: Debug + 'static> Component for Assets<T> {}
+
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<T> Assets<T> {
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        self.items.push(Some(asset));
+        Handle {
+            id: self.items.len() - 1,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.items.get(handle.id)?.as_ref()
+    }
+
+    pub fn try_get(&self, handle: Handle<T>) -> Result<&T, WhirlwindError> {
+        self.get(handle).ok_or(WhirlwindError::AssetMissing {
+            asset: std::any::type_name::<T>(),
+            id: handle.id,
+        })
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.items.get_mut(handle.id)?.as_mut()
+    }
+
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        self.items.get_mut(handle.id)?.take()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.items.iter().enumerate().filter_map(|(id, item)| {
+            item.as_ref().map(|item| {
+                (
+                    Handle {
+                        id,
+                        marker: PhantomData,
+                    },
+                    item,
+                )
+            })
+        })
+    }
+}
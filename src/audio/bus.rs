@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use crate::ecs::component::Component;
+
+use super::{MASTER_BUS, MUSIC_BUS, SAMPLE_RATE, SFX_BUS, VOICE_BUS};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowPass {
+    pub cutoff: f32,
+    state: [f32; 2],
+}
+
+impl LowPass {
+    pub fn new(cutoff: f32) -> Self {
+        Self {
+            cutoff,
+            state: [0.0; 2],
+        }
+    }
+
+    // one-pole filter over interleaved stereo
+    pub fn process(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        let rc = 1.0 / (std::f32::consts::TAU * self.cutoff.max(1.0));
+        let dt = 1.0 / sample_rate as f32;
+        let alpha = dt / (rc + dt);
+        for frame in buffer.chunks_exact_mut(2) {
+            for (sample, state) in frame.iter_mut().zip(self.state.iter_mut()) {
+                *state += alpha * (*sample - *state);
+                *sample = *state;
+            }
+        }
+    }
+}
+
+// a bus's settings, the mixing itself happens on the output
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioBus {
+    pub volume: f32,
+    pub muted: bool,
+    // cutoff in hz
+    pub low_pass: Option<f32>,
+    pub reverb_send: f32,
+}
+
+impl Default for AudioBus {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            muted: false,
+            low_pass: None,
+            reverb_send: 0.0,
+        }
+    }
+}
+
+impl AudioBus {
+    pub fn gain(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume }
+    }
+
+    pub fn set_low_pass(&mut self, cutoff: Option<f32>) {
+        self.low_pass = cutoff;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reverb {
+    pub room_size: f32,
+    pub damping: f32,
+    pub wet: f32,
+}
+
+impl Default for Reverb {
+    fn default() -> Self {
+        Self {
+            room_size: 0.8,
+            damping: 0.3,
+            wet: 0.35,
+        }
+    }
+}
+
+// small schroeder reverb: parallel combs into series allpasses
+#[derive(Debug)]
+struct ReverbTank {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+#[derive(Debug)]
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter_state: f32,
+}
+
+#[derive(Debug)]
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Default for ReverbTank {
+    fn default() -> Self {
+        const COMB_TUNING: [usize; 4] = [1557, 1617, 1491, 1422];
+        const ALLPASS_TUNING: [usize; 2] = [556, 441];
+        let scale = SAMPLE_RATE as f32 / 44_100.0;
+        Self {
+            combs: COMB_TUNING
+                .iter()
+                .map(|&len| Comb {
+                    buffer: vec![0.0; (len as f32 * scale) as usize],
+                    index: 0,
+                    filter_state: 0.0,
+                })
+                .collect(),
+            allpasses: ALLPASS_TUNING
+                .iter()
+                .map(|&len| Allpass {
+                    buffer: vec![0.0; (len as f32 * scale) as usize],
+                    index: 0,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ReverbTank {
+    // mono reverb tail from a stereo send, added back into `out`
+    fn process(&mut self, settings: &Reverb, send: &[f32], out: &mut [f32]) {
+        for (input, output) in send.chunks_exact(2).zip(out.chunks_exact_mut(2)) {
+            let input = (input[0] + input[1]) * 0.5;
+            let mut wet = 0.0;
+            for comb in &mut self.combs {
+                let delayed = comb.buffer[comb.index];
+                comb.filter_state =
+                    delayed * (1.0 - settings.damping) + comb.filter_state * settings.damping;
+                comb.buffer[comb.index] = input + comb.filter_state * settings.room_size;
+                comb.index = (comb.index + 1) % comb.buffer.len();
+                wet += delayed;
+            }
+            for allpass in &mut self.allpasses {
+                let delayed = allpass.buffer[allpass.index];
+                allpass.buffer[allpass.index] = wet + delayed * 0.5;
+                allpass.index = (allpass.index + 1) % allpass.buffer.len();
+                wet = delayed - wet;
+            }
+            output[0] += wet * settings.wet;
+            output[1] += wet * settings.wet;
+        }
+    }
+}
+
+// the settings for every bus, changes reach the output at the end of the frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mixer {
+    buses: HashMap<String, AudioBus>,
+    pub master: AudioBus,
+    pub reverb: Reverb,
+}
+
+impl Component for Mixer {}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        let mut mixer = Self {
+            buses: HashMap::new(),
+            master: AudioBus::default(),
+            reverb: Reverb::default(),
+        };
+        for name in [MUSIC_BUS, SFX_BUS, VOICE_BUS] {
+            mixer.add_bus(name);
+        }
+        mixer
+    }
+}
+
+impl Mixer {
+    pub fn add_bus(&mut self, name: impl Into<String>) -> &mut AudioBus {
+        self.buses.entry(name.into()).or_default()
+    }
+
+    pub fn bus(&self, name: &str) -> Option<&AudioBus> {
+        if name == MASTER_BUS {
+            return Some(&self.master);
+        }
+        self.buses.get(name)
+    }
+
+    pub fn bus_mut(&mut self, name: &str) -> Option<&mut AudioBus> {
+        if name == MASTER_BUS {
+            return Some(&mut self.master);
+        }
+        self.buses.get_mut(name)
+    }
+
+    pub fn bus_names(&self) -> impl Iterator<Item = &str> {
+        self.buses.keys().map(String::as_str)
+    }
+
+    pub fn set_volume(&mut self, bus: &str, volume: f32) {
+        if let Some(bus) = self.bus_mut(bus) {
+            bus.volume = volume;
+        }
+    }
+
+    pub fn set_muted(&mut self, bus: &str, muted: bool) {
+        if let Some(bus) = self.bus_mut(bus) {
+            bus.muted = muted;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BusBuffer {
+    buffer: Vec<f32>,
+    low_pass: LowPass,
+}
+
+impl BusBuffer {
+    fn prepare(&mut self, len: usize) {
+        self.buffer.clear();
+        self.buffer.resize(len, 0.0);
+    }
+
+    fn process(&mut self, settings: &AudioBus) {
+        match settings.low_pass {
+            Some(cutoff) => {
+                self.low_pass.cutoff = cutoff;
+                self.low_pass.process(&mut self.buffer, SAMPLE_RATE);
+            }
+            None => self.low_pass.state = [0.0; 2],
+        }
+        let gain = settings.gain();
+        for sample in &mut self.buffer {
+            *sample *= gain;
+        }
+    }
+}
+
+// the output's side of the mixer, the buffers and effect state the settings
+// are applied with. buffers only grow, so mixing doesn't allocate once warm
+#[derive(Debug, Default)]
+pub(crate) struct Mixdown {
+    buses: HashMap<String, BusBuffer>,
+    master: BusBuffer,
+    reverb: ReverbTank,
+    reverb_send: Vec<f32>,
+}
+
+impl Mixdown {
+    pub(crate) fn prepare(&mut self, mixer: &Mixer, len: usize) {
+        for name in mixer.buses.keys() {
+            if !self.buses.contains_key(name) {
+                self.buses.insert(name.clone(), BusBuffer::default());
+            }
+        }
+        self.master.prepare(len);
+        for bus in self.buses.values_mut() {
+            bus.prepare(len);
+        }
+        self.reverb_send.clear();
+        self.reverb_send.resize(len, 0.0);
+    }
+
+    // buffer sources are mixed into; unknown buses fall through to master
+    pub(crate) fn input(&mut self, bus: &str) -> &mut [f32] {
+        match self.buses.get_mut(bus) {
+            Some(bus) => &mut bus.buffer,
+            None => &mut self.master.buffer,
+        }
+    }
+
+    pub(crate) fn finish(&mut self, mixer: &Mixer, out: &mut [f32]) {
+        // master's send only takes what was mixed into it directly, the buses
+        // have sent theirs by the time they're summed in
+        for (send, sample) in self.reverb_send.iter_mut().zip(&self.master.buffer) {
+            *send += sample * mixer.master.reverb_send;
+        }
+        for (name, bus) in &mut self.buses {
+            let Some(settings) = mixer.buses.get(name) else {
+                continue;
+            };
+            bus.process(settings);
+            for ((master, send), sample) in self
+                .master
+                .buffer
+                .iter_mut()
+                .zip(self.reverb_send.iter_mut())
+                .zip(&bus.buffer)
+            {
+                *master += sample;
+                *send += sample * settings.reverb_send;
+            }
+        }
+
+        self.reverb
+            .process(&mixer.reverb, &self.reverb_send, &mut self.master.buffer);
+        self.master.process(&mixer.master);
+
+        for (out, sample) in out.iter_mut().zip(&self.master.buffer) {
+            *out = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an impulse on the sfx bus, long enough for the reverb tail to come back
+    fn mix_impulse(mixer: &Mixer) -> Vec<f32> {
+        let mut mixdown = Mixdown::default();
+        let mut out = vec![0.0; 8192];
+        mixdown.prepare(mixer, out.len());
+        mixdown.input(SFX_BUS)[..2].fill(1.0);
+        mixdown.finish(mixer, &mut out);
+        out
+    }
+
+    #[test]
+    fn bus_audio_reaches_the_reverb_once() {
+        let mut mixer = Mixer::default();
+        mixer.reverb.wet = 1.0;
+        mixer.master.volume = 0.5;
+        mixer.bus_mut(SFX_BUS).unwrap().reverb_send = 0.5;
+        let bus_only = mix_impulse(&mixer);
+        assert!(bus_only[2..].iter().any(|&sample| sample != 0.0));
+
+        mixer.master.reverb_send = 1.0;
+        assert_eq!(mix_impulse(&mixer), bus_only);
+
+        mixer.bus_mut(SFX_BUS).unwrap().reverb_send = 0.0;
+        let dry = mix_impulse(&mixer);
+        assert_eq!(dry[..2], [0.5, 0.5]);
+        assert!(dry[2..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn muted_and_filtered_buses() {
+        let mut mixer = Mixer::default();
+        mixer.set_muted(SFX_BUS, true);
+        assert!(mix_impulse(&mixer).iter().all(|&sample| sample == 0.0));
+        mixer.set_muted(SFX_BUS, false);
+        mixer.bus_mut(SFX_BUS).unwrap().set_low_pass(Some(1_000.0));
+        let filtered = mix_impulse(&mixer);
+        assert!(filtered[0] > 0.0 && filtered[0] < 1.0 && filtered[2] > 0.0);
+        // unknown buses go straight to master
+        mixer.set_volume(MASTER_BUS, 0.25);
+        let mut mixdown = Mixdown::default();
+        let mut out = vec![0.0; 2];
+        mixdown.prepare(&mixer, 2);
+        mixdown.input("nowhere").fill(1.0);
+        mixdown.finish(&mixer, &mut out);
+        assert_eq!(out, [0.25, 0.25]);
+    }
+}
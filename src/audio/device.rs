@@ -0,0 +1,234 @@
+// The default output device, opened with cpal once the renderer starts. The
+// AudioRenderer is moved into the device's callback, where its stereo at
+// SAMPLE_RATE is spread over the device's channels and resampled when the
+// device can't run at SAMPLE_RATE itself. Taking the renderer out of
+// AudioOutput before then keeps the device closed, to play it some other way.
+
+use cpal::{
+    FromSample, SampleFormat, SampleRate, SizedSample, StreamConfig,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+
+use crate::ecs::{component::Component, world::World};
+
+use super::{AudioOutput, AudioRenderer, SAMPLE_RATE};
+
+// keeps the stream playing for as long as it's a resource
+pub struct AudioDevice {
+    name: String,
+    config: StreamConfig,
+    _stream: cpal::Stream,
+}
+
+impl std::fmt::Debug for AudioDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioDevice")
+            .field("name", &self.name)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Component for AudioDevice {}
+
+impl AudioDevice {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.config.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.0
+    }
+
+    fn open(renderer: AudioRenderer) -> anyhow::Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No output device"))?;
+        let default = device.default_output_config()?;
+        // the default format, at SAMPLE_RATE when the device has it
+        let supported = device
+            .supported_output_configs()?
+            .filter(|range| range.sample_format() == default.sample_format())
+            .find_map(|range| range.try_with_sample_rate(SampleRate(SAMPLE_RATE)))
+            .unwrap_or(default);
+        let sample_format = supported.sample_format();
+        let config = supported.config();
+        let adapter = Adapter::new(renderer, config.channels as usize, config.sample_rate.0);
+        let stream = match sample_format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, adapter)?,
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, adapter)?,
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, adapter)?,
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, adapter)?,
+            other => anyhow::bail!("Unsupported sample format {}", other),
+        };
+        stream.play()?;
+        Ok(Self {
+            name: device.name().unwrap_or_default(),
+            config,
+            _stream: stream,
+        })
+    }
+}
+
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut adapter: Adapter,
+) -> anyhow::Result<cpal::Stream> {
+    let mut mixed = Vec::new();
+    Ok(device.build_output_stream(
+        config,
+        move |out: &mut [T], _: &cpal::OutputCallbackInfo| {
+            mixed.resize(out.len(), 0.0);
+            adapter.fill(&mut mixed);
+            for (out, &sample) in out.iter_mut().zip(&mixed) {
+                *out = T::from_sample(sample);
+            }
+        },
+        |e| log::error!("Audio output failed: {}", e),
+        None,
+    )?)
+}
+
+// the renderer's stereo at SAMPLE_RATE, as the device's channels at its rate
+struct Adapter {
+    renderer: AudioRenderer,
+    channels: usize,
+    // rendered frames per device frame
+    step: f64,
+    // rendered stereo not played yet, and how far into it the device is
+    pending: Vec<f32>,
+    position: f64,
+}
+
+impl Adapter {
+    fn new(renderer: AudioRenderer, channels: usize, sample_rate: u32) -> Self {
+        Self {
+            renderer,
+            channels: channels.max(1),
+            step: SAMPLE_RATE as f64 / sample_rate.max(1) as f64,
+            pending: Vec::new(),
+            position: 0.0,
+        }
+    }
+
+    // mono gets both sides, channels past the first two are left silent
+    fn fill(&mut self, out: &mut [f32]) {
+        let frames = out.len() / self.channels;
+        // one more frame to interpolate towards
+        let needed = (self.position + self.step * frames as f64).ceil() as usize + 1;
+        if self.pending.len() < needed * 2 {
+            let rendered = self.pending.len();
+            self.pending.resize(needed * 2, 0.0);
+            self.renderer.render(&mut self.pending[rendered..]);
+        }
+
+        for frame in out.chunks_mut(self.channels) {
+            let index = self.position as usize;
+            let t = (self.position - index as f64) as f32;
+            let sample = |channel: usize| {
+                let a = self.pending[index * 2 + channel];
+                let b = self
+                    .pending
+                    .get(index * 2 + 2 + channel)
+                    .copied()
+                    .unwrap_or(a);
+                a + (b - a) * t
+            };
+            let (left, right) = (sample(0), sample(1));
+            match frame {
+                [mono] => *mono = (left + right) * 0.5,
+                [l, r, rest @ ..] => {
+                    *l = left;
+                    *r = right;
+                    rest.fill(0.0);
+                }
+                [] => {}
+            }
+            self.position += self.step;
+        }
+
+        let played = (self.position as usize).min(self.pending.len() / 2);
+        self.pending.drain(..played * 2);
+        self.position -= played as f64;
+    }
+}
+
+// plays the renderer on the default device, unless it was taken already
+pub(crate) fn open_default_device(world: &mut World) {
+    let Some(renderer) = world
+        .get_resource_mut::<AudioOutput>()
+        .and_then(AudioOutput::take_renderer)
+    else {
+        return;
+    };
+    match AudioDevice::open(renderer) {
+        Ok(device) => {
+            log::info!(
+                "Audio playing on {}, {} channels at {} Hz",
+                device.name(),
+                device.channels(),
+                device.sample_rate()
+            );
+            world.insert_resource(device);
+        }
+        Err(e) => log::warn!(
+            "Unable to open an audio device, nothing will be heard: {}",
+            e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioClip, AudioSource, Mixer, MusicController, output::sync_audio};
+
+    fn adapter_playing(samples: Vec<f32>, channels: usize, sample_rate: u32) -> (World, Adapter) {
+        let mut world = World::new();
+        world.init_resource::<Mixer>();
+        world.init_resource::<MusicController>();
+        world.init_resource::<AudioOutput>();
+        let renderer = world.resource_mut::<AudioOutput>().take_renderer().unwrap();
+        let clip = AudioClip::new(samples, 1, SAMPLE_RATE);
+        world.spawn().insert(AudioSource::new(clip));
+        sync_audio(&mut world);
+        (world, Adapter::new(renderer, channels, sample_rate))
+    }
+
+    #[test]
+    fn matching_rates_pass_through() {
+        let ramp: Vec<f32> = (0..64).map(|i| i as f32 / 64.0).collect();
+        let (_world, mut adapter) = adapter_playing(ramp.clone(), 2, SAMPLE_RATE);
+        let mut out = vec![0.0; 40];
+        adapter.fill(&mut out);
+        let mut more = vec![0.0; 40];
+        adapter.fill(&mut more);
+        out.extend(more);
+        let left: Vec<f32> = out.iter().step_by(2).copied().collect();
+        assert_eq!(left, ramp[..40]);
+        assert_eq!(out[1], out[0], "mono clips are centered");
+    }
+
+    #[test]
+    fn other_rates_and_channel_counts() {
+        let ramp: Vec<f32> = (0..200).map(|i| i as f32 / 200.0).collect();
+        // a device at twice the rate gets every rendered frame and one between
+        let (_world, mut adapter) = adapter_playing(ramp.clone(), 1, SAMPLE_RATE * 2);
+        let mut out = vec![0.0; 9];
+        adapter.fill(&mut out);
+        for (i, sample) in out.iter().enumerate() {
+            assert!((sample - i as f32 / 400.0).abs() < 1e-6, "{:?}", out);
+        }
+
+        // extra channels are silent
+        let (_world, mut adapter) = adapter_playing(ramp, 4, SAMPLE_RATE);
+        let mut out = vec![1.0; 12];
+        adapter.fill(&mut out);
+        assert_eq!(out[4..8], [1.0 / 200.0, 1.0 / 200.0, 0.0, 0.0]);
+    }
+}
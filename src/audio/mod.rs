@@ -0,0 +1,28 @@
+// Software mixer. Sources are mixed per bus, buses are summed into master,
+// and the result is rendered as interleaved stereo at SAMPLE_RATE by an
+// AudioRenderer, which plays on the default output device on native, see
+// output.rs and device.rs. The web has no device yet, the renderer is left in
+// AudioOutput there.
+
+pub mod bus;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod device;
+pub mod music;
+pub mod output;
+pub mod source;
+pub mod spatial;
+
+pub use bus::{AudioBus, LowPass, Mixer, Reverb};
+#[cfg(not(target_arch = "wasm32"))]
+pub use device::AudioDevice;
+pub use music::{MusicController, MusicStream, MusicTrack};
+pub use output::{AudioOutput, AudioRenderer};
+pub use source::{AudioClip, AudioSource};
+pub use spatial::{AudioListener, Spatial};
+
+pub const SAMPLE_RATE: u32 = 48_000;
+
+pub const MASTER_BUS: &str = "master";
+pub const MUSIC_BUS: &str = "music";
+pub const SFX_BUS: &str = "sfx";
+pub const VOICE_BUS: &str = "voice";
@@ -0,0 +1,450 @@
+use std::{collections::VecDeque, path::PathBuf};
+
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, DecoderOptions},
+    errors::Error,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::ecs::component::Component;
+
+use super::{MUSIC_BUS, SAMPLE_RATE};
+
+#[derive(Debug, Clone)]
+pub struct MusicTrack {
+    pub path: PathBuf,
+    pub looping: bool,
+    // loop points in source frames, `loop_end: None` loops at the end of the file
+    pub loop_start: u64,
+    pub loop_end: Option<u64>,
+}
+
+impl MusicTrack {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            looping: true,
+            loop_start: 0,
+            loop_end: None,
+        }
+    }
+
+    pub fn once(mut self) -> Self {
+        self.looping = false;
+        self
+    }
+
+    pub fn with_loop_points(mut self, start: u64, end: Option<u64>) -> Self {
+        self.looping = true;
+        self.loop_start = start;
+        self.loop_end = end;
+        self
+    }
+}
+
+// decodes packets on demand so only a few packets are ever held in memory
+pub struct MusicStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    looping: bool,
+    loop_start: u64,
+    loop_end: Option<u64>,
+    // source frame index of the next decoded frame
+    frame: u64,
+    // frames to drop after an accurate seek lands before the requested frame
+    skip: u64,
+    buffered: VecDeque<[f32; 2]>,
+    fraction: f64,
+    finished: bool,
+}
+
+impl std::fmt::Debug for MusicStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MusicStream")
+            .field("track_id", &self.track_id)
+            .field("sample_rate", &self.sample_rate)
+            .field("frame", &self.frame)
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+type OpenedTrack = (Box<dyn FormatReader>, Box<dyn Decoder>, u32);
+
+pub(crate) fn open_decoder(path: &std::path::Path) -> anyhow::Result<OpenedTrack> {
+    crate::error::ensure_exists(path)?;
+    let file = std::fs::File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or(anyhow::anyhow!("No audio track in {}", path.display()))?;
+    let track_id = track.id;
+    let decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    Ok((format, decoder, track_id))
+}
+
+impl MusicStream {
+    pub fn open(track: &MusicTrack) -> anyhow::Result<Self> {
+        let (format, decoder, track_id) = open_decoder(&track.path)?;
+        let sample_rate = decoder
+            .codec_params()
+            .sample_rate
+            .ok_or(anyhow::anyhow!("Unknown sample rate"))?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            looping: track.looping,
+            loop_start: track.loop_start,
+            loop_end: track.loop_end,
+            frame: 0,
+            skip: 0,
+            buffered: VecDeque::new(),
+            fraction: 0.0,
+            finished: false,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished && self.buffered.is_empty()
+    }
+
+    fn seek(&mut self, frame: u64) -> anyhow::Result<()> {
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp {
+                ts: frame,
+                track_id: self.track_id,
+            },
+        )?;
+        self.decoder.reset();
+        self.frame = seeked.actual_ts;
+        self.skip = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        Ok(())
+    }
+
+    fn restart_loop(&mut self) {
+        if let Err(e) = self.seek(self.loop_start) {
+            log::error!("Unable to loop music stream: {}", e);
+            self.finished = true;
+        }
+    }
+
+    fn decode_packet(&mut self) {
+        let packet = match self.format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                if self.looping {
+                    self.restart_loop();
+                } else {
+                    self.finished = true;
+                }
+                return;
+            }
+            Err(e) => {
+                log::error!("Unable to read music packet: {}", e);
+                self.finished = true;
+                return;
+            }
+        };
+        if packet.track_id() != self.track_id {
+            return;
+        }
+
+        let decoded = match self.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // corrupt packets are skipped rather than ending the stream
+            Err(Error::DecodeError(e)) => {
+                log::warn!("Skipping undecodable music packet: {}", e);
+                return;
+            }
+            Err(e) => {
+                log::error!("Unable to decode music: {}", e);
+                self.finished = true;
+                return;
+            }
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        samples.copy_interleaved_ref(decoded);
+
+        for frame in samples.samples().chunks_exact(channels) {
+            if self.skip > 0 {
+                self.skip -= 1;
+                self.frame += 1;
+                continue;
+            }
+            if self.looping && self.loop_end.is_some_and(|end| self.frame >= end) {
+                self.restart_loop();
+                return;
+            }
+            let right = if channels > 1 { frame[1] } else { frame[0] };
+            self.buffered.push_back([frame[0], right]);
+            self.frame += 1;
+        }
+    }
+
+    fn fill(&mut self, frames: usize) {
+        while self.buffered.len() < frames && !self.finished {
+            self.decode_packet();
+        }
+    }
+
+    // mixes resampled stereo frames into `out` scaled by a per-frame gain
+    pub(crate) fn mix_into(&mut self, out: &mut [f32], mut gain: impl FnMut() -> f32) {
+        let step = self.sample_rate as f64 / SAMPLE_RATE as f64;
+        for frame in out.chunks_exact_mut(2) {
+            self.fill(2);
+            let Some(&a) = self.buffered.front() else {
+                return;
+            };
+            let b = self.buffered.get(1).copied().unwrap_or(a);
+            let t = self.fraction as f32;
+            let gain = gain();
+
+            frame[0] += (a[0] + (b[0] - a[0]) * t) * gain;
+            frame[1] += (a[1] + (b[1] - a[1]) * t) * gain;
+
+            self.fraction += step;
+            while self.fraction >= 1.0 && !self.buffered.is_empty() {
+                self.buffered.pop_front();
+                self.fraction -= 1.0;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Voice {
+    stream: MusicStream,
+    gain: f32,
+    target: f32,
+    // gain change per output frame
+    rate: f32,
+}
+
+impl Voice {
+    fn fade_to(&mut self, target: f32, seconds: f32) {
+        self.target = target;
+        self.rate = if seconds > 0.0 {
+            (target - self.gain).abs() / (seconds * SAMPLE_RATE as f32)
+        } else {
+            f32::INFINITY
+        };
+    }
+}
+
+fn step_gain(gain: &mut f32, target: f32, rate: f32) -> f32 {
+    if *gain < target {
+        *gain = (*gain + rate).min(target);
+    } else if *gain > target {
+        *gain = (*gain - rate).max(target);
+    }
+    *gain
+}
+
+// what's sent to the output, played in order with the frames around it
+#[derive(Debug)]
+pub(crate) enum MusicCommand {
+    Play { stream: MusicStream, crossfade: f32 },
+    Stop { fade_out: f32 },
+}
+
+// the settings the output mixes music with
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MusicSettings {
+    pub(crate) bus: String,
+    pub(crate) volume: f32,
+    pub(crate) duck_volume: f32,
+    pub(crate) duck_fade: f32,
+    pub(crate) auto_duck: bool,
+}
+
+#[derive(Debug)]
+pub struct MusicController {
+    pub bus: String,
+    pub volume: f32,
+    // gain applied while dialogue plays on the voice bus
+    pub duck_volume: f32,
+    pub duck_fade: f32,
+    pub auto_duck: bool,
+    // tracks are opened here and decoded on the output
+    pub(crate) commands: Vec<MusicCommand>,
+    // commands handed to the output so far
+    pub(crate) sent: u64,
+    playing: bool,
+}
+
+impl Component for MusicController {}
+
+impl Default for MusicController {
+    fn default() -> Self {
+        Self {
+            bus: MUSIC_BUS.to_string(),
+            volume: 1.0,
+            duck_volume: 0.3,
+            duck_fade: 0.25,
+            auto_duck: true,
+            commands: Vec::new(),
+            sent: 0,
+            playing: false,
+        }
+    }
+}
+
+impl MusicController {
+    pub fn play(&mut self, track: &MusicTrack, crossfade: f32) -> anyhow::Result<()> {
+        let stream = MusicStream::open(track)?;
+        self.commands.push(MusicCommand::Play { stream, crossfade });
+        self.playing = true;
+        Ok(())
+    }
+
+    pub fn stop(&mut self, fade_out: f32) {
+        self.commands.push(MusicCommand::Stop { fade_out });
+        self.playing = false;
+    }
+
+    // until a track that doesn't loop is reported finished
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub(crate) fn settings(&self) -> MusicSettings {
+        MusicSettings {
+            bus: self.bus.clone(),
+            volume: self.volume,
+            duck_volume: self.duck_volume,
+            duck_fade: self.duck_fade,
+            auto_duck: self.auto_duck,
+        }
+    }
+
+    // from the output, `applied` commands in and whether its track is still going
+    pub(crate) fn report(&mut self, applied: u64, playing: bool) {
+        if applied == self.sent && self.commands.is_empty() {
+            self.playing = playing;
+        }
+    }
+}
+
+// the output's side, the open streams and their fades
+#[derive(Debug)]
+pub(crate) struct MusicPlayer {
+    duck_gain: f32,
+    current: Option<Voice>,
+    fading_out: Vec<Voice>,
+    ducking: Vec<f32>,
+    pub(crate) applied: u64,
+}
+
+impl Default for MusicPlayer {
+    fn default() -> Self {
+        Self {
+            duck_gain: 1.0,
+            current: None,
+            fading_out: Vec::new(),
+            ducking: Vec::new(),
+            applied: 0,
+        }
+    }
+}
+
+impl MusicPlayer {
+    pub(crate) fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+
+    pub(crate) fn apply(&mut self, command: MusicCommand) {
+        self.applied += 1;
+        let fade = match command {
+            MusicCommand::Play { stream, crossfade } => {
+                let mut voice = Voice {
+                    stream,
+                    gain: 0.0,
+                    target: 0.0,
+                    rate: 0.0,
+                };
+                voice.fade_to(1.0, crossfade);
+                self.current
+                    .replace(voice)
+                    .map(|previous| (previous, crossfade))
+            }
+            MusicCommand::Stop { fade_out } => {
+                self.current.take().map(|previous| (previous, fade_out))
+            }
+        };
+        if let Some((mut previous, seconds)) = fade {
+            previous.fade_to(0.0, seconds);
+            self.fading_out.push(previous);
+        }
+    }
+
+    pub(crate) fn mix_into(&mut self, settings: &MusicSettings, out: &mut [f32], dialogue: bool) {
+        let duck_target = if dialogue && settings.auto_duck {
+            settings.duck_volume
+        } else {
+            1.0
+        };
+        let duck_rate = if settings.duck_fade > 0.0 {
+            1.0 / (settings.duck_fade * SAMPLE_RATE as f32)
+        } else {
+            f32::INFINITY
+        };
+
+        // duck gain is advanced once per frame and shared by every voice
+        self.ducking.clear();
+        for _ in 0..out.len() / 2 {
+            let gain = step_gain(&mut self.duck_gain, duck_target, duck_rate);
+            self.ducking.push(gain * settings.volume);
+        }
+
+        for voice in self.current.iter_mut().chain(self.fading_out.iter_mut()) {
+            let Voice {
+                stream,
+                gain,
+                target,
+                rate,
+            } = voice;
+            let mut ducking = self.ducking.iter();
+            stream.mix_into(out, || {
+                step_gain(gain, *target, *rate) * ducking.next().copied().unwrap_or(1.0)
+            });
+        }
+
+        self.fading_out
+            .retain(|voice| voice.gain > 0.0 && !voice.stream.is_finished());
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|voice| voice.stream.is_finished())
+        {
+            self.current = None;
+        }
+    }
+}
@@ -0,0 +1,346 @@
+// The link between the World and whatever plays the sound. On native the
+// AudioRenderer is moved to the default device's callback when the renderer
+// starts (device.rs); to play it some other way take it out of AudioOutput
+// before then and call `render` from your own callback. At the end of every
+// frame `sync_audio` sends it the mixer settings when they've changed, music
+// commands, and a snapshot of the sources, and reads back how far each source
+// got. The renderer never touches the World. Until it's taken nothing plays,
+// sources stay where they are and music commands wait.
+
+use std::{collections::HashMap, sync::mpsc};
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    importance,
+};
+
+use super::{
+    AudioSource, Mixer, MusicController, SAMPLE_RATE, VOICE_BUS,
+    bus::Mixdown,
+    music::{MusicCommand, MusicPlayer, MusicSettings},
+    source::{Playback, SourceFrame},
+};
+
+#[derive(Debug)]
+enum ToOutput {
+    Mixer(Mixer),
+    Music(MusicCommand),
+    Snapshot(Snapshot),
+}
+
+// the sources for a frame. the renderer sends each one back once a newer one
+// replaces it, with how far the sources and music got
+#[derive(Debug)]
+struct Snapshot {
+    sequence: u64,
+    sources: Vec<SourceFrame>,
+    music: MusicSettings,
+    music_applied: u64,
+    music_playing: bool,
+}
+
+#[derive(Debug)]
+pub struct AudioOutput {
+    sender: mpsc::Sender<ToOutput>,
+    reports: mpsc::Receiver<Snapshot>,
+    renderer: Option<AudioRenderer>,
+    // false once the renderer has been dropped
+    connected: bool,
+    // what the renderer was last sent
+    mixer: Option<Mixer>,
+    sequence: u64,
+}
+
+impl Component for AudioOutput {}
+
+impl Default for AudioOutput {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let (reporter, reports) = mpsc::channel();
+        Self {
+            sender,
+            reports,
+            renderer: Some(AudioRenderer {
+                receiver,
+                reports: reporter,
+                mixer: Mixer::default(),
+                mixdown: Mixdown::default(),
+                music: MusicPlayer::default(),
+                snapshot: None,
+                playbacks: HashMap::new(),
+            }),
+            connected: true,
+            mixer: None,
+            sequence: 0,
+        }
+    }
+}
+
+impl AudioOutput {
+    // None once it's been taken
+    pub fn take_renderer(&mut self) -> Option<AudioRenderer> {
+        self.renderer.take()
+    }
+
+    // whether a renderer has been taken and is still around to play
+    pub fn is_playing_out(&self) -> bool {
+        self.renderer.is_none() && self.connected
+    }
+
+    fn send(&mut self, message: ToOutput) {
+        self.connected &= self.sender.send(message).is_ok();
+    }
+}
+
+// mixes on the device's thread from what the World last sent
+#[derive(Debug)]
+pub struct AudioRenderer {
+    receiver: mpsc::Receiver<ToOutput>,
+    reports: mpsc::Sender<Snapshot>,
+    mixer: Mixer,
+    mixdown: Mixdown,
+    music: MusicPlayer,
+    snapshot: Option<Snapshot>,
+    playbacks: HashMap<Entity, Playback>,
+}
+
+impl AudioRenderer {
+    // fills `out` with interleaved stereo at SAMPLE_RATE
+    pub fn render(&mut self, out: &mut [f32]) {
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                ToOutput::Mixer(mixer) => self.mixer = mixer,
+                ToOutput::Music(command) => self.music.apply(command),
+                ToOutput::Snapshot(snapshot) => self.replace(snapshot),
+            }
+        }
+
+        self.mixdown.prepare(&self.mixer, out.len());
+        if let Some(snapshot) = &self.snapshot {
+            let mut dialogue = false;
+            for source in &snapshot.sources {
+                let Some(playback) = self.playbacks.get_mut(&source.entity) else {
+                    continue;
+                };
+                dialogue |= source.playing && !playback.finished && source.bus == VOICE_BUS;
+                playback.mix_into(source, self.mixdown.input(&source.bus), SAMPLE_RATE);
+            }
+            let music = self.mixdown.input(&snapshot.music.bus);
+            self.music.mix_into(&snapshot.music, music, dialogue);
+        }
+        self.mixdown.finish(&self.mixer, out);
+    }
+
+    fn replace(&mut self, mut snapshot: Snapshot) {
+        for source in &mut snapshot.sources {
+            let playback = self
+                .playbacks
+                .entry(source.entity)
+                .or_insert_with(|| Playback::new(source.cursor));
+            if let Some(cursor) = source.seek {
+                playback.cursor = cursor;
+                playback.finished = false;
+            }
+            // a finished source plays again once the World has seen it stop
+            if !source.playing {
+                playback.finished = false;
+            }
+            playback.seen = snapshot.sequence;
+        }
+        // despawned, or the AudioSource was removed
+        self.playbacks
+            .retain(|_, playback| playback.seen == snapshot.sequence);
+
+        let Some(mut previous) = self.snapshot.replace(snapshot) else {
+            return;
+        };
+        for source in &mut previous.sources {
+            if let Some(playback) = self.playbacks.get(&source.entity) {
+                source.cursor = playback.cursor;
+                source.finished = playback.finished;
+            }
+        }
+        previous.music_applied = self.music.applied;
+        previous.music_playing = self.music.is_playing();
+        // the World may be gone already
+        let _ = self.reports.send(previous);
+    }
+}
+
+pub fn sync_audio(world: &mut World) {
+    let _ = world.try_resource_scope(|world, output: &mut AudioOutput| {
+        if !output.is_playing_out() {
+            return;
+        }
+        for report in output.reports.try_iter() {
+            for frame in &report.sources {
+                if let Some(source) = world.get_component_mut::<AudioSource>(frame.entity) {
+                    source.report(frame, report.sequence);
+                }
+            }
+            if let Some(music) = world.get_resource_mut::<MusicController>() {
+                music.report(report.music_applied, report.music_playing);
+            }
+        }
+
+        if let Some(mixer) = world.get_resource::<Mixer>()
+            && output.mixer.as_ref() != Some(mixer)
+        {
+            output.mixer = Some(mixer.clone());
+            output.send(ToOutput::Mixer(mixer.clone()));
+        }
+        let Some(music) = world.get_resource_mut::<MusicController>() else {
+            return;
+        };
+        let commands = std::mem::take(&mut music.commands);
+        music.sent += commands.len() as u64;
+        let settings = music.settings();
+        for command in commands {
+            output.send(ToOutput::Music(command));
+        }
+
+        output.sequence += 1;
+        let sequence = output.sequence;
+        let audible: Vec<bool> = world
+            .query::<AudioSource>()
+            .into_iter()
+            .map(|(entity, _)| importance::is_audible(world, entity))
+            .collect();
+        let sources = world
+            .query_mut::<AudioSource>()
+            .into_iter()
+            .zip(audible)
+            .map(|((entity, source), audible)| source.frame(entity, audible, sequence))
+            .collect();
+        output.send(ToOutput::Snapshot(Snapshot {
+            sequence,
+            sources,
+            music: settings,
+            music_applied: 0,
+            music_playing: false,
+        }));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioClip, MUSIC_BUS};
+
+    fn world_with_output() -> (World, AudioRenderer) {
+        let mut world = World::new();
+        world.init_resource::<Mixer>();
+        world.init_resource::<MusicController>();
+        world.init_resource::<AudioOutput>();
+        let renderer = world.resource_mut::<AudioOutput>().take_renderer().unwrap();
+        (world, renderer)
+    }
+
+    #[test]
+    fn the_renderer_plays_what_the_world_sends_and_reports_back() {
+        let (mut world, mut renderer) = world_with_output();
+        let clip = AudioClip::new(vec![0.5; 100], 1, SAMPLE_RATE);
+        let source = world.spawn().insert(AudioSource::new(clip)).id();
+
+        let mut out = vec![0.0; 120];
+        sync_audio(&mut world);
+        renderer.render(&mut out);
+        assert!(out.iter().all(|&sample| sample == 0.5));
+        // the report for a snapshot comes back once the next one replaces it
+        for _ in 0..2 {
+            sync_audio(&mut world);
+            renderer.render(&mut out);
+        }
+        sync_audio(&mut world);
+        let source = world.get_component::<AudioSource>(source).unwrap();
+        assert_eq!(source.cursor, 0.0);
+        assert!(!source.playing, "a clip that doesn't loop stops at its end");
+        assert!(out.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn seeks_and_despawns_reach_the_renderer() {
+        let (mut world, mut renderer) = world_with_output();
+        let clip = AudioClip::new(vec![0.25; 1000], 1, SAMPLE_RATE);
+        let source = world.spawn().insert(AudioSource::new(clip).looping()).id();
+        let mut out = vec![0.0; 200];
+        sync_audio(&mut world);
+        renderer.render(&mut out);
+        sync_audio(&mut world);
+        renderer.render(&mut out);
+        sync_audio(&mut world);
+        assert_eq!(world.resource::<AudioOutput>().sequence, 3);
+        let playing = world.get_component_mut::<AudioSource>(source).unwrap();
+        assert_eq!(playing.cursor, 100.0);
+        playing.seek(500.0 / SAMPLE_RATE as f32);
+        sync_audio(&mut world);
+        renderer.render(&mut out);
+        assert_eq!(renderer.playbacks[&source].cursor, 600.0);
+        sync_audio(&mut world);
+        // a report from before the seek doesn't undo it
+        assert_eq!(
+            world.get_component::<AudioSource>(source).unwrap().cursor,
+            500.0
+        );
+
+        world.despawn(source);
+        sync_audio(&mut world);
+        renderer.render(&mut out);
+        assert!(renderer.playbacks.is_empty());
+        assert!(out.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn nothing_is_sent_without_a_renderer() {
+        let mut world = World::new();
+        world.init_resource::<Mixer>();
+        world.init_resource::<AudioOutput>();
+        sync_audio(&mut world);
+        let output = world.resource::<AudioOutput>();
+        assert!(!output.is_playing_out());
+        assert_eq!(output.sequence, 0);
+
+        let renderer = world.resource_mut::<AudioOutput>().take_renderer();
+        drop(renderer);
+        sync_audio(&mut world);
+        assert!(!world.resource::<AudioOutput>().is_playing_out());
+    }
+
+    #[test]
+    fn sources_are_heard_through_their_bus() {
+        let (mut world, mut renderer) = world_with_output();
+        let clip = AudioClip::new(vec![0.8; 4800], 1, SAMPLE_RATE);
+        world
+            .spawn()
+            .insert(AudioSource::new(clip).with_bus(MUSIC_BUS).looping());
+        let mut render = |world: &mut World| {
+            let mut out = vec![0.0; 64];
+            sync_audio(world);
+            renderer.render(&mut out);
+            out
+        };
+
+        world.resource_mut::<Mixer>().set_volume(MUSIC_BUS, 0.5);
+        assert!(render(&mut world).iter().all(|&sample| sample == 0.4));
+        world.resource_mut::<Mixer>().set_muted(MUSIC_BUS, true);
+        assert!(render(&mut world).iter().all(|&sample| sample == 0.0));
+
+        world.resource_mut::<Mixer>().set_muted(MUSIC_BUS, false);
+        world
+            .resource_mut::<Mixer>()
+            .bus_mut(MUSIC_BUS)
+            .unwrap()
+            .set_low_pass(Some(1_000.0));
+        let filtered = render(&mut world);
+        // a one-pole filter rising towards the constant input, after the gain
+        let rc = 1.0 / (std::f32::consts::TAU * 1_000.0);
+        let alpha = (1.0 / SAMPLE_RATE as f32) / (rc + 1.0 / SAMPLE_RATE as f32);
+        assert!((filtered[0] - 0.8 * alpha * 0.5).abs() < 1e-6);
+        assert_eq!(filtered[0], filtered[1]);
+        let left: Vec<f32> = filtered.iter().step_by(2).copied().collect();
+        assert!(
+            left.windows(2)
+                .all(|pair| pair[0] < pair[1] && pair[1] < 0.4)
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::sync::Arc;
+
+use crate::ecs::{component::Component, entity::Entity};
+
+use super::{
+    bus::LowPass,
+    spatial::{Spatial, SpatialState},
+};
+
+#[derive(Clone)]
+pub struct AudioClip {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl AudioClip {
+    pub fn new(samples: impl Into<Arc<[f32]>>, channels: u16, sample_rate: u32) -> Self {
+        Self {
+            samples: samples.into(),
+            channels: channels.max(1),
+            sample_rate,
+        }
+    }
+
+    // fully decodes the file, use MusicStream for long tracks
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        use symphonia::core::{audio::SampleBuffer, errors::Error};
+
+        let (mut format, mut decoder, track_id) = super::music::open_decoder(path.as_ref())?;
+        let mut samples = Vec::new();
+        let mut channels = 1;
+        let mut sample_rate = super::SAMPLE_RATE;
+
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+            let decoded = decoder.decode(&packet)?;
+            let spec = *decoded.spec();
+            channels = spec.channels.count() as u16;
+            sample_rate = spec.rate;
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buffer.samples());
+        }
+
+        Ok(Self::new(samples, channels, sample_rate))
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    // returns the (left, right) pair for a frame, upmixing mono
+    pub(crate) fn frame(&self, index: usize) -> (f32, f32) {
+        let channels = self.channels as usize;
+        let base = index * channels;
+        match channels {
+            1 => (self.samples[base], self.samples[base]),
+            _ => (self.samples[base], self.samples[base + 1]),
+        }
+    }
+}
+
+impl std::fmt::Debug for AudioClip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioClip")
+            .field("channels", &self.channels)
+            .field("sample_rate", &self.sample_rate)
+            .field("frames", &self.frames())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioSource {
+    pub clip: AudioClip,
+    pub bus: String,
+    pub volume: f32,
+    pub looping: bool,
+    pub playing: bool,
+    pub spatial: Option<Spatial>,
+    pub(crate) spatial_state: SpatialState,
+    // playback cursor in source frames as of the output's last report
+    pub(crate) cursor: f64,
+    // where the output should jump to, from `seek` and `stop`
+    pub(crate) seek: Option<f64>,
+    // the snapshot the last seek went out in, older reports don't know about it
+    pub(crate) seeked_in: u64,
+}
+
+impl Component for AudioSource {}
+
+impl AudioSource {
+    pub fn new(clip: AudioClip) -> Self {
+        Self {
+            clip,
+            bus: super::SFX_BUS.to_string(),
+            volume: 1.0,
+            looping: false,
+            playing: true,
+            spatial: None,
+            spatial_state: SpatialState::default(),
+            cursor: 0.0,
+            seek: None,
+            seeked_in: 0,
+        }
+    }
+
+    pub fn with_bus(mut self, bus: impl Into<String>) -> Self {
+        self.bus = bus.into();
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_spatial(mut self, spatial: Spatial) -> Self {
+        self.spatial = Some(spatial);
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.cursor = 0.0;
+        self.seek = Some(0.0);
+    }
+
+    // lags the output by a frame, and stays put while there's no output
+    pub fn position(&self) -> f32 {
+        self.cursor as f32 / self.clip.sample_rate as f32
+    }
+
+    pub fn seek(&mut self, seconds: f32) {
+        self.cursor = (seconds.max(0.0) * self.clip.sample_rate as f32) as f64;
+        self.seek = Some(self.cursor);
+    }
+
+    // what the output needs to play it in snapshot `sequence`, `audible` is
+    // false past the voice limit
+    pub(crate) fn frame(&mut self, entity: Entity, audible: bool, sequence: u64) -> SourceFrame {
+        if self.seek.is_some() {
+            self.seeked_in = sequence;
+        }
+        let (gains, pitch, occlusion) = match self.spatial {
+            Some(_) => {
+                let state = &self.spatial_state;
+                (state.gains, state.pitch, state.occlusion)
+            }
+            None => ([1.0; 2], 1.0, None),
+        };
+        SourceFrame {
+            entity,
+            clip: self.clip.clone(),
+            bus: self.bus.clone(),
+            playing: self.playing && audible,
+            looping: self.looping,
+            gains: gains.map(|gain| gain * self.volume),
+            step: self.clip.sample_rate as f64 / super::SAMPLE_RATE as f64 * pitch as f64,
+            occlusion,
+            seek: self.seek.take(),
+            cursor: self.cursor,
+            finished: false,
+        }
+    }
+
+    // how far the output got, from a snapshot it sent back
+    pub(crate) fn report(&mut self, frame: &SourceFrame, sequence: u64) {
+        if sequence < self.seeked_in || self.seek.is_some() {
+            return;
+        }
+        self.cursor = frame.cursor;
+        if frame.finished {
+            self.playing = false;
+        }
+    }
+}
+
+// a source as the output sees it for a frame. sent back once it's replaced,
+// with how far it got
+#[derive(Debug)]
+pub(crate) struct SourceFrame {
+    pub(crate) entity: Entity,
+    pub(crate) clip: AudioClip,
+    pub(crate) bus: String,
+    pub(crate) playing: bool,
+    pub(crate) looping: bool,
+    pub(crate) gains: [f32; 2],
+    // source frames per output frame, doppler included
+    pub(crate) step: f64,
+    // low-pass cutoff while something's in the way
+    pub(crate) occlusion: Option<f32>,
+    pub(crate) seek: Option<f64>,
+    pub(crate) cursor: f64,
+    // reached the end without looping
+    pub(crate) finished: bool,
+}
+
+// the output's side of a source, what's been played of it
+#[derive(Debug)]
+pub(crate) struct Playback {
+    pub(crate) cursor: f64,
+    pub(crate) finished: bool,
+    occlusion: LowPass,
+    // the occluded source is filtered on its own before it's mixed in
+    scratch: Vec<f32>,
+    // the snapshot it was last in
+    pub(crate) seen: u64,
+}
+
+impl Playback {
+    pub(crate) fn new(cursor: f64) -> Self {
+        Self {
+            cursor,
+            finished: false,
+            occlusion: LowPass::default(),
+            scratch: Vec::new(),
+            seen: 0,
+        }
+    }
+
+    // mixes up to `out.len() / 2` stereo frames into `out`, advancing the cursor
+    pub(crate) fn mix_into(&mut self, source: &SourceFrame, out: &mut [f32], output_rate: u32) {
+        if !source.playing || self.finished || source.clip.frames() == 0 {
+            return;
+        }
+        let Some(cutoff) = source.occlusion else {
+            self.render(source, out);
+            return;
+        };
+
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        scratch.resize(out.len(), 0.0);
+        self.render(source, &mut scratch);
+        self.occlusion.cutoff = cutoff;
+        self.occlusion.process(&mut scratch, output_rate);
+        for (out, sample) in out.iter_mut().zip(&scratch) {
+            *out += sample;
+        }
+        self.scratch = scratch;
+    }
+
+    fn render(&mut self, source: &SourceFrame, out: &mut [f32]) {
+        let clip = &source.clip;
+        let frames = clip.frames();
+
+        for frame in out.chunks_exact_mut(2) {
+            let mut index = self.cursor as usize;
+            if index >= frames {
+                if source.looping {
+                    self.cursor -= frames as f64;
+                    index = self.cursor as usize;
+                } else {
+                    self.cursor = 0.0;
+                    self.finished = true;
+                    return;
+                }
+            }
+
+            let next = if index + 1 < frames {
+                index + 1
+            } else if source.looping {
+                0
+            } else {
+                index
+            };
+            let t = self.cursor.fract() as f32;
+            let (l0, r0) = clip.frame(index);
+            let (l1, r1) = clip.frame(next);
+
+            frame[0] += (l0 + (l1 - l0) * t) * source.gains[0];
+            frame[1] += (r0 + (r1 - r0) * t) * source.gains[1];
+
+            self.cursor += source.step;
+        }
+    }
+}
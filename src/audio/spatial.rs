@@ -0,0 +1,146 @@
+use glam::Vec3;
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    importance,
+    physics::{self, Ray},
+    time::Time,
+    transform,
+};
+
+use super::AudioSource;
+
+pub const SPEED_OF_SOUND: f32 = 343.0;
+
+#[derive(Debug, Default)]
+pub struct AudioListener {
+    pub(crate) previous_position: Option<Vec3>,
+}
+
+impl Component for AudioListener {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Spatial {
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub doppler_scale: f32,
+    // gain and low-pass cutoff applied per collider between source and listener
+    pub occlusion_attenuation: f32,
+    pub occlusion_cutoff: f32,
+}
+
+impl Default for Spatial {
+    fn default() -> Self {
+        Self {
+            min_distance: 1.0,
+            max_distance: 50.0,
+            doppler_scale: 1.0,
+            occlusion_attenuation: 0.5,
+            occlusion_cutoff: 1_200.0,
+        }
+    }
+}
+
+// per-frame output of the spatial system, consumed by the mixer
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SpatialState {
+    pub(crate) gains: [f32; 2],
+    pub(crate) pitch: f32,
+    // low-pass cutoff, the filter itself runs on the output
+    pub(crate) occlusion: Option<f32>,
+    // colliders in the way when the rays were last cast
+    pub(crate) occluders: i32,
+    pub(crate) previous_position: Option<Vec3>,
+}
+
+impl Default for SpatialState {
+    fn default() -> Self {
+        Self {
+            gains: [1.0; 2],
+            pitch: 1.0,
+            occlusion: None,
+            occluders: 0,
+            previous_position: None,
+        }
+    }
+}
+
+fn velocity(previous: Option<Vec3>, current: Vec3, delta: f32) -> Vec3 {
+    match previous {
+        Some(previous) if delta > 0.0 => (current - previous) / delta,
+        _ => Vec3::ZERO,
+    }
+}
+
+pub fn update_spatial_audio(world: &mut World) {
+    let delta = world.get_resource::<Time>().map_or(0.0, Time::delta);
+
+    let Some((listener_entity, listener)) = world
+        .query::<AudioListener>()
+        .first()
+        .map(|(entity, listener)| (*entity, listener.previous_position))
+    else {
+        return;
+    };
+    let Some(listener_transform) = transform::world_transform(world, listener_entity) else {
+        return;
+    };
+    let listener_position = listener_transform.translation;
+    let listener_velocity = velocity(listener, listener_position, delta);
+    let right = listener_transform.rotation * Vec3::X;
+
+    let sources: Vec<(Entity, Spatial, Vec3)> = world
+        .query::<AudioSource>()
+        .into_iter()
+        .filter_map(|(entity, source)| {
+            let transform = transform::world_transform(world, entity)?;
+            Some((entity, source.spatial?, transform.translation))
+        })
+        .collect();
+
+    for (entity, spatial, position) in sources {
+        let (ray, distance) = Ray::between(listener_position, position);
+        // less important sources keep the last count for a few frames
+        let occluders = importance::is_due(world, entity).then(|| {
+            physics::raycast_all(world, &ray, distance)
+                .into_iter()
+                .filter(|hit| hit.entity != entity && hit.entity != listener_entity)
+                .count() as i32
+        });
+
+        let Some(source) = world.get_component_mut::<AudioSource>(entity) else {
+            continue;
+        };
+        let state = &mut source.spatial_state;
+        if let Some(occluders) = occluders {
+            state.occluders = occluders;
+        }
+        let occluders = state.occluders;
+
+        let range = (spatial.max_distance - spatial.min_distance).max(f32::EPSILON);
+        let falloff = 1.0 - ((distance - spatial.min_distance) / range).clamp(0.0, 1.0);
+        let attenuation = spatial.min_distance / distance.max(spatial.min_distance) * falloff;
+        let occlusion = spatial.occlusion_attenuation.powi(occluders);
+
+        // equal power panning against the listener's right axis
+        let pan = ray.direction.dot(right).clamp(-1.0, 1.0);
+        let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+        let gain = attenuation * occlusion;
+        state.gains = [angle.cos() * gain, angle.sin() * gain];
+
+        state.occlusion = (occluders > 0).then(|| spatial.occlusion_cutoff / occluders as f32);
+
+        // speeds are positive when source and listener move towards each other
+        let source_velocity = velocity(state.previous_position, position, delta);
+        let listener_speed = listener_velocity.dot(ray.direction) * spatial.doppler_scale;
+        let source_speed = -source_velocity.dot(ray.direction) * spatial.doppler_scale;
+        state.pitch = ((SPEED_OF_SOUND + listener_speed)
+            / (SPEED_OF_SOUND - source_speed).max(1.0))
+        .clamp(0.5, 2.0);
+        state.previous_position = Some(position);
+    }
+
+    if let Some(listener) = world.get_component_mut::<AudioListener>(listener_entity) {
+        listener.previous_position = Some(listener_position);
+    }
+}
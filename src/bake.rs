@@ -0,0 +1,145 @@
+// Ambient occlusion baked into a mesh's vertex colors, for stylized games
+// that want some contact shading without a screen space pass. Rays are cast
+// from every vertex over the hemisphere around its normal, against the mesh's
+// own triangles, and the share that hit something within `max_distance`
+// darkens the vertex's color. The standard material multiplies vertex colors
+// into the base color, so that's the occlusion applied. Vertices are spread
+// over the cpu's threads, a vertex shared by several faces is only cast once.
+
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+use crate::{bvh::TriangleBvh, mesh::Mesh};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AoBake {
+    // rays per vertex
+    pub samples: u32,
+    // in the mesh's units, hits further than this don't occlude
+    pub max_distance: f32,
+    // 0 leaves the colors alone, 1 is fully dark where every ray hits
+    pub strength: f32,
+}
+
+impl Default for AoBake {
+    fn default() -> Self {
+        Self {
+            samples: 64,
+            max_distance: 1.0,
+            strength: 1.0,
+        }
+    }
+}
+
+// hammersley points mapped onto a cosine weighted hemisphere around +z, so an
+// unweighted count of hits is the occlusion
+fn hemisphere(samples: u32) -> Vec<Vec3> {
+    (0..samples)
+        .map(|i| {
+            let u = (i as f32 + 0.5) / samples as f32;
+            let v = i.reverse_bits() as f32 / 2f32.powi(32);
+            let radius = u.sqrt();
+            let angle = std::f32::consts::TAU * v;
+            Vec3::new(radius * angle.cos(), radius * angle.sin(), (1.0 - u).sqrt())
+        })
+        .collect()
+}
+
+// 0 for open sky, 1 with every ray blocked
+fn occlusion(
+    bvh: &TriangleBvh,
+    directions: &[Vec3],
+    position: Vec3,
+    normal: Vec3,
+    settings: &AoBake,
+    bias: f32,
+) -> f32 {
+    let Some(normal) = normal.try_normalize() else {
+        return 0.0;
+    };
+    // turned a different way per vertex so neighbours don't band together
+    let twist = glam::Quat::from_axis_angle(
+        Vec3::Z,
+        (position.dot(Vec3::new(12.9898, 78.233, 37.719)).sin() * 43758.547).fract()
+            * std::f32::consts::TAU,
+    );
+    let basis = glam::Quat::from_rotation_arc(Vec3::Z, normal) * twist;
+    let origin = position + normal * bias;
+    let hits = directions
+        .iter()
+        .filter(|direction| bvh.occluded(origin, basis * **direction, settings.max_distance))
+        .count();
+    hits as f32 / directions.len().max(1) as f32
+}
+
+// the same position and normal, vertices split only by uvs or colors share a result
+fn vertex_key(mesh: &Mesh, index: usize) -> [u32; 6] {
+    let vertex = &mesh.vertices[index];
+    let [x, y, z, _] = vertex.position;
+    let [nx, ny, nz] = vertex.normal;
+    [x, y, z, nx, ny, nz].map(f32::to_bits)
+}
+
+pub fn bake_ambient_occlusion(mesh: &mut Mesh, settings: &AoBake) {
+    if mesh.vertices.is_empty() || settings.samples == 0 {
+        return;
+    }
+    let bvh = TriangleBvh::new(mesh.triangles());
+    let directions = hemisphere(settings.samples);
+    let (min, max) = mesh.aabb().unwrap_or_default();
+    // off the surface by a little of the mesh's size, so a vertex doesn't hit its own faces
+    let bias = (max - min).length().max(1e-3) * 1e-4;
+
+    let mut unique: HashMap<[u32; 6], usize> = HashMap::new();
+    let mut points = Vec::new();
+    let slots: Vec<usize> = (0..mesh.vertices.len())
+        .map(|index| {
+            *unique.entry(vertex_key(mesh, index)).or_insert_with(|| {
+                let vertex = &mesh.vertices[index];
+                points.push((
+                    Vec3::from_slice(&vertex.position[..3]),
+                    Vec3::from(vertex.normal),
+                ));
+                points.len() - 1
+            })
+        })
+        .collect();
+
+    let bake = |points: &[(Vec3, Vec3)]| -> Vec<f32> {
+        points
+            .iter()
+            .map(|(position, normal)| {
+                occlusion(&bvh, &directions, *position, *normal, settings, bias)
+            })
+            .collect()
+    };
+    // no threads to spread over on the web
+    #[cfg(target_arch = "wasm32")]
+    let occluded = bake(&points);
+    #[cfg(not(target_arch = "wasm32"))]
+    let occluded: Vec<f32> = {
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        let chunk = points.len().div_ceil(threads).max(1);
+        std::thread::scope(|scope| {
+            let bake = &bake;
+            let handles: Vec<_> = points
+                .chunks(chunk)
+                .map(|chunk| scope.spawn(move || bake(chunk)))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    };
+
+    // like the renderer does, short color lists are padded with white
+    mesh.colors.resize(mesh.vertices.len(), [1.0; 4]);
+    for (color, slot) in mesh.colors.iter_mut().zip(slots) {
+        let light = 1.0 - occluded[slot] * settings.strength.clamp(0.0, 1.0);
+        for channel in &mut color[..3] {
+            *channel *= light;
+        }
+    }
+}
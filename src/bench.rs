@@ -0,0 +1,297 @@
+// Benchmark scenes. Reproducible worlds of spinning cubes, orbiting lights and
+// particles, built without a window or gpu and stepped with a fixed delta so
+// every run does the same work. `BenchWorld::tick` and `BenchWorld::frame` are
+// meant to be the body of a criterion `b.iter`, `measure` times them without it.
+
+use std::fmt;
+
+use glam::{Quat, Vec3};
+use web_time::{Duration, Instant};
+
+use crate::{
+    assets::Assets,
+    color::Color,
+    ecs::{
+        component::Component,
+        schedule::{FixedUpdate, Last, Startup, Ui, Update},
+        world::World,
+    },
+    gizmos::Gizmos,
+    input::Input,
+    material::Material,
+    mesh::Mesh,
+    sprite::Light2d,
+    time::Time,
+    transform::Transform,
+};
+
+// seconds every step advances by
+pub const DELTA: f32 = 1.0 / 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchScene {
+    pub cubes: usize,
+    pub lights: usize,
+    pub particles: usize,
+    // the same seed always builds and simulates the same scene
+    pub seed: u64,
+}
+
+impl Default for BenchScene {
+    fn default() -> Self {
+        Self {
+            cubes: 1000,
+            lights: 16,
+            particles: 10_000,
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Spin {
+    pub axis: Vec3,
+    // radians per second
+    pub speed: f32,
+}
+
+impl Component for Spin {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Orbit {
+    pub center: Vec3,
+    pub radius: f32,
+    pub speed: f32,
+    pub angle: f32,
+}
+
+impl Component for Orbit {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub velocity: Vec3,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Component for Particle {}
+
+// xorshift, so respawned particles don't depend on anything outside the seed
+#[derive(Debug)]
+struct BenchRng(u64);
+
+impl Component for BenchRng {}
+
+impl BenchRng {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    fn direction(&mut self) -> Vec3 {
+        Vec3::new(
+            self.range(-1.0, 1.0),
+            self.range(-1.0, 1.0),
+            self.range(-1.0, 1.0),
+        )
+        .try_normalize()
+        .unwrap_or(Vec3::Y)
+    }
+}
+
+fn spin_cubes(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    let spins: Vec<_> = world
+        .query::<Spin>()
+        .into_iter()
+        .map(|(entity, spin)| (entity, *spin))
+        .collect();
+    for (entity, spin) in spins {
+        if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            transform.rotation = (Quat::from_axis_angle(spin.axis, spin.speed * delta)
+                * transform.rotation)
+                .normalize();
+        }
+    }
+}
+
+fn orbit_lights(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    let entities: Vec<_> = world
+        .query::<Orbit>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in entities {
+        let Some(orbit) = world.get_component_mut::<Orbit>(entity) else {
+            continue;
+        };
+        orbit.angle += orbit.speed * delta;
+        let orbit = *orbit;
+        if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            transform.translation =
+                orbit.center + Vec3::new(orbit.angle.cos(), 0.0, orbit.angle.sin()) * orbit.radius;
+        }
+    }
+}
+
+fn simulate_particles(world: &mut World) {
+    let delta = world.resource::<Time>().fixed_delta();
+    let entities: Vec<_> = world
+        .query::<Particle>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in entities {
+        let Some(particle) = world.get_component_mut::<Particle>(entity) else {
+            continue;
+        };
+        particle.age += delta;
+        particle.velocity.y -= 9.81 * delta;
+        let expired = particle.age >= particle.lifetime;
+        let velocity = particle.velocity;
+        if expired {
+            let rng = world.resource_mut::<BenchRng>();
+            let respawned = Particle {
+                velocity: rng.direction() * rng.range(2.0, 6.0) + Vec3::Y * 4.0,
+                age: 0.0,
+                lifetime: rng.range(1.0, 3.0),
+            };
+            world.add_component(entity, respawned);
+            world.add_component(entity, Transform::default());
+        } else if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            transform.translation += velocity * delta;
+        }
+    }
+}
+
+pub struct BenchWorld {
+    pub world: World,
+}
+
+impl BenchWorld {
+    // the engine's own world with the scene spawned into it
+    pub fn new(scene: BenchScene) -> Self {
+        let mut world = crate::create_world();
+        world.add_system_to(Update, spin_cubes);
+        world.add_system_to(Update, orbit_lights);
+        world.add_system_to(FixedUpdate, simulate_particles);
+        let mut rng = BenchRng(scene.seed.max(1));
+
+        // cubes are never drawn here, so a plane stands in for the mesh
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Mesh::plane(1.0, 0));
+        let material = world
+            .resource_mut::<Assets<Material>>()
+            .add(Material::default());
+        let side = (scene.cubes as f32).sqrt().ceil().max(1.0) as usize;
+        for index in 0..scene.cubes {
+            let position = Vec3::new((index % side) as f32, 0.0, (index / side) as f32) * 2.0;
+            let spin = Spin {
+                axis: rng.direction(),
+                speed: rng.range(0.5, 3.0),
+            };
+            world
+                .spawn()
+                .insert(Transform::from_translation(position))
+                .insert(mesh)
+                .insert(material)
+                .insert(spin);
+        }
+        for _ in 0..scene.lights {
+            let light = Light2d::point(Color::WHITE, rng.range(4.0, 16.0));
+            let orbit = Orbit {
+                center: Vec3::new(rng.range(0.0, 64.0), 4.0, rng.range(0.0, 64.0)),
+                radius: rng.range(2.0, 16.0),
+                speed: rng.range(-2.0, 2.0),
+                angle: rng.range(0.0, std::f32::consts::TAU),
+            };
+            world
+                .spawn()
+                .insert(Transform::default())
+                .insert(light)
+                .insert(orbit);
+        }
+        for _ in 0..scene.particles {
+            let particle = Particle {
+                velocity: rng.direction() * rng.range(2.0, 6.0),
+                // staggered so they don't all respawn on the same step
+                age: rng.range(0.0, 1.0),
+                lifetime: rng.range(1.0, 3.0),
+            };
+            world.spawn().insert(Transform::default()).insert(particle);
+        }
+        world.insert_resource(rng);
+        world.run(Startup);
+        Self { world }
+    }
+
+    // the gameplay schedules only, "update" and however many fixed steps are due
+    pub fn tick(&mut self) {
+        self.world.resource_mut::<Time>().advance(DELTA);
+        self.world.run(Update);
+        while self.world.resource_mut::<Time>().expend_fixed() {
+            self.world.run(FixedUpdate);
+        }
+    }
+
+    // every schedule an app frame runs, in its order, without rendering
+    pub fn frame(&mut self) {
+        self.world.resource_mut::<Gizmos>().clear();
+        self.tick();
+        self.world.run(Ui);
+        self.world.run(Last);
+        self.world.resource_mut::<Input>().clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchReport {
+    pub iterations: usize,
+    pub mean: Duration,
+    pub min: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} iterations, mean {:?}, min {:?}, median {:?}, p95 {:?}, max {:?}",
+            self.iterations, self.mean, self.min, self.median, self.p95, self.max
+        )
+    }
+}
+
+// runs `f` `warmup` times untimed, then times each of `iterations` runs
+pub fn measure(warmup: usize, iterations: usize, mut f: impl FnMut()) -> BenchReport {
+    for _ in 0..warmup {
+        f();
+    }
+    let mut samples: Vec<Duration> = (0..iterations.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    samples.sort();
+    let percentile = |p: f32| samples[((samples.len() - 1) as f32 * p).round() as usize];
+    BenchReport {
+        iterations: samples.len(),
+        mean: samples.iter().sum::<Duration>() / samples.len() as u32,
+        min: samples[0],
+        median: percentile(0.5),
+        p95: percentile(0.95),
+        max: samples[samples.len() - 1],
+    }
+}
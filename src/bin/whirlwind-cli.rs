@@ -0,0 +1,288 @@
+// The project tool, built with `--features cli`:
+//   whirlwind-cli new <name>             a project to start from, with an assets
+//                                        directory and a main.rs that draws a cube
+//   whirlwind-cli pack [dir] [out]       the assets directory into one archive,
+//                                        assets and assets.pak by default
+//   whirlwind-cli serve-web [--release] [--port N]
+//                                        builds the wasm target, runs wasm-bindgen
+//                                        into pkg/ and serves the project directory
+// serve-web needs the wasm32-unknown-unknown target and wasm-bindgen-cli
+// installed, it runs them the same way as by hand.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use whirlwind::assets::archive::AssetArchive;
+
+const USAGE: &str = "usage:
+    whirlwind-cli new <name>
+    whirlwind-cli pack [assets directory] [output]
+    whirlwind-cli serve-web [--release] [--port <port>]";
+
+const TEMPLATE_CARGO: &str = r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+anyhow = "1"
+whirlwind = { git = "https://github.com/BUGO07/whirlwind" }
+"#;
+
+const TEMPLATE_MAIN: &str = r#"use whirlwind::{
+    App,
+    assets::embedded::DefaultAssets,
+    ecs::{schedule::Startup, world::World},
+    transform::Transform,
+};
+
+fn setup(world: &mut World) {
+    // compiled into the engine, the project's own go in assets/
+    let defaults = *world.resource::<DefaultAssets>();
+    world
+        .spawn()
+        .insert(Transform::default())
+        .insert(defaults.cube)
+        .insert(defaults.cube_material);
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut app = App::new()?;
+    app.add_system_to(Startup, setup);
+    app.run()?;
+    Ok(())
+}
+"#;
+
+const TEMPLATE_INDEX: &str = r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="UTF-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+    <title>{name}</title>
+    <style>
+      * {
+        padding: 0;
+        margin: 0;
+      }
+      canvas {
+        background-color: black;
+        width: 100%;
+        height: 100%;
+      }
+    </style>
+  </head>
+
+  <body>
+    <canvas id="canvas"></canvas>
+    <script type="module">
+      import init from "./pkg/{crate}.js";
+      init();
+    </script>
+  </body>
+</html>
+"#;
+
+const TEMPLATE_GITIGNORE: &str = "target/\npkg/\n*.pak\n/pipeline_cache/\n";
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        println!("{USAGE}");
+        return Ok(());
+    };
+    match command.as_str() {
+        "new" => match args {
+            [name] => new_project(name),
+            _ => anyhow::bail!("new takes the project's name\n{USAGE}"),
+        },
+        "pack" => {
+            let directory = args.first().map_or("assets", String::as_str);
+            let output = args.get(1).map_or("assets.pak", String::as_str);
+            pack(Path::new(directory), Path::new(output))
+        }
+        "serve-web" => {
+            let mut release = false;
+            let mut port = 8080;
+            let mut args = args.iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--release" => release = true,
+                    "--port" => {
+                        port = args
+                            .next()
+                            .context("--port needs a number")?
+                            .parse()
+                            .context("--port needs a number")?;
+                    }
+                    other => anyhow::bail!("Unknown option {other}\n{USAGE}"),
+                }
+            }
+            serve_web(release, port)
+        }
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        other => anyhow::bail!("Unknown command {other}\n{USAGE}"),
+    }
+}
+
+fn new_project(name: &str) -> anyhow::Result<()> {
+    let root = Path::new(name);
+    anyhow::ensure!(!root.exists(), "{} already exists", root.display());
+    let package = root
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("The project needs a name")?;
+    let fill = |template: &str| {
+        template
+            .replace("{name}", package)
+            .replace("{crate}", &package.replace('-', "_"))
+    };
+
+    std::fs::create_dir_all(root.join("src"))?;
+    std::fs::create_dir_all(root.join("assets"))?;
+    std::fs::write(root.join("Cargo.toml"), fill(TEMPLATE_CARGO))?;
+    std::fs::write(root.join("src/main.rs"), TEMPLATE_MAIN)?;
+    std::fs::write(root.join("index.html"), fill(TEMPLATE_INDEX))?;
+    std::fs::write(root.join(".gitignore"), TEMPLATE_GITIGNORE)?;
+    // so the empty directory survives being committed
+    std::fs::write(root.join("assets/.gitkeep"), "")?;
+
+    println!(
+        "Created {}, `cargo run` in there to start it",
+        root.display()
+    );
+    Ok(())
+}
+
+fn pack(directory: &Path, output: &Path) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        directory.is_dir(),
+        "{} isn't a directory",
+        directory.display()
+    );
+    let data = AssetArchive::pack(directory)?;
+    let count = AssetArchive::from_bytes(data.clone())?.len();
+    std::fs::write(output, &data)?;
+    println!(
+        "Packed {} files from {} into {} ({} bytes)",
+        count,
+        directory.display(),
+        output.display(),
+        data.len()
+    );
+    Ok(())
+}
+
+// the [package] name, without pulling a toml parser in for one line
+fn package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package && let Some(value) = line.strip_prefix("name") {
+            let value = value.trim_start().strip_prefix('=')?.trim();
+            return Some(value.trim_matches('"').to_owned());
+        }
+    }
+    None
+}
+
+fn run(command: &mut Command) -> anyhow::Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("Unable to run {:?}", command.get_program()))?;
+    anyhow::ensure!(status.success(), "{:?} failed", command.get_program());
+    Ok(())
+}
+
+fn serve_web(release: bool, port: u16) -> anyhow::Result<()> {
+    let manifest = std::fs::read_to_string("Cargo.toml")
+        .context("serve-web runs from the project's directory, next to Cargo.toml")?;
+    let package = package_name(&manifest).context("Cargo.toml has no package name")?;
+    let crate_name = package.replace('-', "_");
+
+    // the library when there is one, as it's what holds the wasm start function
+    let mut cargo = Command::new("cargo");
+    cargo.args(["build", "--target", "wasm32-unknown-unknown"]);
+    if Path::new("src/lib.rs").exists() {
+        cargo.arg("--lib");
+    } else {
+        cargo.args(["--bin", &package]);
+    }
+    if release {
+        cargo.arg("--release");
+    }
+    run(&mut cargo)?;
+
+    let profile = if release { "release" } else { "debug" };
+    let target = std::env::var_os("CARGO_TARGET_DIR").map_or("target".into(), PathBuf::from);
+    let wasm = target
+        .join("wasm32-unknown-unknown")
+        .join(profile)
+        .join(format!("{crate_name}.wasm"));
+    run(Command::new("wasm-bindgen")
+        .args(["--target", "web", "--out-dir", "pkg"])
+        .arg(&wasm))?;
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Serving on http://127.0.0.1:{port}, ctrl+c to stop");
+    for stream in listener.incoming() {
+        if let Err(e) = stream.map_err(anyhow::Error::from).and_then(respond) {
+            eprintln!("{e}");
+        }
+    }
+    Ok(())
+}
+
+// one file per connection, only GET, enough for index.html and pkg/
+fn respond(mut stream: TcpStream) -> anyhow::Result<()> {
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    let path = path.split(['?', '#']).next().unwrap_or("/");
+    let path = match path.trim_start_matches('/') {
+        "" => "index.html",
+        path => path,
+    };
+
+    let file = Path::new(path);
+    let escapes = file
+        .components()
+        .any(|component| !matches!(component, std::path::Component::Normal(_)));
+    let (status, body) = if method != "GET" {
+        ("405 Method Not Allowed", Vec::new())
+    } else if escapes {
+        ("403 Forbidden", Vec::new())
+    } else {
+        match std::fs::read(file) {
+            Ok(body) => ("200 OK", body),
+            Err(_) => ("404 Not Found", Vec::new()),
+        }
+    };
+    let content_type = match file.extension().and_then(|e| e.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript",
+        Some("wasm") => "application/wasm",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("css") => "text/css",
+        _ => "application/octet-stream",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    Ok(())
+}
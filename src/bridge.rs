@@ -0,0 +1,187 @@
+// Messages between the game and the page embedding it. The page calls the
+// exported `sendToGame(kind, payload)` and the game reads them as BridgeMessage
+// events on the next frame. The game sends with `Bridge::send`, and the page
+// gets them in callbacks registered with `onGameMessage(kind, callback)`, "*"
+// for every kind. Payloads are anything JSON.stringify takes.
+// `Bridge::set_sender` hands them to something else instead, like a native
+// host's webview; natively they're dropped without one. Either way the outbox
+// is emptied every frame.
+
+use crate::ecs::{component::Component, world::World};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeMessage {
+    pub kind: String,
+    pub payload: serde_json::Value,
+}
+
+impl BridgeMessage {
+    pub fn is(&self, kind: &str) -> bool {
+        self.kind == kind
+    }
+}
+
+pub type BridgeSender = Box<dyn FnMut(&BridgeMessage)>;
+
+#[derive(Default)]
+pub struct Bridge {
+    outbox: Vec<BridgeMessage>,
+    sender: Option<BridgeSender>,
+}
+
+impl std::fmt::Debug for Bridge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Bridge")
+            .field("outbox", &self.outbox)
+            .field("sender", &self.sender.is_some())
+            .finish()
+    }
+}
+
+impl Component for Bridge {}
+
+impl Bridge {
+    // messages are given to this in the order they were sent, instead of the page
+    pub fn set_sender(&mut self, sender: impl FnMut(&BridgeMessage) + 'static) {
+        self.sender = Some(Box::new(sender));
+    }
+
+    // delivered to the page's callbacks at the end of the frame
+    pub fn send(&mut self, kind: impl Into<String>, payload: serde_json::Value) {
+        self.outbox.push(BridgeMessage {
+            kind: kind.into(),
+            payload,
+        });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::cell::RefCell;
+
+    use wasm_bindgen::prelude::*;
+
+    use super::BridgeMessage;
+
+    thread_local! {
+        pub(super) static INBOX: RefCell<Vec<BridgeMessage>> = const { RefCell::new(Vec::new()) };
+        pub(super) static LISTENERS: RefCell<Vec<(String, js_sys::Function)>> =
+            const { RefCell::new(Vec::new()) };
+    }
+
+    #[wasm_bindgen(js_name = sendToGame)]
+    pub fn send_to_game(kind: String, payload: JsValue) -> Result<(), JsValue> {
+        let payload = if payload.is_undefined() {
+            serde_json::Value::Null
+        } else {
+            let json = String::from(js_sys::JSON::stringify(&payload)?);
+            serde_json::from_str(&json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        };
+        INBOX.with_borrow_mut(|inbox| inbox.push(BridgeMessage { kind, payload }));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = onGameMessage)]
+    pub fn on_game_message(kind: String, callback: js_sys::Function) {
+        LISTENERS.with_borrow_mut(|listeners| listeners.push((kind, callback)));
+    }
+
+    // drops every callback for `kind`
+    #[wasm_bindgen(js_name = offGameMessage)]
+    pub fn off_game_message(kind: String) {
+        LISTENERS.with_borrow_mut(|listeners| listeners.retain(|(listened, _)| *listened != kind));
+    }
+
+    pub(super) fn deliver(message: &BridgeMessage) {
+        let payload = js_sys::JSON::parse(&message.payload.to_string()).unwrap_or(JsValue::NULL);
+        let kind = JsValue::from_str(&message.kind);
+        LISTENERS.with_borrow(|listeners| {
+            for (_, callback) in listeners
+                .iter()
+                .filter(|(listened, _)| listened == "*" || *listened == message.kind)
+            {
+                if let Err(e) = callback.call2(&JsValue::NULL, &kind, &payload) {
+                    log::error!("Game message callback for {} failed: {:?}", message.kind, e);
+                }
+            }
+        });
+    }
+}
+
+// called before the frame's systems, so they see what the page sent since the last one
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn receive_messages(world: &mut World) {
+    for message in web::INBOX.with_borrow_mut(std::mem::take) {
+        world.send_event(message);
+    }
+}
+
+pub(crate) fn send_messages(world: &mut World) {
+    let bridge = world.resource_mut::<Bridge>();
+    for message in bridge.outbox.drain(..) {
+        match &mut bridge.sender {
+            Some(sender) => sender(&message),
+            #[cfg(target_arch = "wasm32")]
+            None => web::deliver(&message),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{ecs::schedule::Last, test_utils::TestWorld};
+
+    #[test]
+    fn messages_are_sent_in_order_once() {
+        let mut test = TestWorld::empty()
+            .with_resource(Bridge::default())
+            .with_system(Last, send_messages);
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let bridge = test.world.resource_mut::<Bridge>();
+        bridge.set_sender({
+            let sent = sent.clone();
+            move |message| sent.borrow_mut().push(message.clone())
+        });
+        bridge.send("score", serde_json::json!({ "points": 10 }));
+        bridge.send("level", serde_json::Value::Null);
+        bridge.send("score", serde_json::json!({ "points": 20 }));
+        assert!(
+            sent.borrow().is_empty(),
+            "nothing goes out before the frame ends"
+        );
+
+        test.ticks(1);
+        let kinds: Vec<String> = sent.borrow().iter().map(|m| m.kind.clone()).collect();
+        assert_eq!(kinds, ["score", "level", "score"]);
+        assert!(sent.borrow()[0].is("score") && !sent.borrow()[0].is("level"));
+        assert_eq!(sent.borrow()[2].payload["points"], 20);
+        assert!(test.world.resource::<Bridge>().outbox.is_empty());
+
+        // nothing is sent twice
+        test.ticks(1);
+        assert_eq!(sent.borrow().len(), 3);
+        test.world
+            .resource_mut::<Bridge>()
+            .send("done", serde_json::Value::Null);
+        test.ticks(1);
+        assert_eq!(sent.borrow().last().map(|m| m.kind.as_str()), Some("done"));
+        assert_eq!(sent.borrow().len(), 4);
+    }
+
+    #[test]
+    fn without_a_sender_the_outbox_is_still_emptied() {
+        let mut test = TestWorld::empty()
+            .with_resource(Bridge::default())
+            .with_system(Last, send_messages);
+        test.world
+            .resource_mut::<Bridge>()
+            .send("score", serde_json::Value::Null);
+        test.ticks(1);
+        assert!(test.world.resource::<Bridge>().outbox.is_empty());
+    }
+}
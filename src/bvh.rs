@@ -0,0 +1,274 @@
+// A bounding volume hierarchy over a triangle soup, for casting many rays at
+// one mesh. Built once by splitting the triangles at the median of their
+// centers along the longest axis until a few are left per leaf.
+
+use glam::Vec3;
+
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    min: Vec3,
+    max: Vec3,
+    // a leaf's first triangle, or an inner node's second child (the first follows it)
+    start: u32,
+    // triangles in a leaf, 0 for inner nodes
+    count: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TriangleBvh {
+    triangles: Vec<[Vec3; 3]>,
+    nodes: Vec<Node>,
+}
+
+fn bounds(triangles: &[[Vec3; 3]]) -> (Vec3, Vec3) {
+    triangles.iter().flatten().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), point| (min.min(*point), max.max(*point)),
+    )
+}
+
+fn center(triangle: &[Vec3; 3]) -> Vec3 {
+    (triangle[0] + triangle[1] + triangle[2]) / 3.0
+}
+
+// distance along `direction` to the slab box, None when missed or past `max_distance`
+fn ray_box(origin: Vec3, inverse: Vec3, min: Vec3, max: Vec3, max_distance: f32) -> Option<f32> {
+    let a = (min - origin) * inverse;
+    let b = (max - origin) * inverse;
+    let near = a.min(b).max_element().max(0.0);
+    let far = a.max(b).min_element().min(max_distance);
+    (near <= far).then_some(near)
+}
+
+// möller-trumbore, both faces
+pub(crate) fn ray_triangle(origin: Vec3, direction: Vec3, triangle: &[Vec3; 3]) -> Option<f32> {
+    let edge1 = triangle[1] - triangle[0];
+    let edge2 = triangle[2] - triangle[0];
+    let p = direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let t = origin - triangle[0];
+    let u = t.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = t.cross(edge1);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(q) * inverse;
+    (distance > 0.0).then_some(distance)
+}
+
+impl TriangleBvh {
+    pub(crate) fn new(mut triangles: Vec<[Vec3; 3]>) -> Self {
+        let mut nodes = Vec::with_capacity(triangles.len() * 2 / LEAF_SIZE + 1);
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            build(&mut triangles, 0, len, &mut nodes);
+        }
+        Self { triangles, nodes }
+    }
+
+    // distance to the nearest hit within `max_distance`
+    pub(crate) fn cast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        let mut nearest: Option<f32> = None;
+        self.walk(origin, direction, max_distance, |distance| {
+            if nearest.is_none_or(|nearest| distance < nearest) {
+                nearest = Some(distance);
+            }
+            false
+        });
+        nearest
+    }
+
+    // the triangles whose bounds touch the box
+    pub(crate) fn overlapping(&self, min: Vec3, max: Vec3) -> Vec<[Vec3; 3]> {
+        let mut found = Vec::new();
+        let mut stack = vec![0usize];
+        while let Some(index) = stack.pop() {
+            let Some(node) = self.nodes.get(index) else {
+                continue;
+            };
+            if node.min.cmpgt(max).any() || node.max.cmplt(min).any() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.start as usize);
+                stack.push(index + 1);
+                continue;
+            }
+            let range = node.start as usize..(node.start + node.count) as usize;
+            found.extend(self.triangles[range].iter().filter(|triangle| {
+                let (low, high) = bounds(std::slice::from_ref(*triangle));
+                !(low.cmpgt(max).any() || high.cmplt(min).any())
+            }));
+        }
+        found
+    }
+
+    pub(crate) fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.nodes.first().map(|node| (node.min, node.max))
+    }
+
+    // whether anything is hit within `max_distance`, stops at the first
+    pub(crate) fn occluded(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> bool {
+        let mut hit = false;
+        self.walk(origin, direction, max_distance, |_| {
+            hit = true;
+            true
+        });
+        hit
+    }
+
+    // `visit` gets the distance to every triangle hit within it, returning true stops
+    fn walk(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        mut visit: impl FnMut(f32) -> bool,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let inverse = direction.recip();
+        let mut stack = vec![0usize];
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            if ray_box(origin, inverse, node.min, node.max, max_distance).is_none() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.start as usize);
+                stack.push(index + 1);
+                continue;
+            }
+            let range = node.start as usize..(node.start + node.count) as usize;
+            for triangle in range {
+                if let Some(distance) = ray_triangle(origin, direction, &self.triangles[triangle])
+                    && distance <= max_distance
+                    && visit(distance)
+                {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn build(triangles: &mut [[Vec3; 3]], offset: usize, len: usize, nodes: &mut Vec<Node>) {
+    let slice = &mut triangles[offset..offset + len];
+    let (min, max) = bounds(slice);
+    let index = nodes.len();
+    nodes.push(Node {
+        min,
+        max,
+        start: offset as u32,
+        count: len as u32,
+    });
+    if len <= LEAF_SIZE {
+        return;
+    }
+    let (center_min, center_max) = slice.iter().map(center).fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), point| (min.min(point), max.max(point)),
+    );
+    let extent = center_max - center_min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    };
+    let half = len / 2;
+    slice.select_nth_unstable_by(half, |a, b| center(a)[axis].total_cmp(&center(b)[axis]));
+
+    nodes[index].count = 0;
+    build(triangles, offset, half, nodes);
+    nodes[index].start = nodes.len() as u32;
+    build(triangles, offset + half, len - half, nodes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+
+    // spread over the sphere by the golden angle
+    fn directions(count: usize) -> impl Iterator<Item = Vec3> {
+        (0..count).map(move |i| {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+            let angle = i as f32 * 2.399_963;
+            let across = (1.0 - y * y).sqrt();
+            Vec3::new(across * angle.cos(), y, across * angle.sin())
+        })
+    }
+
+    fn nearest(triangles: &[[Vec3; 3]], origin: Vec3, direction: Vec3) -> Option<f32> {
+        triangles
+            .iter()
+            .filter_map(|triangle| ray_triangle(origin, direction, triangle))
+            .min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn casts_match_every_triangle_tested() {
+        let triangles = Mesh::sphere(1.0, 24, 12).triangles();
+        let bvh = TriangleBvh::new(triangles.clone());
+        assert!(bvh.nodes.len() > 1);
+        let (min, max) = bvh.bounds().unwrap();
+        assert!(min.abs_diff_eq(-Vec3::ONE, 1e-3) && max.abs_diff_eq(Vec3::ONE, 1e-3));
+
+        for (i, direction) in directions(200).enumerate() {
+            // from outside towards points near the middle, and out from the middle
+            let origin = -direction * 3.0 + Vec3::splat((i % 7) as f32 * 0.1);
+            let expected = nearest(&triangles, origin, direction);
+            assert_eq!(bvh.cast(origin, direction, f32::INFINITY), expected);
+            assert_eq!(bvh.occluded(origin, direction, 10.0), expected.is_some());
+            let out = bvh
+                .cast(Vec3::ZERO, direction * 2.0, f32::INFINITY)
+                .unwrap();
+            assert!((out - 0.5).abs() < 0.01);
+        }
+        // only hits within reach count
+        assert!(bvh.cast(Vec3::X * 3.0, Vec3::NEG_X, 1.5).is_none());
+        assert!(!bvh.occluded(Vec3::X * 3.0, Vec3::NEG_X, 1.5));
+        assert!(
+            TriangleBvh::new(Vec::new())
+                .cast(Vec3::ZERO, Vec3::X, 1.0)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn overlapping_finds_the_triangles_near_a_box() {
+        let triangles = Mesh::plane(10.0, 19).triangles();
+        let bvh = TriangleBvh::new(triangles.clone());
+        let (min, max) = (Vec3::new(-1.2, -1.0, 2.1), Vec3::new(0.4, 1.0, 2.9));
+        let mut found = bvh.overlapping(min, max);
+        let mut expected: Vec<[Vec3; 3]> = triangles
+            .into_iter()
+            .filter(|triangle| {
+                let (low, high) = bounds(std::slice::from_ref(triangle));
+                low.cmple(max).all() && high.cmpge(min).all()
+            })
+            .collect();
+        let key = |triangle: &[Vec3; 3]| triangle.map(|corner| corner.to_array().map(f32::to_bits));
+        found.sort_by_key(key);
+        expected.sort_by_key(key);
+        assert_eq!(found, expected);
+        assert!(!found.is_empty() && found.len() < 100);
+        assert!(
+            bvh.overlapping(Vec3::splat(6.0), Vec3::splat(7.0))
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4, Vec4Swizzles};
+
+#[cfg(feature = "render3d")]
+use crate::render::{
+    dof::DepthOfField, ssr::ScreenSpaceReflections, volumetric::VolumetricLighting,
+};
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    physics::Ray,
+    render::{Background, motion_blur::MotionBlur},
+    transform::GlobalTransform,
+    visibility::RenderLayers,
+    window::WindowSettings,
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    inverse_view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+    unjittered_view_proj: [[f32; 4]; 4],
+    previous_view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    // for views that aren't a Camera, like mirrors
+    pub(crate) fn from_view_proj(view_proj: Mat4, position: Vec3) -> Self {
+        Self {
+            view_proj: view_proj.to_cols_array_2d(),
+            inverse_view_proj: view_proj.inverse().to_cols_array_2d(),
+            position: position.extend(1.0).to_array(),
+            unjittered_view_proj: view_proj.to_cols_array_2d(),
+            previous_view_proj: view_proj.to_cols_array_2d(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub fov: f32,
+    pub aspect_ratio: f32,
+    // on camera entities these follow the Transform, move that instead
+    pub pos: Vec3,
+    pub rotation: Quat,
+    // only entities on one of these layers are drawn
+    pub render_layers: RenderLayers,
+    pub background: Background,
+    #[cfg(feature = "render3d")]
+    pub ssr: Option<ScreenSpaceReflections>,
+    #[cfg(feature = "render3d")]
+    pub depth_of_field: Option<DepthOfField>,
+    pub motion_blur: Option<MotionBlur>,
+    #[cfg(feature = "render3d")]
+    pub volumetric_lighting: Option<VolumetricLighting>,
+}
+
+impl Component for Camera {}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            fov: 60.0,
+            aspect_ratio: 1.0,
+            pos: glam::vec3(2.0, 1.0, 5.0),
+            rotation: Quat::IDENTITY,
+            render_layers: RenderLayers::default(),
+            background: Background::default(),
+            #[cfg(feature = "render3d")]
+            ssr: None,
+            #[cfg(feature = "render3d")]
+            depth_of_field: None,
+            motion_blur: None,
+            #[cfg(feature = "render3d")]
+            volumetric_lighting: None,
+        }
+    }
+}
+
+impl Camera {
+    pub fn projection(&self) -> Mat4 {
+        Mat4::perspective_rh(self.fov.to_radians(), self.aspect_ratio, 0.1, 1024.0)
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.pos).inverse()
+    }
+
+    pub fn view_proj(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(self.view_proj())
+    }
+
+    // physical window pixel position of a world point, like Viewport::contains
+    // takes. None behind the camera, points off to the side fall outside the viewport
+    pub fn world_to_viewport(&self, viewport: Viewport, point: Vec3) -> Option<Vec2> {
+        let clip = self.view_proj() * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.xy() / clip.w;
+        Some(viewport.position + Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * viewport.size)
+    }
+
+    // the ray from the near plane through a physical window pixel position, None
+    // for an empty viewport
+    pub fn viewport_to_world(&self, viewport: Viewport, position: Vec2) -> Option<Ray> {
+        if viewport.size.min_element() <= 0.0 {
+            return None;
+        }
+        let local = (position - viewport.position) / viewport.size;
+        let ndc = Vec2::new(local.x * 2.0 - 1.0, 1.0 - local.y * 2.0);
+        let inverse = self.view_proj().inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        Some(Ray::new(near, far - near))
+    }
+
+    // `jitter` shifts the image in ndc for temporal anti-aliasing, the
+    // previous view projection is for motion vectors and isn't jittered
+    pub(crate) fn to_uniform(&self, jitter: Vec2, previous_view_proj: Mat4) -> CameraUniform {
+        let view_proj = self.view_proj();
+        let jittered = Mat4::from_translation(jitter.extend(0.0)) * view_proj;
+        CameraUniform {
+            view_proj: jittered.to_cols_array_2d(),
+            inverse_view_proj: jittered.inverse().to_cols_array_2d(),
+            position: self.pos.extend(1.0).to_array(),
+            unjittered_view_proj: view_proj.to_cols_array_2d(),
+            previous_view_proj: previous_view_proj.to_cols_array_2d(),
+        }
+    }
+}
+
+// the part of the window a camera draws to, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    // top left corner
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl Viewport {
+    pub fn full(size: Vec2) -> Self {
+        Self {
+            position: Vec2::ZERO,
+            size,
+        }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        let local = point - self.position;
+        local.cmpge(Vec2::ZERO).all() && local.cmplt(self.size).all()
+    }
+}
+
+// marks the camera entity the scene is drawn from. without one the first
+// camera is used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MainCamera;
+
+impl Component for MainCamera {}
+
+pub fn main_camera_entity(world: &World) -> Option<Entity> {
+    let cameras = world.query::<Camera>();
+    cameras
+        .iter()
+        .find(|(entity, _)| world.get_component::<MainCamera>(*entity).is_some())
+        .or(cameras.first())
+        .map(|(entity, _)| *entity)
+}
+
+pub fn main_camera(world: &World) -> Option<&Camera> {
+    world.get_component::<Camera>(main_camera_entity(world)?)
+}
+
+pub fn main_camera_mut(world: &mut World) -> Option<&mut Camera> {
+    let entity = main_camera_entity(world)?;
+    world.get_component_mut::<Camera>(entity)
+}
+
+// physical window pixels of a world point as the main camera sees it, for
+// things like hud markers. None behind the camera. the main camera draws to
+// the whole window
+pub fn world_to_screen(world: &World, point: Vec3) -> Option<Vec2> {
+    let viewport = Viewport::full(world.get_resource::<WindowSettings>()?.size());
+    main_camera(world)?.world_to_viewport(viewport, point)
+}
+
+// the main camera's ray through a physical window pixel, like the cursor
+pub fn screen_to_world(world: &World, position: Vec2) -> Option<Ray> {
+    let viewport = Viewport::full(world.get_resource::<WindowSettings>()?.size());
+    main_camera(world)?.viewport_to_world(viewport, position)
+}
+
+// runs after transforms are propagated, so parented cameras follow their parent
+pub fn update_cameras(world: &mut World) {
+    for (_, camera, global) in world.query_pair_mut::<Camera, GlobalTransform>() {
+        let transform = global.compute_transform();
+        camera.pos = transform.translation;
+        camera.rotation = transform.rotation;
+    }
+}
+
+// planes point inwards, a point is inside when every plane's distance is positive
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    // expects a 0..1 depth range like the one glam builds for wgpu
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            let length = plane.xyz().length();
+            if length > 0.0 { plane / length } else { plane }
+        });
+        Self { planes }
+    }
+
+    pub fn intersects_aabb(&self, center: Vec3, half_extents: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            let radius = half_extents.dot(plane.xyz().abs());
+            plane.xyz().dot(center) + plane.w >= -radius
+        })
+    }
+
+    // world space bounds of a local aabb under `model`
+    pub fn intersects_transformed_aabb(&self, model: Mat4, min: Vec3, max: Vec3) -> bool {
+        let center = model.transform_point3((min + max) * 0.5);
+        let half = (max - min) * 0.5;
+        let axes = [model.x_axis.xyz(), model.y_axis.xyz(), model.z_axis.xyz()];
+        let half_extents = axes[0].abs() * half.x + axes[1].abs() * half.y + axes[2].abs() * half.z;
+        self.intersects_aabb(center, half_extents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewport_conversions_use_window_pixels() {
+        let camera = Camera::default();
+        let viewport = Viewport {
+            position: Vec2::new(100.0, 100.0),
+            size: Vec2::new(200.0, 200.0),
+        };
+        let target = camera.pos + camera.forward() * 5.0;
+        let pixel = camera.world_to_viewport(viewport, target).unwrap();
+        assert!(pixel.abs_diff_eq(Vec2::new(200.0, 200.0), 1e-3));
+        assert!(viewport.contains(pixel));
+
+        let ray = camera.viewport_to_world(viewport, pixel).unwrap();
+        assert!(
+            ray.direction
+                .normalize()
+                .abs_diff_eq(camera.forward(), 1e-4)
+        );
+        let corner = camera
+            .viewport_to_world(viewport, viewport.position)
+            .unwrap();
+        let back = camera
+            .world_to_viewport(viewport, corner.origin + corner.direction)
+            .unwrap();
+        assert!(back.abs_diff_eq(viewport.position, 1e-2));
+    }
+}
@@ -0,0 +1,128 @@
+// System clipboard. Native goes through arboard, the web through the browser's
+// async clipboard api. Reading is async on the web, so pasted text always
+// arrives as a Paste event, a frame or so after it's requested.
+
+use std::{cell::RefCell, rc::Rc};
+
+use winit::keyboard::KeyCode;
+
+use crate::{
+    ecs::{component::Component, world::World},
+    input::Input,
+};
+
+#[derive(Debug, Clone)]
+pub struct Paste(pub String);
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+type Backend = Option<arboard::Clipboard>;
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+type Backend = ();
+
+pub struct Clipboard {
+    backend: Backend,
+    // filled from the browser's promises, so it has to be shared
+    pending: Rc<RefCell<Vec<String>>>,
+}
+
+impl Component for Clipboard {}
+
+impl std::fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Clipboard")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+        let backend = arboard::Clipboard::new()
+            .inspect_err(|e| log::warn!("No clipboard available: {}", e))
+            .ok();
+        #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+        let backend = ();
+        Self {
+            backend,
+            pending: Rc::default(),
+        }
+    }
+}
+
+impl Clipboard {
+    // only native can read synchronously, use request_paste to support the web too
+    pub fn get_text(&mut self) -> Option<String> {
+        #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+        return self
+            .backend
+            .as_mut()?
+            .get_text()
+            .inspect_err(|e| log::debug!("Unable to read the clipboard: {}", e))
+            .ok();
+        #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+        None
+    }
+
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+        if let Some(backend) = &mut self.backend
+            && let Err(e) = backend.set_text(text)
+        {
+            log::error!("Unable to write to the clipboard: {}", e);
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(window) = wgpu::web_sys::window() {
+            let promise = window.navigator().clipboard().write_text(&text);
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = wasm_bindgen_futures::JsFuture::from(promise).await {
+                    log::error!("Unable to write to the clipboard: {:?}", e);
+                }
+            });
+        }
+        #[cfg(target_os = "android")]
+        let _ = text;
+    }
+
+    // sends a Paste event with the clipboard's text once it's been read
+    pub fn request_paste(&mut self) {
+        #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+        if let Some(text) = self.get_text() {
+            self.pending.borrow_mut().push(text);
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(window) = wgpu::web_sys::window() {
+            let promise = window.navigator().clipboard().read_text();
+            let pending = self.pending.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match wasm_bindgen_futures::JsFuture::from(promise).await {
+                    Ok(text) => pending.borrow_mut().extend(text.as_string()),
+                    Err(e) => log::debug!("Unable to read the clipboard: {:?}", e),
+                }
+            });
+        }
+    }
+}
+
+fn paste_shortcut(input: &Input) -> bool {
+    let modifier = [
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ]
+    .into_iter()
+    .any(|key| input.pressed(key));
+    modifier && input.just_pressed(KeyCode::KeyV)
+}
+
+pub fn update_clipboard(world: &mut World) {
+    if paste_shortcut(world.resource::<Input>()) {
+        world.resource_mut::<Clipboard>().request_paste();
+    }
+    let pasted = std::mem::take(&mut *world.resource::<Clipboard>().pending.borrow_mut());
+    for text in pasted {
+        world.send_event(Paste(text));
+    }
+}
@@ -0,0 +1,249 @@
+// Colors are stored sRGB encoded, the way they're authored, and converted to
+// linear when they're uploaded to the gpu. Alpha is always linear.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl Color {
+    pub const WHITE: Self = Self::srgb(1.0, 1.0, 1.0);
+    pub const BLACK: Self = Self::srgb(0.0, 0.0, 0.0);
+    pub const RED: Self = Self::srgb(1.0, 0.0, 0.0);
+    pub const GREEN: Self = Self::srgb(0.0, 1.0, 0.0);
+    pub const BLUE: Self = Self::srgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Self = Self::srgb(1.0, 1.0, 0.0);
+    pub const CYAN: Self = Self::srgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Self = Self::srgb(1.0, 0.0, 1.0);
+    pub const ORANGE: Self = Self::srgb(1.0, 0.5, 0.0);
+    pub const NONE: Self = Self::srgba(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::srgba(r, g, b, 1.0)
+    }
+
+    pub const fn srgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn srgb_u8(r: u8, g: u8, b: u8) -> Self {
+        Self::srgba_u8(r, g, b, 255)
+    }
+
+    pub const fn srgba_u8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::srgba(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    pub fn linear_rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::linear_rgba(r, g, b, 1.0)
+    }
+
+    pub fn linear_rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::srgba(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a)
+    }
+
+    // hue in degrees, saturation and lightness in 0..1
+    pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        Self::hsla(hue, saturation, lightness, 1.0)
+    }
+
+    pub fn hsla(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = lightness - chroma * 0.5;
+        Self::srgba(r + m, g + m, b + m, alpha)
+    }
+
+    // "#rgb", "#rgba", "#rrggbb" or "#rrggbbaa", the # is optional
+    pub fn hex(hex: &str) -> anyhow::Result<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let invalid = || anyhow::anyhow!("Invalid hex color '{}'", hex);
+        // from_str_radix alone would take a sign
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(digits.get(i * len..(i + 1) * len)?, 16).ok()?;
+            Some(if len == 1 { value * 17 } else { value })
+        };
+        let len = match digits.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return Err(invalid()),
+        };
+        let [r, g, b] = [0, 1, 2].map(|i| channel(i, len));
+        let a = if digits.len() / len == 4 {
+            channel(3, len)
+        } else {
+            Some(255)
+        };
+        match (r, g, b, a) {
+            (Some(r), Some(g), Some(b), Some(a)) => Ok(Self::srgba_u8(r, g, b, a)),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    // blends in linear space so midpoints don't come out muddy
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let [r0, g0, b0, a0] = self.to_linear();
+        let [r1, g1, b1, a1] = other.to_linear();
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self::linear_rgba(mix(r0, r1), mix(g0, g1), mix(b0, b1), mix(a0, a1))
+    }
+
+    pub fn to_srgba(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    pub fn to_linear(self) -> [f32; 4] {
+        [
+            srgb_to_linear(self.r),
+            srgb_to_linear(self.g),
+            srgb_to_linear(self.b),
+            self.a,
+        ]
+    }
+}
+
+impl From<Color> for wgpu::Color {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.to_linear().map(f64::from);
+        wgpu::Color { r, g, b, a }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(color: Color, expected: [f32; 4]) {
+        for (value, expected) in color.to_srgba().into_iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "{:?} isn't {:?}",
+                color,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn hex_digits() {
+        assert_eq!(Color::hex("#f00").unwrap(), Color::RED);
+        assert_eq!(Color::hex("0f0").unwrap(), Color::GREEN);
+        assert_eq!(
+            Color::hex("#336699").unwrap(),
+            Color::srgb_u8(0x33, 0x66, 0x99)
+        );
+        assert_eq!(
+            Color::hex("#33669980").unwrap(),
+            Color::srgba_u8(0x33, 0x66, 0x99, 0x80)
+        );
+        assert_eq!(Color::hex("#0000").unwrap(), Color::NONE);
+        assert_eq!(
+            Color::hex("#AbCdEf").unwrap(),
+            Color::hex("#abcdef").unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_hex() {
+        for hex in [
+            "",
+            "#",
+            "#ff",
+            "#fffff",
+            "#fffffffff",
+            "#ggg",
+            "#+f0000",
+            "#-f0",
+            "# fff",
+            "#ffé",
+        ] {
+            assert!(Color::hex(hex).is_err(), "{:?} was accepted", hex);
+        }
+    }
+
+    #[test]
+    fn hsl_primaries() {
+        assert_close(Color::hsl(0.0, 1.0, 0.5), [1.0, 0.0, 0.0, 1.0]);
+        assert_close(Color::hsl(120.0, 1.0, 0.5), [0.0, 1.0, 0.0, 1.0]);
+        assert_close(Color::hsl(240.0, 1.0, 0.5), [0.0, 0.0, 1.0, 1.0]);
+        // wraps back around to red
+        assert_close(Color::hsl(360.0, 1.0, 0.5), [1.0, 0.0, 0.0, 1.0]);
+        assert_close(Color::hsl(-120.0, 1.0, 0.5), [0.0, 0.0, 1.0, 1.0]);
+        // no saturation is gray whatever the hue
+        assert_close(Color::hsla(200.0, 0.0, 0.25, 0.5), [0.25, 0.25, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn srgb_and_linear() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert_eq!(srgb_to_linear(1.0), 1.0);
+        // where the curve changes from linear to the power function
+        assert!((srgb_to_linear(0.04045) - 0.04045 / 12.92).abs() < 1e-7);
+        assert!((srgb_to_linear(0.04046) - srgb_to_linear(0.04045)).abs() < 1e-5);
+        for value in [0.0, 0.04045, 0.5, 1.0] {
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-5);
+        }
+        assert_close(
+            Color::linear_rgba(0.0, 0.0031308, 1.0, 0.5),
+            [0.0, 0.0031308 * 12.92, 1.0, 0.5],
+        );
+    }
+
+    #[test]
+    fn lerp_endpoints() {
+        let from = Color::srgba(0.2, 0.4, 0.6, 0.0);
+        let to = Color::ORANGE;
+        assert_close(from.lerp(to, 0.0), from.to_srgba());
+        assert_close(from.lerp(to, 1.0), to.to_srgba());
+        // halfway in linear light is brighter than halfway in srgb
+        let middle = Color::BLACK.lerp(Color::WHITE, 0.5);
+        assert!(middle.r > 0.5);
+        assert_eq!(middle.a, 1.0);
+    }
+}
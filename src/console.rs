@@ -0,0 +1,814 @@
+// Developer console. Toggled with `Console::toggle_key`, typed lines run
+// registered commands against the world. Output goes to the log and is kept
+// in `Console::output`. With the ui feature the open console is a text panel
+// over the top of the window, the last `visible_lines` of output and then the
+// line being typed, scrolled back with page up/down. It's drawn in
+// `Console::font` or the first font loaded.
+
+use std::collections::VecDeque;
+
+use glam::{Vec2, Vec3};
+use wgpu::naga::FastHashMap;
+use winit::keyboard::KeyCode;
+
+#[cfg(feature = "ui")]
+use crate::{
+    assets::Handle,
+    color::Color,
+    text::{Font, Text, TextLayout, TextMaterial, TextShadow, TextSpan, TextStyle},
+};
+use crate::{
+    camera::{main_camera, main_camera_mut},
+    clipboard::Paste,
+    debug::{self, DebugToggle},
+    ecs::{component::Component, event::Events, world::World},
+    editor::Editor,
+    gizmos::TransformGizmo,
+    input::Input,
+    prefab::{Prefabs, spawn_prefab},
+    profiler::Profiler,
+    render::{
+        AntiAliasing, PresentMode, RenderSettings, SurfaceSettings, gpu::GpuFeatures,
+        resolution::RenderScale,
+    },
+    time::Time,
+    transform::Transform,
+    ui::UiScale,
+    window::WindowSettings,
+};
+
+pub type ConsoleCommand = Box<dyn Fn(&mut World, &[&str]) -> anyhow::Result<String>>;
+// called with `None` to read the value, `Some` to set it
+pub type ConsoleVar = Box<dyn Fn(&mut World, Option<&str>) -> anyhow::Result<String>>;
+
+const MAX_OUTPUT: usize = 256;
+
+struct Registered<T> {
+    help: String,
+    run: T,
+}
+
+pub struct Console {
+    pub open: bool,
+    pub toggle_key: KeyCode,
+    pub line: String,
+    // lines of output shown above the input line
+    pub visible_lines: usize,
+    #[cfg(feature = "ui")]
+    pub font: Option<Handle<Font>>,
+    #[cfg(feature = "ui")]
+    pub text_size: f32,
+    commands: FastHashMap<String, Registered<ConsoleCommand>>,
+    vars: FastHashMap<String, Registered<ConsoleVar>>,
+    history: Vec<String>,
+    history_cursor: Option<usize>,
+    output: VecDeque<String>,
+    // lines up from the newest output
+    scroll: usize,
+}
+
+impl std::fmt::Debug for Console {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Console")
+            .field("open", &self.open)
+            .field("line", &self.line)
+            .field("commands", &self.command_names())
+            .field("vars", &self.var_names())
+            .finish()
+    }
+}
+
+impl Component for Console {}
+
+impl Default for Console {
+    fn default() -> Self {
+        let mut console = Self {
+            open: false,
+            toggle_key: KeyCode::Backquote,
+            line: String::new(),
+            visible_lines: 12,
+            #[cfg(feature = "ui")]
+            font: None,
+            #[cfg(feature = "ui")]
+            text_size: 14.0,
+            commands: FastHashMap::default(),
+            vars: FastHashMap::default(),
+            history: Vec::new(),
+            history_cursor: None,
+            output: VecDeque::new(),
+            scroll: 0,
+        };
+        console.register_builtins();
+        console
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> anyhow::Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid value '{}'", value))
+}
+
+fn parse_present_mode(value: &str) -> anyhow::Result<PresentMode> {
+    Ok(match value {
+        "vsync" | "auto" => PresentMode::AutoVsync,
+        "novsync" => PresentMode::AutoNoVsync,
+        "fifo" => PresentMode::Fifo,
+        "fifo_relaxed" => PresentMode::FifoRelaxed,
+        "mailbox" => PresentMode::Mailbox,
+        "immediate" => PresentMode::Immediate,
+        _ => anyhow::bail!(
+            "Unknown present mode '{}', expected vsync, novsync, fifo, fifo_relaxed, mailbox or immediate",
+            value
+        ),
+    })
+}
+
+fn toggle(value: &mut bool, arg: Option<&str>) -> anyhow::Result<bool> {
+    *value = match arg {
+        None => !*value,
+        Some("on" | "1" | "true") => true,
+        Some("off" | "0" | "false") => false,
+        Some(arg) => anyhow::bail!("Expected on/off, got '{}'", arg),
+    };
+    Ok(*value)
+}
+
+impl Console {
+    pub fn register(
+        &mut self,
+        name: &str,
+        help: &str,
+        command: impl Fn(&mut World, &[&str]) -> anyhow::Result<String> + 'static,
+    ) {
+        self.commands.insert(
+            name.to_string(),
+            Registered {
+                help: help.to_string(),
+                run: Box::new(command),
+            },
+        );
+    }
+
+    // exposes a value to the `get` and `set` commands
+    pub fn register_var(
+        &mut self,
+        name: &str,
+        help: &str,
+        var: impl Fn(&mut World, Option<&str>) -> anyhow::Result<String> + 'static,
+    ) {
+        self.vars.insert(
+            name.to_string(),
+            Registered {
+                help: help.to_string(),
+                run: Box::new(var),
+            },
+        );
+    }
+
+    pub fn command_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.commands.keys().map(String::as_str).collect();
+        names.extend(["help", "clear", "get", "set"]);
+        names.sort();
+        names
+    }
+
+    pub fn var_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.vars.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    pub fn output(&self) -> impl Iterator<Item = &str> {
+        self.output.iter().map(String::as_str)
+    }
+
+    // the output that's shown, `visible_lines` of it ending where it's scrolled to
+    pub fn scrollback(&self) -> impl Iterator<Item = &str> {
+        let end = self.output.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(self.visible_lines);
+        self.output.range(start..end).map(String::as_str)
+    }
+
+    // positive goes back to older output
+    pub fn scroll(&mut self, lines: isize) {
+        let oldest = self.output.len().saturating_sub(self.visible_lines);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(oldest);
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        let line = line.into();
+        log::info!("{}", line);
+        self.output.push_back(line);
+        while self.output.len() > MAX_OUTPUT {
+            self.output.pop_front();
+        }
+    }
+
+    fn register_builtins(&mut self) {
+        self.register("spawn", "spawn <prefab> [x y z]", |world, args| {
+            let [name, rest @ ..] = args else {
+                let prefabs = world.resource::<Prefabs>().names().join(", ");
+                anyhow::bail!("Usage: spawn <prefab> [x y z], prefabs: {}", prefabs);
+            };
+            let translation = match rest {
+                [] => {
+                    let camera = main_camera(world)
+                        .ok_or_else(|| anyhow::anyhow!("No camera to spawn in front of"))?;
+                    camera.pos + camera.forward() * 5.0
+                }
+                [x, y, z] => Vec3::new(parse(x)?, parse(y)?, parse(z)?),
+                _ => anyhow::bail!("Expected three coordinates"),
+            };
+            let entity = spawn_prefab(world, name, Transform::from_translation(translation))
+                .ok_or(anyhow::anyhow!("Unknown prefab '{}'", name))?;
+            Ok(format!("Spawned {} as {:?}", name, entity))
+        });
+        self.register("editor", "editor [on|off]", |world, args| {
+            let enabled = toggle(
+                &mut world.resource_mut::<Editor>().enabled,
+                args.first().copied(),
+            )?;
+            world.resource_mut::<TransformGizmo>().enabled = enabled;
+            Ok(format!("Editor {}", if enabled { "on" } else { "off" }))
+        });
+        self.register("gizmos", "gizmos [on|off]", |world, args| {
+            let gizmo = world.resource_mut::<TransformGizmo>();
+            let enabled = toggle(&mut gizmo.enabled, args.first().copied())?;
+            Ok(format!("Gizmos {}", if enabled { "on" } else { "off" }))
+        });
+        self.register(
+            "debug",
+            "debug <wireframe|culling|colliders|bounds|paths|grid|slowmo>",
+            |world, args| {
+                let debug_toggle = match args.first().copied() {
+                    Some("wireframe") => DebugToggle::Wireframe,
+                    Some("culling") => DebugToggle::FreezeCulling,
+                    Some("colliders") => DebugToggle::Colliders,
+                    Some("bounds") => DebugToggle::Bounds,
+                    Some("paths") => DebugToggle::Paths,
+                    Some("grid") => DebugToggle::Grid,
+                    Some("slowmo") => DebugToggle::SlowMotion,
+                    _ => anyhow::bail!(
+                        "Usage: debug <wireframe|culling|colliders|bounds|paths|grid|slowmo>"
+                    ),
+                };
+                let enabled = debug::toggle(world, debug_toggle);
+                Ok(format!(
+                    "{:?} {}",
+                    debug_toggle,
+                    if enabled { "on" } else { "off" }
+                ))
+            },
+        );
+        self.register("pause", "pause [on|off]", |world, args| {
+            let time = world.resource_mut::<Time>();
+            let mut paused = time.is_paused();
+            toggle(&mut paused, args.first().copied())?;
+            if paused {
+                time.pause()
+            } else {
+                time.resume()
+            }
+            Ok(if paused { "Paused" } else { "Resumed" }.to_string())
+        });
+        self.register("profile", "profile [on|off]", |world, args| {
+            let profiler = world.resource_mut::<Profiler>();
+            let enabled = toggle(&mut profiler.enabled, args.first().copied())?;
+            Ok(format!("Profiler {}", if enabled { "on" } else { "off" }))
+        });
+        #[cfg(feature = "renderdoc")]
+        self.register(
+            "capture",
+            "a RenderDoc capture of the next frame",
+            |world, _| {
+                world
+                    .resource_mut::<crate::diagnostics::Diagnostics>()
+                    .capture_next_frame();
+                Ok("Capturing the next frame".to_string())
+            },
+        );
+        self.register(
+            "profile_export",
+            "profile_export [path], chrome trace json of the last frames",
+            |world, args| {
+                let path = args.first().copied().unwrap_or("trace.json");
+                let profiler = world.resource::<Profiler>();
+                let frames = profiler.frames().count();
+                if frames == 0 {
+                    anyhow::bail!("Nothing recorded, turn the profiler on with 'profile on'");
+                }
+                profiler.export_chrome_trace(path)?;
+                Ok(format!("Wrote {} frames to {}", frames, path))
+            },
+        );
+        self.register(
+            "gpu",
+            "the adapter and its optional features",
+            |world, _| {
+                let gpu = world
+                    .get_resource::<GpuFeatures>()
+                    .ok_or_else(|| anyhow::anyhow!("The renderer hasn't started"))?;
+                Ok(gpu.to_string())
+            },
+        );
+        self.register("entities", "prints every entity", |world, _| {
+            world.print_entities();
+            Ok(String::new())
+        });
+
+        self.register_var(
+            "camera.fov",
+            "vertical field of view in degrees",
+            |world, value| {
+                let camera = main_camera_mut(world).ok_or_else(|| anyhow::anyhow!("No camera"))?;
+                if let Some(value) = value {
+                    camera.fov = parse(value)?;
+                }
+                Ok(camera.fov.to_string())
+            },
+        );
+        self.register_var("time.scale", "virtual time multiplier", |world, value| {
+            let time = world.resource_mut::<Time>();
+            if let Some(value) = value {
+                time.set_scale(parse(value)?);
+            }
+            Ok(time.scale().to_string())
+        });
+        self.register_var("ui.scale", "ui size multiplier", |world, value| {
+            let scale = world.resource_mut::<UiScale>();
+            if let Some(value) = value {
+                scale.multiplier = parse::<f32>(value)?.max(0.1);
+            }
+            Ok(scale.multiplier.to_string())
+        });
+        self.register_var(
+            "render.present_mode",
+            "vsync, novsync, fifo, fifo_relaxed, mailbox or immediate",
+            |world, value| {
+                let settings = world.resource_mut::<SurfaceSettings>();
+                if let Some(value) = value {
+                    let present_mode = parse_present_mode(value)?;
+                    if !settings.is_supported(present_mode) {
+                        anyhow::bail!("{:?} isn't supported by this surface", present_mode);
+                    }
+                    settings.present_mode = present_mode;
+                }
+                Ok(format!("{:?}", settings.present_mode))
+            },
+        );
+        self.register_var(
+            "render.frame_latency",
+            "frames queued ahead of the gpu",
+            |world, value| {
+                let settings = world.resource_mut::<SurfaceSettings>();
+                if let Some(value) = value {
+                    settings.frame_latency = parse::<u32>(value)?.max(1);
+                }
+                Ok(settings.frame_latency.to_string())
+            },
+        );
+        self.register_var("render.aa", "none or taa", |world, value| {
+            let settings = world.resource_mut::<RenderSettings>();
+            if let Some(value) = value {
+                settings.anti_aliasing = match value {
+                    "none" | "off" => AntiAliasing::None,
+                    "taa" => AntiAliasing::Taa,
+                    _ => anyhow::bail!("Unknown anti-aliasing '{}', expected none or taa", value),
+                };
+            }
+            Ok(format!("{:?}", settings.anti_aliasing))
+        });
+        self.register_var(
+            "render.scale",
+            "share of the window the scene is drawn at, or auto",
+            |world, value| {
+                let settings = world.resource_mut::<RenderScale>();
+                match value {
+                    Some("auto") => settings.dynamic = true,
+                    Some(value) => {
+                        settings.scale = parse::<f32>(value)?.clamp(0.1, 1.0);
+                        settings.dynamic = false;
+                    }
+                    None => {}
+                }
+                Ok(if settings.dynamic {
+                    format!("auto ({:.2})", settings.current())
+                } else {
+                    settings.scale.to_string()
+                })
+            },
+        );
+        #[cfg(feature = "audio")]
+        self.register_var("audio.volume", "master volume", |world, value| {
+            let mixer = world.resource_mut::<crate::audio::Mixer>();
+            if let Some(value) = value {
+                mixer.set_volume(crate::audio::MASTER_BUS, parse(value)?);
+            }
+            Ok(mixer.master.volume.to_string())
+        });
+        self.register_var(
+            "editor.spawn_distance",
+            "distance new prefabs are placed from the camera",
+            |world, value| {
+                let editor = world.resource_mut::<Editor>();
+                if let Some(value) = value {
+                    editor.spawn_distance = parse(value)?;
+                }
+                Ok(editor.spawn_distance.to_string())
+            },
+        );
+    }
+
+    pub fn run(&mut self, world: &mut World, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if self.history.last().is_none_or(|last| last != line) {
+            self.history.push(line.to_string());
+        }
+        self.history_cursor = None;
+        self.scroll = 0;
+        self.print(format!("> {}", line));
+
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let result = match args.as_slice() {
+            ["help"] => {
+                let mut help: Vec<String> = self
+                    .commands
+                    .iter()
+                    .map(|(name, command)| format!("{} - {}", name, command.help))
+                    .chain(
+                        self.vars
+                            .iter()
+                            .map(|(name, var)| format!("set {} <value> - {}", name, var.help)),
+                    )
+                    .collect();
+                help.sort();
+                Ok(help.join("\n"))
+            }
+            ["clear"] => {
+                self.output.clear();
+                Ok(String::new())
+            }
+            ["get", name] | ["set", name, ..] => match self.vars.get(*name) {
+                Some(var) => {
+                    let value = args.get(2..).map(|value| value.join(" "));
+                    (var.run)(world, value.as_deref().filter(|value| !value.is_empty()))
+                        .map(|value| format!("{} = {}", name, value))
+                }
+                None => Err(anyhow::anyhow!("Unknown variable '{}'", name)),
+            },
+            [name, args @ ..] => match self.commands.get(*name) {
+                Some(command) => (command.run)(world, args),
+                None => Err(anyhow::anyhow!("Unknown command '{}'", name)),
+            },
+            [] => Ok(String::new()),
+        };
+
+        match result {
+            Ok(output) => {
+                for line in output.lines() {
+                    self.print(line);
+                }
+            }
+            Err(e) => self.print(format!("Error: {}", e)),
+        }
+    }
+
+    // completes the command, or the variable name after get/set
+    pub fn complete(&mut self) {
+        let (head, prefix) = match self.line.rsplit_once(' ') {
+            Some((head, prefix)) if matches!(head.trim(), "get" | "set") => {
+                (format!("{} ", head.trim()), prefix.to_string())
+            }
+            Some(_) => return,
+            None => (String::new(), self.line.clone()),
+        };
+        let candidates: Vec<String> = if head.is_empty() {
+            self.command_names()
+        } else {
+            self.var_names()
+        }
+        .into_iter()
+        .filter(|name| name.starts_with(&prefix))
+        .map(String::from)
+        .collect();
+
+        let Some(first) = candidates.first() else {
+            return;
+        };
+        let common = candidates.iter().fold(first.clone(), |common, name| {
+            common
+                .chars()
+                .zip(name.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        });
+        self.line = format!("{}{}", head, common);
+        if candidates.len() == 1 {
+            self.line.push(' ');
+        } else {
+            self.print(candidates.join("  "));
+        }
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        let cursor = match (self.history_cursor, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (Some(cursor), true) => Some(cursor.saturating_sub(1)),
+            (Some(cursor), false) if cursor + 1 < self.history.len() => Some(cursor + 1),
+            _ => None,
+        };
+        self.history_cursor = cursor;
+        self.line = cursor
+            .map(|cursor| self.history[cursor].clone())
+            .unwrap_or_default();
+    }
+}
+
+// other keyboard shortcuts should stay quiet while this is true
+pub fn is_typing(world: &World) -> bool {
+    world
+        .get_resource::<Console>()
+        .is_some_and(|console| console.open)
+}
+
+pub fn execute(world: &mut World, line: &str) {
+    let _ = world.try_resource_scope(|world, console: &mut Console| console.run(world, line));
+}
+
+pub fn update_console(world: &mut World) {
+    let Some(mut console) = world.remove_resource::<Console>() else {
+        return;
+    };
+    let input = world.resource::<Input>();
+
+    if input.just_pressed(console.toggle_key) {
+        console.open = !console.open;
+        console.line.clear();
+    } else if console.open {
+        console.line.push_str(input.text());
+        if let Some(pastes) = world.get_resource::<Events<Paste>>() {
+            for Paste(text) in pastes.iter() {
+                console
+                    .line
+                    .extend(text.chars().map(|c| if c.is_control() { ' ' } else { c }));
+            }
+        }
+        let submit = input.just_pressed(KeyCode::Enter) || input.just_pressed(KeyCode::NumpadEnter);
+        if input.just_pressed(KeyCode::Backspace) {
+            console.line.pop();
+        }
+        if input.just_pressed(KeyCode::Tab) {
+            console.complete();
+        }
+        if input.just_pressed(KeyCode::ArrowUp) {
+            console.browse_history(true);
+        }
+        if input.just_pressed(KeyCode::ArrowDown) {
+            console.browse_history(false);
+        }
+        let page = (console.visible_lines / 2).max(1) as isize;
+        if input.just_pressed(KeyCode::PageUp) {
+            console.scroll(page);
+        }
+        if input.just_pressed(KeyCode::PageDown) {
+            console.scroll(-page);
+        }
+        if input.just_pressed(KeyCode::Escape) {
+            console.open = false;
+        }
+        if submit {
+            let line = std::mem::take(&mut console.line);
+            console.run(world, &line);
+        }
+    }
+
+    #[cfg(feature = "ui")]
+    let height = draw_console(world, &console);
+    #[cfg(not(feature = "ui"))]
+    let height = 32.0;
+
+    // the console drops down from the top, keep the candidate window under it
+    let scale = world.resource::<UiScale>().factor();
+    if let Some(window) = world.get_resource_mut::<WindowSettings>() {
+        window.set_ime_allowed(console.open);
+        let width = window.size().x;
+        window.set_ime_area(Vec2::ZERO, Vec2::new(width, height * scale));
+    }
+
+    world.insert_resource(console);
+}
+
+// on the console's text entity
+#[cfg(feature = "ui")]
+#[derive(Debug)]
+pub struct ConsoleText;
+
+#[cfg(feature = "ui")]
+impl Component for ConsoleText {}
+
+// the panel's text, scrollback then the input line with a caret
+#[cfg(feature = "ui")]
+fn console_text(console: &Console, font: Handle<Font>) -> Text {
+    let style = TextStyle {
+        size: console.text_size,
+        ..TextStyle::default()
+    };
+    let mut spans: Vec<TextSpan> = console
+        .scrollback()
+        .map(|line| {
+            let color = if line.starts_with("Error:") {
+                Color::srgb(1.0, 0.35, 0.3)
+            } else if line.starts_with("> ") {
+                Color::srgb(0.6, 0.6, 0.6)
+            } else {
+                Color::WHITE
+            };
+            TextSpan::new(format!("{}\n", line), TextStyle { color, ..style })
+        })
+        .collect();
+    spans.push(TextSpan::new(
+        format!("> {}_", console.line),
+        TextStyle {
+            color: Color::YELLOW,
+            ..style
+        },
+    ));
+    Text {
+        spans,
+        material: TextMaterial {
+            shadow: Some(TextShadow {
+                offset: Vec2::splat(1.0),
+                color: Color::BLACK,
+                softness: 0.0,
+            }),
+            ..TextMaterial::default()
+        },
+        ..Text::new(font, "", style)
+    }
+}
+
+// how tall the panel is in logical pixels as of its last layout, a line's
+// worth before there is one
+#[cfg(feature = "ui")]
+fn draw_console(world: &mut World, console: &Console) -> f32 {
+    const MARGIN: f32 = 8.0;
+    let existing = world
+        .query::<ConsoleText>()
+        .first()
+        .map(|(entity, _)| *entity);
+    let font = crate::text::font_or_first(world, console.font);
+    let Some(font) = font.filter(|_| console.open) else {
+        if let Some(entity) = existing {
+            world.despawn(entity);
+        }
+        return 32.0;
+    };
+
+    let entity = match existing {
+        Some(entity) => entity,
+        None => world.spawn().insert(ConsoleText).id(),
+    };
+    let window = world
+        .resource::<UiScale>()
+        .to_logical(world.resource::<WindowSettings>().size());
+    let mut text = console_text(console, font);
+    text.position = Vec2::splat(MARGIN);
+    text.max_width = Some((window.x - MARGIN * 2.0).max(0.0));
+    if world.get_component::<Text>(entity) != Some(&text) {
+        world.add_component(entity, text);
+    }
+    world
+        .get_component::<TextLayout>(entity)
+        .map_or(32.0, |layout| layout.size.y + MARGIN * 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console() -> Console {
+        let mut console = Console::default();
+        console.register("add", "add <a> <b>", |_, args| {
+            let [a, b] = args else {
+                anyhow::bail!("Usage: add <a> <b>");
+            };
+            Ok((parse::<i32>(a)? + parse::<i32>(b)?).to_string())
+        });
+        console
+    }
+
+    fn last_output(console: &Console) -> &str {
+        console.output().last().unwrap_or_default()
+    }
+
+    #[test]
+    fn run_reports_errors() {
+        let mut world = World::new();
+        let mut console = console();
+        console.run(&mut world, "  add 2 3 ");
+        assert_eq!(console.output().collect::<Vec<_>>(), ["> add 2 3", "5"]);
+
+        console.run(&mut world, "nope");
+        assert_eq!(last_output(&console), "Error: Unknown command 'nope'");
+        console.run(&mut world, "add 2");
+        assert_eq!(last_output(&console), "Error: Usage: add <a> <b>");
+        console.run(&mut world, "add 2 x");
+        assert_eq!(last_output(&console), "Error: Invalid value 'x'");
+        console.run(&mut world, "spawn crate 1 2");
+        assert_eq!(last_output(&console), "Error: Expected three coordinates");
+        console.run(&mut world, "get nope");
+        assert_eq!(last_output(&console), "Error: Unknown variable 'nope'");
+
+        world.insert_resource(Editor::default());
+        console.run(&mut world, "editor maybe");
+        assert_eq!(last_output(&console), "Error: Expected on/off, got 'maybe'");
+
+        console.run(&mut world, "clear");
+        assert_eq!(console.output().count(), 0);
+        // blank lines aren't run or kept
+        console.run(&mut world, "   ");
+        assert_eq!(console.output().count(), 0);
+    }
+
+    #[test]
+    fn complete_commands_and_vars() {
+        let mut console = console();
+        console.line = "ad".to_string();
+        console.complete();
+        assert_eq!(console.line, "add ");
+
+        // only as far as the candidates agree, and they're listed
+        console.line = "pro".to_string();
+        console.complete();
+        assert_eq!(console.line, "profile");
+        assert_eq!(last_output(&console), "profile  profile_export");
+
+        console.line = "set render.f".to_string();
+        console.complete();
+        assert_eq!(console.line, "set render.frame_latency ");
+
+        // nothing to complete after a command's arguments or without a match
+        console.line = "add 1".to_string();
+        console.complete();
+        assert_eq!(console.line, "add 1");
+        console.line = "zzz".to_string();
+        console.complete();
+        assert_eq!(console.line, "zzz");
+    }
+
+    #[test]
+    fn browse_history_both_ways() {
+        let mut world = World::new();
+        let mut console = console();
+        console.browse_history(true);
+        assert_eq!(console.line, "");
+
+        for line in ["add 1 1", "add 2 2", "add 2 2", "nope"] {
+            console.run(&mut world, line);
+        }
+        // repeats are kept once
+        console.browse_history(true);
+        assert_eq!(console.line, "nope");
+        console.browse_history(true);
+        assert_eq!(console.line, "add 2 2");
+        console.browse_history(true);
+        console.browse_history(true);
+        assert_eq!(console.line, "add 1 1");
+        console.browse_history(false);
+        assert_eq!(console.line, "add 2 2");
+        console.browse_history(false);
+        // past the newest is back to an empty line
+        console.browse_history(false);
+        assert_eq!(console.line, "");
+    }
+
+    #[test]
+    fn scrollback_pages_through_output() {
+        let mut world = World::new();
+        let mut console = console();
+        console.visible_lines = 3;
+        for i in 0..5 {
+            console.print(i.to_string());
+        }
+        assert_eq!(console.scrollback().collect::<Vec<_>>(), ["2", "3", "4"]);
+        console.scroll(1);
+        assert_eq!(console.scrollback().collect::<Vec<_>>(), ["1", "2", "3"]);
+        // no further back than the oldest line
+        console.scroll(10);
+        assert_eq!(console.scrollback().collect::<Vec<_>>(), ["0", "1", "2"]);
+        console.scroll(-10);
+        assert_eq!(console.scrollback().collect::<Vec<_>>(), ["2", "3", "4"]);
+
+        console.scroll(2);
+        console.run(&mut world, "add 1 1");
+        assert_eq!(
+            console.scrollback().collect::<Vec<_>>(),
+            ["4", "> add 1 1", "2"]
+        );
+    }
+}
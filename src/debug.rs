@@ -0,0 +1,332 @@
+// Built-in debug views flipped with the F keys. Each toggle is a flag on
+// `DebugSettings` that the renderer and `draw_debug` read every frame.
+
+use glam::{Mat4, Vec3};
+use winit::keyboard::KeyCode;
+
+use crate::{
+    assets::{Assets, Handle},
+    camera::{Camera, main_camera},
+    color::Color,
+    ecs::{component::Component, world::World},
+    gizmos::{Gizmos, grid::Grid},
+    input::Input,
+    mesh::Mesh,
+    physics::collider::Collider,
+    spline::Path,
+    time::Time,
+    transform::{self, Transform},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugToggle {
+    Wireframe,
+    FreezeCulling,
+    Colliders,
+    Bounds,
+    Paths,
+    Grid,
+    SlowMotion,
+}
+
+#[derive(Debug)]
+pub struct DebugSettings {
+    // needs POLYGON_MODE_LINE, ignored when the adapter doesn't have it
+    pub wireframe: bool,
+    pub freeze_culling: bool,
+    pub show_colliders: bool,
+    pub show_bounds: bool,
+    pub show_paths: bool,
+    // the reference grid and origin axes
+    pub show_grid: bool,
+    pub grid: Grid,
+    pub slow_motion: bool,
+    pub slow_motion_scale: f32,
+    pub(crate) frozen_view_proj: Option<Mat4>,
+}
+
+impl Component for DebugSettings {}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        Self {
+            wireframe: false,
+            freeze_culling: false,
+            show_colliders: false,
+            show_bounds: false,
+            show_paths: false,
+            show_grid: false,
+            grid: Grid::default(),
+            slow_motion: false,
+            slow_motion_scale: 0.25,
+            frozen_view_proj: None,
+        }
+    }
+}
+
+impl DebugSettings {
+    pub fn get(&self, toggle: DebugToggle) -> bool {
+        match toggle {
+            DebugToggle::Wireframe => self.wireframe,
+            DebugToggle::FreezeCulling => self.freeze_culling,
+            DebugToggle::Colliders => self.show_colliders,
+            DebugToggle::Bounds => self.show_bounds,
+            DebugToggle::Paths => self.show_paths,
+            DebugToggle::Grid => self.show_grid,
+            DebugToggle::SlowMotion => self.slow_motion,
+        }
+    }
+
+    pub fn set(&mut self, toggle: DebugToggle, value: bool) {
+        *match toggle {
+            DebugToggle::Wireframe => &mut self.wireframe,
+            DebugToggle::FreezeCulling => &mut self.freeze_culling,
+            DebugToggle::Colliders => &mut self.show_colliders,
+            DebugToggle::Bounds => &mut self.show_bounds,
+            DebugToggle::Paths => &mut self.show_paths,
+            DebugToggle::Grid => &mut self.show_grid,
+            DebugToggle::SlowMotion => &mut self.slow_motion,
+        } = value;
+    }
+}
+
+#[derive(Debug)]
+pub struct DebugKeys {
+    pub bindings: Vec<(KeyCode, DebugToggle)>,
+}
+
+impl Component for DebugKeys {}
+
+impl Default for DebugKeys {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (KeyCode::F2, DebugToggle::Wireframe),
+                (KeyCode::F3, DebugToggle::Colliders),
+                (KeyCode::F4, DebugToggle::Bounds),
+                (KeyCode::F5, DebugToggle::FreezeCulling),
+                (KeyCode::F6, DebugToggle::SlowMotion),
+                (KeyCode::F7, DebugToggle::Paths),
+                (KeyCode::F8, DebugToggle::Grid),
+            ],
+        }
+    }
+}
+
+impl DebugKeys {
+    pub fn bind(&mut self, key: KeyCode, toggle: DebugToggle) {
+        self.bindings.retain(|(_, bound)| *bound != toggle);
+        self.bindings.push((key, toggle));
+    }
+
+    pub fn unbind(&mut self, toggle: DebugToggle) {
+        self.bindings.retain(|(_, bound)| *bound != toggle);
+    }
+}
+
+pub fn toggle(world: &mut World, toggle: DebugToggle) -> bool {
+    let settings = world.resource_mut::<DebugSettings>();
+    let value = !settings.get(toggle);
+    settings.set(toggle, value);
+    value
+}
+
+pub fn update_debug_toggles(world: &mut World) {
+    if crate::console::is_typing(world) {
+        return;
+    }
+    let (Some(input), Some(keys)) = (
+        world.get_resource::<Input>(),
+        world.get_resource::<DebugKeys>(),
+    ) else {
+        return;
+    };
+    let toggled: Vec<DebugToggle> = keys
+        .bindings
+        .iter()
+        .filter(|(key, _)| input.just_pressed(*key))
+        .map(|(_, toggle)| *toggle)
+        .collect();
+    for debug_toggle in toggled {
+        let enabled = toggle(world, debug_toggle);
+        log::info!("{:?} {}", debug_toggle, if enabled { "on" } else { "off" });
+    }
+}
+
+pub fn draw_debug(world: &mut World) {
+    let Some(settings) = world.get_resource::<DebugSettings>() else {
+        return;
+    };
+    let (show_colliders, show_bounds, show_paths, show_grid) = (
+        settings.show_colliders,
+        settings.show_bounds,
+        settings.show_paths,
+        settings.show_grid,
+    );
+    let time_scale = if settings.slow_motion {
+        settings.slow_motion_scale
+    } else {
+        1.0
+    };
+    let freeze = settings.freeze_culling;
+    let view_proj = main_camera(world).map_or(glam::Mat4::IDENTITY, Camera::view_proj);
+
+    world.resource_mut::<Time>().debug_scale = time_scale;
+    let settings = world.resource_mut::<DebugSettings>();
+    let frozen = match (freeze, settings.frozen_view_proj) {
+        (true, None) => *settings.frozen_view_proj.insert(view_proj),
+        (true, Some(frozen)) => frozen,
+        (false, _) => {
+            settings.frozen_view_proj = None;
+            view_proj
+        }
+    };
+
+    let mut lines = Vec::new();
+    if freeze {
+        let inverse = frozen.inverse();
+        let corner = |x: f32, y: f32, z: f32| inverse.project_point3(Vec3::new(x, y, z));
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            lines.push((corner(x, y, 0.0), corner(x, y, 1.0), Color::YELLOW));
+        }
+        for z in [0.0, 1.0] {
+            for ((x0, y0), (x1, y1)) in [
+                ((-1.0, -1.0), (1.0, -1.0)),
+                ((1.0, -1.0), (1.0, 1.0)),
+                ((1.0, 1.0), (-1.0, 1.0)),
+                ((-1.0, 1.0), (-1.0, -1.0)),
+            ] {
+                lines.push((corner(x0, y0, z), corner(x1, y1, z), Color::YELLOW));
+            }
+        }
+    }
+
+    let mut gizmos = world.remove_resource::<Gizmos>().unwrap_or_default();
+    for (start, end, color) in lines {
+        gizmos.line(start, end, color);
+    }
+
+    if show_colliders {
+        for (entity, collider) in world.query::<Collider>() {
+            let transform = transform::world_transform(world, entity).unwrap_or_default();
+            draw_collider(
+                &mut gizmos,
+                &transform,
+                collider,
+                Color::srgb(0.2, 1.0, 0.2),
+            );
+        }
+    }
+
+    if show_bounds && let Some(meshes) = world.get_resource::<Assets<Mesh>>() {
+        for (entity, handle) in world.query::<Handle<Mesh>>() {
+            let Some((min, max)) = meshes.get(*handle).and_then(Mesh::aabb) else {
+                continue;
+            };
+            let transform = transform::world_transform(world, entity).unwrap_or_default();
+            gizmos.cuboid(
+                transform
+                    .compute_matrix()
+                    .transform_point3((min + max) * 0.5),
+                transform.rotation,
+                (max - min) * 0.5 * transform.scale,
+                Color::ORANGE,
+            );
+        }
+    }
+
+    if show_grid {
+        for (axis, color) in [
+            (Vec3::X, Color::RED),
+            (Vec3::Y, Color::GREEN),
+            (Vec3::Z, Color::BLUE),
+        ] {
+            gizmos.line(Vec3::ZERO, axis, color);
+        }
+    }
+
+    if show_paths {
+        for (entity, path) in world.query::<Path>() {
+            let model = transform::model_matrix(world, entity);
+            path.draw(&mut gizmos, model, Color::CYAN);
+        }
+    }
+
+    world.insert_resource(gizmos);
+}
+
+fn draw_collider(gizmos: &mut Gizmos, transform: &Transform, collider: &Collider, color: Color) {
+    match collider {
+        Collider::Sphere { radius } => {
+            let radius = radius * transform.scale.abs().max_element();
+            for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                gizmos.circle(
+                    transform.translation,
+                    transform.rotation * axis,
+                    radius,
+                    color,
+                );
+            }
+        }
+        Collider::Cuboid { half_extents } => gizmos.cuboid(
+            transform.translation,
+            transform.rotation,
+            *half_extents * transform.scale,
+            color,
+        ),
+        Collider::Capsule {
+            half_height,
+            radius,
+        } => {
+            let scale = transform.scale.abs();
+            let radius = radius * scale.x.max(scale.z);
+            let up = transform.rotation * Vec3::Y * (half_height * scale.y);
+            for center in [transform.translation + up, transform.translation - up] {
+                for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                    gizmos.circle(center, transform.rotation * axis, radius, color);
+                }
+            }
+            for side in [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::NEG_Z] {
+                let side = transform.rotation * side * radius;
+                gizmos.line(
+                    transform.translation + up + side,
+                    transform.translation - up + side,
+                    color,
+                );
+            }
+        }
+        Collider::ConvexHull(hull) => {
+            for (start, end) in hull.edges() {
+                gizmos.line(
+                    transform.transform_point(start),
+                    transform.transform_point(end),
+                    color,
+                );
+            }
+        }
+        // just the bounds, every triangle would bury the scene in lines
+        Collider::Heightfield(heightfield) => {
+            let (min, max) = heightfield.bounds();
+            draw_bounds(gizmos, transform, min, max, color);
+        }
+        Collider::TriMesh(trimesh) => {
+            if let Some((min, max)) = trimesh.bounds() {
+                draw_bounds(gizmos, transform, min, max, color);
+            }
+        }
+        Collider::Compound(parts) => {
+            for (offset, part) in parts {
+                draw_collider(gizmos, &transform.mul_transform(offset), part, color);
+            }
+        }
+    }
+}
+
+fn draw_bounds(gizmos: &mut Gizmos, transform: &Transform, min: Vec3, max: Vec3, color: Color) {
+    gizmos.cuboid(
+        transform.transform_point((min + max) * 0.5),
+        transform.rotation,
+        (max - min) * 0.5 * transform.scale,
+        color,
+    );
+}
@@ -0,0 +1,78 @@
+// Frame captures for RenderDoc, with the `renderdoc` feature. When the app
+// was started from RenderDoc (or has it injected), `capture_next_frame`, the
+// capture key or the `capture` console command records everything the
+// renderer sends the gpu for one frame, uploads included, through RenderDoc's
+// in-application api. The frame is split into debug groups, one per stage of
+// the renderer, and every pass has its label. Without RenderDoc attached a
+// capture does nothing. On the web there's nothing to capture with.
+
+use winit::keyboard::KeyCode;
+
+use crate::{
+    ecs::{component::Component, world::World},
+    input::Input,
+};
+
+#[derive(Debug)]
+pub struct Diagnostics {
+    // None to only capture from code and the console
+    pub capture_key: Option<KeyCode>,
+    capture_requested: bool,
+    captures: u32,
+}
+
+impl Component for Diagnostics {}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self {
+            capture_key: Some(KeyCode::F10),
+            capture_requested: false,
+            captures: 0,
+        }
+    }
+}
+
+impl Diagnostics {
+    pub fn capture_next_frame(&mut self) {
+        self.capture_requested = true;
+    }
+
+    // requested and not started yet
+    pub fn is_capture_pending(&self) -> bool {
+        self.capture_requested
+    }
+
+    // frames captured so far, whether or not RenderDoc was there to keep them
+    pub fn captures(&self) -> u32 {
+        self.captures
+    }
+
+    // the renderer's, true when this frame is to be captured
+    pub(crate) fn take_capture(&mut self) -> bool {
+        let requested = std::mem::take(&mut self.capture_requested);
+        if requested {
+            self.captures += 1;
+        }
+        requested
+    }
+}
+
+pub fn update_capture_key(world: &mut World) {
+    if crate::console::is_typing(world) {
+        return;
+    }
+    let (Some(input), Some(diagnostics)) = (
+        world.get_resource::<Input>(),
+        world.get_resource::<Diagnostics>(),
+    ) else {
+        return;
+    };
+    if diagnostics
+        .capture_key
+        .is_some_and(|key| input.just_pressed(key))
+    {
+        world.resource_mut::<Diagnostics>().capture_next_frame();
+        log::info!("Capturing the next frame");
+    }
+}
@@ -0,0 +1,153 @@
+// Files dropped onto the window. Native gets paths from winit, the web reads
+// the dropped files' bytes through the canvas' drop event.
+
+use std::path::PathBuf;
+
+use crate::{
+    assets::Assets,
+    camera::main_camera,
+    ecs::{event::Events, world::World},
+    mesh::{Mesh, MeshImport},
+    transform::Transform,
+};
+
+#[derive(Debug, Clone)]
+pub struct FileDropped {
+    // only the file name on the web
+    pub path: PathBuf,
+    // set on the web, where there's no file system to read the path from
+    pub bytes: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileHovered(pub PathBuf);
+
+#[derive(Debug, Clone)]
+pub struct FileHoverCancelled;
+
+#[derive(Debug)]
+pub struct DragAndDrop {
+    // spawn dropped models in front of the camera
+    pub auto_spawn_models: bool,
+    pub spawn_distance: f32,
+    // what's done to dropped models as they're loaded
+    pub import: MeshImport,
+}
+
+impl crate::ecs::component::Component for DragAndDrop {}
+
+impl Default for DragAndDrop {
+    fn default() -> Self {
+        Self {
+            auto_spawn_models: false,
+            spawn_distance: 5.0,
+            import: MeshImport::default(),
+        }
+    }
+}
+
+pub fn spawn_dropped_models(world: &mut World) {
+    let Some(settings) = world.get_resource::<DragAndDrop>() else {
+        return;
+    };
+    if !settings.auto_spawn_models {
+        return;
+    }
+    let distance = settings.spawn_distance;
+    let import = settings.import.clone();
+    let dropped: Vec<FileDropped> = world
+        .get_resource::<Events<FileDropped>>()
+        .map(|events| events.iter().cloned().collect())
+        .unwrap_or_default();
+
+    for file in dropped {
+        let extension = file
+            .path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let mesh = match (extension.as_deref(), &file.bytes) {
+            (Some("obj"), None) => Mesh::from_obj(&file.path.to_string_lossy()),
+            (Some("obj"), Some(_)) => {
+                log::warn!(
+                    "Models can only be loaded from a path, ignoring {}",
+                    file.path.display()
+                );
+                continue;
+            }
+            #[cfg(feature = "gltf")]
+            (Some("gltf" | "glb"), None) => Mesh::from_gltf(&file.path.to_string_lossy()),
+            // a .gltf's external buffers can't be found from here
+            #[cfg(feature = "gltf")]
+            (Some("gltf" | "glb"), Some(bytes)) => Mesh::from_gltf_bytes(bytes, None),
+            #[cfg(not(feature = "gltf"))]
+            (Some("gltf" | "glb"), _) => {
+                log::warn!(
+                    "Built without the gltf feature, ignoring {}",
+                    file.path.display()
+                );
+                continue;
+            }
+            _ => continue,
+        };
+        let mesh = match mesh {
+            Ok(mesh) => mesh,
+            Err(e) => {
+                log::error!("Unable to load dropped model: {}", e);
+                continue;
+            }
+        };
+
+        let transform = main_camera(world).map_or_else(Transform::default, |camera| {
+            Transform::from_translation(camera.pos + camera.forward() * distance)
+        });
+        let imported = import.add(world.resource_mut::<Assets<Mesh>>(), mesh);
+        let entity = world.spawn().insert(transform).id();
+        imported.insert(world, entity);
+        log::info!("Spawned {} as {:?}", file.path.display(), entity);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type WebDropQueue = std::rc::Rc<std::cell::RefCell<Vec<FileDropped>>>;
+
+// the callbacks can't reach the world, so drops are queued and drained every frame
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn listen_for_web_drops(canvas: &wgpu::web_sys::Element, queue: WebDropQueue) {
+    use wasm_bindgen::{JsCast, closure::Closure};
+    use wgpu::web_sys::DragEvent;
+
+    // the browser only fires drop if dragover is cancelled
+    let dragover = Closure::<dyn FnMut(DragEvent)>::new(|event: DragEvent| {
+        event.prevent_default();
+    });
+    let drop = Closure::<dyn FnMut(DragEvent)>::new(move |event: DragEvent| {
+        event.prevent_default();
+        let Some(files) = event.data_transfer().and_then(|transfer| transfer.files()) else {
+            return;
+        };
+        for file in (0..files.length()).filter_map(|i| files.get(i)) {
+            let queue = queue.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await {
+                    Ok(buffer) => queue.borrow_mut().push(FileDropped {
+                        path: file.name().into(),
+                        bytes: Some(js_sys::Uint8Array::new(&buffer).to_vec()),
+                    }),
+                    Err(e) => log::error!("Unable to read dropped file: {:?}", e),
+                }
+            });
+        }
+    });
+
+    for (name, callback) in [("dragover", &dragover), ("drop", &drop)] {
+        if canvas
+            .add_event_listener_with_callback(name, callback.as_ref().unchecked_ref())
+            .is_err()
+        {
+            log::error!("Unable to listen for {} events", name);
+        }
+    }
+    dragover.forget();
+    drop.forget();
+}
@@ -1,5 +0,0 @@
-// ECS - the naive way
-
-pub mod component;
-pub mod entity;
-pub mod world;
@@ -0,0 +1,370 @@
+// Runtime level editor: click to select, gizmos to edit, prefabs to place,
+// undo/redo through CommandHistory and scene save/load. Toggled with `Editor::toggle_key`.
+// With the ui feature the selected entity's components are listed in an
+// inspector panel in the top right, in `Editor::font` or the first font loaded.
+
+pub mod scene;
+
+use std::path::PathBuf;
+
+#[cfg(feature = "ui")]
+use glam::Vec2;
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+#[cfg(feature = "ui")]
+use crate::{
+    assets::Handle,
+    color::Color,
+    ecs::component::Component,
+    text::{Font, Text, TextLayout, TextMaterial, TextShadow, TextStyle},
+    ui::UiScale,
+    window::WindowSettings,
+};
+use crate::{
+    camera::main_camera,
+    ecs::{entity::Entity, world::World},
+    gizmos::{Selected, TransformGizmo},
+    history::{self, CommandHistory, DespawnEntity, SetComponent, SpawnEntity},
+    input::Input,
+    picking,
+    prefab::{PrefabInstance, Prefabs, spawn_prefab},
+    transform::Transform,
+};
+
+#[derive(Debug)]
+pub struct Editor {
+    pub enabled: bool,
+    pub toggle_key: KeyCode,
+    pub scene_path: PathBuf,
+    // distance in front of the camera new prefabs are placed at
+    pub spawn_distance: f32,
+    pub current_prefab: usize,
+    #[cfg(feature = "ui")]
+    pub font: Option<Handle<Font>>,
+    #[cfg(feature = "ui")]
+    pub text_size: f32,
+    // logical pixels, longer lines wrap
+    #[cfg(feature = "ui")]
+    pub inspector_width: f32,
+    drag_start: Option<(Entity, Transform)>,
+}
+
+impl crate::ecs::component::Component for Editor {}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            toggle_key: KeyCode::F1,
+            scene_path: PathBuf::from("scene.txt"),
+            spawn_distance: 5.0,
+            current_prefab: 0,
+            #[cfg(feature = "ui")]
+            font: None,
+            #[cfg(feature = "ui")]
+            text_size: 14.0,
+            #[cfg(feature = "ui")]
+            inspector_width: 420.0,
+            drag_start: None,
+        }
+    }
+}
+
+fn selected(world: &World) -> Option<Entity> {
+    world.query::<Selected>().first().map(|(entity, _)| *entity)
+}
+
+fn select(world: &mut World, entity: Option<Entity>) {
+    let previous: Vec<Entity> = world
+        .query::<Selected>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in previous {
+        world.remove_component::<Selected>(entity);
+    }
+    if let Some(entity) = entity {
+        world.add_component(entity, Selected);
+    }
+}
+
+// on the inspector's text entity
+#[cfg(feature = "ui")]
+#[derive(Debug)]
+pub struct InspectorText;
+
+#[cfg(feature = "ui")]
+impl Component for InspectorText {}
+
+// characters of a component's debug output shown before it's cut off
+#[cfg(feature = "ui")]
+const MAX_VALUE_LEN: usize = 160;
+
+// the entity, then its components by name, one per line
+#[cfg(feature = "ui")]
+fn inspector_lines(world: &World, entity: Entity) -> Vec<String> {
+    let mut lines = vec![format!("{:?}", entity)];
+    for (name, component) in world.components(entity) {
+        if name == std::any::type_name::<Selected>() {
+            continue;
+        }
+        let mut value = format!("{:?}", component);
+        if let Some((cut, _)) = value.char_indices().nth(MAX_VALUE_LEN) {
+            value.truncate(cut);
+            value.push_str("...");
+        }
+        lines.push(format!("{}: {}", history::short_type_name(name), value));
+    }
+    lines
+}
+
+#[cfg(feature = "ui")]
+fn draw_inspector(world: &mut World, editor: &Editor) {
+    const MARGIN: f32 = 8.0;
+    let existing = world
+        .query::<InspectorText>()
+        .first()
+        .map(|(entity, _)| *entity);
+    let shown = selected(world)
+        .filter(|_| editor.enabled)
+        .zip(crate::text::font_or_first(world, editor.font));
+    let Some((selected, font)) = shown else {
+        if let Some(entity) = existing {
+            world.despawn(entity);
+        }
+        return;
+    };
+
+    let style = TextStyle {
+        size: editor.text_size,
+        ..TextStyle::default()
+    };
+    let mut lines = inspector_lines(world, selected).into_iter();
+    let mut text = Text::new(
+        font,
+        lines.next().unwrap_or_default(),
+        TextStyle {
+            bold: true,
+            ..style
+        },
+    );
+    for line in lines {
+        text = text.with_span(format!("\n{}", line), style);
+    }
+    text.max_width = Some(editor.inspector_width);
+    text.material = TextMaterial {
+        shadow: Some(TextShadow {
+            offset: Vec2::splat(1.0),
+            color: Color::BLACK,
+            softness: 0.0,
+        }),
+        ..TextMaterial::default()
+    };
+
+    // in the top right corner, placed from last frame's layout
+    let entity = match existing {
+        Some(entity) => entity,
+        None => world.spawn().insert(InspectorText).id(),
+    };
+    let window = world
+        .resource::<UiScale>()
+        .to_logical(world.resource::<WindowSettings>().size());
+    let width = world
+        .get_component::<TextLayout>(entity)
+        .map_or(0.0, |layout| layout.size.x);
+    text.position = Vec2::new(window.x - width - MARGIN, MARGIN);
+    if world.get_component::<Text>(entity) != Some(&text) {
+        world.add_component(entity, text);
+    }
+}
+
+impl Editor {
+    // selection is dropped first so the marker isn't captured by spawn/despawn commands
+    pub fn undo(&mut self, world: &mut World) {
+        select(world, None);
+        history::undo(world);
+    }
+
+    pub fn redo(&mut self, world: &mut World) {
+        select(world, None);
+        history::redo(world);
+    }
+
+    fn track_gizmo_drag(&mut self, world: &mut World, dragging: bool) {
+        match (self.drag_start, dragging, selected(world)) {
+            (None, true, Some(entity)) => {
+                self.drag_start = world
+                    .get_component::<Transform>(entity)
+                    .map(|transform| (entity, *transform));
+            }
+            (Some((entity, before)), false, _) => {
+                self.drag_start = None;
+                if let Some(&after) = world.get_component::<Transform>(entity)
+                    && after != before
+                {
+                    world
+                        .resource_mut::<CommandHistory>()
+                        .record(SetComponent::new(entity, Some(before), Some(after)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn spawn_current(&mut self, world: &mut World) {
+        let names = world
+            .get_resource::<Prefabs>()
+            .map(|prefabs| {
+                prefabs
+                    .names()
+                    .into_iter()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let Some(prefab) = names.get(self.current_prefab % names.len().max(1)).cloned() else {
+            log::warn!("No prefabs registered");
+            return;
+        };
+        let Some(camera) = main_camera(world) else {
+            log::warn!("No camera to spawn in front of");
+            return;
+        };
+        let transform =
+            Transform::from_translation(camera.pos + camera.forward() * self.spawn_distance);
+
+        if let Some(entity) = spawn_prefab(world, &prefab, transform) {
+            world
+                .resource_mut::<CommandHistory>()
+                .record(SpawnEntity::new(entity));
+            select(world, Some(entity));
+        }
+    }
+
+    fn delete_selected(&mut self, world: &mut World) {
+        let Some(entity) = selected(world) else {
+            return;
+        };
+        if world.get_component::<PrefabInstance>(entity).is_none() {
+            log::warn!("Only prefab instances can be deleted in the editor");
+            return;
+        }
+        select(world, None);
+        history::execute(world, DespawnEntity::new(entity));
+    }
+
+    fn handle_shortcuts(&mut self, world: &mut World, input: &Input) {
+        let ctrl = input.pressed(KeyCode::ControlLeft) || input.pressed(KeyCode::ControlRight);
+        let shift = input.pressed(KeyCode::ShiftLeft) || input.pressed(KeyCode::ShiftRight);
+
+        if ctrl {
+            if input.just_pressed(KeyCode::KeyZ) && shift || input.just_pressed(KeyCode::KeyY) {
+                self.redo(world);
+            } else if input.just_pressed(KeyCode::KeyZ) {
+                self.undo(world);
+            } else if input.just_pressed(KeyCode::KeyS) {
+                match scene::save_scene(world, &self.scene_path) {
+                    Ok(()) => log::info!("Saved scene to {}", self.scene_path.display()),
+                    Err(e) => log::error!("Unable to save scene: {}", e),
+                }
+            } else if input.just_pressed(KeyCode::KeyO) {
+                match scene::load_scene(world, &self.scene_path) {
+                    Ok(_) => {
+                        history::clear(world);
+                        log::info!("Loaded scene from {}", self.scene_path.display());
+                    }
+                    Err(e) => log::error!("Unable to load scene: {}", e),
+                }
+            }
+            return;
+        }
+
+        if input.just_pressed(KeyCode::Tab) {
+            self.current_prefab = self.current_prefab.wrapping_add(1);
+        }
+        if input.just_pressed(KeyCode::KeyN) {
+            self.spawn_current(world);
+        }
+        if input.just_pressed(KeyCode::Delete) {
+            self.delete_selected(world);
+        }
+    }
+}
+
+pub fn update_editor(world: &mut World) {
+    let Some(mut editor) = world.remove_resource::<Editor>() else {
+        return;
+    };
+    // input is swapped out too so shortcuts can mutate the world while reading it
+    let Some(input) = world.remove_resource::<Input>() else {
+        world.insert_resource(editor);
+        return;
+    };
+
+    if input.just_pressed(editor.toggle_key) {
+        editor.enabled = !editor.enabled;
+        if let Some(gizmo) = world.get_resource_mut::<TransformGizmo>() {
+            gizmo.enabled = editor.enabled;
+        }
+        log::info!(
+            "Editor {}",
+            if editor.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+
+    if editor.enabled {
+        let (dragging, hovered) = world
+            .get_resource::<TransformGizmo>()
+            .map_or((false, false), |gizmo| {
+                (gizmo.is_dragging(), gizmo.is_hovered())
+            });
+        editor.track_gizmo_drag(world, dragging);
+
+        if input.mouse_just_pressed(MouseButton::Left)
+            && !dragging
+            && !hovered
+            && let Some(cursor) = input.cursor_position()
+        {
+            let hit = picking::pick_at(world, cursor).map(|hit| hit.entity);
+            select(world, hit);
+        }
+
+        if !crate::console::is_typing(world) {
+            editor.handle_shortcuts(world, &input);
+        }
+    }
+
+    #[cfg(feature = "ui")]
+    draw_inspector(world, &editor);
+
+    world.insert_resource(input);
+    world.insert_resource(editor);
+}
+
+#[cfg(all(test, feature = "ui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inspector_lists_components_by_short_name() {
+        let mut world = World::new();
+        let entity = world
+            .spawn()
+            .insert(Transform::default())
+            .insert(PrefabInstance("x".repeat(500)))
+            .insert(Selected)
+            .id();
+        let lines = inspector_lines(&world, entity);
+        assert_eq!(lines.len(), 3, "{:?}", lines);
+        assert_eq!(lines[0], format!("{:?}", entity));
+        // long values are cut, the selection marker isn't listed
+        assert!(lines[1].starts_with("PrefabInstance: PrefabInstance(\"xxx"));
+        assert!(lines[1].ends_with("..."));
+        assert_eq!(lines[1].len(), "PrefabInstance: ".len() + MAX_VALUE_LEN + 3);
+        assert!(lines[2].starts_with("Transform: Transform {"));
+    }
+}
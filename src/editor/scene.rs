@@ -0,0 +1,100 @@
+// Plain text scene format, one prefab instance per line:
+// <prefab> <tx> <ty> <tz> <rx> <ry> <rz> <rw> <sx> <sy> <sz>
+
+use std::{fmt::Write, path::Path};
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    ecs::{entity::Entity, world::World},
+    prefab::{PrefabInstance, spawn_prefab},
+    transform::Transform,
+};
+
+pub(crate) const HEADER: &str = "# whirlwind scene\n";
+
+pub(crate) fn write_instance(
+    contents: &mut String,
+    name: &str,
+    transform: &Transform,
+) -> std::fmt::Result {
+    let t = transform.translation;
+    let r = transform.rotation;
+    let s = transform.scale;
+    writeln!(
+        contents,
+        "{name} {} {} {} {} {} {} {} {} {} {}",
+        t.x, t.y, t.z, r.x, r.y, r.z, r.w, s.x, s.y, s.z
+    )
+}
+
+pub fn save_scene(world: &World, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let mut contents = String::from(HEADER);
+    for (entity, PrefabInstance(name)) in world.query::<PrefabInstance>() {
+        let transform = world
+            .get_component::<Transform>(entity)
+            .copied()
+            .unwrap_or_default();
+        write_instance(&mut contents, name, &transform)?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+fn parse_line(line: &str) -> anyhow::Result<(&str, Transform)> {
+    let mut parts = line.split_whitespace();
+    let name = parts.next().ok_or(anyhow::anyhow!("Missing prefab name"))?;
+    let values = parts
+        .map(str::parse::<f32>)
+        .collect::<Result<Vec<_>, _>>()?;
+    let [tx, ty, tz, rx, ry, rz, rw, sx, sy, sz] = values[..] else {
+        anyhow::bail!("Expected 10 transform values, found {}", values.len());
+    };
+    Ok((
+        name,
+        Transform {
+            translation: Vec3::new(tx, ty, tz),
+            rotation: Quat::from_xyzw(rx, ry, rz, rw).normalize(),
+            scale: Vec3::new(sx, sy, sz),
+        },
+    ))
+}
+
+// replaces every prefab instance in the world with the scene's contents
+// the prefab instances in a scene file's contents
+pub(crate) fn parse_scene(contents: &str) -> anyhow::Result<Vec<(String, Transform)>> {
+    let mut instances = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, transform) =
+            parse_line(line).map_err(|e| anyhow::anyhow!("Line {}: {}", number + 1, e))?;
+        instances.push((name.to_string(), transform));
+    }
+    Ok(instances)
+}
+
+pub fn load_scene(world: &mut World, path: impl AsRef<Path>) -> anyhow::Result<Vec<Entity>> {
+    crate::error::ensure_exists(path.as_ref())?;
+    let instances = parse_scene(&std::fs::read_to_string(path)?)?;
+
+    let existing: Vec<Entity> = world
+        .query::<PrefabInstance>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in existing {
+        world.despawn(entity);
+    }
+
+    let mut spawned = Vec::new();
+    for (name, transform) in &instances {
+        match spawn_prefab(world, name, *transform) {
+            Some(entity) => spawned.push(entity),
+            None => log::warn!("Scene references unknown prefab {}", name),
+        }
+    }
+    Ok(spawned)
+}
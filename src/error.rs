@@ -0,0 +1,82 @@
+// The engine's own errors. Fallible apis return these directly, or inside an
+// anyhow::Error where other errors can happen too, from which they can be
+// taken back out with `downcast_ref::<WhirlwindError>()`. The panicking
+// accessors like `World::resource` panic with the same messages.
+
+use std::{fmt, path::PathBuf};
+
+use crate::ecs::EcsError;
+
+#[derive(Debug)]
+pub enum WhirlwindError {
+    // a file loaded from disk that isn't there
+    AssetNotFound(PathBuf),
+    // a file that was read but can't be used
+    InvalidAsset { path: PathBuf, reason: String },
+    // a handle to an asset that was removed, or to another Assets
+    AssetMissing { asset: &'static str, id: usize },
+    // a missing component or resource, from the ecs
+    Ecs(EcsError),
+    Surface(wgpu::SurfaceError),
+    ShaderCompile { label: String, message: String },
+    NoAdapter(String),
+}
+
+impl fmt::Display for WhirlwindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AssetNotFound(path) => write!(f, "Asset not found: {}", path.display()),
+            Self::InvalidAsset { path, reason } => {
+                write!(f, "Invalid asset {}: {reason}", path.display())
+            }
+            Self::AssetMissing { asset, id } => {
+                write!(f, "No {asset} with handle {id}")
+            }
+            Self::Ecs(e) => e.fmt(f),
+            Self::Surface(e) => write!(f, "Surface error: {e}"),
+            Self::ShaderCompile { label, message } => {
+                write!(f, "Unable to compile shader {label}: {message}")
+            }
+            Self::NoAdapter(reason) => write!(f, "No graphics adapter: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for WhirlwindError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Ecs(e) => Some(e),
+            Self::Surface(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<EcsError> for WhirlwindError {
+    fn from(e: EcsError) -> Self {
+        Self::Ecs(e)
+    }
+}
+
+impl From<wgpu::SurfaceError> for WhirlwindError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        Self::Surface(e)
+    }
+}
+
+impl From<wgpu::RequestAdapterError> for WhirlwindError {
+    fn from(e: wgpu::RequestAdapterError) -> Self {
+        Self::NoAdapter(e.to_string())
+    }
+}
+
+// checked before reading, so a missing file is told apart from a broken one
+pub(crate) fn ensure_exists(path: impl Into<PathBuf>) -> Result<(), WhirlwindError> {
+    let path = path.into();
+    // web builds fetch assets instead, there's no filesystem to check
+    if cfg!(target_arch = "wasm32") || path.exists() {
+        Ok(())
+    } else {
+        Err(WhirlwindError::AssetNotFound(path))
+    }
+}
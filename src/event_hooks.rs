@@ -0,0 +1,59 @@
+// Raw winit events for what the engine doesn't turn into input or ecs events
+// itself, touchpad pressure, a pen's tilt, a theme change. Callbacks added to
+// EventHooks get each window event and device event with the world, before
+// the engine handles it. They can't stop the engine seeing it too.
+
+use winit::event::{DeviceEvent, DeviceId, WindowEvent};
+
+use crate::ecs::{component::Component, world::World};
+
+type WindowHook = Box<dyn FnMut(&mut World, &WindowEvent)>;
+type DeviceHook = Box<dyn FnMut(&mut World, DeviceId, &DeviceEvent)>;
+
+#[derive(Default)]
+pub struct EventHooks {
+    window: Vec<WindowHook>,
+    device: Vec<DeviceHook>,
+}
+
+impl std::fmt::Debug for EventHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventHooks")
+            .field("window", &self.window.len())
+            .field("device", &self.device.len())
+            .finish()
+    }
+}
+
+impl Component for EventHooks {}
+
+impl EventHooks {
+    // every event for the window, redraws included, so keep it quick
+    pub fn on_window_event(&mut self, hook: impl FnMut(&mut World, &WindowEvent) + 'static) {
+        self.window.push(Box::new(hook));
+    }
+
+    // raw device input, mouse motion arrives here whether or not the window is focused
+    pub fn on_device_event(
+        &mut self,
+        hook: impl FnMut(&mut World, DeviceId, &DeviceEvent) + 'static,
+    ) {
+        self.device.push(Box::new(hook));
+    }
+}
+
+pub(crate) fn window_event(world: &mut World, event: &WindowEvent) {
+    let _ = world.try_resource_scope(|world, hooks: &mut EventHooks| {
+        for hook in &mut hooks.window {
+            hook(world, event);
+        }
+    });
+}
+
+pub(crate) fn device_event(world: &mut World, device: DeviceId, event: &DeviceEvent) {
+    let _ = world.try_resource_scope(|world, hooks: &mut EventHooks| {
+        for hook in &mut hooks.device {
+            hook(world, device, event);
+        }
+    });
+}
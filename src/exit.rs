@@ -0,0 +1,89 @@
+// Leaving the app. Any system can send AppExit to stop once the frame is done.
+// Closing the window asks the ExitHooks first, any of which can refuse, to
+// show a "save before quitting?" prompt say, and send AppExit itself once it's
+// answered. Either way the Shutdown schedule runs once before the event loop
+// stops, for saving settings and flushing whatever is buffered.
+
+use winit::event_loop::ActiveEventLoop;
+
+use crate::ecs::{component::Component, event::Events, schedule::Shutdown, world::World};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppExit {
+    Success,
+    // App::run returns an error with the code
+    Error(u8),
+}
+
+// true to let the window close
+type CloseCheck = Box<dyn FnMut(&mut World) -> bool>;
+
+#[derive(Default)]
+pub struct ExitHooks {
+    checks: Vec<CloseCheck>,
+}
+
+impl std::fmt::Debug for ExitHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExitHooks")
+            .field("checks", &self.checks.len())
+            .finish()
+    }
+}
+
+impl Component for ExitHooks {}
+
+impl ExitHooks {
+    // asked when the window's close button is pressed, AppExit doesn't ask
+    pub fn confirm_close(&mut self, check: impl FnMut(&mut World) -> bool + 'static) {
+        self.checks.push(Box::new(check));
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct PendingExit {
+    pub(crate) exit: Option<AppExit>,
+    shut_down: bool,
+}
+
+impl Component for PendingExit {}
+
+// in "last", ahead of the event being cleared. an error wins over a success
+pub(crate) fn record_exit(world: &mut World) {
+    let Some(exit) = world
+        .resource::<Events<AppExit>>()
+        .iter()
+        .copied()
+        .max_by_key(|exit| matches!(exit, AppExit::Error(_)))
+    else {
+        return;
+    };
+    let pending = world.resource_mut::<PendingExit>();
+    if !matches!(pending.exit, Some(AppExit::Error(_))) {
+        pending.exit = Some(exit);
+    }
+}
+
+pub(crate) fn close_allowed(world: &mut World) -> bool {
+    world
+        .try_resource_scope(|world, hooks: &mut ExitHooks| {
+            hooks.checks.iter_mut().all(|check| check(world))
+        })
+        .unwrap_or(true)
+}
+
+pub(crate) fn exit(world: &mut World, event_loop: &ActiveEventLoop) {
+    shutdown(world);
+    event_loop.exit();
+}
+
+// runs "shutdown" the first time it's called
+pub(crate) fn shutdown(world: &mut World) {
+    let pending = world.resource_mut::<PendingExit>();
+    if std::mem::replace(&mut pending.shut_down, true) {
+        return;
+    }
+    pending.exit.get_or_insert(AppExit::Success);
+    world.run(Shutdown);
+    log::logger().flush();
+}
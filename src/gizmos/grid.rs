@@ -0,0 +1,142 @@
+// Infinite reference grid on the y = 0 plane, drawn by intersecting each
+// pixel's view ray with the plane so it never runs out. Lines are
+// antialiased by their screen space width and fade out with distance.
+
+use crate::{color::Color, render::post, texture::Texture};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    // world units between minor lines
+    pub spacing: f32,
+    // every nth line is a major one
+    pub major_every: u32,
+    // fully faded at this distance from the camera
+    pub fade_distance: f32,
+    pub minor_color: Color,
+    pub major_color: Color,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            spacing: 1.0,
+            major_every: 10,
+            fade_distance: 150.0,
+            minor_color: Color::srgba(0.5, 0.5, 0.5, 0.35),
+            major_color: Color::srgba(0.7, 0.7, 0.7, 0.6),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GridUniform {
+    minor_color: [f32; 4],
+    major_color: [f32; 4],
+    spacing: f32,
+    major_spacing: f32,
+    fade_distance: f32,
+    _padding: f32,
+}
+
+pub(crate) struct GridPipeline {
+    pipeline: wgpu::RenderPipeline,
+    settings_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GridPipeline {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grid_layout"),
+            entries: &[post::uniform_entry(0)],
+        });
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("grid_settings"),
+            size: std::mem::size_of::<GridUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grid_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("grid.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &layout],
+            immediate_size: 0,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Grid Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the shader writes the plane's depth so the scene hides it properly
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+
+        Self {
+            pipeline,
+            settings_buffer,
+            bind_group,
+        }
+    }
+
+    pub(crate) fn prepare(&self, queue: &wgpu::Queue, grid: &Grid) {
+        let spacing = grid.spacing.max(0.001);
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&GridUniform {
+                minor_color: grid.minor_color.to_linear(),
+                major_color: grid.major_color.to_linear(),
+                spacing,
+                major_spacing: spacing * grid.major_every.max(1) as f32,
+                fade_distance: grid.fade_distance.max(0.001),
+                _padding: 0.0,
+            }),
+        );
+    }
+
+    // expects the camera bind group to already be set at group 0
+    pub(crate) fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,83 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct GridSettings {
+    minor_color: vec4<f32>,
+    major_color: vec4<f32>,
+    spacing: f32,
+    major_spacing: f32,
+    fade_distance: f32,
+};
+@group(1) @binding(0)
+var<uniform> settings: GridSettings;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // homogeneous, divided per fragment so the interpolation stays linear
+    @location(0) near: vec4<f32>,
+    @location(1) far: vec4<f32>,
+};
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.near = camera.inverse_view_proj * vec4<f32>(ndc, 0.0, 1.0);
+    out.far = camera.inverse_view_proj * vec4<f32>(ndc, 1.0, 1.0);
+    return out;
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+// 1 on a line, falling off over about a pixel
+fn lines(coords: vec2<f32>, spacing: f32) -> f32 {
+    let scaled = coords / spacing;
+    let width = max(fwidth(scaled), vec2<f32>(0.0001));
+    let distance = abs(fract(scaled - 0.5) - 0.5) / width;
+    return 1.0 - min(min(distance.x, distance.y), 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let near = in.near.xyz / in.near.w;
+    let far = in.far.xyz / in.far.w;
+    let t = -near.y / (far.y - near.y);
+    let position = near + (far - near) * t;
+    let coords = position.xz;
+
+    // derivatives first, they need every pixel of the quad
+    let minor = lines(coords, settings.spacing);
+    let major = lines(coords, settings.major_spacing);
+    let axis_width = max(fwidth(coords), vec2<f32>(0.0001));
+    let axis = 1.0 - min(abs(coords) / axis_width, vec2<f32>(1.0));
+
+    if t <= 0.0 {
+        discard;
+    }
+
+    var color = vec4<f32>(settings.minor_color.rgb, settings.minor_color.a * minor);
+    color = mix(color, settings.major_color, major);
+    // x runs along z = 0 and z along x = 0
+    color = mix(color, vec4<f32>(0.9, 0.2, 0.2, 1.0), axis.y);
+    color = mix(color, vec4<f32>(0.2, 0.3, 0.9, 1.0), axis.x);
+
+    let distance = length(position - camera.position.xyz);
+    color.a *= 1.0 - smoothstep(settings.fade_distance * 0.5, settings.fade_distance, distance);
+
+    let clip = camera.view_proj * vec4<f32>(position, 1.0);
+    var out: FragmentOutput;
+    out.color = color;
+    out.depth = clamp(clip.z / clip.w, 0.0, 1.0);
+    return out;
+}
@@ -0,0 +1,100 @@
+pub mod grid;
+pub mod render;
+pub mod transform;
+
+pub use transform::{GizmoMode, Selected, TransformGizmo};
+
+use glam::{Quat, Vec3};
+
+use crate::{color::Color, ecs::component::Component};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GizmoVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+// immediate mode debug lines, cleared at the start of every update
+#[derive(Debug, Default)]
+pub struct Gizmos {
+    pub(crate) lines: Vec<GizmoVertex>,
+    // drawn without depth testing, on top of the scene
+    pub(crate) overlay: Vec<GizmoVertex>,
+}
+
+impl Component for Gizmos {}
+
+impl Gizmos {
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        push_line(&mut self.lines, start, end, color);
+    }
+
+    pub fn overlay_line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        push_line(&mut self.overlay, start, end, color);
+    }
+
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Color) {
+        for (start, end) in circle_segments(center, normal, radius) {
+            self.line(start, end, color);
+        }
+    }
+
+    pub fn overlay_circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Color) {
+        for (start, end) in circle_segments(center, normal, radius) {
+            self.overlay_line(start, end, color);
+        }
+    }
+
+    pub fn cuboid(&mut self, center: Vec3, rotation: Quat, half_extents: Vec3, color: Color) {
+        let corner =
+            |x: f32, y: f32, z: f32| center + rotation * (half_extents * Vec3::new(x, y, z));
+        for (a, b) in [
+            ((-1.0, -1.0, -1.0), (1.0, -1.0, -1.0)),
+            ((-1.0, 1.0, -1.0), (1.0, 1.0, -1.0)),
+            ((-1.0, -1.0, 1.0), (1.0, -1.0, 1.0)),
+            ((-1.0, 1.0, 1.0), (1.0, 1.0, 1.0)),
+            ((-1.0, -1.0, -1.0), (-1.0, 1.0, -1.0)),
+            ((1.0, -1.0, -1.0), (1.0, 1.0, -1.0)),
+            ((-1.0, -1.0, 1.0), (-1.0, 1.0, 1.0)),
+            ((1.0, -1.0, 1.0), (1.0, 1.0, 1.0)),
+            ((-1.0, -1.0, -1.0), (-1.0, -1.0, 1.0)),
+            ((1.0, -1.0, -1.0), (1.0, -1.0, 1.0)),
+            ((-1.0, 1.0, -1.0), (-1.0, 1.0, 1.0)),
+            ((1.0, 1.0, -1.0), (1.0, 1.0, 1.0)),
+        ] {
+            self.line(corner(a.0, a.1, a.2), corner(b.0, b.1, b.2), color);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.overlay.clear();
+    }
+}
+
+fn push_line(buffer: &mut Vec<GizmoVertex>, start: Vec3, end: Vec3, color: Color) {
+    let color = color.to_linear();
+    buffer.push(GizmoVertex {
+        position: start.to_array(),
+        color,
+    });
+    buffer.push(GizmoVertex {
+        position: end.to_array(),
+        color,
+    });
+}
+
+pub(crate) fn circle_segments(
+    center: Vec3,
+    normal: Vec3,
+    radius: f32,
+) -> impl Iterator<Item = (Vec3, Vec3)> {
+    const SEGMENTS: usize = 48;
+    let (u, v) = normal.normalize_or(Vec3::Y).any_orthonormal_pair();
+    let point = move |i: usize| {
+        let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        center + (u * angle.cos() + v * angle.sin()) * radius
+    };
+    (0..SEGMENTS).map(move |i| (point(i), point(i + 1)))
+}
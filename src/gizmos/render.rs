@@ -0,0 +1,127 @@
+use crate::texture::Texture;
+
+use super::{GizmoVertex, Gizmos};
+
+pub(crate) struct GizmoPipeline {
+    depth_tested: wgpu::RenderPipeline,
+    overlay: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    line_count: u32,
+    overlay_count: u32,
+}
+
+impl GizmoVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, vertices: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Gizmo Vertex Buffer"),
+        size: (vertices.max(2) * std::mem::size_of::<GizmoVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+impl GizmoPipeline {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("gizmo.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Gizmo Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let create = |label, depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[GizmoVertex::desc()],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache,
+            })
+        };
+
+        Self {
+            depth_tested: create("Gizmo Pipeline", wgpu::CompareFunction::LessEqual),
+            overlay: create("Gizmo Overlay Pipeline", wgpu::CompareFunction::Always),
+            vertex_buffer: create_vertex_buffer(device, 1024),
+            line_count: 0,
+            overlay_count: 0,
+        }
+    }
+
+    pub(crate) fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, gizmos: &Gizmos) {
+        let total = gizmos.lines.len() + gizmos.overlay.len();
+        let size = (total * std::mem::size_of::<GizmoVertex>()) as wgpu::BufferAddress;
+        if size > self.vertex_buffer.size() {
+            self.vertex_buffer = create_vertex_buffer(device, total * 2);
+        }
+        if !gizmos.lines.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&gizmos.lines));
+        }
+        if !gizmos.overlay.is_empty() {
+            queue.write_buffer(
+                &self.vertex_buffer,
+                std::mem::size_of_val(gizmos.lines.as_slice()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&gizmos.overlay),
+            );
+        }
+        self.line_count = gizmos.lines.len() as u32;
+        self.overlay_count = gizmos.overlay.len() as u32;
+    }
+
+    // expects the camera bind group to already be set at group 0
+    pub(crate) fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        if self.line_count > 0 {
+            render_pass.set_pipeline(&self.depth_tested);
+            render_pass.draw(0..self.line_count, 0..1);
+        }
+        if self.overlay_count > 0 {
+            render_pass.set_pipeline(&self.overlay);
+            render_pass.draw(self.line_count..self.line_count + self.overlay_count, 0..1);
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use glam::{Quat, Vec2, Vec3};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::{
+    camera::{Camera, Viewport, main_camera},
+    color::Color,
+    ecs::{component::Component, entity::Entity, world::World},
+    input::Input,
+    physics::Ray,
+    transform::{self, Transform},
+    window::WindowSettings,
+};
+
+use super::{Gizmos, circle_segments};
+
+const AXES: [Vec3; 3] = [Vec3::X, Vec3::Y, Vec3::Z];
+const COLORS: [Color; 3] = [
+    Color::srgb(0.9, 0.2, 0.2),
+    Color::srgb(0.2, 0.9, 0.2),
+    Color::srgb(0.2, 0.4, 0.9),
+];
+const HOVER_COLOR: Color = Color::srgb(1.0, 0.9, 0.1);
+// pick radius around handles, in physical pixels
+const PICK_DISTANCE: f32 = 8.0;
+
+#[derive(Debug, Default)]
+pub struct Selected;
+
+impl Component for Selected {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GizmoHandle {
+    Axis(usize),
+    // plane spanned by the two axes other than the index
+    Plane(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    entity: Entity,
+    handle: GizmoHandle,
+    start: Transform,
+    // axis parameter or plane hit point where the drag began
+    anchor: Vec3,
+}
+
+#[derive(Debug)]
+pub struct TransformGizmo {
+    pub enabled: bool,
+    pub mode: GizmoMode,
+    pub translate_snap: Option<f32>,
+    // in degrees
+    pub rotate_snap: Option<f32>,
+    pub scale_snap: Option<f32>,
+    // W/E/R switch modes
+    pub hotkeys: bool,
+    // handle length as a fraction of the camera distance, keeps a constant screen size
+    pub size: f32,
+    hovered: Option<GizmoHandle>,
+    drag: Option<Drag>,
+}
+
+impl Component for TransformGizmo {}
+
+impl Default for TransformGizmo {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: GizmoMode::Translate,
+            translate_snap: None,
+            rotate_snap: None,
+            scale_snap: None,
+            hotkeys: true,
+            size: 0.15,
+            hovered: None,
+            drag: None,
+        }
+    }
+}
+
+impl TransformGizmo {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn is_hovered(&self) -> bool {
+        self.hovered.is_some()
+    }
+}
+
+fn snap(value: f32, step: Option<f32>) -> f32 {
+    match step {
+        Some(step) if step > 0.0 => (value / step).round() * step,
+        _ => value,
+    }
+}
+
+fn segment_distance(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = ((point - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    point.distance(a + ab * t)
+}
+
+// parameter along the axis line of the point closest to the ray
+fn closest_on_axis(origin: Vec3, axis: Vec3, ray: &Ray) -> Option<f32> {
+    let w = origin - ray.origin;
+    let b = axis.dot(ray.direction);
+    let denom = 1.0 - b * b;
+    if denom.abs() < 1e-5 {
+        return None;
+    }
+    Some((b * ray.direction.dot(w) - axis.dot(w)) / denom)
+}
+
+fn intersect_plane(origin: Vec3, normal: Vec3, ray: &Ray) -> Option<Vec3> {
+    let denom = normal.dot(ray.direction);
+    if denom.abs() < 1e-5 {
+        return None;
+    }
+    let t = normal.dot(origin - ray.origin) / denom;
+    (t >= 0.0).then(|| ray.at(t))
+}
+
+fn handle_axes(mode: GizmoMode, transform: &Transform) -> [Vec3; 3] {
+    match mode {
+        GizmoMode::Scale => AXES.map(|axis| transform.rotation * axis),
+        _ => AXES,
+    }
+}
+
+struct Frame<'a> {
+    camera: &'a Camera,
+    viewport: Vec2,
+    ray: Ray,
+    cursor: Vec2,
+}
+
+impl TransformGizmo {
+    fn pick(
+        &self,
+        frame: &Frame<'_>,
+        origin: Vec3,
+        axes: [Vec3; 3],
+        size: f32,
+    ) -> Option<GizmoHandle> {
+        let project = |point| {
+            frame
+                .camera
+                .world_to_viewport(Viewport::full(frame.viewport), point)
+        };
+        let mut best: Option<(f32, GizmoHandle)> = None;
+        let mut consider = |distance: f32, handle| {
+            if distance < PICK_DISTANCE && best.is_none_or(|(best, _)| distance < best) {
+                best = Some((distance, handle));
+            }
+        };
+
+        for (i, axis) in axes.iter().enumerate() {
+            match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    if let (Some(a), Some(b)) = (project(origin), project(origin + *axis * size)) {
+                        consider(segment_distance(frame.cursor, a, b), GizmoHandle::Axis(i));
+                    }
+                }
+                GizmoMode::Rotate => {
+                    for (start, end) in circle_segments(origin, *axis, size) {
+                        if let (Some(a), Some(b)) = (project(start), project(end)) {
+                            consider(segment_distance(frame.cursor, a, b), GizmoHandle::Axis(i));
+                        }
+                    }
+                }
+            }
+        }
+
+        // planes win when the cursor is inside their square
+        if self.mode == GizmoMode::Translate {
+            for (i, normal) in axes.iter().enumerate() {
+                let Some(hit) = intersect_plane(origin, *normal, &frame.ray) else {
+                    continue;
+                };
+                let (a, b) = (axes[(i + 1) % 3], axes[(i + 2) % 3]);
+                let local = hit - origin;
+                let inside = |v: f32| (0.2 * size..=0.4 * size).contains(&v);
+                if inside(local.dot(a)) && inside(local.dot(b)) {
+                    consider(0.0, GizmoHandle::Plane(i));
+                }
+            }
+        }
+
+        best.map(|(_, handle)| handle)
+    }
+
+    fn anchor(
+        &self,
+        handle: GizmoHandle,
+        origin: Vec3,
+        axes: [Vec3; 3],
+        ray: &Ray,
+    ) -> Option<Vec3> {
+        match (self.mode, handle) {
+            (GizmoMode::Translate | GizmoMode::Scale, GizmoHandle::Axis(i)) => {
+                closest_on_axis(origin, axes[i], ray).map(Vec3::splat)
+            }
+            (GizmoMode::Rotate, GizmoHandle::Axis(i)) | (_, GizmoHandle::Plane(i)) => {
+                intersect_plane(origin, axes[i], ray)
+            }
+        }
+    }
+
+    fn apply(&self, drag: &Drag, axes: [Vec3; 3], ray: &Ray) -> Option<Transform> {
+        let origin = drag.start.translation;
+        let current = self.anchor(drag.handle, origin, axes, ray)?;
+        let mut transform = drag.start;
+
+        match (self.mode, drag.handle) {
+            (GizmoMode::Translate, GizmoHandle::Axis(i)) => {
+                let delta = snap(current.x - drag.anchor.x, self.translate_snap);
+                transform.translation += axes[i] * delta;
+            }
+            (GizmoMode::Translate, GizmoHandle::Plane(_)) => {
+                let delta = current - drag.anchor;
+                transform.translation += Vec3::new(
+                    snap(delta.x, self.translate_snap),
+                    snap(delta.y, self.translate_snap),
+                    snap(delta.z, self.translate_snap),
+                );
+            }
+            (GizmoMode::Rotate, GizmoHandle::Axis(i))
+            | (GizmoMode::Rotate, GizmoHandle::Plane(i)) => {
+                let from = (drag.anchor - origin).normalize_or_zero();
+                let to = (current - origin).normalize_or_zero();
+                let angle = axes[i].dot(from.cross(to)).atan2(from.dot(to));
+                let angle = snap(angle.to_degrees(), self.rotate_snap).to_radians();
+                transform.rotation =
+                    (Quat::from_axis_angle(axes[i], angle) * drag.start.rotation).normalize();
+            }
+            (GizmoMode::Scale, GizmoHandle::Axis(i))
+            | (GizmoMode::Scale, GizmoHandle::Plane(i)) => {
+                if drag.anchor.x.abs() < f32::EPSILON {
+                    return None;
+                }
+                let factor = snap(current.x / drag.anchor.x, self.scale_snap);
+                transform.scale[i] = drag.start.scale[i] * factor;
+            }
+        }
+        Some(transform)
+    }
+
+    fn draw(&self, gizmos: &mut Gizmos, origin: Vec3, axes: [Vec3; 3], size: f32) {
+        let color = |handle, i: usize| {
+            let active = self.drag.map(|drag| drag.handle).or(self.hovered);
+            if active == Some(handle) {
+                HOVER_COLOR
+            } else {
+                COLORS[i]
+            }
+        };
+
+        for (i, axis) in axes.iter().enumerate() {
+            let axis_color = color(GizmoHandle::Axis(i), i);
+            match self.mode {
+                GizmoMode::Translate => {
+                    gizmos.overlay_line(origin, origin + *axis * size, axis_color);
+                    let (a, b) = (axes[(i + 1) % 3], axes[(i + 2) % 3]);
+                    let plane_color = color(GizmoHandle::Plane(i), i);
+                    let corners = [(0.2, 0.2), (0.4, 0.2), (0.4, 0.4), (0.2, 0.4)]
+                        .map(|(u, v)| origin + (a * u + b * v) * size);
+                    for j in 0..4 {
+                        gizmos.overlay_line(corners[j], corners[(j + 1) % 4], plane_color);
+                    }
+                }
+                GizmoMode::Rotate => gizmos.overlay_circle(origin, *axis, size, axis_color),
+                GizmoMode::Scale => {
+                    let end = origin + *axis * size;
+                    gizmos.overlay_line(origin, end, axis_color);
+                    gizmos.overlay_circle(end, *axis, size * 0.05, axis_color);
+                }
+            }
+        }
+    }
+}
+
+pub fn update_transform_gizmo(world: &mut World) {
+    let _ = world.try_resource_scope(|world, gizmo: &mut TransformGizmo| {
+        if gizmo.enabled {
+            update(world, gizmo);
+        } else {
+            gizmo.drag = None;
+            gizmo.hovered = None;
+        }
+    });
+}
+
+fn update(world: &mut World, gizmo: &mut TransformGizmo) {
+    let (Some(camera), Some(window), Some(input)) = (
+        main_camera(world),
+        world.get_resource::<WindowSettings>(),
+        world.get_resource::<Input>(),
+    ) else {
+        return;
+    };
+    let camera = camera.clone();
+    let viewport = window.size();
+    let cursor = input.cursor_position();
+    let dragging = input.mouse_pressed(MouseButton::Left);
+    let clicked = input.mouse_just_pressed(MouseButton::Left);
+
+    if gizmo.hotkeys && gizmo.drag.is_none() && !crate::console::is_typing(world) {
+        for (key, mode) in [
+            (KeyCode::KeyW, GizmoMode::Translate),
+            (KeyCode::KeyE, GizmoMode::Rotate),
+            (KeyCode::KeyR, GizmoMode::Scale),
+        ] {
+            if input.just_pressed(key) {
+                gizmo.mode = mode;
+            }
+        }
+    }
+
+    let Some((entity, transform)) = world
+        .query::<Selected>()
+        .into_iter()
+        .find_map(|(entity, _)| Some((entity, transform::world_transform(world, entity)?)))
+    else {
+        gizmo.drag = None;
+        gizmo.hovered = None;
+        return;
+    };
+
+    let origin = gizmo
+        .drag
+        .map_or(transform.translation, |drag| drag.start.translation);
+    let axes = handle_axes(gizmo.mode, &gizmo.drag.map_or(transform, |drag| drag.start));
+    let size = camera.pos.distance(origin) * gizmo.size;
+
+    if let Some(cursor) = cursor
+        && let Some(ray) = camera.viewport_to_world(Viewport::full(viewport), cursor)
+    {
+        let frame = Frame {
+            camera: &camera,
+            viewport,
+            ray,
+            cursor,
+        };
+
+        match gizmo.drag {
+            Some(drag) if dragging && drag.entity == entity => {
+                if let Some(updated) = gizmo.apply(&drag, axes, &frame.ray) {
+                    transform::set_world_transform(world, entity, updated);
+                }
+            }
+            Some(_) => gizmo.drag = None,
+            None => {
+                gizmo.hovered = gizmo.pick(&frame, origin, axes, size);
+                if clicked
+                    && let Some(handle) = gizmo.hovered
+                    && let Some(anchor) = gizmo.anchor(handle, origin, axes, &frame.ray)
+                {
+                    gizmo.drag = Some(Drag {
+                        entity,
+                        handle,
+                        start: transform,
+                        anchor,
+                    });
+                }
+            }
+        }
+    }
+
+    // draw at the post-drag position so the handles follow the entity
+    let Some(transform) = transform::world_transform(world, entity) else {
+        return;
+    };
+    let axes = handle_axes(gizmo.mode, &transform);
+    if let Some(gizmos) = world.get_resource_mut::<Gizmos>() {
+        gizmo.draw(gizmos, transform.translation, axes, size);
+    }
+}
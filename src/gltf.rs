@@ -0,0 +1,513 @@
+// glTF 2.0 models, from .gltf files with their buffers beside them or inlined
+// as base64, and from binary .glb files. Every triangle primitive reachable
+// from the default scene is baked into one Mesh with its node's transform,
+// each primitive its own submesh, its material slot the glTF material index.
+// Positions, normals, the first two uv sets and COLOR_0 are read, skins,
+// morph targets, animations, cameras and sparse accessors are not.
+
+use std::path::Path;
+
+use glam::{Mat3, Mat4, Quat, Vec3};
+use serde_json::Value;
+
+use crate::{
+    Vertex,
+    mesh::{Mesh, Submesh},
+};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const JSON_CHUNK: u32 = 0x4E4F534A;
+const BIN_CHUNK: u32 = 0x004E4942;
+const TRIANGLES: u64 = 4;
+
+impl Mesh {
+    pub fn from_gltf(path: &str) -> anyhow::Result<Self> {
+        crate::error::ensure_exists(path)?;
+        let bytes = std::fs::read(path)?;
+        Self::from_gltf_bytes(&bytes, Path::new(path).parent())
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", path, e))
+    }
+
+    // `base` is where external buffers are looked up, None when there's no
+    // file system, like for files dropped on the web
+    pub fn from_gltf_bytes(bytes: &[u8], base: Option<&Path>) -> anyhow::Result<Self> {
+        let (json, bin) = if bytes.starts_with(GLB_MAGIC) {
+            split_glb(bytes)?
+        } else {
+            (bytes, None)
+        };
+        let document: Value = serde_json::from_slice(json)?;
+        let buffers = array(&document, "buffers")
+            .iter()
+            .map(|buffer| load_buffer(buffer, bin, base))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let gltf = Gltf {
+            document: &document,
+            buffers,
+        };
+
+        let mut mesh = Mesh::default();
+        let scene = document["scene"].as_u64().unwrap_or(0) as usize;
+        let roots: Vec<usize> = match array(&document, "scenes").get(scene) {
+            Some(scene) => indices(&scene["nodes"]),
+            // without scenes every node that isn't a child is a root
+            None => {
+                let nodes = array(&document, "nodes");
+                let children: Vec<usize> = nodes
+                    .iter()
+                    .flat_map(|node| indices(&node["children"]))
+                    .collect();
+                (0..nodes.len())
+                    .filter(|node| !children.contains(node))
+                    .collect()
+            }
+        };
+        for root in roots {
+            gltf.add_node(&mut mesh, root, Mat4::IDENTITY, 0)?;
+        }
+        // colors only when some primitive had them
+        if mesh.colors.iter().all(|&color| color == [1.0; 4]) {
+            mesh.colors.clear();
+        }
+        Ok(mesh)
+    }
+}
+
+struct Gltf<'a> {
+    document: &'a Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Gltf<'_> {
+    fn add_node(
+        &self,
+        mesh: &mut Mesh,
+        node: usize,
+        parent: Mat4,
+        depth: usize,
+    ) -> anyhow::Result<()> {
+        // a node can't be its own ancestor, this only guards broken files
+        if depth > 64 {
+            anyhow::bail!("Nodes nested too deeply");
+        }
+        let Some(json) = array(self.document, "nodes").get(node) else {
+            anyhow::bail!("Node {} doesn't exist", node);
+        };
+        let matrix = parent * node_matrix(json);
+        if let Some(index) = json["mesh"].as_u64() {
+            let Some(gltf_mesh) = array(self.document, "meshes").get(index as usize) else {
+                anyhow::bail!("Mesh {} doesn't exist", index);
+            };
+            for primitive in array(gltf_mesh, "primitives") {
+                if primitive["mode"].as_u64().unwrap_or(TRIANGLES) != TRIANGLES {
+                    log::warn!("Skipping a glTF primitive that isn't a triangle list");
+                    continue;
+                }
+                self.add_primitive(mesh, primitive, matrix)?;
+            }
+        }
+        for child in indices(&json["children"]) {
+            self.add_node(mesh, child, matrix, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn add_primitive(
+        &self,
+        mesh: &mut Mesh,
+        primitive: &Value,
+        matrix: Mat4,
+    ) -> anyhow::Result<()> {
+        let attributes = &primitive["attributes"];
+        // every attribute has a value per vertex with at least `components` in it
+        let attribute = |name: &str, components: usize, count: Option<usize>| {
+            let Some(accessor) = attributes[name].as_u64() else {
+                return Ok(None);
+            };
+            let values = self.read_floats(accessor as usize)?;
+            if count.is_some_and(|count| values.len() != count)
+                || values.iter().any(|value| value.len() < components)
+            {
+                anyhow::bail!("{} doesn't match the positions", name);
+            }
+            Ok(Some(values))
+        };
+        let positions = attribute("POSITION", 3, None)?
+            .ok_or_else(|| anyhow::anyhow!("Primitive without positions"))?;
+        let count = Some(positions.len());
+        let normals = attribute("NORMAL", 3, count)?;
+        let uvs = attribute("TEXCOORD_0", 2, count)?;
+        let second_uvs = attribute("TEXCOORD_1", 2, count)?;
+        let colors = attribute("COLOR_0", 3, count)?;
+
+        let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+        let first = mesh.vertices.len() as u32;
+        for (i, position) in positions.iter().enumerate() {
+            let position = matrix.transform_point3(vec3(position));
+            let normal = normals.as_ref().map_or(Vec3::Y, |normals| {
+                (normal_matrix * vec3(&normals[i])).normalize_or(Vec3::Y)
+            });
+            let uv = uvs.as_ref().map_or([0.0; 2], |uvs| [uvs[i][0], uvs[i][1]]);
+            let second_uv = second_uvs.as_ref().map_or(uv, |uvs| [uvs[i][0], uvs[i][1]]);
+            mesh.vertices.push(Vertex {
+                position: position.extend(1.0).to_array(),
+                tex_coords: [uv, second_uv],
+                normal: normal.to_array(),
+            });
+            let color = colors.as_ref().map_or([1.0; 4], |colors| {
+                let color = &colors[i];
+                [
+                    color[0],
+                    color[1],
+                    color[2],
+                    color.get(3).copied().unwrap_or(1.0),
+                ]
+            });
+            mesh.colors.push(color);
+        }
+
+        let mut corners: Vec<u32> = match primitive["indices"].as_u64() {
+            Some(accessor) => self.read_indices(accessor as usize)?,
+            None => (0..positions.len() as u32).collect(),
+        };
+        if let Some(&out_of_range) = corners
+            .iter()
+            .find(|&&index| index as usize >= positions.len())
+        {
+            anyhow::bail!("Index {} out of range", out_of_range);
+        }
+        // a mirroring transform turns the triangles inside out
+        if matrix.determinant() < 0.0 {
+            for triangle in corners.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+        let start = mesh.indices.len() as u32;
+        mesh.indices.extend(
+            corners
+                .iter()
+                .take(corners.len() / 3 * 3)
+                .map(|index| first + index),
+        );
+        mesh.submeshes.push(Submesh {
+            range: start..mesh.indices.len() as u32,
+            material: primitive["material"].as_u64().unwrap_or(0) as usize,
+        });
+        Ok(())
+    }
+
+    // the accessor's bytes, its element count and the stride between elements
+    fn view(&self, accessor: &Value, element_size: usize) -> anyhow::Result<(&[u8], usize, usize)> {
+        if !accessor["sparse"].is_null() {
+            anyhow::bail!("Sparse accessors aren't supported");
+        }
+        let count = accessor["count"].as_u64().unwrap_or(0) as usize;
+        let Some(view) = accessor["bufferView"].as_u64() else {
+            anyhow::bail!("Accessors without a buffer view aren't supported");
+        };
+        let Some(view) = array(self.document, "bufferViews").get(view as usize) else {
+            anyhow::bail!("Buffer view {} doesn't exist", view);
+        };
+        let buffer = view["buffer"].as_u64().unwrap_or(0) as usize;
+        let Some(buffer) = self.buffers.get(buffer) else {
+            anyhow::bail!("Buffer {} doesn't exist", buffer);
+        };
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize
+            + accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let stride = view["byteStride"]
+            .as_u64()
+            .map_or(element_size, |stride| stride as usize);
+        let end = match count {
+            0 => offset,
+            count => offset + stride * (count - 1) + element_size,
+        };
+        let Some(bytes) = buffer.get(offset..end) else {
+            anyhow::bail!("Accessor runs past the end of its buffer");
+        };
+        Ok((bytes, count, stride))
+    }
+
+    // floats, or normalized integers scaled into 0..1
+    fn read_floats(&self, accessor: usize) -> anyhow::Result<Vec<Vec<f32>>> {
+        let Some(accessor) = array(self.document, "accessors").get(accessor) else {
+            anyhow::bail!("Accessor {} doesn't exist", accessor);
+        };
+        let components = match accessor["type"].as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            other => anyhow::bail!("Unsupported accessor type {:?}", other),
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+        let (size, read): (usize, fn(&[u8]) -> f32) = match accessor["componentType"].as_u64() {
+            Some(5126) => (4, |bytes| f32::from_le_bytes(bytes.try_into().unwrap())),
+            Some(5121) if normalized => (1, |bytes| bytes[0] as f32 / 255.0),
+            Some(5123) if normalized => (2, |bytes| {
+                u16::from_le_bytes(bytes.try_into().unwrap()) as f32 / 65535.0
+            }),
+            other => anyhow::bail!("Unsupported component type {:?}", other),
+        };
+        let (bytes, count, stride) = self.view(accessor, size * components)?;
+        Ok((0..count)
+            .map(|element| {
+                (0..components)
+                    .map(|component| {
+                        let start = element * stride + component * size;
+                        read(&bytes[start..start + size])
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn read_indices(&self, accessor: usize) -> anyhow::Result<Vec<u32>> {
+        let Some(accessor) = array(self.document, "accessors").get(accessor) else {
+            anyhow::bail!("Accessor {} doesn't exist", accessor);
+        };
+        let (size, read): (usize, fn(&[u8]) -> u32) = match accessor["componentType"].as_u64() {
+            Some(5121) => (1, |bytes| bytes[0] as u32),
+            Some(5123) => (2, |bytes| {
+                u16::from_le_bytes(bytes.try_into().unwrap()) as u32
+            }),
+            Some(5125) => (4, |bytes| u32::from_le_bytes(bytes.try_into().unwrap())),
+            other => anyhow::bail!("Unsupported index type {:?}", other),
+        };
+        let (bytes, count, stride) = self.view(accessor, size)?;
+        Ok((0..count)
+            .map(|element| read(&bytes[element * stride..element * stride + size]))
+            .collect())
+    }
+}
+
+// the json and binary chunks of a .glb
+fn split_glb(bytes: &[u8]) -> anyhow::Result<(&[u8], Option<&[u8]>)> {
+    let word = |offset: usize| -> anyhow::Result<u32> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .ok_or_else(|| anyhow::anyhow!("Truncated glb"))
+    };
+    if word(4)? != 2 {
+        anyhow::bail!("Only glTF 2.0 is supported");
+    }
+    let length = (word(8)? as usize).min(bytes.len());
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let (chunk_length, kind) = (word(offset)? as usize, word(offset + 4)?);
+        let Some(data) = bytes.get(offset + 8..offset + 8 + chunk_length) else {
+            anyhow::bail!("Truncated glb");
+        };
+        chunks.push((kind, data));
+        offset += 8 + chunk_length;
+    }
+    let json = chunks
+        .iter()
+        .find(|(kind, _)| *kind == JSON_CHUNK)
+        .map(|(_, data)| *data)
+        .ok_or_else(|| anyhow::anyhow!("glb without a json chunk"))?;
+    let bin = chunks
+        .iter()
+        .find(|(kind, _)| *kind == BIN_CHUNK)
+        .map(|(_, data)| *data);
+    Ok((json, bin))
+}
+
+fn load_buffer(buffer: &Value, bin: Option<&[u8]>, base: Option<&Path>) -> anyhow::Result<Vec<u8>> {
+    let Some(uri) = buffer["uri"].as_str() else {
+        // the glb's own binary chunk
+        return bin
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| anyhow::anyhow!("Buffer without a uri"));
+    };
+    if let Some(data) = uri.strip_prefix("data:") {
+        let Some((_, encoded)) = data.split_once(";base64,") else {
+            anyhow::bail!("Only base64 data uris are supported");
+        };
+        return decode_base64(encoded);
+    }
+    let Some(base) = base else {
+        anyhow::bail!("Can't read {} without a file system", uri);
+    };
+    let path = base.join(uri);
+    std::fs::read(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+fn decode_base64(encoded: &str) -> anyhow::Result<Vec<u8>> {
+    let value = |byte: u8| -> anyhow::Result<u32> {
+        Ok(match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => anyhow::bail!("Invalid base64"),
+        } as u32)
+    };
+    let digits: Vec<u8> = encoded
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace() && *byte != b'=')
+        .collect();
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for group in digits.chunks(4) {
+        let mut bits = 0;
+        for (i, &digit) in group.iter().enumerate() {
+            bits |= value(digit)? << (18 - 6 * i);
+        }
+        let decoded = bits.to_be_bytes();
+        bytes.extend_from_slice(&decoded[1..group.len()]);
+    }
+    Ok(bytes)
+}
+
+fn node_matrix(node: &Value) -> Mat4 {
+    if let Some(matrix) = floats(&node["matrix"]).filter(|matrix| matrix.len() == 16) {
+        return Mat4::from_cols_slice(&matrix);
+    }
+    let translation = floats(&node["translation"])
+        .filter(|values| values.len() == 3)
+        .map_or(Vec3::ZERO, |values| vec3(&values));
+    let rotation = floats(&node["rotation"])
+        .filter(|values| values.len() == 4)
+        .map_or(Quat::IDENTITY, |values| Quat::from_slice(&values));
+    let scale = floats(&node["scale"])
+        .filter(|values| values.len() == 3)
+        .map_or(Vec3::ONE, |values| vec3(&values));
+    Mat4::from_scale_rotation_translation(scale, rotation, translation)
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value[key].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn indices(value: &Value) -> Vec<usize> {
+    value
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_u64().map(|index| index as usize))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn floats(value: &Value) -> Option<Vec<f32>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|value| value.as_f64().map(|value| value as f32))
+        .collect()
+}
+
+fn vec3(values: &[f32]) -> Vec3 {
+    Vec3::new(values[0], values[1], values[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a triangle in the xy plane, u16 indices after the positions
+    fn triangle_buffer() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in [0u16, 1, 2, 0] {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn triangle_document(buffer: Value, node: Value) -> Value {
+        serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [node],
+            "meshes": [{ "primitives": [{
+                "attributes": { "POSITION": 0 },
+                "indices": 1,
+                "material": 2,
+            }] }],
+            "buffers": [buffer],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 6 },
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+                { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" },
+            ],
+        })
+    }
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut encoded = String::new();
+        for group in bytes.chunks(3) {
+            let mut word = [0; 4];
+            word[1..=group.len()].copy_from_slice(group);
+            let bits = u32::from_be_bytes(word);
+            for i in 0..=group.len() {
+                encoded.push(DIGITS[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        while !encoded.len().is_multiple_of(4) {
+            encoded.push('=');
+        }
+        encoded
+    }
+
+    #[test]
+    fn inlined_buffers_and_node_transforms() {
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            encode_base64(&triangle_buffer())
+        );
+        let document = triangle_document(
+            serde_json::json!({ "uri": uri, "byteLength": 44 }),
+            serde_json::json!({ "mesh": 0, "translation": [0.0, 0.0, 5.0], "scale": [2.0, 2.0, 2.0] }),
+        );
+        let mesh = Mesh::from_gltf_bytes(&serde_json::to_vec(&document).unwrap(), None).unwrap();
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(
+            mesh.submeshes,
+            [Submesh {
+                range: 0..3,
+                material: 2
+            }]
+        );
+        assert_eq!(mesh.vertices[1].position, [2.0, 0.0, 5.0, 1.0]);
+        assert!(mesh.colors.is_empty());
+    }
+
+    #[test]
+    fn glb_with_a_mirrored_node() {
+        let mut json = serde_json::to_vec(&triangle_document(
+            serde_json::json!({ "byteLength": 44 }),
+            serde_json::json!({ "mesh": 0, "scale": [-1.0, 1.0, 1.0] }),
+        ))
+        .unwrap();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let bin = triangle_buffer();
+        let mut glb = Vec::new();
+        glb.extend_from_slice(GLB_MAGIC);
+        for word in [2, (12 + 8 + json.len() + 8 + bin.len()) as u32] {
+            glb.extend_from_slice(&word.to_le_bytes());
+        }
+        for (kind, data) in [(JSON_CHUNK, &json), (BIN_CHUNK, &bin)] {
+            glb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            glb.extend_from_slice(&kind.to_le_bytes());
+            glb.extend_from_slice(data);
+        }
+
+        let mesh = Mesh::from_gltf_bytes(&glb, None).unwrap();
+        assert_eq!(mesh.vertices[1].position, [-1.0, 0.0, 0.0, 1.0]);
+        // flipped so the face still points the same way around
+        assert_eq!(mesh.indices, [0, 2, 1]);
+        assert!(Mesh::from_gltf_bytes(&glb[..30], None).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use glam::Mat4;
+
+use crate::{
+    ecs::{
+        component::Component,
+        entity::{Entity, MapEntities},
+        world::World,
+    },
+    transform::{GlobalTransform, Transform},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+impl Component for Parent {}
+
+// a child moved without its parent becomes a root
+impl MapEntities for Parent {
+    fn map_entities(&mut self, map: &dyn Fn(Entity) -> Option<Entity>) -> bool {
+        map(self.0).map(|parent| self.0 = parent).is_some()
+    }
+}
+
+// kept in sync with Parent by set_parent, don't edit by hand
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<Entity>);
+
+impl Component for Children {}
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, map: &dyn Fn(Entity) -> Option<Entity>) -> bool {
+        self.0 = self.0.iter().filter_map(|&child| map(child)).collect();
+        !self.0.is_empty()
+    }
+}
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+pub fn parent(world: &World, entity: Entity) -> Option<Entity> {
+    world.get_component::<Parent>(entity).map(|parent| parent.0)
+}
+
+pub fn children(world: &World, entity: Entity) -> Vec<Entity> {
+    world
+        .get_component::<Children>(entity)
+        .map(|children| children.0.clone())
+        .unwrap_or_default()
+}
+
+// walks up from the entity's parent to the root
+pub fn ancestors(world: &World, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+    std::iter::successors(parent(world, entity), move |&entity| parent(world, entity))
+}
+
+pub fn set_parent(world: &mut World, child: Entity, parent: Option<Entity>) {
+    if parent.is_some_and(|parent| parent == child || ancestors(world, parent).any(|e| e == child))
+    {
+        log::warn!("Ignoring parent change that would create a cycle");
+        return;
+    }
+
+    if let Some(old) = self::parent(world, child)
+        && let Some(children) = world.get_component_mut::<Children>(old)
+    {
+        children.0.retain(|&entity| entity != child);
+    }
+
+    match parent {
+        Some(parent) => {
+            world.add_component(child, Parent(parent));
+            match world.get_component_mut::<Children>(parent) {
+                Some(children) => children.0.push(child),
+                None => world.add_component(parent, Children(vec![child])),
+            }
+        }
+        None => world.remove_component::<Parent>(child),
+    }
+}
+
+pub fn despawn_recursive(world: &mut World, entity: Entity) {
+    for child in children(world, entity) {
+        despawn_recursive(world, child);
+    }
+    set_parent(world, entity, None);
+    world.despawn(entity);
+}
+
+// world space transforms for everything with a Transform, parents first. entities
+// without one pass their parent's on to their children unchanged
+pub fn propagate_transforms(world: &mut World) {
+    let mut stack: Vec<(Entity, Mat4)> = world
+        .query::<Transform>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .chain(
+            world
+                .query::<Children>()
+                .into_iter()
+                .map(|(entity, _)| entity),
+        )
+        .filter(|&entity| parent(world, entity).is_none())
+        .map(|entity| (entity, Mat4::IDENTITY))
+        .collect();
+    stack.sort_by_key(|(entity, _)| entity.index());
+    stack.dedup_by_key(|(entity, _)| *entity);
+
+    while let Some((entity, parent_matrix)) = stack.pop() {
+        let matrix = match world.get_component::<Transform>(entity) {
+            Some(transform) => {
+                let matrix = parent_matrix * transform.compute_matrix();
+                match world.get_component_mut::<GlobalTransform>(entity) {
+                    Some(global) => global.0 = matrix,
+                    None => world.add_component(entity, GlobalTransform(matrix)),
+                }
+                matrix
+            }
+            None => parent_matrix,
+        };
+        for child in children(world, entity) {
+            stack.push((child, matrix));
+        }
+    }
+}
@@ -0,0 +1,469 @@
+// Undo/redo of world mutations. Changes are recorded as invertible commands,
+// optionally grouped into transactions that undo as one step. Commands that
+// fall out of the history, past `max_len`, on a new change after an undo or
+// on `clear`, are discarded with the world at hand: right away from the free
+// functions here, otherwise in Last.
+
+use std::{any::TypeId, collections::VecDeque, fmt::Debug};
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    hierarchy::{self, Children},
+};
+
+pub trait Command: Debug + 'static {
+    fn apply(&mut self, world: &mut World);
+    fn revert(&mut self, world: &mut World);
+
+    // the command can't be undone or redone anymore. `applied` is whether its
+    // change is in the world, false for one that was undone
+    fn discard(&mut self, _world: &mut World, _applied: bool) {}
+
+    fn label(&self) -> String {
+        short_type_name(std::any::type_name::<Self>())
+    }
+}
+
+// a type name without module paths, generics included, so
+// `history::SetComponent<assets::Handle<mesh::Mesh>>` is `SetComponent<Handle<Mesh>>`
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut start = 0;
+    for (i, c) in name.char_indices() {
+        if matches!(c, '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | ';' | '&') {
+            let path = &name[start..i];
+            short.push_str(path.rsplit("::").next().unwrap_or(path));
+            short.push(c);
+            start = i + c.len_utf8();
+        }
+    }
+    let path = &name[start..];
+    short.push_str(path.rsplit("::").next().unwrap_or(path));
+    short
+}
+
+type BoxedComponents = Vec<(TypeId, Box<dyn Component>)>;
+
+// an entity and its descendants with their components taken off. they're
+// left alive, so their slots aren't reused before the components go back
+#[derive(Debug, Default)]
+struct HeldEntities {
+    entities: Vec<(Entity, BoxedComponents)>,
+    // the root's, which loses it from its Children meanwhile
+    parent: Option<Entity>,
+}
+
+impl HeldEntities {
+    fn take(world: &mut World, root: Entity) -> Self {
+        let parent = hierarchy::parent(world, root);
+        let mut entities = Vec::new();
+        let mut stack = vec![root];
+        while let Some(entity) = stack.pop() {
+            stack.extend(hierarchy::children(world, entity));
+            entities.push((entity, world.take_components(entity)));
+        }
+        if let Some(children) =
+            parent.and_then(|parent| world.get_component_mut::<Children>(parent))
+        {
+            children.0.retain(|&child| child != root);
+        }
+        Self { entities, parent }
+    }
+
+    fn restore(&mut self, world: &mut World) {
+        let Some(&(root, _)) = self.entities.first() else {
+            return;
+        };
+        for (entity, components) in self.entities.drain(..) {
+            world.restore_components(entity, components);
+        }
+        if let Some(parent) = self.parent.take().filter(|&parent| world.is_alive(parent)) {
+            match world.get_component_mut::<Children>(parent) {
+                Some(children) => children.0.push(root),
+                None => world.add_component(parent, Children(vec![root])),
+            }
+        }
+    }
+
+    // for good, the components are dropped and the slots freed
+    fn despawn(&mut self, world: &mut World) {
+        for (entity, _) in self.entities.drain(..) {
+            world.despawn(entity);
+        }
+    }
+}
+
+// entity ids stay valid across undo/redo since components are restored in
+// place, along with the entity's children
+#[derive(Debug)]
+pub struct SpawnEntity {
+    pub entity: Entity,
+    held: HeldEntities,
+}
+
+impl SpawnEntity {
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            held: HeldEntities::default(),
+        }
+    }
+}
+
+impl Command for SpawnEntity {
+    fn apply(&mut self, world: &mut World) {
+        self.held.restore(world);
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        self.held = HeldEntities::take(world, self.entity);
+    }
+
+    fn discard(&mut self, world: &mut World, applied: bool) {
+        if !applied {
+            self.held.despawn(world);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DespawnEntity {
+    pub entity: Entity,
+    held: HeldEntities,
+}
+
+impl DespawnEntity {
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            held: HeldEntities::default(),
+        }
+    }
+}
+
+impl Command for DespawnEntity {
+    fn apply(&mut self, world: &mut World) {
+        self.held = HeldEntities::take(world, self.entity);
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        self.held.restore(world);
+    }
+
+    fn discard(&mut self, world: &mut World, applied: bool) {
+        if applied {
+            self.held.despawn(world);
+        }
+    }
+}
+
+// None on either side means the component is absent, so this covers add and remove too
+#[derive(Debug)]
+pub struct SetComponent<T> {
+    pub entity: Entity,
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+impl<T: Component + Clone> SetComponent<T> {
+    pub fn new(entity: Entity, before: Option<T>, after: Option<T>) -> Self {
+        Self {
+            entity,
+            before,
+            after,
+        }
+    }
+
+    fn set(world: &mut World, entity: Entity, value: &Option<T>) {
+        match value {
+            Some(value) => world.add_component(entity, value.clone()),
+            None => world.remove_component::<T>(entity),
+        }
+    }
+}
+
+impl<T: Component + Clone> Command for SetComponent<T> {
+    fn apply(&mut self, world: &mut World) {
+        Self::set(world, self.entity, &self.after);
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        Self::set(world, self.entity, &self.before);
+    }
+
+    fn label(&self) -> String {
+        format!("Set {}", short_type_name(std::any::type_name::<T>()))
+    }
+}
+
+#[derive(Debug)]
+struct Transaction {
+    label: String,
+    commands: Vec<Box<dyn Command>>,
+}
+
+impl Transaction {
+    fn apply(&mut self, world: &mut World) {
+        for command in &mut self.commands {
+            command.apply(world);
+        }
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        for command in self.commands.iter_mut().rev() {
+            command.revert(world);
+        }
+    }
+
+    fn discard(&mut self, world: &mut World, applied: bool) {
+        for command in self.commands.iter_mut().rev() {
+            command.discard(world, applied);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandHistory {
+    pub max_len: usize,
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    open: Option<Transaction>,
+    // dropped but not discarded yet, and whether each was applied
+    dropped: Vec<(Transaction, bool)>,
+}
+
+impl Component for CommandHistory {}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self {
+            max_len: 100,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            dropped: Vec::new(),
+        }
+    }
+}
+
+impl CommandHistory {
+    pub fn begin_transaction(&mut self, label: impl Into<String>) {
+        self.commit_transaction();
+        self.open = Some(Transaction {
+            label: label.into(),
+            commands: Vec::new(),
+        });
+    }
+
+    pub fn commit_transaction(&mut self) {
+        if let Some(transaction) = self.open.take()
+            && !transaction.commands.is_empty()
+        {
+            self.push(transaction);
+        }
+    }
+
+    // records a change that has already been made to the world
+    pub fn record(&mut self, command: impl Command) {
+        match &mut self.open {
+            Some(transaction) => transaction.commands.push(Box::new(command)),
+            None => self.push(Transaction {
+                label: command.label(),
+                commands: vec![Box::new(command)],
+            }),
+        }
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        self.dropped
+            .extend(self.redo.drain(..).map(|transaction| (transaction, false)));
+        self.undo.push_back(transaction);
+        while self.undo.len() > self.max_len {
+            if let Some(oldest) = self.undo.pop_front() {
+                self.dropped.push((oldest, true));
+            }
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo
+            .back()
+            .map(|transaction| transaction.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo
+            .last()
+            .map(|transaction| transaction.label.as_str())
+    }
+
+    // the dropped commands are discarded in Last, or by `history::clear`
+    pub fn clear(&mut self) {
+        let undone = self.redo.drain(..).map(|transaction| (transaction, false));
+        let applied = self.undo.drain(..).chain(self.open.take());
+        self.dropped
+            .extend(undone.chain(applied.map(|transaction| (transaction, true))));
+    }
+}
+
+// lets the commands that fell out of the history clean up after themselves,
+// the entities a despawn kept around are despawned for real
+pub fn discard_dropped(world: &mut World) {
+    let Some(history) = world.get_resource_mut::<CommandHistory>() else {
+        return;
+    };
+    for (mut transaction, applied) in std::mem::take(&mut history.dropped) {
+        transaction.discard(world, applied);
+    }
+}
+
+pub fn clear(world: &mut World) {
+    if let Some(history) = world.get_resource_mut::<CommandHistory>() {
+        history.clear();
+    }
+    discard_dropped(world);
+}
+
+pub fn execute(world: &mut World, mut command: impl Command) {
+    command.apply(world);
+    world.resource_mut::<CommandHistory>().record(command);
+    discard_dropped(world);
+}
+
+pub fn undo(world: &mut World) -> bool {
+    let Some(history) = world.get_resource_mut::<CommandHistory>() else {
+        return false;
+    };
+    history.commit_transaction();
+    let Some(mut transaction) = history.undo.pop_back() else {
+        return false;
+    };
+    transaction.revert(world);
+    world
+        .resource_mut::<CommandHistory>()
+        .redo
+        .push(transaction);
+    true
+}
+
+pub fn redo(world: &mut World) -> bool {
+    let Some(mut transaction) = world
+        .get_resource_mut::<CommandHistory>()
+        .and_then(|history| history.redo.pop())
+    else {
+        return false;
+    };
+    transaction.apply(world);
+    world
+        .resource_mut::<CommandHistory>()
+        .undo
+        .push_back(transaction);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Name(&'static str);
+
+    impl Component for Name {}
+
+    fn world_with(count: usize) -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        world.init_resource::<CommandHistory>();
+        let entities = (0..count)
+            .map(|_| world.spawn().insert(Name("thing")).id())
+            .collect();
+        (world, entities)
+    }
+
+    #[test]
+    fn cleared_despawns_free_their_entities() {
+        let (mut world, entities) = world_with(5);
+        execute(&mut world, DespawnEntity::new(entities[0]));
+        assert_eq!(world.entity_count(), 5);
+        clear(&mut world);
+        assert_eq!(world.entity_count(), 4);
+        assert!(!world.is_alive(entities[0]));
+    }
+
+    #[test]
+    fn evicted_and_overwritten_commands_are_discarded() {
+        let (mut world, entities) = world_with(3);
+        world.resource_mut::<CommandHistory>().max_len = 1;
+        execute(&mut world, DespawnEntity::new(entities[0]));
+        execute(&mut world, DespawnEntity::new(entities[1]));
+        // the first fell off the end
+        assert!(!world.is_alive(entities[0]));
+        assert!(world.is_alive(entities[1]));
+
+        // an undone spawn is dropped from redo by the next change
+        let spawned = world.spawn().insert(Name("new")).id();
+        execute(&mut world, SpawnEntity::new(spawned));
+        assert!(undo(&mut world));
+        execute(
+            &mut world,
+            SetComponent::new(entities[2], None, Some(Name("renamed"))),
+        );
+        assert!(!world.is_alive(spawned));
+        // the second despawn was pushed out by the spawn
+        assert!(!world.is_alive(entities[1]));
+        assert_eq!(world.entity_count(), 1);
+    }
+
+    #[test]
+    fn despawns_take_children_along() {
+        let (mut world, entities) = world_with(3);
+        let (root, child, grandchild) = (entities[0], entities[1], entities[2]);
+        let holder = world.spawn().id();
+        hierarchy::set_parent(&mut world, root, Some(holder));
+        hierarchy::set_parent(&mut world, child, Some(root));
+        hierarchy::set_parent(&mut world, grandchild, Some(child));
+
+        execute(&mut world, DespawnEntity::new(root));
+        assert!(world.query::<Name>().is_empty());
+        assert!(hierarchy::children(&world, holder).is_empty());
+
+        assert!(undo(&mut world));
+        assert_eq!(world.query::<Name>().len(), 3);
+        assert_eq!(hierarchy::children(&world, holder), [root]);
+        assert_eq!(hierarchy::parent(&world, grandchild), Some(child));
+
+        assert!(redo(&mut world));
+        clear(&mut world);
+        assert_eq!(world.entity_count(), 1);
+    }
+
+    #[test]
+    fn labels_keep_generics() {
+        #[derive(Debug, Clone)]
+        struct Tagged<T>(T);
+        impl<T: Debug + 'static> Component for Tagged<T> {}
+
+        assert_eq!(
+            short_type_name("core::option::Option<(alloc::string::String, [f32; 3], &str)>"),
+            "Option<(String, [f32; 3], &str)>"
+        );
+        let (_, entities) = world_with(1);
+        assert_eq!(
+            SetComponent::new(entities[0], None, Some(Name("renamed"))).label(),
+            "Set Name"
+        );
+        let set = SetComponent::new(entities[0], None, Some(Tagged(Name("renamed"))));
+        assert_eq!(set.label(), "Set Tagged<Name>");
+        assert_eq!(
+            Command::label(&DespawnEntity::new(entities[0])),
+            "DespawnEntity"
+        );
+    }
+}
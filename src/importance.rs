@@ -0,0 +1,282 @@
+// Holds the frame time near a target by turning down the work spent on what
+// matters least. A few times a second, everything with an animation, a sound or
+// a 2d light is ranked by how much it matters on screen: nearer counts for
+// more, outside the view or behind colliders for less, and an Importance
+// component scales it by hand. While frames run long the share kept at full
+// detail shrinks, and it grows back once they're fast again. Entities below the
+// full detail share update their animations every few frames, with the time
+// in between, and skip their occlusion rays. Voices past the limit go quiet
+// and pick up where they were, and the 2d lights drawn are the most important
+// ones, the least of them without shadows. Off by default.
+
+use glam::Vec3;
+
+use crate::{
+    camera::{Frustum, main_camera},
+    ecs::{component::Component, entity::Entity, world::World},
+    physics::{self, Ray},
+    time::Time,
+    transform,
+};
+
+#[derive(Debug, Clone)]
+pub struct ImportanceBudget {
+    pub enabled: bool,
+    // seconds
+    pub target_frame_time: f32,
+    // never less than this share at full detail
+    pub min_quality: f32,
+    // most voices that play at once, the least important go quiet
+    pub max_voices: usize,
+    // how often everything is ranked again, in seconds
+    pub rank_interval: f32,
+    // frames between updates below full detail
+    pub reduced_interval: u32,
+    pub minimal_interval: u32,
+    // an importance of 1 this far from the camera counts half as much as up close
+    pub reference_distance: f32,
+    pub offscreen_weight: f32,
+    // per collider between the camera and the entity
+    pub occluded_weight: f32,
+    // share of the ranked entities at full detail, 0..1
+    quality: f32,
+    frame_time: f32,
+    since_ranking: f32,
+    frame: u32,
+    // whether DetailLevels are out there to take back
+    ranked: bool,
+}
+
+impl Component for ImportanceBudget {}
+
+impl Default for ImportanceBudget {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_frame_time: 1.0 / 60.0,
+            min_quality: 0.1,
+            max_voices: 32,
+            rank_interval: 0.25,
+            reduced_interval: 2,
+            minimal_interval: 4,
+            reference_distance: 10.0,
+            offscreen_weight: 0.25,
+            occluded_weight: 0.5,
+            quality: 1.0,
+            frame_time: 1.0 / 60.0,
+            since_ranking: f32::INFINITY,
+            frame: 0,
+            ranked: false,
+        }
+    }
+}
+
+impl ImportanceBudget {
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+
+    // smoothed, what the budget steers by
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time
+    }
+}
+
+// scales how much an entity matters, 1 when it's missing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Importance(pub f32);
+
+impl Component for Importance {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Detail {
+    #[default]
+    Full,
+    Reduced,
+    Minimal,
+}
+
+// written by the budget on everything it ranked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetailLevel {
+    pub detail: Detail,
+    pub importance: f32,
+    // frames between updates, 1 is every frame
+    pub interval: u32,
+    pub audible: bool,
+}
+
+impl Component for DetailLevel {}
+
+impl DetailLevel {
+    // updates are spread over the interval by entity
+    fn is_due(&self, entity: Entity, frame: u32) -> bool {
+        frame
+            .wrapping_add(entity.index() as u32)
+            .is_multiple_of(self.interval.max(1))
+    }
+}
+
+// the time to advance the entity by this frame, None on the frames it skips
+pub(crate) fn frame_delta(world: &World, entity: Entity, delta: f32) -> Option<f32> {
+    let Some(level) = world.get_component::<DetailLevel>(entity) else {
+        return Some(delta);
+    };
+    let frame = world
+        .get_resource::<ImportanceBudget>()
+        .map_or(0, |budget| budget.frame);
+    level
+        .is_due(entity, frame)
+        .then_some(delta * level.interval.max(1) as f32)
+}
+
+#[cfg(feature = "audio")]
+pub(crate) fn is_due(world: &World, entity: Entity) -> bool {
+    frame_delta(world, entity, 0.0).is_some()
+}
+
+#[cfg(feature = "audio")]
+pub(crate) fn is_audible(world: &World, entity: Entity) -> bool {
+    world
+        .get_component::<DetailLevel>(entity)
+        .is_none_or(|level| level.audible)
+}
+
+// what's ranked: anything with work the budget can turn down
+fn candidates(world: &World) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = Vec::new();
+    entities.extend(
+        world
+            .query::<crate::animation::TransformAnimation>()
+            .into_iter()
+            .map(|(entity, _)| entity),
+    );
+    entities.extend(
+        world
+            .query::<crate::animation::MorphAnimation>()
+            .into_iter()
+            .map(|(entity, _)| entity),
+    );
+    #[cfg(feature = "render2d")]
+    entities.extend(
+        world
+            .query::<crate::sprite::SpriteAnimation>()
+            .into_iter()
+            .map(|(entity, _)| entity),
+    );
+    #[cfg(feature = "render2d")]
+    entities.extend(
+        world
+            .query::<crate::sprite::Light2d>()
+            .into_iter()
+            .map(|(entity, _)| entity),
+    );
+    #[cfg(feature = "audio")]
+    entities.extend(
+        world
+            .query::<crate::audio::AudioSource>()
+            .into_iter()
+            .map(|(entity, _)| entity),
+    );
+    entities.sort_by_key(|entity| entity.index());
+    entities.dedup();
+    entities
+}
+
+fn importance(world: &World, budget: &ImportanceBudget, entity: Entity) -> f32 {
+    let weight = world
+        .get_component::<Importance>(entity)
+        .map_or(1.0, |importance| importance.0);
+    let Some(camera) = main_camera(world) else {
+        return weight;
+    };
+    let position = transform::model_matrix(world, entity).w_axis.truncate();
+    let distance = camera.pos.distance(position);
+    let mut importance = weight / (1.0 + distance / budget.reference_distance.max(f32::EPSILON));
+    if !Frustum::from_view_proj(camera.view_proj()).intersects_aabb(position, Vec3::ONE) {
+        return importance * budget.offscreen_weight;
+    }
+    if budget.occluded_weight < 1.0 && distance > f32::EPSILON {
+        let (ray, distance) = Ray::between(camera.pos, position);
+        let occluders = physics::raycast_all(world, &ray, distance)
+            .into_iter()
+            .filter(|hit| hit.entity != entity)
+            .count() as i32;
+        importance *= budget.occluded_weight.powi(occluders);
+    }
+    importance
+}
+
+pub fn update_importance(world: &mut World) {
+    let real_delta = world.get_resource::<Time>().map_or(0.0, Time::real_delta);
+    let budget = world.resource_mut::<ImportanceBudget>();
+    if !budget.enabled {
+        if std::mem::take(&mut budget.ranked) {
+            budget.quality = 1.0;
+            budget.since_ranking = f32::INFINITY;
+            let ranked: Vec<Entity> = world
+                .query::<DetailLevel>()
+                .into_iter()
+                .map(|(entity, _)| entity)
+                .collect();
+            for entity in ranked {
+                world.remove_component::<DetailLevel>(entity);
+            }
+        }
+        return;
+    }
+    budget.frame = budget.frame.wrapping_add(1);
+    // a hitch shouldn't throw everything to minimal at once
+    budget.frame_time += (real_delta.min(0.25) - budget.frame_time) * 0.1;
+    let pressure = budget.frame_time / budget.target_frame_time.max(1e-4);
+    if pressure > 1.05 {
+        budget.quality -= real_delta * 0.5;
+    } else if pressure < 0.9 {
+        budget.quality += real_delta * 0.25;
+    }
+    budget.quality = budget
+        .quality
+        .clamp(budget.min_quality.clamp(0.0, 1.0), 1.0);
+    budget.since_ranking += real_delta;
+    if budget.since_ranking < budget.rank_interval {
+        return;
+    }
+    budget.since_ranking = 0.0;
+    budget.ranked = true;
+    let budget = budget.clone();
+
+    let mut ranked: Vec<(Entity, f32)> = candidates(world)
+        .into_iter()
+        .map(|entity| (entity, importance(world, &budget, entity)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let full = (ranked.len() as f32 * budget.quality).ceil() as usize;
+    let reduced = full + (ranked.len() - full.min(ranked.len())).div_ceil(2);
+    let mut voices = 0;
+    for (rank, (entity, importance)) in ranked.into_iter().enumerate() {
+        let (detail, interval) = if rank < full {
+            (Detail::Full, 1)
+        } else if rank < reduced {
+            (Detail::Reduced, budget.reduced_interval.max(1))
+        } else {
+            (Detail::Minimal, budget.minimal_interval.max(1))
+        };
+        #[cfg(feature = "audio")]
+        let voice = world
+            .get_component::<crate::audio::AudioSource>(entity)
+            .is_some_and(|source| source.playing);
+        #[cfg(not(feature = "audio"))]
+        let voice = false;
+        let audible = !voice || voices < budget.max_voices;
+        voices += voice as usize;
+        world.add_component(
+            entity,
+            DetailLevel {
+                detail,
+                importance,
+                interval,
+                audible,
+            },
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use glam::Vec2;
+use wgpu::naga::FastHashSet;
+use winit::{
+    event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+pub use winit::event::Ime;
+
+use crate::ecs::component::Component;
+
+// phones have no mouse, touch drives the cursor there unless turned off
+const TOUCH_FIRST: bool = cfg!(any(target_os = "android", target_os = "ios"));
+
+// a finger on the screen, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Touch {
+    pub id: u64,
+    pub position: Vec2,
+    // where it went down
+    pub start: Vec2,
+    // movement this frame
+    pub delta: Vec2,
+}
+
+#[derive(Debug)]
+struct Touches {
+    active: Vec<Touch>,
+    started: Vec<Touch>,
+    ended: Vec<Touch>,
+    // the first finger down drives the cursor and the left button, so mouse
+    // driven code works on touch screens
+    emulate_mouse: bool,
+    mouse_touch: Option<u64>,
+}
+
+impl Default for Touches {
+    fn default() -> Self {
+        Self {
+            active: Vec::new(),
+            started: Vec::new(),
+            ended: Vec::new(),
+            emulate_mouse: TOUCH_FIRST,
+            mouse_touch: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Input {
+    keys: FastHashSet<KeyCode>,
+    keys_pressed: FastHashSet<KeyCode>,
+    keys_released: FastHashSet<KeyCode>,
+    buttons: FastHashSet<MouseButton>,
+    buttons_pressed: FastHashSet<MouseButton>,
+    buttons_released: FastHashSet<MouseButton>,
+    cursor_position: Option<Vec2>,
+    cursor_delta: Vec2,
+    mouse_motion: Vec2,
+    scroll: Vec2,
+    scroll_lines: Vec2,
+    scroll_pixels: Vec2,
+    raw_scroll: Vec2,
+    raw_buttons: FastHashSet<u32>,
+    raw_buttons_pressed: FastHashSet<u32>,
+    raw_buttons_released: FastHashSet<u32>,
+    text: String,
+    ime_preedit: Option<(String, Option<(usize, usize)>)>,
+    touches: Touches,
+}
+
+impl Component for Input {}
+
+impl Input {
+    pub fn pressed(&self, key: KeyCode) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn just_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn just_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    // in physical pixels from the top left of the window
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    pub fn cursor_delta(&self) -> Vec2 {
+        self.cursor_delta
+    }
+
+    // unaccelerated device motion, keeps coming while the cursor is captured
+    pub fn mouse_motion(&self) -> Vec2 {
+        self.mouse_motion
+    }
+
+    // in lines, pixel scrolling is converted at 16 pixels a line
+    pub fn scroll(&self) -> Vec2 {
+        self.scroll
+    }
+
+    // scroll split by the unit the device reported it in
+    pub fn scroll_lines(&self) -> Vec2 {
+        self.scroll_lines
+    }
+
+    pub fn scroll_pixels(&self) -> Vec2 {
+        self.scroll_pixels
+    }
+
+    // straight from the device, even when the window isn't under the cursor
+    pub fn raw_scroll(&self) -> Vec2 {
+        self.raw_scroll
+    }
+
+    // raw button ids are platform specific, use these for buttons winit doesn't name
+    pub fn raw_button_pressed(&self, button: u32) -> bool {
+        self.raw_buttons.contains(&button)
+    }
+
+    pub fn raw_button_just_pressed(&self, button: u32) -> bool {
+        self.raw_buttons_pressed.contains(&button)
+    }
+
+    pub fn raw_button_just_released(&self, button: u32) -> bool {
+        self.raw_buttons_released.contains(&button)
+    }
+
+    // characters typed this frame, with layout and modifiers applied
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // text being composed with an input method, not committed yet. the range
+    // is the composition cursor in bytes
+    pub fn ime_preedit(&self) -> Option<(&str, Option<(usize, usize)>)> {
+        self.ime_preedit
+            .as_ref()
+            .map(|(text, cursor)| (text.as_str(), *cursor))
+    }
+
+    // every finger on the screen, oldest first
+    pub fn touches(&self) -> &[Touch] {
+        &self.touches.active
+    }
+
+    pub fn touch(&self, id: u64) -> Option<&Touch> {
+        self.touches.active.iter().find(|touch| touch.id == id)
+    }
+
+    pub fn touches_started(&self) -> &[Touch] {
+        &self.touches.started
+    }
+
+    // lifted or cancelled this frame, with their last position
+    pub fn touches_ended(&self) -> &[Touch] {
+        &self.touches.ended
+    }
+
+    pub fn touch_emulates_mouse(&self) -> bool {
+        self.touches.emulate_mouse
+    }
+
+    pub fn set_touch_emulates_mouse(&mut self, emulate: bool) {
+        self.touches.emulate_mouse = emulate;
+    }
+
+    pub(crate) fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if event.state == ElementState::Pressed
+                    && let Some(text) = &event.text
+                {
+                    self.text.extend(text.chars().filter(|c| !c.is_control()));
+                }
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return;
+                };
+                match event.state {
+                    ElementState::Pressed if !event.repeat => {
+                        self.keys.insert(code);
+                        self.keys_pressed.insert(code);
+                    }
+                    ElementState::Pressed => {}
+                    ElementState::Released => {
+                        self.keys.remove(&code);
+                        self.keys_released.insert(code);
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.buttons.insert(*button);
+                    self.buttons_pressed.insert(*button);
+                }
+                ElementState::Released => {
+                    self.buttons.remove(button);
+                    self.buttons_released.insert(*button);
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vec2::new(position.x as f32, position.y as f32);
+                if let Some(previous) = self.cursor_position {
+                    self.cursor_delta += position - previous;
+                }
+                self.cursor_position = Some(position);
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => {
+                    self.scroll_lines += Vec2::new(*x, *y);
+                    self.scroll += Vec2::new(*x, *y);
+                }
+                MouseScrollDelta::PixelDelta(delta) => {
+                    let delta = Vec2::new(delta.x as f32, delta.y as f32);
+                    self.scroll_pixels += delta;
+                    self.scroll += delta / 16.0;
+                }
+            },
+            WindowEvent::Ime(ime) => match ime {
+                Ime::Preedit(text, _) if text.is_empty() => self.ime_preedit = None,
+                Ime::Preedit(text, cursor) => self.ime_preedit = Some((text.clone(), *cursor)),
+                // committed text arrives the same way typed characters do
+                Ime::Commit(text) => {
+                    self.ime_preedit = None;
+                    self.text.push_str(text);
+                }
+                Ime::Enabled | Ime::Disabled => self.ime_preedit = None,
+            },
+            WindowEvent::Touch(touch) => {
+                let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+                self.touch_input(touch.id, touch.phase, position);
+            }
+            WindowEvent::Focused(false) => {
+                self.keys_released.extend(self.keys.drain());
+                self.buttons_released.extend(self.buttons.drain());
+                self.raw_buttons_released.extend(self.raw_buttons.drain());
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn handle_device_event(&mut self, event: &DeviceEvent) {
+        match event {
+            DeviceEvent::MouseMotion { delta } => {
+                self.mouse_motion += Vec2::new(delta.0 as f32, delta.1 as f32);
+            }
+            DeviceEvent::MouseWheel { delta } => {
+                self.raw_scroll += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        Vec2::new(delta.x as f32, delta.y as f32) / 16.0
+                    }
+                };
+            }
+            DeviceEvent::Button { button, state } => match state {
+                ElementState::Pressed => {
+                    self.raw_buttons.insert(*button);
+                    self.raw_buttons_pressed.insert(*button);
+                }
+                ElementState::Released => {
+                    self.raw_buttons.remove(button);
+                    self.raw_buttons_released.insert(*button);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn touch_input(&mut self, id: u64, phase: TouchPhase, position: Vec2) {
+        let touches = &mut self.touches;
+        let index = touches.active.iter().position(|touch| touch.id == id);
+        match (phase, index) {
+            (TouchPhase::Started, None) => {
+                let touch = Touch {
+                    id,
+                    position,
+                    start: position,
+                    delta: Vec2::ZERO,
+                };
+                touches.active.push(touch);
+                touches.started.push(touch);
+            }
+            (TouchPhase::Moved, Some(index)) => {
+                let touch = &mut touches.active[index];
+                touch.delta += position - touch.position;
+                touch.position = position;
+            }
+            (TouchPhase::Ended | TouchPhase::Cancelled, Some(index)) => {
+                let mut touch = touches.active.remove(index);
+                touch.delta += position - touch.position;
+                touch.position = position;
+                touches.ended.push(touch);
+            }
+            _ => return,
+        }
+
+        if !touches.emulate_mouse || touches.mouse_touch.is_some_and(|touch| touch != id) {
+            return;
+        }
+        match phase {
+            TouchPhase::Started if touches.mouse_touch.is_none() => {
+                touches.mouse_touch = Some(id);
+                self.move_cursor(Some(position));
+                self.press_mouse(MouseButton::Left);
+            }
+            TouchPhase::Moved => self.move_cursor(Some(position)),
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                touches.mouse_touch = None;
+                self.move_cursor(Some(position));
+                self.release_mouse(MouseButton::Left);
+            }
+            _ => {}
+        }
+    }
+
+    // simulated input, for tests and on-screen controls. transitions show up
+    // the same way real ones do, until the end of the frame
+
+    pub fn press_key(&mut self, key: KeyCode) {
+        if self.keys.insert(key) {
+            self.keys_pressed.insert(key);
+        }
+    }
+
+    pub fn release_key(&mut self, key: KeyCode) {
+        if self.keys.remove(&key) {
+            self.keys_released.insert(key);
+        }
+    }
+
+    pub fn press_mouse(&mut self, button: MouseButton) {
+        if self.buttons.insert(button) {
+            self.buttons_pressed.insert(button);
+        }
+    }
+
+    pub fn release_mouse(&mut self, button: MouseButton) {
+        if self.buttons.remove(&button) {
+            self.buttons_released.insert(button);
+        }
+    }
+
+    // None is the cursor leaving the window
+    pub fn move_cursor(&mut self, position: Option<Vec2>) {
+        if let (Some(previous), Some(position)) = (self.cursor_position, position) {
+            self.cursor_delta += position - previous;
+        }
+        self.cursor_position = position;
+    }
+
+    pub fn move_mouse(&mut self, motion: Vec2) {
+        self.mouse_motion += motion;
+    }
+
+    // in lines
+    pub fn scroll_by(&mut self, lines: Vec2) {
+        self.scroll_lines += lines;
+        self.scroll += lines;
+    }
+
+    pub fn touch_start(&mut self, id: u64, position: Vec2) {
+        self.touch_input(id, TouchPhase::Started, position);
+    }
+
+    pub fn touch_move(&mut self, id: u64, position: Vec2) {
+        self.touch_input(id, TouchPhase::Moved, position);
+    }
+
+    pub fn touch_end(&mut self, id: u64, position: Vec2) {
+        self.touch_input(id, TouchPhase::Ended, position);
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.text.extend(text.chars().filter(|c| !c.is_control()));
+    }
+
+    // called once the frame is done with this frame's transitions
+    pub(crate) fn clear(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.cursor_delta = Vec2::ZERO;
+        self.mouse_motion = Vec2::ZERO;
+        self.scroll = Vec2::ZERO;
+        self.scroll_lines = Vec2::ZERO;
+        self.scroll_pixels = Vec2::ZERO;
+        self.raw_scroll = Vec2::ZERO;
+        self.raw_buttons_pressed.clear();
+        self.raw_buttons_released.clear();
+        self.text.clear();
+        self.touches.started.clear();
+        self.touches.ended.clear();
+        for touch in &mut self.touches.active {
+            touch.delta = Vec2::ZERO;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touches_start_move_and_end_within_a_frame() {
+        let mut input = Input::default();
+        input.set_touch_emulates_mouse(false);
+        input.touch_start(1, Vec2::new(10.0, 10.0));
+        input.touch_start(2, Vec2::new(50.0, 50.0));
+        assert_eq!(input.touches_started().len(), 2);
+        input.clear();
+        assert!(input.touches_started().is_empty());
+
+        input.touch_move(1, Vec2::new(15.0, 12.0));
+        input.touch_move(1, Vec2::new(20.0, 10.0));
+        let touch = input.touch(1).unwrap();
+        assert_eq!(touch.delta, Vec2::new(10.0, 0.0));
+        assert_eq!(touch.start, Vec2::new(10.0, 10.0));
+        input.clear();
+        assert_eq!(input.touch(1).unwrap().delta, Vec2::ZERO);
+
+        input.touch_end(2, Vec2::new(40.0, 50.0));
+        assert_eq!(input.touches().len(), 1);
+        assert_eq!(input.touches_ended()[0].id, 2);
+        assert_eq!(input.touches_ended()[0].delta, Vec2::new(-10.0, 0.0));
+        // moving or ending a finger that isn't down does nothing
+        input.touch_move(2, Vec2::ZERO);
+        input.touch_end(3, Vec2::ZERO);
+        assert_eq!(input.touches_ended().len(), 1);
+        assert!(input.touch(2).is_none());
+        assert!(!input.mouse_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn the_first_finger_drives_the_mouse() {
+        let mut input = Input::default();
+        input.set_touch_emulates_mouse(true);
+        input.touch_start(1, Vec2::new(10.0, 10.0));
+        input.touch_start(2, Vec2::new(90.0, 90.0));
+        assert!(input.mouse_just_pressed(MouseButton::Left));
+        assert_eq!(input.cursor_position(), Some(Vec2::new(10.0, 10.0)));
+        input.clear();
+
+        input.touch_move(2, Vec2::new(80.0, 80.0));
+        input.touch_move(1, Vec2::new(20.0, 10.0));
+        assert_eq!(input.cursor_delta(), Vec2::new(10.0, 0.0));
+        // the second finger lifting leaves the button held
+        input.touch_end(2, Vec2::new(80.0, 80.0));
+        assert!(input.mouse_pressed(MouseButton::Left));
+        input.touch_end(1, Vec2::new(20.0, 10.0));
+        assert!(input.mouse_just_released(MouseButton::Left));
+        input.clear();
+
+        // once it's up, the next finger down takes over
+        input.touch_start(3, Vec2::new(5.0, 5.0));
+        assert!(input.mouse_pressed(MouseButton::Left));
+        assert_eq!(input.cursor_position(), Some(Vec2::new(5.0, 5.0)));
+    }
+}
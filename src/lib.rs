@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use glam::Mat4;
+use wgpu::naga::FastHashMap;
 use wgpu::util::DeviceExt;
 use winit::{
     application::ApplicationHandler,
@@ -9,43 +9,298 @@ use winit::{
     window::Window,
 };
 
-pub mod ecs;
+pub mod animation;
+pub mod assets;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod bake;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod bridge;
+pub(crate) mod bvh;
+pub mod camera;
+pub mod clipboard;
+pub mod color;
+pub mod console;
+pub mod debug;
+#[cfg(feature = "renderdoc")]
+pub mod diagnostics;
+pub mod drag_drop;
+pub mod editor;
+pub mod error;
+pub mod event_hooks;
+pub mod exit;
+pub mod gizmos;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod hierarchy;
+pub mod history;
+pub mod importance;
+pub mod input;
+pub mod lod;
+pub mod material;
+pub mod mesh;
+pub mod physics;
+pub mod picking;
+pub mod prefab;
+pub mod profiler;
+pub mod proxy;
+pub mod render;
+pub mod scene;
+pub mod simplify;
+#[cfg(feature = "render3d")]
+pub mod skeleton;
+pub mod spline;
+#[cfg(feature = "render2d")]
+pub mod sprite;
+#[cfg(feature = "ui")]
+pub mod stats;
+pub mod streaming;
+pub mod test_utils;
+#[cfg(feature = "ui")]
+pub mod text;
 pub mod texture;
+#[cfg(feature = "render2d")]
+pub mod tilemap;
+pub mod time;
+pub mod transform;
+pub mod ui;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod visibility;
+pub mod web;
+pub mod window;
+
+// the ecs is its own crate, usable without the engine
+pub use whirlwind_ecs as ecs;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use crate::ecs::world::World;
+#[cfg(feature = "render3d")]
+use crate::render::{
+    dof::DofPass,
+    foliage::{Foliage, FoliagePass},
+    probe::ReflectionProbes,
+    ssr::SsrPass,
+    transient::TransientPool,
+    upsample::UpsamplePass,
+    volumetric::VolumetricPass,
+    water::{Water, WaterPass},
+};
+#[cfg(feature = "ui")]
+use crate::render::{resolution::DepthUpsample, text::TextPass};
+#[cfg(feature = "render2d")]
+use crate::render::{sprite::SpritePass, tilemap::TilemapPass};
+use crate::{
+    assets::{Assets, Handle},
+    camera::{Camera, MainCamera, main_camera, main_camera_mut},
+    ecs::{
+        entity::Entity,
+        schedule::{FixedUpdate, Last, ScheduleLabel, Shutdown, Startup, Ui, Update},
+        system::IntoSystem,
+        world::World,
+    },
+    error::WhirlwindError,
+    gizmos::{Gizmos, grid::GridPipeline, render::GizmoPipeline},
+    input::Input,
+    material::{Material, MeshMaterials},
+    mesh::{Mesh, MorphWeights},
+    render::{
+        AntiAliasing, Background, ClearColor, OutputFormat, RenderDevice, RenderSettings,
+        RenderStats, SurfaceSettings,
+        gpu::{GpuFeatures, OPTIONAL_FEATURES},
+        id_pass::{IdPass, IdPicking},
+        minimap::MinimapPass,
+        morph::MorphTargets,
+        motion_blur::MotionBlurPass,
+        outline::{OutlinePass, Outlined},
+        output::{OutputEncoding, SurfaceOutput, choose_surface_format},
+        pipeline_cache::PipelineCache,
+        planar::PlanarReflections,
+        post::{PostTarget, PostTargets},
+        prepass::Prepass,
+        prewarm::PrewarmItem,
+        resolution::{self, GpuTimer, RenderScale},
+        sky::SkyPass,
+        skybox::SkyboxPipeline,
+        taa::TaaPass,
+    },
+    texture::Texture,
+    window::WindowSettings,
+};
+
+struct GpuMesh {
+    vertex_buffer: wgpu::Buffer,
+    // per vertex colors in slot 2, only for meshes that have them
+    color_buffer: Option<wgpu::Buffer>,
+    index_buffer: Option<wgpu::Buffer>,
+    count: u32,
+    aabb: Option<(glam::Vec3, glam::Vec3)>,
+    // element range and material slot of each submesh
+    submeshes: Vec<(std::ops::Range<u32>, usize)>,
+}
+
+impl GpuMesh {
+    // `submesh` None draws the whole mesh
+    fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        submesh: Option<usize>,
+        instances: std::ops::Range<u32>,
+    ) {
+        let range = submesh
+            .and_then(|submesh| self.submeshes.get(submesh))
+            .map_or(0..self.count, |(range, _)| range.clone());
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(range, 0, instances);
+            }
+            None => render_pass.draw(range, instances),
+        }
+    }
+}
+
+// specialized on whether the mesh has vertex colors
+struct MeshPipeline {
+    plain: wgpu::RenderPipeline,
+    colored: wgpu::RenderPipeline,
+}
+
+// the draw's position in the list is its instance
+#[derive(Debug, Clone, Copy)]
+struct MeshDraw {
+    mesh: usize,
+    texture: Option<usize>,
+    submesh: Option<usize>,
+    // dynamic offset of the morph weights, 0 for none
+    morph: u32,
+}
+
+// where finished frames go
+enum FrameTarget {
+    Window {
+        instance: wgpu::Instance,
+        // None while suspended, mobile platforms take the native window away
+        surface: Option<wgpu::Surface<'static>>,
+        window: Arc<Window>,
+    },
+    // for rendering without a window, copyable so frames can be read back
+    Headless(wgpu::Texture),
+}
 
 struct State {
-    surface: wgpu::Surface<'static>,
+    target: FrameTarget,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     is_surface_configured: bool,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    camera: Camera,
+    present_modes: Vec<wgpu::PresentMode>,
+    output: SurfaceOutput,
+    gpu: GpuFeatures,
+    render_pipeline: MeshPipeline,
+    wireframe_pipeline: Option<MeshPipeline>,
+    mirrored_pipeline: MeshPipeline,
+    depth_texture: Texture,
+    meshes: FastHashMap<usize, GpuMesh>,
+    instance_buffer: wgpu::Buffer,
+    gizmo_pipeline: GizmoPipeline,
+    grid_pipeline: GridPipeline,
+    skybox_pipeline: SkyboxPipeline,
+    sky_pass: SkyPass,
+    id_pass: IdPass,
+    outline_pass: OutlinePass,
+    outline_instance_buffer: wgpu::Buffer,
+    outline_draws: Vec<MeshDraw>,
+    post_targets: PostTargets,
+    prepass: Prepass,
+    #[cfg(feature = "render3d")]
+    ssr_pass: SsrPass,
+    #[cfg(feature = "render3d")]
+    reflection_probes: ReflectionProbes,
+    planar_reflections: PlanarReflections,
+    #[cfg(feature = "render3d")]
+    water_pass: WaterPass,
+    #[cfg(feature = "render3d")]
+    foliage_pass: FoliagePass,
+    #[cfg(feature = "render2d")]
+    sprite_pass: SpritePass,
+    #[cfg(feature = "render2d")]
+    tilemap_pass: TilemapPass,
+    #[cfg(feature = "ui")]
+    text_pass: TextPass,
+    // cpu time of each stretch of the last render, for the profiler
+    pass_spans: Vec<(&'static str, web_time::Instant, web_time::Instant)>,
+    minimap_pass: MinimapPass,
+    morph_targets: MorphTargets,
+    // entity, mesh and model of the water surfaces drawn this frame
+    #[cfg(feature = "render3d")]
+    water_draws: Vec<(Entity, usize, glam::Mat4)>,
+    #[cfg(feature = "render3d")]
+    white_texture: Texture,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    taa_pass: TaaPass,
+    #[cfg(feature = "render3d")]
+    dof_pass: DofPass,
+    motion_blur_pass: MotionBlurPass,
+    #[cfg(feature = "render3d")]
+    volumetric_pass: VolumetricPass,
+    #[cfg(feature = "render3d")]
+    upsample_pass: UpsamplePass,
+    // intermediate targets passes lease for part of a frame
+    #[cfg(feature = "render3d")]
+    transient: TransientPool,
+    // the history is stale after resizes and frames without taa
+    taa_reset: bool,
+    // the scene beneath an overlay scene, drawn instead of its background
+    backdrop: Option<PostTarget>,
+    // keep this frame for an overlay that's about to be pushed
+    capture_backdrop: bool,
+    captured_backdrop: Option<PostTarget>,
+    // share of the window's size the scene is drawn at
+    render_scale: f32,
+    gpu_timer: Option<GpuTimer>,
+    // counted while drawing, render only has the world to read
+    draw_calls: std::cell::Cell<u32>,
+    // a RenderDoc capture is open
+    #[cfg(feature = "renderdoc")]
+    capturing: bool,
+    // the scene's depth at the window's size, for overlays over a scaled scene
+    #[cfg(feature = "ui")]
+    upscaled_depth: Option<Texture>,
+    #[cfg(feature = "ui")]
+    depth_upsample: DepthUpsample,
+    // None where the backend can't keep compiled pipelines
+    pipeline_cache: Option<PipelineCache>,
+    frame_index: u32,
+    previous_view_proj: glam::Mat4,
+    previous_models: FastHashMap<Entity, glam::Mat4>,
+    // requests taken from IdPicking for this frame's render
+    id_picks: Vec<glam::UVec2>,
+    id_results: Arc<Mutex<Vec<(glam::UVec2, u32)>>>,
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
-    diffuse_bind_group: wgpu::BindGroup,
-    last_frame_time: std::time::Instant,
-    world: World,
-    window: Arc<Window>,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_groups: FastHashMap<usize, wgpu::BindGroup>,
+    default_bind_group: wgpu::BindGroup,
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub position: [f32; 4],
-    pub tex_coords: [f32; 3],
+    // two uv sets, the second is for lightmaps and detail maps and usually
+    // just a copy of the first
+    pub tex_coords: [[f32; 2]; 2],
     pub normal: [f32; 3],
 }
 
 impl Vertex {
+    // both uv sets come in as one vec4 at location 1
     const ATTRIBS: [wgpu::VertexAttribute; 3] =
-        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x3, 2 => Float32x3];
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x3];
 
     fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
@@ -59,30 +314,44 @@ impl Vertex {
 }
 
 #[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct CameraUniform {
-    view_proj: [[f32; 4]; 4],
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    // linear base color
+    color: [f32; 4],
+    // entity id for the picking pass, 0 is reserved for nothing
+    id: u32,
+    roughness: f32,
+    // last frame's model matrix for motion vectors
+    previous_model: [[f32; 4]; 4],
+    // which uv set the base color texture samples
+    uv_set: u32,
 }
 
-struct Camera {
-    fov: f32,
-    aspect_ratio: f32,
-    pos: glam::Vec3,
-    rotation: glam::Quat,
-}
+impl InstanceRaw {
+    const ATTRIBS: [wgpu::VertexAttribute; 12] = wgpu::vertex_attr_array![
+        5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32x4,
+        10 => Uint32, 11 => Float32,
+        12 => Float32x4, 13 => Float32x4, 14 => Float32x4, 15 => Float32x4,
+        4 => Uint32
+    ];
 
-impl Camera {
-    fn to_uniform(&self) -> CameraUniform {
-        let proj = Mat4::perspective_rh(self.fov.to_radians(), self.aspect_ratio, 0.1, 1024.0);
-        let view = Mat4::from_rotation_translation(self.rotation, self.pos).inverse();
-        CameraUniform {
-            view_proj: (proj * view).to_cols_array_2d(),
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
         }
     }
 }
 
 impl State {
-    async fn new(window: Arc<Window>) -> anyhow::Result<State> {
+    async fn new(
+        window: Arc<Window>,
+        output_format: OutputFormat,
+        transparent: bool,
+        pipeline_cache: Option<std::path::PathBuf>,
+    ) -> anyhow::Result<State> {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
@@ -93,7 +362,7 @@ impl State {
             ..Default::default()
         });
 
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = instance.create_surface(window.clone())?;
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
@@ -101,45 +370,109 @@ impl State {
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
             })
-            .await?;
-
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                experimental_features: wgpu::ExperimentalFeatures::disabled(),
-
-                required_limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
-            })
-            .await?;
+            .await
+            .map_err(WhirlwindError::from)?;
+        let (device, queue) = request_device(&adapter).await?;
+        let gpu = GpuFeatures::new(&adapter, &device);
+        log::info!("Renderer running on {}", gpu);
+        let pipeline_cache = pipeline_cache
+            .and_then(|directory| PipelineCache::load(&device, &gpu.adapter, &directory));
 
         let surface_caps = surface.get_capabilities(&adapter);
 
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .find(|f| f.is_srgb())
-            .copied()
-            .unwrap_or(surface_caps.formats[0]);
+        let (surface_format, output) = choose_surface_format(&surface_caps.formats, output_format);
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
             present_mode: wgpu::PresentMode::AutoVsync,
-            alpha_mode: surface_caps.alpha_modes[0],
+            alpha_mode: choose_alpha_mode(&surface_caps.alpha_modes, transparent),
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
-        let obj = whirlwind_obj::Obj::load("assets/cube.obj").unwrap();
-        let diffuse_texture = texture::Texture::from_path(&device, &queue, "assets/cube.png")?;
+        Self::with_target(
+            device,
+            queue,
+            config,
+            surface_caps.present_modes,
+            output,
+            gpu,
+            FrameTarget::Window {
+                instance,
+                surface: Some(surface),
+                window,
+            },
+            pipeline_cache,
+        )
+    }
 
+    // renders into a texture instead of a window, on the software adapter
+    // (lavapipe, warp) when there's no gpu
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn headless(width: u32, height: u32) -> anyhow::Result<State> {
+        // gl too, for ci machines that only have mesa's llvmpipe
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::PRIMARY | wgpu::Backends::GL,
+            ..Default::default()
+        });
+        let adapter = match instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+        {
+            Ok(adapter) => adapter,
+            Err(_) => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    force_fallback_adapter: true,
+                    ..Default::default()
+                })
+                .await
+                .map_err(WhirlwindError::from)?,
+        };
+        let (device, queue) = request_device(&adapter).await?;
+        let gpu = GpuFeatures::new(&adapter, &device);
+        log::info!("Renderer running on {}", gpu);
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let target = headless_texture(&device, &config);
+        let mut state = Self::with_target(
+            device,
+            queue,
+            config,
+            Vec::new(),
+            SurfaceOutput::srgb(),
+            gpu,
+            FrameTarget::Headless(target),
+            None,
+        )?;
+        state.resize(width.max(1), height.max(1));
+        Ok(state)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_target(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        present_modes: Vec<wgpu::PresentMode>,
+        output: SurfaceOutput,
+        gpu: GpuFeatures,
+        target: FrameTarget,
+        pipeline_cache: Option<PipelineCache>,
+    ) -> anyhow::Result<State> {
+        let cache = pipeline_cache.as_ref().map(PipelineCache::get);
+        // what the scene's pipelines draw into, the surface's own format
+        // unless the final blit has to encode for it
+        let format = output.scene_format(config.format);
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -165,32 +498,28 @@ impl State {
                 label: Some("texture_bind_group_layout"),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
+        let white_texture = Texture::from_image(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([255; 4]),
+            )),
+            texture::TextureUsage::Color,
+            Some("white_texture"),
+        )?;
+        let default_bind_group =
+            create_texture_bind_group(&device, &texture_bind_group_layout, &white_texture);
+        let depth_texture = Texture::create_depth_texture(&device, &config, "depth_texture");
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("material.wgsl"));
 
-        let size = window.inner_size();
-
         let camera = Camera {
-            fov: 60.0,
-            aspect_ratio: size.width as f32 / size.height as f32,
-            pos: glam::vec3(2.0, 1.0, 5.0),
-            rotation: glam::Quat::IDENTITY,
+            aspect_ratio: config.width as f32 / config.height.max(1) as f32,
+            ..Default::default()
         };
-        let camera_uniform = camera.to_uniform();
+        let camera_uniform = camera.to_uniform(glam::Vec2::ZERO, camera.view_proj());
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
             contents: bytemuck::cast_slice(&[camera_uniform]),
@@ -201,7 +530,7 @@ impl State {
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -221,283 +550,2027 @@ impl State {
             label: Some("camera_bind_group"),
         });
 
+        let morph_targets = MorphTargets::new(&device);
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&camera_bind_group_layout, &texture_bind_group_layout],
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &texture_bind_group_layout,
+                    &morph_targets.layout,
+                ],
                 immediate_size: 0,
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview_mask: None,
-            cache: None,
-        });
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(
-                obj.mesh
-                    .vertices()
-                    .into_iter()
-                    .map(|x| Vertex {
-                        position: x.position,
-                        tex_coords: x.tex_coords,
-                        normal: x.normal,
-                    })
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-            ),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(obj.mesh.indices.as_slice()),
-            usage: wgpu::BufferUsages::INDEX,
+        let render_pipeline = create_mesh_pipeline(
+            &device,
+            cache,
+            &render_pipeline_layout,
+            &shader,
+            format,
+            wgpu::PolygonMode::Fill,
+            wgpu::Face::Back,
+        );
+        // mirrored views flip the winding
+        let mirrored_pipeline = create_mesh_pipeline(
+            &device,
+            cache,
+            &render_pipeline_layout,
+            &shader,
+            format,
+            wgpu::PolygonMode::Fill,
+            wgpu::Face::Front,
+        );
+        let wireframe_pipeline = gpu.wireframe.then(|| {
+            create_mesh_pipeline(
+                &device,
+                cache,
+                &render_pipeline_layout,
+                &shader,
+                format,
+                wgpu::PolygonMode::Line,
+                wgpu::Face::Back,
+            )
         });
 
-        let mut world = World::new();
+        let instance_buffer = create_instance_buffer(&device, 64);
+        let outline_instance_buffer = create_instance_buffer(&device, 8);
+        let gizmo_pipeline = GizmoPipeline::new(&device, cache, format, &camera_bind_group_layout);
+        let grid_pipeline = GridPipeline::new(&device, cache, format, &camera_bind_group_layout);
+        let skybox_pipeline = SkyboxPipeline::new(
+            &device,
+            cache,
+            format,
+            &camera_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+        let sky_pass = SkyPass::new(&device, cache, &texture_bind_group_layout);
+        let id_pass = IdPass::new(
+            &device,
+            cache,
+            &camera_bind_group_layout,
+            &[Vertex::desc(), InstanceRaw::desc()],
+            config.width,
+            config.height,
+        );
+        let outline_pass = OutlinePass::new(
+            &device,
+            cache,
+            format,
+            &camera_bind_group_layout,
+            &[Vertex::desc(), InstanceRaw::desc()],
+            config.width,
+            config.height,
+        );
 
-        world.register_schedule("update");
+        let post_targets = PostTargets::new(
+            &device,
+            cache,
+            format,
+            format,
+            output.encoding,
+            config.width,
+            config.height,
+        );
+        let prepass = Prepass::new(
+            &device,
+            cache,
+            &camera_bind_group_layout,
+            &[Vertex::desc(), InstanceRaw::desc()],
+            config.width,
+            config.height,
+        );
+        #[cfg(feature = "render3d")]
+        let water_pass = WaterPass::new(
+            &device,
+            cache,
+            format,
+            &camera_bind_group_layout,
+            Vertex::desc(),
+        );
+        #[cfg(feature = "render3d")]
+        let foliage_pass = FoliagePass::new(
+            &device,
+            cache,
+            format,
+            &camera_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+        #[cfg(feature = "render2d")]
+        let sprite_pass = SpritePass::new(
+            &device,
+            cache,
+            &queue,
+            format,
+            &camera_bind_group_layout,
+            &texture_bind_group_layout,
+        )?;
+        #[cfg(feature = "ui")]
+        let text_pass = TextPass::new(&device, cache, format);
+        let minimap_pass = MinimapPass::new(&device, cache, format, &texture_bind_group_layout);
+        #[cfg(feature = "render2d")]
+        let tilemap_pass = TilemapPass::new(
+            &device,
+            cache,
+            format,
+            &camera_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+        #[cfg(feature = "render3d")]
+        let reflection_probes = ReflectionProbes::new(&device, format, &camera_bind_group_layout);
+        #[cfg(feature = "render3d")]
+        let ssr_pass = SsrPass::new(
+            &device,
+            cache,
+            format,
+            &camera_bind_group_layout,
+            &texture_bind_group_layout,
+            &reflection_probes.layout,
+        );
+        let taa_pass = TaaPass::new(&device, cache, format);
+        #[cfg(feature = "render3d")]
+        let dof_pass = DofPass::new(&device, cache, format, &camera_bind_group_layout);
+        let motion_blur_pass = MotionBlurPass::new(&device, cache, format);
+        #[cfg(feature = "render3d")]
+        let volumetric_pass = VolumetricPass::new(&device, cache, &camera_bind_group_layout);
+        #[cfg(feature = "render3d")]
+        let upsample_pass = UpsamplePass::new(&device, cache, format, &camera_bind_group_layout);
+        #[cfg(feature = "render3d")]
+        let transient = TransientPool::new(config.width, config.height);
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        #[cfg(feature = "ui")]
+        let depth_upsample = DepthUpsample::new(&device, cache);
 
         Ok(Self {
-            surface,
+            target,
             device,
             queue,
             config,
             is_surface_configured: false,
+            present_modes,
+            output,
+            gpu,
             render_pipeline,
-            vertex_buffer,
-            index_buffer,
-            camera,
+            wireframe_pipeline,
+            mirrored_pipeline,
+            depth_texture,
+            meshes: FastHashMap::default(),
+            instance_buffer,
+            gizmo_pipeline,
+            grid_pipeline,
+            skybox_pipeline,
+            sky_pass,
+            id_pass,
+            outline_pass,
+            outline_instance_buffer,
+            outline_draws: Vec::new(),
+            post_targets,
+            prepass,
+            #[cfg(feature = "render3d")]
+            ssr_pass,
+            #[cfg(feature = "render3d")]
+            reflection_probes,
+            planar_reflections: PlanarReflections::new(),
+            #[cfg(feature = "render3d")]
+            water_pass,
+            #[cfg(feature = "render3d")]
+            foliage_pass,
+            #[cfg(feature = "render2d")]
+            sprite_pass,
+            #[cfg(feature = "render2d")]
+            tilemap_pass,
+            #[cfg(feature = "ui")]
+            text_pass,
+            pass_spans: Vec::new(),
+            minimap_pass,
+            morph_targets,
+            #[cfg(feature = "render3d")]
+            water_draws: Vec::new(),
+            #[cfg(feature = "render3d")]
+            white_texture,
+            camera_bind_group_layout,
+            taa_pass,
+            #[cfg(feature = "render3d")]
+            dof_pass,
+            motion_blur_pass,
+            #[cfg(feature = "render3d")]
+            volumetric_pass,
+            #[cfg(feature = "render3d")]
+            upsample_pass,
+            #[cfg(feature = "render3d")]
+            transient,
+            taa_reset: true,
+            backdrop: None,
+            capture_backdrop: false,
+            captured_backdrop: None,
+            render_scale: 1.0,
+            gpu_timer,
+            draw_calls: std::cell::Cell::new(0),
+            #[cfg(feature = "renderdoc")]
+            capturing: false,
+            #[cfg(feature = "ui")]
+            upscaled_depth: None,
+            #[cfg(feature = "ui")]
+            depth_upsample,
+            pipeline_cache,
+            frame_index: 0,
+            previous_view_proj: camera.view_proj(),
+            previous_models: FastHashMap::default(),
+            id_picks: Vec::new(),
+            id_results: Default::default(),
             camera_buffer,
             camera_bind_group,
-            diffuse_bind_group,
-            last_frame_time: std::time::Instant::now(),
-            world,
-            window,
+            texture_bind_group_layout,
+            texture_bind_groups: FastHashMap::default(),
+            default_bind_group,
         })
     }
 
+    fn window(&self) -> Option<&Arc<Window>> {
+        match &self.target {
+            FrameTarget::Window { window, .. } => Some(window),
+            FrameTarget::Headless(_) => None,
+        }
+    }
+
+    fn configure_target(&mut self) {
+        match &mut self.target {
+            FrameTarget::Window {
+                surface: Some(surface),
+                ..
+            } => surface.configure(&self.device, &self.config),
+            FrameTarget::Window { surface: None, .. } => {}
+            FrameTarget::Headless(texture) => {
+                *texture = headless_texture(&self.device, &self.config)
+            }
+        }
+    }
+
+    fn suspend(&mut self) {
+        if let FrameTarget::Window { surface, .. } = &mut self.target {
+            *surface = None;
+            self.is_surface_configured = false;
+        }
+    }
+
+    // the window outlives the surface, it's recreated for the same one
+    fn resume(&mut self) -> anyhow::Result<()> {
+        let FrameTarget::Window {
+            instance,
+            surface: surface @ None,
+            window,
+        } = &mut self.target
+        else {
+            return Ok(());
+        };
+        *surface = Some(instance.create_surface(window.clone())?);
+        let size = window.inner_size();
+        self.resize(size.width, size.height);
+        Ok(())
+    }
+
+    fn save_pipeline_cache(&self) {
+        if let Some(cache) = &self.pipeline_cache
+            && let Err(e) = cache.save()
+        {
+            log::warn!("Unable to save the pipeline cache: {}", e);
+        }
+    }
+
+    // drops what was uploaded for the world that was drawn until now
+    fn forget_world(&mut self) {
+        self.meshes.clear();
+        self.texture_bind_groups.clear();
+        self.previous_models.clear();
+        self.morph_targets.forget_meshes();
+        #[cfg(feature = "render3d")]
+        self.reflection_probes.forget();
+        self.taa_reset = true;
+    }
+
+    fn render_device(&self) -> RenderDevice {
+        RenderDevice {
+            device: self.device.clone(),
+            queue: self.queue.clone(),
+            surface_format: self.output.scene_format(self.config.format),
+        }
+    }
+
     fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
             self.config.width = width;
             self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
+            self.configure_target();
+            self.outline_pass.resize(&self.device, width, height);
+            self.resize_scene_targets();
             self.is_surface_configured = true;
         }
     }
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        self.last_frame_time = std::time::Instant::now();
-        self.window.request_redraw();
+    // the size the scene is drawn at, the window's scaled by render_scale
+    fn scene_size(&self) -> (u32, u32) {
+        resolution::scaled_size(self.config.width, self.config.height, self.render_scale)
+    }
+
+    fn resize_scene_targets(&mut self) {
+        let (width, height) = self.scene_size();
+        let config = wgpu::SurfaceConfiguration {
+            width,
+            height,
+            ..self.config.clone()
+        };
+        self.depth_texture = Texture::create_depth_texture(&self.device, &config, "depth_texture");
+        self.id_pass.resize(&self.device, width, height);
+        self.post_targets.resize(&self.device, width, height);
+        self.prepass.resize(&self.device, width, height);
+        #[cfg(feature = "render3d")]
+        self.transient.resize(width, height);
+        #[cfg(feature = "ui")]
+        {
+            self.upscaled_depth = (self.render_scale < 1.0).then(|| {
+                Texture::create_depth_texture(&self.device, &self.config, "upscaled_depth")
+            });
+        }
+        self.taa_reset = true;
+    }
+
+    fn apply_render_scale(&mut self, world: &mut World) {
+        let measured = self.gpu_timer.as_ref().and_then(GpuTimer::take);
+        let real_delta = world.resource::<time::Time>().real_delta();
+        let settings = world.resource_mut::<RenderScale>();
+        // a kept frame only fits targets of the size it was kept at
+        let scale = if self.backdrop.is_some() {
+            self.render_scale
+        } else {
+            settings.update(measured, real_delta)
+        };
+        self.post_targets
+            .set_sharpness(if scale < 1.0 { settings.sharpness } else { 0.0 });
+        if scale != self.render_scale {
+            self.render_scale = scale;
+            if self.is_surface_configured {
+                self.resize_scene_targets();
+            }
+        }
+    }
+
+    // started ahead of the frame's uploads, ended once it's presented
+    #[cfg(feature = "renderdoc")]
+    fn update_capture(&mut self, world: &mut World) {
+        // a frame that didn't get as far as presenting ends its capture here
+        if self.capturing {
+            self.end_capture();
+        }
+        if world
+            .get_resource_mut::<diagnostics::Diagnostics>()
+            .is_some_and(diagnostics::Diagnostics::take_capture)
+        {
+            // safety: only one capture is open at a time, each is ended before the next starts
+            unsafe { self.device.start_graphics_debugger_capture() };
+            self.capturing = true;
+        }
+    }
+
+    #[cfg(feature = "renderdoc")]
+    fn end_capture(&mut self) {
+        // safety: ends the capture update_capture started, after the frame was submitted
+        unsafe { self.device.stop_graphics_debugger_capture() };
+        self.capturing = false;
+    }
+
+    fn record_stats(&mut self, world: &mut World) {
+        let stats = world.resource_mut::<RenderStats>();
+        stats.draw_calls = self.draw_calls.replace(0);
+        stats.meshes = self.meshes.len();
+        stats.textures = self.texture_bind_groups.len();
+        stats.gpu_memory = if stats.track_memory {
+            self.device
+                .generate_allocator_report()
+                .map(|report| report.total_allocated_bytes)
+        } else {
+            None
+        };
+    }
+
+    fn apply_surface_settings(&mut self, world: &mut World) {
+        let settings = world.resource_mut::<SurfaceSettings>();
+        if !settings.is_supported(settings.present_mode) {
+            log::warn!(
+                "{:?} isn't supported by this surface, falling back to AutoVsync",
+                settings.present_mode
+            );
+            settings.present_mode = wgpu::PresentMode::AutoVsync;
+        }
+        settings.frame_latency = settings.frame_latency.max(1);
+        self.post_targets.set_output_levels(
+            &self.queue,
+            settings.paper_white,
+            settings.max_luminance,
+        );
+        if settings.present_mode != self.config.present_mode
+            || settings.frame_latency != self.config.desired_maximum_frame_latency
+        {
+            self.config.present_mode = settings.present_mode;
+            self.config.desired_maximum_frame_latency = settings.frame_latency;
+            if self.is_surface_configured {
+                self.configure_target();
+            }
+        }
+    }
+
+    fn prepare_texture(&mut self, texture: Handle<Texture>, textures: Option<&Assets<Texture>>) {
+        if !self.texture_bind_groups.contains_key(&texture.id())
+            && let Some(gpu_texture) = textures.and_then(|textures| textures.get(texture))
+        {
+            self.texture_bind_groups.insert(
+                texture.id(),
+                create_texture_bind_group(
+                    &self.device,
+                    &self.texture_bind_group_layout,
+                    gpu_texture,
+                ),
+            );
+        }
+    }
+
+    // false when the mesh isn't loaded
+    fn prepare_mesh(&mut self, mesh: Handle<Mesh>, meshes: Option<&Assets<Mesh>>) -> bool {
+        if !self.meshes.contains_key(&mesh.id()) {
+            let Some(data) = meshes.and_then(|meshes| meshes.get(mesh)) else {
+                return false;
+            };
+            self.meshes
+                .insert(mesh.id(), upload_mesh(&self.device, data));
+            self.morph_targets
+                .upload(&self.device, &self.queue, mesh.id(), data);
+        }
+        true
+    }
+
+    fn prewarm(&mut self, world: &mut World) {
+        let batch = render::prewarm::next_batch(world);
+        if batch.is_empty() {
+            return;
+        }
+        let meshes = world.get_resource::<Assets<Mesh>>();
+        let textures = world.get_resource::<Assets<Texture>>();
+        for item in batch {
+            match item {
+                PrewarmItem::Mesh(mesh) => {
+                    self.prepare_mesh(mesh, meshes);
+                }
+                PrewarmItem::Texture(texture) => self.prepare_texture(texture, textures),
+            }
+        }
+    }
+
+    // uploads any meshes and textures the world references that the gpu hasn't seen yet
+    // `cull` is off on frames that capture reflection probes, they look every way
+    fn prepare(&mut self, world: &World, cull: bool) -> Vec<MeshDraw> {
+        let mut draws = Vec::new();
+        let mut instances = Vec::new();
+        let mut outline_instances = Vec::new();
+        let mut models = FastHashMap::default();
+        self.outline_draws.clear();
+        #[cfg(feature = "render3d")]
+        self.water_draws.clear();
+        self.morph_targets.clear();
+
+        let meshes = world.get_resource::<Assets<Mesh>>();
+        let materials = world.get_resource::<Assets<Material>>();
+        let textures = world.get_resource::<Assets<Texture>>();
+        let frustum = world
+            .get_resource::<debug::DebugSettings>()
+            .and_then(|debug| debug.frozen_view_proj)
+            .map(camera::Frustum::from_view_proj)
+            .or_else(|| Some(main_camera(world)?.frustum()))
+            .filter(|_| cull);
+        let render_layers =
+            main_camera(world).map_or_else(Default::default, |camera| camera.render_layers);
+
+        for (entity, handle) in world.query::<Handle<Mesh>>() {
+            if !visibility::is_visible_to(world, entity, render_layers) {
+                continue;
+            }
+            if !self.prepare_mesh(*handle, meshes) {
+                continue;
+            }
+
+            let model = transform::model_matrix(world, entity);
+            let previous_model = self.previous_models.get(&entity).copied().unwrap_or(model);
+            models.insert(entity, model);
+            // waves move the vertices past the mesh bounds, so water isn't culled
+            #[cfg(feature = "render3d")]
+            if world.get_component::<Water>(entity).is_some() {
+                self.water_draws.push((entity, handle.id(), model));
+                continue;
+            }
+            if let (Some(frustum), Some((min, max))) = (&frustum, self.meshes[&handle.id()].aabb)
+                && !frustum.intersects_transformed_aabb(model, min, max)
+            {
+                continue;
+            }
+
+            // editor selections get the default outline
+            let outline = world
+                .get_component::<Outlined>(entity)
+                .copied()
+                .or_else(|| {
+                    world
+                        .get_component::<gizmos::Selected>(entity)
+                        .map(|_| Outlined::default())
+                });
+            if let Some(outline) = outline {
+                let [r, g, b, _] = outline.color.to_linear();
+                outline_instances.push(InstanceRaw {
+                    model: model.to_cols_array_2d(),
+                    color: [r, g, b, outline.width.clamp(0.0, 8.0)],
+                    id: 0,
+                    roughness: 1.0,
+                    previous_model: previous_model.to_cols_array_2d(),
+                    uv_set: 0,
+                });
+                self.outline_draws.push(MeshDraw {
+                    mesh: handle.id(),
+                    texture: None,
+                    submesh: None,
+                    morph: 0,
+                });
+            }
+
+            let weights = world
+                .get_component::<MorphWeights>(entity)
+                .map(|weights| weights.0.as_slice());
+            let morph = self.morph_targets.push(handle.id(), weights);
+
+            // one draw per submesh, each with the material in its slot
+            let slots = world.get_component::<MeshMaterials>(entity);
+            let fallback = world.get_component::<Handle<Material>>(entity).copied();
+            let material_in = |slot: usize| {
+                slots
+                    .and_then(|slots| slots.0.get(slot).copied())
+                    .or(fallback)
+            };
+            let parts: Vec<_> = match self.meshes[&handle.id()].submeshes.as_slice() {
+                [] => vec![(None, material_in(0))],
+                submeshes => submeshes
+                    .iter()
+                    .enumerate()
+                    .map(|(submesh, (_, slot))| (Some(submesh), material_in(*slot)))
+                    .collect(),
+            };
+            for (submesh, material) in parts {
+                let material = material.and_then(|material| materials?.get(material));
+                let texture = material.and_then(|material| material.base_color_texture);
+                if let Some(texture) = texture {
+                    self.prepare_texture(texture, textures);
+                }
+                instances.push(InstanceRaw {
+                    model: model.to_cols_array_2d(),
+                    color: material
+                        .map_or(color::Color::WHITE, |material| material.base_color)
+                        .to_linear(),
+                    id: render::id_pass::entity_id(entity),
+                    roughness: material.map_or(1.0, |material| material.roughness.clamp(0.0, 1.0)),
+                    previous_model: previous_model.to_cols_array_2d(),
+                    uv_set: material.map_or(0, |material| material.base_color_uv as u32),
+                });
+                draws.push(MeshDraw {
+                    mesh: handle.id(),
+                    texture: texture.map(|texture| texture.id()),
+                    submesh,
+                    morph,
+                });
+            }
+        }
+
+        self.previous_models = models;
+        self.morph_targets.write(&self.device, &self.queue);
+
+        let size = (instances.len() * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(&self.device, instances.len() * 2);
+        }
+        self.queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+
+        let size =
+            (outline_instances.len() * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+        if size > self.outline_instance_buffer.size() {
+            self.outline_instance_buffer =
+                create_instance_buffer(&self.device, outline_instances.len() * 2);
+        }
+        self.queue.write_buffer(
+            &self.outline_instance_buffer,
+            0,
+            bytemuck::cast_slice(&outline_instances),
+        );
+
+        #[cfg(feature = "render3d")]
+        {
+            for (_, foliage) in world.query::<Foliage>() {
+                let (foliage_meshes, impostor) = render::foliage::assets(foliage);
+                for mesh in foliage_meshes {
+                    self.prepare_mesh(mesh, meshes);
+                }
+                let texture = materials
+                    .and_then(|materials| materials.get(foliage.material))
+                    .and_then(|material| material.base_color_texture);
+                for texture in texture.into_iter().chain(impostor) {
+                    self.prepare_texture(texture, textures);
+                }
+            }
+            self.foliage_pass.prepare(
+                &self.device,
+                &self.queue,
+                world,
+                &self.meshes,
+                frustum.as_ref(),
+                main_camera(world).map_or(glam::Vec3::ZERO, |camera| camera.pos),
+                world
+                    .get_resource::<time::Time>()
+                    .map_or(0.0, |time| time.elapsed()),
+            );
+        }
+
+        #[cfg(feature = "render2d")]
+        {
+            for (_, sprite) in world.query::<sprite::Sprite>() {
+                for texture in std::iter::once(sprite.texture).chain(sprite.normal_map) {
+                    self.prepare_texture(texture, textures);
+                }
+            }
+            self.sprite_pass.prepare(&self.device, &self.queue, world);
+
+            if let Some(tilemaps) = world.get_resource::<Assets<tilemap::Tilemap>>() {
+                for (_, handle) in world.query::<Handle<tilemap::Tilemap>>() {
+                    let tilesets = tilemaps.get(*handle).map_or(&[][..], |map| &map.tilesets);
+                    for tileset in tilesets {
+                        self.prepare_texture(tileset.texture, textures);
+                    }
+                }
+            }
+            self.tilemap_pass
+                .prepare(&self.device, &self.queue, world, frustum.as_ref());
+        }
+        for (_, minimap) in world.query::<render::minimap::Minimap>() {
+            self.prepare_texture(minimap.texture, textures);
+        }
+        for (_, marker) in world.query::<render::minimap::MinimapMarker>() {
+            self.prepare_texture(marker.icon, textures);
+        }
+        #[cfg(feature = "ui")]
+        self.text_pass.prepare(
+            &self.device,
+            &self.queue,
+            world,
+            glam::UVec2::new(self.config.width, self.config.height),
+        );
+
+        if let Some(Background::Skybox(texture)) =
+            main_camera(world).map(|camera| camera.background)
+        {
+            self.prepare_texture(texture, textures);
+        }
+
+        if let Some(gizmos) = world.get_resource::<Gizmos>() {
+            self.gizmo_pipeline
+                .prepare(&self.device, &self.queue, gizmos);
+        }
+
+        draws
+    }
+
+    // the equirectangular panorama behind the scene, if the background has one
+    fn environment(&self, background: Background) -> Option<&wgpu::BindGroup> {
+        match background {
+            Background::Skybox(texture) => self.texture_bind_groups.get(&texture.id()),
+            Background::ProceduralSky(_) => Some(&self.sky_pass.bind_group),
+            _ => None,
+        }
+    }
+
+    // expects the camera bind group and instance buffer to be set, and the
+    // pipeline too unless `pipeline` is given to pick per mesh, which also
+    // binds each draw's texture
+    fn draw_meshes(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        draws: &[MeshDraw],
+        pipeline: Option<&MeshPipeline>,
+        // a texture can't be sampled while it's being drawn into
+        skip_texture: Option<usize>,
+    ) {
+        for (instance, draw) in draws.iter().enumerate() {
+            let Some(mesh) = self.meshes.get(&draw.mesh) else {
+                continue;
+            };
+            if skip_texture.is_some() && draw.texture == skip_texture {
+                continue;
+            }
+            let instance = instance as u32;
+
+            if let Some(pipeline) = pipeline {
+                match &mesh.color_buffer {
+                    Some(color_buffer) => {
+                        render_pass.set_pipeline(&pipeline.colored);
+                        render_pass.set_vertex_buffer(2, color_buffer.slice(..));
+                    }
+                    None => render_pass.set_pipeline(&pipeline.plain),
+                }
+                let bind_group = draw
+                    .texture
+                    .and_then(|texture| self.texture_bind_groups.get(&texture))
+                    .unwrap_or(&self.default_bind_group);
+                render_pass.set_bind_group(1, bind_group, &[]);
+                render_pass.set_bind_group(
+                    2,
+                    self.morph_targets.bind_group(draw.mesh),
+                    &[draw.morph],
+                );
+            }
+            mesh.draw(render_pass, draw.submesh, instance..instance + 1);
+            self.draw_calls.set(self.draw_calls.get() + 1);
+        }
+    }
+
+    fn render(&mut self, world: &World) -> Result<(), wgpu::SurfaceError> {
+        if let Some(window) = self.window()
+            && world.resource::<WindowSettings>().redraw_next()
+        {
+            window.request_redraw();
+        }
 
         if !self.is_surface_configured {
             return Ok(());
         }
 
-        let output = self.surface.get_current_texture()?;
+        let mut timer = profiler::PassTimer::start(
+            world
+                .get_resource::<profiler::Profiler>()
+                .is_some_and(|profiler| profiler.enabled),
+        );
+        #[cfg(feature = "render3d")]
+        let probe_bake = self.reflection_probes.pending(world);
+        // probes are only sampled by ssr
+        #[cfg(not(feature = "render3d"))]
+        let probe_bake = None::<()>;
+        let planar_reflections = self.planar_reflections.prepare(
+            &self.device,
+            &self.queue,
+            &self.camera_bind_group_layout,
+            world,
+        );
+        let minimaps = self.minimap_pass.prepare(
+            &self.device,
+            &self.queue,
+            &self.camera_bind_group_layout,
+            world,
+            glam::Vec2::new(self.config.width as f32, self.config.height as f32),
+        );
+        let draws = self.prepare(
+            world,
+            probe_bake.is_none() && planar_reflections.is_empty() && minimaps.is_empty(),
+        );
+
+        timer.mark("prepare");
+        let output = match &self.target {
+            FrameTarget::Window {
+                surface: Some(surface),
+                ..
+            } => Some(surface.get_current_texture()?),
+            FrameTarget::Window { surface: None, .. } => return Ok(()),
+            FrameTarget::Headless(_) => None,
+        };
+        timer.mark("acquire");
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = match (&output, &self.target) {
+            (Some(output), _) => &output.texture,
+            (None, FrameTarget::Headless(texture)) => texture,
+            (None, FrameTarget::Window { .. }) => unreachable!(),
+        }
+        .create_view(&wgpu::TextureViewDescriptor::default());
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        // only what the dynamic scale is steered by
+        let gpu_timer = self.gpu_timer.as_ref().filter(|_| {
+            world
+                .get_resource::<RenderScale>()
+                .is_some_and(|scale| scale.dynamic)
+        });
+        if let Some(gpu_timer) = gpu_timer {
+            gpu_timer.begin(&mut encoder);
+        }
+        // a group per stage, for graphics debuggers
+        encoder.push_debug_group("probes and reflections");
+
+        let id_picks = if self.id_picks.is_empty() {
+            Vec::new()
+        } else {
+            {
+                let mut render_pass = self.id_pass.begin(&mut encoder, &self.depth_texture.view);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                self.draw_meshes(&mut render_pass, &draws, None, None);
+            }
+            let picks = std::mem::take(&mut self.id_picks);
+            // asked for in window pixels, the ids are at the scene's size
+            let scaled: Vec<glam::UVec2> = picks
+                .iter()
+                .map(|position| (position.as_vec2() * self.render_scale).as_uvec2())
+                .collect();
+            self.id_pass
+                .read_back(&self.device, &mut encoder, &scaled)
+                .into_iter()
+                .zip(picks)
+                .map(|((_, staging), position)| (position, staging))
+                .collect()
+        };
+
+        let camera = main_camera(world);
+        #[cfg(feature = "render3d")]
+        let (ssr, depth_of_field, volumetric_lighting) = (
+            camera.and_then(|camera| camera.ssr),
+            camera.and_then(|camera| camera.depth_of_field),
+            camera.and_then(|camera| camera.volumetric_lighting),
+        );
+        // ssr reads the prepass, the other two work from the post target
+        #[cfg(feature = "render3d")]
+        let (ssr_prepass, effects_post) = (
+            ssr.is_some(),
+            depth_of_field.is_some() || volumetric_lighting.is_some(),
+        );
+        #[cfg(not(feature = "render3d"))]
+        let (ssr_prepass, effects_post) = (false, false);
+        let motion_blur = camera.and_then(|camera| camera.motion_blur);
+        let taa = world
+            .get_resource::<RenderSettings>()
+            .is_some_and(|settings| settings.anti_aliasing == AntiAliasing::Taa);
+        let prepass = ssr_prepass || taa || motion_blur.is_some();
+        let encode_output = self.output.encoding != OutputEncoding::None;
+        // one kept from before a resize doesn't fit anymore
+        let backdrop = self.backdrop.as_ref().filter(|backdrop| {
+            backdrop.texture.size() == self.post_targets.current().texture.size()
+        });
+        let capture = std::mem::take(&mut self.capture_backdrop);
+        let scaled = self.render_scale < 1.0;
+        // both need the frame in a post target, a scaled scene is upsampled from one
+        let post =
+            scaled || encode_output || prepass || effects_post || backdrop.is_some() || capture;
+        // overlays go into the post target too, so a captured frame has them
+        let late_finish = encode_output || capture;
+        if prepass {
+            let mut render_pass = self.prepass.begin(&mut encoder, &self.depth_texture.view);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            self.draw_meshes(&mut render_pass, &draws, None, None);
+        }
+
+        let background = camera.map(|camera| camera.background).unwrap_or_default();
+        if let Background::ProceduralSky(sky) = background {
+            let sun = world
+                .get_resource::<render::sky::SunLight>()
+                .copied()
+                .unwrap_or_default();
+            self.sky_pass.update(&self.queue, &mut encoder, sky, sun);
+        }
+        let clear = |color: color::Color| {
+            let mut color: wgpu::Color = color.into();
+            // a premultiplied surface reads the color as already multiplied
+            if self.config.alpha_mode == wgpu::CompositeAlphaMode::PreMultiplied {
+                color.r *= color.a;
+                color.g *= color.a;
+                color.b *= color.a;
+            }
+            wgpu::LoadOp::Clear(color)
+        };
+        let load = match background {
+            Background::ClearColor => clear(
+                world
+                    .get_resource::<ClearColor>()
+                    .copied()
+                    .unwrap_or_default()
+                    .0,
+            ),
+            Background::Color(color) => clear(color),
+            // the skybox covers every pixel anyway
+            Background::Skybox(_) | Background::ProceduralSky(_) => {
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+            }
+            Background::None => wgpu::LoadOp::Load,
+        };
+
+        #[cfg(feature = "render3d")]
+        if let Some((entity, position, generation)) = probe_bake {
+            self.reflection_probes.begin_bake(
+                &self.device,
+                &self.queue,
+                entity,
+                position,
+                generation,
+            );
+            let load = match load {
+                wgpu::LoadOp::Load => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                load => load,
+            };
+            for face in 0..render::probe::FACE_COUNT {
+                let Some(mut render_pass) =
+                    self.reflection_probes
+                        .begin_face(&mut encoder, entity, face, load)
+                else {
+                    break;
+                };
+                if let Some(bind_group) = self.environment(background) {
+                    self.skybox_pipeline.draw(&mut render_pass, bind_group);
+                }
+                render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                self.draw_meshes(&mut render_pass, &draws, Some(&self.render_pipeline), None);
+            }
+        }
+
+        let textures = world.get_resource::<Assets<Texture>>();
+        for (entity, texture) in planar_reflections {
+            let Some(target) = textures.and_then(|textures| textures.get(texture)) else {
+                continue;
+            };
+            let load = match load {
+                wgpu::LoadOp::Load => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                load => load,
+            };
+            let Some(mut render_pass) =
+                self.planar_reflections
+                    .begin(&mut encoder, entity, &target.view, load)
+            else {
+                continue;
+            };
+            if let Some(bind_group) = self.environment(background) {
+                self.skybox_pipeline.draw(&mut render_pass, bind_group);
+            }
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            self.draw_meshes(
+                &mut render_pass,
+                &draws,
+                Some(&self.mirrored_pipeline),
+                Some(texture.id()),
+            );
+        }
 
+        timer.mark("probes and reflections");
+        encoder.pop_debug_group();
+        encoder.push_debug_group("minimaps");
+        for (entity, texture) in minimaps {
+            let Some(target) = textures.and_then(|textures| textures.get(texture)) else {
+                continue;
+            };
+            let Some(mut render_pass) = self.minimap_pass.begin(&mut encoder, entity, &target.view)
+            else {
+                continue;
+            };
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            self.draw_meshes(
+                &mut render_pass,
+                &draws,
+                Some(&self.render_pipeline),
+                Some(texture.id()),
+            );
+            self.minimap_pass.draw_markers(
+                &mut render_pass,
+                entity,
+                &self.texture_bind_groups,
+                &self.default_bind_group,
+            );
+        }
+        timer.mark("minimaps");
+        encoder.pop_debug_group();
+        encoder.push_debug_group("main pass");
+
+        if let Some(backdrop) = backdrop {
+            encoder.copy_texture_to_texture(
+                backdrop.texture.as_image_copy(),
+                self.post_targets.current().texture.as_image_copy(),
+                backdrop.texture.size(),
+            );
+        }
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    // effects read the frame back, so it goes offscreen first
+                    view: if post {
+                        &self.post_targets.current().view
+                    } else {
+                        &view
+                    },
                     resolve_target: None,
                     depth_slice: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                        load: if backdrop.is_some() {
+                            wgpu::LoadOp::Load
+                        } else {
+                            load
+                        },
                         store: wgpu::StoreOp::Store,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
                 multiview_mask: None,
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.diffuse_bind_group, &[]);
+            if let Some(bind_group) = self.environment(background).filter(|_| backdrop.is_none()) {
+                self.skybox_pipeline.draw(&mut render_pass, bind_group);
+            }
 
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32); // 1.
+            let wireframe = world
+                .get_resource::<debug::DebugSettings>()
+                .is_some_and(|debug| debug.wireframe);
+            let pipeline = self
+                .wireframe_pipeline
+                .as_ref()
+                .filter(|_| wireframe)
+                .unwrap_or(&self.render_pipeline);
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+            self.draw_meshes(&mut render_pass, &draws, Some(pipeline), None);
+            #[cfg(feature = "render3d")]
+            self.foliage_pass.draw(
+                &mut render_pass,
+                &self.meshes,
+                &self.texture_bind_groups,
+                &self.default_bind_group,
+            );
+            #[cfg(feature = "render2d")]
+            self.tilemap_pass.draw(
+                &mut render_pass,
+                &self.texture_bind_groups,
+                &self.default_bind_group,
+            );
+            #[cfg(feature = "render2d")]
+            self.sprite_pass.draw(
+                &mut render_pass,
+                &self.texture_bind_groups,
+                &self.default_bind_group,
+            );
 
-            render_pass.draw(
-                0..self.vertex_buffer.size() as u32 / std::mem::size_of::<Vertex>() as u32,
-                0..1,
-            )
+            if let Some(debug) = world
+                .get_resource::<debug::DebugSettings>()
+                .filter(|debug| debug.show_grid)
+            {
+                self.grid_pipeline.prepare(&self.queue, &debug.grid);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                self.grid_pipeline.draw(&mut render_pass);
+            }
+            self.gizmo_pipeline.draw(&mut render_pass);
         }
 
+        #[cfg(feature = "render3d")]
+        if !self.water_draws.is_empty() {
+            let time = world
+                .get_resource::<time::Time>()
+                .map_or(0.0, |time| time.elapsed());
+            let entities: Vec<_> = self
+                .water_draws
+                .iter()
+                .map(|(entity, ..)| *entity)
+                .collect();
+            self.water_pass.retain(&entities);
+            let mut bind_groups = Vec::new();
+            for &(entity, mesh, model) in &self.water_draws {
+                let Some(water) = world.get_component::<Water>(entity) else {
+                    continue;
+                };
+                let reflection = water
+                    .reflection
+                    .and_then(|texture| textures.and_then(|textures| textures.get(texture)));
+                let bind_group = self.water_pass.prepare(
+                    &self.device,
+                    &self.queue,
+                    entity,
+                    water,
+                    model,
+                    time,
+                    &self.depth_texture.view,
+                    reflection,
+                    &self.white_texture,
+                );
+                bind_groups.push((mesh, bind_group));
+            }
+
+            let target = if post {
+                &self.post_targets.current().view
+            } else {
+                &view
+            };
+            let mut render_pass =
+                self.water_pass
+                    .begin(&mut encoder, target, &self.depth_texture.view);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            for (mesh, bind_group) in &bind_groups {
+                let Some(mesh) = self.meshes.get(mesh) else {
+                    continue;
+                };
+                render_pass.set_bind_group(1, bind_group, &[]);
+                mesh.draw(&mut render_pass, None, 0..1);
+                self.draw_calls.set(self.draw_calls.get() + 1);
+            }
+        }
+
+        timer.mark("main pass");
+        encoder.pop_debug_group();
+        encoder.push_debug_group("post");
+        #[cfg(feature = "render3d")]
+        if let Some(ssr) = ssr {
+            let probes = self.reflection_probes.bind_group(
+                &self.device,
+                &self.queue,
+                world,
+                camera.map_or(glam::Vec3::ZERO, |camera| camera.pos),
+            );
+            // not self.environment, post_targets is borrowed mutably below
+            let environment = match background {
+                Background::Skybox(texture) => self.texture_bind_groups.get(&texture.id()),
+                Background::ProceduralSky(_) => Some(&self.sky_pass.bind_group),
+                _ => None,
+            };
+            self.ssr_pass.run(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &mut self.post_targets,
+                &mut self.transient,
+                &self.upsample_pass,
+                &self.camera_bind_group,
+                &self.depth_texture.view,
+                &self.prepass.normals.view,
+                environment,
+                &self.default_bind_group,
+                &probes,
+                ssr,
+            );
+        }
+        #[cfg(feature = "render3d")]
+        if let Some(volumetric_lighting) = volumetric_lighting {
+            self.volumetric_pass.run(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &mut self.post_targets,
+                &mut self.transient,
+                &self.upsample_pass,
+                &self.camera_bind_group,
+                &self.depth_texture.view,
+                volumetric_lighting,
+            );
+        }
+        if taa {
+            self.taa_pass.run(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &mut self.post_targets,
+                &self.prepass.motion.view,
+                self.taa_reset,
+            );
+        }
+        self.taa_reset = !taa;
+        if post {
+            self.post_targets.keep_history(&mut encoder);
+        }
+        #[cfg(feature = "render3d")]
+        if let Some(depth_of_field) = depth_of_field {
+            self.dof_pass.run(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &mut self.post_targets,
+                &self.camera_bind_group,
+                &self.depth_texture.view,
+                camera.map_or(60.0, |camera| camera.fov),
+                self.depth_texture.texture.height(),
+                depth_of_field,
+            );
+        }
+        if let Some(motion_blur) = motion_blur {
+            self.motion_blur_pass.run(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &mut self.post_targets,
+                &self.prepass.motion.view,
+                motion_blur,
+            );
+        }
+        if post && !late_finish {
+            self.post_targets.finish(&self.device, &mut encoder, &view);
+        }
+
+        timer.mark("post");
+        encoder.pop_debug_group();
+        encoder.push_debug_group("overlays");
+        // overlays go on before the output encoding
+        let overlay_target = if late_finish {
+            self.post_targets.current().view.clone()
+        } else {
+            view.clone()
+        };
+        // text is the only overlay tested against depth
+        #[cfg(feature = "ui")]
+        let overlay_depth = match &self.upscaled_depth {
+            Some(upscaled) if scaled && !late_finish => {
+                self.depth_upsample.run(
+                    &self.device,
+                    &mut encoder,
+                    &self.depth_texture.view,
+                    &upscaled.view,
+                );
+                &upscaled.view
+            }
+            _ => &self.depth_texture.view,
+        };
+        if !self.outline_draws.is_empty() {
+            let size = overlay_target.texture().size();
+            self.outline_pass.fit(&self.device, size.width, size.height);
+            {
+                let mut render_pass = self.outline_pass.begin_mask(&mut encoder);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(1, self.outline_instance_buffer.slice(..));
+                self.draw_meshes(&mut render_pass, &self.outline_draws, None, None);
+            }
+            self.outline_pass.composite(&mut encoder, &overlay_target);
+        }
+        self.minimap_pass
+            .draw_widgets(&mut encoder, &overlay_target, &self.texture_bind_groups);
+        #[cfg(feature = "ui")]
+        self.text_pass
+            .draw(&mut encoder, &overlay_target, overlay_depth);
+        if capture {
+            let current = &self.post_targets.current().texture;
+            let captured = PostTarget::new(
+                &self.device,
+                current.format(),
+                current.width(),
+                current.height(),
+                "scene_backdrop",
+            );
+            encoder.copy_texture_to_texture(
+                current.as_image_copy(),
+                captured.texture.as_image_copy(),
+                current.size(),
+            );
+            self.captured_backdrop = Some(captured);
+        }
+        if late_finish {
+            self.post_targets.finish(&self.device, &mut encoder, &view);
+        }
+
+        encoder.pop_debug_group();
+        let gpu_time = gpu_timer.and_then(|gpu_timer| gpu_timer.end(&self.device, &mut encoder));
+
+        timer.mark("overlays");
         self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        if let (Some(gpu_timer), Some(staging)) = (gpu_timer, gpu_time) {
+            gpu_timer.map(staging);
+        }
+        #[cfg(feature = "render3d")]
+        self.transient.end_frame();
+        if let Some(output) = output {
+            output.present();
+        }
+        #[cfg(feature = "renderdoc")]
+        if self.capturing {
+            self.end_capture();
+        }
+        timer.mark("submit");
+        self.pass_spans = timer.spans;
+
+        for (position, staging) in id_picks {
+            let results = self.id_results.clone();
+            match staging {
+                Some(staging) => staging.map(move |data| match data {
+                    Ok(data) => {
+                        let id = bytemuck::pod_read_unaligned(&data[..4]);
+                        results.lock().unwrap().push((position, id));
+                    }
+                    Err(e) => log::error!("Unable to read back ids: {}", e),
+                }),
+                None => results.lock().unwrap().push((position, 0)),
+            }
+        }
 
         Ok(())
     }
 
-    fn update(&mut self) {
-        println!("{}", self.last_frame_time.elapsed().as_secs_f32());
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera.to_uniform()]),
-        );
-        self.world.run_schedule("update");
+    fn update(&mut self, world: &mut World) {
+        if let Some(profiler) = world.get_resource_mut::<profiler::Profiler>() {
+            profiler.begin_frame();
+        }
+        #[cfg(feature = "renderdoc")]
+        self.update_capture(world);
+        self.apply_render_scale(world);
+        self.apply_surface_settings(world);
+        self.record_stats(world);
+        world.resource_mut::<time::Time>().update();
+        world.resource_mut::<Gizmos>().clear();
+        let settings = world.resource_mut::<WindowSettings>();
+        settings.begin_frame();
+        settings.width = self.config.width;
+        settings.height = self.config.height;
+        if let Some(camera) = main_camera_mut(world) {
+            camera.aspect_ratio = self.config.width as f32 / self.config.height.max(1) as f32;
+        }
+
+        // paused worlds skip gameplay but keep tools and overlays responsive
+        if !world.resource::<time::Time>().is_paused() {
+            world.run(Update);
+        }
+        while world.resource_mut::<time::Time>().expend_fixed() {
+            world.run(FixedUpdate);
+        }
+        world.run(Ui);
+        world.run(Last);
+        render::compute::run_compute_passes(world);
+        render::readback::run_readbacks(world);
+        self.prewarm(world);
+
+        let picking = world.resource_mut::<IdPicking>();
+        self.id_picks.append(&mut picking.requests);
+        self.id_results = picking.finished.clone();
+
+        let jitter = if world.resource::<RenderSettings>().anti_aliasing == AntiAliasing::Taa {
+            self.frame_index = self.frame_index.wrapping_add(1);
+            let (width, height) = self.scene_size();
+            render::taa::jitter(self.frame_index, glam::vec2(width as f32, height as f32))
+        } else {
+            glam::Vec2::ZERO
+        };
+        // a world without a camera keeps drawing from the last one it had
+        if let Some(camera) = main_camera(world) {
+            self.queue.write_buffer(
+                &self.camera_buffer,
+                0,
+                bytemuck::cast_slice(&[camera.to_uniform(jitter, self.previous_view_proj)]),
+            );
+            self.previous_view_proj = camera.view_proj();
+        }
+    }
+}
+
+fn upload_mesh(device: &wgpu::Device, mesh: &Mesh) -> GpuMesh {
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&mesh.vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let index_buffer = (!mesh.indices.is_empty()).then(|| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        })
+    });
+    let color_buffer = (!mesh.colors.is_empty()).then(|| {
+        // short color lists are padded with white so every vertex has one
+        let mut colors = mesh.colors.clone();
+        colors.resize(mesh.vertices.len(), [1.0; 4]);
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Buffer"),
+            contents: bytemuck::cast_slice(&colors),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
+    });
+    GpuMesh {
+        count: if index_buffer.is_some() {
+            mesh.indices.len()
+        } else {
+            mesh.vertices.len()
+        } as u32,
+        vertex_buffer,
+        color_buffer,
+        index_buffer,
+        aabb: mesh.aabb(),
+        submeshes: mesh
+            .submeshes
+            .iter()
+            .map(|submesh| {
+                let end = submesh.range.end.min(mesh.element_count());
+                (submesh.range.start.min(end)..end, submesh.material)
+            })
+            .collect(),
+    }
+}
+
+fn create_mesh_pipeline(
+    device: &wgpu::Device,
+    cache: Option<&wgpu::PipelineCache>,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    polygon_mode: wgpu::PolygonMode,
+    cull_mode: wgpu::Face,
+) -> MeshPipeline {
+    let create = |entry_point, buffers: &[wgpu::VertexBufferLayout<'_>]| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some(entry_point),
+                buffers,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(cull_mode),
+                polygon_mode,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview_mask: None,
+            cache,
+        })
+    };
+    MeshPipeline {
+        plain: create("vs_main", &[Vertex::desc(), InstanceRaw::desc()]),
+        colored: create(
+            "vs_main_colored",
+            &[
+                Vertex::desc(),
+                InstanceRaw::desc(),
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![3 => Float32x4],
+                },
+            ],
+        ),
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Instance Buffer"),
+        size: (capacity.max(1) * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+        label: Some("texture_bind_group"),
+    })
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    Ok(adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features: adapter.features() & OPTIONAL_FEATURES,
+            experimental_features: wgpu::ExperimentalFeatures::disabled(),
+
+            required_limits: if cfg!(target_arch = "wasm32") {
+                wgpu::Limits::downlevel_webgl2_defaults()
+            } else {
+                wgpu::Limits::default()
+            },
+            memory_hints: Default::default(),
+            trace: wgpu::Trace::Off,
+        })
+        .await?)
+}
+
+// transparent windows need the compositor to blend, the first mode is usually opaque
+fn choose_alpha_mode(
+    modes: &[wgpu::CompositeAlphaMode],
+    transparent: bool,
+) -> wgpu::CompositeAlphaMode {
+    if !transparent {
+        return modes[0];
+    }
+    let blending = [
+        wgpu::CompositeAlphaMode::PreMultiplied,
+        wgpu::CompositeAlphaMode::PostMultiplied,
+        wgpu::CompositeAlphaMode::Inherit,
+    ];
+    match blending.into_iter().find(|mode| modes.contains(mode)) {
+        Some(mode) => mode,
+        None => {
+            log::warn!("This surface can't be transparent, it supports {:?}", modes);
+            modes[0]
+        }
     }
 }
 
+fn headless_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("headless_target"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+// the schedules, resources, engine systems and main camera every app starts
+// with, without a window or renderer
+pub(crate) fn create_world() -> World {
+    let mut world = World::new();
+    world.set_schedule_runner(profiler::run_profiled);
+
+    world.add_schedule(Startup);
+    world.add_schedule(Last);
+    world.add_schedule(Update);
+    world.add_schedule(FixedUpdate);
+    world.add_schedule(Ui);
+    world.add_schedule(Shutdown);
+    #[cfg(feature = "audio")]
+    world.init_resource::<audio::Mixer>();
+    #[cfg(feature = "audio")]
+    world.init_resource::<audio::MusicController>();
+    #[cfg(feature = "audio")]
+    world.init_resource::<audio::AudioOutput>();
+    world.init_resource::<time::Time>();
+    world.register_entity_refs::<hierarchy::Parent>();
+    world.register_entity_refs::<hierarchy::Children>();
+    world.register_entity_refs::<animation::TransformAnimation>();
+    world.register_entity_refs::<spline::FollowPath>();
+    world.init_resource::<profiler::Profiler>();
+    world.init_resource::<Assets<Mesh>>();
+    world.init_resource::<Assets<Material>>();
+    world.init_resource::<Assets<Texture>>();
+    #[cfg(feature = "render2d")]
+    world.init_resource::<Assets<tilemap::Tilemap>>();
+    #[cfg(feature = "ui")]
+    world.init_resource::<Assets<text::Font>>();
+    world.init_resource::<assets::fallback::FallbackAssets>();
+    world.add_event::<assets::fallback::AssetLoadFailed>();
+    world.init_resource::<ClearColor>();
+    world.init_resource::<SurfaceSettings>();
+    world.init_resource::<RenderSettings>();
+    world.init_resource::<RenderScale>();
+    world.init_resource::<RenderStats>();
+    world.init_resource::<render::prewarm::Prewarm>();
+    #[cfg(feature = "render3d")]
+    world.init_resource::<render::foliage::Wind>();
+    world.init_resource::<render::sky::SunLight>();
+    world.init_resource::<render::compute::ComputePasses>();
+    world.init_resource::<Assets<render::compute::ComputePipeline>>();
+    world.init_resource::<Assets<render::compute::StorageBuffer>>();
+    world.add_event::<animation::AnimationEvent>();
+    world.add_event::<render::compute::ComputeReadback>();
+    world.init_resource::<render::readback::Readbacks>();
+    world.add_event::<render::readback::ReadbackComplete>();
+    world.add_event::<render::prewarm::PrewarmFinished>();
+    world.init_resource::<IdPicking>();
+    world.add_event::<render::id_pass::IdPicked>();
+    world.init_resource::<Input>();
+    world.init_resource::<WindowSettings>();
+    world.init_resource::<window::BackgroundSettings>();
+    world.init_resource::<bridge::Bridge>();
+    world.init_resource::<exit::ExitHooks>();
+    world.init_resource::<event_hooks::EventHooks>();
+    world.init_resource::<exit::PendingExit>();
+    world.init_resource::<scene::SceneManager>();
+    world.init_resource::<streaming::StreamingSettings>();
+    world.init_resource::<importance::ImportanceBudget>();
+    #[cfg(feature = "physics")]
+    world.init_resource::<physics::Gravity>();
+    #[cfg(feature = "physics")]
+    world.init_resource::<physics::fluid::FluidContacts>();
+    world.init_resource::<streaming::CellStreamer>();
+    world.add_event::<streaming::CellLoaded>();
+    world.add_event::<streaming::CellUnloaded>();
+    world.init_resource::<web::WebSettings>();
+    world.init_resource::<ui::UiScale>();
+    world.init_resource::<Gizmos>();
+    world.init_resource::<gizmos::TransformGizmo>();
+    world.init_resource::<prefab::Prefabs>();
+    world.init_resource::<history::CommandHistory>();
+    world.init_resource::<editor::Editor>();
+    world.init_resource::<console::Console>();
+    world.init_resource::<debug::DebugSettings>();
+    world.init_resource::<debug::DebugKeys>();
+    #[cfg(feature = "renderdoc")]
+    world.init_resource::<diagnostics::Diagnostics>();
+    world.init_resource::<drag_drop::DragAndDrop>();
+    world.init_resource::<clipboard::Clipboard>();
+    world.add_event::<clipboard::Paste>();
+    world.add_event::<input::Ime>();
+    world.add_event::<ui::ScaleFactorChanged>();
+    // recorded before the event is cleared, the app stops after the frame
+    world.add_system_to(Last, exit::record_exit);
+    world.add_event::<exit::AppExit>();
+    world.add_event::<window::WindowFocused>();
+    world.add_event::<window::Lifecycle>();
+    world.add_event::<bridge::BridgeMessage>();
+    world.add_event::<window::WindowVisibilityChanged>();
+    world.add_event::<drag_drop::FileDropped>();
+    world.add_event::<drag_drop::FileHovered>();
+    world.add_event::<drag_drop::FileHoverCancelled>();
+    #[cfg(feature = "physics")]
+    world.add_event::<physics::fluid::FluidEntered>();
+    #[cfg(feature = "physics")]
+    world.add_event::<physics::fluid::FluidExited>();
+    world.add_system_to(Startup, assets::embedded::insert_default_assets);
+    world.add_system_to(Update, physics::trimesh::build_static_colliders);
+    #[cfg(feature = "audio")]
+    world.add_system_to(Update, audio::spatial::update_spatial_audio);
+    world.add_system_to(Update, streaming::stream_cells);
+    world.add_system_to(Update, importance::update_importance);
+    world.add_system_to(Update, spline::follow_paths);
+    world.add_system_to(Update, animation::animate_morph_weights);
+    world.add_system_to(Update, animation::animate_transforms);
+    #[cfg(feature = "render3d")]
+    world.add_system_to(Update, skeleton::update_skeletons);
+    #[cfg(feature = "render2d")]
+    world.add_system_to(Update, sprite::animate_sprites);
+    world.add_system_to(Update, render::sky::update_time_of_day);
+    #[cfg(feature = "video")]
+    world.add_system_to(Update, video::update_video_players);
+    #[cfg(feature = "physics")]
+    world.add_system_to(FixedUpdate, physics::fluid::apply_fluids);
+    #[cfg(feature = "physics")]
+    world.add_system_to(FixedUpdate, physics::force_field::apply_force_fields);
+    #[cfg(feature = "physics")]
+    world.add_system_to(FixedUpdate, physics::body::step_bodies);
+    world.add_system_to(Ui, render::id_pass::send_id_picks);
+    world.add_system_to(Ui, clipboard::update_clipboard);
+    world.add_system_to(Ui, console::update_console);
+    world.add_system_to(Ui, debug::update_debug_toggles);
+    #[cfg(feature = "renderdoc")]
+    world.add_system_to(Ui, diagnostics::update_capture_key);
+    world.add_system_to(Ui, gizmos::transform::update_transform_gizmo);
+    world.add_system_to(Ui, editor::update_editor);
+    world.add_system_to(Ui, drag_drop::spawn_dropped_models);
+    world.add_system_to(Ui, debug::draw_debug);
+    #[cfg(feature = "ui")]
+    world.add_system_to(Ui, stats::update_stats_overlay);
+    world.add_system_to(Last, hierarchy::propagate_transforms);
+    world.add_system_to(Last, lod::select_lods);
+    world.add_system_to(Last, camera::update_cameras);
+    #[cfg(feature = "ui")]
+    world.add_system_to(Last, text::layout_text);
+    world.add_system_to(Last, bridge::send_messages);
+    world.add_system_to(Last, history::discard_dropped);
+    #[cfg(feature = "audio")]
+    world.add_system_to(Last, audio::output::sync_audio);
+
+    let camera = Camera::default();
+    world
+        .spawn()
+        .insert(transform::Transform::from_translation(camera.pos))
+        .insert(camera)
+        .insert(MainCamera);
+    world
+}
+
 struct Application {
     #[cfg(target_arch = "wasm32")]
-    proxy: Option<winit::event_loop::EventLoopProxy<State>>,
+    proxy: Option<winit::event_loop::EventLoopProxy<proxy::EngineEvent>>,
+    #[cfg(target_arch = "wasm32")]
+    web_drops: drag_drop::WebDropQueue,
+    // for the safe area, winit doesn't report it on android
+    #[cfg(target_os = "android")]
+    android_app: Option<winit::platform::android::activity::AndroidApp>,
     state: Option<State>,
+    // the top scene
+    world: World,
+    paused_scenes: Vec<scene::PausedScene>,
 }
 
 impl Application {
     #[allow(clippy::new_without_default)]
-    fn new(#[cfg(target_arch = "wasm32")] event_loop: &EventLoop<State>) -> Self {
+    fn new(#[cfg(target_arch = "wasm32")] event_loop: &EventLoop<proxy::EngineEvent>) -> Self {
         #[cfg(target_arch = "wasm32")]
         let proxy = Some(event_loop.create_proxy());
+
+        let world = create_world();
+
         Self {
             state: None,
+            world,
+            paused_scenes: Vec::new(),
             #[cfg(target_arch = "wasm32")]
             proxy,
+            #[cfg(target_arch = "wasm32")]
+            web_drops: Default::default(),
+            #[cfg(target_os = "android")]
+            android_app: None,
+        }
+    }
+
+    // the surface comes back for the window created on the first resume
+    fn resume(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if let Err(e) = state.resume() {
+            log::error!("Unable to recreate the surface: {}", e);
+            event_loop.exit();
+            return;
+        }
+        if let Some(window) = state.window() {
+            window.request_redraw();
         }
+        window::set_visible(&mut self.world, true);
+        self.world.send_event(window::Lifecycle::Resumed);
     }
+
+    fn set_state(&mut self, state: State) {
+        self.world.insert_resource(state.render_device());
+        self.world.insert_resource(state.gpu.clone());
+        self.world
+            .resource_mut::<SurfaceSettings>()
+            .supported_present_modes = state.present_modes.clone();
+        let settings = self.world.resource_mut::<SurfaceSettings>();
+        settings.supported_output_formats = state.output.supported.clone();
+        settings.active_output_format = state.output.format;
+        if let Some(window) = state.window() {
+            self.world.resource_mut::<ui::UiScale>().window_scale_factor = window.scale_factor();
+        }
+        self.state = Some(state);
+        #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+        audio::device::open_default_device(&mut self.world);
+        self.world.run(Startup);
+    }
+}
+
+fn init_logging() {
+    #[cfg(target_arch = "wasm32")]
+    console_log::init_with_level(log::Level::Info).unwrap_throw();
+    #[cfg(not(target_arch = "wasm32"))]
+    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+}
+
+// a set of resources and systems a game adds with one `App::add_plugin`
+pub trait Plugin {
+    fn build(&self, app: &mut App);
 }
 
 pub struct App {
     application: Application,
-    event_loop: EventLoop<State>,
+    event_loop: EventLoop<proxy::EngineEvent>,
 }
 
 impl App {
     pub fn new() -> anyhow::Result<Self> {
-        #[cfg(target_arch = "wasm32")]
-        console_log::init_with_level(log::Level::Info).unwrap_throw();
-        #[cfg(not(target_arch = "wasm32"))]
-        env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
-
+        init_logging();
         let event_loop = EventLoop::with_user_event().build()?;
-        let app = Application::new(
+        Ok(Self::with_event_loop(event_loop))
+    }
+
+    // for `android_main`, with the activity android-activity passes it
+    #[cfg(target_os = "android")]
+    pub fn new_android(
+        android_app: winit::platform::android::activity::AndroidApp,
+    ) -> anyhow::Result<Self> {
+        use winit::platform::android::EventLoopBuilderExtAndroid;
+
+        init_logging();
+        let event_loop = EventLoop::with_user_event()
+            .with_android_app(android_app.clone())
+            .build()?;
+        let mut app = Self::with_event_loop(event_loop);
+        app.application.android_app = Some(android_app);
+        Ok(app)
+    }
+
+    fn with_event_loop(event_loop: EventLoop<proxy::EngineEvent>) -> Self {
+        let mut app = Application::new(
             #[cfg(target_arch = "wasm32")]
             &event_loop,
         );
+        app.world
+            .insert_resource(proxy::EngineProxy::new(event_loop.create_proxy()));
 
-        Ok(Self {
+        Self {
             application: app,
             event_loop,
-        })
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.application.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.application.world
     }
+
+    pub fn add_system_to(
+        &mut self,
+        label: impl ScheduleLabel,
+        system: impl IntoSystem,
+    ) -> &mut Self {
+        self.application.world.add_system_to(label, system);
+        self
+    }
+
+    #[deprecated = "use add_system_to with a ScheduleLabel"]
+    pub fn add_system(
+        &mut self,
+        schedule_name: &'static str,
+        system: impl IntoSystem,
+    ) -> &mut Self {
+        #[allow(deprecated)]
+        self.application.world.add_system(schedule_name, system);
+        self
+    }
+
+    // for threads started before `run`, systems find it as a resource
+    pub fn proxy(&self) -> proxy::EngineProxy {
+        self.application
+            .world
+            .resource::<proxy::EngineProxy>()
+            .clone()
+    }
+
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        plugin.build(self);
+        self
+    }
+
     pub fn run(mut self) -> anyhow::Result<()> {
         self.event_loop.run_app(&mut self.application)?;
 
-        Ok(())
+        match self.application.world.resource::<exit::PendingExit>().exit {
+            Some(exit::AppExit::Error(code)) => anyhow::bail!("Exited with code {}", code),
+            _ => Ok(()),
+        }
     }
 }
 
-impl ApplicationHandler<State> for Application {
+impl ApplicationHandler<proxy::EngineEvent> for Application {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_some() {
+            self.resume(event_loop);
+            return;
+        }
         #[allow(unused_mut)]
-        let mut window_attributes = Window::default_attributes().with_title("Whirlwind Engine");
+        let mut window_attributes = self.world.resource_mut::<WindowSettings>().attributes();
+        let transparent = self.world.resource::<WindowSettings>().transparent();
 
         #[cfg(target_arch = "wasm32")]
         {
-            use wasm_bindgen::JsCast;
             use winit::platform::web::WindowAttributesExtWebSys;
 
-            const CANVAS_ID: &str = "canvas";
-
-            let window = wgpu::web_sys::window().unwrap_throw();
-            let document = window.document().unwrap_throw();
-            let canvas = document.get_element_by_id(CANVAS_ID).unwrap_throw();
-            let html_canvas_element = canvas.unchecked_into();
-            window_attributes = window_attributes.with_canvas(Some(html_canvas_element));
+            let canvas = match web::canvas(self.world.resource::<web::WebSettings>()) {
+                Ok(canvas) => canvas,
+                Err(e) => {
+                    log::error!("Unable to find the canvas: {}", e);
+                    event_loop.exit();
+                    return;
+                }
+            };
+            drag_drop::listen_for_web_drops(&canvas, self.web_drops.clone());
+            window_attributes = window_attributes.with_canvas(Some(canvas));
         }
 
-        let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
+        let output_format = self.world.resource::<SurfaceSettings>().output_format;
+        let pipeline_cache = self
+            .world
+            .resource::<SurfaceSettings>()
+            .pipeline_cache
+            .clone();
+        let window = match event_loop.create_window(window_attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => {
+                log::error!("Unable to create the window: {}", e);
+                event_loop.exit();
+                return;
+            }
+        };
 
         #[cfg(not(target_arch = "wasm32"))]
         {
-            self.state = Some(pollster::block_on(State::new(window)).unwrap());
+            match pollster::block_on(State::new(
+                window,
+                output_format,
+                transparent,
+                pipeline_cache,
+            )) {
+                Ok(state) => self.set_state(state),
+                Err(e) => {
+                    log::error!("Unable to start the renderer: {}", e);
+                    event_loop.exit();
+                }
+            }
         }
 
         #[cfg(target_arch = "wasm32")]
         {
             if let Some(proxy) = self.proxy.take() {
                 wasm_bindgen_futures::spawn_local(async move {
-                    assert!(
-                        proxy
-                            .send_event(
-                                State::new(window)
-                                    .await
-                                    .expect("Unable to create canvas!!!")
-                            )
-                            .is_ok()
-                    )
+                    match State::new(window, output_format, transparent, pipeline_cache).await {
+                        Ok(state) => assert!(
+                            proxy
+                                .send_event(proxy::EngineEvent::Renderer(Box::new(state)))
+                                .is_ok()
+                        ),
+                        Err(e) => log::error!("Unable to start the renderer: {}", e),
+                    }
                 });
             }
         }
     }
 
-    #[allow(unused_mut)]
-    fn user_event(&mut self, _event_loop: &ActiveEventLoop, mut event: State) {
-        #[cfg(target_arch = "wasm32")]
-        {
-            event.window.request_redraw();
-            event.resize(
-                event.window.inner_size().width,
-                event.window.inner_size().height,
-            );
+    // however the loop ends, "shutdown" has run by the time it does
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        exit::shutdown(&mut self.world);
+        scene::shut_down_paused(&mut self.paused_scenes);
+        if let Some(state) = &self.state {
+            state.save_pipeline_cache();
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            // the app may not come back from this on mobile
+            state.save_pipeline_cache();
+            state.suspend();
+        }
+        window::set_visible(&mut self.world, false);
+        self.world.send_event(window::Lifecycle::Suspended);
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: proxy::EngineEvent) {
+        match event {
+            #[cfg(target_arch = "wasm32")]
+            proxy::EngineEvent::Renderer(mut state) => {
+                if let Some(window) = state.window().cloned() {
+                    window.request_redraw();
+                    let size = window.inner_size();
+                    state.resize(size.width, size.height);
+                }
+                self.set_state(*state);
+            }
+            proxy::EngineEvent::Run(job) => {
+                job(&mut self.world);
+                // so what it changed gets drawn when frames only follow events
+                if let Some(window) = self.state.as_ref().and_then(State::window) {
+                    window.request_redraw();
+                }
+            }
         }
-        self.state = Some(event);
     }
     fn window_event(
         &mut self,
@@ -510,19 +2583,78 @@ impl ApplicationHandler<State> for Application {
             None => return,
         };
 
+        event_hooks::window_event(&mut self.world, &event);
+        self.world.resource_mut::<Input>().handle_event(&event);
+        if !matches!(event, WindowEvent::RedrawRequested)
+            && self.world.resource::<WindowSettings>().redraw_on_event()
+            && let Some(window) = state.window()
+        {
+            window.request_redraw();
+        }
+        if let WindowEvent::Ime(ime) = &event {
+            self.world.send_event(ime.clone());
+        }
+
         match event {
             WindowEvent::KeyboardInput {
                 event: KeyEvent { .. },
                 ..
             } => {}
             WindowEvent::RedrawRequested => {
-                state.update();
-                match state.render() {
+                #[cfg(target_arch = "wasm32")]
+                for file in self.web_drops.borrow_mut().drain(..) {
+                    self.world.send_event(file);
+                }
+                #[cfg(target_arch = "wasm32")]
+                bridge::receive_messages(&mut self.world);
+                #[cfg(target_arch = "wasm32")]
+                if self.world.resource::<web::WebSettings>().fit_to_parent
+                    && let Some(window) = state.window()
+                {
+                    web::fit_to_parent(window);
+                }
+                if let Some(window) = state.window() {
+                    window::update_safe_area(
+                        &mut self.world,
+                        window,
+                        #[cfg(target_os = "android")]
+                        self.android_app.as_ref(),
+                    );
+                }
+                state.update(&mut self.world);
+                if let Some(window) = state.window() {
+                    self.world
+                        .resource_mut::<WindowSettings>()
+                        .apply(window, event_loop);
+                }
+                state.capture_backdrop = self
+                    .world
+                    .resource::<scene::SceneManager>()
+                    .wants_backdrop();
+                let result = state.render(&self.world);
+                if let Some(profiler) = self.world.get_resource_mut::<profiler::Profiler>() {
+                    for (name, start, end) in state.pass_spans.drain(..) {
+                        profiler.record(name, "render", start, end);
+                    }
+                }
+                self.world.resource_mut::<Input>().clear();
+                if self.world.resource::<exit::PendingExit>().exit.is_some() {
+                    exit::exit(&mut self.world, event_loop);
+                } else if scene::change_scenes(
+                    &mut self.world,
+                    &mut self.paused_scenes,
+                    &mut state.backdrop,
+                    state.captured_backdrop.take(),
+                ) {
+                    state.forget_world();
+                }
+                match result {
                     Ok(_) => {}
 
                     Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                        let size = state.window.inner_size();
-                        state.resize(size.width, size.height);
+                        if let Some(size) = state.window().map(|window| window.inner_size()) {
+                            state.resize(size.width, size.height);
+                        }
                     }
                     Err(e) => {
                         log::error!("Unable to render {}", e);
@@ -532,10 +2664,62 @@ impl ApplicationHandler<State> for Application {
             WindowEvent::Resized(size) => {
                 state.resize(size.width, size.height);
             }
-            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.world.resource_mut::<ui::UiScale>().window_scale_factor = scale_factor;
+                self.world
+                    .send_event(ui::ScaleFactorChanged { scale_factor });
+            }
+            WindowEvent::Focused(focused) => window::set_focused(&mut self.world, focused),
+            WindowEvent::Occluded(occluded) => {
+                window::set_visible(&mut self.world, !occluded);
+                // frames stop while hidden, this starts them again
+                if let Some(window) = state.window().filter(|_| !occluded) {
+                    window.request_redraw();
+                }
+            }
+            WindowEvent::DroppedFile(path) => self
+                .world
+                .send_event(drag_drop::FileDropped { path, bytes: None }),
+            WindowEvent::HoveredFile(path) => {
+                self.world.send_event(drag_drop::FileHovered(path));
+            }
+            WindowEvent::HoveredFileCancelled => {
+                self.world.send_event(drag_drop::FileHoverCancelled);
+            }
+            WindowEvent::CloseRequested if exit::close_allowed(&mut self.world) => {
+                exit::exit(&mut self.world, event_loop);
+            }
             _ => {}
         }
     }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        event_hooks::device_event(&mut self.world, device_id, &event);
+        self.world
+            .resource_mut::<Input>()
+            .handle_device_event(&event);
+        // raw motion arrives wherever the pointer is, only the focused window wakes for it
+        let settings = self.world.resource::<WindowSettings>();
+        if settings.redraw_on_event()
+            && settings.is_focused()
+            && let Some(window) = self.state.as_ref().and_then(|state| state.window())
+        {
+            window.request_redraw();
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(window) = self.state.as_ref().and_then(|state| state.window()) {
+            self.world
+                .resource::<WindowSettings>()
+                .schedule_wake(window, event_loop);
+        }
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -0,0 +1,175 @@
+// Levels of detail for meshes. An entity with a MeshLod has its Handle<Mesh>
+// swapped for a simpler mesh as it gets further from the main camera, and back
+// as it comes closer. The levels are usually made at import, with `lods` set
+// on MeshImport, but any meshes will do. Switching back to a nearer level
+// waits until the entity is a little closer than the level's distance, so
+// something sat right on it doesn't flicker between the two.
+
+use crate::{
+    assets::Handle,
+    camera::main_camera,
+    ecs::{component::Component, world::World},
+    mesh::Mesh,
+    transform,
+};
+
+// share of a level's distance to come back within before switching to the nearer level
+const HYSTERESIS: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodLevel {
+    // used from this camera distance on
+    pub distance: f32,
+    pub mesh: Handle<Mesh>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshLod {
+    // what's drawn up close
+    pub base: Handle<Mesh>,
+    // sorted by distance
+    pub levels: Vec<LodLevel>,
+    // 0 for the base mesh, otherwise one past the level in use
+    current: usize,
+}
+
+impl Component for MeshLod {}
+
+impl MeshLod {
+    pub fn new(base: Handle<Mesh>, mut levels: Vec<LodLevel>) -> Self {
+        levels.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Self {
+            base,
+            levels,
+            current: 0,
+        }
+    }
+
+    // 0 is the base mesh, 1 the first level and so on
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn mesh(&self) -> Handle<Mesh> {
+        match self.current {
+            0 => self.base,
+            level => self.levels[level - 1].mesh,
+        }
+    }
+
+    fn select(&mut self, distance: f32) {
+        let far = self
+            .levels
+            .iter()
+            .take_while(|level| distance >= level.distance)
+            .count();
+        if far >= self.current {
+            self.current = far;
+            return;
+        }
+        // nearer, but only once past the margin of each level given up
+        while self.current > far
+            && distance < self.levels[self.current - 1].distance * (1.0 - HYSTERESIS)
+        {
+            self.current -= 1;
+        }
+    }
+}
+
+pub fn select_lods(world: &mut World) {
+    let Some(camera) = main_camera(world).map(|camera| camera.pos) else {
+        return;
+    };
+    let entities: Vec<_> = world
+        .query::<MeshLod>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in entities {
+        let distance = transform::model_matrix(world, entity)
+            .w_axis
+            .truncate()
+            .distance(camera);
+        let Some(lod) = world.get_component_mut::<MeshLod>(entity) else {
+            continue;
+        };
+        lod.select(distance);
+        let mesh = lod.mesh();
+        match world.get_component_mut::<Handle<Mesh>>(entity) {
+            Some(handle) => *handle = mesh,
+            None => world.add_component(entity, mesh),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::Assets,
+        mesh::{LodSettings, MeshImport},
+    };
+
+    #[test]
+    fn levels_switch_back_past_the_margin() {
+        let mut meshes = Assets::default();
+        let handles: Vec<Handle<Mesh>> = (0..3).map(|_| meshes.add(Mesh::cube(1.0))).collect();
+        let mut lod = MeshLod::new(
+            handles[0],
+            vec![
+                LodLevel {
+                    distance: 20.0,
+                    mesh: handles[2],
+                },
+                LodLevel {
+                    distance: 10.0,
+                    mesh: handles[1],
+                },
+            ],
+        );
+        lod.select(5.0);
+        assert_eq!((lod.current(), lod.mesh()), (0, handles[0]));
+        lod.select(25.0);
+        assert_eq!((lod.current(), lod.mesh()), (2, handles[2]));
+        // just inside the level's distance isn't enough
+        lod.select(19.5);
+        assert_eq!(lod.current(), 2);
+        lod.select(18.0);
+        assert_eq!(lod.current(), 1);
+        lod.select(0.0);
+        assert_eq!(lod.current(), 0);
+    }
+
+    #[test]
+    fn imported_levels_follow_the_camera() {
+        let mut meshes = Assets::default();
+        let import = MeshImport {
+            lods: Some(LodSettings::default()),
+            ..Default::default()
+        };
+        let imported = import.add(&mut meshes, Mesh::sphere(1.0, 32, 16));
+        let lod = imported.lod.clone().unwrap();
+        let triangles: Vec<usize> = std::iter::once(lod.base)
+            .chain(lod.levels.iter().map(|level| level.mesh))
+            .map(|handle| meshes.get(handle).unwrap().triangles().len())
+            .collect();
+        assert!(
+            triangles.windows(2).all(|pair| pair[1] < pair[0]),
+            "{triangles:?}"
+        );
+
+        let mut world = crate::create_world();
+        let entity = world.spawn().id();
+        imported.insert(&mut world, entity);
+        world.add_component(
+            entity,
+            transform::Transform::from_translation(glam::Vec3::Z * 40.0),
+        );
+        select_lods(&mut world);
+        assert_eq!(world.get_component::<MeshLod>(entity).unwrap().current(), 2);
+        assert_eq!(
+            world.get_component::<Handle<Mesh>>(entity),
+            Some(&lod.levels[1].mesh)
+        );
+    }
+}
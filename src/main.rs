@@ -1,6 +1,41 @@
-use whirlwind::App;
+use whirlwind::{
+    App,
+    assets::{Handle, embedded::DefaultAssets},
+    ecs::{component::Component, entity::Entity, schedule::Startup, world::World},
+    material::Material,
+    mesh::Mesh,
+    prefab::{Prefabs, spawn_prefab},
+    transform::Transform,
+};
+
+#[derive(Debug)]
+struct CubeAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<Material>,
+}
+
+impl Component for CubeAssets {}
+
+fn cube(world: &mut World, entity: Entity) {
+    let assets = world.resource::<CubeAssets>();
+    let (mesh, material) = (assets.mesh, assets.material);
+    world.add_component(entity, mesh);
+    world.add_component(entity, material);
+}
+
+fn setup(world: &mut World) {
+    // compiled in, see assets::embedded
+    let defaults = *world.resource::<DefaultAssets>();
+    let (mesh, material) = (defaults.cube, defaults.cube_material);
+
+    world.insert_resource(CubeAssets { mesh, material });
+    world.resource_mut::<Prefabs>().register("cube", cube);
+    spawn_prefab(world, "cube", Transform::default());
+}
 
 fn main() -> anyhow::Result<()> {
-    App::new()?.run()?;
+    let mut app = App::new()?;
+    app.add_system_to(Startup, setup);
+    app.run()?;
     Ok(())
 }
@@ -0,0 +1,64 @@
+use crate::{
+    assets::Handle,
+    color::Color,
+    ecs::component::Component,
+    texture::{Texture, TextureUsage},
+};
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    // multiplied with the texture
+    pub base_color: Color,
+    // falls back to a white texture when unset
+    pub base_color_texture: Option<Handle<Texture>>,
+    pub base_color_uv: UvSet,
+    // 0 is a mirror, only used by screen space reflections so far
+    pub roughness: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: Color::default(),
+            base_color_texture: None,
+            base_color_uv: UvSet::First,
+            roughness: 1.0,
+        }
+    }
+}
+
+// which of a vertex's two uv sets a texture is sampled with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UvSet {
+    #[default]
+    First,
+    Second,
+}
+
+// one material per submesh slot, takes over from the entity's Handle<Material>
+// which is still used for slots this doesn't cover
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshMaterials(pub Vec<Handle<Material>>);
+
+impl Component for MeshMaterials {}
+
+// the texture inputs a material can have, for picking how to load a texture
+// meant for one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialSlot {
+    BaseColor,
+    Emissive,
+    Normal,
+    MetallicRoughness,
+    Occlusion,
+}
+
+impl MaterialSlot {
+    pub fn texture_usage(self) -> TextureUsage {
+        match self {
+            MaterialSlot::BaseColor | MaterialSlot::Emissive => TextureUsage::Color,
+            MaterialSlot::Normal => TextureUsage::NormalMap,
+            MaterialSlot::MetallicRoughness | MaterialSlot::Occlusion => TextureUsage::Data,
+        }
+    }
+}
@@ -2,13 +2,25 @@
 
 struct VertexInput {
     @location(0) position: vec4<f32>,
-    @location(1) normal: vec3<f32>,
-    @location(2) tex_coords: vec3<f32>,
+    // xy is the first uv set, zw the second
+    @location(1) tex_coords: vec4<f32>,
+    @location(2) normal: vec3<f32>,
+    @builtin(vertex_index) index: u32,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+    @location(4) uv_set: u32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec3<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
 };
 
 struct CameraUniform {
@@ -17,13 +29,63 @@ struct CameraUniform {
 @group(0) @binding(0) // 1.
 var<uniform> camera: CameraUniform;
 
+struct MorphUniform {
+    vertex_count: u32,
+    target_count: u32,
+    weights: array<vec4<f32>, 15>,
+};
+// position deltas, all of one target's vertices then the next target's
+@group(2) @binding(0)
+var morph_deltas: texture_2d<f32>;
+@group(2) @binding(1)
+var<uniform> morph: MorphUniform;
+
+const MORPH_TEXTURE_WIDTH: u32 = 1024u;
+
+fn morph_position(position: vec4<f32>, index: u32) -> vec4<f32> {
+    var offset = vec3<f32>(0.0);
+    for (var i = 0u; i < morph.target_count; i += 1u) {
+        let weight = morph.weights[i / 4u][i % 4u];
+        if weight != 0.0 {
+            let texel = i * morph.vertex_count + index;
+            let coords = vec2<u32>(texel % MORPH_TEXTURE_WIDTH, texel / MORPH_TEXTURE_WIDTH);
+            offset += textureLoad(morph_deltas, coords, 0).xyz * weight;
+        }
+    }
+    return vec4<f32>(position.xyz + offset * position.w, position.w);
+}
+
+fn vertex(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    var out: VertexOutput;
+    out.tex_coords = select(model.tex_coords.xy, model.tex_coords.zw, instance.uv_set == 1u);
+    out.color = instance.color;
+    out.clip_position = camera.view_proj * model_matrix * morph_position(model.position, model.index);
+    return out;
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
-    var out: VertexOutput;
-    out.tex_coords = model.tex_coords;
-    out.clip_position = camera.view_proj * model.position;
+    return vertex(model, instance);
+}
+
+// for meshes with vertex colors, which tint the base color
+@vertex
+fn vs_main_colored(
+    model: VertexInput,
+    instance: InstanceInput,
+    @location(3) color: vec4<f32>,
+) -> VertexOutput {
+    var out = vertex(model, instance);
+    out.color *= color;
     return out;
 }
 
@@ -34,5 +96,5 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords.xy);
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
 }
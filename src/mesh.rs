@@ -0,0 +1,412 @@
+use std::ops::Range;
+
+use glam::Vec3;
+
+use crate::{
+    Vertex,
+    assets::{Assets, Handle},
+    bake::{AoBake, bake_ambient_occlusion},
+    ecs::{component::Component, entity::Entity, world::World},
+    lod::{LodLevel, MeshLod},
+    physics::{Collider, fit::ColliderFit},
+    simplify::simplify,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    // empty for non-indexed triangle lists
+    pub indices: Vec<u32>,
+    // linear rgba per vertex, multiplied into the base color, empty for none
+    pub colors: Vec<[f32; 4]>,
+    // parts drawn with different materials, empty draws the whole mesh with slot 0
+    pub submeshes: Vec<Submesh>,
+    // blend shapes, mixed in by the entity's MorphWeights
+    pub morph_targets: Vec<MorphTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submesh {
+    // into `indices`, or `vertices` when there are none
+    pub range: Range<u32>,
+    // index into the entity's MeshMaterials
+    pub material: usize,
+}
+
+// offsets from the base mesh per vertex, shorter lists leave the rest in place
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MorphTarget {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+}
+
+// how much of each of the mesh's morph targets is applied, in target order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MorphWeights(pub Vec<f32>);
+
+impl Component for MorphWeights {}
+
+impl Mesh {
+    // local space bounds as (min, max)
+    pub fn aabb(&self) -> Option<(Vec3, Vec3)> {
+        let mut positions = self
+            .vertices
+            .iter()
+            .map(|vertex| Vec3::from_slice(&vertex.position[..3]));
+        let first = positions.next()?;
+        Some(positions.fold((first, first), |(min, max), position| {
+            (min.min(position), max.max(position))
+        }))
+    }
+
+    // how many elements the draw ranges index into
+    pub fn element_count(&self) -> u32 {
+        if self.indices.is_empty() {
+            self.vertices.len() as u32
+        } else {
+            self.indices.len() as u32
+        }
+    }
+
+    // local space positions of every triangle, indexed or not
+    pub fn triangles(&self) -> Vec<[Vec3; 3]> {
+        let position = |index: u32| Vec3::from_slice(&self.vertices[index as usize].position[..3]);
+        if self.indices.is_empty() {
+            (0..self.vertices.len() as u32 / 3)
+                .map(|triangle| [0, 1, 2].map(|corner| position(triangle * 3 + corner)))
+                .collect()
+        } else {
+            self.indices
+                .chunks_exact(3)
+                .map(|corners| [0, 1, 2].map(|corner| position(corners[corner])))
+                .collect()
+        }
+    }
+
+    pub fn morph_target_index(&self, name: &str) -> Option<usize> {
+        self.morph_targets
+            .iter()
+            .position(|target| target.name == name)
+    }
+
+    // a flat square on the xz plane facing +y, split into a grid for vertex displacement
+    pub fn plane(size: f32, subdivisions: u32) -> Self {
+        let cells = subdivisions + 1;
+        let mut vertices = Vec::new();
+        for z in 0..=cells {
+            for x in 0..=cells {
+                let u = x as f32 / cells as f32;
+                let v = z as f32 / cells as f32;
+                vertices.push(Vertex {
+                    position: [(u - 0.5) * size, 0.0, (v - 0.5) * size, 1.0],
+                    tex_coords: [[u, v]; 2],
+                    normal: [0.0, 1.0, 0.0],
+                });
+            }
+        }
+        let mut indices = Vec::new();
+        let row = cells + 1;
+        for z in 0..cells {
+            for x in 0..cells {
+                let a = z * row + x;
+                let (b, c, d) = (a + row, a + 1, a + row + 1);
+                indices.extend_from_slice(&[a, b, c, c, b, d]);
+            }
+        }
+        Self {
+            vertices,
+            indices,
+            colors: Vec::new(),
+            submeshes: Vec::new(),
+            morph_targets: Vec::new(),
+        }
+    }
+
+    // a box `size` wide on every side around the origin, each face with its own full uv square
+    pub fn cube(size: f32) -> Self {
+        let half = size * 0.5;
+        // normal, then the face's right and up, right x up = normal
+        let faces = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+            (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+            (Vec3::Z, Vec3::X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+        ];
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (normal, right, up) in faces {
+            let first = vertices.len() as u32;
+            for (s, t) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                let position = (normal + right * s + up * t) * half;
+                vertices.push(Vertex {
+                    position: position.extend(1.0).to_array(),
+                    tex_coords: [[(s + 1.0) * 0.5, (1.0 - t) * 0.5]; 2],
+                    normal: normal.to_array(),
+                });
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+        }
+        Self {
+            vertices,
+            indices,
+            colors: Vec::new(),
+            submeshes: Vec::new(),
+            morph_targets: Vec::new(),
+        }
+    }
+
+    // a uv sphere, `segments` around and `rings` from pole to pole
+    pub fn sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        let mut vertices = Vec::new();
+        for ring in 0..=rings {
+            let v = ring as f32 / rings as f32;
+            let (sin_theta, cos_theta) = (v * std::f32::consts::PI).sin_cos();
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let (sin_phi, cos_phi) = (u * std::f32::consts::TAU).sin_cos();
+                let normal = Vec3::new(cos_phi * sin_theta, cos_theta, sin_phi * sin_theta);
+                vertices.push(Vertex {
+                    position: (normal * radius).extend(1.0).to_array(),
+                    tex_coords: [[u, v]; 2],
+                    normal: normal.to_array(),
+                });
+            }
+        }
+        let mut indices = Vec::new();
+        let row = segments + 1;
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let a = ring * row + segment;
+                let (b, c, d) = (a + row, a + 1, a + row + 1);
+                indices.extend_from_slice(&[a, c, b, c, d, b]);
+            }
+        }
+        Self {
+            vertices,
+            indices,
+            colors: Vec::new(),
+            submeshes: Vec::new(),
+            morph_targets: Vec::new(),
+        }
+    }
+
+    // positions, uvs and normals of an obj file's faces, for files already in
+    // memory. materials and groups are ignored
+    pub fn from_obj_source(source: &str) -> anyhow::Result<Self> {
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+        let mut vertices = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let mut parts = line.split_whitespace();
+            let floats = |parts: std::str::SplitWhitespace| -> anyhow::Result<Vec<f32>> {
+                parts
+                    .map(|part| {
+                        part.parse::<f32>()
+                            .map_err(|e| anyhow::anyhow!("Line {}: {}", number + 1, e))
+                    })
+                    .collect()
+            };
+            let floats = |parts, min: usize| {
+                let values = floats(parts)?;
+                if values.len() < min {
+                    anyhow::bail!("Line {}: expected {} numbers", number + 1, min);
+                }
+                Ok(values)
+            };
+            match parts.next() {
+                Some("v") => positions.push(floats(parts, 3)?),
+                Some("vt") => uvs.push(floats(parts, 1)?),
+                Some("vn") => normals.push(floats(parts, 3)?),
+                Some("f") => {
+                    let corners = parts
+                        .map(|corner| {
+                            let mut indices = corner.split('/');
+                            let mut next = |count: usize| {
+                                indices
+                                    .next()
+                                    .filter(|index| !index.is_empty())
+                                    .map(|index| obj_index(index, count, number))
+                                    .transpose()
+                            };
+                            Ok((
+                                next(positions.len())?.ok_or_else(|| {
+                                    anyhow::anyhow!("Line {}: face without a position", number + 1)
+                                })?,
+                                next(uvs.len())?,
+                                next(normals.len())?,
+                            ))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    // fans out polygons from their first corner
+                    for i in 1..corners.len().saturating_sub(1) {
+                        for (position, uv, normal) in [corners[0], corners[i], corners[i + 1]] {
+                            let p = &positions[position];
+                            // obj's v goes up, ours goes down
+                            let uv = uv.map_or([0.0; 2], |uv: usize| {
+                                [uvs[uv][0], 1.0 - uvs[uv].get(1).copied().unwrap_or(0.0)]
+                            });
+                            let normal = normal.map_or([0.0, 1.0, 0.0], |n: usize| {
+                                [normals[n][0], normals[n][1], normals[n][2]]
+                            });
+                            vertices.push(Vertex {
+                                position: [p[0], p[1], p[2], 1.0],
+                                tex_coords: [uv; 2],
+                                normal,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            vertices,
+            indices: Vec::new(),
+            colors: Vec::new(),
+            submeshes: Vec::new(),
+            morph_targets: Vec::new(),
+        })
+    }
+
+    #[cfg(feature = "obj")]
+    pub fn from_obj(path: &str) -> anyhow::Result<Self> {
+        crate::error::ensure_exists(path)?;
+        let obj = whirlwind_obj::Obj::load(path)
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {:?}", path, e))?;
+        Ok(Self {
+            vertices: obj
+                .mesh
+                .vertices()
+                .into_iter()
+                .map(|x| Vertex {
+                    position: x.position,
+                    // obj only has the one uv set
+                    tex_coords: [[x.tex_coords[0], x.tex_coords[1]]; 2],
+                    normal: x.normal,
+                })
+                .collect(),
+            indices: Vec::new(),
+            colors: Vec::new(),
+            submeshes: Vec::new(),
+            morph_targets: Vec::new(),
+        })
+    }
+
+    #[cfg(not(feature = "obj"))]
+    pub fn from_obj(path: &str) -> anyhow::Result<Self> {
+        crate::error::ensure_exists(path)?;
+        Self::from_obj_source(&std::fs::read_to_string(path)?)
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", path, e))
+    }
+}
+
+// simpler copies of an imported mesh, each used from its distance on
+#[derive(Debug, Clone, PartialEq)]
+pub struct LodSettings {
+    // (share of the triangles kept, camera distance)
+    pub levels: Vec<(f32, f32)>,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            levels: vec![(0.5, 15.0), (0.25, 30.0), (0.1, 60.0)],
+        }
+    }
+}
+
+// what's done to a mesh as it's loaded
+#[derive(Debug, Clone, Default)]
+pub struct MeshImport {
+    // cast on the cpu into the vertex colors, which can take a while for big meshes
+    pub ambient_occlusion: Option<AoBake>,
+    // simplified levels, only made when the mesh is added with `add`
+    pub lods: Option<LodSettings>,
+    // fitted to the mesh as it's added with `add`, before any simplifying
+    pub collider: Option<ColliderFit>,
+}
+
+// a mesh added through MeshImport, with what goes on its entity
+#[derive(Debug, Clone)]
+pub struct ImportedMesh {
+    pub mesh: Handle<Mesh>,
+    pub lod: Option<MeshLod>,
+    pub collider: Option<Collider>,
+}
+
+impl ImportedMesh {
+    pub fn insert(self, world: &mut World, entity: Entity) {
+        world.add_component(entity, self.mesh);
+        if let Some(lod) = self.lod {
+            world.add_component(entity, lod);
+        }
+        if let Some(collider) = self.collider {
+            world.add_component(entity, collider);
+        }
+    }
+}
+
+impl MeshImport {
+    pub fn apply(&self, mesh: &mut Mesh) {
+        if let Some(settings) = &self.ambient_occlusion {
+            bake_ambient_occlusion(mesh, settings);
+        }
+    }
+
+    // applies the import and adds the mesh with its levels and collider
+    pub fn add(&self, meshes: &mut Assets<Mesh>, mut mesh: Mesh) -> ImportedMesh {
+        self.apply(&mut mesh);
+        let collider = self.collider.and_then(|fit| fit.fit(&mesh));
+        let levels: Vec<(Mesh, f32)> = self
+            .lods
+            .iter()
+            .flat_map(|settings| &settings.levels)
+            .map(|&(ratio, distance)| (simplify(&mesh, ratio), distance))
+            .collect();
+        let base = meshes.add(mesh);
+        let lod = (!levels.is_empty()).then(|| {
+            let levels = levels
+                .into_iter()
+                .map(|(mesh, distance)| LodLevel {
+                    distance,
+                    mesh: meshes.add(mesh),
+                })
+                .collect();
+            MeshLod::new(base, levels)
+        });
+        ImportedMesh {
+            mesh: base,
+            lod,
+            collider,
+        }
+    }
+}
+
+impl Mesh {
+    pub fn from_obj_with(path: &str, import: &MeshImport) -> anyhow::Result<Self> {
+        let mut mesh = Self::from_obj(path)?;
+        import.apply(&mut mesh);
+        Ok(mesh)
+    }
+}
+
+// 1 based, negative counts back from the last one read so far
+fn obj_index(index: &str, count: usize, line: usize) -> anyhow::Result<usize> {
+    let index: i64 = index
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Line {}: {}", line + 1, e))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        anyhow::bail!("Line {}: index {} out of range", line + 1, index);
+    }
+    Ok(resolved as usize)
+}
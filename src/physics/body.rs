@@ -0,0 +1,182 @@
+// Rigid bodies, moved by the physics step in FixedUpdate. Every step a body's
+// velocity picks up gravity and the forces applied to it since the last one,
+// loses some to damping, and moves its Transform, forces are cleared after.
+// Fluids and force fields push bodies by applying forces before the step.
+// Bodies don't collide with anything yet, and turn as if their mass were
+// spread evenly through a unit sphere.
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    ecs::{component::Component, world::World},
+    time::Time,
+    transform::{self, Transform},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gravity(pub Vec3);
+
+impl Component for Gravity {}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Self(Vec3::new(0.0, -9.81, 0.0))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RigidBody {
+    // world space, per second
+    pub velocity: Vec3,
+    // radians per second around each world axis
+    pub angular_velocity: Vec3,
+    // kilograms
+    pub mass: f32,
+    // share of the velocity lost per second
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub gravity_scale: f32,
+    force: Vec3,
+    torque: Vec3,
+}
+
+impl Component for RigidBody {}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl RigidBody {
+    pub fn new(mass: f32) -> Self {
+        Self {
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            mass,
+            linear_damping: 0.01,
+            angular_damping: 0.05,
+            gravity_scale: 1.0,
+            force: Vec3::ZERO,
+            torque: Vec3::ZERO,
+        }
+    }
+
+    // newtons until the next step
+    pub fn apply_force(&mut self, force: Vec3) {
+        self.force += force;
+    }
+
+    // `offset` is from the body's origin to where the force pushes, in world space
+    pub fn apply_force_at(&mut self, force: Vec3, offset: Vec3) {
+        self.force += force;
+        self.torque += offset.cross(force);
+    }
+
+    pub fn apply_torque(&mut self, torque: Vec3) {
+        self.torque += torque;
+    }
+
+    // changes the velocity at once, newton seconds
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        self.velocity += impulse / self.mass.max(f32::EPSILON);
+    }
+
+    // the forces applied since the last step
+    pub fn force(&self) -> Vec3 {
+        self.force
+    }
+
+    fn inertia(&self) -> f32 {
+        // a solid unit sphere's
+        0.4 * self.mass.max(f32::EPSILON)
+    }
+}
+
+pub fn step_bodies(world: &mut World) {
+    let delta = world.resource::<Time>().fixed_delta();
+    let gravity = world
+        .get_resource::<Gravity>()
+        .copied()
+        .unwrap_or_default()
+        .0;
+    let bodies: Vec<_> = world
+        .query::<RigidBody>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in bodies {
+        let Some(body) = world.get_component_mut::<RigidBody>(entity) else {
+            continue;
+        };
+        let acceleration = gravity * body.gravity_scale + body.force / body.mass.max(f32::EPSILON);
+        body.velocity += acceleration * delta;
+        body.velocity *= (1.0 - body.linear_damping * delta).max(0.0);
+        body.angular_velocity += body.torque / body.inertia() * delta;
+        body.angular_velocity *= (1.0 - body.angular_damping * delta).max(0.0);
+        body.force = Vec3::ZERO;
+        body.torque = Vec3::ZERO;
+        let (velocity, angular_velocity) = (body.velocity, body.angular_velocity);
+
+        // velocities are in world space, the transform is in its parent's
+        let parent = transform::parent_matrix(world, entity);
+        let (_, parent_rotation, _) = parent.to_scale_rotation_translation();
+        let Some(transform) = world.get_component_mut::<Transform>(entity) else {
+            continue;
+        };
+        transform.translation += parent.inverse().transform_vector3(velocity * delta);
+        let turn = Quat::from_scaled_axis(parent_rotation.inverse() * angular_velocity * delta);
+        transform.rotation = (turn * transform.rotation).normalize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::schedule::{FixedUpdate, Last},
+        hierarchy,
+        test_utils::TestWorld,
+        transform::GlobalTransform,
+    };
+
+    #[test]
+    fn parented_bodies_move_in_world_space() {
+        let mut test = TestWorld::empty()
+            .with_resource(Gravity(Vec3::ZERO))
+            .with_system(FixedUpdate, step_bodies)
+            .with_system(Last, hierarchy::propagate_transforms);
+        let platform = test
+            .spawn()
+            .insert(Transform {
+                translation: Vec3::new(0.0, 10.0, 0.0),
+                rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+                scale: Vec3::splat(2.0),
+            })
+            .id();
+        let body = test
+            .spawn()
+            .insert(Transform::IDENTITY)
+            .insert(RigidBody {
+                velocity: Vec3::X,
+                angular_velocity: Vec3::Y,
+                linear_damping: 0.0,
+                angular_damping: 0.0,
+                ..Default::default()
+            })
+            .id();
+        hierarchy::set_parent(&mut test.world, body, Some(platform));
+        test.ticks(30);
+
+        let global = test.component::<GlobalTransform>(body);
+        let position = global.translation();
+        assert!((position.y - 10.0).abs() < 1e-4 && position.z.abs() < 1e-4);
+        assert!(position.x > 0.45 && position.x < 0.55, "{position}");
+        // spinning around world y leaves the axis lying along it alone
+        assert!(
+            global.right().abs_diff_eq(Vec3::Y, 1e-4),
+            "{}",
+            global.right()
+        );
+    }
+}
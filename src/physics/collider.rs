@@ -0,0 +1,239 @@
+use glam::Vec3;
+
+use crate::{ecs::component::Component, transform::Transform};
+
+use super::{Ray, heightfield::Heightfield, hull::ConvexHull, trimesh::TriMesh};
+
+#[derive(Debug, Clone)]
+pub enum Collider {
+    Sphere { radius: f32 },
+    Cuboid { half_extents: Vec3 },
+    // along local y, `half_height` is the straight part between the two caps
+    Capsule { half_height: f32, radius: f32 },
+    ConvexHull(ConvexHull),
+    // terrain, scaled with the entity like a mesh
+    Heightfield(Heightfield),
+    // level geometry that doesn't move, see Static
+    TriMesh(TriMesh),
+    // several shapes, each placed relative to the entity
+    Compound(Vec<(Transform, Collider)>),
+}
+
+impl Component for Collider {}
+
+impl Collider {
+    pub fn sphere(radius: f32) -> Self {
+        Self::Sphere { radius }
+    }
+
+    pub fn cuboid(half_extents: Vec3) -> Self {
+        Self::Cuboid { half_extents }
+    }
+
+    pub fn capsule(half_height: f32, radius: f32) -> Self {
+        Self::Capsule {
+            half_height,
+            radius,
+        }
+    }
+
+    // local space (min, max), before the entity's transform
+    fn local_bounds(&self) -> (Vec3, Vec3) {
+        match self {
+            Collider::Sphere { radius } => (Vec3::splat(-radius), Vec3::splat(*radius)),
+            Collider::Cuboid { half_extents } => (-*half_extents, *half_extents),
+            Collider::Capsule {
+                half_height,
+                radius,
+            } => {
+                let extent = Vec3::new(*radius, half_height + radius, *radius);
+                (-extent, extent)
+            }
+            Collider::ConvexHull(hull) => hull.points().iter().fold(
+                (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+                |(min, max), point| (min.min(*point), max.max(*point)),
+            ),
+            Collider::Heightfield(heightfield) => heightfield.bounds(),
+            Collider::TriMesh(trimesh) => trimesh.bounds().unwrap_or_default(),
+            Collider::Compound(_) => (Vec3::ZERO, Vec3::ZERO),
+        }
+    }
+
+    // world space (min, max) around the collider placed by `transform`
+    pub fn aabb(&self, transform: &Transform) -> (Vec3, Vec3) {
+        if let Collider::Compound(parts) = self {
+            return parts
+                .iter()
+                .map(|(offset, part)| part.aabb(&transform.mul_transform(offset)))
+                .reduce(|(a, b), (c, d)| (a.min(c), b.max(d)))
+                .unwrap_or((transform.translation, transform.translation));
+        }
+        let (min, max) = self.local_bounds();
+        (0..8)
+            .map(|corner| {
+                transform.transform_point(Vec3::new(
+                    if corner & 1 == 0 { min.x } else { max.x },
+                    if corner & 2 == 0 { min.y } else { max.y },
+                    if corner & 4 == 0 { min.z } else { max.z },
+                ))
+            })
+            .fold(
+                (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+                |(min, max), point| (min.min(point), max.max(point)),
+            )
+    }
+
+    // cubic units once scaled, 0 for heightfields and trimeshes which have no inside
+    pub fn volume(&self, scale: Vec3) -> f32 {
+        let scale = scale.abs();
+        match self {
+            Collider::Sphere { radius } => {
+                let radius = radius * scale.max_element();
+                4.0 / 3.0 * std::f32::consts::PI * radius.powi(3)
+            }
+            Collider::Cuboid { half_extents } => (*half_extents * scale * 2.0).element_product(),
+            Collider::Capsule {
+                half_height,
+                radius,
+            } => {
+                let radius = radius * scale.x.max(scale.z);
+                let half_height = half_height * scale.y;
+                std::f32::consts::PI * radius * radius * (2.0 * half_height + 4.0 / 3.0 * radius)
+            }
+            // tetrahedra from the origin to each face
+            Collider::ConvexHull(hull) => {
+                let points = hull.points();
+                hull.faces()
+                    .iter()
+                    .map(|&[a, b, c]| {
+                        let [a, b, c] = [a, b, c].map(|index| points[index as usize] * scale);
+                        a.dot(b.cross(c)) / 6.0
+                    })
+                    .sum::<f32>()
+                    .abs()
+            }
+            Collider::Heightfield(_) | Collider::TriMesh(_) => 0.0,
+            Collider::Compound(parts) => parts
+                .iter()
+                .map(|(offset, part)| part.volume(scale * offset.scale))
+                .sum(),
+        }
+    }
+
+    // distance along the ray to the first hit, if any
+    pub fn intersect_ray(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        match *self {
+            Collider::Sphere { radius } => {
+                let radius = radius * transform.scale.abs().max_element();
+                let offset = ray.origin - transform.translation;
+                let b = offset.dot(ray.direction);
+                let c = offset.length_squared() - radius * radius;
+                if c > 0.0 && b > 0.0 {
+                    return None;
+                }
+                let discriminant = b * b - c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                Some((-b - discriminant.sqrt()).max(0.0))
+            }
+            Collider::Cuboid { half_extents } => {
+                // slab test in the box's local space, scale folded into the extents
+                let inverse = transform.rotation.inverse();
+                let origin = inverse * (ray.origin - transform.translation);
+                let direction = inverse * ray.direction;
+                let half_extents = half_extents * transform.scale.abs();
+
+                let inv_dir = direction.recip();
+                let t1 = (-half_extents - origin) * inv_dir;
+                let t2 = (half_extents - origin) * inv_dir;
+                let near = t1.min(t2).max_element();
+                let far = t1.max(t2).min_element();
+                if near > far || far < 0.0 {
+                    return None;
+                }
+                Some(near.max(0.0))
+            }
+            Collider::Capsule {
+                half_height,
+                radius,
+            } => {
+                // scaled along y for the length and by the widest other axis for the radius
+                let scale = transform.scale.abs();
+                let radius = radius * scale.x.max(scale.z);
+                let half_height = half_height * scale.y;
+                let inverse = transform.rotation.inverse();
+                let origin = inverse * (ray.origin - transform.translation);
+                let direction = inverse * ray.direction;
+                intersect_capsule(origin, direction, half_height, radius)
+            }
+            Collider::ConvexHull(ref hull) => {
+                // into the hull's space, scale included, so distances stay the world's
+                let inverse = transform.compute_matrix().inverse();
+                hull.intersect_ray(
+                    inverse.transform_point3(ray.origin),
+                    inverse.transform_vector3(ray.direction),
+                )
+            }
+            Collider::Heightfield(ref heightfield) => {
+                let inverse = transform.compute_matrix().inverse();
+                heightfield.intersect_ray(
+                    inverse.transform_point3(ray.origin),
+                    inverse.transform_vector3(ray.direction),
+                )
+            }
+            Collider::TriMesh(ref trimesh) => {
+                let inverse = transform.compute_matrix().inverse();
+                trimesh.intersect_ray(
+                    inverse.transform_point3(ray.origin),
+                    inverse.transform_vector3(ray.direction),
+                )
+            }
+            Collider::Compound(ref parts) => parts
+                .iter()
+                .filter_map(|(offset, part)| {
+                    part.intersect_ray(&transform.mul_transform(offset), ray)
+                })
+                .min_by(f32::total_cmp),
+        }
+    }
+}
+
+// a capsule between -half_height and half_height on y, for a normalized direction
+fn intersect_capsule(origin: Vec3, direction: Vec3, half_height: f32, radius: f32) -> Option<f32> {
+    let closest = |y: f32| Vec3::new(0.0, y.clamp(-half_height, half_height), 0.0);
+    if origin.distance(closest(origin.y)) <= radius {
+        return Some(0.0);
+    }
+    // the side, an infinite cylinder cut to the straight part
+    let flat_origin = Vec3::new(origin.x, 0.0, origin.z);
+    let flat_direction = Vec3::new(direction.x, 0.0, direction.z);
+    let a = flat_direction.length_squared();
+    let mut best: Option<f32> = None;
+    if a > 1e-12 {
+        let b = flat_origin.dot(flat_direction);
+        let c = flat_origin.length_squared() - radius * radius;
+        let discriminant = b * b - a * c;
+        if discriminant >= 0.0 {
+            let t = (-b - discriminant.sqrt()) / a;
+            if t >= 0.0 && (origin.y + direction.y * t).abs() <= half_height {
+                best = Some(t);
+            }
+        }
+    }
+    // and the caps
+    for center in [Vec3::Y * half_height, Vec3::NEG_Y * half_height] {
+        let offset = origin - center;
+        let b = offset.dot(direction);
+        let c = offset.length_squared() - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            continue;
+        }
+        let t = -b - discriminant.sqrt();
+        if t >= 0.0 && best.is_none_or(|best| t < best) {
+            best = Some(t);
+        }
+    }
+    best
+}
@@ -0,0 +1,403 @@
+// Colliders fitted to render meshes, so they don't have to be built by hand.
+// A box, sphere or capsule around the mesh for the cheap cases, its convex
+// hull for a tight convex shape, and an approximate convex decomposition for
+// concave meshes: the triangles are split in half again and again, where the
+// hull of a part strays furthest from the part's own surface, until the hulls
+// are close enough or there are `max_parts` of them. Shapes that don't sit on
+// the mesh's origin come back inside a Compound. Set `collider` on MeshImport
+// to have them made as meshes are added.
+
+use glam::{Quat, Vec3};
+
+use crate::{mesh::Mesh, transform::Transform};
+
+use super::{Collider, hull::ConvexHull, trimesh::TriMesh};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    pub max_parts: usize,
+    // how far a part's hull may reach past its surface, as a share of the mesh's size
+    pub max_concavity: f32,
+}
+
+impl Default for Decomposition {
+    fn default() -> Self {
+        Self {
+            max_parts: 8,
+            max_concavity: 0.02,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderFit {
+    // the mesh's bounds
+    Box,
+    Sphere,
+    // along the mesh's longest axis
+    Capsule,
+    ConvexHull,
+    Decomposition(Decomposition),
+    // every triangle, for level geometry that doesn't move
+    TriMesh,
+}
+
+impl ColliderFit {
+    // None for a mesh without triangles, flat meshes get a thin hull
+    pub fn fit(&self, mesh: &Mesh) -> Option<Collider> {
+        match self {
+            ColliderFit::Box => fit_box(mesh),
+            ColliderFit::Sphere => fit_sphere(mesh),
+            ColliderFit::Capsule => fit_capsule(mesh),
+            ColliderFit::ConvexHull => convex_hull(mesh),
+            ColliderFit::Decomposition(settings) => convex_decomposition(mesh, settings),
+            ColliderFit::TriMesh => {
+                (!mesh.vertices.is_empty()).then(|| Collider::TriMesh(TriMesh::from_mesh(mesh)))
+            }
+        }
+    }
+}
+
+fn positions(mesh: &Mesh) -> Vec<Vec3> {
+    mesh.vertices
+        .iter()
+        .map(|vertex| Vec3::from_slice(&vertex.position[..3]))
+        .collect()
+}
+
+// the collider as is on the origin, otherwise moved into place
+fn placed(translation: Vec3, rotation: Quat, collider: Collider) -> Collider {
+    if translation.length_squared() < 1e-12 && rotation.abs_diff_eq(Quat::IDENTITY, 1e-6) {
+        return collider;
+    }
+    let offset = Transform {
+        translation,
+        rotation,
+        ..Default::default()
+    };
+    Collider::Compound(vec![(offset, collider)])
+}
+
+pub fn fit_box(mesh: &Mesh) -> Option<Collider> {
+    let (min, max) = mesh.aabb()?;
+    Some(placed(
+        (min + max) * 0.5,
+        Quat::IDENTITY,
+        Collider::cuboid((max - min) * 0.5),
+    ))
+}
+
+// ritter's, from the two points furthest apart along the way, then grown over what's left out
+pub fn fit_sphere(mesh: &Mesh) -> Option<Collider> {
+    let points = positions(mesh);
+    let first = *points.first()?;
+    let furthest = |from: Vec3| {
+        points
+            .iter()
+            .copied()
+            .max_by(|a, b| {
+                a.distance_squared(from)
+                    .total_cmp(&b.distance_squared(from))
+            })
+            .unwrap_or(from)
+    };
+    let a = furthest(first);
+    let b = furthest(a);
+    let mut center = (a + b) * 0.5;
+    let mut radius = a.distance(b) * 0.5;
+    for &point in &points {
+        let distance = point.distance(center);
+        if distance > radius {
+            let grown = (radius + distance) * 0.5;
+            center += (point - center) * ((grown - radius) / distance);
+            radius = grown;
+        }
+    }
+    Some(placed(center, Quat::IDENTITY, Collider::sphere(radius)))
+}
+
+pub fn fit_capsule(mesh: &Mesh) -> Option<Collider> {
+    let (min, max) = mesh.aabb()?;
+    let extent = max - min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        Vec3::X
+    } else if extent.y >= extent.z {
+        Vec3::Y
+    } else {
+        Vec3::Z
+    };
+    let center = (min + max) * 0.5;
+    let points = positions(mesh);
+    // wide enough for every point, then only as long as the caps need
+    let split = |point: Vec3| {
+        let offset = point - center;
+        let along = offset.dot(axis);
+        (along, (offset - axis * along).length())
+    };
+    let radius = points
+        .iter()
+        .map(|&point| split(point).1)
+        .fold(0.0f32, f32::max)
+        .max(1e-4);
+    let half_height = points
+        .iter()
+        .map(|&point| {
+            let (along, across) = split(point);
+            along.abs() - (radius * radius - across * across).max(0.0).sqrt()
+        })
+        .fold(0.0f32, f32::max);
+    Some(placed(
+        center,
+        Quat::from_rotation_arc(Vec3::Y, axis),
+        Collider::capsule(half_height, radius),
+    ))
+}
+
+// the hull of `points`, or of a thin slab when they're flat
+fn hull_of(points: &[Vec3], normal: Vec3, thickness: f32) -> Option<ConvexHull> {
+    ConvexHull::new(points).or_else(|| {
+        let slab: Vec<Vec3> = points
+            .iter()
+            .flat_map(|&point| [point, point - normal * thickness])
+            .collect();
+        ConvexHull::new(&slab)
+    })
+}
+
+fn flat_normal(triangles: &[[Vec3; 3]]) -> Vec3 {
+    triangles
+        .iter()
+        .map(|[a, b, c]| (*b - *a).cross(*c - *a))
+        .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        .and_then(Vec3::try_normalize)
+        .unwrap_or(Vec3::Y)
+}
+
+pub fn convex_hull(mesh: &Mesh) -> Option<Collider> {
+    let (min, max) = mesh.aabb()?;
+    let hull = hull_of(
+        &positions(mesh),
+        flat_normal(&mesh.triangles()),
+        (max - min).length() * 1e-3,
+    )?;
+    Some(Collider::ConvexHull(hull))
+}
+
+struct Part {
+    triangles: Vec<[Vec3; 3]>,
+    hull: ConvexHull,
+    // deepest any of the part's surface is inside its hull
+    concavity: f32,
+}
+
+impl Part {
+    fn new(triangles: Vec<[Vec3; 3]>, thickness: f32) -> Option<Self> {
+        let points: Vec<Vec3> = triangles.iter().flatten().copied().collect();
+        let hull = hull_of(&points, flat_normal(&triangles), thickness)?;
+        // the middles too, a big face can sink in without any of its corners
+        let concavity = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                [
+                    a,
+                    b,
+                    c,
+                    (a + b) * 0.5,
+                    (b + c) * 0.5,
+                    (c + a) * 0.5,
+                    (a + b + c) / 3.0,
+                ]
+            })
+            .map(|point| hull.depth(point))
+            .fold(0.0f32, f32::max);
+        Some(Self {
+            triangles,
+            hull,
+            concavity,
+        })
+    }
+
+    // halves at the median along whichever axis leaves the flattest parts
+    fn split(&self, thickness: f32) -> Option<(Part, Part)> {
+        if self.triangles.len() < 2 {
+            return None;
+        }
+        let center = |triangle: &[Vec3; 3]| (triangle[0] + triangle[1] + triangle[2]) / 3.0;
+        (0..3)
+            .filter_map(|axis| {
+                let mut triangles = self.triangles.clone();
+                triangles.sort_by(|a, b| center(a)[axis].total_cmp(&center(b)[axis]));
+                let second = triangles.split_off(triangles.len() / 2);
+                Some((
+                    Part::new(triangles, thickness)?,
+                    Part::new(second, thickness)?,
+                ))
+            })
+            .min_by(|(a, b), (c, d)| {
+                a.concavity
+                    .max(b.concavity)
+                    .total_cmp(&c.concavity.max(d.concavity))
+            })
+    }
+}
+
+pub fn convex_decomposition(mesh: &Mesh, settings: &Decomposition) -> Option<Collider> {
+    let (min, max) = mesh.aabb()?;
+    let size = (max - min).length();
+    let thickness = size * 1e-3;
+    let threshold = settings.max_concavity * size;
+    let mut parts = vec![Part::new(mesh.triangles(), thickness)?];
+    // parts that can't be split any further
+    let mut done = Vec::new();
+    while parts.len() + done.len() < settings.max_parts.max(1) {
+        let Some(worst) =
+            (0..parts.len()).max_by(|&a, &b| parts[a].concavity.total_cmp(&parts[b].concavity))
+        else {
+            break;
+        };
+        if parts[worst].concavity <= threshold {
+            break;
+        }
+        let part = parts.swap_remove(worst);
+        match part.split(thickness) {
+            Some((a, b)) => parts.extend([a, b]),
+            None => done.push(part),
+        }
+    }
+    let mut hulls: Vec<Collider> = parts
+        .into_iter()
+        .chain(done)
+        .map(|part| Collider::ConvexHull(part.hull))
+        .collect();
+    if hulls.len() == 1 {
+        return hulls.pop();
+    }
+    Some(Collider::Compound(
+        hulls
+            .into_iter()
+            .map(|hull| (Transform::default(), hull))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(mut mesh: Mesh, offset: Vec3, scale: Vec3) -> Mesh {
+        for vertex in &mut mesh.vertices {
+            let position = Vec3::from_slice(&vertex.position[..3]) * scale + offset;
+            vertex.position = position.extend(1.0).to_array();
+        }
+        mesh
+    }
+
+    // three unit cubes in an L on the xy plane, the corner at (1, 1) left out
+    fn l_shape() -> Mesh {
+        let mut mesh = Mesh::cube(1.0);
+        for offset in [Vec3::X, Vec3::Y] {
+            let cube = moved(Mesh::cube(1.0), offset, Vec3::ONE);
+            let first = mesh.vertices.len() as u32;
+            mesh.indices
+                .extend(cube.indices.iter().map(|index| first + index));
+            mesh.vertices.extend(cube.vertices);
+        }
+        mesh
+    }
+
+    #[test]
+    fn primitives_wrap_the_mesh() {
+        let cube = moved(Mesh::cube(2.0), Vec3::new(3.0, 0.0, 0.0), Vec3::ONE);
+        let Some(Collider::Compound(parts)) = fit_box(&cube) else {
+            panic!("an offset box is placed in a compound");
+        };
+        assert_eq!(parts[0].0.translation, Vec3::new(3.0, 0.0, 0.0));
+        assert!(
+            matches!(parts[0].1, Collider::Cuboid { half_extents } if half_extents == Vec3::ONE)
+        );
+
+        let Some(Collider::Sphere { radius }) = fit_sphere(&Mesh::sphere(1.0, 16, 8)) else {
+            panic!("a sphere around the origin stays one");
+        };
+        assert!((1.0..1.05).contains(&radius));
+
+        let tall = moved(
+            Mesh::sphere(1.0, 16, 8),
+            Vec3::ZERO,
+            Vec3::new(1.0, 3.0, 1.0),
+        );
+        let Some(Collider::Capsule {
+            half_height,
+            radius,
+        }) = fit_capsule(&tall)
+        else {
+            panic!("a capsule along y needs no rotation");
+        };
+        assert!((radius - 1.0).abs() < 0.01);
+        assert!(half_height > 1.0 && half_height + radius >= 3.0 - 1e-4);
+
+        let mut empty = Mesh::cube(1.0);
+        empty.vertices.clear();
+        empty.indices.clear();
+        assert!(ColliderFit::Box.fit(&empty).is_none());
+        assert!(ColliderFit::TriMesh.fit(&empty).is_none());
+    }
+
+    #[test]
+    fn hulls_of_flat_and_solid_meshes() {
+        let Some(Collider::ConvexHull(hull)) = convex_hull(&Mesh::plane(2.0, 1)) else {
+            panic!("a flat mesh gets a thin hull");
+        };
+        assert!(hull.depth(Vec3::new(0.5, -1e-3, 0.5)) >= 0.0);
+        assert!(hull.depth(Vec3::new(0.5, 0.1, 0.5)) < 0.0);
+
+        let Some(Collider::ConvexHull(hull)) = convex_hull(&l_shape()) else {
+            panic!("the hull is one shape");
+        };
+        assert!(hull.depth(Vec3::new(0.9, 0.9, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn concave_meshes_split_into_convex_parts() {
+        let Some(Collider::ConvexHull(_)) =
+            convex_decomposition(&Mesh::cube(1.0), &Decomposition::default())
+        else {
+            panic!("a convex mesh stays in one part");
+        };
+
+        let settings = Decomposition::default();
+        let Some(Collider::Compound(parts)) = convex_decomposition(&l_shape(), &settings) else {
+            panic!("the L is split");
+        };
+        assert!((2..=settings.max_parts).contains(&parts.len()));
+        let hulls: Vec<&ConvexHull> = parts
+            .iter()
+            .map(|(_, part)| match part {
+                Collider::ConvexHull(hull) => hull,
+                other => panic!("{other:?} isn't a hull"),
+            })
+            .collect();
+        // the far end of the missing corner is left empty, the arms are still covered
+        assert!(
+            hulls
+                .iter()
+                .all(|hull| hull.depth(Vec3::new(1.4, 1.4, 0.0)) < 0.0)
+        );
+        for point in [
+            Vec3::ZERO,
+            Vec3::new(1.2, 0.0, 0.0),
+            Vec3::new(0.0, 1.2, 0.0),
+        ] {
+            assert!(hulls.iter().any(|hull| hull.depth(point) > 0.0), "{point}");
+        }
+
+        let one = Decomposition {
+            max_parts: 1,
+            ..settings
+        };
+        assert!(matches!(
+            convex_decomposition(&l_shape(), &one),
+            Some(Collider::ConvexHull(_))
+        ));
+    }
+}
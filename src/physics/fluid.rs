@@ -0,0 +1,235 @@
+// Water and other fluids that bodies float in. A FluidVolume fills a box
+// around its entity, the box's top is the surface. Each physics step, a rigid
+// body with a collider whose center is over the box is pushed up by the weight
+// of the fluid it displaces, judged from how much of its bounds are under the
+// surface, and dragged toward the fluid's flow. Bodies coming in and going out
+// send FluidEntered and FluidExited for splashes, from FixedUpdate, so they're
+// read in Ui or the next FixedUpdate.
+
+use std::collections::HashSet;
+
+use glam::Vec3;
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    transform::{self, Transform},
+};
+
+use super::{Collider, body::Gravity, body::RigidBody};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FluidVolume {
+    // around the entity, scaled and turned with it
+    pub half_extents: Vec3,
+    // kilograms per cubic unit, water is 1000
+    pub density: f32,
+    // world space, the fluid's own velocity, a river's current
+    pub flow: Vec3,
+    // how quickly a fully submerged body takes on the flow, per second
+    pub linear_drag: f32,
+    pub angular_drag: f32,
+}
+
+impl Component for FluidVolume {}
+
+impl Default for FluidVolume {
+    fn default() -> Self {
+        Self::water(Vec3::ONE)
+    }
+}
+
+impl FluidVolume {
+    pub fn water(half_extents: Vec3) -> Self {
+        Self {
+            half_extents,
+            density: 1000.0,
+            flow: Vec3::ZERO,
+            linear_drag: 1.0,
+            angular_drag: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FluidEntered {
+    pub body: Entity,
+    pub volume: Entity,
+    // where it broke the surface
+    pub point: Vec3,
+    pub velocity: Vec3,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FluidExited {
+    pub body: Entity,
+    pub volume: Entity,
+    pub point: Vec3,
+    pub velocity: Vec3,
+}
+
+// the bodies in each volume as of the last step
+#[derive(Debug, Default)]
+pub(crate) struct FluidContacts {
+    inside: HashSet<(Entity, Entity)>,
+}
+
+impl Component for FluidContacts {}
+
+// the share of `bounds` under the volume's surface, and the surface point over
+// `center`. None when `center` isn't over the volume
+fn submerged(
+    volume: &FluidVolume,
+    matrix: glam::Mat4,
+    center: Vec3,
+    (min, max): (Vec3, Vec3),
+) -> Option<(f32, Vec3)> {
+    let local = matrix.inverse().transform_point3(center);
+    let extents = volume.half_extents;
+    if local.x.abs() > extents.x || local.z.abs() > extents.z {
+        return None;
+    }
+    let surface = matrix.transform_point3(Vec3::new(local.x, extents.y, local.z));
+    let bottom = matrix.transform_point3(Vec3::new(local.x, -extents.y, local.z));
+    if max.y < bottom.y {
+        return None;
+    }
+    let height = (max.y - min.y).max(f32::EPSILON);
+    let fraction = ((surface.y - min.y) / height).clamp(0.0, 1.0);
+    (fraction > 0.0).then_some((fraction, Vec3::new(center.x, surface.y, center.z)))
+}
+
+pub fn apply_fluids(world: &mut World) {
+    let volumes: Vec<_> = world
+        .query::<FluidVolume>()
+        .into_iter()
+        .map(|(entity, volume)| (entity, *volume, transform::model_matrix(world, entity)))
+        .collect();
+    let gravity = world
+        .get_resource::<Gravity>()
+        .copied()
+        .unwrap_or_default()
+        .0;
+
+    let mut inside = HashSet::new();
+    let mut forces = Vec::new();
+    let mut entered = Vec::new();
+    let previous = world
+        .get_resource_mut::<FluidContacts>()
+        .map(|contacts| std::mem::take(&mut contacts.inside))
+        .unwrap_or_default();
+    for (entity, body) in world.query::<RigidBody>() {
+        let (Some(collider), Some(_)) = (
+            world.get_component::<Collider>(entity),
+            world.get_component::<Transform>(entity),
+        ) else {
+            continue;
+        };
+        // bodies can be parented, the volumes are compared in world space
+        let transform = Transform::from_matrix(transform::current_model_matrix(world, entity));
+        let bounds = collider.aabb(&transform);
+        let center = (bounds.0 + bounds.1) * 0.5;
+        let found = volumes.iter().find_map(|(volume_entity, volume, matrix)| {
+            submerged(volume, *matrix, center, bounds)
+                .map(|(fraction, point)| (*volume_entity, volume, fraction, point))
+        });
+        let Some((volume_entity, volume, fraction, point)) = found else {
+            continue;
+        };
+
+        // archimedes, the weight of the fluid pushed aside
+        let displaced = collider.volume(transform.scale) * fraction;
+        let buoyancy = -gravity * volume.density * displaced;
+        let drag = (volume.flow - body.velocity) * volume.linear_drag * fraction * body.mass;
+        let torque = -body.angular_velocity * volume.angular_drag * fraction * body.mass;
+        forces.push((entity, buoyancy + drag, torque));
+
+        inside.insert((entity, volume_entity));
+        if !previous.contains(&(entity, volume_entity)) {
+            entered.push(FluidEntered {
+                body: entity,
+                volume: volume_entity,
+                point,
+                velocity: body.velocity,
+            });
+        }
+    }
+
+    let exited: Vec<FluidExited> = previous
+        .difference(&inside)
+        .map(|&(body, volume)| {
+            let point = transform::current_model_matrix(world, body)
+                .w_axis
+                .truncate();
+            FluidExited {
+                body,
+                volume,
+                point,
+                velocity: world
+                    .get_component::<RigidBody>(body)
+                    .map_or(Vec3::ZERO, |body| body.velocity),
+            }
+        })
+        .collect();
+    for (entity, force, torque) in forces {
+        if let Some(body) = world.get_component_mut::<RigidBody>(entity) {
+            body.apply_force(force);
+            body.apply_torque(torque);
+        }
+    }
+    for event in entered {
+        world.send_event(event);
+    }
+    for event in exited {
+        world.send_event(event);
+    }
+    if let Some(contacts) = world.get_resource_mut::<FluidContacts>() {
+        contacts.inside = inside;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::schedule::{FixedUpdate, Last},
+        hierarchy,
+        test_utils::TestWorld,
+    };
+
+    #[test]
+    fn parented_bodies_enter_and_leave_in_world_space() {
+        let mut test = TestWorld::empty()
+            .with_resource(Gravity(Vec3::ZERO))
+            .with_resource(FluidContacts::default())
+            .with_system(FixedUpdate, apply_fluids)
+            .with_system(Last, hierarchy::propagate_transforms);
+        test.capture_events::<FluidEntered>();
+        test.capture_events::<FluidExited>();
+        test.spawn()
+            .insert(Transform::IDENTITY)
+            .insert(FluidVolume::water(Vec3::splat(2.0)));
+        let boat = test
+            .spawn()
+            .insert(Transform::from_translation(Vec3::new(100.0, 0.0, 0.0)))
+            .id();
+        let barrel = test
+            .spawn()
+            .insert(Transform::from_translation(Vec3::new(-100.0, 0.0, 0.0)))
+            .insert(Collider::sphere(0.5))
+            .insert(RigidBody::default())
+            .id();
+        hierarchy::set_parent(&mut test.world, barrel, Some(boat));
+        test.ticks(2);
+        test.assert_event_count::<FluidEntered>(1);
+
+        test.world
+            .get_component_mut::<Transform>(boat)
+            .unwrap()
+            .translation
+            .x = 110.0;
+        test.ticks(2);
+        test.assert_event_sent::<FluidExited>(|exited| {
+            exited.body == barrel && exited.point.abs_diff_eq(Vec3::new(10.0, 0.0, 0.0), 1e-4)
+        });
+    }
+}
@@ -0,0 +1,218 @@
+// Area forces. A ForceField pushes on every rigid body within its radius each
+// physics step, weakened toward the edge by its falloff: wind blows one way,
+// a radial field pushes away from the entity (or pulls in, when negative) and
+// a vortex swirls around the entity's up axis. Strengths are accelerations, so
+// a crate and a feather in the same wind move alike. `sample` gives the sum at
+// any point for things that aren't bodies, foliage adds it to the global wind
+// and particles can add it to their velocity. `explode` is the one-off kind.
+
+use glam::Vec3;
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    transform::{self, Transform},
+};
+
+use super::{Collider, body::RigidBody};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Falloff {
+    // full strength up to the radius
+    Constant,
+    Linear,
+    // drops off quickly, (1 - t)²
+    Quadratic,
+    // eases out at the center and in at the edge
+    Smooth,
+}
+
+impl Falloff {
+    // the share of the strength left at `distance`
+    pub fn factor(&self, distance: f32, radius: f32) -> f32 {
+        if distance > radius {
+            return 0.0;
+        }
+        let t = if radius.is_finite() && radius > 0.0 {
+            (distance / radius).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Quadratic => (1.0 - t) * (1.0 - t),
+            Falloff::Smooth => 1.0 - t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForceFieldKind {
+    // along a world direction
+    Wind { direction: Vec3 },
+    // away from the entity
+    Radial,
+    // around the entity's local y axis, `pull` is the share drawn in toward it
+    Vortex { pull: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForceField {
+    pub kind: ForceFieldKind,
+    // units per second squared at the entity
+    pub strength: f32,
+    // infinite for everywhere
+    pub radius: f32,
+    pub falloff: Falloff,
+}
+
+impl Component for ForceField {}
+
+impl ForceField {
+    pub fn wind(direction: Vec3, strength: f32) -> Self {
+        Self {
+            kind: ForceFieldKind::Wind { direction },
+            strength,
+            radius: f32::INFINITY,
+            falloff: Falloff::Constant,
+        }
+    }
+
+    pub fn radial(strength: f32, radius: f32) -> Self {
+        Self {
+            kind: ForceFieldKind::Radial,
+            strength,
+            radius,
+            falloff: Falloff::Linear,
+        }
+    }
+
+    pub fn vortex(strength: f32, radius: f32, pull: f32) -> Self {
+        Self {
+            kind: ForceFieldKind::Vortex { pull },
+            strength,
+            radius,
+            falloff: Falloff::Smooth,
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    // the acceleration at `point` from the field placed at `origin`, its up axis `up`
+    pub fn at(&self, origin: Vec3, up: Vec3, point: Vec3) -> Vec3 {
+        let offset = point - origin;
+        let factor = self.falloff.factor(offset.length(), self.radius) * self.strength;
+        if factor == 0.0 {
+            return Vec3::ZERO;
+        }
+        match self.kind {
+            ForceFieldKind::Wind { direction } => direction.normalize_or_zero() * factor,
+            ForceFieldKind::Radial => offset.normalize_or_zero() * factor,
+            ForceFieldKind::Vortex { pull } => {
+                let up = up.normalize_or(Vec3::Y);
+                let from_axis = offset - up * offset.dot(up);
+                let around = up.cross(from_axis).normalize_or_zero();
+                (around * (1.0 - pull.abs()) - from_axis.normalize_or_zero() * pull) * factor
+            }
+        }
+    }
+}
+
+// every field's acceleration at `point` added up
+pub fn sample(world: &World, point: Vec3) -> Vec3 {
+    world
+        .query::<ForceField>()
+        .into_iter()
+        .map(|(entity, field)| {
+            let matrix = transform::model_matrix(world, entity);
+            field.at(
+                matrix.w_axis.truncate(),
+                matrix.transform_vector3(Vec3::Y),
+                point,
+            )
+        })
+        .sum()
+}
+
+// where a body is pushed from in world space, the middle of its collider or its origin
+fn body_center(world: &World, entity: Entity) -> Vec3 {
+    let transform = Transform::from_matrix(transform::current_model_matrix(world, entity));
+    world
+        .get_component::<Collider>(entity)
+        .map_or(transform.translation, |collider| {
+            let (min, max) = collider.aabb(&transform);
+            (min + max) * 0.5
+        })
+}
+
+pub fn apply_force_fields(world: &mut World) {
+    if world.query::<ForceField>().is_empty() {
+        return;
+    }
+    let forces: Vec<_> = world
+        .query::<RigidBody>()
+        .into_iter()
+        .filter_map(|(entity, body)| {
+            world.get_component::<Transform>(entity)?;
+            let acceleration = sample(world, body_center(world, entity));
+            Some((entity, acceleration * body.mass))
+        })
+        .collect();
+    for (entity, force) in forces {
+        if let Some(body) = world.get_component_mut::<RigidBody>(entity) {
+            body.apply_force(force);
+        }
+    }
+}
+
+// a one-off push away from `center` for every body within `radius`, `impulse`
+// in newton seconds at the center, so lighter bodies fly further
+pub fn explode(world: &mut World, center: Vec3, radius: f32, impulse: f32, falloff: Falloff) {
+    let impulses: Vec<_> = world
+        .query::<RigidBody>()
+        .into_iter()
+        .filter_map(|(entity, _)| {
+            world.get_component::<Transform>(entity)?;
+            let offset = body_center(world, entity) - center;
+            let factor = falloff.factor(offset.length(), radius);
+            // straight up when it's right on the center
+            let direction = offset.try_normalize().unwrap_or(Vec3::Y);
+            (factor > 0.0).then_some((entity, direction * impulse * factor))
+        })
+        .collect();
+    for (entity, impulse) in impulses {
+        if let Some(body) = world.get_component_mut::<RigidBody>(entity) {
+            body.apply_impulse(impulse);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hierarchy;
+
+    #[test]
+    fn explosions_push_parented_bodies_from_where_they_are() {
+        let mut world = World::new();
+        let ship = world
+            .spawn()
+            .insert(Transform::from_translation(Vec3::new(0.0, 0.0, 5.0)))
+            .id();
+        let cargo = world
+            .spawn()
+            .insert(Transform::IDENTITY)
+            .insert(RigidBody::new(2.0))
+            .id();
+        hierarchy::set_parent(&mut world, cargo, Some(ship));
+        explode(&mut world, Vec3::ZERO, 10.0, 4.0, Falloff::Constant);
+        let velocity = world.get_component::<RigidBody>(cargo).unwrap().velocity;
+        assert!(
+            velocity.abs_diff_eq(Vec3::new(0.0, 0.0, 2.0), 1e-5),
+            "{velocity}"
+        );
+    }
+}
@@ -0,0 +1,392 @@
+// Heightfield colliders for terrain. A grid of heights over the xz plane,
+// centered on the origin and split into two triangles per cell the way
+// Mesh::plane is, so a plane displaced for terrain can be turned into one with
+// `from_plane`, or a heightfield turned into the mesh to draw with `mesh`. A
+// ray walks the cells it passes over in order and stops at the first with a
+// hit, and contacts only look at the cells under the box they're asked about.
+
+use glam::{Vec2, Vec3};
+
+use crate::{Vertex, bvh::ray_triangle, mesh::Mesh};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightfield {
+    // rows along z of heights along x
+    heights: Vec<f32>,
+    columns: usize,
+    rows: usize,
+    // across the whole grid, x and z
+    size: Vec2,
+}
+
+impl Heightfield {
+    // `columns` by `rows` heights, at least 2 of each
+    pub fn new(columns: usize, rows: usize, size: Vec2, heights: Vec<f32>) -> anyhow::Result<Self> {
+        if columns < 2 || rows < 2 {
+            anyhow::bail!("A heightfield needs at least 2x2 heights, got {columns}x{rows}");
+        }
+        if heights.len() != columns * rows {
+            anyhow::bail!(
+                "Expected {} heights for {columns}x{rows}, got {}",
+                columns * rows,
+                heights.len()
+            );
+        }
+        Ok(Self {
+            heights,
+            columns,
+            rows,
+            size,
+        })
+    }
+
+    // `height` gets each point's x and z
+    pub fn from_fn(columns: usize, rows: usize, size: Vec2, height: impl Fn(Vec2) -> f32) -> Self {
+        let (columns, rows) = (columns.max(2), rows.max(2));
+        let mut field = Self {
+            heights: Vec::with_capacity(columns * rows),
+            columns,
+            rows,
+            size,
+        };
+        for z in 0..rows {
+            for x in 0..columns {
+                let point = field.point(x, z);
+                field.heights.push(height(Vec2::new(point.x, point.z)));
+            }
+        }
+        field
+    }
+
+    // from a Mesh::plane whose vertices were only moved up and down, None for anything else
+    pub fn from_plane(mesh: &Mesh) -> Option<Self> {
+        let side = (mesh.vertices.len() as f64).sqrt() as usize;
+        if side < 2 || side * side != mesh.vertices.len() {
+            return None;
+        }
+        let (min, max) = mesh.aabb()?;
+        let size = Vec2::new(max.x - min.x, max.z - min.z);
+        let field = Self::new(
+            side,
+            side,
+            size,
+            mesh.vertices
+                .iter()
+                .map(|vertex| vertex.position[1])
+                .collect(),
+        )
+        .ok()?;
+        let tolerance = size.max_element() * 1e-4;
+        let on_grid = mesh.vertices.iter().enumerate().all(|(index, vertex)| {
+            let point = field.point(index % side, index / side);
+            (point.x - vertex.position[0]).abs() <= tolerance
+                && (point.z - vertex.position[2]).abs() <= tolerance
+        });
+        on_grid.then_some(field)
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    pub fn set_height(&mut self, x: usize, z: usize, height: f32) {
+        if x < self.columns && z < self.rows {
+            self.heights[z * self.columns + x] = height;
+        }
+    }
+
+    fn spacing(&self) -> Vec2 {
+        self.size / Vec2::new(self.columns as f32 - 1.0, self.rows as f32 - 1.0)
+    }
+
+    fn point(&self, x: usize, z: usize) -> Vec3 {
+        let spacing = self.spacing();
+        Vec3::new(
+            x as f32 * spacing.x - self.size.x * 0.5,
+            self.heights
+                .get(z * self.columns + x)
+                .copied()
+                .unwrap_or(0.0),
+            z as f32 * spacing.y - self.size.y * 0.5,
+        )
+    }
+
+    // the cell's two triangles, wound like Mesh::plane's
+    fn cell(&self, x: usize, z: usize) -> [[Vec3; 3]; 2] {
+        let a = self.point(x, z);
+        let b = self.point(x, z + 1);
+        let c = self.point(x + 1, z);
+        let d = self.point(x + 1, z + 1);
+        [[a, b, c], [c, b, d]]
+    }
+
+    // the surface's height over a local x and z, None off the grid
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let spacing = self.spacing();
+        let cell = (Vec2::new(x, z) + self.size * 0.5) / spacing;
+        let last = Vec2::new(self.columns as f32 - 1.0, self.rows as f32 - 1.0);
+        if cell.cmplt(Vec2::ZERO).any() || cell.cmpgt(last).any() {
+            return None;
+        }
+        let column = (cell.x as usize).min(self.columns - 2);
+        let row = (cell.y as usize).min(self.rows - 2);
+        let (fx, fz) = (cell.x - column as f32, cell.y - row as f32);
+        let [[a, b, c], [_, _, d]] = self.cell(column, row);
+        Some(if fx + fz <= 1.0 {
+            a.y + (c.y - a.y) * fx + (b.y - a.y) * fz
+        } else {
+            d.y + (b.y - d.y) * (1.0 - fx) + (c.y - d.y) * (1.0 - fz)
+        })
+    }
+
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let (low, high) = self
+            .heights
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), height| {
+                (low.min(*height), high.max(*height))
+            });
+        let half = self.size * 0.5;
+        (
+            Vec3::new(-half.x, low, -half.y),
+            Vec3::new(half.x, high, half.y),
+        )
+    }
+
+    // the midphase for contacts, the triangles of the cells under the local box
+    pub fn overlapping(&self, min: Vec3, max: Vec3) -> Vec<[Vec3; 3]> {
+        let spacing = self.spacing();
+        let half = self.size * 0.5;
+        let to_cell = |value: f32, half: f32, spacing: f32, cells: usize| {
+            (((value + half) / spacing).floor().max(0.0) as usize).min(cells - 1)
+        };
+        if max.x < -half.x || min.x > half.x || max.z < -half.y || min.z > half.y {
+            return Vec::new();
+        }
+        let columns = to_cell(min.x, half.x, spacing.x, self.columns - 1)
+            ..=to_cell(max.x, half.x, spacing.x, self.columns - 1);
+        let rows = to_cell(min.z, half.y, spacing.y, self.rows - 1)
+            ..=to_cell(max.z, half.y, spacing.y, self.rows - 1);
+        rows.flat_map(|z| columns.clone().map(move |x| (x, z)))
+            .flat_map(|(x, z)| self.cell(x, z))
+            .filter(|triangle| {
+                let low = triangle[0].y.min(triangle[1].y).min(triangle[2].y);
+                let high = triangle[0].y.max(triangle[1].y).max(triangle[2].y);
+                low <= max.y && high >= min.y
+            })
+            .collect()
+    }
+
+    // to draw the terrain with, normals from the neighbouring heights
+    pub fn mesh(&self) -> Mesh {
+        let spacing = self.spacing();
+        let height = |x: usize, z: usize| self.heights[z * self.columns + x];
+        let mut vertices = Vec::with_capacity(self.heights.len());
+        for z in 0..self.rows {
+            for x in 0..self.columns {
+                let slope_x = (height((x + 1).min(self.columns - 1), z)
+                    - height(x.saturating_sub(1), z))
+                    / (spacing.x * ((x + 1).min(self.columns - 1) - x.saturating_sub(1)) as f32);
+                let slope_z = (height(x, (z + 1).min(self.rows - 1))
+                    - height(x, z.saturating_sub(1)))
+                    / (spacing.y * ((z + 1).min(self.rows - 1) - z.saturating_sub(1)) as f32);
+                let u = x as f32 / (self.columns - 1) as f32;
+                let v = z as f32 / (self.rows - 1) as f32;
+                vertices.push(Vertex {
+                    position: self.point(x, z).extend(1.0).to_array(),
+                    tex_coords: [[u, v]; 2],
+                    normal: Vec3::new(-slope_x, 1.0, -slope_z).normalize().to_array(),
+                });
+            }
+        }
+        let mut indices = Vec::new();
+        let row = self.columns as u32;
+        for z in 0..self.rows as u32 - 1 {
+            for x in 0..row - 1 {
+                let a = z * row + x;
+                let (b, c, d) = (a + row, a + 1, a + row + 1);
+                indices.extend_from_slice(&[a, b, c, c, b, d]);
+            }
+        }
+        Mesh {
+            vertices,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    // local space, `direction` needn't be normalized and the result is in its lengths
+    pub(crate) fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let (min, max) = self.bounds();
+        let inverse = direction.recip();
+        let a = (min - origin) * inverse;
+        let b = (max - origin) * inverse;
+        let enter = a.min(b).max_element().max(0.0);
+        let exit = a.max(b).min_element();
+        if enter > exit {
+            return None;
+        }
+
+        // through the cells in the order the ray crosses them, from where it enters
+        let spacing = self.spacing();
+        let start = origin + direction * enter;
+        let cells = (self.columns - 1, self.rows - 1);
+        let to_cell = |value: f32, low: f32, spacing: f32, cells: usize| {
+            (((value - low) / spacing).floor().max(0.0) as usize).min(cells - 1)
+        };
+        let mut x = to_cell(start.x, min.x, spacing.x, cells.0);
+        let mut z = to_cell(start.z, min.z, spacing.y, cells.1);
+        let crossing = |cell: usize, low: f32, spacing: f32, origin: f32, direction: f32| {
+            if direction == 0.0 {
+                return (f32::INFINITY, f32::INFINITY);
+            }
+            let boundary = low + (cell + usize::from(direction > 0.0)) as f32 * spacing;
+            ((boundary - origin) / direction, (spacing / direction).abs())
+        };
+        let (mut next_x, step_x) = crossing(x, min.x, spacing.x, origin.x, direction.x);
+        let (mut next_z, step_z) = crossing(z, min.z, spacing.y, origin.z, direction.z);
+        loop {
+            let hit = self
+                .cell(x, z)
+                .iter()
+                .filter_map(|triangle| ray_triangle(origin, direction, triangle))
+                .min_by(f32::total_cmp);
+            if hit.is_some() {
+                return hit;
+            }
+            if next_x.min(next_z) > exit {
+                return None;
+            }
+            if next_x < next_z {
+                x = match direction.x > 0.0 {
+                    true if x + 1 < cells.0 => x + 1,
+                    false if x > 0 => x - 1,
+                    _ => return None,
+                };
+                next_x += step_x;
+            } else {
+                z = match direction.z > 0.0 {
+                    true if z + 1 < cells.1 => z + 1,
+                    false if z > 0 => z - 1,
+                    _ => return None,
+                };
+                next_z += step_z;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hills() -> Heightfield {
+        Heightfield::from_fn(9, 5, Vec2::new(8.0, 4.0), |point| {
+            point.x.sin() + point.y * 0.5
+        })
+    }
+
+    #[test]
+    fn heights_on_and_between_the_grid() {
+        assert!(Heightfield::new(1, 4, Vec2::ONE, vec![0.0; 4]).is_err());
+        assert!(Heightfield::new(2, 2, Vec2::ONE, vec![0.0; 3]).is_err());
+
+        let field = hills();
+        assert_eq!((field.columns(), field.rows()), (9, 5));
+        // grid points are exact, between them is on the cell's triangles
+        let expected = (-2.0f32).sin() - 0.5;
+        assert!((field.height_at(-2.0, -1.0).unwrap() - expected).abs() < 1e-5);
+        let (a, b) = (
+            field.height_at(0.0, 0.0).unwrap(),
+            field.height_at(1.0, 0.0).unwrap(),
+        );
+        assert!((field.height_at(0.5, 0.0).unwrap() - (a + b) * 0.5).abs() < 1e-5);
+        assert!(field.height_at(4.0, 2.0).is_some());
+        assert!(field.height_at(4.1, 0.0).is_none());
+
+        let mut field = field;
+        field.set_height(8, 4, 10.0);
+        field.set_height(9, 4, -10.0);
+        assert_eq!(field.height_at(4.0, 2.0), Some(10.0));
+        assert_eq!(field.bounds().1, Vec3::new(4.0, 10.0, 2.0));
+    }
+
+    #[test]
+    fn planes_and_meshes_round_trip() {
+        let mut plane = Mesh::plane(4.0, 3);
+        for vertex in &mut plane.vertices {
+            vertex.position[1] = vertex.position[0] * vertex.position[2];
+        }
+        let field = Heightfield::from_plane(&plane).unwrap();
+        assert_eq!(
+            (field.columns(), field.rows(), field.size()),
+            (5, 5, Vec2::splat(4.0))
+        );
+        let mesh = field.mesh();
+        assert_eq!(mesh.triangles(), plane.triangles());
+        assert!(Heightfield::from_plane(&Mesh::cube(1.0)).is_none());
+
+        // the midphase finds the cells under the box, nothing off the grid
+        let found = field.overlapping(Vec3::new(-0.5, -10.0, -0.5), Vec3::new(0.5, 10.0, 0.5));
+        assert_eq!(found.len(), 8);
+        assert!(
+            field
+                .overlapping(Vec3::splat(3.0), Vec3::splat(4.0))
+                .is_empty()
+        );
+        let above = field.overlapping(Vec3::new(-2.0, 5.0, -2.0), Vec3::new(2.0, 6.0, 2.0));
+        assert!(above.is_empty());
+    }
+
+    #[test]
+    fn rays_stop_at_the_first_cell_hit() {
+        let field = hills();
+        let triangles = field.mesh().triangles();
+        let origins = [
+            Vec3::new(-5.0, 3.0, -1.5),
+            Vec3::new(3.5, 4.0, 1.5),
+            Vec3::new(0.3, 5.0, 0.2),
+        ];
+        for origin in origins {
+            for target in [Vec3::new(3.0, -1.0, 1.0), Vec3::new(-2.5, 0.0, -0.5)] {
+                let direction = target - origin;
+                let expected = triangles
+                    .iter()
+                    .filter_map(|triangle| ray_triangle(origin, direction, triangle))
+                    .min_by(f32::total_cmp);
+                let hit = field.intersect_ray(origin, direction);
+                assert_eq!(hit.is_some(), expected.is_some(), "{origin} to {target}");
+                if let (Some(hit), Some(expected)) = (hit, expected) {
+                    assert!((hit - expected).abs() < 1e-5);
+                }
+            }
+        }
+        // straight down lands on the surface
+        let hit = field
+            .intersect_ray(Vec3::new(1.25, 10.0, 0.5), Vec3::NEG_Y)
+            .unwrap();
+        assert!((10.0 - hit - field.height_at(1.25, 0.5).unwrap()).abs() < 1e-4);
+        assert!(
+            field
+                .intersect_ray(Vec3::new(1.0, 10.0, 0.0), Vec3::Y)
+                .is_none()
+        );
+        assert!(
+            field
+                .intersect_ray(Vec3::new(9.0, 10.0, 0.0), Vec3::NEG_Y)
+                .is_none()
+        );
+    }
+}
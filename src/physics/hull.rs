@@ -0,0 +1,348 @@
+// Convex hulls of point clouds, by quickhull. A tetrahedron of extreme points
+// starts it, every other point goes to a face it's in front of, and the
+// furthest point in front of any face is added one at a time: the faces it can
+// see are removed and the hole is closed with new faces from their rim to the
+// point. Points within a small tolerance of a face count as on it, so nearly
+// flat sides don't fill up with slivers.
+
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvexHull {
+    points: Vec<Vec3>,
+    // counter clockwise seen from outside
+    faces: Vec<[u32; 3]>,
+}
+
+struct Face {
+    vertices: [u32; 3],
+    normal: Vec3,
+    offset: f32,
+    // points in front of it, not yet in the hull
+    outside: Vec<u32>,
+    alive: bool,
+}
+
+impl Face {
+    fn new(points: &[Vec3], vertices: [u32; 3]) -> Self {
+        let [a, b, c] = vertices.map(|vertex| points[vertex as usize]);
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        Self {
+            vertices,
+            normal,
+            offset: normal.dot(a),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    fn distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) - self.offset
+    }
+
+    fn edges(&self) -> [(u32, u32); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+// the first of `faces` that `point` is in front of gets it
+fn assign(faces: &mut [Face], candidates: &[usize], points: &[Vec3], point: u32, epsilon: f32) {
+    if let Some(&face) = candidates
+        .iter()
+        .find(|&&face| faces[face].distance(points[point as usize]) > epsilon)
+    {
+        faces[face].outside.push(point);
+    }
+}
+
+// four points that aren't flat, the first two furthest apart along an axis
+fn simplex(points: &[Vec3], epsilon: f32) -> Option<[u32; 4]> {
+    let extreme = |axis: usize, max: bool| {
+        (0..points.len())
+            .max_by(|&a, &b| {
+                let ordering = points[a][axis].total_cmp(&points[b][axis]);
+                if max { ordering } else { ordering.reverse() }
+            })
+            .unwrap_or(0)
+    };
+    let (a, b) = (0..3)
+        .map(|axis| (extreme(axis, false), extreme(axis, true)))
+        .max_by(|x, y| {
+            let spread = |(a, b): (usize, usize)| points[a].distance_squared(points[b]);
+            spread(*x).total_cmp(&spread(*y))
+        })?;
+    let line = (points[b] - points[a]).normalize_or_zero();
+    let from_line = |point: Vec3| {
+        let offset = point - points[a];
+        (offset - line * offset.dot(line)).length()
+    };
+    let c =
+        (0..points.len()).max_by(|&x, &y| from_line(points[x]).total_cmp(&from_line(points[y])))?;
+    if from_line(points[c]) <= epsilon {
+        return None;
+    }
+    let normal = (points[b] - points[a])
+        .cross(points[c] - points[a])
+        .normalize_or_zero();
+    let from_plane = |point: Vec3| normal.dot(point - points[a]).abs();
+    let d = (0..points.len())
+        .max_by(|&x, &y| from_plane(points[x]).total_cmp(&from_plane(points[y])))?;
+    if from_plane(points[d]) <= epsilon {
+        return None;
+    }
+    Some([a, b, c, d].map(|index| index as u32))
+}
+
+impl ConvexHull {
+    // None when the points are all on a plane or a line
+    pub fn new(points: &[Vec3]) -> Option<Self> {
+        let (min, max) = points.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), point| (min.min(*point), max.max(*point)),
+        );
+        let epsilon = (max - min).length() * 1e-5;
+        if points.len() < 4 || !epsilon.is_finite() || epsilon <= 0.0 {
+            return None;
+        }
+        let [a, b, c, d] = simplex(points, epsilon)?;
+        let center = [a, b, c, d]
+            .iter()
+            .map(|&index| points[index as usize])
+            .sum::<Vec3>()
+            * 0.25;
+        let mut faces: Vec<Face> = [[a, b, c], [a, d, b], [b, d, c], [c, d, a]]
+            .into_iter()
+            .map(|[x, y, z]| {
+                let face = Face::new(points, [x, y, z]);
+                // turned to face away from the middle
+                if face.distance(center) > 0.0 {
+                    Face::new(points, [x, z, y])
+                } else {
+                    face
+                }
+            })
+            .collect();
+        // each directed edge to the face it goes counter clockwise around
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for (index, face) in faces.iter().enumerate() {
+            for edge in face.edges() {
+                edges.insert(edge, index);
+            }
+        }
+        let first = [0, 1, 2, 3];
+        for point in 0..points.len() as u32 {
+            if ![a, b, c, d].contains(&point) {
+                assign(&mut faces, &first, points, point, epsilon);
+            }
+        }
+
+        let mut pending: Vec<usize> = first.to_vec();
+        while let Some(start) = pending.pop() {
+            if !faces[start].alive || faces[start].outside.is_empty() {
+                continue;
+            }
+            let eye = *faces[start]
+                .outside
+                .iter()
+                .max_by(|&&x, &&y| {
+                    let face = &faces[start];
+                    face.distance(points[x as usize])
+                        .total_cmp(&face.distance(points[y as usize]))
+                })
+                .unwrap();
+            let eye_point = points[eye as usize];
+
+            // the faces the new point sees, they're all connected
+            let mut visible = HashSet::from([start]);
+            let mut stack = vec![start];
+            while let Some(face) = stack.pop() {
+                for (from, to) in faces[face].edges() {
+                    let Some(&neighbour) = edges.get(&(to, from)) else {
+                        continue;
+                    };
+                    if !visible.contains(&neighbour)
+                        && faces[neighbour].distance(eye_point) > epsilon
+                    {
+                        visible.insert(neighbour);
+                        stack.push(neighbour);
+                    }
+                }
+            }
+
+            // in order, so the same points always make the same hull
+            let mut visible_faces: Vec<usize> = visible.iter().copied().collect();
+            visible_faces.sort_unstable();
+            let mut horizon = Vec::new();
+            let mut orphans = Vec::new();
+            for &face in &visible_faces {
+                for (from, to) in faces[face].edges() {
+                    if edges
+                        .get(&(to, from))
+                        .is_none_or(|neighbour| !visible.contains(neighbour))
+                    {
+                        horizon.push((from, to));
+                    }
+                }
+                faces[face].alive = false;
+                orphans.extend(
+                    std::mem::take(&mut faces[face].outside)
+                        .into_iter()
+                        .filter(|&point| point != eye),
+                );
+            }
+            for &face in &visible_faces {
+                for edge in faces[face].edges() {
+                    if edges.get(&edge) == Some(&face) {
+                        edges.remove(&edge);
+                    }
+                }
+            }
+
+            let added: Vec<usize> = horizon
+                .into_iter()
+                .map(|(from, to)| {
+                    faces.push(Face::new(points, [from, to, eye]));
+                    let index = faces.len() - 1;
+                    for edge in faces[index].edges() {
+                        edges.insert(edge, index);
+                    }
+                    index
+                })
+                .collect();
+            for point in orphans {
+                assign(&mut faces, &added, points, point, epsilon);
+            }
+            pending.extend(added);
+        }
+
+        // only the points the faces use, renumbered
+        let mut used: HashMap<u32, u32> = HashMap::new();
+        let mut hull = Self {
+            points: Vec::new(),
+            faces: Vec::new(),
+        };
+        for face in faces.iter().filter(|face| face.alive) {
+            let vertices = face.vertices.map(|vertex| {
+                *used.entry(vertex).or_insert_with(|| {
+                    hull.points.push(points[vertex as usize]);
+                    hull.points.len() as u32 - 1
+                })
+            });
+            hull.faces.push(vertices);
+        }
+        Some(hull)
+    }
+
+    pub fn points(&self) -> &[Vec3] {
+        self.points.as_slice()
+    }
+
+    pub fn faces(&self) -> &[[u32; 3]] {
+        self.faces.as_slice()
+    }
+
+    // each edge once
+    pub fn edges(&self) -> Vec<(Vec3, Vec3)> {
+        let mut seen = HashSet::new();
+        self.faces
+            .iter()
+            .flat_map(|&[a, b, c]| [(a, b), (b, c), (c, a)])
+            .filter(|&(a, b)| seen.insert((a.min(b), a.max(b))))
+            .map(|(a, b)| (self.points[a as usize], self.points[b as usize]))
+            .collect()
+    }
+
+    // outward normals and offsets along them
+    fn planes(&self) -> impl Iterator<Item = (Vec3, f32)> + '_ {
+        self.faces.iter().map(|&face| {
+            let face = Face::new(&self.points, face);
+            (face.normal, face.offset)
+        })
+    }
+
+    // how far inside the hull `point` is, negative outside
+    pub(crate) fn depth(&self, point: Vec3) -> f32 {
+        self.planes()
+            .map(|(normal, offset)| offset - normal.dot(point))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    // hull space, `direction` needn't be normalized and the result is in its lengths
+    pub(crate) fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for (normal, offset) in self.planes() {
+            let facing = normal.dot(direction);
+            let distance = offset - normal.dot(origin);
+            if facing.abs() < 1e-12 {
+                if distance < 0.0 {
+                    return None;
+                }
+                continue;
+            }
+            let t = distance / facing;
+            if facing < 0.0 {
+                near = near.max(t);
+            } else {
+                far = far.min(t);
+            }
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the corners of a unit cube around the origin, with points inside
+    fn cube_cloud() -> Vec<Vec3> {
+        let mut points: Vec<Vec3> = (0..8)
+            .map(|i| Vec3::new((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2) as f32) - 0.5)
+            .collect();
+        points.extend([Vec3::ZERO, Vec3::splat(0.2), Vec3::new(-0.3, 0.1, 0.4)]);
+        points
+    }
+
+    #[test]
+    fn hull_of_a_cube() {
+        let hull = ConvexHull::new(&cube_cloud()).unwrap();
+        assert_eq!(hull.points().len(), 8);
+        assert_eq!(hull.faces().len(), 12);
+        assert_eq!(hull.edges().len(), 18);
+        // every face turns outwards
+        for &face in hull.faces() {
+            let [a, b, c] = face.map(|vertex| hull.points()[vertex as usize]);
+            assert!((b - a).cross(c - a).dot(a + b + c) > 0.0);
+        }
+        assert!((hull.depth(Vec3::ZERO) - 0.5).abs() < 1e-5);
+        assert!((hull.depth(Vec3::new(0.4, 0.0, 0.0)) - 0.1).abs() < 1e-5);
+        assert!(hull.depth(Vec3::new(0.0, 0.0, 0.75)) < 0.0);
+    }
+
+    #[test]
+    fn flat_and_sparse_points_have_no_hull() {
+        assert!(ConvexHull::new(&[Vec3::ZERO, Vec3::X, Vec3::Y]).is_none());
+        let flat: Vec<Vec3> = (0..10)
+            .map(|i| Vec3::new(i as f32, 0.0, (i * i) as f32))
+            .collect();
+        assert!(ConvexHull::new(&flat).is_none());
+        assert!(ConvexHull::new(&[Vec3::ONE; 6]).is_none());
+    }
+
+    #[test]
+    fn rays_enter_through_the_nearest_face() {
+        let hull = ConvexHull::new(&cube_cloud()).unwrap();
+        let hit = hull.intersect_ray(Vec3::new(-2.0, 0.1, 0.0), Vec3::X * 2.0);
+        assert!((hit.unwrap() - 0.75).abs() < 1e-5);
+        assert_eq!(hull.intersect_ray(Vec3::ZERO, Vec3::Y), Some(0.0));
+        assert_eq!(hull.intersect_ray(Vec3::new(-2.0, 1.0, 0.0), Vec3::X), None);
+        assert_eq!(hull.intersect_ray(Vec3::new(2.0, 0.0, 0.0), Vec3::X), None);
+    }
+}
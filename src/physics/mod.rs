@@ -0,0 +1,84 @@
+// Colliders and raycasts, which picking and the editor need, and with the
+// physics feature the simulation: rigid bodies, fluids and force fields.
+
+#[cfg(feature = "physics")]
+pub mod body;
+pub mod collider;
+pub mod fit;
+#[cfg(feature = "physics")]
+pub mod fluid;
+#[cfg(feature = "physics")]
+pub mod force_field;
+pub mod heightfield;
+pub mod hull;
+pub mod trimesh;
+
+#[cfg(feature = "physics")]
+pub use body::{Gravity, RigidBody};
+pub use collider::Collider;
+#[cfg(feature = "physics")]
+pub use fluid::FluidVolume;
+#[cfg(feature = "physics")]
+pub use force_field::{Falloff, ForceField};
+pub use heightfield::Heightfield;
+pub use hull::ConvexHull;
+pub use trimesh::{Static, TriMesh};
+
+use glam::Vec3;
+
+use crate::{
+    ecs::{entity::Entity, world::World},
+    transform,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize_or_zero(),
+        }
+    }
+
+    pub fn between(from: Vec3, to: Vec3) -> (Self, f32) {
+        (Self::new(from, to - from), from.distance(to))
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub point: Vec3,
+}
+
+pub fn raycast_all(world: &World, ray: &Ray, max_distance: f32) -> Vec<RayHit> {
+    let mut hits: Vec<RayHit> = world
+        .query::<Collider>()
+        .into_iter()
+        .filter_map(|(entity, collider)| {
+            let transform = transform::world_transform(world, entity)?;
+            let distance = collider.intersect_ray(&transform, ray)?;
+            (distance <= max_distance).then(|| RayHit {
+                entity,
+                distance,
+                point: ray.at(distance),
+            })
+        })
+        .collect();
+    hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    hits
+}
+
+pub fn raycast(world: &World, ray: &Ray, max_distance: f32) -> Option<RayHit> {
+    raycast_all(world, ray, max_distance).into_iter().next()
+}
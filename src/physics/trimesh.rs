@@ -0,0 +1,145 @@
+// Triangle mesh colliders for level geometry that never moves. The triangles
+// sit in a bounding volume hierarchy, so a ray or a contact query only looks
+// at the few near it. Entities marked Static with a mesh and no collider get
+// one built from the mesh by `build_static_colliders`, shared between the
+// entities using the same mesh. A mesh's triangles are one sided to nothing,
+// rays hit them from either side.
+
+use std::{collections::HashMap, sync::Arc};
+
+use glam::Vec3;
+
+use crate::{
+    assets::{Assets, Handle},
+    bvh::TriangleBvh,
+    ecs::{component::Component, world::World},
+    mesh::Mesh,
+};
+
+use super::Collider;
+
+// marks level geometry, its mesh becomes its collider
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Static;
+
+impl Component for Static {}
+
+#[derive(Debug, Clone)]
+pub struct TriMesh {
+    bvh: Arc<TriangleBvh>,
+}
+
+impl TriMesh {
+    pub fn new(triangles: Vec<[Vec3; 3]>) -> Self {
+        Self {
+            bvh: Arc::new(TriangleBvh::new(triangles)),
+        }
+    }
+
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        Self::new(mesh.triangles())
+    }
+
+    // mesh space (min, max), None without triangles
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.bvh.bounds()
+    }
+
+    // the midphase for contacts, the triangles whose bounds touch the mesh space box
+    pub fn overlapping(&self, min: Vec3, max: Vec3) -> Vec<[Vec3; 3]> {
+        self.bvh.overlapping(min, max)
+    }
+
+    // mesh space, `direction` needn't be normalized and the result is in its lengths
+    pub(crate) fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        self.bvh.cast(origin, direction, f32::INFINITY)
+    }
+}
+
+pub fn build_static_colliders(world: &mut World) {
+    let pending: Vec<_> = world
+        .query::<Static>()
+        .into_iter()
+        .filter(|(entity, _)| world.get_component::<Collider>(*entity).is_none())
+        .filter_map(|(entity, _)| Some((entity, *world.get_component::<Handle<Mesh>>(entity)?)))
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+    let Some(meshes) = world.get_resource::<Assets<Mesh>>() else {
+        return;
+    };
+    let mut built: HashMap<Handle<Mesh>, TriMesh> = HashMap::new();
+    let colliders: Vec<_> = pending
+        .into_iter()
+        .filter_map(|(entity, handle)| {
+            let mesh = meshes.get(handle)?;
+            let trimesh = built
+                .entry(handle)
+                .or_insert_with(|| TriMesh::from_mesh(mesh));
+            Some((entity, Collider::TriMesh(trimesh.clone())))
+        })
+        .collect();
+    for (entity, collider) in colliders {
+        world.add_component(entity, collider);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_meshes_share_one_collider() {
+        let mut world = crate::create_world();
+        let (ramp, floor) = {
+            let meshes = world.resource_mut::<Assets<Mesh>>();
+            (
+                meshes.add(Mesh::cube(2.0)),
+                meshes.add(Mesh::plane(10.0, 3)),
+            )
+        };
+        let spawn =
+            |world: &mut World, mesh: Handle<Mesh>| world.spawn().insert(Static).insert(mesh).id();
+        let entities = [
+            spawn(&mut world, ramp),
+            spawn(&mut world, ramp),
+            spawn(&mut world, floor),
+        ];
+        let moving = world.spawn().insert(ramp).id();
+        let kept = world.spawn().insert(Static).insert(ramp).id();
+        world.add_component(kept, Collider::sphere(1.0));
+        build_static_colliders(&mut world);
+
+        let trimesh = |entity| match world.get_component::<Collider>(entity) {
+            Some(Collider::TriMesh(trimesh)) => trimesh.clone(),
+            other => panic!("{other:?}"),
+        };
+        let [first, second, third] = entities.map(trimesh);
+        assert!(Arc::ptr_eq(&first.bvh, &second.bvh));
+        assert!(!Arc::ptr_eq(&first.bvh, &third.bvh));
+        assert!(world.get_component::<Collider>(moving).is_none());
+        assert!(matches!(
+            world.get_component::<Collider>(kept),
+            Some(Collider::Sphere { .. })
+        ));
+
+        assert_eq!(first.bounds(), Some((-Vec3::ONE, Vec3::ONE)));
+        assert_eq!(
+            first.overlapping(Vec3::splat(0.5), Vec3::splat(2.0)).len(),
+            6
+        );
+        // both sides of a face are hit
+        assert_eq!(first.intersect_ray(Vec3::X * 3.0, Vec3::NEG_X), Some(2.0));
+        assert_eq!(first.intersect_ray(Vec3::ZERO, Vec3::X * 2.0), Some(0.5));
+        assert_eq!(
+            third.intersect_ray(Vec3::new(1.0, 2.0, 1.0), Vec3::NEG_Y),
+            Some(2.0)
+        );
+        assert!(
+            third
+                .intersect_ray(Vec3::new(1.0, 2.0, 1.0), Vec3::Y)
+                .is_none()
+        );
+    }
+}
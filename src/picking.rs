@@ -0,0 +1,61 @@
+// Ray picking against colliders and mesh bounds. Hidden entities are skipped.
+
+use glam::Vec2;
+
+use crate::{
+    assets::{Assets, Handle},
+    camera::screen_to_world,
+    ecs::{entity::Entity, world::World},
+    mesh::Mesh,
+    physics::{self, Collider, Ray},
+    transform::{self, Transform},
+    visibility,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PickHit {
+    pub entity: Entity,
+    pub distance: f32,
+}
+
+pub fn pick_ray(world: &World, ray: &Ray) -> Option<PickHit> {
+    let collider_hit = physics::raycast_all(world, ray, f32::INFINITY)
+        .into_iter()
+        .find(|hit| visibility::is_visible(world, hit.entity))
+        .map(|hit| PickHit {
+            entity: hit.entity,
+            distance: hit.distance,
+        });
+
+    let meshes = world.get_resource::<Assets<Mesh>>();
+    let mesh_hit = world
+        .query::<Handle<Mesh>>()
+        .into_iter()
+        .filter(|(entity, _)| {
+            world.get_component::<Collider>(*entity).is_none()
+                && visibility::is_visible(world, *entity)
+        })
+        .filter_map(|(entity, handle)| {
+            let (min, max) = meshes?.get(*handle)?.aabb()?;
+            let transform = transform::world_transform(world, entity)?;
+            // the bounds may be off center, so shift the box into place
+            let bounds = Transform {
+                translation: transform.translation
+                    + transform.rotation * (transform.scale * (min + max) * 0.5),
+                ..transform
+            };
+            let distance = Collider::cuboid((max - min) * 0.5).intersect_ray(&bounds, ray)?;
+            Some(PickHit { entity, distance })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance));
+
+    match (collider_hit, mesh_hit) {
+        (Some(a), Some(b)) => Some(if a.distance <= b.distance { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+// cursor position is in physical pixels
+pub fn pick_at(world: &World, cursor: Vec2) -> Option<PickHit> {
+    pick_ray(world, &screen_to_world(world, cursor)?)
+}
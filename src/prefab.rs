@@ -0,0 +1,50 @@
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    transform::Transform,
+};
+
+pub type PrefabFn = fn(&mut World, Entity);
+
+// marks entities spawned from a prefab so they can be saved and respawned by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefabInstance(pub String);
+
+impl Component for PrefabInstance {}
+
+#[derive(Debug, Default)]
+pub struct Prefabs {
+    prefabs: FastHashMap<String, PrefabFn>,
+}
+
+impl Component for Prefabs {}
+
+impl Prefabs {
+    // names are whitespace free so they survive the scene file format
+    pub fn register(&mut self, name: &str, prefab: PrefabFn) {
+        let name = name.split_whitespace().collect::<Vec<_>>().join("_");
+        self.prefabs.insert(name, prefab);
+    }
+
+    pub fn get(&self, name: &str) -> Option<PrefabFn> {
+        self.prefabs.get(name).copied()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.prefabs.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+}
+
+pub fn spawn_prefab(world: &mut World, name: &str, transform: Transform) -> Option<Entity> {
+    let prefab = world.get_resource::<Prefabs>()?.get(name)?;
+    let entity = world
+        .spawn()
+        .insert(transform)
+        .insert(PrefabInstance(name.to_string()))
+        .id();
+    prefab(world, entity);
+    Some(entity)
+}
@@ -0,0 +1,177 @@
+// Frame profiler. While enabled, every system run and every stretch of the
+// renderer's cpu work is kept as a span, for the last `max_frames` frames.
+// They can be written out as chrome trace event json, which chrome://tracing
+// and ui.perfetto.dev both open, with the `profile_export` console command.
+
+use std::{collections::VecDeque, path::Path};
+
+use serde_json::json;
+use web_time::{Duration, Instant};
+
+use crate::ecs::{
+    component::Component,
+    system::System,
+    world::{World, run_systems},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub name: String,
+    // systems, render or frame, each on its own row of the trace
+    pub category: &'static str,
+    // from when the profiler was created
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+pub struct Profiler {
+    pub enabled: bool,
+    pub max_frames: usize,
+    epoch: Instant,
+    frames: VecDeque<Vec<Span>>,
+    frame_start: Option<Instant>,
+}
+
+impl Component for Profiler {}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_frames: 120,
+            epoch: Instant::now(),
+            frames: VecDeque::new(),
+            frame_start: None,
+        }
+    }
+}
+
+impl Profiler {
+    // oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &[Span]> {
+        self.frames.iter().map(Vec::as_slice)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.frame_start = None;
+    }
+
+    // closes the previous frame with a span covering all of it
+    pub(crate) fn begin_frame(&mut self) {
+        if !self.enabled {
+            self.frame_start = None;
+            return;
+        }
+        let now = Instant::now();
+        if let Some(start) = self.frame_start.replace(now) {
+            self.record("frame", "frame", start, now);
+        }
+        self.frames.push_back(Vec::new());
+        while self.frames.len() > self.max_frames.max(1) {
+            self.frames.pop_front();
+        }
+    }
+
+    // ignored while disabled
+    pub fn record(
+        &mut self,
+        name: impl Into<String>,
+        category: &'static str,
+        start: Instant,
+        end: Instant,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let span = Span {
+            name: name.into(),
+            category,
+            start: start.saturating_duration_since(self.epoch),
+            duration: end.saturating_duration_since(start),
+        };
+        match self.frames.back_mut() {
+            Some(frame) => frame.push(span),
+            None => self.frames.push_back(vec![span]),
+        }
+    }
+
+    pub fn chrome_trace(&self) -> String {
+        let events: Vec<serde_json::Value> = self
+            .frames
+            .iter()
+            .flatten()
+            .map(|span| {
+                json!({
+                    "name": span.name,
+                    "cat": span.category,
+                    "ph": "X",
+                    "ts": span.start.as_secs_f64() * 1_000_000.0,
+                    "dur": span.duration.as_secs_f64() * 1_000_000.0,
+                    "pid": 1,
+                    "tid": match span.category {
+                        "frame" => 0,
+                        "systems" => 1,
+                        _ => 2,
+                    },
+                })
+            })
+            .collect();
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" }).to_string()
+    }
+
+    pub fn export_chrome_trace(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, self.chrome_trace())?;
+        Ok(())
+    }
+}
+
+// the world's schedule runner, times every system while the profiler is enabled
+pub(crate) fn run_profiled(world: &mut World, schedule: &'static str, systems: &mut [System]) {
+    let profiling = world
+        .get_resource::<Profiler>()
+        .is_some_and(|profiler| profiler.enabled);
+    if !profiling {
+        run_systems(world, schedule, systems);
+        return;
+    }
+
+    let schedule_start = Instant::now();
+    for system in systems {
+        let start = Instant::now();
+        system.run(world);
+        let end = Instant::now();
+        if let Some(profiler) = world.get_resource_mut::<Profiler>() {
+            profiler.record(system.name(), "systems", start, end);
+        }
+    }
+    if let Some(profiler) = world.get_resource_mut::<Profiler>() {
+        profiler.record(schedule, "systems", schedule_start, Instant::now());
+    }
+}
+
+// times consecutive stretches of the renderer, which only sees the world
+// immutably, for the app to hand to the profiler after the frame
+pub(crate) struct PassTimer {
+    last: Option<Instant>,
+    pub(crate) spans: Vec<(&'static str, Instant, Instant)>,
+}
+
+impl PassTimer {
+    pub(crate) fn start(enabled: bool) -> Self {
+        Self {
+            last: enabled.then(Instant::now),
+            spans: Vec::new(),
+        }
+    }
+
+    // the span from the previous mark to now
+    pub(crate) fn mark(&mut self, name: &'static str) {
+        if let Some(last) = self.last {
+            let now = Instant::now();
+            self.spans.push((name, last, now));
+            self.last = Some(now);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// Reaching the main thread from other threads. A network receiver or a file
+// watcher gets a clone of the EngineProxy resource (or `App::proxy`), and what
+// it posts wakes the event loop and runs on the main thread with the world,
+// before the next frame's update. Posting fails once the event loop has ended.
+
+use std::fmt;
+
+use winit::event_loop::EventLoopProxy;
+
+#[cfg(target_arch = "wasm32")]
+use crate::State;
+use crate::ecs::{component::Component, world::World};
+
+pub(crate) type Job = Box<dyn FnOnce(&mut World) + Send>;
+
+// what the event loop is woken with
+pub(crate) enum EngineEvent {
+    // the renderer, made asynchronously on the web
+    #[cfg(target_arch = "wasm32")]
+    Renderer(Box<State>),
+    Run(Job),
+}
+
+#[derive(Clone)]
+pub struct EngineProxy {
+    proxy: EventLoopProxy<EngineEvent>,
+}
+
+impl Component for EngineProxy {}
+
+impl fmt::Debug for EngineProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EngineProxy").finish_non_exhaustive()
+    }
+}
+
+impl EngineProxy {
+    pub(crate) fn new(proxy: EventLoopProxy<EngineEvent>) -> Self {
+        Self { proxy }
+    }
+
+    // runs `job` on the main thread, with whichever scene is on top then
+    pub fn run(&self, job: impl FnOnce(&mut World) + Send + 'static) -> anyhow::Result<()> {
+        self.proxy
+            .send_event(EngineEvent::Run(Box::new(job)))
+            .map_err(|_| anyhow::anyhow!("The event loop has ended"))
+    }
+
+    // sent as an ecs event, which needs `add_event::<T>()` like any other
+    pub fn send_event<T: fmt::Debug + Send + 'static>(&self, event: T) -> anyhow::Result<()> {
+        self.run(move |world| world.send_event(event))
+    }
+}
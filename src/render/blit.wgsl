@@ -0,0 +1,99 @@
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+// encoding: 0 copies, 1 srgb, 2 scrgb, 3 hdr10 (pq), see output.rs
+struct Output {
+    encoding: u32,
+    paper_white: f32,
+    max_luminance: f32,
+    // contrast adaptive, for a scene drawn smaller than the target
+    sharpness: f32,
+};
+@group(1) @binding(0)
+var<uniform> output: Output;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn srgb_encode(color: vec3<f32>) -> vec3<f32> {
+    let c = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+// linear below the knee, then rolls off towards the peak without clipping
+fn shoulder(nits: vec3<f32>, peak: f32) -> vec3<f32> {
+    let knee = peak * 0.75;
+    let range = peak - knee;
+    let rolled = knee + range * (1.0 - exp(-(nits - knee) / range));
+    return select(rolled, nits, nits <= vec3<f32>(knee));
+}
+
+fn pq_encode(nits: vec3<f32>) -> vec3<f32> {
+    let m1 = 0.1593017578125;
+    let m2 = 78.84375;
+    let c1 = 0.8359375;
+    let c2 = 18.8515625;
+    let c3 = 18.6875;
+    let y = pow(clamp(nits / 10000.0, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(m1));
+    return pow((c1 + c2 * y) / (1.0 + c3 * y), vec3<f32>(m2));
+}
+
+const REC709_TO_REC2020 = mat3x3<f32>(
+    vec3<f32>(0.6274, 0.0691, 0.0164),
+    vec3<f32>(0.3293, 0.9195, 0.0880),
+    vec3<f32>(0.0433, 0.0114, 0.8956),
+);
+
+// sharpens less where the neighbourhood already has contrast, so edges don't ring
+fn sharpen(uv: vec2<f32>, center: vec4<f32>) -> vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    let up = textureSample(t_source, s_source, uv - vec2<f32>(0.0, texel.y)).rgb;
+    let down = textureSample(t_source, s_source, uv + vec2<f32>(0.0, texel.y)).rgb;
+    let left = textureSample(t_source, s_source, uv - vec2<f32>(texel.x, 0.0)).rgb;
+    let right = textureSample(t_source, s_source, uv + vec2<f32>(texel.x, 0.0)).rgb;
+    let low = min(center.rgb, min(min(up, down), min(left, right)));
+    let high = max(center.rgb, max(max(up, down), max(left, right)));
+    let amount = sqrt(clamp(low / max(high, vec3<f32>(1e-4)), vec3<f32>(0.0), vec3<f32>(1.0)));
+    let weight = -amount * output.sharpness * 0.2;
+    let color = (center.rgb + (up + down + left + right) * weight) / (1.0 + 4.0 * weight);
+    return vec4<f32>(color, center.a);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var source = textureSample(t_source, s_source, in.uv);
+    if output.sharpness > 0.0 {
+        source = sharpen(in.uv, source);
+    }
+    let color = max(source.rgb, vec3<f32>(0.0));
+    switch output.encoding {
+        case 1u: {
+            return vec4<f32>(srgb_encode(color), source.a);
+        }
+        case 2u: {
+            // scrgb's 1.0 is 80 nits
+            let nits = shoulder(color * output.paper_white, output.max_luminance);
+            return vec4<f32>(nits / 80.0, source.a);
+        }
+        case 3u: {
+            let nits = shoulder(REC709_TO_REC2020 * color * output.paper_white, output.max_luminance);
+            return vec4<f32>(pq_encode(nits), source.a);
+        }
+        default: {
+            return source;
+        }
+    }
+}
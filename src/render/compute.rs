@@ -0,0 +1,408 @@
+// User compute work. Systems queue `ComputePass`es on the `ComputePasses`
+// resource, they're encoded and submitted after the frame's systems ran, ahead
+// of the frame's draw. Passes run once, queue them again to run every frame.
+
+use std::sync::{Arc, Mutex};
+
+use glam::UVec3;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    assets::{Assets, Handle},
+    ecs::{component::Component, world::World},
+    error::WhirlwindError,
+    render::{RenderDevice, gpu::GpuFeatures, readback::Readback},
+    texture::Texture,
+};
+
+#[derive(Debug)]
+pub struct ComputePipeline {
+    pub pipeline: wgpu::ComputePipeline,
+}
+
+impl ComputePipeline {
+    // the bind group layout is taken from the shader itself
+    pub fn from_wgsl(
+        device: &wgpu::Device,
+        source: &str,
+        entry_point: &str,
+        label: Option<&str>,
+    ) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label,
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label,
+            layout: None,
+            module: &module,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Self { pipeline }
+    }
+
+    // like `from_wgsl`, but a shader that doesn't compile is an error instead of a panic
+    pub fn try_from_wgsl(
+        device: &wgpu::Device,
+        source: &str,
+        entry_point: &str,
+        label: Option<&str>,
+    ) -> Result<Self, WhirlwindError> {
+        let scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = Self::from_wgsl(device, source, entry_point, label);
+        // resolves right away on everything but webgpu
+        match pollster::block_on(scope.pop()) {
+            Some(e) => Err(WhirlwindError::ShaderCompile {
+                label: label.unwrap_or("compute").to_string(),
+                message: e.to_string(),
+            }),
+            None => Ok(pipeline),
+        }
+    }
+
+    pub fn from_path(
+        device: &wgpu::Device,
+        path: impl AsRef<std::path::Path>,
+        entry_point: &str,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        crate::error::ensure_exists(path)?;
+        let source = std::fs::read_to_string(path)?;
+        Ok(Self::try_from_wgsl(
+            device,
+            &source,
+            entry_point,
+            Some(&path.to_string_lossy()),
+        )?)
+    }
+}
+
+#[derive(Debug)]
+pub struct StorageBuffer {
+    pub buffer: wgpu::Buffer,
+}
+
+impl StorageBuffer {
+    const USAGE: wgpu::BufferUsages = wgpu::BufferUsages::STORAGE
+        .union(wgpu::BufferUsages::COPY_SRC)
+        .union(wgpu::BufferUsages::COPY_DST);
+
+    pub fn new(device: &wgpu::Device, size: u64, label: Option<&str>) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label,
+                size,
+                usage: Self::USAGE,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    pub fn from_slice<T: bytemuck::Pod>(
+        device: &wgpu::Device,
+        data: &[T],
+        label: Option<&str>,
+    ) -> Self {
+        Self {
+            buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label,
+                contents: bytemuck::cast_slice(data),
+                usage: Self::USAGE,
+            }),
+        }
+    }
+
+    pub fn write<T: bytemuck::Pod>(&self, queue: &wgpu::Queue, offset: u64, data: &[T]) {
+        queue.write_buffer(&self.buffer, offset, bytemuck::cast_slice(data));
+    }
+
+    pub fn size(&self) -> u64 {
+        self.buffer.size()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ComputeBinding {
+    Buffer(Handle<StorageBuffer>),
+    // sampled or storage, depending on how the shader declares it
+    Texture(Handle<Texture>),
+    Sampler(Handle<Texture>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ComputePass {
+    pub label: Option<String>,
+    pub pipeline: Handle<ComputePipeline>,
+    // (binding, resource) pairs for @group(0)
+    pub bindings: Vec<(u32, ComputeBinding)>,
+    pub workgroups: UVec3,
+    // copied back once the pass finished, arrives as a ComputeReadback event
+    pub readback: Option<Handle<StorageBuffer>>,
+}
+
+impl ComputePass {
+    pub fn new(pipeline: Handle<ComputePipeline>, workgroups: UVec3) -> Self {
+        Self {
+            label: None,
+            pipeline,
+            bindings: Vec::new(),
+            workgroups,
+            readback: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn bind(mut self, binding: u32, resource: ComputeBinding) -> Self {
+        self.bindings.push((binding, resource));
+        self
+    }
+
+    pub fn with_readback(mut self, buffer: Handle<StorageBuffer>) -> Self {
+        self.readback = Some(buffer);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ComputeReadback {
+    pub buffer: Handle<StorageBuffer>,
+    pub data: Vec<u8>,
+}
+
+impl ComputeReadback {
+    pub fn cast<T: bytemuck::Pod>(&self) -> Vec<T> {
+        bytemuck::pod_collect_to_vec(&self.data)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ComputePasses {
+    queued: Vec<ComputePass>,
+    // filled by the map callbacks, which may run on another thread
+    finished: Arc<Mutex<Vec<ComputeReadback>>>,
+    warned_unsupported: bool,
+}
+
+impl Component for ComputePasses {}
+
+impl ComputePasses {
+    pub fn dispatch(&mut self, pass: ComputePass) {
+        self.queued.push(pass);
+    }
+}
+
+fn bind_group(
+    device: &wgpu::Device,
+    pass: &ComputePass,
+    pipeline: &ComputePipeline,
+    buffers: Option<&Assets<StorageBuffer>>,
+    textures: Option<&Assets<Texture>>,
+) -> anyhow::Result<wgpu::BindGroup> {
+    let missing = |binding: u32| anyhow::anyhow!("Missing resource for binding {}", binding);
+    let entries = pass
+        .bindings
+        .iter()
+        .map(|&(binding, resource)| {
+            let resource = match resource {
+                ComputeBinding::Buffer(handle) => buffers
+                    .and_then(|buffers| buffers.get(handle))
+                    .ok_or_else(|| missing(binding))?
+                    .buffer
+                    .as_entire_binding(),
+                ComputeBinding::Texture(handle) => wgpu::BindingResource::TextureView(
+                    &textures
+                        .and_then(|textures| textures.get(handle))
+                        .ok_or_else(|| missing(binding))?
+                        .view,
+                ),
+                ComputeBinding::Sampler(handle) => wgpu::BindingResource::Sampler(
+                    &textures
+                        .and_then(|textures| textures.get(handle))
+                        .ok_or_else(|| missing(binding))?
+                        .sampler,
+                ),
+            };
+            Ok(wgpu::BindGroupEntry { binding, resource })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: pass.label.as_deref(),
+        layout: &pipeline.pipeline.get_bind_group_layout(0),
+        entries: &entries,
+    }))
+}
+
+// runs after "last", so readbacks are sent as events for the next frame's systems
+pub(crate) fn run_compute_passes(world: &mut World) {
+    let Some(RenderDevice { device, queue, .. }) = world.get_resource::<RenderDevice>().cloned()
+    else {
+        return;
+    };
+    let Some(passes) = world.get_resource_mut::<ComputePasses>() else {
+        return;
+    };
+    let mut queued = std::mem::take(&mut passes.queued);
+    let finished = passes.finished.clone();
+    // webgl has no compute, the passes are dropped instead of failing validation
+    if !queued.is_empty()
+        && world
+            .get_resource::<GpuFeatures>()
+            .is_some_and(|gpu| !gpu.compute_shaders)
+    {
+        let passes = world.resource_mut::<ComputePasses>();
+        if !passes.warned_unsupported {
+            passes.warned_unsupported = true;
+            log::warn!(
+                "Compute shaders aren't supported by this adapter, compute passes are skipped"
+            );
+        }
+        queued.clear();
+    }
+
+    if !queued.is_empty() {
+        let pipelines = world.get_resource::<Assets<ComputePipeline>>();
+        let buffers = world.get_resource::<Assets<StorageBuffer>>();
+        let textures = world.get_resource::<Assets<Texture>>();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("compute_encoder"),
+        });
+        let mut readbacks = Vec::new();
+
+        for pass in &queued {
+            let Some(pipeline) = pipelines.and_then(|pipelines| pipelines.get(pass.pipeline))
+            else {
+                log::error!("Compute pass {:?} has no pipeline", pass.label);
+                continue;
+            };
+            let bind_group = match bind_group(&device, pass, pipeline, buffers, textures) {
+                Ok(bind_group) => bind_group,
+                Err(e) => {
+                    log::error!("Unable to bind compute pass {:?}: {}", pass.label, e);
+                    continue;
+                }
+            };
+
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: pass.label.as_deref(),
+                    timestamp_writes: None,
+                });
+                compute_pass.set_pipeline(&pipeline.pipeline);
+                compute_pass.set_bind_group(0, &bind_group, &[]);
+                compute_pass.dispatch_workgroups(
+                    pass.workgroups.x,
+                    pass.workgroups.y,
+                    pass.workgroups.z,
+                );
+            }
+
+            if let Some(handle) = pass.readback
+                && let Some(buffer) = buffers.and_then(|buffers| buffers.get(handle))
+            {
+                match Readback::buffer(&buffer.buffer).encode(&device, &mut encoder) {
+                    Ok(staging) => readbacks.push((handle, staging)),
+                    Err(e) => log::error!("Unable to read back {:?}: {}", handle, e),
+                }
+            }
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+
+        for (handle, staging) in readbacks {
+            let finished = finished.clone();
+            staging.map(move |result| match result {
+                Ok(data) => finished.lock().unwrap().push(ComputeReadback {
+                    buffer: handle,
+                    data,
+                }),
+                Err(e) => log::error!("Unable to read back compute buffer: {}", e),
+            });
+        }
+    }
+
+    if let Err(e) = device.poll(wgpu::PollType::Poll) {
+        log::error!("Unable to poll the device: {}", e);
+    }
+    let finished = std::mem::take(&mut *finished.lock().unwrap());
+    for readback in finished {
+        world.send_event(readback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::event::Events;
+
+    const DOUBLE: &str = "
+        @group(0) @binding(0) var<storage, read_write> values: array<u32>;
+
+        @compute @workgroup_size(4)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            values[id.x] *= 2u;
+        }
+    ";
+
+    fn dispatch_double(world: &mut World) -> Handle<StorageBuffer> {
+        let device = world.resource::<RenderDevice>().device.clone();
+        let pipeline = ComputePipeline::from_wgsl(&device, DOUBLE, "main", Some("double"));
+        let pipeline = world
+            .resource_mut::<Assets<ComputePipeline>>()
+            .add(pipeline);
+        let buffer = StorageBuffer::from_slice(&device, &[1u32, 2, 3, 4], Some("values"));
+        let buffer = world.resource_mut::<Assets<StorageBuffer>>().add(buffer);
+        world.resource_mut::<ComputePasses>().dispatch(
+            ComputePass::new(pipeline, UVec3::ONE)
+                .bind(0, ComputeBinding::Buffer(buffer))
+                .with_readback(buffer),
+        );
+        buffer
+    }
+
+    #[test]
+    fn passes_run_or_are_dropped_by_what_the_adapter_can_do() {
+        let state = match pollster::block_on(crate::State::headless(4, 4)) {
+            Ok(state) => state,
+            Err(e) if matches!(e.downcast_ref(), Some(WhirlwindError::NoAdapter(_))) => {
+                eprintln!("Skipping, no adapter: {e}");
+                return;
+            }
+            Err(e) => panic!("{e}"),
+        };
+        let mut world = crate::create_world();
+        world.insert_resource(state.render_device());
+        let mut gpu = state.gpu.clone();
+
+        if gpu.compute_shaders {
+            world.insert_resource(gpu.clone());
+            let buffer = dispatch_double(&mut world);
+            run_compute_passes(&mut world);
+            state
+                .device
+                .poll(wgpu::PollType::wait_indefinitely())
+                .unwrap();
+            // the readback arrives on the next run
+            run_compute_passes(&mut world);
+            let readback = world.resource::<Events<ComputeReadback>>().iter().next();
+            let readback = readback.expect("the pass should have been read back");
+            assert_eq!(readback.buffer, buffer);
+            assert_eq!(readback.cast::<u32>(), [2, 4, 6, 8]);
+        }
+
+        gpu.compute_shaders = false;
+        world.insert_resource(gpu);
+        world.resource_mut::<Events<ComputeReadback>>().clear();
+        dispatch_double(&mut world);
+        run_compute_passes(&mut world);
+        let passes = world.resource::<ComputePasses>();
+        assert!(passes.queued.is_empty() && passes.warned_unsupported);
+        assert!(world.resource::<Events<ComputeReadback>>().is_empty());
+    }
+}
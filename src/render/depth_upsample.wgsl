@@ -0,0 +1,24 @@
+@group(0) @binding(0)
+var t_depth: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// nearest, blending depths across an edge would put text in mid air
+@fragment
+fn fs_main(in: VertexOutput) -> @builtin(frag_depth) f32 {
+    let size = vec2<i32>(textureDimensions(t_depth));
+    let coords = clamp(vec2<i32>(in.uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    return textureLoad(t_depth, coords, 0).r;
+}
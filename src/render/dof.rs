@@ -0,0 +1,146 @@
+// Depth of field. The circle of confusion comes from a thin lens model using
+// the camera's field of view and a full frame sensor, and the blur gathers
+// along a golden angle spiral, which gives round bokeh-ish highlights.
+
+use crate::render::post::{self, PostTargets};
+
+const SENSOR_HEIGHT: f32 = 0.024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthOfField {
+    // distance in world units that is perfectly sharp
+    pub focus_distance: f32,
+    // f-number, lower is a wider aperture and a shallower focus
+    pub f_stop: f32,
+    // in physical pixels
+    pub max_blur_radius: f32,
+}
+
+impl Default for DepthOfField {
+    fn default() -> Self {
+        Self {
+            focus_distance: 10.0,
+            f_stop: 2.8,
+            max_blur_radius: 16.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DofUniform {
+    focal_length: f32,
+    aperture: f32,
+    focus_distance: f32,
+    // circle of confusion in meters to pixels
+    pixels_per_meter: f32,
+    max_blur_radius: f32,
+    _padding: [u32; 3],
+}
+
+pub(crate) struct DofPass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    settings_buffer: wgpu::Buffer,
+}
+
+impl DofPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("dof_layout"),
+            entries: &[
+                post::texture_entry(0),
+                post::sampler_entry(1),
+                post::depth_entry(2),
+                post::uniform_entry(3),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("dof.wgsl"));
+        let pipeline = post::fullscreen_pipeline(
+            device,
+            cache,
+            "DoF Pipeline",
+            &shader,
+            &[camera_bind_group_layout, &layout],
+            format,
+        );
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("dof_settings"),
+            size: std::mem::size_of::<DofUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            layout,
+            settings_buffer,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn run(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        post: &mut PostTargets,
+        camera_bind_group: &wgpu::BindGroup,
+        depth: &wgpu::TextureView,
+        fov: f32,
+        viewport_height: u32,
+        settings: DepthOfField,
+    ) {
+        let focal_length = SENSOR_HEIGHT / (2.0 * (fov.to_radians() * 0.5).tan());
+        let focus_distance = settings.focus_distance.max(focal_length * 2.0);
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&DofUniform {
+                focal_length,
+                aperture: focal_length / settings.f_stop.max(0.1),
+                focus_distance,
+                pixels_per_meter: viewport_height as f32 / SENSOR_HEIGHT,
+                max_blur_radius: settings.max_blur_radius.clamp(0.0, 64.0),
+                _padding: [0; 3],
+            }),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("dof_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&post.current().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&post.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.settings_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let (_, target) = post.swap();
+        post::run_fullscreen(
+            encoder,
+            "DoF Pass",
+            &target.view,
+            &self.pipeline,
+            &[camera_bind_group, &bind_group],
+        );
+    }
+}
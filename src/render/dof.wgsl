@@ -0,0 +1,90 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct DofSettings {
+    focal_length: f32,
+    aperture: f32,
+    focus_distance: f32,
+    pixels_per_meter: f32,
+    max_blur_radius: f32,
+};
+
+@group(1) @binding(0)
+var t_color: texture_2d<f32>;
+@group(1) @binding(1)
+var s_linear: sampler;
+@group(1) @binding(2)
+var t_depth: texture_2d<f32>;
+@group(1) @binding(3)
+var<uniform> settings: DofSettings;
+
+const SAMPLES: u32 = 48u;
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn distance_at(coords: vec2<i32>, size: vec2<i32>) -> f32 {
+    let clamped = clamp(coords, vec2<i32>(0), size - 1);
+    let depth = textureLoad(t_depth, clamped, 0).r;
+    let uv = (vec2<f32>(clamped) + 0.5) / vec2<f32>(size);
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = camera.inverse_view_proj * ndc;
+    return distance(position.xyz / position.w, camera.position.xyz);
+}
+
+// blur radius in pixels for something at `distance`
+fn circle_of_confusion(distance: f32) -> f32 {
+    let coc = settings.aperture * settings.focal_length * abs(distance - settings.focus_distance)
+        / (distance * (settings.focus_distance - settings.focal_length));
+    return min(coc * settings.pixels_per_meter * 0.5, settings.max_blur_radius);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_color));
+    let coords = vec2<i32>(in.clip_position.xy);
+    let center = textureLoad(t_color, coords, 0);
+    let center_distance = distance_at(coords, size);
+    let center_coc = circle_of_confusion(center_distance);
+    if settings.max_blur_radius < 0.5 {
+        return center;
+    }
+
+    // scatter as gather, a sample lands here when its own blur reaches this far
+    var color = center.rgb;
+    var weight = 1.0;
+    for (var i = 0u; i < SAMPLES; i++) {
+        let radius = sqrt((f32(i) + 0.5) / f32(SAMPLES)) * settings.max_blur_radius;
+        let angle = f32(i) * GOLDEN_ANGLE;
+        let offset = vec2<f32>(cos(angle), sin(angle)) * radius;
+        let sample_coords = coords + vec2<i32>(offset);
+        let sample_distance = distance_at(sample_coords, size);
+        var sample_coc = circle_of_confusion(sample_distance);
+        // sharp things in front shouldn't pick up the blurred background
+        if sample_distance > center_distance {
+            sample_coc = min(sample_coc, center_coc);
+        }
+        let sample_weight = clamp(sample_coc - radius + 1.0, 0.0, 1.0);
+        let uv = in.uv + offset / vec2<f32>(size);
+        color += textureSampleLevel(t_color, s_linear, uv, 0.0).rgb * sample_weight;
+        weight += sample_weight;
+    }
+    return vec4<f32>(color / weight, center.a);
+}
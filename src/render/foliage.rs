@@ -0,0 +1,588 @@
+// Foliage. Each Foliage entity draws its mesh once per instance in a single
+// instanced call per level of detail, with the wind resource and any force
+// fields over it bending vertices by their height, the material's texture
+// alpha tested for leaves, and instances dithering out at the draw distance
+// and between lod levels.
+// Instances are placed with scatter (area + density map) or paint (brush).
+
+use std::ops::Range;
+
+use glam::{Quat, Vec2, Vec3};
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    GpuMesh, Vertex,
+    assets::{Assets, Handle},
+    camera::{Frustum, main_camera},
+    ecs::{component::Component, entity::Entity, world::World},
+    material::Material,
+    mesh::Mesh,
+    physics::{self, Ray},
+    render::post,
+    texture::Texture,
+    transform::{self, Transform},
+    visibility,
+};
+
+// global wind, read by foliage and anything else that wants to sway
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wind {
+    // along the world xz plane
+    pub direction: Vec2,
+    pub strength: f32,
+    // sways per second
+    pub frequency: f32,
+}
+
+impl Component for Wind {}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::X,
+            strength: 0.3,
+            frequency: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoliageLod {
+    // used from this camera distance on
+    pub distance: f32,
+    pub mesh: Handle<Mesh>,
+}
+
+// a camera facing card drawn instead of the mesh far away
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impostor {
+    pub distance: f32,
+    pub texture: Handle<Texture>,
+    // width and height at an instance scale of 1, the card stands on its origin
+    pub size: Vec2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Foliage {
+    pub mesh: Handle<Mesh>,
+    // base color and texture, the texture's alpha is tested against alpha_cutoff
+    pub material: Handle<Material>,
+    // relative to the entity's transform
+    pub instances: Vec<Transform>,
+    // sorted by distance
+    pub lods: Vec<FoliageLod>,
+    pub impostor: Option<Impostor>,
+    pub alpha_cutoff: f32,
+    // instances past this are dropped
+    pub draw_distance: f32,
+    // how far before the draw distance and past each lod distance instances dither
+    pub fade_range: f32,
+    // how much the wind bends the mesh per unit of local height
+    pub wind_response: f32,
+    // how much force fields add to the wind, per unit of their acceleration
+    pub field_response: f32,
+}
+
+impl Component for Foliage {}
+
+impl Foliage {
+    pub fn new(mesh: Handle<Mesh>, material: Handle<Material>) -> Self {
+        Self {
+            mesh,
+            material,
+            instances: Vec::new(),
+            lods: Vec::new(),
+            impostor: None,
+            alpha_cutoff: 0.5,
+            draw_distance: 150.0,
+            fade_range: 5.0,
+            wind_response: 0.2,
+            field_response: 0.05,
+        }
+    }
+
+    // removes the instances within the brush
+    pub fn erase(&mut self, center: Vec2, radius: f32) {
+        self.instances.retain(|instance| {
+            Vec2::new(instance.translation.x, instance.translation.z).distance(center) > radius
+        });
+    }
+}
+
+// grayscale placement weights stretched over the scattered area
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityMap {
+    pub width: u32,
+    pub height: u32,
+    // 0..1, row major
+    pub values: Vec<f32>,
+}
+
+impl DensityMap {
+    pub fn from_image(image: &image::DynamicImage) -> Self {
+        let luma = image.to_luma32f();
+        Self {
+            width: luma.width(),
+            height: luma.height(),
+            values: luma.into_raw(),
+        }
+    }
+
+    // nearest sample, uv in 0..1
+    pub fn sample(&self, uv: Vec2) -> f32 {
+        if self.width == 0 || self.height == 0 {
+            return 0.0;
+        }
+        let x = ((uv.x * self.width as f32) as u32).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as u32).min(self.height - 1);
+        self.values
+            .get((y * self.width + x) as usize)
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScatterSettings {
+    // instances per square unit where the density is 1
+    pub density: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub seed: u64,
+}
+
+impl Default for ScatterSettings {
+    fn default() -> Self {
+        Self {
+            density: 1.0,
+            min_scale: 0.8,
+            max_scale: 1.2,
+            seed: 0,
+        }
+    }
+}
+
+// random instances over the xz area between `min` and `max`, `ground` gives
+// the height at a point or None where nothing should grow
+pub fn scatter(
+    min: Vec2,
+    max: Vec2,
+    settings: ScatterSettings,
+    density_map: Option<&DensityMap>,
+    ground: impl Fn(Vec2) -> Option<f32>,
+) -> Vec<Transform> {
+    let size = (max - min).max(Vec2::ZERO);
+    let count = (size.x * size.y * settings.density.max(0.0)) as u64;
+    let mut random = Random(settings.seed);
+    let mut instances = Vec::new();
+    for _ in 0..count {
+        let uv = Vec2::new(random.next(), random.next());
+        let keep = random.next();
+        let transform = random_transform(&mut random, settings);
+        if density_map.is_some_and(|map| keep >= map.sample(uv)) {
+            continue;
+        }
+        let point = min + uv * size;
+        if let Some(height) = ground(point) {
+            instances.push(Transform {
+                translation: Vec3::new(point.x, height, point.y),
+                ..transform
+            });
+        }
+    }
+    instances
+}
+
+// a round brush stroke for painting foliage in by hand
+pub fn paint(
+    center: Vec2,
+    radius: f32,
+    settings: ScatterSettings,
+    ground: impl Fn(Vec2) -> Option<f32>,
+) -> Vec<Transform> {
+    let count = (std::f32::consts::PI * radius * radius * settings.density.max(0.0)) as u64;
+    let mut random = Random(settings.seed);
+    let mut instances = Vec::new();
+    for _ in 0..count {
+        let angle = random.next() * std::f32::consts::TAU;
+        // sqrt spreads them evenly over the disc instead of bunching at the center
+        let distance = random.next().sqrt() * radius;
+        let transform = random_transform(&mut random, settings);
+        let point = center + Vec2::from_angle(angle) * distance;
+        if let Some(height) = ground(point) {
+            instances.push(Transform {
+                translation: Vec3::new(point.x, height, point.y),
+                ..transform
+            });
+        }
+    }
+    instances
+}
+
+// height of the first collider straight below `point`, for scattering on
+// whatever the scene is made of
+pub fn raycast_ground(world: &World, point: Vec2) -> Option<f32> {
+    const TOP: f32 = 1000.0;
+    let ray = Ray::new(Vec3::new(point.x, TOP, point.y), Vec3::NEG_Y);
+    physics::raycast(world, &ray, TOP * 2.0).map(|hit| hit.point.y)
+}
+
+fn random_transform(random: &mut Random, settings: ScatterSettings) -> Transform {
+    let yaw = random.next() * std::f32::consts::TAU;
+    let scale = settings.min_scale + (settings.max_scale - settings.min_scale) * random.next();
+    Transform {
+        translation: Vec3::ZERO,
+        rotation: Quat::from_rotation_y(yaw),
+        scale: Vec3::splat(scale),
+    }
+}
+
+// splitmix64, placement only has to be cheap and repeatable per seed
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FoliageInstance {
+    model: [[f32; 4]; 4],
+    // dither threshold, negative for the inverted pattern of an instance
+    // fading in while the previous lod fades out
+    fade: f32,
+}
+
+impl FoliageInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FoliageUniform {
+    color: [f32; 4],
+    wind_direction: [f32; 2],
+    wind_strength: f32,
+    wind_frequency: f32,
+    impostor_size: [f32; 2],
+    time: f32,
+    alpha_cutoff: f32,
+    wind_response: f32,
+    _padding: [f32; 3],
+}
+
+// what a level draws with, a mesh or the impostor card
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Level {
+    Mesh(usize),
+    Impostor,
+}
+
+struct FoliageDraw {
+    entity: Entity,
+    level: Level,
+    texture: Option<usize>,
+    instances: Range<u32>,
+}
+
+pub(crate) struct FoliagePass {
+    pipeline: wgpu::RenderPipeline,
+    impostor_pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    instance_buffer: wgpu::Buffer,
+    uniforms: FastHashMap<Entity, (wgpu::Buffer, wgpu::BindGroup)>,
+    draws: Vec<FoliageDraw>,
+}
+
+impl FoliagePass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let mut uniform_entry = post::uniform_entry(0);
+        uniform_entry.visibility = wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("foliage_layout"),
+            entries: &[uniform_entry],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("foliage.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Foliage Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, texture_bind_group_layout, &layout],
+            immediate_size: 0,
+        });
+        let create_pipeline = |label, entry_point, buffers: &[wgpu::VertexBufferLayout<'_>]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    buffers,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                // leaves are single quads seen from both sides
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache,
+            })
+        };
+        let pipeline = create_pipeline(
+            "Foliage Pipeline",
+            "vs_main",
+            &[Vertex::desc(), FoliageInstance::desc()],
+        );
+        // the card's corners come from the vertex index
+        let impostor_pipeline = create_pipeline(
+            "Foliage Impostor Pipeline",
+            "vs_impostor",
+            &[FoliageInstance::desc()],
+        );
+
+        Self {
+            pipeline,
+            impostor_pipeline,
+            layout,
+            instance_buffer: create_instance_buffer(device, 256),
+            uniforms: FastHashMap::default(),
+            draws: Vec::new(),
+        }
+    }
+
+    // sorts every visible instance into its lod level, `frustum` is None when
+    // culling is off
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        meshes: &FastHashMap<usize, GpuMesh>,
+        frustum: Option<&Frustum>,
+        camera_position: Vec3,
+        time: f32,
+    ) {
+        self.draws.clear();
+        let render_layers =
+            main_camera(world).map_or_else(Default::default, |camera| camera.render_layers);
+        let wind = world.get_resource::<Wind>().copied().unwrap_or_default();
+        let materials = world.get_resource::<Assets<Material>>();
+        let mut instances = Vec::new();
+        let mut entities = Vec::new();
+
+        for (entity, foliage) in world.query::<Foliage>() {
+            if !visibility::is_visible_to(world, entity, render_layers) {
+                continue;
+            }
+            entities.push(entity);
+            let material = materials.and_then(|materials| materials.get(foliage.material));
+            let parent = transform::model_matrix(world, entity);
+            // force fields over the patch's origin blow along with the global wind
+            #[cfg(feature = "physics")]
+            let field = physics::force_field::sample(world, parent.w_axis.truncate());
+            #[cfg(not(feature = "physics"))]
+            let field = glam::Vec3::ZERO;
+            let blowing = wind.direction.normalize_or_zero() * wind.strength
+                + Vec2::new(field.x, field.z) * foliage.field_response;
+            let uniform = FoliageUniform {
+                color: material.map_or([1.0; 4], |material| material.base_color.to_linear()),
+                wind_direction: blowing.normalize_or_zero().to_array(),
+                wind_strength: blowing.length(),
+                wind_frequency: wind.frequency,
+                impostor_size: foliage
+                    .impostor
+                    .map_or([0.0; 2], |impostor| impostor.size.to_array()),
+                time,
+                alpha_cutoff: foliage.alpha_cutoff,
+                wind_response: foliage.wind_response,
+                _padding: [0.0; 3],
+            };
+            let layout = &self.layout;
+            let (buffer, _) = self.uniforms.entry(entity).or_insert_with(|| {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("foliage_uniform"),
+                    size: std::mem::size_of::<FoliageUniform>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("foliage_bind_group"),
+                    layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+                (buffer, bind_group)
+            });
+            queue.write_buffer(buffer, 0, bytemuck::bytes_of(&uniform));
+
+            // the start distance and what's drawn from there on
+            let mut levels = vec![(0.0, Level::Mesh(foliage.mesh.id()))];
+            levels.extend(
+                foliage
+                    .lods
+                    .iter()
+                    .map(|lod| (lod.distance, Level::Mesh(lod.mesh.id()))),
+            );
+            if let Some(impostor) = foliage.impostor {
+                levels.push((impostor.distance, Level::Impostor));
+            }
+            let bounds = meshes.get(&foliage.mesh.id()).and_then(|mesh| mesh.aabb);
+            let fade_range = foliage.fade_range.max(0.001);
+
+            let mut buckets = vec![Vec::new(); levels.len()];
+            for instance in &foliage.instances {
+                let model = parent * instance.compute_matrix();
+                let distance = model.w_axis.truncate().distance(camera_position);
+                if distance >= foliage.draw_distance {
+                    continue;
+                }
+                if let (Some(frustum), Some((min, max))) = (frustum, bounds)
+                    && !frustum.intersects_transformed_aabb(model, min, max)
+                {
+                    continue;
+                }
+
+                let level = levels
+                    .iter()
+                    .rposition(|(start, _)| distance >= *start)
+                    .unwrap_or(0);
+                let fade = ((foliage.draw_distance - distance) / fade_range).min(1.0);
+                let model = model.to_cols_array_2d();
+                let blend = (distance - levels[level].0) / fade_range;
+                if level > 0 && blend < 1.0 {
+                    // both levels draw with opposite dither patterns while switching
+                    buckets[level - 1].push(FoliageInstance {
+                        model,
+                        fade: 1.0 - blend,
+                    });
+                    buckets[level].push(FoliageInstance {
+                        model,
+                        fade: -(1.0 - blend),
+                    });
+                } else {
+                    buckets[level].push(FoliageInstance { model, fade });
+                }
+            }
+
+            let texture = material
+                .and_then(|material| material.base_color_texture)
+                .map(|texture| texture.id());
+            for ((_, level), bucket) in levels.into_iter().zip(buckets) {
+                if bucket.is_empty() {
+                    continue;
+                }
+                let start = instances.len() as u32;
+                instances.extend(bucket);
+                self.draws.push(FoliageDraw {
+                    entity,
+                    level,
+                    texture: match level {
+                        Level::Mesh(_) => texture,
+                        Level::Impostor => foliage.impostor.map(|impostor| impostor.texture.id()),
+                    },
+                    instances: start..instances.len() as u32,
+                });
+            }
+        }
+        self.uniforms.retain(|entity, _| entities.contains(entity));
+
+        let size =
+            (instances.len() * std::mem::size_of::<FoliageInstance>()) as wgpu::BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, instances.len() * 2);
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+    }
+
+    // expects the camera bind group to be set
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        meshes: &FastHashMap<usize, GpuMesh>,
+        texture_bind_groups: &FastHashMap<usize, wgpu::BindGroup>,
+        default_bind_group: &wgpu::BindGroup,
+    ) {
+        for draw in &self.draws {
+            let Some((_, bind_group)) = self.uniforms.get(&draw.entity) else {
+                continue;
+            };
+            let texture = draw
+                .texture
+                .and_then(|texture| texture_bind_groups.get(&texture))
+                .unwrap_or(default_bind_group);
+            render_pass.set_bind_group(1, texture, &[]);
+            render_pass.set_bind_group(2, bind_group, &[]);
+            match draw.level {
+                Level::Mesh(mesh) => {
+                    let Some(mesh) = meshes.get(&mesh) else {
+                        continue;
+                    };
+                    render_pass.set_pipeline(&self.pipeline);
+                    render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                    mesh.draw(render_pass, None, draw.instances.clone());
+                }
+                Level::Impostor => {
+                    render_pass.set_pipeline(&self.impostor_pipeline);
+                    render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+                    render_pass.draw(0..6, draw.instances.clone());
+                }
+            }
+        }
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Foliage Instance Buffer"),
+        size: (capacity.max(1) * std::mem::size_of::<FoliageInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// the handles a foliage entity needs uploaded
+pub(crate) fn assets(foliage: &Foliage) -> (Vec<Handle<Mesh>>, Option<Handle<Texture>>) {
+    let mut meshes = vec![foliage.mesh];
+    meshes.extend(foliage.lods.iter().map(|lod| lod.mesh));
+    (meshes, foliage.impostor.map(|impostor| impostor.texture))
+}
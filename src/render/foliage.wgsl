@@ -0,0 +1,139 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+struct FoliageSettings {
+    color: vec4<f32>,
+    wind_direction: vec2<f32>,
+    wind_strength: f32,
+    wind_frequency: f32,
+    impostor_size: vec2<f32>,
+    time: f32,
+    alpha_cutoff: f32,
+    wind_response: f32,
+};
+@group(2) @binding(0)
+var<uniform> settings: FoliageSettings;
+
+struct VertexInput {
+    @location(0) position: vec4<f32>,
+    @location(1) tex_coords: vec4<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(9) fade: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) fade: f32,
+};
+
+const PI: f32 = 3.14159265359;
+
+// sideways push for a point `height` above the instance's origin
+fn wind_offset(origin: vec3<f32>, point: vec3<f32>, height: f32) -> vec3<f32> {
+    let direction = vec3<f32>(settings.wind_direction.x, 0.0, settings.wind_direction.y);
+    // instances further along the wind sway later, so gusts roll across
+    let phase = settings.time * settings.wind_frequency * 2.0 * PI
+        - dot(origin.xz, settings.wind_direction) * 0.3;
+    let sway = sin(phase) * 0.7 + sin(phase * 2.3 + 1.7) * 0.3;
+    // leaves flutter on their own a little faster
+    let flutter = sin(settings.time * settings.wind_frequency * 9.0 + dot(point, vec3<f32>(1.3, 0.7, 1.1)));
+    let bend = max(height, 0.0) * settings.wind_response;
+    let strength = settings.wind_strength;
+    return direction * (strength + sway * strength * 0.5) * bend * bend
+        + vec3<f32>(0.0, flutter, 0.0) * strength * bend * 0.05;
+}
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    let world = (model_matrix * model.position).xyz;
+    let origin = instance.model_3.xyz;
+    let height = world.y - origin.y;
+    let position = world + wind_offset(origin, world, height);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.tex_coords = model.tex_coords.xy;
+    out.fade = instance.fade;
+    return out;
+}
+
+// a card standing on the instance's origin, turned around y to face the camera
+@vertex
+fn vs_impostor(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(-0.5, 0.0),
+        vec2<f32>(0.5, 0.0),
+        vec2<f32>(0.5, 1.0),
+        vec2<f32>(-0.5, 0.0),
+        vec2<f32>(0.5, 1.0),
+        vec2<f32>(-0.5, 1.0),
+    );
+    let corner = corners[index];
+    let origin = instance.model_3.xyz;
+    let scale = length(instance.model_1.xyz);
+    let to_camera = camera.position.xz - origin.xz;
+    var forward = vec2<f32>(0.0, 1.0);
+    if dot(to_camera, to_camera) > 0.0 {
+        forward = normalize(to_camera);
+    }
+    let right = vec3<f32>(forward.y, 0.0, -forward.x);
+    let size = settings.impostor_size * scale;
+    let world = origin + right * corner.x * size.x + vec3<f32>(0.0, corner.y * size.y, 0.0);
+    let position = world + wind_offset(origin, world, world.y - origin.y);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.tex_coords = vec2<f32>(corner.x + 0.5, 1.0 - corner.y);
+    out.fade = instance.fade;
+    return out;
+}
+
+// 4x4 ordered dither, 0..1
+fn bayer(position: vec2<f32>) -> f32 {
+    let matrix = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0,
+    );
+    let coords = vec2<u32>(position) % vec2<u32>(4u);
+    return (matrix[coords.y * 4u + coords.x] + 0.5) / 16.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * settings.color;
+    let dither = bayer(in.clip_position.xy);
+    // negative fades use the inverted pattern so two lods never cover the same pixel
+    if (in.fade >= 0.0 && dither > in.fade) || (in.fade < 0.0 && dither <= -in.fade) {
+        discard;
+    }
+    if color.a < settings.alpha_cutoff {
+        discard;
+    }
+    return vec4<f32>(color.rgb, 1.0);
+}
@@ -0,0 +1,241 @@
+// Golden image tests. A scene is rendered offscreen with time stopped, read
+// back and compared against a reference png with a perceptual tolerance, so
+// driver and rasterizer differences pass while real regressions fail. Without
+// a gpu a software adapter is used, lavapipe or mesa's llvmpipe through gl,
+// which is how this runs under `cargo test` on ci, and without even that the
+// tests are skipped. Missing references are written on the first run, and all
+// of them are rewritten when WHIRLWIND_BLESS is set. They live in tests/golden.
+
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    State,
+    ecs::{schedule::Startup, world::World},
+    error::WhirlwindError,
+    render::readback::Readback,
+    time::Time,
+};
+
+// pixelmatch's yiq delta between black and white
+const MAX_DELTA: f32 = 35215.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoldenTest {
+    pub width: u32,
+    pub height: u32,
+    // rendered before the one that's compared, for history effects like taa to settle
+    pub frames: u32,
+    // 0 to 1, how different a pixel can look before it counts as changed
+    pub threshold: f32,
+    // fraction of changed pixels that still passes
+    pub max_diff_fraction: f32,
+}
+
+impl Default for GoldenTest {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            frames: 1,
+            threshold: 0.1,
+            max_diff_fraction: 0.001,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GoldenDiff {
+    pub differing: usize,
+    pub total: usize,
+    // changed pixels in red over a faded copy of the reference
+    pub image: RgbaImage,
+}
+
+impl GoldenDiff {
+    pub fn fraction(&self) -> f32 {
+        self.differing as f32 / self.total.max(1) as f32
+    }
+}
+
+impl GoldenTest {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    // `setup` runs on a fresh world before "startup", with the RenderDevice in it
+    pub fn render(&self, setup: impl FnOnce(&mut World)) -> anyhow::Result<RgbaImage> {
+        let mut state = pollster::block_on(State::headless(self.width, self.height))?;
+        let mut world = crate::create_world();
+        world.insert_resource(state.render_device());
+        // a scale of 0 keeps every frame at the same virtual time
+        world.resource_mut::<Time>().set_scale(0.0);
+        setup(&mut world);
+        world.run(Startup);
+
+        for _ in 0..=self.frames {
+            state.update(&mut world);
+            state.render(&world)?;
+        }
+
+        let crate::FrameTarget::Headless(texture) = &state.target else {
+            anyhow::bail!("Golden tests need a headless renderer");
+        };
+        let mut encoder = state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("golden_readback_encoder"),
+            });
+        let staging = Readback::texture(texture).encode(&state.device, &mut encoder)?;
+        state.queue.submit(std::iter::once(encoder.finish()));
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging.map(move |result| {
+            let _ = sender.send(result);
+        });
+        state.device.poll(wgpu::PollType::wait_indefinitely())?;
+        let data = receiver.recv()??;
+
+        RgbaImage::from_raw(self.width, self.height, data)
+            .ok_or_else(|| anyhow::anyhow!("Readback doesn't match the target size"))
+    }
+
+    pub fn compare(&self, actual: &RgbaImage, expected: &RgbaImage) -> GoldenDiff {
+        let total = (expected.width() * expected.height()) as usize;
+        if actual.dimensions() != expected.dimensions() {
+            return GoldenDiff {
+                differing: total,
+                total,
+                image: RgbaImage::from_pixel(
+                    expected.width(),
+                    expected.height(),
+                    Rgba([255, 0, 0, 255]),
+                ),
+            };
+        }
+
+        let limit = MAX_DELTA * self.threshold * self.threshold;
+        let mut differing = 0;
+        let image = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+            let a = actual.get_pixel(x, y);
+            let b = expected.get_pixel(x, y);
+            if yiq_delta(a, b) > limit {
+                differing += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                let luma = (yiq(b)[0] * 0.1 + 255.0 * 0.9) as u8;
+                Rgba([luma, luma, luma, 255])
+            }
+        });
+        GoldenDiff {
+            differing,
+            total,
+            image,
+        }
+    }
+
+    // writes `<name>.actual.png` and `<name>.diff.png` next to the reference on failure
+    pub fn check(&self, reference: impl AsRef<Path>, actual: &RgbaImage) -> anyhow::Result<()> {
+        let reference = reference.as_ref();
+        if std::env::var_os("WHIRLWIND_BLESS").is_some() || !reference.exists() {
+            if let Some(parent) = reference.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            actual.save(reference)?;
+            log::warn!("Wrote golden image {}", reference.display());
+            return Ok(());
+        }
+
+        let expected = image::open(reference)?.to_rgba8();
+        let diff = self.compare(actual, &expected);
+        if diff.fraction() <= self.max_diff_fraction {
+            return Ok(());
+        }
+        actual.save(sibling(reference, "actual"))?;
+        diff.image.save(sibling(reference, "diff"))?;
+        anyhow::bail!(
+            "{} of {} pixels differ from {} ({:.3}%, {:.3}% allowed)",
+            diff.differing,
+            diff.total,
+            reference.display(),
+            diff.fraction() * 100.0,
+            self.max_diff_fraction * 100.0
+        )
+    }
+
+    // for `#[test]`s, panics when the render fails or doesn't match. passes
+    // with a note on machines without any adapter, not even a software one
+    pub fn assert(&self, reference: impl AsRef<Path>, setup: impl FnOnce(&mut World)) {
+        let reference = reference.as_ref();
+        let result = self
+            .render(setup)
+            .and_then(|actual| self.check(reference, &actual));
+        if let Err(e) = &result
+            && let Some(WhirlwindError::NoAdapter(reason)) = e.downcast_ref::<WhirlwindError>()
+        {
+            eprintln!("Skipping golden image {}: {reason}", reference.display());
+            return;
+        }
+        if let Err(e) = result {
+            panic!("Golden image {} failed: {}", reference.display(), e);
+        }
+    }
+}
+
+fn sibling(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    reference.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+fn yiq(pixel: &Rgba<u8>) -> [f32; 3] {
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(f32::from);
+    [
+        r * 0.298_895 + g * 0.586_622 + b * 0.114_482,
+        r * 0.595_978 - g * 0.274_176 - b * 0.321_802,
+        r * 0.211_470 - g * 0.522_617 + b * 0.311_147,
+    ]
+}
+
+fn yiq_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let [ya, ia, qa] = yiq(a);
+    let [yb, ib, qb] = yiq(b);
+    0.5053 * (ya - yb).powi(2) + 0.299 * (ia - ib).powi(2) + 0.1957 * (qa - qb).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{EulerRot, Quat, Vec3};
+
+    use super::*;
+    use crate::{assets::embedded::DefaultAssets, transform::Transform};
+
+    #[test]
+    fn default_cube() {
+        GoldenTest::new(128, 128).assert(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/default_cube.png"),
+            |world| {
+                // the default assets are made in startup
+                world.add_system_to(Startup, |world: &mut World| {
+                    let defaults = *world.resource::<DefaultAssets>();
+                    world
+                        .spawn()
+                        .insert(Transform {
+                            // straight ahead of the default camera, turned to show three faces
+                            translation: Vec3::new(2.0, 1.0, 0.0),
+                            rotation: Quat::from_euler(EulerRot::YXZ, 0.6, 0.4, 0.0),
+                            ..Default::default()
+                        })
+                        .insert(defaults.cube)
+                        .insert(defaults.cube_material);
+                });
+            },
+        );
+    }
+}
@@ -0,0 +1,89 @@
+// What the adapter the renderer started on can do. Filled in once the device
+// exists and logged at startup, the `gpu` console command prints it again.
+// Optional features are requested when the adapter has them, subsystems that
+// need one check here and fall back or skip their work instead of hitting a
+// validation error.
+
+use std::fmt;
+
+use crate::ecs::component::Component;
+
+pub use wgpu::AdapterInfo;
+
+// the optional features the device is created with when the adapter has them
+pub(crate) const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::POLYGON_MODE_LINE
+    .union(wgpu::Features::TEXTURE_COMPRESSION_BC)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC)
+    .union(wgpu::Features::TIMESTAMP_QUERY)
+    .union(wgpu::Features::PIPELINE_CACHE);
+
+#[derive(Debug, Clone)]
+pub struct GpuFeatures {
+    pub adapter: AdapterInfo,
+    pub compute_shaders: bool,
+    pub indirect_draws: bool,
+    pub anisotropic_filtering: bool,
+    // line polygon mode, for the debug wireframe view
+    pub wireframe: bool,
+    pub timestamp_queries: bool,
+    pub texture_compression_bc: bool,
+    pub texture_compression_etc2: bool,
+    pub texture_compression_astc: bool,
+    pub limits: wgpu::Limits,
+}
+
+impl Component for GpuFeatures {}
+
+impl GpuFeatures {
+    pub(crate) fn new(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Self {
+        let downlevel = adapter.get_downlevel_capabilities().flags;
+        let features = device.features();
+        Self {
+            adapter: adapter.get_info(),
+            compute_shaders: downlevel.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+            indirect_draws: downlevel.contains(wgpu::DownlevelFlags::INDIRECT_EXECUTION),
+            anisotropic_filtering: downlevel.contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING),
+            wireframe: features.contains(wgpu::Features::POLYGON_MODE_LINE),
+            timestamp_queries: features.contains(wgpu::Features::TIMESTAMP_QUERY),
+            texture_compression_bc: features.contains(wgpu::Features::TEXTURE_COMPRESSION_BC),
+            texture_compression_etc2: features.contains(wgpu::Features::TEXTURE_COMPRESSION_ETC2),
+            texture_compression_astc: features.contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC),
+            limits: device.limits(),
+        }
+    }
+}
+
+impl fmt::Display for GpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} ({:?}, {:?}), driver {} {}",
+            self.adapter.name,
+            self.adapter.backend,
+            self.adapter.device_type,
+            self.adapter.driver,
+            self.adapter.driver_info
+        )?;
+        let flags = [
+            ("compute", self.compute_shaders),
+            ("indirect", self.indirect_draws),
+            ("anisotropy", self.anisotropic_filtering),
+            ("wireframe", self.wireframe),
+            ("timestamps", self.timestamp_queries),
+            ("bc", self.texture_compression_bc),
+            ("etc2", self.texture_compression_etc2),
+            ("astc", self.texture_compression_astc),
+        ];
+        let flags: Vec<String> = flags
+            .iter()
+            .map(|(name, supported)| format!("{}{}", if *supported { '+' } else { '-' }, name))
+            .collect();
+        writeln!(f, "{}", flags.join(" "))?;
+        write!(
+            f,
+            "max texture {}, max bind groups {}",
+            self.limits.max_texture_dimension_2d, self.limits.max_bind_groups
+        )
+    }
+}
@@ -0,0 +1,46 @@
+// Writes entity ids for pixel picking, 0 means nothing was drawn
+
+struct VertexInput {
+    @location(0) position: vec4<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(10) id: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+};
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    var out: VertexOutput;
+    out.id = instance.id;
+    out.clip_position = camera.view_proj * model_matrix * model.position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) u32 {
+    return in.id;
+}
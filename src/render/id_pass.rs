@@ -0,0 +1,208 @@
+// Pixel perfect picking. Only runs on frames with pending `IdPicking::pick_at`
+// requests: the visible meshes are drawn again with their entity ids into an
+// R32Uint target and the texels under the requests are read back.
+
+use std::sync::{Arc, Mutex};
+
+use glam::UVec2;
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    render::readback::Readback,
+    texture::Texture,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct IdPicked {
+    pub position: UVec2,
+    // None when nothing was under the position
+    pub entity: Option<Entity>,
+}
+
+#[derive(Debug, Default)]
+pub struct IdPicking {
+    pub(crate) requests: Vec<UVec2>,
+    // (position, id) from the map callbacks
+    pub(crate) finished: Arc<Mutex<Vec<(UVec2, u32)>>>,
+}
+
+impl Component for IdPicking {}
+
+impl IdPicking {
+    // physical pixels, the result arrives as an IdPicked event a frame or two later
+    pub fn pick_at(&mut self, x: u32, y: u32) {
+        self.requests.push(UVec2::new(x, y));
+    }
+}
+
+pub(crate) fn entity_id(entity: Entity) -> u32 {
+    entity.index() as u32 + 1
+}
+
+pub fn send_id_picks(world: &mut World) {
+    let Some(picking) = world.get_resource::<IdPicking>() else {
+        return;
+    };
+    let finished = std::mem::take(&mut *picking.finished.lock().unwrap());
+    for (position, id) in finished {
+        // None too if it was despawned since the pass ran
+        let entity = id
+            .checked_sub(1)
+            .and_then(|index| world.entity_at(index as usize));
+        world.send_event(IdPicked { position, entity });
+    }
+}
+
+pub(crate) struct IdPass {
+    pipeline: wgpu::RenderPipeline,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl IdPass {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        buffers: &[wgpu::VertexBufferLayout<'_>],
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("id.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Id Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Id Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Self::FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+
+        let (texture, view) = Self::create_target(device, width, height);
+        Self {
+            pipeline,
+            texture,
+            view,
+        }
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("id_texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.texture, self.view) = Self::create_target(device, width, height);
+    }
+
+    // the depth buffer is cleared and reused, the main pass clears it again afterwards
+    pub(crate) fn begin<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        depth: &wgpu::TextureView,
+    ) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Id Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass
+    }
+
+    // positions outside the target resolve to no entity
+    pub(crate) fn read_back(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        positions: &[UVec2],
+    ) -> Vec<(UVec2, Option<crate::render::readback::Staging>)> {
+        let size = self.texture.size();
+        positions
+            .iter()
+            .map(|&position| {
+                let staging = (position.x < size.width && position.y < size.height)
+                    .then(|| {
+                        Readback::texture_region(&self.texture, position, UVec2::ONE)
+                            .encode(device, encoder)
+                            .inspect_err(|e| log::error!("Unable to read back ids: {}", e))
+                            .ok()
+                    })
+                    .flatten();
+                (position, staging)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,596 @@
+// Minimaps. An orthographic camera looks straight down on the map's center
+// and renders the scene's meshes into a texture, then tracked entities are
+// stamped on top as icons. The texture can be shown on screen by the map's
+// widget, or used anywhere else a texture goes.
+
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4Swizzles};
+use wgpu::{naga::FastHashMap, util::DeviceExt};
+
+use crate::{
+    assets::{Assets, Handle},
+    camera::{CameraUniform, main_camera},
+    color::Color,
+    ecs::{component::Component, entity::Entity, world::World},
+    render::{RenderDevice, post},
+    texture::Texture,
+    transform::{self, Transform},
+    ui::UiScale,
+    visibility,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MinimapRotation {
+    // -z is always up on the map
+    #[default]
+    NorthUp,
+    // turns so the target's forward is up
+    FollowTarget,
+}
+
+// where the map is drawn over the frame, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapWidget {
+    // top left corner from the top left of the window
+    pub position: Vec2,
+    pub size: f32,
+    // cuts the map to a circle, with markers pinned to its edge instead of the square's
+    pub circular: bool,
+    pub opacity: f32,
+}
+
+impl Default for MinimapWidget {
+    fn default() -> Self {
+        Self {
+            position: Vec2::splat(16.0),
+            size: 192.0,
+            circular: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Minimap {
+    // what the map renders into, square
+    pub texture: Handle<Texture>,
+    // entity the map is centered on, the camera's position when None
+    pub target: Option<Entity>,
+    // world units from the center to the map's edge, smaller zooms in
+    pub radius: f32,
+    pub rotation: MinimapRotation,
+    // how far above the center the map looks down from, and below it sees
+    pub height: f32,
+    pub background: Color,
+    pub widget: Option<MinimapWidget>,
+}
+
+impl Component for Minimap {}
+
+impl Minimap {
+    // creates the target texture, so the render device must already exist
+    pub fn new(world: &mut World, size: u32) -> anyhow::Result<Self> {
+        let RenderDevice {
+            device,
+            surface_format,
+            ..
+        } = world
+            .get_resource::<RenderDevice>()
+            .ok_or(anyhow::anyhow!("Render device is not ready"))?
+            .clone();
+        let texture = Texture::create_render_target(&device, size, size, surface_format, "minimap");
+        Ok(Self {
+            texture: world.resource_mut::<Assets<Texture>>().add(texture),
+            target: None,
+            radius: 32.0,
+            rotation: MinimapRotation::NorthUp,
+            height: 64.0,
+            background: Color::BLACK,
+            widget: Some(MinimapWidget::default()),
+        })
+    }
+}
+
+// shows the entity as an icon on every minimap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapMarker {
+    pub icon: Handle<Texture>,
+    pub color: Color,
+    // pixels of the map's texture
+    pub size: f32,
+    // turns the icon's up with the entity's forward
+    pub rotate: bool,
+    // kept on the edge of the map when the entity is off it
+    pub pin_to_edge: bool,
+}
+
+impl Component for MinimapMarker {}
+
+impl MinimapMarker {
+    pub fn new(icon: Handle<Texture>) -> Self {
+        Self {
+            icon,
+            color: Color::WHITE,
+            size: 16.0,
+            rotate: false,
+            pin_to_edge: false,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MarkerInstance {
+    // ndc of the icon's center, then from it to the right edge and the top edge
+    center: [f32; 4],
+    axes: [f32; 4],
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct WidgetInstance {
+    // physical pixels from the top left of the window, min then max
+    rect: [f32; 4],
+    // circular, then opacity
+    params: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScreenUniform {
+    screen_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+// every attribute is a vec4
+fn instance_layout(
+    attributes: &'static [wgpu::VertexAttribute],
+) -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: attributes.len() as wgpu::BufferAddress * 16,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes,
+    }
+}
+
+const MARKER_ATTRIBS: [wgpu::VertexAttribute; 3] =
+    wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
+const WIDGET_ATTRIBS: [wgpu::VertexAttribute; 2] =
+    wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+
+struct MinimapTarget {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    depth_view: wgpu::TextureView,
+    size: wgpu::Extent3d,
+    background: wgpu::Color,
+    markers: Option<wgpu::Buffer>,
+    // icon texture and instances of each icon in `markers`
+    batches: Vec<(usize, std::ops::Range<u32>)>,
+}
+
+pub(crate) struct MinimapPass {
+    marker_pipeline: wgpu::RenderPipeline,
+    widget_pipeline: wgpu::RenderPipeline,
+    screen_buffer: wgpu::Buffer,
+    screen_bind_group: wgpu::BindGroup,
+    targets: FastHashMap<Entity, MinimapTarget>,
+    widgets: Option<wgpu::Buffer>,
+    // map texture of each widget in `widgets`
+    widget_textures: Vec<usize>,
+}
+
+impl MinimapPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("minimap.wgsl"));
+        let mut uniform_entry = post::uniform_entry(0);
+        uniform_entry.visibility = wgpu::ShaderStages::VERTEX;
+        let screen_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("minimap_screen_layout"),
+            entries: &[uniform_entry],
+        });
+        let screen_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("minimap_screen_uniform"),
+            size: std::mem::size_of::<ScreenUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("minimap_screen_bind_group"),
+            layout: &screen_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline = |label: &str,
+                        vertex: &str,
+                        fragment: &str,
+                        buffer: wgpu::VertexBufferLayout<'static>,
+                        depth_stencil: Option<wgpu::DepthStencilState>| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[&screen_layout, texture_bind_group_layout],
+                immediate_size: 0,
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some(vertex),
+                    buffers: &[buffer],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(fragment),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil,
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache,
+            })
+        };
+        // markers go into the map's own pass, which has a depth attachment
+        let marker_pipeline = pipeline(
+            "Minimap Marker Pipeline",
+            "vs_marker",
+            "fs_marker",
+            instance_layout(&MARKER_ATTRIBS),
+            Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+        );
+        let widget_pipeline = pipeline(
+            "Minimap Widget Pipeline",
+            "vs_widget",
+            "fs_widget",
+            instance_layout(&WIDGET_ATTRIBS),
+            None,
+        );
+
+        Self {
+            marker_pipeline,
+            widget_pipeline,
+            screen_buffer,
+            screen_bind_group,
+            targets: FastHashMap::default(),
+            widgets: None,
+            widget_textures: Vec::new(),
+        }
+    }
+
+    // points each map's camera down at its center and places its markers,
+    // returns the maps that can be drawn this frame
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        world: &World,
+        screen_size: Vec2,
+    ) -> Vec<(Entity, Handle<Texture>)> {
+        let minimaps = world.query::<Minimap>();
+        self.targets
+            .retain(|entity, _| minimaps.iter().any(|(other, _)| other == entity));
+        self.widgets = None;
+        self.widget_textures.clear();
+        let Some(textures) = world.get_resource::<Assets<Texture>>() else {
+            return Vec::new();
+        };
+        let camera = main_camera(world);
+        let transform =
+            |entity: Entity| transform::world_transform(world, entity).unwrap_or_default();
+        let markers: Vec<(Transform, MinimapMarker)> = world
+            .query::<MinimapMarker>()
+            .into_iter()
+            .filter(|(entity, _)| visibility::is_visible(world, *entity))
+            .map(|(entity, marker)| (transform(entity), *marker))
+            .collect();
+
+        let mut ready = Vec::new();
+        let mut widgets = Vec::new();
+        for (entity, minimap) in minimaps {
+            let Some(texture) = textures.get(minimap.texture) else {
+                continue;
+            };
+            let size = texture.texture.size();
+            let target = self
+                .targets
+                .entry(entity)
+                .or_insert_with(|| Self::create_target(device, camera_bind_group_layout, size));
+            if target.size != size {
+                *target = Self::create_target(device, camera_bind_group_layout, size);
+            }
+
+            let (center, heading) = match minimap.target {
+                Some(entity) => {
+                    let transform = transform(entity);
+                    (transform.translation, transform.rotation)
+                }
+                None => camera.map_or((Vec3::ZERO, Quat::IDENTITY), |camera| {
+                    (camera.pos, camera.rotation)
+                }),
+            };
+            let up = match minimap.rotation {
+                MinimapRotation::NorthUp => Vec3::NEG_Z,
+                MinimapRotation::FollowTarget => {
+                    let forward = heading * Vec3::NEG_Z;
+                    Vec3::new(forward.x, 0.0, forward.z)
+                        .try_normalize()
+                        .unwrap_or(Vec3::NEG_Z)
+                }
+            };
+            let eye = center + Vec3::Y * minimap.height;
+            let view = Mat4::look_to_rh(eye, Vec3::NEG_Y, up);
+            let radius = minimap.radius.max(0.001);
+            let projection =
+                Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, minimap.height * 2.0);
+            let view_proj = projection * view;
+            queue.write_buffer(
+                &target.buffer,
+                0,
+                bytemuck::cast_slice(&[CameraUniform::from_view_proj(view_proj, eye)]),
+            );
+            let [r, g, b, a] = minimap.background.to_linear();
+            target.background = wgpu::Color {
+                r: r as f64,
+                g: g as f64,
+                b: b as f64,
+                a: a as f64,
+            };
+
+            let circular = minimap.widget.is_some_and(|widget| widget.circular);
+            let instances = place_markers(&markers, view_proj, size.width as f32, circular);
+            target.batches.clear();
+            let mut sorted = Vec::with_capacity(instances.len());
+            for (icon, instance) in instances {
+                let index = sorted.len() as u32;
+                sorted.push(instance);
+                match target.batches.last_mut() {
+                    Some((last, range)) if *last == icon => range.end = index + 1,
+                    _ => target.batches.push((icon, index..index + 1)),
+                }
+            }
+            target.markers = (!sorted.is_empty()).then(|| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Minimap Marker Buffer"),
+                    contents: bytemuck::cast_slice(&sorted),
+                    usage: wgpu::BufferUsages::VERTEX,
+                })
+            });
+
+            if let Some(widget) = minimap.widget {
+                let scale = world.get_resource::<UiScale>().map_or(1.0, UiScale::factor);
+                let min = widget.position * scale;
+                let max = (widget.position + widget.size) * scale;
+                widgets.push(WidgetInstance {
+                    rect: [min.x, min.y, max.x, max.y],
+                    params: [widget.circular as u32 as f32, widget.opacity, 0.0, 0.0],
+                });
+                self.widget_textures.push(minimap.texture.id());
+            }
+            ready.push((entity, minimap.texture));
+        }
+
+        if !widgets.is_empty() {
+            let uniform = ScreenUniform {
+                screen_size: screen_size.to_array(),
+                _padding: [0.0; 2],
+            };
+            queue.write_buffer(&self.screen_buffer, 0, bytemuck::bytes_of(&uniform));
+            self.widgets = Some(
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Minimap Widget Buffer"),
+                    contents: bytemuck::cast_slice(&widgets),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }),
+            );
+        }
+        ready
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        size: wgpu::Extent3d,
+    ) -> MinimapTarget {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("minimap_camera"),
+            size: std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("minimap_camera_bind_group"),
+            layout: camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("minimap_depth"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        MinimapTarget {
+            buffer,
+            bind_group,
+            depth_view: depth.create_view(&wgpu::TextureViewDescriptor::default()),
+            size,
+            background: wgpu::Color::BLACK,
+            markers: None,
+            batches: Vec::new(),
+        }
+    }
+
+    // a pass drawing into the map, with its camera bound to group 0
+    pub(crate) fn begin<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        entity: Entity,
+        view: &wgpu::TextureView,
+    ) -> Option<wgpu::RenderPass<'a>> {
+        let target = self.targets.get(&entity)?;
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Minimap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(target.background),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &target.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        Some(render_pass)
+    }
+
+    // stamps the markers over the map in a pass from `begin`
+    pub(crate) fn draw_markers(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        entity: Entity,
+        texture_bind_groups: &FastHashMap<usize, wgpu::BindGroup>,
+        default_bind_group: &wgpu::BindGroup,
+    ) {
+        let Some(target) = self.targets.get(&entity) else {
+            return;
+        };
+        let Some(buffer) = &target.markers else {
+            return;
+        };
+        render_pass.set_pipeline(&self.marker_pipeline);
+        render_pass.set_bind_group(0, &self.screen_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, buffer.slice(..));
+        for (icon, instances) in &target.batches {
+            let icon = texture_bind_groups.get(icon).unwrap_or(default_bind_group);
+            render_pass.set_bind_group(1, icon, &[]);
+            render_pass.draw(0..6, instances.clone());
+        }
+    }
+
+    // draws every map's widget over whatever is in `target`
+    pub(crate) fn draw_widgets(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        texture_bind_groups: &FastHashMap<usize, wgpu::BindGroup>,
+    ) {
+        let Some(buffer) = &self.widgets else {
+            return;
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Minimap Widget Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(&self.widget_pipeline);
+        render_pass.set_bind_group(0, &self.screen_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, buffer.slice(..));
+        for (index, texture) in self.widget_textures.iter().enumerate() {
+            let Some(bind_group) = texture_bind_groups.get(texture) else {
+                continue;
+            };
+            render_pass.set_bind_group(1, bind_group, &[]);
+            let index = index as u32;
+            render_pass.draw(0..6, index..index + 1);
+        }
+    }
+}
+
+// icon texture and instance of each marker on a map `width` pixels across,
+// sorted by icon
+fn place_markers(
+    markers: &[(Transform, MinimapMarker)],
+    view_proj: Mat4,
+    width: f32,
+    circular: bool,
+) -> Vec<(usize, MarkerInstance)> {
+    let mut instances: Vec<(usize, MarkerInstance)> = markers
+        .iter()
+        .filter_map(|(transform, marker)| {
+            let position = (view_proj * transform.translation.extend(1.0)).xy();
+            let outside = if circular {
+                position.length() > 1.0
+            } else {
+                position.abs().max_element() > 1.0
+            };
+            let center = match (outside, marker.pin_to_edge) {
+                (false, _) => position,
+                (true, false) => return None,
+                (true, true) if circular => position.normalize(),
+                (true, true) => position / position.abs().max_element(),
+            };
+            // the texture is square, so ndc is the same scale both ways
+            let half = marker.size / width.max(1.0);
+            let (right, up) = if marker.rotate {
+                let ahead = transform.translation + transform.rotation * Vec3::NEG_Z;
+                let direction = ((view_proj * ahead.extend(1.0)).xy() - position)
+                    .try_normalize()
+                    .unwrap_or(Vec2::Y);
+                (Vec2::new(direction.y, -direction.x), direction)
+            } else {
+                (Vec2::X, Vec2::Y)
+            };
+            Some((
+                marker.icon.id(),
+                MarkerInstance {
+                    center: [center.x, center.y, 0.0, 0.0],
+                    axes: [right.x * half, right.y * half, up.x * half, up.y * half],
+                    color: marker.color.to_linear(),
+                },
+            ))
+        })
+        .collect();
+    instances.sort_by_key(|(icon, _)| *icon);
+    instances
+}
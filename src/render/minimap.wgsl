@@ -0,0 +1,86 @@
+struct ScreenUniform {
+    screen_size: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> screen: ScreenUniform;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+fn quad_corner(index: u32) -> vec2<f32> {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    return corners[index];
+}
+
+struct MarkerInput {
+    // ndc of the icon's center, then from it to the right edge and the top edge
+    @location(0) center: vec4<f32>,
+    @location(1) axes: vec4<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    // circular, then opacity
+    @location(2) params: vec4<f32>,
+};
+
+@vertex
+fn vs_marker(@builtin(vertex_index) index: u32, instance: MarkerInput) -> VertexOutput {
+    let corner = quad_corner(index);
+    let offset = corner * 2.0 - 1.0;
+    let position = instance.center.xy + instance.axes.xy * offset.x + instance.axes.zw * offset.y;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.tex_coords = vec2<f32>(corner.x, 1.0 - corner.y);
+    out.color = instance.color;
+    out.params = vec4<f32>(0.0);
+    return out;
+}
+
+@fragment
+fn fs_marker(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+}
+
+struct WidgetInput {
+    // physical pixels from the top left, min then max
+    @location(0) rect: vec4<f32>,
+    @location(1) params: vec4<f32>,
+};
+
+@vertex
+fn vs_widget(@builtin(vertex_index) index: u32, instance: WidgetInput) -> VertexOutput {
+    let corner = quad_corner(index);
+    let pixel = mix(instance.rect.xy, instance.rect.zw, corner);
+    let ndc = pixel / screen.screen_size * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.tex_coords = corner;
+    out.color = vec4<f32>(1.0);
+    out.params = instance.params;
+    return out;
+}
+
+@fragment
+fn fs_widget(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // a pixel wide fade at the circle's edge
+    let distance = length(in.tex_coords - 0.5) * 2.0;
+    let circle = 1.0 - smoothstep(1.0 - fwidth(distance), 1.0, distance);
+    let mask = select(1.0, circle, in.params.x > 0.5);
+    return vec4<f32>(color.rgb, color.a * mask * in.params.y);
+}
@@ -0,0 +1,183 @@
+pub mod compute;
+#[cfg(feature = "render3d")]
+pub mod dof;
+#[cfg(feature = "render3d")]
+pub mod foliage;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod golden;
+pub mod gpu;
+pub mod id_pass;
+pub mod minimap;
+pub(crate) mod morph;
+pub mod motion_blur;
+pub mod outline;
+pub mod output;
+pub(crate) mod pipeline_cache;
+pub mod planar;
+pub(crate) mod post;
+pub(crate) mod prepass;
+pub mod prewarm;
+#[cfg(feature = "render3d")]
+pub mod probe;
+pub mod readback;
+pub mod resolution;
+pub mod sky;
+pub(crate) mod skybox;
+#[cfg(feature = "render2d")]
+pub(crate) mod sprite;
+#[cfg(feature = "render3d")]
+pub mod ssr;
+pub(crate) mod taa;
+#[cfg(feature = "ui")]
+pub(crate) mod text;
+#[cfg(feature = "render2d")]
+pub(crate) mod tilemap;
+#[cfg(feature = "render3d")]
+pub(crate) mod transient;
+#[cfg(feature = "render3d")]
+pub mod upsample;
+#[cfg(feature = "render3d")]
+pub mod volumetric;
+#[cfg(feature = "render3d")]
+pub mod water;
+
+use std::path::PathBuf;
+
+use crate::{assets::Handle, color::Color, ecs::component::Component, texture::Texture};
+
+// cloned handles to the renderer's device so systems can create and update gpu resources
+#[derive(Debug, Clone)]
+pub struct RenderDevice {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    // what render targets the mesh pipelines draw into have to use
+    pub surface_format: wgpu::TextureFormat,
+}
+
+impl Component for RenderDevice {}
+
+pub use output::{OutputFormat, TransferFunction};
+pub use wgpu::PresentMode;
+
+// changes are picked up at the start of the next frame and reconfigure the surface,
+// except `output_format` and `pipeline_cache` which are only read when the
+// renderer starts
+#[derive(Debug, Clone)]
+pub struct SurfaceSettings {
+    pub present_mode: PresentMode,
+    // frames the cpu may queue ahead of the gpu, lower means less input lag
+    pub frame_latency: u32,
+    pub output_format: OutputFormat,
+    // on hdr outputs, the nits a value of 1.0 is shown at
+    pub paper_white: f32,
+    // on hdr outputs, the brightest the display goes, highlights roll off towards it
+    pub max_luminance: f32,
+    // the directory compiled pipelines are kept in between launches, None turns it off
+    pub pipeline_cache: Option<PathBuf>,
+    pub(crate) supported_present_modes: Vec<PresentMode>,
+    pub(crate) supported_output_formats: Vec<OutputFormat>,
+    pub(crate) active_output_format: OutputFormat,
+}
+
+impl Component for SurfaceSettings {}
+
+impl Default for SurfaceSettings {
+    fn default() -> Self {
+        Self {
+            present_mode: PresentMode::AutoVsync,
+            frame_latency: 2,
+            output_format: OutputFormat::Sdr,
+            paper_white: 203.0,
+            max_luminance: 1000.0,
+            pipeline_cache: Some(PathBuf::from("pipeline_cache")),
+            supported_present_modes: Vec::new(),
+            supported_output_formats: Vec::new(),
+            active_output_format: OutputFormat::Sdr,
+        }
+    }
+}
+
+impl SurfaceSettings {
+    // empty until the surface exists, the Auto modes are always accepted
+    pub fn supported_present_modes(&self) -> &[PresentMode] {
+        &self.supported_present_modes
+    }
+
+    pub fn is_supported(&self, present_mode: PresentMode) -> bool {
+        matches!(
+            present_mode,
+            PresentMode::AutoVsync | PresentMode::AutoNoVsync
+        ) || self.supported_present_modes.contains(&present_mode)
+    }
+
+    // empty until the surface exists, Sdr is always there
+    pub fn supported_output_formats(&self) -> &[OutputFormat] {
+        &self.supported_output_formats
+    }
+
+    // what the surface was created with, Sdr when `output_format` isn't supported
+    pub fn active_output_format(&self) -> OutputFormat {
+        self.active_output_format
+    }
+
+    pub fn transfer_function(&self) -> TransferFunction {
+        self.active_output_format.transfer_function()
+    }
+}
+
+// the color cameras with `Background::ClearColor` clear to
+#[derive(Debug, Clone, Copy)]
+pub struct ClearColor(pub Color);
+
+impl Component for ClearColor {}
+
+impl Default for ClearColor {
+    fn default() -> Self {
+        Self(Color::linear_rgb(0.1, 0.2, 0.3))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Background {
+    #[default]
+    ClearColor,
+    Color(Color),
+    // equirectangular panorama
+    Skybox(Handle<Texture>),
+    // analytic sky lit by the SunLight resource
+    ProceduralSky(sky::ProceduralSky),
+    // keep whatever is already in the target, for overlay cameras
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AntiAliasing {
+    #[default]
+    None,
+    // jittered frames resolved against the reprojected history
+    Taa,
+}
+
+// renderer wide options, read every frame
+#[derive(Debug, Clone, Default)]
+pub struct RenderSettings {
+    pub anti_aliasing: AntiAliasing,
+}
+
+impl Component for RenderSettings {}
+
+// what the renderer did the frame before, filled in at the start of each frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderStats {
+    // mesh draws across every pass, shadows and prepasses included
+    pub draw_calls: u32,
+    // on the gpu, uploaded and not yet freed
+    pub meshes: usize,
+    pub textures: usize,
+    // what wgpu has allocated, where the backend reports it and only with `track_memory`
+    pub gpu_memory: Option<u64>,
+    // the report walks every allocation, so it's off unless something shows it
+    pub track_memory: bool,
+}
+
+impl Component for RenderStats {}
@@ -0,0 +1,239 @@
+// Morph targets. Each mesh's position deltas are packed into a float texture
+// and the mesh shader adds them up by vertex index, weighted by the entity's
+// MorphWeights. A texture rather than a storage buffer so it also works on
+// webgl, and the weights of every draw share one uniform buffer that's bound
+// with a dynamic offset.
+
+use bytemuck::Zeroable;
+use wgpu::naga::FastHashMap;
+
+use crate::mesh::Mesh;
+
+// the uniform holds this many weights, further targets are ignored
+pub const MAX_TARGETS: usize = 60;
+// texels per row of the delta texture, the shader has the same constant
+const TEXTURE_WIDTH: u32 = 1024;
+
+// exactly the 256 byte dynamic offset alignment
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphUniform {
+    vertex_count: u32,
+    target_count: u32,
+    _padding: [u32; 2],
+    weights: [[f32; 4]; MAX_TARGETS / 4],
+}
+
+struct MeshTargets {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    vertex_count: u32,
+    target_count: u32,
+}
+
+pub(crate) struct MorphTargets {
+    pub(crate) layout: wgpu::BindGroupLayout,
+    weights_buffer: wgpu::Buffer,
+    // for meshes without targets, the uniform at offset 0 has none
+    empty_view: wgpu::TextureView,
+    empty_bind_group: wgpu::BindGroup,
+    meshes: FastHashMap<usize, MeshTargets>,
+    uniforms: Vec<MorphUniform>,
+}
+
+impl MorphTargets {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("morph_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<MorphUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let weights_buffer = create_weights_buffer(device, 16);
+        let empty_view = create_delta_texture(device, 1).create_view(&Default::default());
+        let empty_bind_group = create_bind_group(device, &layout, &empty_view, &weights_buffer);
+
+        Self {
+            layout,
+            weights_buffer,
+            empty_view,
+            empty_bind_group,
+            meshes: FastHashMap::default(),
+            uniforms: Vec::new(),
+        }
+    }
+
+    // does nothing for meshes without targets
+    pub(crate) fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: usize,
+        mesh: &Mesh,
+    ) {
+        if mesh.morph_targets.is_empty() {
+            return;
+        }
+        let vertex_count = mesh.vertices.len();
+        let targets = &mesh.morph_targets[..mesh.morph_targets.len().min(MAX_TARGETS)];
+
+        // target after target, missing deltas are zero. the shader is unlit so
+        // only the positions are needed for now
+        let rows = ((vertex_count * targets.len()) as u32).div_ceil(TEXTURE_WIDTH);
+        let mut texels = vec![[0.0f32; 4]; (rows.max(1) * TEXTURE_WIDTH) as usize];
+        for (target, morph) in targets.iter().enumerate() {
+            for (vertex, delta) in morph.positions.iter().take(vertex_count).enumerate() {
+                let [x, y, z] = *delta;
+                texels[target * vertex_count + vertex] = [x, y, z, 0.0];
+            }
+        }
+
+        let texture = create_delta_texture(device, rows.max(1));
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(TEXTURE_WIDTH * 16),
+                rows_per_image: None,
+            },
+            texture.size(),
+        );
+        let view = texture.create_view(&Default::default());
+        let bind_group = create_bind_group(device, &self.layout, &view, &self.weights_buffer);
+        self.meshes.insert(
+            id,
+            MeshTargets {
+                view,
+                bind_group,
+                vertex_count: vertex_count as u32,
+                target_count: targets.len() as u32,
+            },
+        );
+    }
+
+    // for a different world, whose mesh ids mean other meshes
+    pub(crate) fn forget_meshes(&mut self) {
+        self.meshes.clear();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.uniforms.clear();
+        self.uniforms.push(MorphUniform::zeroed());
+    }
+
+    // the dynamic offset for a draw of `mesh` with these weights
+    pub(crate) fn push(&mut self, mesh: usize, weights: Option<&[f32]>) -> u32 {
+        let (Some(targets), Some(weights)) = (self.meshes.get(&mesh), weights) else {
+            return 0;
+        };
+        let mut uniform = MorphUniform {
+            vertex_count: targets.vertex_count,
+            target_count: targets.target_count,
+            ..MorphUniform::zeroed()
+        };
+        for (i, weight) in weights.iter().take(MAX_TARGETS).enumerate() {
+            uniform.weights[i / 4][i % 4] = *weight;
+        }
+        self.uniforms.push(uniform);
+        ((self.uniforms.len() - 1) * std::mem::size_of::<MorphUniform>()) as u32
+    }
+
+    pub(crate) fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let size = std::mem::size_of_val(self.uniforms.as_slice()) as wgpu::BufferAddress;
+        if size > self.weights_buffer.size() {
+            // every bind group points at the old buffer
+            self.weights_buffer = create_weights_buffer(device, self.uniforms.len() * 2);
+            self.empty_bind_group =
+                create_bind_group(device, &self.layout, &self.empty_view, &self.weights_buffer);
+            for targets in self.meshes.values_mut() {
+                targets.bind_group =
+                    create_bind_group(device, &self.layout, &targets.view, &self.weights_buffer);
+            }
+        }
+        queue.write_buffer(
+            &self.weights_buffer,
+            0,
+            bytemuck::cast_slice(&self.uniforms),
+        );
+    }
+
+    pub(crate) fn bind_group(&self, mesh: usize) -> &wgpu::BindGroup {
+        self.meshes
+            .get(&mesh)
+            .map_or(&self.empty_bind_group, |targets| &targets.bind_group)
+    }
+}
+
+fn create_weights_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("morph_weights"),
+        size: (capacity.max(1) * std::mem::size_of::<MorphUniform>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_delta_texture(device: &wgpu::Device, rows: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("morph_deltas"),
+        size: wgpu::Extent3d {
+            width: TEXTURE_WIDTH,
+            height: rows,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    weights_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("morph_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: weights_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<MorphUniform>() as u64),
+                }),
+            },
+        ],
+    })
+}
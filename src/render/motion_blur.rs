@@ -0,0 +1,125 @@
+// Motion blur along the prepass motion vectors, so both camera and object
+// movement smear.
+
+use crate::render::post::{self, PostTargets};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionBlur {
+    // share of the frame the shutter is open for, 0.5 is a 180 degree shutter
+    pub shutter: f32,
+    pub samples: u32,
+}
+
+impl Default for MotionBlur {
+    fn default() -> Self {
+        Self {
+            shutter: 0.5,
+            samples: 8,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MotionBlurUniform {
+    shutter: f32,
+    samples: u32,
+    _padding: [u32; 2],
+}
+
+pub(crate) struct MotionBlurPass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    settings_buffer: wgpu::Buffer,
+}
+
+impl MotionBlurPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("motion_blur_layout"),
+            entries: &[
+                post::texture_entry(0),
+                post::sampler_entry(1),
+                post::texture_entry(2),
+                post::uniform_entry(3),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("motion_blur.wgsl"));
+        let pipeline = post::fullscreen_pipeline(
+            device,
+            cache,
+            "Motion Blur Pipeline",
+            &shader,
+            &[&layout],
+            format,
+        );
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("motion_blur_settings"),
+            size: std::mem::size_of::<MotionBlurUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            layout,
+            settings_buffer,
+        }
+    }
+
+    pub(crate) fn run(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        post: &mut PostTargets,
+        motion: &wgpu::TextureView,
+        settings: MotionBlur,
+    ) {
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&MotionBlurUniform {
+                shutter: settings.shutter.clamp(0.0, 1.0),
+                samples: settings.samples.clamp(1, 32),
+                _padding: [0; 2],
+            }),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("motion_blur_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&post.current().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&post.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(motion),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.settings_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let (_, target) = post.swap();
+        post::run_fullscreen(
+            encoder,
+            "Motion Blur Pass",
+            &target.view,
+            &self.pipeline,
+            &[&bind_group],
+        );
+    }
+}
@@ -0,0 +1,46 @@
+struct MotionBlurSettings {
+    shutter: f32,
+    samples: u32,
+};
+
+@group(0) @binding(0)
+var t_color: texture_2d<f32>;
+@group(0) @binding(1)
+var s_linear: sampler;
+@group(0) @binding(2)
+var t_motion: texture_2d<f32>;
+@group(0) @binding(3)
+var<uniform> settings: MotionBlurSettings;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(in.clip_position.xy);
+    let center = textureLoad(t_color, coords, 0);
+    let velocity = textureLoad(t_motion, coords, 0).xy * settings.shutter;
+    if settings.samples <= 1u || dot(velocity, velocity) == 0.0 {
+        return center;
+    }
+
+    // centered on the pixel, half the smear before and half after
+    var color = vec3<f32>(0.0);
+    for (var i = 0u; i < settings.samples; i++) {
+        let t = f32(i) / f32(settings.samples - 1u) - 0.5;
+        let uv = clamp(in.uv - velocity * t, vec2<f32>(0.0), vec2<f32>(1.0));
+        color += textureSampleLevel(t_color, s_linear, uv, 0.0).rgb;
+    }
+    return vec4<f32>(color / f32(settings.samples), center.a);
+}
@@ -0,0 +1,90 @@
+// Mask: outlined meshes write their outline color, with the width in alpha
+
+struct VertexInput {
+    @location(0) position: vec4<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(9) outline: vec4<f32>,
+};
+
+struct MaskOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) outline: vec4<f32>,
+};
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@vertex
+fn vs_mask(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> MaskOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    var out: MaskOutput;
+    out.outline = instance.outline;
+    out.clip_position = camera.view_proj * model_matrix * model.position;
+    return out;
+}
+
+@fragment
+fn fs_mask(in: MaskOutput) -> @location(0) vec4<f32> {
+    return in.outline;
+}
+
+// Composite: pixels outside the mask take the color of the nearest masked
+// pixel that is within its width
+
+const MAX_WIDTH: i32 = 8;
+
+@group(0) @binding(0)
+var t_mask: texture_2d<f32>;
+
+@vertex
+fn vs_composite(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_composite(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let center = vec2<i32>(position.xy);
+    let size = vec2<i32>(textureDimensions(t_mask));
+    if textureLoad(t_mask, center, 0).a > 0.0 {
+        discard;
+    }
+
+    var best = vec4<f32>(0.0);
+    var best_distance = f32(MAX_WIDTH) + 1.0;
+    for (var y = -MAX_WIDTH; y <= MAX_WIDTH; y++) {
+        for (var x = -MAX_WIDTH; x <= MAX_WIDTH; x++) {
+            let coords = center + vec2<i32>(x, y);
+            if any(coords < vec2<i32>(0)) || any(coords >= size) {
+                continue;
+            }
+            let sample = textureLoad(t_mask, coords, 0);
+            let distance = length(vec2<f32>(f32(x), f32(y)));
+            if sample.a > 0.0 && distance <= sample.a && distance < best_distance {
+                best = sample;
+                best_distance = distance;
+            }
+        }
+    }
+    if best.a <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(best.rgb, 1.0);
+}
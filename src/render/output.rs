@@ -0,0 +1,182 @@
+// What the window's swapchain holds and how the finished frame is encoded into
+// it. The format is picked once when the surface is created, every pipeline is
+// built for it. Outputs the hardware can't encode, hdr and 8 bit surfaces
+// without an srgb variant, render the scene into a linear half float target
+// instead and the final blit tonemaps and encodes it for the surface.
+// wgpu doesn't expose swapchain color spaces. A half float surface is taken to
+// be scRGB, which is how vulkan and dx12 present it, and a 10 bit one HDR10,
+// which only looks right where the compositor treats it that way.
+
+// the format asked for with `SurfaceSettings::output_format`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    // 8 bit srgb
+    #[default]
+    Sdr,
+    // linear half float in rec.709 primaries, 1.0 is 80 nits and brighter
+    // values go past it
+    ScRgb,
+    // 10 bit rec.2020 with the pq curve
+    Hdr10,
+}
+
+// how values written to the surface map to light on the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferFunction {
+    Srgb,
+    Linear,
+    Pq,
+}
+
+impl OutputFormat {
+    pub fn transfer_function(self) -> TransferFunction {
+        match self {
+            Self::Sdr => TransferFunction::Srgb,
+            Self::ScRgb => TransferFunction::Linear,
+            Self::Hdr10 => TransferFunction::Pq,
+        }
+    }
+
+    pub fn is_hdr(self) -> bool {
+        self != Self::Sdr
+    }
+
+    fn surface_format(self) -> Option<wgpu::TextureFormat> {
+        match self {
+            Self::Sdr => None,
+            Self::ScRgb => Some(wgpu::TextureFormat::Rgba16Float),
+            Self::Hdr10 => Some(wgpu::TextureFormat::Rgb10a2Unorm),
+        }
+    }
+}
+
+// what the final blit does to the scene, the values match blit.wgsl
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutputEncoding {
+    // the scene is drawn straight into the surface
+    None = 0,
+    Srgb = 1,
+    ScRgb = 2,
+    Pq = 3,
+}
+
+// the scene is linear, this is where it's drawn when the surface can't take it
+pub(crate) const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+pub(crate) struct SurfaceOutput {
+    pub(crate) format: OutputFormat,
+    pub(crate) supported: Vec<OutputFormat>,
+    pub(crate) encoding: OutputEncoding,
+}
+
+impl SurfaceOutput {
+    // offscreen targets are always srgb
+    pub(crate) fn srgb() -> Self {
+        Self {
+            format: OutputFormat::Sdr,
+            supported: vec![OutputFormat::Sdr],
+            encoding: OutputEncoding::None,
+        }
+    }
+
+    // the format the pipelines render into
+    pub(crate) fn scene_format(&self, surface_format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        match self.encoding {
+            OutputEncoding::None => surface_format,
+            _ => SCENE_FORMAT,
+        }
+    }
+}
+
+// the surface format for `preferred`, falling back to sdr when it isn't offered
+pub(crate) fn choose_surface_format(
+    formats: &[wgpu::TextureFormat],
+    preferred: OutputFormat,
+) -> (wgpu::TextureFormat, SurfaceOutput) {
+    let supported: Vec<OutputFormat> =
+        [OutputFormat::Sdr, OutputFormat::ScRgb, OutputFormat::Hdr10]
+            .into_iter()
+            .filter(|output| {
+                output
+                    .surface_format()
+                    .is_none_or(|format| formats.contains(&format))
+            })
+            .collect();
+    if let Some(format) = preferred.surface_format() {
+        if supported.contains(&preferred) {
+            let encoding = match preferred {
+                OutputFormat::Hdr10 => OutputEncoding::Pq,
+                _ => OutputEncoding::ScRgb,
+            };
+            return (
+                format,
+                SurfaceOutput {
+                    format: preferred,
+                    supported,
+                    encoding,
+                },
+            );
+        }
+        log::warn!(
+            "{:?} output isn't supported by this surface, falling back to Sdr",
+            preferred
+        );
+    }
+    let (format, encoding) = match formats.iter().find(|format| format.is_srgb()) {
+        Some(format) => (*format, OutputEncoding::None),
+        // written through a plain unorm view, the blit does the srgb curve
+        None => (formats[0], OutputEncoding::Srgb),
+    };
+    (
+        format,
+        SurfaceOutput {
+            format: OutputFormat::Sdr,
+            supported,
+            encoding,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::TextureFormat;
+
+    const FORMATS: [TextureFormat; 4] = [
+        TextureFormat::Bgra8Unorm,
+        TextureFormat::Bgra8UnormSrgb,
+        TextureFormat::Rgba16Float,
+        TextureFormat::Rgb10a2Unorm,
+    ];
+
+    #[test]
+    fn hdr_outputs_are_used_when_offered() {
+        let (format, output) = choose_surface_format(&FORMATS, OutputFormat::Hdr10);
+        assert_eq!(format, TextureFormat::Rgb10a2Unorm);
+        assert_eq!(output.encoding, OutputEncoding::Pq);
+        assert_eq!(output.scene_format(format), SCENE_FORMAT);
+
+        let (format, output) = choose_surface_format(&FORMATS, OutputFormat::ScRgb);
+        assert_eq!(format, TextureFormat::Rgba16Float);
+        assert_eq!(output.encoding, OutputEncoding::ScRgb);
+        assert_eq!(output.supported.len(), 3);
+    }
+
+    #[test]
+    fn missing_hdr_falls_back_to_srgb() {
+        let (format, output) = choose_surface_format(&FORMATS[..2], OutputFormat::Hdr10);
+        assert_eq!(format, TextureFormat::Bgra8UnormSrgb);
+        assert_eq!(output.format, OutputFormat::Sdr);
+        assert_eq!(output.supported, [OutputFormat::Sdr]);
+        // drawn straight into the srgb surface
+        assert_eq!(output.scene_format(format), format);
+    }
+
+    #[test]
+    fn unorm_only_surfaces_are_encoded_by_the_blit() {
+        let (format, output) = choose_surface_format(&FORMATS[..1], OutputFormat::Sdr);
+        assert_eq!(format, TextureFormat::Bgra8Unorm);
+        assert_eq!(output.encoding, OutputEncoding::Srgb);
+        assert_eq!(output.scene_format(format), SCENE_FORMAT);
+    }
+}
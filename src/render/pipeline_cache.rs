@@ -0,0 +1,68 @@
+// Keeps the driver's compiled pipelines between launches. Where the backend
+// can (vulkan so far), the renderer creates its pipelines through a wgpu
+// pipeline cache loaded from a file named after the adapter and driver, in
+// `SurfaceSettings::pipeline_cache`. The cache is written back when the app is
+// suspended or exits, so the next start skips most of the shader compiling.
+// A file from another driver version has another name, and one wgpu can't
+// read is ignored and replaced.
+
+use std::path::{Path, PathBuf};
+
+pub(crate) struct PipelineCache {
+    cache: wgpu::PipelineCache,
+    path: PathBuf,
+}
+
+impl PipelineCache {
+    // None where the backend has no pipeline cache
+    pub(crate) fn load(
+        device: &wgpu::Device,
+        adapter: &wgpu::AdapterInfo,
+        directory: &Path,
+    ) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            return None;
+        }
+        let path = directory.join(wgpu::util::pipeline_cache_key(adapter)?);
+        let data = match std::fs::read(&path) {
+            Ok(data) => Some(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!(
+                    "Unable to read the pipeline cache {}: {}",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        };
+        // safety: wgpu validates the data's header against the adapter, and with
+        // `fallback` a cache that doesn't match starts empty instead of failing
+        let cache = unsafe {
+            device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("pipeline_cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+        Some(Self { cache, path })
+    }
+
+    pub(crate) fn get(&self) -> &wgpu::PipelineCache {
+        &self.cache
+    }
+
+    // written next to the old one and moved over it, a crash halfway leaves the old one
+    pub(crate) fn save(&self) -> anyhow::Result<()> {
+        let Some(data) = self.cache.get_data() else {
+            return Ok(());
+        };
+        if let Some(directory) = self.path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let temporary = self.path.with_extension("tmp");
+        std::fs::write(&temporary, data)?;
+        std::fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+// Planar reflections for mirrors and water. The main camera is mirrored about
+// the entity's plane and renders into a texture materials can sample. The
+// near plane is moved onto the mirror so nothing behind it leaks into the
+// reflection.
+
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    assets::{Assets, Handle},
+    camera::{CameraUniform, main_camera},
+    ecs::{component::Component, entity::Entity, world::World},
+    render::RenderDevice,
+    texture::Texture,
+    transform,
+};
+
+// mirrors about the entity's local xz plane, its local up is the normal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlanarReflection {
+    // what the reflection renders into, meant for the mirror's material
+    pub texture: Handle<Texture>,
+}
+
+impl Component for PlanarReflection {}
+
+impl PlanarReflection {
+    // creates the target texture, so the render device must already exist
+    pub fn new(world: &mut World, width: u32, height: u32) -> anyhow::Result<Self> {
+        let RenderDevice {
+            device,
+            surface_format,
+            ..
+        } = world
+            .get_resource::<RenderDevice>()
+            .ok_or(anyhow::anyhow!("Render device is not ready"))?
+            .clone();
+        let texture = Texture::create_render_target(
+            &device,
+            width,
+            height,
+            surface_format,
+            "planar_reflection",
+        );
+        Ok(Self {
+            texture: world.resource_mut::<Assets<Texture>>().add(texture),
+        })
+    }
+}
+
+// reflects points about the plane through `point` facing `normal`
+pub fn reflection_matrix(point: Vec3, normal: Vec3) -> Mat4 {
+    let n = normal.normalize();
+    let d = n.dot(point);
+    Mat4::from_cols(
+        Vec4::new(
+            1.0 - 2.0 * n.x * n.x,
+            -2.0 * n.y * n.x,
+            -2.0 * n.z * n.x,
+            0.0,
+        ),
+        Vec4::new(
+            -2.0 * n.x * n.y,
+            1.0 - 2.0 * n.y * n.y,
+            -2.0 * n.z * n.y,
+            0.0,
+        ),
+        Vec4::new(
+            -2.0 * n.x * n.z,
+            -2.0 * n.y * n.z,
+            1.0 - 2.0 * n.z * n.z,
+            0.0,
+        ),
+        (2.0 * d * n).extend(1.0),
+    )
+}
+
+// replaces the near plane of a 0..1 depth projection with `plane`, given in
+// view space with the kept side positive
+pub fn oblique_projection(projection: Mat4, plane: Vec4) -> Mat4 {
+    let corner = projection.inverse() * Vec4::new(plane.x.signum(), plane.y.signum(), 1.0, 1.0);
+    let row = plane * (projection.row(3).dot(corner) / plane.dot(corner));
+    let mut projection = projection;
+    projection.x_axis.z = row.x;
+    projection.y_axis.z = row.y;
+    projection.z_axis.z = row.z;
+    projection.w_axis.z = row.w;
+    projection
+}
+
+struct PlanarTarget {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    depth_view: wgpu::TextureView,
+    size: wgpu::Extent3d,
+}
+
+pub(crate) struct PlanarReflections {
+    targets: FastHashMap<Entity, PlanarTarget>,
+}
+
+impl PlanarReflections {
+    pub(crate) fn new() -> Self {
+        Self {
+            targets: FastHashMap::default(),
+        }
+    }
+
+    // points each reflection's camera at the mirrored view, returns the
+    // reflections that can be drawn this frame
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        world: &World,
+    ) -> Vec<(Entity, Handle<Texture>)> {
+        let reflections = world.query::<PlanarReflection>();
+        self.targets
+            .retain(|entity, _| reflections.iter().any(|(other, _)| other == entity));
+        let (Some(camera), Some(textures)) =
+            (main_camera(world), world.get_resource::<Assets<Texture>>())
+        else {
+            return Vec::new();
+        };
+
+        let mut ready = Vec::new();
+        for (entity, reflection) in reflections {
+            let Some(texture) = textures.get(reflection.texture) else {
+                continue;
+            };
+            let model = transform::model_matrix(world, entity);
+            let normal = model.transform_vector3(Vec3::Y).normalize_or(Vec3::Y);
+            let point = model.w_axis.truncate();
+            // looking at the mirror from behind shows nothing
+            if normal.dot(camera.pos - point) <= 0.0 {
+                continue;
+            }
+
+            let size = texture.texture.size();
+            let target = self
+                .targets
+                .entry(entity)
+                .or_insert_with(|| Self::create_target(device, camera_bind_group_layout, size));
+            if target.size != size {
+                *target = Self::create_target(device, camera_bind_group_layout, size);
+            }
+
+            let view = camera.view() * reflection_matrix(point, normal);
+            let plane = view.inverse().transpose() * normal.extend(-normal.dot(point));
+            let projection = Mat4::perspective_rh(
+                camera.fov.to_radians(),
+                size.width as f32 / size.height as f32,
+                0.1,
+                1024.0,
+            );
+            let view_proj = oblique_projection(projection, plane) * view;
+            let position = camera.pos - 2.0 * normal.dot(camera.pos - point) * normal;
+            queue.write_buffer(
+                &target.buffer,
+                0,
+                bytemuck::cast_slice(&[CameraUniform::from_view_proj(view_proj, position)]),
+            );
+            ready.push((entity, reflection.texture));
+        }
+        ready
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        size: wgpu::Extent3d,
+    ) -> PlanarTarget {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("planar_reflection_camera"),
+            size: std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("planar_reflection_camera_bind_group"),
+            layout: camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("planar_reflection_depth"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        PlanarTarget {
+            buffer,
+            bind_group,
+            depth_view: depth.create_view(&wgpu::TextureViewDescriptor::default()),
+            size,
+        }
+    }
+
+    // a pass drawing into the reflection, with its camera bound to group 0
+    pub(crate) fn begin<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        entity: Entity,
+        view: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> Option<wgpu::RenderPass<'a>> {
+        let target = self.targets.get(&entity)?;
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Planar Reflection Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &target.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        Some(render_pass)
+    }
+}
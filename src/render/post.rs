@@ -0,0 +1,372 @@
+// Offscreen targets for effects that read the rendered frame. While any effect
+// is on, the scene renders into one of two targets instead of the swapchain,
+// effects ping-pong between them, and the result is blitted to the surface.
+// The frame before the lens effects is kept as next frame's history.
+// The blit also tonemaps and encodes for surfaces the scene can't be drawn into
+// directly, see output.rs.
+
+use super::output::OutputEncoding;
+
+pub(crate) struct PostTarget {
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+}
+
+impl PostTarget {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { texture, view }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutputUniform {
+    encoding: u32,
+    paper_white: f32,
+    max_luminance: f32,
+    sharpness: f32,
+}
+
+pub(crate) struct PostTargets {
+    format: wgpu::TextureFormat,
+    encoding: OutputEncoding,
+    targets: [PostTarget; 2],
+    current: usize,
+    // last frame's final color
+    pub(crate) history: PostTarget,
+    pub(crate) sampler: wgpu::Sampler,
+    pub(crate) input_layout: wgpu::BindGroupLayout,
+    blit_pipeline: wgpu::RenderPipeline,
+    output_buffer: wgpu::Buffer,
+    output_bind_group: wgpu::BindGroup,
+    // written with the output levels
+    sharpness: f32,
+}
+
+impl PostTargets {
+    // the targets are in `format`, the blit writes `surface_format`
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        surface_format: wgpu::TextureFormat,
+        encoding: OutputEncoding,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let input_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_input_layout"),
+            entries: &[texture_entry(0), sampler_entry(1)],
+        });
+        let output_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blit_output_layout"),
+            entries: &[uniform_entry(0)],
+        });
+        let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("blit_output"),
+            size: std::mem::size_of::<OutputUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let output_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("blit_output_bind_group"),
+            layout: &output_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: output_buffer.as_entire_binding(),
+            }],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("blit.wgsl"));
+        let blit_pipeline = fullscreen_pipeline(
+            device,
+            cache,
+            "Blit Pipeline",
+            &shader,
+            &[&input_layout, &output_layout],
+            surface_format,
+        );
+
+        Self {
+            format,
+            encoding,
+            targets: [0, 1].map(|i| {
+                PostTarget::new(device, format, width, height, &format!("post_target_{}", i))
+            }),
+            current: 0,
+            history: PostTarget::new(device, format, width, height, "post_history"),
+            sampler,
+            input_layout,
+            blit_pipeline,
+            output_buffer,
+            output_bind_group,
+            sharpness: 0.0,
+        }
+    }
+
+    // the hdr levels the blit maps the scene to, in nits
+    pub(crate) fn set_output_levels(
+        &self,
+        queue: &wgpu::Queue,
+        paper_white: f32,
+        max_luminance: f32,
+    ) {
+        let paper_white = paper_white.max(1.0);
+        queue.write_buffer(
+            &self.output_buffer,
+            0,
+            bytemuck::bytes_of(&OutputUniform {
+                encoding: self.encoding as u32,
+                paper_white,
+                max_luminance: max_luminance.max(paper_white),
+                sharpness: self.sharpness,
+            }),
+        );
+    }
+
+    // how much the blit sharpens, for targets smaller than the surface. applied
+    // with the next set_output_levels
+    pub(crate) fn set_sharpness(&mut self, sharpness: f32) {
+        self.sharpness = sharpness.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let format = self.format;
+        self.targets = [0, 1]
+            .map(|i| PostTarget::new(device, format, width, height, &format!("post_target_{}", i)));
+        self.history = PostTarget::new(device, format, width, height, "post_history");
+        self.current = 0;
+    }
+
+    // where the scene and the next effect read from
+    pub(crate) fn current(&self) -> &PostTarget {
+        &self.targets[self.current]
+    }
+
+    // (source, destination) for the next effect, the destination becomes current
+    pub(crate) fn swap(&mut self) -> (&PostTarget, &PostTarget) {
+        let source = self.current;
+        self.current = 1 - self.current;
+        (&self.targets[source], &self.targets[self.current])
+    }
+
+    pub(crate) fn input_bind_group(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post_input_bind_group"),
+            layout: &self.input_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    // keeps the frame so far as next frame's history, called before the
+    // effects that shouldn't feed back into it
+    pub(crate) fn keep_history(&self, encoder: &mut wgpu::CommandEncoder) {
+        let current = self.current();
+        encoder.copy_texture_to_texture(
+            current.texture.as_image_copy(),
+            self.history.texture.as_image_copy(),
+            current.texture.size(),
+        );
+    }
+
+    pub(crate) fn finish(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        surface: &wgpu::TextureView,
+    ) {
+        let input = self.input_bind_group(device, &self.current().view);
+        run_fullscreen(
+            encoder,
+            "Blit Pass",
+            surface,
+            &self.blit_pipeline,
+            &[&input, &self.output_bind_group],
+        );
+    }
+}
+
+pub(crate) fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    }
+}
+
+#[cfg(feature = "render3d")]
+pub(crate) fn depth_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+        },
+        count: None,
+    }
+}
+
+pub(crate) fn sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
+
+pub(crate) fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+// expects `vs_fullscreen` and `fs_main` entry points
+pub(crate) fn fullscreen_pipeline(
+    device: &wgpu::Device,
+    cache: Option<&wgpu::PipelineCache>,
+    label: &str,
+    shader: &wgpu::ShaderModule,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    fullscreen_pipeline_entry(
+        device,
+        cache,
+        label,
+        shader,
+        "fs_main",
+        bind_group_layouts,
+        format,
+    )
+}
+
+// for shaders with more than one fragment entry point
+pub(crate) fn fullscreen_pipeline_entry(
+    device: &wgpu::Device,
+    cache: Option<&wgpu::PipelineCache>,
+    label: &str,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts,
+        immediate_size: 0,
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_fullscreen"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(entry_point),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache,
+    })
+}
+
+pub(crate) fn run_fullscreen(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    target: &wgpu::TextureView,
+    pipeline: &wgpu::RenderPipeline,
+    bind_groups: &[&wgpu::BindGroup],
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            depth_slice: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+        multiview_mask: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    for (index, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index as u32, *bind_group, &[]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
@@ -0,0 +1,146 @@
+// Draws the visible meshes before the main pass for the effects that need
+// more than color and depth. Only runs on frames where one of them is on.
+// Motion comes from the previous frame's camera and model matrices.
+
+use crate::{render::post::PostTarget, texture::Texture};
+
+pub(crate) struct Prepass {
+    pipeline: wgpu::RenderPipeline,
+    // xyz world normal, w roughness, zero where nothing was drawn
+    pub(crate) normals: PostTarget,
+    // uv offset since last frame, zero where nothing was drawn
+    pub(crate) motion: PostTarget,
+}
+
+impl Prepass {
+    pub(crate) const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    pub(crate) const MOTION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        buffers: &[wgpu::VertexBufferLayout<'_>],
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("prepass.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Prepass Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Prepass Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: Self::NORMAL_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: Self::MOTION_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+
+        let (normals, motion) = Self::targets(device, width, height);
+        Self {
+            pipeline,
+            normals,
+            motion,
+        }
+    }
+
+    fn targets(device: &wgpu::Device, width: u32, height: u32) -> (PostTarget, PostTarget) {
+        (
+            PostTarget::new(
+                device,
+                Self::NORMAL_FORMAT,
+                width,
+                height,
+                "prepass_normals",
+            ),
+            PostTarget::new(device, Self::MOTION_FORMAT, width, height, "prepass_motion"),
+        )
+    }
+
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.normals, self.motion) = Self::targets(device, width, height);
+    }
+
+    // the depth buffer is cleared and reused, the main pass clears it again afterwards
+    pub(crate) fn begin<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        depth: &wgpu::TextureView,
+    ) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Prepass"),
+            color_attachments: &[
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &self.normals.view,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &self.motion.view,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass
+    }
+}
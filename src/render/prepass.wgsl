@@ -0,0 +1,79 @@
+// World space normals and roughness for screen space effects, and per pixel
+// motion for temporal ones
+
+struct VertexInput {
+    @location(0) position: vec4<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+    @location(11) roughness: f32,
+    @location(12) previous_model_0: vec4<f32>,
+    @location(13) previous_model_1: vec4<f32>,
+    @location(14) previous_model_2: vec4<f32>,
+    @location(15) previous_model_3: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) @interpolate(flat) roughness: f32,
+    @location(2) current: vec4<f32>,
+    @location(3) previous: vec4<f32>,
+};
+
+struct FragmentOutput {
+    @location(0) normal: vec4<f32>,
+    @location(1) motion: vec2<f32>,
+};
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+    unjittered_view_proj: mat4x4<f32>,
+    previous_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    let previous_model_matrix = mat4x4<f32>(
+        instance.previous_model_0,
+        instance.previous_model_1,
+        instance.previous_model_2,
+        instance.previous_model_3,
+    );
+    var out: VertexOutput;
+    // fine for uniform scale, which is all the engine does so far
+    out.normal = (model_matrix * vec4<f32>(model.normal, 0.0)).xyz;
+    out.roughness = instance.roughness;
+    out.clip_position = camera.view_proj * model_matrix * model.position;
+    out.current = camera.unjittered_view_proj * model_matrix * model.position;
+    out.previous = camera.previous_view_proj * previous_model_matrix * model.position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.normal = vec4<f32>(normalize(in.normal), in.roughness);
+    // in uv units, where this pixel was last frame is uv - motion
+    let motion = in.current.xy / in.current.w - in.previous.xy / in.previous.w;
+    out.motion = motion * vec2<f32>(0.5, -0.5);
+    return out;
+}
@@ -0,0 +1,119 @@
+// Gets the loaded meshes and materials onto the gpu ahead of the frame they
+// first show up in. Otherwise a mesh's buffers and a texture's bind group are
+// made the first frame something draws them, and a level's worth appearing
+// at once stalls that frame. `start` takes stock of every loaded mesh and every
+// texture a loaded material uses, and the renderer works through `per_frame`
+// of them each frame, behind a loading screen say, then sends PrewarmFinished.
+// The renderer's pipelines don't need warming, every permutation is created
+// when it starts, and wgpu has no async pipeline creation to hand them to.
+
+use std::collections::VecDeque;
+
+use crate::{
+    assets::{Assets, Handle},
+    ecs::{component::Component, world::World},
+    material::Material,
+    mesh::Mesh,
+    texture::Texture,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PrewarmItem {
+    Mesh(Handle<Mesh>),
+    Texture(Handle<Texture>),
+}
+
+// sent once everything taken stock of is on the gpu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrewarmFinished;
+
+#[derive(Debug)]
+pub struct Prewarm {
+    // uploads each frame while it runs
+    pub per_frame: usize,
+    requested: bool,
+    pending: VecDeque<PrewarmItem>,
+    total: usize,
+}
+
+impl Component for Prewarm {}
+
+impl Default for Prewarm {
+    fn default() -> Self {
+        Self {
+            per_frame: 16,
+            requested: false,
+            pending: VecDeque::new(),
+            total: 0,
+        }
+    }
+}
+
+impl Prewarm {
+    // stock is taken at the end of the frame, so assets added this frame count
+    pub fn start(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.requested || !self.pending.is_empty()
+    }
+
+    // 0..1, 1 when nothing's left
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            return if self.requested { 0.0 } else { 1.0 };
+        }
+        1.0 - self.pending.len() as f32 / self.total as f32
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+fn take_stock(world: &World) -> VecDeque<PrewarmItem> {
+    let mut items = VecDeque::new();
+    if let Some(meshes) = world.get_resource::<Assets<Mesh>>() {
+        items.extend(meshes.iter().map(|(mesh, _)| PrewarmItem::Mesh(mesh)));
+    }
+    if let Some(materials) = world.get_resource::<Assets<Material>>() {
+        let mut textures: Vec<Handle<Texture>> = materials
+            .iter()
+            .filter_map(|(_, material)| material.base_color_texture)
+            .collect();
+        textures.sort_by_key(|texture| texture.id());
+        textures.dedup();
+        items.extend(textures.into_iter().map(PrewarmItem::Texture));
+    }
+    items
+}
+
+// what the renderer uploads this frame
+pub(crate) fn next_batch(world: &mut World) -> Vec<PrewarmItem> {
+    let stock = world
+        .resource::<Prewarm>()
+        .requested
+        .then(|| take_stock(world));
+    let prewarm = world.resource_mut::<Prewarm>();
+    if let Some(stock) = stock {
+        prewarm.requested = false;
+        prewarm.total = stock.len();
+        prewarm.pending = stock;
+        // nothing loaded, done as soon as it started
+        if prewarm.pending.is_empty() {
+            world.send_event(PrewarmFinished);
+            return Vec::new();
+        }
+    }
+    let count = prewarm.per_frame.max(1).min(prewarm.pending.len());
+    let batch: Vec<PrewarmItem> = prewarm.pending.drain(..count).collect();
+    if !batch.is_empty() && prewarm.pending.is_empty() {
+        world.send_event(PrewarmFinished);
+    }
+    batch
+}
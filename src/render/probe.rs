@@ -0,0 +1,383 @@
+// Local reflection probes. Each probe renders the scene around it into six
+// faces the first frame it's seen or after `rebake`, one probe per frame.
+// Reflections blend the two probes closest to the camera by how far inside
+// their influence a pixel is, and correct the lookup for the probe's shape
+// so nearby walls line up. Boxes are axis aligned, rotations are ignored.
+
+use glam::{Mat3, Quat, Vec2, Vec3};
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    camera::{Camera, CameraUniform},
+    ecs::{component::Component, entity::Entity, world::World},
+    render::post,
+    texture::Texture,
+    transform,
+};
+
+const RESOLUTION: u32 = 128;
+pub(crate) const FACE_COUNT: usize = 6;
+
+// forward and up of each face, the shaders pick faces the same way
+const FACES: [(Vec3, Vec3); FACE_COUNT] = [
+    (Vec3::X, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Y),
+    (Vec3::Y, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::Z),
+    (Vec3::Z, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeShape {
+    Box { half_extents: Vec3 },
+    Sphere { radius: f32 },
+}
+
+// captured from the entity's translation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReflectionProbe {
+    pub shape: ProbeShape,
+    // how far inside the shape the probe fades in
+    pub blend_distance: f32,
+    generation: u32,
+}
+
+impl Component for ReflectionProbe {}
+
+impl Default for ReflectionProbe {
+    fn default() -> Self {
+        Self::new(ProbeShape::Box {
+            half_extents: Vec3::splat(5.0),
+        })
+    }
+}
+
+impl ReflectionProbe {
+    pub fn new(shape: ProbeShape) -> Self {
+        Self {
+            shape,
+            blend_distance: 1.0,
+            generation: 0,
+        }
+    }
+
+    // captures again on one of the next frames, for when the scene around it changed
+    pub fn rebake(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuProbe {
+    // w is the blend distance
+    center: [f32; 4],
+    // half extents or radius in x, w is 0 for boxes and 1 for spheres
+    extents: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ProbesUniform {
+    probes: [GpuProbe; 2],
+    count: u32,
+    _padding: [u32; 3],
+}
+
+struct BakedProbe {
+    generation: u32,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+pub(crate) struct ReflectionProbes {
+    format: wgpu::TextureFormat,
+    baked: FastHashMap<Entity, BakedProbe>,
+    face_buffers: Vec<wgpu::Buffer>,
+    face_bind_groups: Vec<wgpu::BindGroup>,
+    depth_view: wgpu::TextureView,
+    // stands in for missing probes
+    empty_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    pub(crate) layout: wgpu::BindGroupLayout,
+}
+
+impl ReflectionProbes {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let (face_buffers, face_bind_groups) = FACES
+            .iter()
+            .map(|_| {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("probe_camera_buffer"),
+                    size: std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("probe_camera_bind_group"),
+                    layout: camera_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+                (buffer, bind_group)
+            })
+            .unzip();
+
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("probe_depth"),
+            size: wgpu::Extent3d {
+                width: RESOLUTION,
+                height: RESOLUTION,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        let array_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("reflection_probes_layout"),
+            entries: &[
+                array_entry(0),
+                array_entry(1),
+                post::sampler_entry(2),
+                post::uniform_entry(3),
+            ],
+        });
+
+        Self {
+            format,
+            baked: FastHashMap::default(),
+            face_buffers,
+            face_bind_groups,
+            depth_view: depth.create_view(&wgpu::TextureViewDescriptor::default()),
+            empty_view: Self::create_faces(device, format, 1).1,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("probe_sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            uniform_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("reflection_probes_uniform"),
+                size: std::mem::size_of::<ProbesUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            layout,
+        }
+    }
+
+    fn create_faces(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("reflection_probe"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: FACE_COUNT as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        (texture, view)
+    }
+
+    // for a different world, its probes are baked again
+    pub(crate) fn forget(&mut self) {
+        self.baked.clear();
+    }
+
+    // the first probe that was never captured or asked to rebake, and drops
+    // the captures of probes that are gone
+    pub(crate) fn pending(&mut self, world: &World) -> Option<(Entity, Vec3, u32)> {
+        let probes = world.query::<ReflectionProbe>();
+        self.baked
+            .retain(|entity, _| probes.iter().any(|(probe, _)| probe == entity));
+        probes.into_iter().find_map(|(entity, probe)| {
+            let stale = self
+                .baked
+                .get(&entity)
+                .is_none_or(|baked| baked.generation != probe.generation);
+            let position = transform::model_matrix(world, entity).w_axis.truncate();
+            stale.then_some((entity, position, probe.generation))
+        })
+    }
+
+    // makes room for the capture and points the face cameras at it
+    pub(crate) fn begin_bake(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entity: Entity,
+        position: Vec3,
+        generation: u32,
+    ) {
+        for (buffer, (forward, up)) in self.face_buffers.iter().zip(FACES) {
+            let camera = Camera {
+                fov: 90.0,
+                aspect_ratio: 1.0,
+                pos: position,
+                rotation: Quat::from_mat3(&Mat3::from_cols(forward.cross(up), up, -forward)),
+                ..Default::default()
+            };
+            let uniform = camera.to_uniform(Vec2::ZERO, camera.view_proj());
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[uniform]));
+        }
+
+        let format = self.format;
+        let baked = self.baked.entry(entity).or_insert_with(|| {
+            let (texture, view) = Self::create_faces(device, format, RESOLUTION);
+            BakedProbe {
+                generation,
+                texture,
+                view,
+            }
+        });
+        baked.generation = generation;
+    }
+
+    // a pass drawing into one face, with the face's camera bound to group 0
+    pub(crate) fn begin_face<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        entity: Entity,
+        face: usize,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> Option<wgpu::RenderPass<'a>> {
+        let baked = self.baked.get(&entity)?;
+        let view = baked.texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: face as u32,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Reflection Probe Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_bind_group(0, &self.face_bind_groups[face], &[]);
+        Some(render_pass)
+    }
+
+    // the two captured probes closest to `position`
+    pub(crate) fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        position: Vec3,
+    ) -> wgpu::BindGroup {
+        let mut nearest = world
+            .query::<ReflectionProbe>()
+            .into_iter()
+            .filter_map(|(entity, probe)| {
+                let baked = self.baked.get(&entity)?;
+                let center = transform::model_matrix(world, entity).w_axis.truncate();
+                Some((
+                    center.distance_squared(position),
+                    center,
+                    *probe,
+                    &baked.view,
+                ))
+            })
+            .collect::<Vec<_>>();
+        nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+        nearest.truncate(2);
+
+        let mut uniform = ProbesUniform {
+            probes: [GpuProbe::default(); 2],
+            count: nearest.len() as u32,
+            _padding: [0; 3],
+        };
+        for (gpu, (_, center, probe, _)) in uniform.probes.iter_mut().zip(&nearest) {
+            gpu.center = center.extend(probe.blend_distance.max(0.001)).to_array();
+            gpu.extents = match probe.shape {
+                ProbeShape::Box { half_extents } => half_extents.extend(0.0).to_array(),
+                ProbeShape::Sphere { radius } => [radius, radius, radius, 1.0],
+            };
+        }
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let view = |index: usize| {
+            nearest
+                .get(index)
+                .map_or(&self.empty_view, |(_, _, _, view)| *view)
+        };
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("reflection_probes_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view(0)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view(1)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
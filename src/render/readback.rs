@@ -0,0 +1,332 @@
+// Copying gpu data back to the cpu. Requests are copied into staging buffers
+// after the frame's systems ran and mapped asynchronously. Results show up as
+// ReadbackComplete events the frame after they're ready, or through the future
+// returned by `Readbacks::request_future`.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use glam::UVec2;
+
+use crate::{
+    ecs::{component::Component, world::World},
+    render::RenderDevice,
+};
+
+#[derive(Debug, Clone)]
+pub enum Readback {
+    Buffer {
+        buffer: wgpu::Buffer,
+        offset: u64,
+        size: u64,
+    },
+    Texture {
+        texture: wgpu::Texture,
+        mip_level: u32,
+        origin: UVec2,
+        // the rest of the mip when unset
+        size: Option<UVec2>,
+    },
+}
+
+impl Readback {
+    // the buffer needs COPY_SRC
+    pub fn buffer(buffer: &wgpu::Buffer) -> Self {
+        Self::buffer_range(buffer, 0, buffer.size())
+    }
+
+    pub fn buffer_range(buffer: &wgpu::Buffer, offset: u64, size: u64) -> Self {
+        Self::Buffer {
+            buffer: buffer.clone(),
+            offset,
+            size,
+        }
+    }
+
+    // the texture needs COPY_SRC, rows come back tightly packed
+    pub fn texture(texture: &wgpu::Texture) -> Self {
+        Self::texture_mip(texture, 0)
+    }
+
+    pub fn texture_mip(texture: &wgpu::Texture, mip_level: u32) -> Self {
+        Self::Texture {
+            texture: texture.clone(),
+            mip_level,
+            origin: UVec2::ZERO,
+            size: None,
+        }
+    }
+
+    pub fn texture_region(texture: &wgpu::Texture, origin: UVec2, size: UVec2) -> Self {
+        Self::Texture {
+            texture: texture.clone(),
+            mip_level: 0,
+            origin,
+            size: Some(size),
+        }
+    }
+
+    pub(crate) fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> anyhow::Result<Staging> {
+        match self {
+            Readback::Buffer {
+                buffer,
+                offset,
+                size,
+            } => {
+                let staging = create_staging(device, *size);
+                encoder.copy_buffer_to_buffer(buffer, *offset, &staging, 0, *size);
+                Ok(Staging {
+                    buffer: staging,
+                    rows: None,
+                })
+            }
+            Readback::Texture {
+                texture,
+                mip_level,
+                origin,
+                size,
+            } => {
+                let mip_size = texture
+                    .size()
+                    .mip_level_size(*mip_level, texture.dimension());
+                let size = match size {
+                    Some(size) => wgpu::Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    None => wgpu::Extent3d {
+                        width: mip_size.width.saturating_sub(origin.x),
+                        height: mip_size.height.saturating_sub(origin.y),
+                        ..mip_size
+                    },
+                };
+                let block_size = texture.format().block_copy_size(None).ok_or_else(|| {
+                    anyhow::anyhow!("Unable to read back {:?} textures", texture.format())
+                })?;
+                let row_bytes = size.width * block_size;
+                let padded_row_bytes =
+                    row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+                let rows = size.height * size.depth_or_array_layers;
+                let staging = create_staging(device, padded_row_bytes as u64 * rows as u64);
+                encoder.copy_texture_to_buffer(
+                    wgpu::TexelCopyTextureInfo {
+                        texture,
+                        mip_level: *mip_level,
+                        origin: wgpu::Origin3d {
+                            x: origin.x,
+                            y: origin.y,
+                            z: 0,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::TexelCopyBufferInfo {
+                        buffer: &staging,
+                        layout: wgpu::TexelCopyBufferLayout {
+                            offset: 0,
+                            bytes_per_row: Some(padded_row_bytes),
+                            rows_per_image: Some(size.height),
+                        },
+                    },
+                    size,
+                );
+                Ok(Staging {
+                    buffer: staging,
+                    rows: Some((row_bytes, padded_row_bytes)),
+                })
+            }
+        }
+    }
+}
+
+fn create_staging(device: &wgpu::Device, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback_staging"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+pub(crate) struct Staging {
+    buffer: wgpu::Buffer,
+    // (row, padded row) bytes for textures, the padding is stripped after mapping
+    rows: Option<(u32, u32)>,
+}
+
+impl Staging {
+    // call after the copy was submitted
+    pub(crate) fn map(self, done: impl FnOnce(anyhow::Result<Vec<u8>>) + Send + 'static) {
+        let Staging { buffer, rows } = self;
+        buffer
+            .clone()
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if let Err(e) = result {
+                    done(Err(e.into()));
+                    return;
+                }
+                let data = {
+                    let mapped = buffer.slice(..).get_mapped_range();
+                    match rows {
+                        Some((row_bytes, padded_row_bytes)) => mapped
+                            .chunks(padded_row_bytes as usize)
+                            .flat_map(|row| &row[..row_bytes as usize])
+                            .copied()
+                            .collect(),
+                        None => mapped.to_vec(),
+                    }
+                };
+                buffer.unmap();
+                done(Ok(data));
+            });
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadbackId(u64);
+
+#[derive(Debug, Clone)]
+pub struct ReadbackComplete {
+    pub id: ReadbackId,
+    pub data: Vec<u8>,
+}
+
+impl ReadbackComplete {
+    pub fn cast<T: bytemuck::Pod>(&self) -> Vec<T> {
+        bytemuck::pod_collect_to_vec(&self.data)
+    }
+}
+
+#[derive(Debug, Default)]
+struct FutureSlot {
+    result: Option<anyhow::Result<Vec<u8>>>,
+    waker: Option<Waker>,
+}
+
+// resolves once the data is mapped, which needs the engine to keep running frames
+#[derive(Debug)]
+pub struct ReadbackFuture {
+    slot: Arc<Mutex<FutureSlot>>,
+}
+
+impl Future for ReadbackFuture {
+    type Output = anyhow::Result<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct QueuedReadback {
+    id: ReadbackId,
+    readback: Readback,
+    future: Option<Arc<Mutex<FutureSlot>>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Readbacks {
+    next_id: u64,
+    queued: Vec<QueuedReadback>,
+    // filled by the map callbacks, which may run on another thread
+    finished: Arc<Mutex<Vec<ReadbackComplete>>>,
+}
+
+impl Component for Readbacks {}
+
+impl Readbacks {
+    fn queue(&mut self, readback: Readback, future: Option<Arc<Mutex<FutureSlot>>>) -> ReadbackId {
+        let id = ReadbackId(self.next_id);
+        self.next_id += 1;
+        self.queued.push(QueuedReadback {
+            id,
+            readback,
+            future,
+        });
+        id
+    }
+
+    pub fn request(&mut self, readback: Readback) -> ReadbackId {
+        self.queue(readback, None)
+    }
+
+    // the result only goes to the future, no event is sent
+    pub fn request_future(&mut self, readback: Readback) -> ReadbackFuture {
+        let slot = Arc::new(Mutex::new(FutureSlot::default()));
+        self.queue(readback, Some(slot.clone()));
+        ReadbackFuture { slot }
+    }
+}
+
+// runs after "last", so completed readbacks are seen by the next frame's systems
+pub(crate) fn run_readbacks(world: &mut World) {
+    let Some(RenderDevice { device, queue, .. }) = world.get_resource::<RenderDevice>().cloned()
+    else {
+        return;
+    };
+    let Some(readbacks) = world.get_resource_mut::<Readbacks>() else {
+        return;
+    };
+    let queued = std::mem::take(&mut readbacks.queued);
+    let finished = readbacks.finished.clone();
+
+    if !queued.is_empty() {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("readback_encoder"),
+        });
+        let staging: Vec<_> = queued
+            .into_iter()
+            .filter_map(
+                |queued| match queued.readback.encode(&device, &mut encoder) {
+                    Ok(staging) => Some((queued.id, staging, queued.future)),
+                    Err(e) => {
+                        log::error!("Unable to read back {:?}: {}", queued.id, e);
+                        None
+                    }
+                },
+            )
+            .collect();
+        queue.submit(std::iter::once(encoder.finish()));
+
+        for (id, staging, future) in staging {
+            let finished = finished.clone();
+            staging.map(move |result| match future {
+                Some(slot) => {
+                    let mut slot = slot.lock().unwrap();
+                    slot.result = Some(result);
+                    if let Some(waker) = slot.waker.take() {
+                        waker.wake();
+                    }
+                }
+                None => match result {
+                    Ok(data) => finished.lock().unwrap().push(ReadbackComplete { id, data }),
+                    Err(e) => log::error!("Unable to read back {:?}: {}", id, e),
+                },
+            });
+        }
+    }
+
+    if let Err(e) = device.poll(wgpu::PollType::Poll) {
+        log::error!("Unable to poll the device: {}", e);
+    }
+    let finished = std::mem::take(&mut *finished.lock().unwrap());
+    for readback in finished {
+        world.send_event(readback);
+    }
+}
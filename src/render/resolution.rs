@@ -0,0 +1,292 @@
+// Dynamic resolution. The 3d scene is drawn into targets a share of the
+// window's size and upsampled into the window by the output blit, which
+// sharpens what it stretched. The outlines, minimap widgets and text go on
+// after that at the window's size, against the scene's depth stretched to fit.
+// With `dynamic` on the share follows the gpu time of the frame: it drops as
+// soon as frames run over the target and climbs back in small steps once
+// there's room. The gpu time is measured with timestamp queries where the
+// adapter has them, elsewhere the whole frame time stands in for it, which
+// vsync holds at the refresh rate. The share moves in 5% steps so the targets
+// aren't recreated every frame, and it holds still while an overlay scene is
+// drawn over a kept frame. With an encoded output the overlays are drawn
+// before the upsample, at the scene's size.
+
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "ui")]
+use crate::texture::Texture;
+use crate::{
+    ecs::component::Component,
+    render::readback::{Readback, Staging},
+};
+
+const STEP: f32 = 0.05;
+
+#[derive(Debug, Clone)]
+pub struct RenderScale {
+    // share of the window's width and height the scene is drawn at, used as is
+    // while `dynamic` is off
+    pub scale: f32,
+    pub dynamic: bool,
+    // seconds of gpu time a frame should take
+    pub target_frame_time: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    // how much the upsample sharpens, 0..1
+    pub sharpness: f32,
+    // seconds between changes of the dynamic scale
+    pub interval: f32,
+    current: f32,
+    gpu_time: f32,
+    since_change: f32,
+}
+
+impl Component for RenderScale {}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            dynamic: false,
+            target_frame_time: 1.0 / 60.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+            sharpness: 0.5,
+            interval: 0.5,
+            current: 1.0,
+            gpu_time: 1.0 / 60.0,
+            since_change: 0.0,
+        }
+    }
+}
+
+impl RenderScale {
+    // the share the scene was last drawn at
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    // smoothed, what the dynamic scale steers by
+    pub fn gpu_time(&self) -> f32 {
+        self.gpu_time
+    }
+
+    // `measured` is the gpu time of a recent frame, when there's one
+    pub(crate) fn update(&mut self, measured: Option<f32>, real_delta: f32) -> f32 {
+        let min_scale = self.min_scale.clamp(0.1, 1.0);
+        let max_scale = self.max_scale.clamp(min_scale, 1.0);
+        if !self.dynamic {
+            self.current = quantize(self.scale.clamp(0.1, 1.0));
+            return self.current;
+        }
+        if let Some(time) = measured.or((real_delta > 0.0).then_some(real_delta)) {
+            self.gpu_time += (time.min(0.25) - self.gpu_time) * 0.1;
+        }
+        self.since_change += real_delta;
+        if self.since_change >= self.interval {
+            let target = self.target_frame_time.max(1e-4);
+            // the time goes with the pixel count, the square of the scale
+            let scale = if self.gpu_time > target {
+                self.current * (target / self.gpu_time).sqrt()
+            } else if self.gpu_time < target * 0.8 {
+                self.current + STEP
+            } else {
+                self.current
+            };
+            let scale = quantize(scale.clamp(min_scale, max_scale));
+            if scale != self.current {
+                self.current = scale;
+                self.since_change = 0.0;
+            }
+        }
+        self.current = self.current.clamp(min_scale, max_scale);
+        self.current
+    }
+}
+
+fn quantize(scale: f32) -> f32 {
+    ((scale / STEP).round() * STEP).min(1.0)
+}
+
+// the scene's size for a window of `width` by `height`
+pub(crate) fn scaled_size(width: u32, height: u32, scale: f32) -> (u32, u32) {
+    let scaled = |size: u32| ((size as f32 * scale).round() as u32).clamp(1, size.max(1));
+    (scaled(width), scaled(height))
+}
+
+// times the frame's gpu work between two empty compute passes
+pub(crate) struct GpuTimer {
+    queries: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    // nanoseconds per tick
+    period: f32,
+    measured: Arc<Mutex<Option<f32>>>,
+}
+
+impl GpuTimer {
+    // None when the device can't write timestamps
+    pub(crate) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let queries = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu_timer_queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu_timer_resolve"),
+            size: 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            queries,
+            resolve,
+            period: queue.get_timestamp_period(),
+            measured: Default::default(),
+        })
+    }
+
+    fn mark(&self, encoder: &mut wgpu::CommandEncoder, index: u32) {
+        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("GPU Timer"),
+            timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                query_set: &self.queries,
+                beginning_of_pass_write_index: Some(index),
+                end_of_pass_write_index: None,
+            }),
+        });
+    }
+
+    pub(crate) fn begin(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.mark(encoder, 0);
+    }
+
+    // map the result after the encoder is submitted
+    pub(crate) fn end(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Option<Staging> {
+        self.mark(encoder, 1);
+        encoder.resolve_query_set(&self.queries, 0..2, &self.resolve, 0);
+        Readback::buffer(&self.resolve)
+            .encode(device, encoder)
+            .inspect_err(|e| log::error!("Unable to read back the gpu time: {}", e))
+            .ok()
+    }
+
+    pub(crate) fn map(&self, staging: Staging) {
+        let measured = self.measured.clone();
+        let period = self.period;
+        staging.map(move |data| match data {
+            Ok(data) => {
+                let ticks: [u64; 2] = bytemuck::pod_read_unaligned(&data[..16]);
+                let seconds = ticks[1].saturating_sub(ticks[0]) as f64 * period as f64 * 1e-9;
+                *measured.lock().unwrap() = Some(seconds as f32);
+            }
+            Err(e) => log::error!("Unable to read back the gpu time: {}", e),
+        });
+    }
+
+    // the latest gpu time that came back, once
+    pub(crate) fn take(&self) -> Option<f32> {
+        self.measured.lock().unwrap().take()
+    }
+}
+
+// stretches the scene's depth to the window's size, for the overlays
+#[cfg(feature = "ui")]
+pub(crate) struct DepthUpsample {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+}
+
+#[cfg(feature = "ui")]
+impl DepthUpsample {
+    pub(crate) fn new(device: &wgpu::Device, cache: Option<&wgpu::PipelineCache>) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("depth_upsample_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth Upsample Pipeline"),
+            bind_group_layouts: &[&layout],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("depth_upsample.wgsl"));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Depth Upsample Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_fullscreen"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+        Self { pipeline, layout }
+    }
+
+    pub(crate) fn run(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth_upsample_bind_group"),
+            layout: &self.layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            }],
+        });
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Depth Upsample Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: target,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,220 @@
+// Procedural sky. `Background::ProceduralSky` renders a Preetham sky with a
+// sun disc into an equirectangular texture whenever the sun or the settings
+// change, and that texture is then used everywhere a skybox would be: the
+// background, reflection probes, planar reflections and the ssr fallback.
+// With a TimeOfDay resource the sun follows the clock.
+
+use glam::Vec3;
+
+use crate::{
+    color::Color,
+    ecs::{component::Component, world::World},
+    render::post,
+    texture::Texture,
+    time::Time,
+};
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProceduralSky {
+    // haziness, 2 is a clear day and 10 a hazy one
+    pub turbidity: f32,
+    // angular diameter of the sun disc in degrees
+    pub sun_size: f32,
+    pub exposure: f32,
+    // what the sky fades to after sunset
+    pub night_color: Color,
+    // below the horizon
+    pub ground_color: Color,
+}
+
+impl Default for ProceduralSky {
+    fn default() -> Self {
+        Self {
+            turbidity: 2.5,
+            sun_size: 1.5,
+            exposure: 0.1,
+            night_color: Color::srgb(0.01, 0.015, 0.04),
+            ground_color: Color::srgb(0.25, 0.22, 0.2),
+        }
+    }
+}
+
+// the main directional light, drives the sky and whatever else wants a sun
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunLight {
+    // towards the sun
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Component for SunLight {}
+
+impl Default for SunLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(0.3, 0.6, 0.4).normalize(),
+            color: Color::WHITE,
+            intensity: 1.0,
+        }
+    }
+}
+
+// insert as a resource to have the sun move with the clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeOfDay {
+    // 0..24, 12 is noon
+    pub hours: f32,
+    // real seconds for a whole day, 0 holds the time still
+    pub day_length: f32,
+    // in degrees, how high the sun gets at noon
+    pub latitude: f32,
+}
+
+impl Component for TimeOfDay {}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            hours: 9.0,
+            day_length: 20.0 * 60.0,
+            latitude: 45.0,
+        }
+    }
+}
+
+impl TimeOfDay {
+    // the sun at the equinox, rising in +x and at its highest towards +z
+    pub fn sun_direction(&self) -> Vec3 {
+        let hour_angle = (self.hours - 12.0) / 24.0 * std::f32::consts::TAU;
+        let latitude = self.latitude.to_radians();
+        Vec3::new(
+            -hour_angle.sin(),
+            latitude.cos() * hour_angle.cos(),
+            latitude.sin() * hour_angle.cos(),
+        )
+    }
+}
+
+pub fn update_time_of_day(world: &mut World) {
+    let delta = world.get_resource::<Time>().map_or(0.0, Time::delta);
+    let Some(time_of_day) = world.get_resource_mut::<TimeOfDay>() else {
+        return;
+    };
+    if time_of_day.day_length > 0.0 {
+        time_of_day.hours = (time_of_day.hours + delta * 24.0 / time_of_day.day_length) % 24.0;
+    }
+    let direction = time_of_day.sun_direction();
+
+    // redder and dimmer through more atmosphere, gone once it's set
+    let height = direction.y;
+    let warmth = (1.0 - height / 0.3).clamp(0.0, 1.0);
+    let sun = Color::srgb(1.0, 1.0 - warmth * 0.45, 1.0 - warmth * 0.75);
+    let intensity = ((height + 0.05) / 0.15).clamp(0.0, 1.0);
+
+    let light = world.resource_mut::<SunLight>();
+    light.direction = direction;
+    light.color = sun;
+    light.intensity = intensity;
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniform {
+    sun_direction: [f32; 4],
+    sun_color: [f32; 4],
+    night_color: [f32; 4],
+    ground_color: [f32; 4],
+    turbidity: f32,
+    sun_size: f32,
+    exposure: f32,
+    sun_intensity: f32,
+}
+
+pub(crate) struct SkyPass {
+    pipeline: wgpu::RenderPipeline,
+    settings_buffer: wgpu::Buffer,
+    settings_bind_group: wgpu::BindGroup,
+    target: Texture,
+    // the panorama in the texture layout, for the skybox pipeline
+    pub(crate) bind_group: wgpu::BindGroup,
+    last: Option<SkyUniform>,
+}
+
+impl SkyPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let format = wgpu::TextureFormat::Rgba16Float;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sky_layout"),
+            entries: &[post::uniform_entry(0)],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("sky.wgsl"));
+        let pipeline =
+            post::fullscreen_pipeline(device, cache, "Sky Pipeline", &shader, &[&layout], format);
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sky_settings"),
+            size: std::mem::size_of::<SkyUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sky_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+        let target = Texture::create_render_target(device, WIDTH, HEIGHT, format, "sky_panorama");
+        let bind_group =
+            crate::create_texture_bind_group(device, texture_bind_group_layout, &target);
+
+        Self {
+            pipeline,
+            settings_buffer,
+            settings_bind_group,
+            target,
+            bind_group,
+            last: None,
+        }
+    }
+
+    // redraws the panorama when anything it depends on changed
+    pub(crate) fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        sky: ProceduralSky,
+        sun: SunLight,
+    ) {
+        let uniform = SkyUniform {
+            sun_direction: sun.direction.normalize_or(Vec3::Y).extend(0.0).to_array(),
+            sun_color: sun.color.to_linear(),
+            night_color: sky.night_color.to_linear(),
+            ground_color: sky.ground_color.to_linear(),
+            turbidity: sky.turbidity.clamp(1.7, 10.0),
+            sun_size: sky.sun_size.to_radians() * 0.5,
+            exposure: sky.exposure,
+            sun_intensity: sun.intensity,
+        };
+        if self.last == Some(uniform) {
+            return;
+        }
+        self.last = Some(uniform);
+        queue.write_buffer(&self.settings_buffer, 0, bytemuck::bytes_of(&uniform));
+        post::run_fullscreen(
+            encoder,
+            "Sky Pass",
+            &self.target.view,
+            &self.pipeline,
+            &[&self.settings_bind_group],
+        );
+    }
+}
@@ -0,0 +1,109 @@
+struct SkySettings {
+    sun_direction: vec4<f32>,
+    sun_color: vec4<f32>,
+    night_color: vec4<f32>,
+    ground_color: vec4<f32>,
+    turbidity: f32,
+    // angular radius
+    sun_size: f32,
+    exposure: f32,
+    sun_intensity: f32,
+};
+@group(0) @binding(0)
+var<uniform> settings: SkySettings;
+
+const PI: f32 = 3.14159265359;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// Perez distribution, coefficients are A..E
+fn perez(theta: f32, gamma: f32, a: f32, b: f32, c: f32, d: f32, e: f32) -> f32 {
+    let cos_gamma = cos(gamma);
+    return (1.0 + a * exp(b / max(cos(theta), 0.01)))
+        * (1.0 + c * exp(d * gamma) + e * cos_gamma * cos_gamma);
+}
+
+// Preetham et al. 1999, xyY of the sky in a direction `theta` from the zenith
+// and `gamma` from the sun, with the sun `theta_sun` from the zenith
+fn preetham(theta: f32, gamma: f32, theta_sun: f32) -> vec3<f32> {
+    let t = settings.turbidity;
+    let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+    let zenith_luminance = (4.0453 * t - 4.9710) * tan(chi) - 0.2155 * t + 2.4192;
+    let s = vec4<f32>(theta_sun * theta_sun * theta_sun, theta_sun * theta_sun, theta_sun, 1.0);
+    let zenith_x = dot(vec3<f32>(t * t, t, 1.0), vec3<f32>(
+        dot(vec4<f32>(0.00166, -0.00375, 0.00209, 0.0), s),
+        dot(vec4<f32>(-0.02903, 0.06377, -0.03202, 0.00394), s),
+        dot(vec4<f32>(0.11693, -0.21196, 0.06052, 0.25886), s),
+    ));
+    let zenith_y = dot(vec3<f32>(t * t, t, 1.0), vec3<f32>(
+        dot(vec4<f32>(0.00275, -0.00610, 0.00317, 0.0), s),
+        dot(vec4<f32>(-0.04214, 0.08970, -0.04153, 0.00516), s),
+        dot(vec4<f32>(0.15346, -0.26756, 0.06670, 0.26688), s),
+    ));
+
+    let luminance = zenith_luminance
+        * perez(theta, gamma, 0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703)
+        / perez(0.0, theta_sun, 0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703);
+    let x = zenith_x
+        * perez(theta, gamma, -0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452)
+        / perez(0.0, theta_sun, -0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452);
+    let y = zenith_y
+        * perez(theta, gamma, -0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529)
+        / perez(0.0, theta_sun, -0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529);
+    return vec3<f32>(x, y, luminance);
+}
+
+fn xyy_to_linear(xyy: vec3<f32>) -> vec3<f32> {
+    let y = max(xyy.y, 0.0001);
+    let big_x = xyy.x / y * xyy.z;
+    let big_z = (1.0 - xyy.x - xyy.y) / y * xyy.z;
+    return max(vec3<f32>(
+        3.2406 * big_x - 1.5372 * xyy.z - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * xyy.z + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * xyy.z + 1.0570 * big_z,
+    ), vec3<f32>(0.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // same equirectangular mapping the skybox samples with
+    let phi = (in.uv.x - 0.5) * 2.0 * PI;
+    let theta = in.uv.y * PI;
+    let direction = vec3<f32>(sin(theta) * cos(phi), cos(theta), sin(theta) * sin(phi));
+    let sun = normalize(settings.sun_direction.xyz);
+
+    // the model falls apart below the horizon, so both angles stay just above it
+    let theta_sun = min(acos(clamp(sun.y, -1.0, 1.0)), PI * 0.5 - 0.02);
+    let view_theta = min(theta, PI * 0.5 - 0.01);
+    let clamped_sun = vec3<f32>(normalize(sun.xz + vec2<f32>(0.0001)) * sin(theta_sun), cos(theta_sun));
+    let clamped_view = vec3<f32>(normalize(direction.xz + vec2<f32>(0.0001)) * sin(view_theta), cos(view_theta));
+    let gamma = acos(clamp(dot(clamped_view, clamped_sun), -1.0, 1.0));
+    var color = xyy_to_linear(preetham(view_theta, gamma, theta_sun)) * settings.exposure;
+
+    // fades to the night color as the sun goes down
+    let day = smoothstep(-0.1, 0.05, sun.y);
+    color = mix(settings.night_color.rgb, color, day);
+
+    let sun_angle = acos(clamp(dot(direction, sun), -1.0, 1.0));
+    let disc = 1.0 - smoothstep(settings.sun_size * 0.8, settings.sun_size, sun_angle);
+    color += settings.sun_color.rgb * settings.sun_intensity * disc * 20.0;
+
+    let ground = settings.ground_color.rgb * mix(0.05, 1.0, day);
+    color = mix(color, ground, smoothstep(0.0, -0.05, direction.y));
+
+    // keeps the highlights from clipping hard
+    color = 1.0 - exp(-color);
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,43 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var t_sky: texture_2d<f32>;
+@group(1) @binding(1)
+var s_sky: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // homogeneous, divided per fragment so the interpolation stays linear
+    @location(0) near: vec4<f32>,
+    @location(1) far: vec4<f32>,
+};
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.near = camera.inverse_view_proj * vec4<f32>(ndc, 0.0, 1.0);
+    out.far = camera.inverse_view_proj * vec4<f32>(ndc, 1.0, 1.0);
+    return out;
+}
+
+const PI: f32 = 3.14159265359;
+
+// equirectangular lookup
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(in.far.xyz / in.far.w - in.near.xyz / in.near.w);
+    let uv = vec2<f32>(
+        atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+    return textureSampleLevel(t_sky, s_sky, uv, 0.0);
+}
@@ -0,0 +1,508 @@
+// Sprite renderer. Every visible sprite becomes one instance of a quad built
+// from the vertex index, sorted back to front for blending, and consecutive
+// sprites sharing a texture and normal map are drawn together. 2D lights and
+// occluder edges go in one uniform, and each pixel walks the edges between it
+// and every light for shadows, which is plenty for a screen's worth of walls.
+
+use std::ops::Range;
+
+use glam::{UVec2, Vec2, Vec3};
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    assets::Assets,
+    camera::main_camera,
+    ecs::{entity::Entity, world::World},
+    importance::{Detail, DetailLevel},
+    render::post,
+    sprite::{
+        Light2d, Lighting2d, MAX_LIGHTS_2D, MAX_OCCLUDER_EDGES_2D, Occluder2d, Sprite, SpriteMode,
+        TextureSlices, UvRect,
+    },
+    texture::{Texture, TextureUsage},
+    transform, visibility,
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteInstance {
+    model: [[f32; 4]; 4],
+    // uv of the bottom left and top right corners, swapped to flip
+    rect: [f32; 4],
+    color: [f32; 4],
+    // size then anchor
+    quad: [f32; 4],
+    // the sprite's own occluder edges, which don't shadow it
+    occluder: [u32; 2],
+}
+
+impl SpriteInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+        0 => Float32x4, 1 => Float32x4, 2 => Float32x4, 3 => Float32x4,
+        4 => Float32x4, 5 => Float32x4, 6 => Float32x4, 7 => Uint32x2
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Light2dRaw {
+    // world xy, height, radius
+    position: [f32; 4],
+    // premultiplied by intensity
+    color: [f32; 4],
+    // world direction, then the cosines of the inner and outer angles
+    cone: [f32; 4],
+    // casts shadows
+    flags: [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Lighting2dUniform {
+    ambient: [f32; 4],
+    // lights, edges, whether lighting is on
+    counts: [u32; 4],
+    lights: [Light2dRaw; MAX_LIGHTS_2D],
+    // world xy of both ends
+    edges: [[f32; 4]; MAX_OCCLUDER_EDGES_2D],
+}
+
+// texture and normal map
+type BatchKey = (usize, Option<usize>);
+
+pub(crate) struct SpritePass {
+    pipeline: wgpu::RenderPipeline,
+    instance_buffer: wgpu::Buffer,
+    lighting_buffer: wgpu::Buffer,
+    lighting_bind_group: wgpu::BindGroup,
+    // for sprites without a normal map
+    flat_normal_bind_group: wgpu::BindGroup,
+    // texture, normal map and instances of each batch
+    batches: Vec<(usize, Option<usize>, Range<u32>)>,
+}
+
+impl SpritePass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> anyhow::Result<Self> {
+        let mut uniform_entry = post::uniform_entry(0);
+        uniform_entry.visibility = wgpu::ShaderStages::FRAGMENT;
+        let lighting_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sprite_lighting_layout"),
+            entries: &[uniform_entry],
+        });
+        let lighting_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sprite_lighting_uniform"),
+            size: std::mem::size_of::<Lighting2dUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let lighting_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sprite_lighting_bind_group"),
+            layout: &lighting_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: lighting_buffer.as_entire_binding(),
+            }],
+        });
+        let flat_normal = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([128, 128, 255, 255]),
+            )),
+            TextureUsage::NormalMap,
+            Some("flat_normal_texture"),
+        )?;
+        let flat_normal_bind_group =
+            crate::create_texture_bind_group(device, texture_bind_group_layout, &flat_normal);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("sprite.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sprite Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                texture_bind_group_layout,
+                texture_bind_group_layout,
+                &lighting_layout,
+            ],
+            immediate_size: 0,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sprite Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[SpriteInstance::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            // sprites can be seen from behind when the camera orbits
+            primitive: wgpu::PrimitiveState::default(),
+            // tested against the scene but sorted among themselves
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+
+        Ok(Self {
+            pipeline,
+            instance_buffer: create_instance_buffer(device, 256),
+            lighting_buffer,
+            lighting_bind_group,
+            flat_normal_bind_group,
+            batches: Vec::new(),
+        })
+    }
+
+    pub(crate) fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &World) {
+        self.batches.clear();
+        let camera = main_camera(world);
+        let render_layers = camera.map_or_else(Default::default, |camera| camera.render_layers);
+        let camera_position = camera.map_or(Vec3::ZERO, |camera| camera.pos);
+        let occluders = self.prepare_lighting(queue, world);
+
+        let textures = world.get_resource::<Assets<Texture>>();
+        let slices = world.get_resource::<TextureSlices>();
+
+        let mut sprites: Vec<(f32, BatchKey, Vec<SpriteInstance>)> = world
+            .query::<Sprite>()
+            .into_iter()
+            .filter(|(entity, _)| visibility::is_visible_to(world, *entity, render_layers))
+            .map(|(entity, sprite)| {
+                let model = transform::model_matrix(world, entity);
+                let distance = model.w_axis.truncate().distance_squared(camera_position);
+                let batch = (
+                    sprite.texture.id(),
+                    sprite.normal_map.map(|normal_map| normal_map.id()),
+                );
+                let occluder = occluders
+                    .iter()
+                    .find(|(occluder, _)| *occluder == entity)
+                    .map_or([0; 2], |(_, edges)| *edges);
+                let texture_size = textures
+                    .and_then(|textures| textures.get(sprite.texture))
+                    .map(|texture| UVec2::new(texture.texture.width(), texture.texture.height()));
+                let instances = pieces(sprite, texture_size, slices)
+                    .into_iter()
+                    .map(|piece| instance(sprite, piece, model, occluder))
+                    .collect();
+                (distance, batch, instances)
+            })
+            .collect();
+        // farthest first, ties keep textures together
+        sprites.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let mut instances = Vec::with_capacity(sprites.len());
+        for (_, (texture, normal_map), pieces) in sprites {
+            let start = instances.len() as u32;
+            instances.extend(pieces);
+            let end = instances.len() as u32;
+            match self.batches.last_mut() {
+                Some((last, last_normal, range))
+                    if *last == texture && *last_normal == normal_map =>
+                {
+                    range.end = end
+                }
+                _ => self.batches.push((texture, normal_map, start..end)),
+            }
+        }
+
+        let size = (instances.len() * std::mem::size_of::<SpriteInstance>()) as wgpu::BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, instances.len() * 2);
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+    }
+
+    // uploads the lights and occluder edges, returning each occluder's range of edges
+    fn prepare_lighting(&self, queue: &wgpu::Queue, world: &World) -> Vec<(Entity, [u32; 2])> {
+        let mut uniform: Lighting2dUniform = bytemuck::Zeroable::zeroed();
+        let Some(lighting) = world.get_resource::<Lighting2d>() else {
+            queue.write_buffer(&self.lighting_buffer, 0, bytemuck::bytes_of(&uniform));
+            return Vec::new();
+        };
+        let model = |entity| transform::model_matrix(world, entity);
+
+        let mut lights = world.query::<Light2d>();
+        // with the importance budget on, the most important lights get the slots
+        let detail = |entity: &Entity| world.get_component::<DetailLevel>(*entity);
+        lights.sort_by(|(a, _), (b, _)| {
+            let importance =
+                |entity| detail(entity).map_or(f32::INFINITY, |level| level.importance);
+            importance(b).total_cmp(&importance(a))
+        });
+        for (raw, (entity, light)) in uniform.lights.iter_mut().zip(&lights) {
+            let model = model(*entity);
+            let position = model.w_axis.truncate();
+            let [r, g, b, _] = light.color.to_linear();
+            // no cone lights everything, cos is never below -1
+            let (direction, inner, outer) = light.cone.map_or((Vec2::X, -2.0, -3.0), |cone| {
+                let local = Vec3::new(cone.angle.cos(), cone.angle.sin(), 0.0);
+                (
+                    model
+                        .transform_vector3(local)
+                        .truncate()
+                        .normalize_or_zero(),
+                    cone.inner.cos(),
+                    cone.outer.max(cone.inner + 0.001).cos(),
+                )
+            });
+            *raw = Light2dRaw {
+                position: [position.x, position.y, light.height, light.radius],
+                color: [
+                    r * light.intensity,
+                    g * light.intensity,
+                    b * light.intensity,
+                    1.0,
+                ],
+                cone: [direction.x, direction.y, inner, outer],
+                flags: [
+                    (light.shadows
+                        && detail(entity).is_none_or(|level| level.detail != Detail::Minimal))
+                        as u32,
+                    0,
+                    0,
+                    0,
+                ],
+            };
+        }
+
+        let mut ranges = Vec::new();
+        let mut edges = 0;
+        for (entity, occluder) in world.query::<Occluder2d>() {
+            let model = model(entity);
+            let points: Vec<Vec2> = occluder
+                .points
+                .iter()
+                .map(|point| model.transform_point3(point.extend(0.0)).truncate())
+                .collect();
+            // a line has one edge, polygons close back to the start
+            let count = match points.len() {
+                0 | 1 => 0,
+                2 => 1,
+                n => n,
+            };
+            let start = edges;
+            for i in 0..count {
+                if edges == MAX_OCCLUDER_EDGES_2D {
+                    break;
+                }
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                uniform.edges[edges] = [a.x, a.y, b.x, b.y];
+                edges += 1;
+            }
+            ranges.push((entity, [start as u32, edges as u32]));
+        }
+
+        uniform.ambient = lighting.ambient.to_linear();
+        uniform.counts = [lights.len().min(MAX_LIGHTS_2D) as u32, edges as u32, 1, 0];
+        queue.write_buffer(&self.lighting_buffer, 0, bytemuck::bytes_of(&uniform));
+        ranges
+    }
+
+    // expects the camera bind group to be set
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        texture_bind_groups: &FastHashMap<usize, wgpu::BindGroup>,
+        default_bind_group: &wgpu::BindGroup,
+    ) {
+        if self.batches.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.set_bind_group(3, &self.lighting_bind_group, &[]);
+        for (texture, normal_map, instances) in &self.batches {
+            let texture = texture_bind_groups
+                .get(texture)
+                .unwrap_or(default_bind_group);
+            let normal_map = normal_map
+                .and_then(|normal_map| texture_bind_groups.get(&normal_map))
+                .unwrap_or(&self.flat_normal_bind_group);
+            render_pass.set_bind_group(1, texture, &[]);
+            render_pass.set_bind_group(2, normal_map, &[]);
+            render_pass.draw(0..6, instances.clone());
+        }
+    }
+}
+
+// part of a sprite drawn as one quad, in the sprite's space before flipping
+// with the origin at its anchor
+struct Piece {
+    min: Vec2,
+    max: Vec2,
+    rect: UvRect,
+}
+
+// tiled sprites stop adding tiles here
+const MAX_TILES: usize = 4096;
+
+fn pieces(
+    sprite: &Sprite,
+    texture_size: Option<UVec2>,
+    slices: Option<&TextureSlices>,
+) -> Vec<Piece> {
+    let min = -(Vec2::splat(0.5) + sprite.anchor) * sprite.size;
+    let max = min + sprite.size;
+    let (uv_min, uv_max) = (sprite.rect.min, sprite.rect.max);
+    let whole = vec![Piece {
+        min,
+        max,
+        rect: sprite.rect,
+    }];
+
+    match sprite.mode {
+        SpriteMode::Stretch => whole,
+        SpriteMode::NineSlice { border, scale } => {
+            let Some(border) = border.or_else(|| slices?.0.get(&sprite.texture).copied()) else {
+                return whole;
+            };
+            let Some(texture_size) = texture_size else {
+                return whole;
+            };
+            let pixel = Vec2::ONE / texture_size.max(UVec2::ONE).as_vec2();
+            // world and uv cuts along each axis, y going up the sprite and down the image
+            let cuts = |low: f32, high: f32, start: f32, end: f32| {
+                let (mut low, mut high) = (low * scale, high * scale);
+                let span = end - start;
+                if low + high > span.abs() && low + high > 0.0 {
+                    let shrink = span.abs() / (low + high);
+                    low *= shrink;
+                    high *= shrink;
+                }
+                [start, start + low, end - high, end]
+            };
+            let xs = cuts(border.left as f32, border.right as f32, min.x, max.x);
+            let ys = cuts(border.bottom as f32, border.top as f32, min.y, max.y);
+            let us = [
+                uv_min.x,
+                uv_min.x + border.left as f32 * pixel.x,
+                uv_max.x - border.right as f32 * pixel.x,
+                uv_max.x,
+            ];
+            let vs = [
+                uv_max.y,
+                uv_max.y - border.bottom as f32 * pixel.y,
+                uv_min.y + border.top as f32 * pixel.y,
+                uv_min.y,
+            ];
+            let mut pieces = Vec::with_capacity(9);
+            for row in 0..3 {
+                for column in 0..3 {
+                    if xs[column + 1] <= xs[column] || ys[row + 1] <= ys[row] {
+                        continue;
+                    }
+                    pieces.push(Piece {
+                        min: Vec2::new(xs[column], ys[row]),
+                        max: Vec2::new(xs[column + 1], ys[row + 1]),
+                        rect: UvRect {
+                            min: Vec2::new(us[column], vs[row + 1]),
+                            max: Vec2::new(us[column + 1], vs[row]),
+                        },
+                    });
+                }
+            }
+            pieces
+        }
+        SpriteMode::Tiled { tile } => {
+            if tile.x <= 0.0 || tile.y <= 0.0 {
+                return whole;
+            }
+            let counts = (sprite.size / tile).ceil().max(Vec2::ZERO).as_uvec2();
+            let mut pieces = Vec::new();
+            'rows: for row in 0..counts.y {
+                for column in 0..counts.x {
+                    if pieces.len() == MAX_TILES {
+                        break 'rows;
+                    }
+                    let start = min + UVec2::new(column, row).as_vec2() * tile;
+                    let end = (start + tile).min(max);
+                    // the last row and column are cut, showing the image's bottom left
+                    let used = (end - start) / tile;
+                    pieces.push(Piece {
+                        min: start,
+                        max: end,
+                        rect: UvRect {
+                            min: Vec2::new(uv_min.x, uv_max.y - used.y * (uv_max.y - uv_min.y)),
+                            max: Vec2::new(uv_min.x + used.x * (uv_max.x - uv_min.x), uv_max.y),
+                        },
+                    });
+                }
+            }
+            pieces
+        }
+    }
+}
+
+fn instance(
+    sprite: &Sprite,
+    piece: Piece,
+    model: glam::Mat4,
+    occluder: [u32; 2],
+) -> SpriteInstance {
+    // uv v runs down the image while the quad's y runs up
+    let (mut left, mut right) = (piece.rect.min.x, piece.rect.max.x);
+    let (mut bottom, mut top) = (piece.rect.max.y, piece.rect.min.y);
+    let mut center = (piece.min + piece.max) * 0.5;
+    // pieces mirror about the anchor along with their uvs
+    if sprite.flip_x {
+        std::mem::swap(&mut left, &mut right);
+        center.x = -center.x;
+    }
+    if sprite.flip_y {
+        std::mem::swap(&mut bottom, &mut top);
+        center.y = -center.y;
+    }
+    let size = piece.max - piece.min;
+    let anchor = -center / size.max(Vec2::splat(f32::EPSILON));
+    SpriteInstance {
+        model: model.to_cols_array_2d(),
+        rect: [left, bottom, right, top],
+        color: sprite.color.to_linear(),
+        quad: [size.x, size.y, anchor.x, anchor.y],
+        occluder,
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Sprite Instance Buffer"),
+        size: (capacity.max(1) * std::mem::size_of::<SpriteInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
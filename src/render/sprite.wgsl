@@ -0,0 +1,161 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+@group(2) @binding(0)
+var t_normal: texture_2d<f32>;
+@group(2) @binding(1)
+var s_normal: sampler;
+
+struct Light2d {
+    // world xy, height, radius
+    position: vec4<f32>,
+    color: vec4<f32>,
+    // world direction, then the cosines of the inner and outer angles
+    cone: vec4<f32>,
+    // casts shadows
+    flags: vec4<u32>,
+};
+
+struct Lighting2d {
+    ambient: vec4<f32>,
+    // lights, edges, whether lighting is on
+    counts: vec4<u32>,
+    lights: array<Light2d, 16>,
+    edges: array<vec4<f32>, 256>,
+};
+@group(3) @binding(0)
+var<uniform> lighting: Lighting2d;
+
+struct InstanceInput {
+    @location(0) model_0: vec4<f32>,
+    @location(1) model_1: vec4<f32>,
+    @location(2) model_2: vec4<f32>,
+    @location(3) model_3: vec4<f32>,
+    // uv of the bottom left corner, then the top right
+    @location(4) rect: vec4<f32>,
+    @location(5) color: vec4<f32>,
+    // size, then anchor
+    @location(6) quad: vec4<f32>,
+    // the sprite's own occluder edges
+    @location(7) occluder: vec2<u32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) world_position: vec2<f32>,
+    // the quad's x and y axes in the world, negated when flipped
+    @location(3) tangent: vec3<f32>,
+    @location(4) bitangent: vec3<f32>,
+    @location(5) @interpolate(flat) occluder: vec2<u32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+    let model = mat4x4<f32>(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    let local = (corner - 0.5 - instance.quad.zw) * instance.quad.xy;
+
+    let world_position = model * vec4<f32>(local, 0.0, 1.0);
+    // flipped sprites swap their uvs, so the normal map's axes turn around too
+    let flip_x = select(1.0, -1.0, instance.rect.x > instance.rect.z);
+    let flip_y = select(1.0, -1.0, instance.rect.y < instance.rect.w);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * world_position;
+    out.tex_coords = mix(instance.rect.xy, instance.rect.zw, corner);
+    out.color = instance.color;
+    out.world_position = world_position.xy;
+    out.tangent = normalize(model[0].xyz) * flip_x;
+    out.bitangent = normalize(model[1].xyz) * flip_y;
+    out.occluder = instance.occluder;
+    return out;
+}
+
+// whether the segment p..q crosses a..b, touching the ends at p or q doesn't count
+fn crosses(p: vec2<f32>, q: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> bool {
+    let r = q - p;
+    let s = b - a;
+    let denominator = r.x * s.y - r.y * s.x;
+    if abs(denominator) < 1e-6 {
+        return false;
+    }
+    let offset = a - p;
+    let t = (offset.x * s.y - offset.y * s.x) / denominator;
+    let u = (offset.x * r.y - offset.y * r.x) / denominator;
+    return t > 0.0 && t < 1.0 && u >= 0.0 && u <= 1.0;
+}
+
+fn shadowed(position: vec2<f32>, light: vec2<f32>, skip: vec2<u32>) -> bool {
+    for (var i = 0u; i < lighting.counts.y; i++) {
+        if i >= skip.x && i < skip.y {
+            continue;
+        }
+        let edge = lighting.edges[i];
+        if crosses(position, light, edge.xy, edge.zw) {
+            return true;
+        }
+    }
+    return false;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+    if color.a <= 0.0 {
+        discard;
+    }
+    let tangent_normal = textureSample(t_normal, s_normal, in.tex_coords).xyz * 2.0 - 1.0;
+    if lighting.counts.z == 0u {
+        return color;
+    }
+
+    let facing = cross(in.tangent, in.bitangent);
+    let normal = normalize(
+        in.tangent * tangent_normal.x + in.bitangent * tangent_normal.y + facing * tangent_normal.z
+    );
+    var light = lighting.ambient.rgb;
+    for (var i = 0u; i < lighting.counts.x; i++) {
+        let source = lighting.lights[i];
+        let to_light = source.position.xy - in.world_position;
+        let distance = length(to_light);
+        if distance >= source.position.w {
+            continue;
+        }
+        let falloff = 1.0 - distance / source.position.w;
+        let spot = dot(-to_light / max(distance, 1e-4), source.cone.xy);
+        let attenuation = falloff * falloff * smoothstep(source.cone.w, source.cone.z, spot);
+        let direction = normalize(vec3<f32>(to_light, source.position.z));
+        let diffuse = max(dot(normal, direction), 0.0);
+        if attenuation * diffuse <= 0.0 {
+            continue;
+        }
+        if source.flags.x != 0u && shadowed(in.world_position, source.position.xy, in.occluder) {
+            continue;
+        }
+        light += source.color.rgb * attenuation * diffuse;
+    }
+    return vec4<f32>(color.rgb * light, color.a);
+}
@@ -0,0 +1,212 @@
+// Screen space reflections. Rays are marched against the depth buffer from the
+// prepass normals, hits sample last frame's color blurred by roughness, misses
+// fall back to reflection probes and then the skybox when there is one.
+// At half resolution the reflections are traced on their own and upsampled
+// onto the frame.
+
+use crate::render::{
+    post::{self, PostTargets},
+    transient::TransientPool,
+    upsample::{PassResolution, UpsamplePass},
+};
+
+// set on the camera to turn reflections on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenSpaceReflections {
+    pub max_steps: u32,
+    // in world units
+    pub max_distance: f32,
+    // how far behind the depth buffer a ray can be and still count as a hit
+    pub thickness: f32,
+    pub intensity: f32,
+    pub resolution: PassResolution,
+}
+
+impl Default for ScreenSpaceReflections {
+    fn default() -> Self {
+        Self {
+            max_steps: 64,
+            max_distance: 20.0,
+            thickness: 0.2,
+            intensity: 1.0,
+            resolution: PassResolution::Full,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsrUniform {
+    max_steps: u32,
+    max_distance: f32,
+    thickness: f32,
+    intensity: f32,
+    has_environment: u32,
+    _padding: [u32; 3],
+}
+
+pub(crate) struct SsrPass {
+    pipeline: wgpu::RenderPipeline,
+    trace_pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    settings_buffer: wgpu::Buffer,
+}
+
+impl SsrPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        probes_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ssr_layout"),
+            entries: &[
+                post::texture_entry(0),
+                post::sampler_entry(1),
+                post::texture_entry(2),
+                post::depth_entry(3),
+                post::texture_entry(4),
+                post::uniform_entry(5),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("ssr.wgsl"));
+        let layouts = [
+            camera_bind_group_layout,
+            &layout,
+            texture_bind_group_layout,
+            probes_layout,
+        ];
+        let pipeline =
+            post::fullscreen_pipeline(device, cache, "SSR Pipeline", &shader, &layouts, format);
+        let trace_pipeline = post::fullscreen_pipeline_entry(
+            device,
+            cache,
+            "SSR Trace Pipeline",
+            &shader,
+            "fs_trace",
+            &layouts,
+            UpsamplePass::EFFECT_FORMAT,
+        );
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ssr_settings"),
+            size: std::mem::size_of::<SsrUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            trace_pipeline,
+            layout,
+            settings_buffer,
+        }
+    }
+
+    // `environment` is the skybox's texture bind group, None when there isn't one
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn run(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        post: &mut PostTargets,
+        transient: &mut TransientPool,
+        upsample: &UpsamplePass,
+        camera_bind_group: &wgpu::BindGroup,
+        depth: &wgpu::TextureView,
+        normals: &wgpu::TextureView,
+        environment: Option<&wgpu::BindGroup>,
+        fallback_environment: &wgpu::BindGroup,
+        probes: &wgpu::BindGroup,
+        settings: ScreenSpaceReflections,
+    ) {
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&SsrUniform {
+                max_steps: settings.max_steps.max(1),
+                max_distance: settings.max_distance,
+                thickness: settings.thickness,
+                intensity: settings.intensity,
+                has_environment: environment.is_some() as u32,
+                _padding: [0; 3],
+            }),
+        );
+
+        let sampler = &post.sampler;
+        let history = &post.history.view;
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ssr_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&post.current().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(history),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(normals),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: self.settings_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let bind_groups = [
+            camera_bind_group,
+            &bind_group,
+            environment.unwrap_or(fallback_environment),
+            probes,
+        ];
+        if settings.resolution == PassResolution::Full {
+            let (_, target) = post.swap();
+            post::run_fullscreen(
+                encoder,
+                "SSR Pass",
+                &target.view,
+                &self.pipeline,
+                &bind_groups,
+            );
+            return;
+        }
+        let reflections = transient.texture(
+            device,
+            "ssr_reflections",
+            settings.resolution.divisor(),
+            UpsamplePass::EFFECT_FORMAT,
+        );
+        post::run_fullscreen(
+            encoder,
+            "SSR Trace Pass",
+            &reflections.view,
+            &self.trace_pipeline,
+            &bind_groups,
+        );
+        upsample.run(
+            device,
+            encoder,
+            post,
+            camera_bind_group,
+            depth,
+            &reflections.view,
+        );
+        transient.release_texture(reflections);
+    }
+}
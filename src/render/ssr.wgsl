@@ -0,0 +1,270 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct SsrSettings {
+    max_steps: u32,
+    max_distance: f32,
+    thickness: f32,
+    intensity: f32,
+    has_environment: u32,
+};
+
+@group(1) @binding(0)
+var t_color: texture_2d<f32>;
+@group(1) @binding(1)
+var s_linear: sampler;
+@group(1) @binding(2)
+var t_history: texture_2d<f32>;
+@group(1) @binding(3)
+var t_depth: texture_2d<f32>;
+@group(1) @binding(4)
+var t_normals: texture_2d<f32>;
+@group(1) @binding(5)
+var<uniform> settings: SsrSettings;
+
+@group(2) @binding(0)
+var t_environment: texture_2d<f32>;
+@group(2) @binding(1)
+var s_environment: sampler;
+
+struct Probe {
+    center: vec4<f32>,
+    extents: vec4<f32>,
+};
+
+struct Probes {
+    probes: array<Probe, 2>,
+    count: u32,
+};
+
+@group(3) @binding(0)
+var t_probe_0: texture_2d_array<f32>;
+@group(3) @binding(1)
+var t_probe_1: texture_2d_array<f32>;
+@group(3) @binding(2)
+var s_probe: sampler;
+@group(3) @binding(3)
+var<uniform> probes: Probes;
+
+const PI: f32 = 3.14159265359;
+
+// forward and up of each probe face, matching render/probe.rs
+const FACE_FORWARD = array<vec3<f32>, 6>(
+    vec3<f32>(1.0, 0.0, 0.0),
+    vec3<f32>(-1.0, 0.0, 0.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, -1.0, 0.0),
+    vec3<f32>(0.0, 0.0, 1.0),
+    vec3<f32>(0.0, 0.0, -1.0),
+);
+const FACE_UP = array<vec3<f32>, 6>(
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, 0.0, -1.0),
+    vec3<f32>(0.0, 0.0, 1.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+);
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn world_position(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = camera.inverse_view_proj * ndc;
+    return position.xyz / position.w;
+}
+
+// xy uv, z depth
+fn project(position: vec3<f32>) -> vec3<f32> {
+    let clip = camera.view_proj * vec4<f32>(position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    return vec3<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5, ndc.z);
+}
+
+fn environment(direction: vec3<f32>) -> vec3<f32> {
+    let uv = vec2<f32>(
+        atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+    return textureSampleLevel(t_environment, s_environment, uv, 0.0).rgb;
+}
+
+fn probe_face(direction: vec3<f32>) -> i32 {
+    let a = abs(direction);
+    if a.x >= a.y && a.x >= a.z {
+        return select(1, 0, direction.x > 0.0);
+    }
+    if a.y >= a.z {
+        return select(3, 2, direction.y > 0.0);
+    }
+    return select(5, 4, direction.z > 0.0);
+}
+
+// layer and uv of a direction from the probe's center
+fn probe_coords(direction: vec3<f32>) -> vec3<f32> {
+    let face = probe_face(direction);
+    let forward = FACE_FORWARD[face];
+    let up = FACE_UP[face];
+    let local = direction / dot(direction, forward);
+    let x = dot(local, cross(forward, up));
+    let y = dot(local, up);
+    return vec3<f32>(x * 0.5 + 0.5, 0.5 - y * 0.5, f32(face));
+}
+
+// 0 outside, fading in to 1 over the blend distance
+fn probe_influence(probe: Probe, position: vec3<f32>) -> f32 {
+    let offset = position - probe.center.xyz;
+    if probe.extents.w > 0.5 {
+        return clamp((probe.extents.x - length(offset)) / probe.center.w, 0.0, 1.0);
+    }
+    let inside = probe.extents.xyz - abs(offset);
+    return clamp(min(inside.x, min(inside.y, inside.z)) / probe.center.w, 0.0, 1.0);
+}
+
+// where the ray leaves the probe's shape, seen from its center
+fn probe_direction(probe: Probe, position: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    let offset = position - probe.center.xyz;
+    var t: f32;
+    if probe.extents.w > 0.5 {
+        let b = dot(offset, direction);
+        let c = dot(offset, offset) - probe.extents.x * probe.extents.x;
+        t = -b + sqrt(max(b * b - c, 0.0));
+    } else {
+        let exit = (sign(direction) * probe.extents.xyz - offset) / direction;
+        t = min(exit.x, min(exit.y, exit.z));
+    }
+    return offset + direction * max(t, 0.0);
+}
+
+// premultiplied by the total influence in alpha
+fn probe_reflection(position: vec3<f32>, direction: vec3<f32>) -> vec4<f32> {
+    var color = vec3<f32>(0.0);
+    var total = 0.0;
+    if probes.count > 0u {
+        let probe = probes.probes[0];
+        let weight = probe_influence(probe, position);
+        if weight > 0.0 {
+            let coords = probe_coords(probe_direction(probe, position, direction));
+            color += textureSampleLevel(t_probe_0, s_probe, coords.xy, i32(coords.z), 0.0).rgb * weight;
+            total += weight;
+        }
+    }
+    if probes.count > 1u {
+        let probe = probes.probes[1];
+        let weight = probe_influence(probe, position);
+        if weight > 0.0 {
+            let coords = probe_coords(probe_direction(probe, position, direction));
+            color += textureSampleLevel(t_probe_1, s_probe, coords.xy, i32(coords.z), 0.0).rgb * weight;
+            total += weight;
+        }
+    }
+    if total > 1.0 {
+        return vec4<f32>(color / total, 1.0);
+    }
+    return vec4<f32>(color, total);
+}
+
+// a few taps spread by roughness stand in for a proper glossy blur
+fn blurred_history(uv: vec2<f32>, roughness: f32) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_history));
+    let radius = roughness * 8.0 * texel;
+    var color = textureSampleLevel(t_history, s_linear, uv, 0.0).rgb;
+    color += textureSampleLevel(t_history, s_linear, uv + vec2<f32>(radius.x, 0.0), 0.0).rgb;
+    color += textureSampleLevel(t_history, s_linear, uv - vec2<f32>(radius.x, 0.0), 0.0).rgb;
+    color += textureSampleLevel(t_history, s_linear, uv + vec2<f32>(0.0, radius.y), 0.0).rgb;
+    color += textureSampleLevel(t_history, s_linear, uv - vec2<f32>(0.0, radius.y), 0.0).rgb;
+    return color / 5.0;
+}
+
+// premultiplied by how much of the surface it covers
+fn reflection_at(uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_depth));
+    // by uv, the target can be smaller than the depth
+    let coords = clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    let depth = textureLoad(t_depth, coords, 0).r;
+    let surface = textureLoad(t_normals, coords, 0);
+    let roughness = surface.a;
+    if depth >= 1.0 || roughness >= 1.0 || dot(surface.xyz, surface.xyz) == 0.0 {
+        return vec4<f32>(0.0);
+    }
+
+    let position = world_position(uv, depth);
+    let view = normalize(position - camera.position.xyz);
+    let normal = normalize(surface.xyz);
+    let direction = reflect(view, normal);
+
+    let step = settings.max_distance / f32(settings.max_steps);
+    var hit = false;
+    var hit_uv = vec2<f32>(0.0);
+    for (var i = 1u; i <= settings.max_steps; i++) {
+        let sample_position = position + direction * step * f32(i);
+        let projected = project(sample_position);
+        if any(projected.xy < vec2<f32>(0.0)) || any(projected.xy > vec2<f32>(1.0))
+            || projected.z < 0.0 || projected.z > 1.0 {
+            break;
+        }
+        let sample_coords = clamp(vec2<i32>(projected.xy * vec2<f32>(size)), vec2<i32>(0), size - 1);
+        let scene_depth = textureLoad(t_depth, sample_coords, 0).r;
+        if projected.z > scene_depth {
+            let scene_position = world_position(projected.xy, scene_depth);
+            hit = distance(sample_position, scene_position) < settings.thickness;
+            hit_uv = projected.xy;
+            break;
+        }
+    }
+
+    var reflection: vec3<f32>;
+    var weight = 1.0;
+    if hit {
+        // the previous frame is lit and post processed, close enough for reflections
+        reflection = blurred_history(hit_uv, roughness);
+        let edge = min(hit_uv, 1.0 - hit_uv);
+        weight = clamp(min(edge.x, edge.y) * 10.0, 0.0, 1.0);
+    } else {
+        // local probes first, the skybox fills in whatever they don't cover
+        let local = probe_reflection(position, direction);
+        if settings.has_environment != 0u {
+            reflection = local.rgb + environment(direction) * (1.0 - local.a);
+        } else if local.a > 0.0 {
+            reflection = local.rgb / local.a;
+            weight = local.a;
+        } else {
+            return vec4<f32>(0.0);
+        }
+    }
+
+    let fresnel = 0.04 + 0.96 * pow(1.0 - max(dot(normal, -view), 0.0), 5.0);
+    let smoothness = 1.0 - roughness;
+    weight *= clamp(smoothness * smoothness * fresnel * settings.intensity, 0.0, 1.0);
+    return vec4<f32>(reflection * weight, weight);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(t_color, s_linear, in.uv, 0.0);
+    let reflection = reflection_at(in.uv);
+    return vec4<f32>(color.rgb * (1.0 - reflection.a) + reflection.rgb, color.a);
+}
+
+// the reflections alone, for the upsample to put on the frame
+@fragment
+fn fs_trace(in: VertexOutput) -> @location(0) vec4<f32> {
+    return reflection_at(in.uv);
+}
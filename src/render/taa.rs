@@ -0,0 +1,134 @@
+// Temporal anti-aliasing. The projection is shifted by a sub-pixel amount
+// every frame and the resolve blends the frame into the history reprojected
+// with the prepass motion vectors, clamped to the current neighbourhood so
+// stale history doesn't ghost.
+
+use glam::Vec2;
+
+use crate::render::post::{self, PostTargets};
+
+// share of the current frame in the resolved one
+const BLEND: f32 = 0.1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TaaUniform {
+    blend: f32,
+    reset: u32,
+    _padding: [u32; 2],
+}
+
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+// ndc offset for the frame, cycles through 8 points of the 2, 3 halton sequence
+pub(crate) fn jitter(frame: u32, viewport: Vec2) -> Vec2 {
+    let index = frame % 8 + 1;
+    let pixels = Vec2::new(halton(index, 2), halton(index, 3)) - 0.5;
+    pixels * 2.0 / viewport.max(Vec2::ONE)
+}
+
+pub(crate) struct TaaPass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    settings_buffer: wgpu::Buffer,
+}
+
+impl TaaPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("taa_layout"),
+            entries: &[
+                post::texture_entry(0),
+                post::sampler_entry(1),
+                post::texture_entry(2),
+                post::texture_entry(3),
+                post::uniform_entry(4),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("taa.wgsl"));
+        let pipeline =
+            post::fullscreen_pipeline(device, cache, "TAA Pipeline", &shader, &[&layout], format);
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("taa_settings"),
+            size: std::mem::size_of::<TaaUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            layout,
+            settings_buffer,
+        }
+    }
+
+    // `reset` ignores the history, for the first frame and after resizes
+    pub(crate) fn run(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        post: &mut PostTargets,
+        motion: &wgpu::TextureView,
+        reset: bool,
+    ) {
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&TaaUniform {
+                blend: BLEND,
+                reset: reset as u32,
+                _padding: [0; 2],
+            }),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("taa_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&post.current().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&post.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&post.history.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(motion),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: self.settings_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let (_, target) = post.swap();
+        post::run_fullscreen(
+            encoder,
+            "TAA Pass",
+            &target.view,
+            &self.pipeline,
+            &[&bind_group],
+        );
+    }
+}
@@ -0,0 +1,58 @@
+struct TaaSettings {
+    blend: f32,
+    reset: u32,
+};
+
+@group(0) @binding(0)
+var t_color: texture_2d<f32>;
+@group(0) @binding(1)
+var s_linear: sampler;
+@group(0) @binding(2)
+var t_history: texture_2d<f32>;
+@group(0) @binding(3)
+var t_motion: texture_2d<f32>;
+@group(0) @binding(4)
+var<uniform> settings: TaaSettings;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_color));
+    let coords = vec2<i32>(in.clip_position.xy);
+    let current = textureLoad(t_color, coords, 0);
+    if settings.reset != 0u {
+        return current;
+    }
+
+    var min_color = current.rgb;
+    var max_color = current.rgb;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = clamp(coords + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            let color = textureLoad(t_color, neighbour, 0).rgb;
+            min_color = min(min_color, color);
+            max_color = max(max_color, color);
+        }
+    }
+
+    let previous_uv = in.uv - textureLoad(t_motion, coords, 0).xy;
+    if any(previous_uv < vec2<f32>(0.0)) || any(previous_uv > vec2<f32>(1.0)) {
+        return current;
+    }
+    let history = textureSampleLevel(t_history, s_linear, previous_uv, 0.0).rgb;
+    let clamped = clamp(history, min_color, max_color);
+    return vec4<f32>(mix(clamped, current.rgb, settings.blend), current.a);
+}
@@ -0,0 +1,752 @@
+// Text renderer. Glyphs are rasterized on first use and packed row by row
+// into an atlas, then every glyph on screen is an instanced quad drawn over
+// the finished frame. Bitmap text keeps coverage at the exact size it's shown
+// in physical pixels, sdf text one distance field per glyph at SDF_SIZE that
+// any size samples from, thresholded in the shader for fills and outlines. A
+// full atlas is wiped and refilled with just what the current frame needs.
+// World text is projected on the cpu a glyph at a time and tested against the
+// scene's depth buffer, so it can be drawn in the same pass as screen text.
+
+use ab_glyph::{Font as _, PxScale, point};
+use glam::{Mat4, UVec2, Vec2, Vec3, Vec4Swizzles};
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    assets::Assets,
+    camera::main_camera,
+    ecs::{entity::Entity, world::World},
+    render::post,
+    text::{Font, Text, TextLayout, WorldText, WorldTextScale},
+    texture::Texture,
+    transform,
+    ui::UiScale,
+    visibility,
+};
+
+const ATLAS_SIZE: u32 = 1024;
+// empty texels around each glyph so neighbours don't bleed in when filtering
+const PADDING: u32 = 1;
+// pixel size distance fields are rasterized at
+const SDF_SIZE: u32 = 32;
+// how far out and in from the outline distance fields reach, in their pixels
+const SDF_SPREAD: u32 = 4;
+// further than any distance in a glyph, without the nans infinity brings
+const FAR: f32 = 1e20;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GlyphInstance {
+    // physical pixels from the top left of the window to the quad's top left
+    // corner, then its depth, 0 for anything drawn over the scene
+    origin: [f32; 4],
+    // physical pixels along the quad's top edge, then down its left edge
+    axes: [f32; 4],
+    uv: [f32; 4],
+    color: [f32; 4],
+    outline_color: [f32; 4],
+    // outline width and softness in distance field units, then whether it's sdf
+    params: [f32; 4],
+}
+
+impl GlyphInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x4, 1 => Float32x4, 2 => Float32x4, 3 => Float32x4, 4 => Float32x4,
+        5 => Float32x4
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextUniform {
+    screen_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+// where a text's glyph quads end up on screen
+#[derive(Debug, Clone, Copy)]
+enum Placement {
+    // quads are already in physical pixels
+    Screen {
+        scale: f32,
+    },
+    // quads are in logical pixels from the entity, laid along `right` and
+    // `down`, which are world units per logical pixel
+    World {
+        origin: Vec3,
+        right: Vec3,
+        down: Vec3,
+        offset: Vec2,
+        view_proj: Mat4,
+        viewport: Vec2,
+        on_top: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quad {
+    origin: Vec3,
+    axes: [f32; 4],
+}
+
+impl Quad {
+    fn apply(self, instance: GlyphInstance) -> GlyphInstance {
+        GlyphInstance {
+            origin: self.origin.extend(0.0).to_array(),
+            axes: self.axes,
+            ..instance
+        }
+    }
+}
+
+impl Placement {
+    // None when part of the quad is behind the camera
+    fn quad(&self, min: Vec2, size: Vec2) -> Option<Quad> {
+        match *self {
+            Placement::Screen { .. } => Some(Quad {
+                origin: min.extend(0.0),
+                axes: [size.x, 0.0, 0.0, size.y],
+            }),
+            Placement::World {
+                origin,
+                right,
+                down,
+                view_proj,
+                viewport,
+                on_top,
+                ..
+            } => {
+                // small enough that each glyph is projected as a parallelogram
+                let project = |point: Vec2| {
+                    let world = origin + right * point.x + down * point.y;
+                    let clip = view_proj * world.extend(1.0);
+                    (clip.w > 0.0).then(|| {
+                        let ndc = clip.xyz() / clip.w;
+                        (Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * viewport).extend(ndc.z)
+                    })
+                };
+                let top_left = project(min)?;
+                let top_right = project(min + Vec2::new(size.x, 0.0))?;
+                let bottom_left = project(min + Vec2::new(0.0, size.y))?;
+                let across = top_right - top_left;
+                let down = bottom_left - top_left;
+                let depth = if on_top {
+                    0.0
+                } else {
+                    top_left.z + (across.z + down.z) * 0.5
+                };
+                Some(Quad {
+                    origin: top_left.truncate().extend(depth),
+                    axes: [across.x, across.y, down.x, down.y],
+                })
+            }
+        }
+    }
+}
+
+// font asset, bold face, glyph and pixel size
+type GlyphKey = (usize, bool, u16, u32);
+
+#[derive(Debug, Clone, Copy)]
+struct AtlasGlyph {
+    uv: [f32; 4],
+    // from the pen position to the bitmap's top left, and its size
+    offset: Vec2,
+    size: Vec2,
+}
+
+struct GlyphAtlas {
+    texture: wgpu::Texture,
+    sdf: bool,
+    // None for glyphs with nothing to draw, like spaces
+    glyphs: FastHashMap<GlyphKey, Option<AtlasGlyph>>,
+    cursor: UVec2,
+    row_height: u32,
+}
+
+impl GlyphAtlas {
+    fn new(device: &wgpu::Device, sdf: bool) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(if sdf {
+                "sdf_glyph_atlas"
+            } else {
+                "glyph_atlas"
+            }),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        Self {
+            texture,
+            sdf,
+            glyphs: FastHashMap::default(),
+            cursor: UVec2::ZERO,
+            row_height: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.cursor = UVec2::ZERO;
+        self.row_height = 0;
+    }
+
+    // Err when the atlas is full
+    fn get(
+        &mut self,
+        queue: &wgpu::Queue,
+        key: GlyphKey,
+        face: &ab_glyph::FontArc,
+    ) -> Result<Option<AtlasGlyph>, ()> {
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return Ok(*glyph);
+        }
+        let (_, _, id, size) = key;
+        let glyph = ab_glyph::GlyphId(id)
+            .with_scale_and_position(PxScale::from(size as f32), point(0.0, 0.0));
+        let Some(outline) = face.outline_glyph(glyph) else {
+            self.glyphs.insert(key, None);
+            return Ok(None);
+        };
+        let bounds = outline.px_bounds();
+        let size = UVec2::new(bounds.width() as u32, bounds.height() as u32).max(UVec2::ONE);
+        let mut coverage = vec![0u8; (size.x * size.y) as usize];
+        outline.draw(|x, y, c| {
+            if x < size.x && y < size.y {
+                coverage[(y * size.x + x) as usize] = (c.clamp(0.0, 1.0) * 255.0) as u8;
+            }
+        });
+
+        let (coverage, size, offset) = if self.sdf {
+            let (field, padded) = distance_field(&coverage, size, SDF_SPREAD);
+            (
+                field,
+                padded,
+                Vec2::new(bounds.min.x, bounds.min.y) - SDF_SPREAD as f32,
+            )
+        } else {
+            (coverage, size, Vec2::new(bounds.min.x, bounds.min.y))
+        };
+
+        let Some(origin) = self.allocate(size) else {
+            return Err(());
+        };
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: origin.x,
+                    y: origin.y,
+                    z: 0,
+                },
+            },
+            &coverage,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(size.x),
+                rows_per_image: Some(size.y),
+            },
+            wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        let min = origin.as_vec2() / ATLAS_SIZE as f32;
+        let max = (origin + size).as_vec2() / ATLAS_SIZE as f32;
+        let glyph = AtlasGlyph {
+            uv: [min.x, min.y, max.x, max.y],
+            offset,
+            size: size.as_vec2(),
+        };
+        self.glyphs.insert(key, Some(glyph));
+        Ok(Some(glyph))
+    }
+
+    fn allocate(&mut self, size: UVec2) -> Option<UVec2> {
+        let padded = size + PADDING;
+        if self.cursor.x + padded.x > ATLAS_SIZE {
+            self.cursor = UVec2::new(0, self.cursor.y + self.row_height);
+            self.row_height = 0;
+        }
+        if self.cursor.x + padded.x > ATLAS_SIZE || self.cursor.y + padded.y > ATLAS_SIZE {
+            return None;
+        }
+        let origin = self.cursor;
+        self.cursor.x += padded.x;
+        self.row_height = self.row_height.max(padded.y);
+        Some(origin)
+    }
+}
+
+pub(crate) struct TextPass {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    atlas: GlyphAtlas,
+    sdf_atlas: GlyphAtlas,
+    instance_buffer: wgpu::Buffer,
+    instances: u32,
+}
+
+impl TextPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let atlas = GlyphAtlas::new(device, false);
+        let sdf_atlas = GlyphAtlas::new(device, true);
+        let view = atlas
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let sdf_view = sdf_atlas
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text_uniform"),
+            size: std::mem::size_of::<TextUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut uniform_entry = post::uniform_entry(0);
+        uniform_entry.visibility = wgpu::ShaderStages::VERTEX;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("text_layout"),
+            entries: &[
+                uniform_entry,
+                post::texture_entry(1),
+                post::sampler_entry(2),
+                post::texture_entry(3),
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("text_bind_group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&sdf_view),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("text.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            immediate_size: 0,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[GlyphInstance::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // world text is hidden behind the scene, everything else sits at depth 0
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+
+        Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            atlas,
+            sdf_atlas,
+            instance_buffer: create_instance_buffer(device, 1024),
+            instances: 0,
+        }
+    }
+
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        screen_size: UVec2,
+    ) {
+        self.instances = 0;
+        let Some(fonts) = world.get_resource::<Assets<Font>>() else {
+            return;
+        };
+        let scale = world.get_resource::<UiScale>().map_or(1.0, UiScale::factor);
+        let uniform = TextUniform {
+            screen_size: screen_size.as_vec2().to_array(),
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        // a second go with an emptied atlas when this frame's glyphs didn't fit
+        let mut instances = Vec::new();
+        for _ in 0..2 {
+            match self.glyph_instances(queue, world, fonts, scale, screen_size.as_vec2()) {
+                Ok(glyphs) => {
+                    instances = glyphs;
+                    break;
+                }
+                Err(()) => {
+                    self.atlas.clear();
+                    self.sdf_atlas.clear();
+                }
+            }
+        }
+
+        let size = (instances.len() * std::mem::size_of::<GlyphInstance>()) as wgpu::BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, instances.len() * 2);
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        self.instances = instances.len() as u32;
+    }
+
+    fn glyph_instances(
+        &mut self,
+        queue: &wgpu::Queue,
+        world: &World,
+        fonts: &Assets<Font>,
+        scale: f32,
+        screen_size: Vec2,
+    ) -> Result<Vec<GlyphInstance>, ()> {
+        let camera = main_camera(world);
+        let mut texts: Vec<(f32, Entity, &Text, Placement)> = Vec::new();
+        for (entity, text) in world.query::<Text>() {
+            if !visibility::is_visible(world, entity) {
+                continue;
+            }
+            let Some(world_text) = world.get_component::<WorldText>(entity) else {
+                texts.push((f32::NEG_INFINITY, entity, text, Placement::Screen { scale }));
+                continue;
+            };
+            let (Some(camera), Some(layout)) = (camera, world.get_component::<TextLayout>(entity))
+            else {
+                continue;
+            };
+            if !visibility::is_visible_to(world, entity, camera.render_layers) {
+                continue;
+            }
+            let model = transform::model_matrix(world, entity);
+            let anchor = model.w_axis.truncate();
+            let distance = (anchor - camera.pos).dot(camera.forward());
+            if distance <= 0.0 {
+                continue;
+            }
+            let per_pixel = match world_text.scale {
+                WorldTextScale::World(units) => units,
+                // how much world a physical pixel covers at this depth
+                WorldTextScale::Screen => {
+                    distance * 2.0 * (camera.fov.to_radians() * 0.5).tan() / screen_size.y * scale
+                }
+            };
+            let (right, up) = if world_text.billboard {
+                (camera.rotation * Vec3::X, camera.rotation * Vec3::Y)
+            } else {
+                (
+                    model.x_axis.truncate().normalize_or_zero(),
+                    model.y_axis.truncate().normalize_or_zero(),
+                )
+            };
+            let placement = Placement::World {
+                origin: anchor,
+                right: right * per_pixel,
+                down: -up * per_pixel,
+                offset: text.position - layout.size * 0.5,
+                view_proj: camera.view_proj(),
+                viewport: screen_size,
+                on_top: world_text.always_on_top,
+            };
+            texts.push((distance, entity, text, placement));
+        }
+        // world text farthest first, then screen text over all of it
+        texts.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut instances = Vec::new();
+        for (_, entity, text, placement) in texts {
+            let (Some(layout), Some(font)) = (
+                world.get_component::<TextLayout>(entity),
+                fonts.get(text.font),
+            ) else {
+                continue;
+            };
+            let material = text.material;
+            let screen = matches!(placement, Placement::Screen { .. });
+            let sdf = !screen || material.uses_sdf();
+            let atlas = if sdf {
+                &mut self.sdf_atlas
+            } else {
+                &mut self.atlas
+            };
+            let mut glyphs = Vec::with_capacity(layout.glyphs.len());
+            let mut shadows = Vec::new();
+            for glyph in &layout.glyphs {
+                // physical pixels for screen text, logical pixels for world text
+                let (shown, pen) = match placement {
+                    Placement::Screen { scale } => (
+                        (glyph.size * scale).round().max(1.0),
+                        ((text.position + glyph.position) * scale).round(),
+                    ),
+                    Placement::World { offset, .. } => (glyph.size, offset + glyph.position),
+                };
+                let bold_face = glyph.bold && font.bold.is_some();
+                let raster_size = if sdf { SDF_SIZE } else { shown as u32 };
+                let key = (text.font.id(), bold_face, glyph.id.0, raster_size);
+                let Some(cached) = atlas.get(queue, key, font.face(glyph.bold))? else {
+                    continue;
+                };
+                // distance fields scale to the shown size, bitmaps are already there
+                let stretch = shown / raster_size as f32;
+                let min = pen + cached.offset * stretch;
+                let size = cached.size * stretch;
+                // logical pixels to distance field units
+                let to_field = SDF_SIZE as f32 / glyph.size.max(1.0) / (2.0 * SDF_SPREAD as f32);
+                let (outline_width, outline_color) =
+                    material.outline.map_or((0.0, glyph.color), |outline| {
+                        (outline.width * to_field, outline.color)
+                    });
+                let instance = GlyphInstance {
+                    origin: [0.0; 4],
+                    axes: [0.0; 4],
+                    uv: cached.uv,
+                    color: glyph.color.to_linear(),
+                    outline_color: outline_color.to_linear(),
+                    params: [outline_width.min(0.45), 0.0, sdf as u32 as f32, 0.0],
+                };
+                let mut corners = vec![min];
+                // faked bold, the same glyph again a little to the right
+                if glyph.bold && !bold_face {
+                    let shift = if screen {
+                        (shown / 24.0).max(1.0)
+                    } else {
+                        shown / 24.0
+                    };
+                    corners.push(min + Vec2::new(shift, 0.0));
+                }
+                for corner in corners {
+                    if let Some(quad) = placement.quad(corner, size) {
+                        glyphs.push(quad.apply(instance));
+                    }
+                    // every shadow goes under every letter
+                    let Some(shadow) = material.shadow else {
+                        continue;
+                    };
+                    let offset = match placement {
+                        Placement::Screen { scale } => shadow.offset * scale,
+                        Placement::World { .. } => shadow.offset,
+                    };
+                    if let Some(quad) = placement.quad(corner + offset, size) {
+                        let color = shadow.color.to_linear();
+                        shadows.push(quad.apply(GlyphInstance {
+                            color,
+                            outline_color: color,
+                            params: [
+                                instance.params[0],
+                                shadow.softness.clamp(0.0, 1.0) * 0.5,
+                                1.0,
+                                0.0,
+                            ],
+                            ..instance
+                        }));
+                    }
+                }
+            }
+            instances.extend(shadows);
+            instances.extend(glyphs);
+        }
+        Ok(instances)
+    }
+
+    // draws over whatever is in `target`, testing world text against the scene's depth
+    pub(crate) fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        depth: &wgpu::TextureView,
+    ) {
+        if self.instances == 0 {
+            return;
+        }
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Text Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instances);
+    }
+}
+
+// coverage to a signed distance field reaching `spread` pixels either side of
+// the outline, padded by as much. 0.5 is on the outline and higher is inside.
+// coverage between 0 and 1 places the outline within the pixel, as in tinysdf
+fn distance_field(coverage: &[u8], size: UVec2, spread: u32) -> (Vec<u8>, UVec2) {
+    let padded = size + spread * 2;
+    let (width, height) = (padded.x as usize, padded.y as usize);
+    // squared distances to the nearest inside and outside pixel
+    let mut outside = vec![FAR; width * height];
+    let mut inside = vec![0.0; width * height];
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let alpha = coverage[(y * size.x + x) as usize] as f32 / 255.0;
+            let i = (y + spread) as usize * width + (x + spread) as usize;
+            (outside[i], inside[i]) = match alpha {
+                1.0 => (0.0, FAR),
+                0.0 => (FAR, 0.0),
+                _ => (
+                    (0.5 - alpha).max(0.0).powi(2),
+                    (alpha - 0.5).max(0.0).powi(2),
+                ),
+            };
+        }
+    }
+    distance_transform(&mut outside, width, height);
+    distance_transform(&mut inside, width, height);
+
+    let field = outside
+        .iter()
+        .zip(&inside)
+        .map(|(outside, inside)| {
+            let distance = outside.sqrt() - inside.sqrt();
+            ((0.5 - distance / (2.0 * spread as f32)).clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+    (field, padded)
+}
+
+// felzenszwalb and huttenlocher's squared euclidean distance transform, columns then rows
+fn distance_transform(grid: &mut [f32], width: usize, height: usize) {
+    let n = width.max(height);
+    let mut line = vec![0.0; n];
+    let mut distances = vec![0.0; n];
+    let mut parabolas = vec![0; n];
+    let mut bounds = vec![0.0; n + 1];
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = grid[y * width + x];
+        }
+        transform_line(&line[..height], &mut distances, &mut parabolas, &mut bounds);
+        for y in 0..height {
+            grid[y * width + x] = distances[y];
+        }
+    }
+    for y in 0..height {
+        line[..width].copy_from_slice(&grid[y * width..(y + 1) * width]);
+        transform_line(&line[..width], &mut distances, &mut parabolas, &mut bounds);
+        grid[y * width..(y + 1) * width].copy_from_slice(&distances[..width]);
+    }
+}
+
+fn transform_line(f: &[f32], distances: &mut [f32], parabolas: &mut [usize], bounds: &mut [f32]) {
+    // where the parabolas rooted at q and r cross
+    let intersect = |q: usize, r: usize| {
+        ((f[q] + (q * q) as f32) - (f[r] + (r * r) as f32)) / (2.0 * (q as f32 - r as f32))
+    };
+    let mut k = 0;
+    parabolas[0] = 0;
+    bounds[0] = -FAR;
+    bounds[1] = FAR;
+    for q in 1..f.len() {
+        let mut s = intersect(q, parabolas[k]);
+        while s <= bounds[k] {
+            k -= 1;
+            s = intersect(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        bounds[k] = s;
+        bounds[k + 1] = FAR;
+    }
+    k = 0;
+    for (q, distance) in distances.iter_mut().enumerate().take(f.len()) {
+        while bounds[k + 1] < q as f32 {
+            k += 1;
+        }
+        let r = parabolas[k];
+        *distance = (q as f32 - r as f32).powi(2) + f[r];
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Glyph Instance Buffer"),
+        size: (capacity.max(1) * std::mem::size_of::<GlyphInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
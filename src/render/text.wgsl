@@ -0,0 +1,75 @@
+struct TextUniform {
+    screen_size: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> text: TextUniform;
+@group(0) @binding(1)
+var t_atlas: texture_2d<f32>;
+@group(0) @binding(2)
+var s_atlas: sampler;
+@group(0) @binding(3)
+var t_sdf_atlas: texture_2d<f32>;
+
+struct InstanceInput {
+    // physical pixels from the top left to the top left corner, then depth
+    @location(0) origin: vec4<f32>,
+    // physical pixels along the top edge, then down the left edge
+    @location(1) axes: vec4<f32>,
+    @location(2) uv: vec4<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) outline_color: vec4<f32>,
+    // outline width and softness in distance field units, then 1 for sdf glyphs
+    @location(5) params: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) outline_color: vec4<f32>,
+    @location(3) params: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+    let pixel = instance.origin.xy + instance.axes.xy * corner.x + instance.axes.zw * corner.y;
+    let ndc = pixel / text.screen_size * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc.x, -ndc.y, instance.origin.z, 1.0);
+    out.tex_coords = mix(instance.uv.xy, instance.uv.zw, corner);
+    out.color = instance.color;
+    out.outline_color = instance.outline_color;
+    out.params = instance.params;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // both sampled up front so derivatives stay in uniform control flow
+    let coverage = textureSample(t_atlas, s_atlas, in.tex_coords).r;
+    let distance = textureSample(t_sdf_atlas, s_atlas, in.tex_coords).r;
+    // about a screen pixel of distance, so edges stay a pixel soft at any size
+    let aa = max(fwidth(distance) * 0.5, 0.0001) + in.params.y;
+    if in.params.z < 0.5 {
+        return vec4<f32>(in.color.rgb, in.color.a * coverage);
+    }
+
+    let outline = in.params.x;
+    let alpha = smoothstep(0.5 - outline - aa, 0.5 - outline + aa, distance);
+    let fill = smoothstep(0.5 - aa, 0.5 + aa, distance);
+    var color = in.color;
+    if outline > 0.0 {
+        color = mix(in.outline_color, in.color, fill);
+    }
+    return vec4<f32>(color.rgb, color.a * alpha);
+}
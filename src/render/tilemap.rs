@@ -0,0 +1,327 @@
+// Tilemap renderer. Each chunk of a layer keeps its own instance buffer of
+// tiles, rebuilt only when its revision or the layer's opacity changes, and
+// is drawn with one instanced call per tileset it uses. Maps are drawn back
+// to front with alpha blending like sprites, layers in order within a map.
+
+use std::ops::Range;
+
+use glam::{Mat4, UVec2, Vec2, Vec3};
+use wgpu::{naga::FastHashMap, util::DeviceExt};
+
+use crate::{
+    assets::{Assets, Handle},
+    camera::{Frustum, main_camera},
+    ecs::{entity::Entity, world::World},
+    render::post,
+    texture::Texture,
+    tilemap::{CHUNK_SIZE, Tilemap},
+    transform, visibility,
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TileInstance {
+    // x and y in tiles, then the layer's opacity
+    tile: [f32; 4],
+    // uv of the bottom left and top right corners, swapped to flip
+    rect: [f32; 4],
+}
+
+impl TileInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TilemapUniform {
+    model: [[f32; 4]; 4],
+    tile_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+// tilemap asset, layer and chunk
+type ChunkKey = (usize, usize, UVec2);
+
+struct Chunk {
+    // revision and opacity bits the buffer was built from
+    stamp: (u32, u32),
+    buffer: Option<wgpu::Buffer>,
+    // texture and instances of each tileset in the chunk
+    batches: Vec<(usize, Range<u32>)>,
+}
+
+pub(crate) struct TilemapPass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    uniforms: FastHashMap<Entity, (wgpu::Buffer, wgpu::BindGroup)>,
+    chunks: FastHashMap<ChunkKey, Chunk>,
+    draws: Vec<(Entity, ChunkKey)>,
+}
+
+impl TilemapPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let mut uniform_entry = post::uniform_entry(0);
+        uniform_entry.visibility = wgpu::ShaderStages::VERTEX;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tilemap_layout"),
+            entries: &[uniform_entry],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("tilemap.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tilemap Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, texture_bind_group_layout, &layout],
+            immediate_size: 0,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tilemap Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[TileInstance::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // layers share a plane, so later ones can't be depth tested against earlier ones
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+
+        Self {
+            pipeline,
+            layout,
+            uniforms: FastHashMap::default(),
+            chunks: FastHashMap::default(),
+            draws: Vec::new(),
+        }
+    }
+
+    // `frustum` is None when culling is off
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &World,
+        frustum: Option<&Frustum>,
+    ) {
+        self.draws.clear();
+        let Some(tilemaps) = world.get_resource::<Assets<Tilemap>>() else {
+            self.chunks.clear();
+            self.uniforms.clear();
+            return;
+        };
+        let camera = main_camera(world);
+        let render_layers = camera.map_or_else(Default::default, |camera| camera.render_layers);
+        let camera_position = camera.map_or(Vec3::ZERO, |camera| camera.pos);
+
+        let mut maps: Vec<(f32, Entity, Handle<Tilemap>, Mat4)> = world
+            .query::<Handle<Tilemap>>()
+            .into_iter()
+            .filter(|(entity, _)| visibility::is_visible_to(world, *entity, render_layers))
+            .map(|(entity, handle)| {
+                let model = transform::model_matrix(world, entity);
+                let distance = model.w_axis.truncate().distance_squared(camera_position);
+                (distance, entity, *handle, model)
+            })
+            .collect();
+        // farthest first
+        maps.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        for (_, entity, handle, model) in &maps {
+            let Some(tilemap) = tilemaps.get(*handle) else {
+                continue;
+            };
+            let uniform = TilemapUniform {
+                model: model.to_cols_array_2d(),
+                tile_size: tilemap.tile_size.to_array(),
+                _padding: [0.0; 2],
+            };
+            let layout = &self.layout;
+            let (buffer, _) = self.uniforms.entry(*entity).or_insert_with(|| {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("tilemap_uniform"),
+                    size: std::mem::size_of::<TilemapUniform>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("tilemap_bind_group"),
+                    layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+                (buffer, bind_group)
+            });
+            queue.write_buffer(buffer, 0, bytemuck::bytes_of(&uniform));
+
+            let chunk_size = tilemap.tile_size * CHUNK_SIZE as f32;
+            for (index, layer) in tilemap.layers.iter().enumerate() {
+                if !layer.visible || layer.opacity <= 0.0 {
+                    continue;
+                }
+                let chunks = layer.chunks();
+                for chunk in
+                    (0..chunks.y).flat_map(|y| (0..chunks.x).map(move |x| UVec2::new(x, y)))
+                {
+                    // chunks grow down from the origin like their tiles
+                    let min = Vec2::new(chunk.x as f32, -(chunk.y as f32 + 1.0)) * chunk_size;
+                    if let Some(frustum) = frustum
+                        && !frustum.intersects_transformed_aabb(
+                            *model,
+                            min.extend(0.0),
+                            (min + chunk_size).extend(0.0),
+                        )
+                    {
+                        continue;
+                    }
+
+                    let key = (handle.id(), index, chunk);
+                    let stamp = (layer.chunk_revision(chunk), layer.opacity.to_bits());
+                    let cached = self.chunks.entry(key).or_insert_with(|| Chunk {
+                        // never matches a real stamp's opacity
+                        stamp: (0, u32::MAX),
+                        buffer: None,
+                        batches: Vec::new(),
+                    });
+                    if cached.stamp != stamp {
+                        cached.stamp = stamp;
+                        build_chunk(device, tilemap, index, chunk, cached);
+                    }
+                    if !cached.batches.is_empty() {
+                        self.draws.push((*entity, key));
+                    }
+                }
+            }
+        }
+
+        // chunks off screen keep their buffers for when they come back
+        let live: Vec<usize> = maps.iter().map(|(_, _, handle, _)| handle.id()).collect();
+        self.chunks.retain(|(map, _, _), _| live.contains(map));
+        self.uniforms
+            .retain(|entity, _| maps.iter().any(|(_, other, _, _)| other == entity));
+    }
+
+    // expects the camera bind group to be set
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        texture_bind_groups: &FastHashMap<usize, wgpu::BindGroup>,
+        default_bind_group: &wgpu::BindGroup,
+    ) {
+        if self.draws.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        for (entity, key) in &self.draws {
+            let (Some((_, bind_group)), Some(chunk)) =
+                (self.uniforms.get(entity), self.chunks.get(key))
+            else {
+                continue;
+            };
+            let Some(buffer) = &chunk.buffer else {
+                continue;
+            };
+            render_pass.set_bind_group(2, bind_group, &[]);
+            render_pass.set_vertex_buffer(0, buffer.slice(..));
+            for (texture, instances) in &chunk.batches {
+                let texture = texture_bind_groups
+                    .get(texture)
+                    .unwrap_or(default_bind_group);
+                render_pass.set_bind_group(1, texture, &[]);
+                render_pass.draw(0..6, instances.clone());
+            }
+        }
+    }
+}
+
+fn build_chunk(
+    device: &wgpu::Device,
+    tilemap: &Tilemap,
+    layer: usize,
+    chunk: UVec2,
+    cached: &mut Chunk,
+) {
+    let layer = &tilemap.layers[layer];
+    let mut tiles: Vec<(u32, TileInstance)> = layer
+        .chunk_tiles(chunk)
+        .filter_map(|(position, tile)| {
+            let rect = tilemap
+                .tilesets
+                .get(tile.tileset as usize)?
+                .atlas
+                .frame(tile.index as usize)?;
+            // uv v runs down the image while the tile's y runs up
+            let (mut left, mut right) = (rect.min.x, rect.max.x);
+            let (mut bottom, mut top) = (rect.max.y, rect.min.y);
+            if tile.flip_x {
+                std::mem::swap(&mut left, &mut right);
+            }
+            if tile.flip_y {
+                std::mem::swap(&mut bottom, &mut top);
+            }
+            let position = position.as_vec2();
+            Some((
+                tile.tileset,
+                TileInstance {
+                    tile: [position.x, position.y, layer.opacity, 0.0],
+                    rect: [left, bottom, right, top],
+                },
+            ))
+        })
+        .collect();
+    tiles.sort_by_key(|(tileset, _)| *tileset);
+
+    cached.batches.clear();
+    let mut instances = Vec::with_capacity(tiles.len());
+    for (tileset, tile) in tiles {
+        let texture = tilemap.tilesets[tileset as usize].texture.id();
+        let index = instances.len() as u32;
+        instances.push(tile);
+        match cached.batches.last_mut() {
+            Some((last, range)) if *last == texture => range.end = index + 1,
+            _ => cached.batches.push((texture, index..index + 1)),
+        }
+    }
+    cached.buffer = (!instances.is_empty()).then(|| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tilemap Chunk Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
+    });
+}
@@ -0,0 +1,61 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+struct TilemapUniform {
+    model: mat4x4<f32>,
+    tile_size: vec2<f32>,
+};
+@group(2) @binding(0)
+var<uniform> tilemap: TilemapUniform;
+
+struct InstanceInput {
+    // x and y in tiles, then the layer's opacity
+    @location(0) tile: vec4<f32>,
+    // uv of the bottom left corner, then the top right
+    @location(1) rect: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) opacity: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+    // rows run down from the origin
+    let local = vec2<f32>(instance.tile.x + corner.x, corner.y - instance.tile.y - 1.0) * tilemap.tile_size;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * tilemap.model * vec4<f32>(local, 0.0, 1.0);
+    out.tex_coords = mix(instance.rect.xy, instance.rect.zw, corner);
+    out.opacity = instance.tile.z;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    color.a *= in.opacity;
+    if color.a <= 0.0 {
+        discard;
+    }
+    return color;
+}
@@ -0,0 +1,122 @@
+// Transient render targets. Passes that only need an intermediate texture for
+// part of a frame lease one from the pool and release it when they're done, so
+// later passes and later frames reuse it instead of each pass keeping its own.
+// Targets are sized relative to the window and dropped when it's resized, and
+// ones that sit unused for a while are freed. Leases still out at the end of
+// the frame are reported once and taken back.
+// Buffers aren't pooled, queue writes all land before the frame's commands run,
+// so two passes sharing one in a frame would both see the last write.
+
+use super::post::PostTarget;
+
+// frames an unused resource is kept around for
+const MAX_IDLE_FRAMES: u32 = 120;
+
+struct Slot {
+    target: PostTarget,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    // who has it this frame
+    leased: Option<&'static str>,
+    idle_frames: u32,
+}
+
+pub(crate) struct TransientTexture {
+    slot: usize,
+    frame: u64,
+    pub(crate) view: wgpu::TextureView,
+}
+
+pub(crate) struct TransientPool {
+    width: u32,
+    height: u32,
+    frame: u64,
+    slots: Vec<Slot>,
+    reported: Vec<&'static str>,
+}
+
+impl TransientPool {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            frame: 0,
+            slots: Vec::new(),
+            reported: Vec::new(),
+        }
+    }
+
+    // the old targets are recreated at the new size the next time they're asked for.
+    // only called between frames, when nothing is leased
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.slots.clear();
+    }
+
+    // a free target the window's size divided by `divisor` in this format, or a new one
+    pub(crate) fn texture(
+        &mut self,
+        device: &wgpu::Device,
+        label: &'static str,
+        divisor: u32,
+        format: wgpu::TextureFormat,
+    ) -> TransientTexture {
+        let width = self.width.div_ceil(divisor.max(1)).max(1);
+        let height = self.height.div_ceil(divisor.max(1)).max(1);
+        let free = self.slots.iter().position(|slot| {
+            slot.leased.is_none()
+                && slot.width == width
+                && slot.height == height
+                && slot.format == format
+        });
+        let index = free.unwrap_or_else(|| {
+            self.slots.push(Slot {
+                target: PostTarget::new(device, format, width, height, label),
+                width,
+                height,
+                format,
+                leased: None,
+                idle_frames: 0,
+            });
+            self.slots.len() - 1
+        });
+        let slot = &mut self.slots[index];
+        slot.leased = Some(label);
+        slot.idle_frames = 0;
+        TransientTexture {
+            slot: index,
+            frame: self.frame,
+            view: slot.target.view.clone(),
+        }
+    }
+
+    // passes recorded after this in the same frame may get the texture
+    pub(crate) fn release_texture(&mut self, texture: TransientTexture) {
+        if texture.frame == self.frame
+            && let Some(slot) = self.slots.get_mut(texture.slot)
+        {
+            slot.leased = None;
+        }
+    }
+
+    // after the frame is submitted
+    pub(crate) fn end_frame(&mut self) {
+        for slot in &mut self.slots {
+            if let Some(label) = slot.leased.take()
+                && !self.reported.contains(&label)
+            {
+                log::warn!(
+                    "Transient {} wasn't released before the end of the frame",
+                    label
+                );
+                self.reported.push(label);
+            }
+            slot.idle_frames += 1;
+        }
+        self.slots
+            .retain(|slot| slot.idle_frames <= MAX_IDLE_FRAMES);
+        self.frame += 1;
+    }
+}
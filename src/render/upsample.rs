@@ -0,0 +1,104 @@
+// Puts an effect traced at a lower resolution onto the frame. The effect's
+// texture is premultiplied, its alpha is how much of the frame underneath it
+// replaces, 0 adds it on. Upsampling is bilateral: the four nearest texels are
+// blended bilinearly, less the further their depth is from the pixel's, so
+// reflections and light shafts don't bleed over the edges of what's in front.
+// Effects traced at full size go through it too, it's a plain composite then.
+
+use crate::render::post::{self, PostTargets};
+
+// what an expensive screen space pass is traced at, set per pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PassResolution {
+    #[default]
+    Full,
+    // a quarter of the pixels, upsampled against the depth
+    Half,
+}
+
+impl PassResolution {
+    pub(crate) fn divisor(self) -> u32 {
+        match self {
+            PassResolution::Full => 1,
+            PassResolution::Half => 2,
+        }
+    }
+}
+
+pub(crate) struct UpsamplePass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+}
+
+impl UpsamplePass {
+    // the format effects are traced into
+    pub(crate) const EFFECT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("upsample_layout"),
+            entries: &[
+                post::texture_entry(0),
+                post::sampler_entry(1),
+                post::texture_entry(2),
+                post::depth_entry(3),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("upsample.wgsl"));
+        let pipeline = post::fullscreen_pipeline(
+            device,
+            cache,
+            "Upsample Pipeline",
+            &shader,
+            &[camera_bind_group_layout, &layout],
+            format,
+        );
+        Self { pipeline, layout }
+    }
+
+    pub(crate) fn run(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        post: &mut PostTargets,
+        camera_bind_group: &wgpu::BindGroup,
+        depth: &wgpu::TextureView,
+        effect: &wgpu::TextureView,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("upsample_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&post.current().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&post.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(effect),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+            ],
+        });
+        let (_, target) = post.swap();
+        post::run_fullscreen(
+            encoder,
+            "Upsample Pass",
+            &target.view,
+            &self.pipeline,
+            &[camera_bind_group, &bind_group],
+        );
+    }
+}
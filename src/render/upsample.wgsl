@@ -0,0 +1,74 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var t_color: texture_2d<f32>;
+@group(1) @binding(1)
+var s_linear: sampler;
+// premultiplied, alpha is how much of the frame it covers
+@group(1) @binding(2)
+var t_effect: texture_2d<f32>;
+@group(1) @binding(3)
+var t_depth: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// from the camera to what's drawn at the depth texel
+fn scene_distance(coords: vec2<i32>, size: vec2<i32>) -> f32 {
+    let depth = textureLoad(t_depth, coords, 0).r;
+    let uv = (vec2<f32>(coords) + 0.5) / vec2<f32>(size);
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = camera.inverse_view_proj * ndc;
+    return distance(position.xyz / position.w, camera.position.xyz);
+}
+
+// bilinear over the four nearest effect texels, each weighed down by how far
+// its depth is from the pixel's so nothing bleeds across silhouettes
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(t_color, s_linear, in.uv, 0.0);
+    let depth_size = vec2<i32>(textureDimensions(t_depth));
+    let effect_size = vec2<i32>(textureDimensions(t_effect));
+    let center = scene_distance(clamp(vec2<i32>(in.uv * vec2<f32>(depth_size)), vec2<i32>(0), depth_size - 1), depth_size);
+
+    let position = in.uv * vec2<f32>(effect_size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let fraction = fract(position);
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = 0; i < 4; i++) {
+        let offset = vec2<i32>(i & 1, i >> 1u);
+        let texel = clamp(base + offset, vec2<i32>(0), effect_size - 1);
+        let bilinear = select(1.0 - fraction.x, fraction.x, offset.x == 1)
+            * select(1.0 - fraction.y, fraction.y, offset.y == 1);
+        let depth_coords = clamp(
+            vec2<i32>((vec2<f32>(texel) + 0.5) * vec2<f32>(depth_size) / vec2<f32>(effect_size)),
+            vec2<i32>(0),
+            depth_size - 1,
+        );
+        let difference = abs(scene_distance(depth_coords, depth_size) - center) / max(center, 1e-3);
+        // a little plain bilinear, for pixels none of the texels match
+        let weight = bilinear * (exp(-difference * 50.0) + 1e-3);
+        sum += textureLoad(t_effect, texel, 0) * weight;
+        total += weight;
+    }
+    let effect = sum / max(total, 1e-5);
+    return vec4<f32>(color.rgb * (1.0 - effect.a) + effect.rgb, color.a);
+}
@@ -0,0 +1,160 @@
+// Light shafts from a distant light. There are no shadow maps yet, so
+// occlusion is marched in screen space towards the light against the depth
+// buffer, at half resolution unless asked otherwise, then upsampled and added
+// onto the frame.
+
+use glam::Vec3;
+
+use crate::{
+    color::Color,
+    render::{
+        post::{self, PostTargets},
+        transient::TransientPool,
+        upsample::{PassResolution, UpsamplePass},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumetricLighting {
+    // the way the light travels, the shafts point back along it
+    pub direction: Vec3,
+    pub color: Color,
+    pub density: f32,
+    // henyey-greenstein g, 0 scatters evenly, towards 1 glows around the light
+    pub anisotropy: f32,
+    pub steps: u32,
+    pub resolution: PassResolution,
+}
+
+impl Default for VolumetricLighting {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-0.3, -0.5, -1.0),
+            color: Color::WHITE,
+            density: 1.0,
+            anisotropy: 0.6,
+            steps: 32,
+            resolution: PassResolution::Half,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct VolumetricUniform {
+    // xyz towards the light
+    to_light: [f32; 4],
+    color: [f32; 4],
+    density: f32,
+    anisotropy: f32,
+    steps: u32,
+    _padding: u32,
+}
+
+pub(crate) struct VolumetricPass {
+    scatter_pipeline: wgpu::RenderPipeline,
+    scatter_layout: wgpu::BindGroupLayout,
+    settings_buffer: wgpu::Buffer,
+}
+
+impl VolumetricPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("volumetric.wgsl"));
+
+        let scatter_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("volumetric_scatter_layout"),
+            entries: &[post::depth_entry(0), post::uniform_entry(1)],
+        });
+        let scatter_pipeline = post::fullscreen_pipeline(
+            device,
+            cache,
+            "Volumetric Scatter Pipeline",
+            &shader,
+            &[camera_bind_group_layout, &scatter_layout],
+            UpsamplePass::EFFECT_FORMAT,
+        );
+
+        let settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("volumetric_settings"),
+            size: std::mem::size_of::<VolumetricUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            scatter_pipeline,
+            scatter_layout,
+            settings_buffer,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn run(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        post: &mut PostTargets,
+        transient: &mut TransientPool,
+        upsample: &UpsamplePass,
+        camera_bind_group: &wgpu::BindGroup,
+        depth: &wgpu::TextureView,
+        settings: VolumetricLighting,
+    ) {
+        let to_light = (-settings.direction).try_normalize().unwrap_or(Vec3::Y);
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&VolumetricUniform {
+                to_light: to_light.extend(0.0).to_array(),
+                color: settings.color.to_linear(),
+                density: settings.density.max(0.0),
+                anisotropy: settings.anisotropy.clamp(-0.99, 0.99),
+                steps: settings.steps.clamp(1, 128),
+                _padding: 0,
+            }),
+        );
+
+        let scatter_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("volumetric_scatter_bind_group"),
+            layout: &self.scatter_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.settings_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        // in-scattered light, added onto the frame
+        let scatter = transient.texture(
+            device,
+            "volumetric_scatter",
+            settings.resolution.divisor(),
+            UpsamplePass::EFFECT_FORMAT,
+        );
+        post::run_fullscreen(
+            encoder,
+            "Volumetric Scatter Pass",
+            &scatter.view,
+            &self.scatter_pipeline,
+            &[camera_bind_group, &scatter_bind_group],
+        );
+        upsample.run(
+            device,
+            encoder,
+            post,
+            camera_bind_group,
+            depth,
+            &scatter.view,
+        );
+        transient.release_texture(scatter);
+    }
+}
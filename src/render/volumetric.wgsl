@@ -0,0 +1,83 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VolumetricSettings {
+    to_light: vec4<f32>,
+    color: vec4<f32>,
+    density: f32,
+    anisotropy: f32,
+    steps: u32,
+};
+
+@group(1) @binding(0)
+var t_depth: texture_2d<f32>;
+@group(1) @binding(1)
+var<uniform> settings: VolumetricSettings;
+
+const PI: f32 = 3.14159265359;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn phase(cos_theta: f32) -> f32 {
+    let g = settings.anisotropy;
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    return (1.0 - g * g) / (4.0 * PI * pow(denominator, 1.5));
+}
+
+// whether the light can be seen past the scene at `uv`
+fn lit(uv: vec2<f32>, size: vec2<i32>) -> f32 {
+    let coords = clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    return select(0.0, 1.0, textureLoad(t_depth, coords, 0).r >= 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let light_clip = camera.view_proj * vec4<f32>(camera.position.xyz + settings.to_light.xyz * 1000.0, 1.0);
+    if light_clip.w <= 0.0 {
+        return vec4<f32>(0.0);
+    }
+    let light_ndc = light_clip.xy / light_clip.w;
+    let light_uv = vec2<f32>(light_ndc.x * 0.5 + 0.5, 0.5 - light_ndc.y * 0.5);
+
+    let ndc = vec4<f32>(in.uv.x * 2.0 - 1.0, 1.0 - in.uv.y * 2.0, 1.0, 1.0);
+    let far = camera.inverse_view_proj * ndc;
+    let view_direction = normalize(far.xyz / far.w - camera.position.xyz);
+
+    // march from the pixel towards the light, counting the unoccluded samples
+    let size = vec2<i32>(textureDimensions(t_depth));
+    let delta = (light_uv - in.uv) / f32(settings.steps);
+    var visibility = 0.0;
+    var uv = in.uv;
+    for (var i = 0u; i < settings.steps; i++) {
+        if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+            break;
+        }
+        visibility += lit(uv, size);
+        uv += delta;
+    }
+    visibility /= f32(settings.steps);
+
+    // shafts fade out as the light leaves the screen
+    let edge = max(abs(light_ndc.x), abs(light_ndc.y));
+    let fade = clamp(2.0 - edge, 0.0, 1.0);
+    let scattering = visibility * fade * settings.density * phase(dot(view_direction, settings.to_light.xyz));
+    // alpha 0, the light is added onto the frame
+    return vec4<f32>(settings.color.rgb * scattering, 0.0);
+}
@@ -0,0 +1,288 @@
+// Water surfaces. Meshes with a Water component skip the regular pipeline and
+// are drawn after the opaque scene with gerstner waves displacing the
+// vertices, color absorbed by how much water is between the surface and the
+// scene behind it, foam where it meets geometry, and optionally a planar
+// reflection on top. Use a subdivided plane like Mesh::plane for the waves.
+
+use glam::Vec2;
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    assets::Handle,
+    color::Color,
+    ecs::{component::Component, entity::Entity},
+    render::post,
+    texture::Texture,
+};
+
+const MAX_WAVES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wave {
+    // along the water's local xz plane
+    pub direction: Vec2,
+    pub amplitude: f32,
+    pub wavelength: f32,
+    // 0 is a sine wave, 1 the sharpest crest before it loops over itself
+    pub steepness: f32,
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Water {
+    // alpha is how see-through the water is
+    pub shallow_color: Color,
+    pub deep_color: Color,
+    // how much water it takes to reach the deep color
+    pub absorption_depth: f32,
+    pub foam_color: Color,
+    // how far from geometry the foam reaches
+    pub foam_distance: f32,
+    // at most 4 are used
+    pub waves: Vec<Wave>,
+    // a PlanarReflection's texture, usually on the same entity
+    pub reflection: Option<Handle<Texture>>,
+    pub reflectivity: f32,
+}
+
+impl Component for Water {}
+
+impl Default for Water {
+    fn default() -> Self {
+        Self {
+            shallow_color: Color::srgba(0.1, 0.6, 0.6, 0.4),
+            deep_color: Color::srgb(0.0, 0.1, 0.25),
+            absorption_depth: 4.0,
+            foam_color: Color::WHITE,
+            foam_distance: 0.4,
+            waves: vec![
+                Wave {
+                    direction: Vec2::new(1.0, 0.3),
+                    amplitude: 0.15,
+                    wavelength: 6.0,
+                    steepness: 0.5,
+                    speed: 1.5,
+                },
+                Wave {
+                    direction: Vec2::new(-0.4, 1.0),
+                    amplitude: 0.08,
+                    wavelength: 3.0,
+                    steepness: 0.4,
+                    speed: 1.0,
+                },
+            ],
+            reflection: None,
+            reflectivity: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuWave {
+    // xy direction, z amplitude, w wavelength
+    shape: [f32; 4],
+    // x steepness, y speed
+    motion: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct WaterUniform {
+    model: [[f32; 4]; 4],
+    shallow_color: [f32; 4],
+    deep_color: [f32; 4],
+    foam_color: [f32; 4],
+    waves: [GpuWave; MAX_WAVES],
+    absorption_depth: f32,
+    foam_distance: f32,
+    reflectivity: f32,
+    time: f32,
+    wave_count: u32,
+    has_reflection: u32,
+    _padding: [u32; 2],
+}
+
+pub(crate) struct WaterPass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    buffers: FastHashMap<Entity, wgpu::Buffer>,
+}
+
+impl WaterPass {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: Option<&wgpu::PipelineCache>,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        vertex_buffer: wgpu::VertexBufferLayout<'_>,
+    ) -> Self {
+        let mut uniform_entry = post::uniform_entry(0);
+        uniform_entry.visibility = wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("water_layout"),
+            entries: &[
+                uniform_entry,
+                post::depth_entry(1),
+                post::texture_entry(2),
+                post::sampler_entry(3),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("water.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Water Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &layout],
+            immediate_size: 0,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Water Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[vertex_buffer],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            // seen from below too
+            primitive: wgpu::PrimitiveState::default(),
+            // the scene depth is sampled while tested against, so it stays read only
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache,
+        });
+
+        Self {
+            pipeline,
+            layout,
+            buffers: FastHashMap::default(),
+        }
+    }
+
+    // one bind group per water surface, `reflection` falls back to `fallback`
+    // when the water has none
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entity: Entity,
+        water: &Water,
+        model: glam::Mat4,
+        time: f32,
+        depth: &wgpu::TextureView,
+        reflection: Option<&Texture>,
+        fallback: &Texture,
+    ) -> wgpu::BindGroup {
+        let mut waves = [GpuWave::default(); MAX_WAVES];
+        for (gpu, wave) in waves.iter_mut().zip(&water.waves) {
+            let direction = wave.direction.try_normalize().unwrap_or(Vec2::X);
+            gpu.shape = [
+                direction.x,
+                direction.y,
+                wave.amplitude,
+                wave.wavelength.max(0.01),
+            ];
+            gpu.motion = [wave.steepness.clamp(0.0, 1.0), wave.speed, 0.0, 0.0];
+        }
+        let uniform = WaterUniform {
+            model: model.to_cols_array_2d(),
+            shallow_color: water.shallow_color.to_linear(),
+            deep_color: water.deep_color.to_linear(),
+            foam_color: water.foam_color.to_linear(),
+            waves,
+            absorption_depth: water.absorption_depth.max(0.01),
+            foam_distance: water.foam_distance.max(0.0),
+            reflectivity: water.reflectivity,
+            time,
+            wave_count: water.waves.len().min(MAX_WAVES) as u32,
+            has_reflection: reflection.is_some() as u32,
+            _padding: [0; 2],
+        };
+
+        let buffer = self.buffers.entry(entity).or_insert_with(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("water_uniform"),
+                size: std::mem::size_of::<WaterUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        queue.write_buffer(buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let reflection = reflection.unwrap_or(fallback);
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("water_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&reflection.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&reflection.sampler),
+                },
+            ],
+        })
+    }
+
+    // drops the buffers of water that is gone
+    pub(crate) fn retain(&mut self, entities: &[Entity]) {
+        self.buffers.retain(|entity, _| entities.contains(entity));
+    }
+
+    pub(crate) fn begin<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        depth: &wgpu::TextureView,
+    ) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Water Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: None,
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass
+    }
+}
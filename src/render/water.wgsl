@@ -0,0 +1,110 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct Wave {
+    shape: vec4<f32>,
+    motion: vec4<f32>,
+};
+
+struct WaterUniform {
+    model: mat4x4<f32>,
+    shallow_color: vec4<f32>,
+    deep_color: vec4<f32>,
+    foam_color: vec4<f32>,
+    waves: array<Wave, 4>,
+    absorption_depth: f32,
+    foam_distance: f32,
+    reflectivity: f32,
+    time: f32,
+    wave_count: u32,
+    has_reflection: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> water: WaterUniform;
+@group(1) @binding(1)
+var t_depth: texture_2d<f32>;
+@group(1) @binding(2)
+var t_reflection: texture_2d<f32>;
+@group(1) @binding(3)
+var s_reflection: sampler;
+
+const PI: f32 = 3.14159265359;
+
+struct VertexInput {
+    @location(0) position: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    let base = (water.model * model.position).xyz;
+    var position = base;
+    var normal = vec3<f32>(0.0, 1.0, 0.0);
+    for (var i = 0u; i < water.wave_count; i++) {
+        let wave = water.waves[i];
+        let direction = wave.shape.xy;
+        let amplitude = wave.shape.z;
+        let k = 2.0 * PI / wave.shape.w;
+        let f = k * (dot(direction, base.xz) - wave.motion.y * water.time);
+        // spread the steepness between the waves so crests don't loop
+        let q = wave.motion.x / max(k * amplitude * f32(water.wave_count), 0.0001);
+        position += vec3<f32>(
+            q * amplitude * direction.x * cos(f),
+            amplitude * sin(f),
+            q * amplitude * direction.y * cos(f),
+        );
+        normal -= vec3<f32>(
+            direction.x * k * amplitude * cos(f),
+            q * k * amplitude * sin(f),
+            direction.y * k * amplitude * cos(f),
+        );
+    }
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.world_position = position;
+    out.normal = normal;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_depth));
+    let uv = in.clip_position.xy / size;
+    let depth = textureLoad(t_depth, vec2<i32>(in.clip_position.xy), 0).r;
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let scene = camera.inverse_view_proj * ndc;
+    let behind = distance(scene.xyz / scene.w, camera.position.xyz)
+        - distance(in.world_position, camera.position.xyz);
+    // nothing behind the water counts as deep
+    let thickness = select(max(behind, 0.0), 1e6, depth >= 1.0);
+
+    let absorbed = 1.0 - exp(-thickness / water.absorption_depth);
+    var color = mix(water.shallow_color, water.deep_color, absorbed);
+
+    let normal = normalize(in.normal);
+    let view = normalize(camera.position.xyz - in.world_position);
+    if water.has_reflection != 0u {
+        let offset = normal.xz * 0.02;
+        let reflection = textureSampleLevel(t_reflection, s_reflection, uv + offset, 0.0).rgb;
+        let fresnel = 0.02 + 0.98 * pow(1.0 - max(dot(normal, view), 0.0), 5.0);
+        color = vec4<f32>(mix(color.rgb, reflection, clamp(fresnel * water.reflectivity, 0.0, 1.0)), color.a);
+    }
+
+    if water.foam_distance > 0.0 {
+        let foam = 1.0 - clamp(thickness / water.foam_distance, 0.0, 1.0);
+        color = mix(color, water.foam_color, foam * water.foam_color.a);
+    }
+    return color;
+}
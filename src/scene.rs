@@ -0,0 +1,324 @@
+// A stack of scenes, each its own World with its own entities, resources and
+// schedules. Only the top one runs, it's the world the app updates and draws,
+// the ones beneath wait paused as they were left. Systems ask the SceneManager
+// for changes and they happen after the frame: `push` pauses the current scene
+// under a new one, `pop` drops the top one and resumes the one beneath, and
+// `replace` swaps the top one. A new scene's world starts like the app's first
+// one but without the systems added to App, its setup adds the rest before its
+// "startup" runs. Scenes that go away run their "shutdown" first.
+// An overlay scene is drawn over the last frame of the scene beneath it, a
+// pause menu over the frozen game say, until the window is resized.
+// The window, input, surface settings and exit state belong to the app and move
+// to whichever scene is on top. The gpu copies of meshes and textures don't,
+// they're uploaded again when a scene comes back.
+
+use crate::{
+    ecs::{
+        component::Component,
+        schedule::{Shutdown, Startup},
+        world::World,
+    },
+    render::{RenderDevice, RenderSettings, SurfaceSettings, gpu::GpuFeatures, post::PostTarget},
+};
+
+pub struct Scene {
+    name: String,
+    setup: Box<dyn FnOnce(&mut World)>,
+    overlay: bool,
+}
+
+impl std::fmt::Debug for Scene {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scene")
+            .field("name", &self.name)
+            .field("overlay", &self.overlay)
+            .finish()
+    }
+}
+
+impl Scene {
+    // `setup` adds the scene's systems and spawns what it starts with
+    pub fn new(name: impl Into<String>, setup: impl FnOnce(&mut World) + 'static) -> Self {
+        Self {
+            name: name.into(),
+            setup: Box::new(setup),
+            overlay: false,
+        }
+    }
+
+    // drawn over the scene beneath instead of its own background
+    pub fn overlay(mut self) -> Self {
+        self.overlay = true;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Debug)]
+enum SceneChange {
+    Push(Scene),
+    Pop,
+    Replace(Scene),
+}
+
+#[derive(Debug)]
+pub struct SceneManager {
+    current: String,
+    // bottom first
+    paused: Vec<String>,
+    pending: Vec<SceneChange>,
+}
+
+impl Component for SceneManager {}
+
+impl Default for SceneManager {
+    fn default() -> Self {
+        Self {
+            current: "main".to_string(),
+            paused: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl SceneManager {
+    pub fn push(&mut self, scene: Scene) {
+        self.pending.push(SceneChange::Push(scene));
+    }
+
+    // ignored for the last scene, the app would have nothing to run
+    pub fn pop(&mut self) {
+        self.pending.push(SceneChange::Pop);
+    }
+
+    pub fn replace(&mut self, scene: Scene) {
+        self.pending.push(SceneChange::Replace(scene));
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    // the paused scenes, bottom first
+    pub fn paused(&self) -> &[String] {
+        &self.paused
+    }
+
+    // how many scenes there are, the top one included
+    pub fn depth(&self) -> usize {
+        self.paused.len() + 1
+    }
+
+    // whether this frame should be kept for an overlay pushed over it
+    pub(crate) fn wants_backdrop(&self) -> bool {
+        self.pending
+            .iter()
+            .any(|change| matches!(change, SceneChange::Push(scene) if scene.overlay))
+    }
+}
+
+pub(crate) struct PausedScene {
+    name: String,
+    world: World,
+    // what the scene was drawn over, if it's an overlay
+    backdrop: Option<PostTarget>,
+}
+
+// the resources that stay with the app rather than a scene
+fn carry_resources(from: &mut World, to: &mut World) {
+    fn carry<T: Component + 'static>(from: &mut World, to: &mut World) {
+        if let Some(resource) = from.remove_resource::<T>() {
+            to.insert_resource(resource);
+        }
+    }
+
+    carry::<SceneManager>(from, to);
+    carry::<crate::window::WindowSettings>(from, to);
+    carry::<crate::window::BackgroundSettings>(from, to);
+    carry::<crate::input::Input>(from, to);
+    carry::<SurfaceSettings>(from, to);
+    carry::<RenderSettings>(from, to);
+    carry::<crate::render::resolution::RenderScale>(from, to);
+    carry::<RenderDevice>(from, to);
+    carry::<GpuFeatures>(from, to);
+    carry::<crate::ui::UiScale>(from, to);
+    #[cfg(feature = "renderdoc")]
+    carry::<crate::diagnostics::Diagnostics>(from, to);
+    #[cfg(feature = "ui")]
+    carry::<crate::stats::StatsOverlay>(from, to);
+    carry::<crate::bridge::Bridge>(from, to);
+    carry::<crate::web::WebSettings>(from, to);
+    carry::<crate::exit::ExitHooks>(from, to);
+    carry::<crate::event_hooks::EventHooks>(from, to);
+    carry::<crate::exit::PendingExit>(from, to);
+    carry::<crate::proxy::EngineProxy>(from, to);
+}
+
+fn start_scene(world: &mut World, scene: Scene) -> World {
+    let mut next = crate::create_world();
+    carry_resources(world, &mut next);
+    (scene.setup)(&mut next);
+    next.run(Startup);
+    next
+}
+
+// applies the changes asked for this frame, true when the top scene changed.
+// `captured` is this frame, kept when an overlay was pushed
+pub(crate) fn change_scenes(
+    world: &mut World,
+    paused: &mut Vec<PausedScene>,
+    backdrop: &mut Option<PostTarget>,
+    mut captured: Option<PostTarget>,
+) -> bool {
+    let changes = std::mem::take(&mut world.resource_mut::<SceneManager>().pending);
+    let mut changed = false;
+    for change in changes {
+        match change {
+            SceneChange::Push(scene) => {
+                let name = scene.name.clone();
+                let next_backdrop = if scene.overlay { captured.take() } else { None };
+                let next = start_scene(world, scene);
+                let previous = std::mem::replace(world, next);
+                let manager = world.resource_mut::<SceneManager>();
+                let previous_name = std::mem::replace(&mut manager.current, name);
+                manager.paused.push(previous_name.clone());
+                paused.push(PausedScene {
+                    name: previous_name,
+                    world: previous,
+                    backdrop: std::mem::replace(backdrop, next_backdrop),
+                });
+            }
+            SceneChange::Pop => {
+                let Some(mut scene) = paused.pop() else {
+                    log::warn!("There's no scene beneath to go back to");
+                    continue;
+                };
+                world.run(Shutdown);
+                carry_resources(world, &mut scene.world);
+                *world = scene.world;
+                *backdrop = scene.backdrop;
+                // the time it spent paused isn't a frame
+                world.resource_mut::<crate::time::Time>().reset_clock();
+                let manager = world.resource_mut::<SceneManager>();
+                manager.paused.pop();
+                manager.current = scene.name;
+            }
+            SceneChange::Replace(scene) => {
+                let name = scene.name.clone();
+                if !scene.overlay {
+                    *backdrop = None;
+                }
+                world.run(Shutdown);
+                let next = start_scene(world, scene);
+                *world = next;
+                world.resource_mut::<SceneManager>().current = name;
+            }
+        }
+        changed = true;
+    }
+    changed
+}
+
+// at exit, after the top scene's
+pub(crate) fn shut_down_paused(paused: &mut [PausedScene]) {
+    for scene in paused.iter_mut().rev() {
+        scene.world.run(Shutdown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::input::Input;
+
+    // what ran, in order, shared between the scenes' worlds
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn logged(log: &Log, name: &str) -> Scene {
+        let (log, name) = (log.clone(), name.to_string());
+        Scene::new(name.clone(), move |world| {
+            log.borrow_mut().push(format!("setup {name}"));
+            let shutdown = log.clone();
+            world.add_system_to(Shutdown, move |_: &mut World| {
+                shutdown.borrow_mut().push(format!("shutdown {name}"));
+            });
+        })
+    }
+
+    fn change(world: &mut World, paused: &mut Vec<PausedScene>) -> bool {
+        change_scenes(world, paused, &mut None, None)
+    }
+
+    #[test]
+    fn push_and_pop_pause_and_resume() {
+        let log = Log::default();
+        let mut world = crate::create_world();
+        let player = world.spawn().id();
+        let mut paused = Vec::new();
+
+        world
+            .resource_mut::<SceneManager>()
+            .push(logged(&log, "menu"));
+        assert!(!world.resource::<SceneManager>().wants_backdrop());
+        assert!(change(&mut world, &mut paused));
+        let manager = world.resource::<SceneManager>();
+        assert_eq!(
+            (manager.current(), manager.paused()),
+            ("menu", &["main".to_string()][..])
+        );
+        assert_eq!(manager.depth(), 2);
+        assert!(!world.is_alive(player), "the menu has a world of its own");
+        // the app's resources came along
+        assert!(world.get_resource::<Input>().is_some());
+
+        world.resource_mut::<SceneManager>().pop();
+        assert!(change(&mut world, &mut paused));
+        assert!(world.is_alive(player));
+        assert_eq!(world.resource::<SceneManager>().current(), "main");
+        assert!(paused.is_empty());
+        assert_eq!(*log.borrow(), ["setup menu", "shutdown menu"]);
+
+        // the last scene stays
+        world.resource_mut::<SceneManager>().pop();
+        assert!(!change(&mut world, &mut paused));
+        assert!(world.is_alive(player));
+    }
+
+    #[test]
+    fn replacing_and_shutting_down_the_stack() {
+        let log = Log::default();
+        let mut world = crate::create_world();
+        let mut paused = Vec::new();
+        let manager = world.resource_mut::<SceneManager>();
+        manager.replace(logged(&log, "level 1"));
+        manager.push(logged(&log, "pause").overlay());
+        assert!(manager.wants_backdrop());
+        change(&mut world, &mut paused);
+        world
+            .resource_mut::<SceneManager>()
+            .replace(logged(&log, "options"));
+        change(&mut world, &mut paused);
+
+        let manager = world.resource::<SceneManager>();
+        assert_eq!(manager.current(), "options");
+        assert_eq!(manager.paused(), ["level 1"]);
+        world.run(Shutdown);
+        shut_down_paused(&mut paused);
+        assert_eq!(
+            *log.borrow(),
+            [
+                "setup level 1",
+                "setup pause",
+                "shutdown pause",
+                "setup options",
+                "shutdown options",
+                "shutdown level 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,440 @@
+// Mesh simplification by quadric error edge collapse, after Garland and
+// Heckbert. Every vertex keeps the sum of the planes of the faces around it,
+// and the edge whose collapse moves the surface least is taken first, over
+// and over until few enough triangles are left. An edge collapses onto one of
+// its ends, so the vertices left are the mesh's own with their uvs, normals
+// and colors as they were. Vertices on an open edge, which includes uv and
+// normal seams where the mesh is split, stay where they are, so silhouettes and
+// seams hold their shape. A collapse that would flip a face is skipped.
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use glam::{DVec3, Vec3};
+
+use crate::mesh::{Mesh, MorphTarget, Submesh};
+
+// the upper triangle of a symmetric 4x4 matrix
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: DVec3, d: f64, weight: f64) -> Self {
+        let [a, b, c] = normal.to_array();
+        Self(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|value| value * weight),
+        )
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(other.0) {
+            *value += other;
+        }
+    }
+
+    // the squared distance to the planes summed in, at `p`
+    fn error(&self, p: DVec3) -> f64 {
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, d2] = self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        a2 * x * x
+            + 2.0 * ab * x * y
+            + 2.0 * ac * x * z
+            + 2.0 * ad * x
+            + b2 * y * y
+            + 2.0 * bc * y * z
+            + 2.0 * bd * y
+            + c2 * z * z
+            + 2.0 * cd * z
+            + d2
+    }
+}
+
+// `from` collapsing onto `to`, the cheapest first out of the heap
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    // both ends' versions when it was priced, it's stale once either changes
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+struct Simplifier {
+    positions: Vec<DVec3>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    removed: Vec<bool>,
+    versions: Vec<u32>,
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    // the triangles around each vertex, dead ones included until they're skipped
+    around: Vec<Vec<u32>>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    fn normal(&self, triangle: [u32; 3]) -> DVec3 {
+        let [a, b, c] = triangle.map(|vertex| self.positions[vertex as usize]);
+        (b - a).cross(c - a)
+    }
+
+    fn push(&mut self, from: u32, to: u32) {
+        let (f, t) = (from as usize, to as usize);
+        if self.locked[f] {
+            return;
+        }
+        let mut quadric = self.quadrics[f];
+        quadric.add(&self.quadrics[t]);
+        self.heap.push(Collapse {
+            cost: quadric.error(self.positions[t]),
+            from,
+            to,
+            versions: (self.versions[f], self.versions[t]),
+        });
+    }
+
+    // none of the triangles moved by the collapse may turn over or close up
+    fn keeps_orientation(&self, from: u32, to: u32) -> bool {
+        self.around[from as usize]
+            .iter()
+            .filter(|&&triangle| self.alive[triangle as usize])
+            .map(|&triangle| self.triangles[triangle as usize])
+            .filter(|triangle| !triangle.contains(&to))
+            .all(|triangle| {
+                let before = self.normal(triangle);
+                let after = self.normal(triangle.map(|v| if v == from { to } else { v }));
+                after.length_squared() > before.length_squared() * 1e-6 && before.dot(after) > 0.0
+            })
+    }
+
+    fn collapse(&mut self, from: u32, to: u32) -> usize {
+        let mut killed = 0;
+        for triangle in std::mem::take(&mut self.around[from as usize]) {
+            let t = triangle as usize;
+            if !self.alive[t] {
+                continue;
+            }
+            if self.triangles[t].contains(&to) {
+                self.alive[t] = false;
+                killed += 1;
+            } else {
+                for vertex in &mut self.triangles[t] {
+                    if *vertex == from {
+                        *vertex = to;
+                    }
+                }
+                self.around[to as usize].push(triangle);
+            }
+        }
+        self.removed[from as usize] = true;
+        let quadric = self.quadrics[from as usize];
+        self.quadrics[to as usize].add(&quadric);
+        self.versions[to as usize] += 1;
+
+        let alive = &self.alive;
+        self.around[to as usize].retain(|&triangle| alive[triangle as usize]);
+        let neighbours: Vec<u32> = self.around[to as usize]
+            .iter()
+            .flat_map(|&triangle| self.triangles[triangle as usize])
+            .filter(|&vertex| vertex != to)
+            .collect();
+        for neighbour in neighbours {
+            self.push(to, neighbour);
+            self.push(neighbour, to);
+        }
+        killed
+    }
+}
+
+// the same mesh down to about `ratio` of its triangles, fewer only when
+// nothing more can be taken without crossing a seam or flipping a face
+pub fn simplify(mesh: &Mesh, ratio: f32) -> Mesh {
+    let corners: Vec<u32> = if mesh.indices.is_empty() {
+        (0..mesh.vertices.len() as u32).collect()
+    } else {
+        mesh.indices.clone()
+    };
+    let triangle_count = corners.len() / 3;
+
+    // vertices that are the same in every way are one, so faces share them
+    let mut welded: HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut original = Vec::new();
+    let remap: Vec<u32> = (0..mesh.vertices.len())
+        .map(|index| {
+            let vertex = &mesh.vertices[index];
+            let mut key: Vec<u32> = bytemuck::bytes_of(vertex)
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect();
+            if let Some(color) = mesh.colors.get(index) {
+                key.extend(color.map(f32::to_bits));
+            }
+            *welded.entry(key).or_insert_with(|| {
+                original.push(index);
+                original.len() as u32 - 1
+            })
+        })
+        .collect();
+
+    let triangles: Vec<[u32; 3]> = corners
+        .chunks_exact(3)
+        .map(|corners| [0, 1, 2].map(|corner| remap[corners[corner] as usize]))
+        .collect();
+    // which submesh each triangle came from
+    let slots: Vec<usize> = (0..triangle_count)
+        .map(|triangle| {
+            let element = (triangle * 3) as u32;
+            mesh.submeshes
+                .iter()
+                .find(|submesh| submesh.range.contains(&element))
+                .map_or(0, |submesh| submesh.material)
+        })
+        .collect();
+
+    let positions: Vec<DVec3> = original
+        .iter()
+        .map(|&index| Vec3::from_slice(&mesh.vertices[index].position[..3]).as_dvec3())
+        .collect();
+    let count = positions.len();
+    let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+    let mut around = vec![Vec::new(); count];
+    let mut quadrics = vec![Quadric::default(); count];
+    for (index, triangle) in triangles.iter().enumerate() {
+        let [a, b, c] = triangle.map(|vertex| positions[vertex as usize]);
+        let cross = (b - a).cross(c - a);
+        let area = cross.length() * 0.5;
+        if let Some(normal) = cross.try_normalize() {
+            let quadric = Quadric::plane(normal, -normal.dot(a), area);
+            for vertex in triangle {
+                quadrics[*vertex as usize].add(&quadric);
+            }
+        }
+        for i in 0..3 {
+            let (from, to) = (triangle[i], triangle[(i + 1) % 3]);
+            *edges.entry((from.min(to), from.max(to))).or_default() += 1;
+            around[from as usize].push(index as u32);
+        }
+    }
+    let mut locked = vec![false; count];
+    for (&(a, b), &uses) in &edges {
+        if uses == 1 {
+            locked[a as usize] = true;
+            locked[b as usize] = true;
+        }
+    }
+
+    let mut simplifier = Simplifier {
+        positions,
+        quadrics,
+        locked,
+        removed: vec![false; count],
+        versions: vec![0; count],
+        alive: vec![true; triangles.len()],
+        triangles,
+        around,
+        heap: BinaryHeap::new(),
+    };
+    for &(a, b) in edges.keys() {
+        simplifier.push(a, b);
+        simplifier.push(b, a);
+    }
+
+    let target = ((triangle_count as f32 * ratio.clamp(0.0, 1.0)).ceil() as usize).max(1);
+    let mut remaining = triangle_count;
+    while remaining > target {
+        let Some(collapse) = simplifier.heap.pop() else {
+            break;
+        };
+        let (from, to) = (collapse.from as usize, collapse.to as usize);
+        if simplifier.removed[from]
+            || simplifier.removed[to]
+            || collapse.versions != (simplifier.versions[from], simplifier.versions[to])
+            || !simplifier.keeps_orientation(collapse.from, collapse.to)
+        {
+            continue;
+        }
+        remaining -= simplifier.collapse(collapse.from, collapse.to);
+    }
+
+    // what's left, grouped back into the submeshes
+    let mut kept: Vec<(usize, [u32; 3])> = simplifier
+        .triangles
+        .iter()
+        .zip(&simplifier.alive)
+        .zip(&slots)
+        .filter(|((_, alive), _)| **alive)
+        .map(|((triangle, _), slot)| (*slot, *triangle))
+        .collect();
+    kept.sort_by_key(|(slot, _)| *slot);
+
+    let mut compact: HashMap<u32, u32> = HashMap::new();
+    let mut used = Vec::new();
+    let mut indices = Vec::with_capacity(kept.len() * 3);
+    let mut submeshes: Vec<Submesh> = Vec::new();
+    for (slot, triangle) in &kept {
+        let start = indices.len() as u32;
+        for vertex in triangle {
+            indices.push(*compact.entry(*vertex).or_insert_with(|| {
+                used.push(original[*vertex as usize]);
+                used.len() as u32 - 1
+            }));
+        }
+        match submeshes.last_mut() {
+            Some(submesh) if submesh.material == *slot => submesh.range.end = start + 3,
+            _ => submeshes.push(Submesh {
+                range: start..start + 3,
+                material: *slot,
+            }),
+        }
+    }
+    if mesh.submeshes.is_empty() {
+        submeshes.clear();
+    }
+
+    Mesh {
+        vertices: used.iter().map(|&index| mesh.vertices[index]).collect(),
+        indices,
+        colors: if mesh.colors.is_empty() {
+            Vec::new()
+        } else {
+            used.iter()
+                .map(|&index| mesh.colors.get(index).copied().unwrap_or([1.0; 4]))
+                .collect()
+        },
+        submeshes,
+        morph_targets: mesh
+            .morph_targets
+            .iter()
+            .map(|target| MorphTarget {
+                name: target.name.clone(),
+                positions: used
+                    .iter()
+                    .map(|&index| target.positions.get(index).copied().unwrap_or_default())
+                    .collect(),
+                normals: used
+                    .iter()
+                    .map(|&index| target.normals.get(index).copied().unwrap_or_default())
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(mesh: &Mesh) -> Vec<Vec3> {
+        mesh.vertices
+            .iter()
+            .map(|vertex| Vec3::from_slice(&vertex.position[..3]))
+            .collect()
+    }
+
+    #[test]
+    fn flat_interiors_collapse_and_borders_stay() {
+        let plane = Mesh::plane(2.0, 7);
+        let simple = simplify(&plane, 0.25);
+        let (before, after) = (plane.triangles().len(), simple.triangles().len());
+        assert_eq!(before, 128);
+        assert!(after < before / 2, "{after} triangles left");
+        assert_eq!(simple.aabb(), plane.aabb());
+        // the vertices left are the plane's own, and nothing turned over
+        let original = positions(&plane);
+        assert!(positions(&simple).iter().all(|p| original.contains(p)));
+        for [a, b, c] in simple.triangles() {
+            assert!((b - a).cross(c - a).y > 0.0);
+        }
+
+        assert_eq!(simplify(&plane, 1.0).triangles().len(), before);
+    }
+
+    #[test]
+    fn curved_surfaces_keep_their_shape() {
+        let sphere = Mesh::sphere(1.0, 32, 16);
+        let simple = simplify(&sphere, 0.3);
+        let (before, after) = (sphere.triangles().len(), simple.triangles().len());
+        assert!(after < before / 2, "{after} of {before} triangles left");
+        assert!(after > before / 10);
+        assert!(
+            positions(&simple)
+                .iter()
+                .all(|p| (p.length() - 1.0).abs() < 1e-5)
+        );
+        assert!(simple.aabb().unwrap().1.abs_diff_eq(Vec3::ONE, 0.05));
+    }
+
+    #[test]
+    fn submeshes_and_colors_carry_over() {
+        let mut plane = Mesh::plane(2.0, 7);
+        let half = plane.element_count() / 2;
+        plane.submeshes = vec![
+            Submesh {
+                range: 0..half,
+                material: 0,
+            },
+            Submesh {
+                range: half..half * 2,
+                material: 1,
+            },
+        ];
+        plane.colors = plane
+            .vertices
+            .iter()
+            .map(|vertex| [vertex.position[0], 0.0, 0.0, 1.0])
+            .collect();
+        let simple = simplify(&plane, 0.25);
+        assert_eq!(
+            simple
+                .submeshes
+                .iter()
+                .map(|submesh| submesh.material)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(simple.submeshes[0].range.start, 0);
+        assert_eq!(
+            simple.submeshes[0].range.end,
+            simple.submeshes[1].range.start
+        );
+        assert_eq!(simple.submeshes[1].range.end, simple.element_count());
+        assert_eq!(simple.colors.len(), simple.vertices.len());
+        for (vertex, color) in simple.vertices.iter().zip(&simple.colors) {
+            assert_eq!(color[0], vertex.position[0]);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// Inverse kinematics on top of the animated pose. Each constraint pulls the
+// end of a bone chain towards a world space target, the two-bone solver
+// analytically for legs and arms and FABRIK iteratively for longer chains.
+// Solvers work out where the joints should be and then rotate each bone
+// onto that, scaled by the weight so the result blends with the animation.
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    skeleton::Skeleton,
+    transform,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IkSolver {
+    // the end bone, its parent and grandparent. the middle joint bends
+    // towards `pole`, in world space, or keeps its current side without one
+    TwoBone {
+        pole: Option<Vec3>,
+    },
+    // every bone from `root` down to the end bone
+    Fabrik {
+        root: usize,
+        iterations: u32,
+        // close enough to stop early
+        tolerance: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IkConstraint {
+    // the bone that should reach the target, a foot or a hand
+    pub end: usize,
+    // in world space
+    pub target: Vec3,
+    pub solver: IkSolver,
+    // 0 keeps the animated pose, 1 is fully solved
+    pub weight: f32,
+}
+
+impl IkConstraint {
+    pub fn two_bone(end: usize, target: Vec3) -> Self {
+        Self {
+            end,
+            target,
+            solver: IkSolver::TwoBone { pole: None },
+            weight: 1.0,
+        }
+    }
+
+    pub fn fabrik(root: usize, end: usize, target: Vec3) -> Self {
+        Self {
+            end,
+            target,
+            solver: IkSolver::Fabrik {
+                root,
+                iterations: 10,
+                tolerance: 0.001,
+            },
+            weight: 1.0,
+        }
+    }
+}
+
+// solved in order, so later constraints see the earlier ones
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IkConstraints(pub Vec<IkConstraint>);
+
+impl Component for IkConstraints {}
+
+pub(crate) fn solve_ik(world: &mut World) {
+    let entities: Vec<Entity> = world
+        .query::<IkConstraints>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for entity in entities {
+        let Some(constraints) = world.get_component::<IkConstraints>(entity).cloned() else {
+            continue;
+        };
+        // targets come in world space, the skeleton works in the entity's
+        let to_model = transform::current_model_matrix(world, entity).inverse();
+        let Some(skeleton) = world.get_component_mut::<Skeleton>(entity) else {
+            continue;
+        };
+        for constraint in constraints.0 {
+            let target = to_model.transform_point3(constraint.target);
+            let weight = constraint.weight.clamp(0.0, 1.0);
+            if constraint.end >= skeleton.bones.len() || weight == 0.0 {
+                continue;
+            }
+            match constraint.solver {
+                IkSolver::TwoBone { pole } => {
+                    let pole = pole.map(|pole| to_model.transform_point3(pole));
+                    solve_two_bone(skeleton, constraint.end, target, pole, weight);
+                }
+                IkSolver::Fabrik {
+                    root,
+                    iterations,
+                    tolerance,
+                } => solve_fabrik(
+                    skeleton,
+                    root,
+                    constraint.end,
+                    target,
+                    iterations,
+                    tolerance,
+                    weight,
+                ),
+            }
+        }
+    }
+}
+
+fn solve_two_bone(
+    skeleton: &mut Skeleton,
+    end: usize,
+    target: Vec3,
+    pole: Option<Vec3>,
+    weight: f32,
+) {
+    let Some(middle) = skeleton.parent(end) else {
+        return;
+    };
+    let Some(root) = skeleton.parent(middle) else {
+        return;
+    };
+    let position = |bone: usize| skeleton.model_pose[bone].translation;
+    let (a, b, c) = (position(root), position(middle), position(end));
+    let (upper, lower) = (a.distance(b), b.distance(c));
+    if upper <= 0.0 || lower <= 0.0 {
+        return;
+    }
+
+    // out of reach stretches straight towards the target
+    let Some(direction) = (target - a).try_normalize() else {
+        return;
+    };
+    let reach = a
+        .distance(target)
+        .clamp((upper - lower).abs() + 0.001, upper + lower - 0.001);
+    // law of cosines for the angle at the root
+    let cos_root =
+        ((upper * upper + reach * reach - lower * lower) / (2.0 * upper * reach)).clamp(-1.0, 1.0);
+    let towards = pole.unwrap_or(b) - a;
+    let bend = (towards - direction * towards.dot(direction))
+        .try_normalize()
+        .unwrap_or_else(|| direction.any_orthonormal_vector());
+    let middle_target = a + direction * (cos_root * upper) + bend * (cos_root.acos().sin() * upper);
+    let end_target = a + direction * reach;
+
+    aim(skeleton, root, middle, middle_target, weight);
+    aim(skeleton, middle, end, end_target, weight);
+}
+
+fn solve_fabrik(
+    skeleton: &mut Skeleton,
+    root: usize,
+    end: usize,
+    target: Vec3,
+    iterations: u32,
+    tolerance: f32,
+    weight: f32,
+) {
+    if root >= skeleton.bones.len() || !skeleton.is_descendant(end, root) || root == end {
+        return;
+    }
+    let mut chain: Vec<usize> = std::iter::successors(Some(end), |&bone| skeleton.parent(bone))
+        .take_while(|&bone| bone != root)
+        .collect();
+    chain.push(root);
+    chain.reverse();
+
+    let mut points: Vec<Vec3> = chain
+        .iter()
+        .map(|&bone| skeleton.model_pose[bone].translation)
+        .collect();
+    let lengths: Vec<f32> = points
+        .windows(2)
+        .map(|pair| pair[0].distance(pair[1]))
+        .collect();
+    let base = points[0];
+    let last = points.len() - 1;
+
+    if base.distance(target) >= lengths.iter().sum::<f32>() {
+        // out of reach, the chain points straight at it
+        let direction = (target - base).normalize_or_zero();
+        for i in 1..points.len() {
+            points[i] = points[i - 1] + direction * lengths[i - 1];
+        }
+    } else {
+        for _ in 0..iterations {
+            if points[last].distance(target) <= tolerance {
+                break;
+            }
+            // backwards from the target, then forwards from the fixed base
+            points[last] = target;
+            for i in (0..last).rev() {
+                let direction = (points[i] - points[i + 1]).normalize_or_zero();
+                points[i] = points[i + 1] + direction * lengths[i];
+            }
+            points[0] = base;
+            for i in 1..points.len() {
+                let direction = (points[i] - points[i - 1]).normalize_or_zero();
+                points[i] = points[i - 1] + direction * lengths[i - 1];
+            }
+        }
+    }
+
+    for i in 0..last {
+        aim(skeleton, chain[i], chain[i + 1], points[i + 1], weight);
+    }
+}
+
+// rotates `bone` and everything below it about its joint so `child` moves towards `goal`
+fn aim(skeleton: &mut Skeleton, bone: usize, child: usize, goal: Vec3, weight: f32) {
+    let pivot = skeleton.model_pose[bone].translation;
+    let (Some(from), Some(to)) = (
+        (skeleton.model_pose[child].translation - pivot).try_normalize(),
+        (goal - pivot).try_normalize(),
+    ) else {
+        return;
+    };
+    let rotation = Quat::IDENTITY.slerp(Quat::from_rotation_arc(from, to), weight);
+    for i in 0..skeleton.bones.len() {
+        if !skeleton.is_descendant(i, bone) {
+            continue;
+        }
+        let model = &mut skeleton.model_pose[i];
+        model.translation = pivot + rotation * (model.translation - pivot);
+        model.rotation = rotation * model.rotation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{skeleton::Bone, transform::Transform};
+
+    // bones straight up the y axis, one unit apart
+    fn chain(count: usize) -> Skeleton {
+        Skeleton::new(
+            (0..count)
+                .map(|i| Bone {
+                    name: format!("bone{i}"),
+                    parent: i.checked_sub(1),
+                    rest: Transform::from_translation(if i == 0 { Vec3::ZERO } else { Vec3::Y }),
+                })
+                .collect(),
+        )
+    }
+
+    fn position(skeleton: &Skeleton, bone: usize) -> Vec3 {
+        skeleton.model_pose[bone].translation
+    }
+
+    fn lengths(skeleton: &Skeleton) -> Vec<f32> {
+        (1..skeleton.bones.len())
+            .map(|bone| position(skeleton, bone).distance(position(skeleton, bone - 1)))
+            .collect()
+    }
+
+    #[test]
+    fn two_bone_bends_towards_the_pole() {
+        let mut skeleton = chain(3);
+        let target = Vec3::new(0.0, 1.0, 1.0);
+        solve_two_bone(&mut skeleton, 2, target, Some(Vec3::X * 5.0), 1.0);
+        assert!(position(&skeleton, 2).abs_diff_eq(target, 1e-3));
+        assert!(
+            position(&skeleton, 1).x > 0.5,
+            "the elbow points at the pole"
+        );
+        for length in lengths(&skeleton) {
+            assert!((length - 1.0).abs() < 1e-4);
+        }
+
+        // out of reach, the arm stretches straight at it
+        let mut skeleton = chain(3);
+        solve_two_bone(&mut skeleton, 2, Vec3::X * 10.0, None, 1.0);
+        assert!(position(&skeleton, 2).abs_diff_eq(Vec3::X * 2.0, 1e-2));
+        assert!(position(&skeleton, 1).abs_diff_eq(Vec3::X, 0.1));
+    }
+
+    #[test]
+    fn weight_blends_with_the_animated_pose() {
+        let mut skeleton = chain(3);
+        let rest = skeleton.model_pose.clone();
+        solve_two_bone(&mut skeleton, 2, Vec3::X * 10.0, None, 0.0);
+        assert_eq!(skeleton.model_pose, rest);
+
+        let mut half = chain(3);
+        solve_two_bone(&mut half, 2, Vec3::X * 10.0, None, 0.5);
+        let mut full = chain(3);
+        solve_two_bone(&mut full, 2, Vec3::X * 10.0, None, 1.0);
+        let hand = position(&half, 2);
+        assert!(hand.x > 0.0 && hand.x < position(&full, 2).x);
+        assert!(hand.y > position(&full, 2).y);
+    }
+
+    #[test]
+    fn fabrik_reaches_along_the_chain() {
+        let mut skeleton = chain(5);
+        let target = Vec3::new(2.0, 2.0, 0.0);
+        solve_fabrik(&mut skeleton, 0, 4, target, 20, 0.001, 1.0);
+        assert!(position(&skeleton, 4).distance(target) < 0.01);
+        assert_eq!(position(&skeleton, 0), Vec3::ZERO);
+        for length in lengths(&skeleton) {
+            assert!((length - 1.0).abs() < 1e-3);
+        }
+
+        // only the bones from the root down move
+        let mut skeleton = chain(5);
+        solve_fabrik(
+            &mut skeleton,
+            2,
+            4,
+            Vec3::new(2.0, 2.0, 0.0),
+            20,
+            0.001,
+            1.0,
+        );
+        assert_eq!(position(&skeleton, 2), Vec3::Y * 2.0);
+        assert!(position(&skeleton, 4).abs_diff_eq(Vec3::new(2.0, 2.0, 0.0), 1e-3));
+
+        // a root that isn't above the end does nothing
+        let mut skeleton = chain(5);
+        let rest = skeleton.model_pose.clone();
+        solve_fabrik(&mut skeleton, 4, 2, Vec3::X, 20, 0.001, 1.0);
+        assert_eq!(skeleton.model_pose, rest);
+    }
+}
@@ -0,0 +1,202 @@
+pub mod ik;
+
+pub use ik::{IkConstraint, IkConstraints, IkSolver};
+
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    transform::{self, Transform},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bone {
+    pub name: String,
+    // index of the parent bone, which has to come before this one
+    pub parent: Option<usize>,
+    // local to the parent
+    pub rest: Transform,
+}
+
+// a bone hierarchy in the entity's local space. animation writes `pose`,
+// update_skeletons turns it into the model space pose and applies ik on top
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    pub bones: Vec<Bone>,
+    // local transform of every bone, starts out at the rest pose
+    pub pose: Vec<Transform>,
+    model_pose: Vec<Transform>,
+}
+
+impl Component for Skeleton {}
+
+impl Skeleton {
+    pub fn new(bones: Vec<Bone>) -> Self {
+        let pose: Vec<Transform> = bones.iter().map(|bone| bone.rest).collect();
+        let mut skeleton = Self {
+            bones,
+            pose,
+            model_pose: Vec::new(),
+        };
+        skeleton.compute_model_pose();
+        skeleton
+    }
+
+    pub fn bone_index(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|bone| bone.name == name)
+    }
+
+    // the bone relative to the skeleton's entity, as of the last update
+    pub fn model_transform(&self, bone: usize) -> Option<Transform> {
+        self.model_pose.get(bone).copied()
+    }
+
+    pub fn model_pose(&self) -> &[Transform] {
+        &self.model_pose
+    }
+
+    pub(crate) fn compute_model_pose(&mut self) {
+        self.model_pose.clear();
+        for (i, bone) in self.bones.iter().enumerate() {
+            let local = self.pose.get(i).copied().unwrap_or(bone.rest);
+            let model = match self.parent(i) {
+                Some(parent) => self.model_pose[parent].mul_transform(&local),
+                None => local,
+            };
+            self.model_pose.push(model);
+        }
+    }
+
+    // parents listed after their children are ignored, which also rules out cycles
+    pub fn parent(&self, bone: usize) -> Option<usize> {
+        self.bones.get(bone)?.parent.filter(|&parent| parent < bone)
+    }
+
+    // whether `bone` is `ancestor` or somewhere below it
+    pub(crate) fn is_descendant(&self, bone: usize, ancestor: usize) -> bool {
+        std::iter::successors(Some(bone), |&bone| self.parent(bone)).any(|b| b == ancestor)
+    }
+}
+
+// keeps the entity on a bone, like a sword in a hand. its Transform is
+// overwritten every frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachedToBone {
+    pub skeleton_entity: Entity,
+    pub bone_name: String,
+    // relative to the bone
+    pub offset: Transform,
+}
+
+impl Component for AttachedToBone {}
+
+impl AttachedToBone {
+    pub fn new(skeleton_entity: Entity, bone_name: impl Into<String>) -> Self {
+        Self {
+            skeleton_entity,
+            bone_name: bone_name.into(),
+            offset: Transform::IDENTITY,
+        }
+    }
+}
+
+// runs after animation so ik sees this frame's pose, and attachments the solved one
+pub fn update_skeletons(world: &mut World) {
+    for (_, skeleton) in world.query_mut::<Skeleton>() {
+        skeleton.compute_model_pose();
+    }
+    ik::solve_ik(world);
+    update_attachments(world);
+}
+
+fn update_attachments(world: &mut World) {
+    let attachments: Vec<(Entity, Transform)> = world
+        .query::<AttachedToBone>()
+        .into_iter()
+        .filter_map(|(entity, attached)| {
+            let skeleton = world.get_component::<Skeleton>(attached.skeleton_entity)?;
+            // a missing bone leaves the entity where it is
+            let bone = skeleton.model_transform(skeleton.bone_index(&attached.bone_name)?)?;
+            let root = transform::current_model_matrix(world, attached.skeleton_entity);
+            let world_matrix = root * bone.compute_matrix() * attached.offset.compute_matrix();
+            // into the space of whatever the attached entity is parented to
+            let parent = transform::parent_matrix(world, entity);
+            Some((
+                entity,
+                Transform::from_matrix(parent.inverse() * world_matrix),
+            ))
+        })
+        .collect();
+
+    for (entity, transform) in attachments {
+        match world.get_component_mut::<Transform>(entity) {
+            Some(current) => *current = transform,
+            None => world.add_component(entity, transform),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::{
+        ecs::schedule::{Last, Update},
+        hierarchy,
+        test_utils::TestWorld,
+        transform::GlobalTransform,
+    };
+
+    fn arm() -> Skeleton {
+        let bone = |name: &str, parent: Option<usize>, y: f32| Bone {
+            name: name.into(),
+            parent,
+            rest: Transform::from_translation(Vec3::new(0.0, y, 0.0)),
+        };
+        Skeleton::new(vec![
+            bone("shoulder", None, 0.0),
+            bone("elbow", Some(0), 1.0),
+            bone("hand", Some(1), 1.0),
+        ])
+    }
+
+    #[test]
+    fn parented_skeletons_solve_and_attach_in_world_space() {
+        let mut test = TestWorld::empty()
+            .with_system(Update, update_skeletons)
+            .with_system(Last, hierarchy::propagate_transforms);
+        let body = test
+            .spawn()
+            .insert(Transform::from_translation(Vec3::new(5.0, 0.0, 0.0)))
+            .id();
+        let target = Vec3::new(6.0, 1.0, 0.0);
+        let skeleton = test
+            .spawn()
+            .insert(Transform::IDENTITY)
+            .insert(arm())
+            .insert(IkConstraints(vec![IkConstraint::two_bone(2, target)]))
+            .id();
+        let holster = test
+            .spawn()
+            .insert(Transform::from_translation(Vec3::new(0.0, 0.0, 3.0)))
+            .id();
+        let sword = test
+            .spawn()
+            .insert(Transform::IDENTITY)
+            .insert(AttachedToBone::new(skeleton, "hand"))
+            .id();
+        hierarchy::set_parent(&mut test.world, skeleton, Some(body));
+        hierarchy::set_parent(&mut test.world, sword, Some(holster));
+        test.ticks(3);
+
+        let hand = test
+            .component::<Skeleton>(skeleton)
+            .model_transform(2)
+            .unwrap();
+        let reached = test
+            .component::<GlobalTransform>(skeleton)
+            .transform_point(hand.translation);
+        assert!(reached.abs_diff_eq(target, 1e-3), "{reached}");
+        let sword = test.component::<GlobalTransform>(sword).translation();
+        assert!(sword.abs_diff_eq(target, 1e-3), "{sword}");
+    }
+}
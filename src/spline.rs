@@ -0,0 +1,545 @@
+// Curves for roads, rails and moving platforms. A Path wraps a spline with an
+// arc length table so things can move along it at a constant speed, and
+// FollowPath moves an entity along another entity's Path.
+
+use glam::{Mat3, Quat, Vec3};
+
+use crate::{
+    color::Color,
+    ecs::{
+        component::Component,
+        entity::{Entity, MapEntities},
+        world::World,
+    },
+    gizmos::Gizmos,
+    time::Time,
+    transform::{self, Transform},
+};
+
+const SAMPLES_PER_SEGMENT: usize = 16;
+
+pub trait Curve {
+    // `t` runs from 0 at the start to 1 at the end of the whole curve
+    fn position(&self, t: f32) -> Vec3;
+    fn velocity(&self, t: f32) -> Vec3;
+    fn segment_count(&self) -> usize;
+}
+
+// `points` are start, control, control, end, control, control, end...
+// so consecutive segments share their end points
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubicBezier {
+    pub points: Vec<Vec3>,
+}
+
+impl CubicBezier {
+    pub fn new(points: Vec<Vec3>) -> Self {
+        Self { points }
+    }
+
+    fn segment(&self, t: f32) -> ([Vec3; 4], f32) {
+        let (index, t) = split(t, self.segment_count());
+        let points = &self.points[index * 3..index * 3 + 4];
+        ([points[0], points[1], points[2], points[3]], t)
+    }
+}
+
+impl Curve for CubicBezier {
+    fn position(&self, t: f32) -> Vec3 {
+        if self.segment_count() == 0 {
+            return self.points.first().copied().unwrap_or_default();
+        }
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let u = 1.0 - t;
+        p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+    }
+
+    fn velocity(&self, t: f32) -> Vec3 {
+        if self.segment_count() == 0 {
+            return Vec3::ZERO;
+        }
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let u = 1.0 - t;
+        let velocity =
+            (p1 - p0) * (3.0 * u * u) + (p2 - p1) * (6.0 * u * t) + (p3 - p2) * (3.0 * t * t);
+        velocity * self.segment_count() as f32
+    }
+
+    fn segment_count(&self) -> usize {
+        self.points.len().saturating_sub(1) / 3
+    }
+}
+
+// passes through every point, closed curves join the last point back to the first
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatmullRom {
+    pub points: Vec<Vec3>,
+    pub closed: bool,
+}
+
+impl CatmullRom {
+    pub fn new(points: Vec<Vec3>) -> Self {
+        Self {
+            points,
+            closed: false,
+        }
+    }
+
+    pub fn closed(points: Vec<Vec3>) -> Self {
+        Self {
+            points,
+            closed: true,
+        }
+    }
+
+    fn point(&self, index: isize) -> Vec3 {
+        let count = self.points.len() as isize;
+        let index = if self.closed {
+            index.rem_euclid(count)
+        } else {
+            // open ends repeat the first and last points
+            index.clamp(0, count - 1)
+        };
+        self.points[index as usize]
+    }
+
+    fn segment(&self, t: f32) -> ([Vec3; 4], f32) {
+        let (index, t) = split(t, self.segment_count());
+        let index = index as isize;
+        (
+            [
+                self.point(index - 1),
+                self.point(index),
+                self.point(index + 1),
+                self.point(index + 2),
+            ],
+            t,
+        )
+    }
+}
+
+impl Curve for CatmullRom {
+    fn position(&self, t: f32) -> Vec3 {
+        if self.segment_count() == 0 {
+            return self.points.first().copied().unwrap_or_default();
+        }
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let (t2, t3) = (t * t, t * t * t);
+        0.5 * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    fn velocity(&self, t: f32) -> Vec3 {
+        if self.segment_count() == 0 {
+            return Vec3::ZERO;
+        }
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let velocity = 0.5
+            * ((p2 - p0)
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * (2.0 * t)
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * (3.0 * t * t));
+        velocity * self.segment_count() as f32
+    }
+
+    fn segment_count(&self) -> usize {
+        match self.points.len() {
+            0 | 1 => 0,
+            count if self.closed => count,
+            count => count - 1,
+        }
+    }
+}
+
+// segment index and the t within it
+fn split(t: f32, segments: usize) -> (usize, f32) {
+    let scaled = t.clamp(0.0, 1.0) * segments as f32;
+    let index = (scaled as usize).min(segments - 1);
+    (index, scaled - index as f32)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Spline {
+    CubicBezier(CubicBezier),
+    CatmullRom(CatmullRom),
+}
+
+impl From<CubicBezier> for Spline {
+    fn from(curve: CubicBezier) -> Self {
+        Self::CubicBezier(curve)
+    }
+}
+
+impl From<CatmullRom> for Spline {
+    fn from(curve: CatmullRom) -> Self {
+        Self::CatmullRom(curve)
+    }
+}
+
+impl Curve for Spline {
+    fn position(&self, t: f32) -> Vec3 {
+        match self {
+            Self::CubicBezier(curve) => curve.position(t),
+            Self::CatmullRom(curve) => curve.position(t),
+        }
+    }
+
+    fn velocity(&self, t: f32) -> Vec3 {
+        match self {
+            Self::CubicBezier(curve) => curve.velocity(t),
+            Self::CatmullRom(curve) => curve.velocity(t),
+        }
+    }
+
+    fn segment_count(&self) -> usize {
+        match self {
+            Self::CubicBezier(curve) => curve.segment_count(),
+            Self::CatmullRom(curve) => curve.segment_count(),
+        }
+    }
+}
+
+// a spline in the entity's local space, measured so it can be walked by distance
+#[derive(Debug, Clone)]
+pub struct Path {
+    spline: Spline,
+    // length from the start at evenly spaced t
+    lengths: Vec<f32>,
+}
+
+impl Component for Path {}
+
+impl Path {
+    pub fn new(spline: impl Into<Spline>) -> Self {
+        let spline = spline.into();
+        let samples = spline.segment_count().max(1) * SAMPLES_PER_SEGMENT;
+        let mut lengths = Vec::with_capacity(samples + 1);
+        let mut length = 0.0;
+        let mut previous = spline.position(0.0);
+        lengths.push(0.0);
+        for i in 1..=samples {
+            let position = spline.position(i as f32 / samples as f32);
+            length += position.distance(previous);
+            lengths.push(length);
+            previous = position;
+        }
+        Self { spline, lengths }
+    }
+
+    pub fn spline(&self) -> &Spline {
+        &self.spline
+    }
+
+    pub fn length(&self) -> f32 {
+        self.lengths.last().copied().unwrap_or(0.0)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(&self.spline, Spline::CatmullRom(curve) if curve.closed)
+    }
+
+    // curve t at `distance` along it, clamped to the ends
+    pub fn t_at_distance(&self, distance: f32) -> f32 {
+        let distance = distance.clamp(0.0, self.length());
+        let index = self.lengths.partition_point(|length| *length < distance);
+        if index == 0 {
+            return 0.0;
+        }
+        let (start, end) = (self.lengths[index - 1], self.lengths[index]);
+        let along = if end > start {
+            (distance - start) / (end - start)
+        } else {
+            0.0
+        };
+        (index as f32 - 1.0 + along) / (self.lengths.len() - 1) as f32
+    }
+
+    pub fn position_at_distance(&self, distance: f32) -> Vec3 {
+        self.spline.position(self.t_at_distance(distance))
+    }
+
+    pub fn tangent_at_distance(&self, distance: f32) -> Vec3 {
+        self.spline
+            .velocity(self.t_at_distance(distance))
+            .normalize_or_zero()
+    }
+
+    // `model` is the path entity's world matrix
+    pub fn draw(&self, gizmos: &mut Gizmos, model: glam::Mat4, color: Color) {
+        let samples = self.lengths.len() - 1;
+        let mut previous = model.transform_point3(self.spline.position(0.0));
+        for i in 1..=samples {
+            let position = model.transform_point3(self.spline.position(i as f32 / samples as f32));
+            gizmos.line(previous, position, color);
+            previous = position;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    // stops at the end
+    #[default]
+    Once,
+    // jumps back to the start, closed paths wrap around seamlessly
+    Loop,
+    // turns around at either end
+    PingPong,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FollowPath {
+    // an entity with a Path
+    pub path: Entity,
+    // units per second, negative runs backwards
+    pub speed: f32,
+    pub loop_mode: LoopMode,
+    // faces -z along the direction of travel
+    pub orient_to_tangent: bool,
+    // how far along the path the entity is
+    pub distance: f32,
+}
+
+impl Component for FollowPath {}
+
+// there's nothing to follow without the path
+impl MapEntities for FollowPath {
+    fn map_entities(&mut self, map: &dyn Fn(Entity) -> Option<Entity>) -> bool {
+        map(self.path).map(|path| self.path = path).is_some()
+    }
+}
+
+impl FollowPath {
+    pub fn new(path: Entity, speed: f32) -> Self {
+        Self {
+            path,
+            speed,
+            loop_mode: LoopMode::Once,
+            orient_to_tangent: false,
+            distance: 0.0,
+        }
+    }
+}
+
+pub fn follow_paths(world: &mut World) {
+    let delta = world.get_resource::<Time>().map_or(0.0, Time::delta);
+    let followers: Vec<(Entity, FollowPath)> = world
+        .query::<FollowPath>()
+        .into_iter()
+        .map(|(entity, follow)| (entity, *follow))
+        .collect();
+
+    for (entity, mut follow) in followers {
+        let Some(path) = world.get_component::<Path>(follow.path) else {
+            continue;
+        };
+        let length = path.length();
+        follow.distance += follow.speed * delta;
+        match follow.loop_mode {
+            LoopMode::Once => follow.distance = follow.distance.clamp(0.0, length),
+            LoopMode::Loop if length > 0.0 => follow.distance = follow.distance.rem_euclid(length),
+            LoopMode::PingPong if length > 0.0 => {
+                if follow.distance > length {
+                    follow.distance = 2.0 * length - follow.distance;
+                    follow.speed = -follow.speed.abs();
+                } else if follow.distance < 0.0 {
+                    follow.distance = -follow.distance;
+                    follow.speed = follow.speed.abs();
+                }
+                follow.distance = follow.distance.clamp(0.0, length);
+            }
+            _ => follow.distance = 0.0,
+        }
+
+        // the path's points are in its own space, the follower's transform in its parent's
+        let model = transform::model_matrix(world, follow.path);
+        let position = model.transform_point3(path.position_at_distance(follow.distance));
+        let direction = model.transform_vector3(path.tangent_at_distance(follow.distance))
+            * follow.speed.signum();
+        let parent = transform::parent_matrix(world, entity);
+        let (_, parent_rotation, _) = parent.to_scale_rotation_translation();
+        let position = parent.inverse().transform_point3(position);
+        let rotation = (follow.orient_to_tangent && direction != Vec3::ZERO)
+            .then(|| parent_rotation.inverse() * look_rotation(direction.normalize()));
+
+        if let Some(component) = world.get_component_mut::<FollowPath>(entity) {
+            *component = follow;
+        }
+        if world.get_component::<Transform>(entity).is_none() {
+            world.add_component(entity, Transform::IDENTITY);
+        }
+        let Some(transform) = world.get_component_mut::<Transform>(entity) else {
+            continue;
+        };
+        transform.translation = position;
+        if let Some(rotation) = rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+// turns -z to `direction`, keeping +y up unless the direction is vertical
+fn look_rotation(direction: Vec3) -> Quat {
+    let up = if direction.y.abs() > 0.999 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    Quat::from_mat3(&Mat3::look_to_rh(direction, up)).inverse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::schedule::{Last, Update},
+        hierarchy,
+        test_utils::TestWorld,
+        transform::GlobalTransform,
+    };
+
+    #[test]
+    fn curves_pass_through_their_ends() {
+        let bezier = CubicBezier::new(vec![
+            Vec3::ZERO,
+            Vec3::Y,
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::X,
+            Vec3::new(2.0, -1.0, 0.0),
+            Vec3::new(3.0, -1.0, 0.0),
+            Vec3::new(3.0, 0.0, 0.0),
+        ]);
+        assert_eq!(bezier.segment_count(), 2);
+        assert_eq!(bezier.position(0.0), Vec3::ZERO);
+        assert_eq!(bezier.position(0.5), Vec3::X);
+        assert_eq!(bezier.position(1.0), Vec3::new(3.0, 0.0, 0.0));
+        // three times the first control arm, times the two segments
+        assert_eq!(bezier.velocity(0.0), Vec3::Y * 6.0);
+
+        let points = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, 1.0), Vec3::Z];
+        let open = CatmullRom::new(points.clone());
+        let closed = CatmullRom::closed(points.clone());
+        assert_eq!((open.segment_count(), closed.segment_count()), (3, 4));
+        for (i, point) in points.iter().enumerate() {
+            assert!(open.position(i as f32 / 3.0).abs_diff_eq(*point, 1e-5));
+            assert!(closed.position(i as f32 / 4.0).abs_diff_eq(*point, 1e-5));
+        }
+        assert!(closed.position(1.0).abs_diff_eq(Vec3::ZERO, 1e-5));
+        assert_eq!(CatmullRom::new(vec![Vec3::Y]).position(0.5), Vec3::Y);
+    }
+
+    #[test]
+    fn paths_are_walked_by_distance() {
+        // control points bunched at the start, so t and distance disagree
+        let path = Path::new(CubicBezier::new(vec![
+            Vec3::ZERO,
+            Vec3::X,
+            Vec3::X * 2.0,
+            Vec3::X * 10.0,
+        ]));
+        assert!((path.length() - 10.0).abs() < 1e-4);
+        for distance in [0.0, 2.5, 5.0, 7.5, 10.0] {
+            let position = path.position_at_distance(distance);
+            assert!(
+                (position.x - distance).abs() < 0.05,
+                "{distance}: {position}"
+            );
+            assert!(
+                path.tangent_at_distance(distance)
+                    .abs_diff_eq(Vec3::X, 1e-5)
+            );
+        }
+        assert_eq!(path.position_at_distance(20.0), Vec3::X * 10.0);
+        assert_eq!(path.t_at_distance(-1.0), 0.0);
+        assert!(!path.is_closed());
+        assert!(Path::new(CatmullRom::closed(vec![Vec3::ZERO, Vec3::X])).is_closed());
+    }
+
+    fn distance_after(loop_mode: LoopMode, speed: f32, ticks: usize) -> FollowPath {
+        let mut test = TestWorld::empty()
+            .with_delta(1.0)
+            .with_system(Update, follow_paths);
+        let path = test
+            .spawn()
+            .insert(Path::new(CatmullRom::new(vec![Vec3::ZERO, Vec3::X * 10.0])))
+            .id();
+        let follower = test
+            .spawn()
+            .insert(FollowPath {
+                loop_mode,
+                ..FollowPath::new(path, speed)
+            })
+            .id();
+        test.ticks(ticks);
+        let follow = *test.component::<FollowPath>(follower);
+        let translation = test.component::<Transform>(follower).translation;
+        // as close as the arc length table gets
+        assert!(
+            translation.abs_diff_eq(Vec3::X * follow.distance, 0.05),
+            "{translation} at {}",
+            follow.distance
+        );
+        follow
+    }
+
+    #[test]
+    fn loop_modes_at_the_ends() {
+        let once = distance_after(LoopMode::Once, 4.0, 3);
+        assert!((once.distance - 10.0).abs() < 1e-4);
+        let looped = distance_after(LoopMode::Loop, 4.0, 3);
+        assert!((looped.distance - 2.0).abs() < 1e-3);
+        let backwards = distance_after(LoopMode::Loop, -4.0, 1);
+        assert!((backwards.distance - 6.0).abs() < 1e-3);
+
+        let turned = distance_after(LoopMode::PingPong, 4.0, 3);
+        assert!((turned.distance - 8.0).abs() < 1e-3);
+        assert_eq!(turned.speed, -4.0);
+        let back = distance_after(LoopMode::PingPong, 4.0, 6);
+        assert!((back.distance - 4.0).abs() < 1e-3);
+        assert_eq!(back.speed, 4.0);
+    }
+
+    #[test]
+    fn parented_followers_land_on_parented_paths() {
+        let mut test = TestWorld::empty()
+            .with_system(Update, follow_paths)
+            .with_system(Last, hierarchy::propagate_transforms);
+        let track = test
+            .spawn()
+            .insert(Transform::from_translation(Vec3::new(10.0, 0.0, 0.0)))
+            .id();
+        let path = test
+            .spawn()
+            .insert(Transform::IDENTITY)
+            .insert(Path::new(CatmullRom::new(vec![Vec3::ZERO, Vec3::Z * 10.0])))
+            .id();
+        let cart = test
+            .spawn()
+            .insert(Transform {
+                translation: Vec3::new(0.0, 5.0, 0.0),
+                rotation: Quat::from_rotation_y(1.0),
+                ..Default::default()
+            })
+            .id();
+        let follower = test
+            .spawn()
+            .insert(Transform::IDENTITY)
+            .insert(FollowPath {
+                orient_to_tangent: true,
+                ..FollowPath::new(path, 0.0)
+            })
+            .id();
+        hierarchy::set_parent(&mut test.world, path, Some(track));
+        hierarchy::set_parent(&mut test.world, follower, Some(cart));
+        test.ticks(3);
+
+        let global = test.component::<GlobalTransform>(follower);
+        assert!(
+            global
+                .translation()
+                .abs_diff_eq(Vec3::new(10.0, 0.0, 0.0), 1e-4)
+        );
+        assert!(global.forward().abs_diff_eq(Vec3::Z, 1e-4));
+    }
+}
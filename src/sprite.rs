@@ -0,0 +1,521 @@
+// 2D sprites. A Sprite is a textured quad in its entity's xy plane facing +z,
+// drawn after the opaque scene with alpha blending. A TextureAtlas cuts a
+// texture into frames and a SpriteAnimation flips through them. Nine-slice
+// and tiled modes keep borders and patterns from stretching. With a
+// Lighting2d resource sprites are lit by Light2d entities in the world xy
+// plane, bumped by their normal maps and shadowed by Occluder2d polygons.
+
+use glam::{UVec2, Vec2};
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    animation::AnimationEvent,
+    assets::Handle,
+    color::Color,
+    ecs::{component::Component, entity::Entity, world::World},
+    importance,
+    spline::LoopMode,
+    texture::Texture,
+    time::Time,
+};
+
+// part of a texture in uv space, v grows downwards like the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl UvRect {
+    pub const FULL: Self = Self {
+        min: Vec2::ZERO,
+        max: Vec2::ONE,
+    };
+
+    // from pixel coordinates in a texture of `texture_size`
+    pub fn from_pixels(texture_size: UVec2, min: UVec2, size: UVec2) -> Self {
+        let texture_size = texture_size.max(UVec2::ONE).as_vec2();
+        Self {
+            min: min.as_vec2() / texture_size,
+            max: (min + size).as_vec2() / texture_size,
+        }
+    }
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextureAtlas {
+    pub frames: Vec<UvRect>,
+}
+
+impl TextureAtlas {
+    // equally sized cells, row by row from the top left
+    pub fn from_grid(columns: u32, rows: u32) -> Self {
+        let cell = Vec2::ONE / UVec2::new(columns, rows).max(UVec2::ONE).as_vec2();
+        let frames = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let min = UVec2::new(column, row).as_vec2() * cell;
+                UvRect {
+                    min,
+                    max: min + cell,
+                }
+            })
+            .collect();
+        Self { frames }
+    }
+
+    // cells of `tile_size` pixels, `margin` in from the edges and `spacing` apart
+    pub fn from_pixel_grid(
+        texture_size: UVec2,
+        tile_size: UVec2,
+        spacing: u32,
+        margin: u32,
+    ) -> Self {
+        let tile_size = tile_size.max(UVec2::ONE);
+        let usable = texture_size.saturating_sub(UVec2::splat(margin * 2)) + spacing;
+        let cells = usable / (tile_size + spacing);
+        let frames = (0..cells.y)
+            .flat_map(|row| (0..cells.x).map(move |column| UVec2::new(column, row)))
+            .map(|cell| {
+                let min = UVec2::splat(margin) + cell * (tile_size + spacing);
+                UvRect::from_pixels(texture_size, min, tile_size)
+            })
+            .collect();
+        Self { frames }
+    }
+
+    pub fn frame(&self, index: usize) -> Option<UvRect> {
+        self.frames.get(index).copied()
+    }
+}
+
+// texture pixels in from each edge of the sprite's rect
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SliceBorder {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl SliceBorder {
+    pub fn all(pixels: u32) -> Self {
+        Self {
+            left: pixels,
+            right: pixels,
+            top: pixels,
+            bottom: pixels,
+        }
+    }
+}
+
+// borders for nine-slice sprites that don't set their own, by texture
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextureSlices(pub FastHashMap<Handle<Texture>, SliceBorder>);
+
+impl Component for TextureSlices {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SpriteMode {
+    // the rect fills the whole size
+    #[default]
+    Stretch,
+    // corners keep their size, edges stretch along them and the middle both ways.
+    // corners shrink together when the sprite is smaller than its borders
+    NineSlice {
+        // None looks the texture up in TextureSlices
+        border: Option<SliceBorder>,
+        // world units per texture pixel of border
+        scale: f32,
+    },
+    // the rect repeats every `tile` world units from the bottom left, cut off at the top and right
+    Tiled {
+        tile: Vec2,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    pub texture: Handle<Texture>,
+    pub rect: UvRect,
+    // multiplied with the texture, linear alpha blends
+    pub color: Color,
+    // in world units before the entity's scale
+    pub size: Vec2,
+    // the point on the sprite at the entity's origin, (0, 0) is the center
+    // and (-0.5, -0.5) the bottom left corner
+    pub anchor: Vec2,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // tangent space, loaded with TextureUsage::NormalMap. only used when lit
+    pub normal_map: Option<Handle<Texture>>,
+    pub mode: SpriteMode,
+}
+
+impl Component for Sprite {}
+
+impl Sprite {
+    pub fn new(texture: Handle<Texture>, size: Vec2) -> Self {
+        Self {
+            texture,
+            rect: UvRect::FULL,
+            color: Color::WHITE,
+            size,
+            anchor: Vec2::ZERO,
+            flip_x: false,
+            flip_y: false,
+            normal_map: None,
+            mode: SpriteMode::Stretch,
+        }
+    }
+}
+
+// insert as a resource to light sprites, without it they're drawn as is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lighting2d {
+    // what unlit and shadowed parts get
+    pub ambient: Color,
+}
+
+impl Component for Lighting2d {}
+
+impl Default for Lighting2d {
+    fn default() -> Self {
+        Self {
+            ambient: Color::srgb(0.1, 0.1, 0.12),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cone2d {
+    // radians from +x in the entity's xy plane
+    pub angle: f32,
+    // radians either side of the angle that are fully lit, then fading to `outer`
+    pub inner: f32,
+    pub outer: f32,
+}
+
+// at the entity's position. only MAX_LIGHTS_2D lights are drawn, the most
+// important ones when the ImportanceBudget is on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light2d {
+    pub color: Color,
+    pub intensity: f32,
+    // nothing is lit past this
+    pub radius: f32,
+    // how far above the sprites the light sits, lower lights graze normal maps more
+    pub height: f32,
+    // None shines all around
+    pub cone: Option<Cone2d>,
+    pub shadows: bool,
+}
+
+impl Component for Light2d {}
+
+impl Light2d {
+    pub fn point(color: Color, radius: f32) -> Self {
+        Self {
+            color,
+            intensity: 1.0,
+            radius,
+            height: radius * 0.25,
+            cone: None,
+            shadows: true,
+        }
+    }
+
+    pub fn cone(color: Color, radius: f32, angle: f32, spread: f32) -> Self {
+        Self {
+            cone: Some(Cone2d {
+                angle,
+                inner: spread * 0.8,
+                outer: spread,
+            }),
+            ..Self::point(color, radius)
+        }
+    }
+}
+
+pub const MAX_LIGHTS_2D: usize = 16;
+// edges of every occluder together, the rest cast no shadows
+pub const MAX_OCCLUDER_EDGES_2D: usize = 256;
+
+// a closed polygon in the entity's xy plane that blocks Light2d. a sprite on
+// the same entity is lit as if the polygon weren't there
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occluder2d {
+    pub points: Vec<Vec2>,
+}
+
+impl Component for Occluder2d {}
+
+impl Occluder2d {
+    pub fn rect(size: Vec2) -> Self {
+        let half = size * 0.5;
+        Self {
+            points: vec![
+                Vec2::new(-half.x, -half.y),
+                Vec2::new(half.x, -half.y),
+                Vec2::new(half.x, half.y),
+                Vec2::new(-half.x, half.y),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteFrame {
+    // into the atlas
+    pub index: usize,
+    // seconds
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteAnimation {
+    pub atlas: TextureAtlas,
+    pub frames: Vec<SpriteFrame>,
+    pub mode: LoopMode,
+    pub playing: bool,
+    pub speed: f32,
+    // (frame, name) sends an AnimationEvent whenever that frame comes up
+    pub events: Vec<(usize, String)>,
+    current: usize,
+    elapsed: f32,
+    // for ping pong, 1 or -1
+    step: isize,
+}
+
+impl Component for SpriteAnimation {}
+
+impl SpriteAnimation {
+    pub fn new(atlas: TextureAtlas, frames: Vec<SpriteFrame>) -> Self {
+        Self {
+            atlas,
+            frames,
+            mode: LoopMode::Loop,
+            playing: true,
+            speed: 1.0,
+            events: Vec::new(),
+            current: 0,
+            elapsed: 0.0,
+            step: 1,
+        }
+    }
+
+    // every frame of the atlas in order at a fixed rate
+    pub fn from_fps(atlas: TextureAtlas, fps: f32) -> Self {
+        let duration = 1.0 / fps.max(0.001);
+        let frames = (0..atlas.frames.len())
+            .map(|index| SpriteFrame { index, duration })
+            .collect();
+        Self::new(atlas, frames)
+    }
+
+    pub fn with_event(mut self, frame: usize, name: impl Into<String>) -> Self {
+        self.events.push((frame, name.into()));
+        self
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.elapsed = 0.0;
+        self.step = 1;
+        self.playing = true;
+    }
+
+    // index into `frames`
+    pub fn current_frame(&self) -> usize {
+        self.current
+    }
+
+    // only Once animations finish, they hold their last frame
+    pub fn is_finished(&self) -> bool {
+        self.mode == LoopMode::Once && !self.playing && self.current + 1 >= self.frames.len()
+    }
+
+    // moves on a frame, false when there's nowhere to go
+    fn next(&mut self) -> bool {
+        let last = self.frames.len() as isize - 1;
+        let next = self.current as isize + self.step;
+        self.current = match self.mode {
+            LoopMode::Once if next > last => return false,
+            LoopMode::Loop if next > last => 0,
+            LoopMode::PingPong if next > last || next < 0 => {
+                self.step = -self.step;
+                (self.current as isize + self.step).clamp(0, last) as usize
+            }
+            _ => next as usize,
+        };
+        true
+    }
+}
+
+pub fn animate_sprites(world: &mut World) {
+    let delta = world.get_resource::<Time>().map_or(0.0, Time::delta);
+    let entities: Vec<Entity> = world
+        .query::<SpriteAnimation>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for entity in entities {
+        // less important ones catch up every few frames
+        let Some(delta) = importance::frame_delta(world, entity, delta) else {
+            continue;
+        };
+        let Some(animation) = world.get_component_mut::<SpriteAnimation>(entity) else {
+            continue;
+        };
+        if animation.frames.is_empty() {
+            continue;
+        }
+        animation.current = animation.current.min(animation.frames.len() - 1);
+
+        let mut events = Vec::new();
+        if animation.playing {
+            animation.elapsed += delta * animation.speed.max(0.0);
+            // a long hitch can skip several frames, each still sends its events
+            loop {
+                let duration = animation.frames[animation.current].duration.max(0.001);
+                if animation.elapsed < duration {
+                    break;
+                }
+                if !animation.next() {
+                    animation.playing = false;
+                    animation.elapsed = 0.0;
+                    break;
+                }
+                animation.elapsed -= duration;
+                let current = animation.current;
+                events.extend(
+                    animation
+                        .events
+                        .iter()
+                        .filter(|(frame, _)| *frame == current)
+                        .map(|(_, name)| name.clone()),
+                );
+            }
+        }
+        let rect = animation
+            .atlas
+            .frame(animation.frames[animation.current].index);
+
+        for name in events {
+            world.send_event(AnimationEvent { entity, name });
+        }
+        if let Some(rect) = rect
+            && let Some(sprite) = world.get_component_mut::<Sprite>(entity)
+        {
+            sprite.rect = rect;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ecs::schedule::Update, test_utils::TestWorld};
+
+    #[test]
+    fn atlases_cut_grids() {
+        let grid = TextureAtlas::from_grid(4, 2);
+        assert_eq!(grid.frames.len(), 8);
+        assert_eq!(
+            grid.frame(5),
+            Some(UvRect {
+                min: Vec2::new(0.25, 0.5),
+                max: Vec2::new(0.5, 1.0),
+            })
+        );
+        assert_eq!(grid.frame(8), None);
+
+        // 16px tiles, 2px apart and 1px in from the edges
+        let size = Vec2::new(56.0, 38.0);
+        let pixels = TextureAtlas::from_pixel_grid(size.as_uvec2(), UVec2::splat(16), 2, 1);
+        assert_eq!(pixels.frames.len(), 6);
+        let last = pixels.frame(5).unwrap();
+        assert!((last.min * size).abs_diff_eq(Vec2::new(37.0, 19.0), 1e-4));
+        assert!((last.max * size).abs_diff_eq(Vec2::new(53.0, 35.0), 1e-4));
+    }
+
+    fn flipbook(mode: LoopMode) -> (TestWorld, Entity) {
+        let mut test = TestWorld::empty()
+            .with_delta(0.1)
+            .with_system(Update, animate_sprites);
+        test.capture_events::<AnimationEvent>();
+        let mut animation =
+            SpriteAnimation::from_fps(TextureAtlas::from_grid(3, 1), 10.0).with_event(2, "step");
+        animation.mode = mode;
+        let entity = test.spawn().insert(animation).id();
+        (test, entity)
+    }
+
+    fn frames(test: &mut TestWorld, entity: Entity, ticks: usize) -> Vec<usize> {
+        (0..ticks)
+            .map(|_| {
+                test.tick();
+                test.component::<SpriteAnimation>(entity).current_frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_modes() {
+        let (mut test, entity) = flipbook(LoopMode::Loop);
+        assert_eq!(frames(&mut test, entity, 5), [1, 2, 0, 1, 2]);
+        assert_eq!(test.events::<AnimationEvent>().len(), 2);
+
+        let (mut test, entity) = flipbook(LoopMode::PingPong);
+        assert_eq!(frames(&mut test, entity, 6), [1, 2, 1, 0, 1, 2]);
+
+        let (mut test, entity) = flipbook(LoopMode::Once);
+        assert_eq!(frames(&mut test, entity, 4), [1, 2, 2, 2]);
+        assert!(test.component::<SpriteAnimation>(entity).is_finished());
+        assert_eq!(test.events::<AnimationEvent>().len(), 1);
+        test.world
+            .get_component_mut::<SpriteAnimation>(entity)
+            .unwrap()
+            .restart();
+        assert_eq!(frames(&mut test, entity, 1), [1]);
+    }
+
+    #[test]
+    fn hitches_skip_frames_but_not_their_events() {
+        let (mut test, entity) = flipbook(LoopMode::Loop);
+        test.tick_by(0.35);
+        assert_eq!(test.component::<SpriteAnimation>(entity).current_frame(), 0);
+        test.assert_event_sent::<AnimationEvent>(|event| {
+            event.entity == entity && event.name == "step"
+        });
+
+        test.world
+            .get_component_mut::<SpriteAnimation>(entity)
+            .unwrap()
+            .pause();
+        assert_eq!(frames(&mut test, entity, 3), [0, 0, 0]);
+        let animation = test
+            .world
+            .get_component_mut::<SpriteAnimation>(entity)
+            .unwrap();
+        animation.play();
+        // twice as fast, two frames a tick
+        animation.speed = 2.0;
+        assert_eq!(frames(&mut test, entity, 2), [2, 1]);
+    }
+}
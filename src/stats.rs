@@ -0,0 +1,321 @@
+// An on-screen readout of how the game is running, added with
+// `app.add_plugin(StatsOverlayPlugin)`. It's one Text entity in a corner: the
+// frame rate, then at more detail a graph of recent frame times, then the
+// entity count, draw calls and memory. `StatsOverlay::cycle_key` steps through
+// the detail levels and off. Text needs a font, it uses `StatsOverlay::font`,
+// or the first one loaded when that's None, and shows nothing without either.
+
+use std::collections::VecDeque;
+
+use ab_glyph::Font as _;
+use glam::Vec2;
+use winit::keyboard::KeyCode;
+
+use crate::{
+    App, Plugin,
+    assets::{Assets, Handle},
+    color::Color,
+    ecs::{component::Component, world::World},
+    input::Input,
+    render::RenderStats,
+    text::{Font, Text, TextLayout, TextMaterial, TextShadow, TextStyle},
+    time::Time,
+    ui::UiScale,
+    window::WindowSettings,
+};
+
+pub struct StatsOverlayPlugin;
+
+impl Plugin for StatsOverlayPlugin {
+    // the system is always registered, it does nothing without the resource
+    fn build(&self, app: &mut App) {
+        app.world_mut().init_resource::<StatsOverlay>();
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatsDetail {
+    Hidden,
+    // frames per second and the average frame time
+    #[default]
+    Fps,
+    // and a graph of the last `StatsOverlay::history` frames
+    Graph,
+    // and entities, draw calls and memory
+    Full,
+}
+
+impl StatsDetail {
+    pub fn next(self) -> Self {
+        match self {
+            StatsDetail::Hidden => StatsDetail::Fps,
+            StatsDetail::Fps => StatsDetail::Graph,
+            StatsDetail::Graph => StatsDetail::Full,
+            StatsDetail::Full => StatsDetail::Hidden,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatsCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug)]
+pub struct StatsOverlay {
+    pub detail: StatsDetail,
+    pub cycle_key: KeyCode,
+    pub corner: StatsCorner,
+    // logical pixels from the window's edges
+    pub margin: f32,
+    pub font: Option<Handle<Font>>,
+    pub text_size: f32,
+    // frames the graph and averages cover
+    pub history: usize,
+    // columns in the graph
+    pub graph_width: usize,
+    // seconds between updates of the text, so the numbers can be read
+    pub refresh_interval: f32,
+    frame_times: VecDeque<f32>,
+    since_refresh: f32,
+}
+
+impl Component for StatsOverlay {}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self {
+            detail: StatsDetail::Fps,
+            cycle_key: KeyCode::F9,
+            corner: StatsCorner::TopLeft,
+            margin: 8.0,
+            font: None,
+            text_size: 14.0,
+            history: 120,
+            graph_width: 40,
+            refresh_interval: 0.25,
+            frame_times: VecDeque::new(),
+            since_refresh: f32::INFINITY,
+        }
+    }
+}
+
+impl StatsOverlay {
+    // seconds, oldest first
+    pub fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+}
+
+// on the overlay's text entity
+#[derive(Debug)]
+pub struct StatsOverlayText;
+
+impl Component for StatsOverlayText {}
+
+// resident set size, from /proc where there is one
+fn process_memory() -> Option<u64> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+        // statm counts pages, 4k everywhere this runs in practice
+        Some(pages * 4096)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    None
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+// one column per bucket of frames, each as tall as the slowest frame in it
+fn graph(frame_times: &VecDeque<f32>, width: usize, blocks: bool) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    const ASCII: [char; 8] = ['_', '.', ',', ':', '-', '=', '+', '#'];
+    let levels = if blocks { &BLOCKS } else { &ASCII };
+    let width = width.clamp(1, frame_times.len().max(1));
+    let bucket = frame_times.len().div_ceil(width).max(1);
+    let columns: Vec<f32> = frame_times
+        .iter()
+        .copied()
+        .collect::<Vec<_>>()
+        .chunks(bucket)
+        .map(|chunk| chunk.iter().copied().fold(0.0, f32::max))
+        .collect();
+    // never taller than a 30 fps frame scaled to the top, so a steady 60 reads as steady
+    let top = columns.iter().copied().fold(1.0 / 30.0, f32::max);
+    columns
+        .into_iter()
+        .map(|time| {
+            let level = (time / top * levels.len() as f32) as usize;
+            levels[level.min(levels.len() - 1)]
+        })
+        .collect()
+}
+
+fn frame_color(seconds: f32) -> Color {
+    if seconds <= 1.0 / 55.0 {
+        Color::srgb(0.4, 1.0, 0.4)
+    } else if seconds <= 1.0 / 28.0 {
+        Color::YELLOW
+    } else {
+        Color::srgb(1.0, 0.35, 0.3)
+    }
+}
+
+pub fn update_stats_overlay(world: &mut World) {
+    let Some(overlay) = world.get_resource::<StatsOverlay>() else {
+        return;
+    };
+    let cycle = !crate::console::is_typing(world)
+        && world
+            .get_resource::<Input>()
+            .is_some_and(|input| input.just_pressed(overlay.cycle_key));
+    let real_delta = world.resource::<Time>().real_delta();
+    let existing = world
+        .query::<StatsOverlayText>()
+        .first()
+        .map(|(entity, _)| *entity);
+
+    let overlay = world.resource_mut::<StatsOverlay>();
+    if cycle {
+        overlay.detail = overlay.detail.next();
+        overlay.since_refresh = f32::INFINITY;
+    }
+    overlay.frame_times.push_back(real_delta);
+    while overlay.frame_times.len() > overlay.history.max(1) {
+        overlay.frame_times.pop_front();
+    }
+    overlay.since_refresh += real_delta;
+    let detail = overlay.detail;
+    let refresh = overlay.since_refresh >= overlay.refresh_interval;
+    if refresh {
+        overlay.since_refresh = 0.0;
+    }
+    // walking the gpu allocations isn't free, only done while they're shown
+    if let Some(stats) = world.get_resource_mut::<RenderStats>() {
+        stats.track_memory = detail == StatsDetail::Full;
+    }
+
+    let overlay = world.resource::<StatsOverlay>();
+    let font = crate::text::font_or_first(world, overlay.font);
+    let Some(font) = font.filter(|_| detail != StatsDetail::Hidden) else {
+        if let Some(entity) = existing {
+            world.despawn(entity);
+        }
+        return;
+    };
+
+    let entity = match existing {
+        Some(entity) => entity,
+        None => world.spawn().insert(StatsOverlayText).id(),
+    };
+    let overlay = world.resource::<StatsOverlay>();
+    if refresh || existing.is_none() {
+        let text = stats_text(world, overlay, font, detail);
+        world.add_component(entity, text);
+    }
+
+    // placed from last frame's layout, the text rarely changes size
+    let overlay = world.resource::<StatsOverlay>();
+    let (corner, margin) = (overlay.corner, overlay.margin);
+    let settings = world.resource::<WindowSettings>();
+    let window = world
+        .resource::<UiScale>()
+        .to_logical(Vec2::new(settings.width as f32, settings.height as f32));
+    let size = world
+        .get_component::<TextLayout>(entity)
+        .map_or(Vec2::ZERO, |layout| layout.size);
+    let position = match corner {
+        StatsCorner::TopLeft => Vec2::splat(margin),
+        StatsCorner::TopRight => Vec2::new(window.x - size.x - margin, margin),
+        StatsCorner::BottomLeft => Vec2::new(margin, window.y - size.y - margin),
+        StatsCorner::BottomRight => window - size - margin,
+    };
+    if let Some(text) = world.get_component_mut::<Text>(entity) {
+        text.position = position;
+    }
+}
+
+fn stats_text(
+    world: &World,
+    overlay: &StatsOverlay,
+    font: Handle<Font>,
+    detail: StatsDetail,
+) -> Text {
+    let style = TextStyle {
+        size: overlay.text_size,
+        ..TextStyle::default()
+    };
+    let count = overlay.frame_times.len().max(1) as f32;
+    let average = overlay.frame_times.iter().sum::<f32>() / count;
+    let worst = overlay.frame_times.iter().copied().fold(0.0, f32::max);
+
+    let mut text = Text::new(
+        font,
+        format!(
+            "{:.0} fps  {:.2} ms",
+            1.0 / average.max(1e-6),
+            average * 1000.0
+        ),
+        TextStyle {
+            color: frame_color(average),
+            ..style
+        },
+    );
+    text.material = TextMaterial {
+        shadow: Some(TextShadow {
+            offset: Vec2::splat(1.0),
+            color: Color::BLACK,
+            softness: 0.0,
+        }),
+        ..TextMaterial::default()
+    };
+
+    if detail != StatsDetail::Fps {
+        // fonts without the block elements get ascii
+        let blocks = world
+            .get_resource::<Assets<Font>>()
+            .and_then(|fonts| fonts.get(font))
+            .is_some_and(|font| font.regular.glyph_id('▅').0 != 0);
+        text = text
+            .with_span(
+                format!(
+                    "\n{}",
+                    graph(&overlay.frame_times, overlay.graph_width, blocks)
+                ),
+                TextStyle {
+                    color: frame_color(worst),
+                    ..style
+                },
+            )
+            .with_span(format!("\nworst {:.2} ms", worst * 1000.0), style);
+    }
+
+    if detail == StatsDetail::Full {
+        let stats = world
+            .get_resource::<RenderStats>()
+            .copied()
+            .unwrap_or_default();
+        // not counting the overlay's own
+        let entities = world.entity_count().saturating_sub(1);
+        let mut lines = format!(
+            "\nentities {}\ndraw calls {}\nmeshes {}  textures {}",
+            entities, stats.draw_calls, stats.meshes, stats.textures
+        );
+        if let Some(memory) = process_memory() {
+            lines += &format!("\nmemory {:.1} MB", megabytes(memory));
+        }
+        if let Some(memory) = stats.gpu_memory {
+            lines += &format!("\ngpu memory {:.1} MB", megabytes(memory));
+        }
+        text = text.with_span(lines, style);
+    }
+    text
+}
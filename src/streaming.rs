@@ -0,0 +1,416 @@
+// Streams big levels in around the camera. The level is split into square cells
+// on the xz plane, each one a scene file named `cell_<x>_<z>.scene` in the
+// streaming directory, and `save_cells` writes them from a world laid out in
+// the editor. Cells within `load_radius` of the main camera are read and parsed
+// off the main thread, then their prefabs are spawned a few per frame so a cell
+// coming in doesn't cost one long frame. Cells past `unload_radius` are
+// despawned the same way, the gap between the radii keeps a camera sitting on
+// a border from loading and unloading the same cell over and over.
+// Prefabs load their meshes and textures as they're spawned, keep
+// `spawns_per_frame` low for ones with heavy assets. Web builds have no
+// filesystem to stream from, every cell fails to read there.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+};
+
+use glam::{IVec2, Vec2, Vec3, Vec3Swizzles};
+use wgpu::naga::FastHashMap;
+
+use crate::{
+    camera::main_camera,
+    ecs::{component::Component, entity::Entity, world::World},
+    editor::scene::{HEADER, parse_scene, write_instance},
+    hierarchy::despawn_recursive,
+    prefab::{PrefabInstance, spawn_prefab},
+    transform::Transform,
+};
+
+#[derive(Debug, Clone)]
+pub struct StreamingSettings {
+    // where the cell files are, None turns streaming off
+    pub directory: Option<PathBuf>,
+    pub cell_size: f32,
+    // from the camera to the nearest edge of a cell
+    pub load_radius: f32,
+    pub unload_radius: f32,
+    // prefabs spawned and despawned each frame, across every cell
+    pub spawns_per_frame: usize,
+}
+
+impl Component for StreamingSettings {}
+
+impl Default for StreamingSettings {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            cell_size: 64.0,
+            load_radius: 128.0,
+            unload_radius: 160.0,
+            spawns_per_frame: 32,
+        }
+    }
+}
+
+// on the entities a cell spawned, they go when it's unloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamedCell(pub IVec2);
+
+impl Component for StreamedCell {}
+
+// sent once all of a cell's prefabs are spawned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellLoaded(pub IVec2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellUnloaded(pub IVec2);
+
+type CellContents = anyhow::Result<Vec<(String, Transform)>>;
+
+enum CellState {
+    Reading(Receiver<CellContents>),
+    Spawning {
+        pending: VecDeque<(String, Transform)>,
+        entities: Vec<Entity>,
+    },
+    Loaded(Vec<Entity>),
+    Unloading(Vec<Entity>),
+}
+
+#[derive(Default)]
+pub struct CellStreamer {
+    cells: FastHashMap<IVec2, CellState>,
+}
+
+impl std::fmt::Debug for CellStreamer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CellStreamer")
+            .field("cells", &self.cells.len())
+            .field("loaded", &self.loaded_cells().len())
+            .finish()
+    }
+}
+
+impl Component for CellStreamer {}
+
+impl CellStreamer {
+    pub fn is_loaded(&self, cell: IVec2) -> bool {
+        matches!(self.cells.get(&cell), Some(CellState::Loaded(_)))
+    }
+
+    pub fn loaded_cells(&self) -> Vec<IVec2> {
+        self.cells
+            .iter()
+            .filter(|(_, state)| matches!(state, CellState::Loaded(_)))
+            .map(|(cell, _)| *cell)
+            .collect()
+    }
+
+    // cells still being read or spawned in
+    pub fn loading(&self) -> usize {
+        self.cells
+            .values()
+            .filter(|state| matches!(state, CellState::Reading(_) | CellState::Spawning { .. }))
+            .count()
+    }
+}
+
+pub fn cell_at(position: Vec3, cell_size: f32) -> IVec2 {
+    (position.xz() / cell_size).floor().as_ivec2()
+}
+
+pub fn cell_path(directory: &Path, cell: IVec2) -> PathBuf {
+    directory.join(format!("cell_{}_{}.scene", cell.x, cell.y))
+}
+
+// from a point on the xz plane to the nearest point of the cell
+fn distance_to_cell(point: Vec2, cell: IVec2, cell_size: f32) -> f32 {
+    let min = cell.as_vec2() * cell_size;
+    point.distance(point.clamp(min, min + cell_size))
+}
+
+// a cell nobody authored has no file, that's an empty cell
+fn read_cell(path: &Path) -> CellContents {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse_scene(&contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn start_reading(path: PathBuf) -> Receiver<CellContents> {
+    let (sender, receiver) = mpsc::channel();
+    let read = move || {
+        // the cell went out of range if nobody's listening
+        let _ = sender.send(read_cell(&path));
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(read);
+    #[cfg(target_arch = "wasm32")]
+    read();
+    receiver
+}
+
+pub(crate) fn stream_cells(world: &mut World) {
+    let settings = world.resource::<StreamingSettings>().clone();
+    let Some(directory) = settings.directory else {
+        return;
+    };
+    let Some(focus) = main_camera(world).map(|camera| camera.pos.xz()) else {
+        return;
+    };
+    let cell_size = settings.cell_size.max(1e-3);
+    world.resource_scope(|world, streamer: &mut CellStreamer| {
+        let center = (focus / cell_size).floor().as_ivec2();
+        let reach = (settings.load_radius / cell_size).ceil() as i32;
+        for x in -reach..=reach {
+            for z in -reach..=reach {
+                let cell = center + IVec2::new(x, z);
+                if !streamer.cells.contains_key(&cell)
+                    && distance_to_cell(focus, cell, cell_size) <= settings.load_radius
+                {
+                    let reader = start_reading(cell_path(&directory, cell));
+                    streamer.cells.insert(cell, CellState::Reading(reader));
+                }
+            }
+        }
+
+        let mut cells: Vec<IVec2> = streamer.cells.keys().copied().collect();
+        // nearest first, so what's in front of the camera is spawned before the rest
+        cells.sort_by(|a, b| {
+            distance_to_cell(focus, *a, cell_size)
+                .total_cmp(&distance_to_cell(focus, *b, cell_size))
+        });
+        let mut budget = settings.spawns_per_frame.max(1);
+        for cell in cells {
+            let Some(state) = streamer.cells.remove(&cell) else {
+                continue;
+            };
+            let out_of_range = distance_to_cell(focus, cell, cell_size) > settings.unload_radius;
+            let state = match state {
+                CellState::Reading(_) if out_of_range => continue,
+                CellState::Spawning { entities, .. } | CellState::Loaded(entities)
+                    if out_of_range =>
+                {
+                    CellState::Unloading(entities)
+                }
+                CellState::Reading(reader) => match reader.try_recv() {
+                    Ok(Ok(instances)) => CellState::Spawning {
+                        pending: instances.into(),
+                        entities: Vec::new(),
+                    },
+                    Ok(Err(e)) => {
+                        log::error!("Unable to read cell {}: {}", cell, e);
+                        CellState::Loaded(Vec::new())
+                    }
+                    Err(TryRecvError::Empty) => CellState::Reading(reader),
+                    Err(TryRecvError::Disconnected) => {
+                        log::error!("Reading cell {} stopped without a result", cell);
+                        CellState::Loaded(Vec::new())
+                    }
+                },
+                state => state,
+            };
+            let state = match state {
+                CellState::Spawning {
+                    mut pending,
+                    mut entities,
+                } => {
+                    while budget > 0
+                        && let Some((name, transform)) = pending.pop_front()
+                    {
+                        budget -= 1;
+                        match spawn_prefab(world, &name, transform) {
+                            Some(entity) => {
+                                world.add_component(entity, StreamedCell(cell));
+                                entities.push(entity);
+                            }
+                            None => log::warn!("Cell {} references unknown prefab {}", cell, name),
+                        }
+                    }
+                    if pending.is_empty() {
+                        world.send_event(CellLoaded(cell));
+                        CellState::Loaded(entities)
+                    } else {
+                        CellState::Spawning { pending, entities }
+                    }
+                }
+                CellState::Unloading(mut entities) => {
+                    while budget > 0
+                        && let Some(entity) = entities.pop()
+                    {
+                        budget -= 1;
+                        despawn_recursive(world, entity);
+                    }
+                    if entities.is_empty() {
+                        world.send_event(CellUnloaded(cell));
+                        continue;
+                    }
+                    CellState::Unloading(entities)
+                }
+                state => state,
+            };
+            streamer.cells.insert(cell, state);
+        }
+    });
+}
+
+// splits the world's prefab instances into cell files, replacing the cells
+// already in the directory. returns how many cells were written
+pub fn save_cells(
+    world: &World,
+    directory: impl AsRef<Path>,
+    cell_size: f32,
+) -> anyhow::Result<usize> {
+    let directory = directory.as_ref();
+    std::fs::create_dir_all(directory)?;
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let is_cell = path
+            .extension()
+            .is_some_and(|extension| extension == "scene")
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("cell_"));
+        if is_cell {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    let mut cells: FastHashMap<IVec2, String> = FastHashMap::default();
+    for (entity, PrefabInstance(name)) in world.query::<PrefabInstance>() {
+        let transform = world
+            .get_component::<Transform>(entity)
+            .copied()
+            .unwrap_or_default();
+        let contents = cells
+            .entry(cell_at(transform.translation, cell_size))
+            .or_insert_with(|| HEADER.to_string());
+        write_instance(contents, name, &transform)?;
+    }
+    for (cell, contents) in &cells {
+        std::fs::write(cell_path(directory, *cell), contents)?;
+    }
+    Ok(cells.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::main_camera_entity, prefab::Prefabs, test_utils::TestWorld};
+
+    fn rock(_: &mut World, _: Entity) {}
+
+    fn rocks(test: &TestWorld) -> Vec<IVec2> {
+        let mut cells: Vec<IVec2> = test
+            .world
+            .query::<StreamedCell>()
+            .into_iter()
+            .map(|(_, cell)| cell.0)
+            .collect();
+        cells.sort_by_key(|cell| (cell.x, cell.y));
+        cells
+    }
+
+    fn move_camera(test: &mut TestWorld, position: Vec3) {
+        let camera = main_camera_entity(&test.world).unwrap();
+        test.world
+            .get_component_mut::<Transform>(camera)
+            .unwrap()
+            .translation = position;
+        // the camera follows its transform at the end of the frame
+        test.tick();
+    }
+
+    // reading happens on other threads, so this waits on them between frames
+    fn settle(test: &mut TestWorld) {
+        for _ in 0..500 {
+            test.tick();
+            let streamer = test.world.resource::<CellStreamer>();
+            if streamer
+                .cells
+                .values()
+                .all(|state| matches!(state, CellState::Loaded(_)))
+            {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("cells never finished streaming");
+    }
+
+    #[test]
+    fn cells_and_distances() {
+        assert_eq!(
+            cell_at(Vec3::new(-1.0, 5.0, 130.0), 64.0),
+            IVec2::new(-1, 2)
+        );
+        assert_eq!(
+            cell_path(Path::new("level"), IVec2::new(-1, 2)),
+            Path::new("level/cell_-1_2.scene")
+        );
+        assert_eq!(
+            distance_to_cell(Vec2::new(10.0, 10.0), IVec2::ZERO, 64.0),
+            0.0
+        );
+        assert_eq!(
+            distance_to_cell(Vec2::new(-3.0, 68.0), IVec2::ZERO, 64.0),
+            5.0
+        );
+    }
+
+    #[test]
+    fn cells_stream_in_and_out_around_the_camera() {
+        let directory = std::env::temp_dir().join("whirlwind_streaming_test");
+        let mut editor = TestWorld::new();
+        editor
+            .world
+            .resource_mut::<Prefabs>()
+            .register("rock", rock);
+        for position in [
+            Vec3::new(10.0, 0.0, 10.0),
+            Vec3::new(20.0, 0.0, -5.0),
+            Vec3::new(300.0, 0.0, 0.0),
+        ] {
+            spawn_prefab(
+                &mut editor.world,
+                "rock",
+                Transform::from_translation(position),
+            );
+        }
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(cell_path(&directory, IVec2::new(9, 9)), "stale").unwrap();
+        assert_eq!(save_cells(&editor.world, &directory, 64.0).unwrap(), 3);
+        assert!(!cell_path(&directory, IVec2::new(9, 9)).exists());
+
+        let mut test = TestWorld::new();
+        test.capture_events::<CellLoaded>();
+        test.capture_events::<CellUnloaded>();
+        test.world.resource_mut::<Prefabs>().register("rock", rock);
+        *test.world.resource_mut::<StreamingSettings>() = StreamingSettings {
+            directory: Some(directory.clone()),
+            cell_size: 64.0,
+            load_radius: 32.0,
+            unload_radius: 96.0,
+            spawns_per_frame: 1,
+        };
+        move_camera(&mut test, Vec3::new(1.0, 0.0, 1.0));
+        settle(&mut test);
+        assert_eq!(rocks(&test), [IVec2::new(0, -1), IVec2::ZERO]);
+        let streamer = test.world.resource::<CellStreamer>();
+        assert!(streamer.is_loaded(IVec2::ZERO) && !streamer.is_loaded(IVec2::new(4, 0)));
+        // cells without a file load empty
+        assert_eq!(streamer.loaded_cells().len(), 4);
+        assert_eq!(test.events::<CellLoaded>().len(), 4);
+
+        move_camera(&mut test, Vec3::new(300.0, 0.0, 10.0));
+        settle(&mut test);
+        assert_eq!(rocks(&test), [IVec2::new(4, 0)]);
+        test.assert_event_sent::<CellUnloaded>(|unloaded| unloaded.0 == IVec2::ZERO);
+        assert_eq!(test.events::<CellUnloaded>().len(), 4);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+// Helpers for testing systems. A TestWorld is the engine's own world without
+// a window or gpu, stepped by hand with a fixed delta. Input can be simulated
+// between ticks, events are kept after the frame that sent them, and the
+// assertions print the component or events they looked at when they fail.
+
+use std::fmt::Debug;
+
+use crate::{
+    ecs::{
+        component::Component,
+        entity::{Entity, EntityWorld},
+        event::Events,
+        schedule::{FixedUpdate, Last, ScheduleLabel, Startup, Ui, Update},
+        system::IntoSystem,
+        world::World,
+    },
+    exit::{AppExit, PendingExit},
+    gizmos::Gizmos,
+    input::Input,
+    time::Time,
+};
+
+type SystemFn = fn(&mut World);
+
+// every event of a type sent since it was captured
+#[derive(Debug)]
+struct CapturedEvents<T> {
+    events: Vec<T>,
+}
+
+impl<T: Debug + 'static> Component for CapturedEvents<T> {}
+
+// copies this frame's events before "last" clears them
+fn capture_events<T: Clone + Debug + 'static>(world: &mut World) {
+    let events: Vec<T> = world
+        .get_resource::<Events<T>>()
+        .map(|events| events.iter().cloned().collect())
+        .unwrap_or_default();
+    if let Some(captured) = world.get_resource_mut::<CapturedEvents<T>>() {
+        captured.events.extend(events);
+    }
+}
+
+pub struct TestWorld {
+    pub world: World,
+    // seconds every tick advances by
+    pub delta: f32,
+    started: bool,
+    captures: Vec<SystemFn>,
+}
+
+impl Default for TestWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestWorld {
+    // the schedules, resources and engine systems an app starts with
+    pub fn new() -> Self {
+        Self {
+            world: crate::create_world(),
+            delta: 1.0 / 60.0,
+            started: false,
+            captures: Vec::new(),
+        }
+    }
+
+    // a world with nothing but time and input, for systems that don't need the
+    // engine's. schedules are added with the first system in them
+    pub fn empty() -> Self {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Input>();
+        Self {
+            world,
+            delta: 1.0 / 60.0,
+            started: false,
+            captures: Vec::new(),
+        }
+    }
+
+    pub fn with_system(mut self, schedule: impl ScheduleLabel, system: impl IntoSystem) -> Self {
+        self.world.add_system_to(schedule, system);
+        self
+    }
+
+    pub fn with_resource<T: Component + 'static>(mut self, resource: T) -> Self {
+        self.world.insert_resource(resource);
+        self
+    }
+
+    pub fn with_delta(mut self, delta: f32) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    pub fn spawn(&mut self) -> EntityWorld<'_> {
+        self.world.spawn()
+    }
+
+    pub fn input(&mut self) -> &mut Input {
+        self.world.resource_mut::<Input>()
+    }
+
+    pub fn time(&mut self) -> &mut Time {
+        self.world.resource_mut::<Time>()
+    }
+
+    // runs "startup", once, the first tick does it otherwise
+    pub fn startup(&mut self) {
+        if !self.started {
+            self.started = true;
+            self.world.run(Startup);
+        }
+    }
+
+    // one frame's schedules in the app's order, with time moved on by `delta`.
+    // input set before the tick is seen by it and cleared after, like a real frame
+    pub fn tick(&mut self) {
+        self.tick_by(self.delta);
+    }
+
+    pub fn ticks(&mut self, count: usize) {
+        for _ in 0..count {
+            self.tick();
+        }
+    }
+
+    pub fn tick_by(&mut self, delta: f32) {
+        self.startup();
+        if let Some(gizmos) = self.world.get_resource_mut::<Gizmos>() {
+            gizmos.clear();
+        }
+        self.world.resource_mut::<Time>().advance(delta);
+        if !self.world.resource::<Time>().is_paused() {
+            self.world.run(Update);
+        }
+        while self.world.resource_mut::<Time>().expend_fixed() {
+            self.world.run(FixedUpdate);
+        }
+        self.world.run(Ui);
+        for capture in &self.captures {
+            capture(&mut self.world);
+        }
+        self.world.run(Last);
+        self.world.resource_mut::<Input>().clear();
+    }
+
+    // what an AppExit sent so far asked for
+    pub fn exit_requested(&self) -> Option<AppExit> {
+        self.world
+            .get_resource::<PendingExit>()
+            .and_then(|pending| pending.exit)
+    }
+
+    // keeps events of this type from now on, registering it if it wasn't
+    pub fn capture_events<T: Clone + Debug + 'static>(&mut self) {
+        if self.world.get_resource::<CapturedEvents<T>>().is_some() {
+            return;
+        }
+        self.world.add_event::<T>();
+        self.world
+            .insert_resource(CapturedEvents::<T> { events: Vec::new() });
+        self.captures.push(capture_events::<T>);
+    }
+
+    // oldest first, empty if they aren't captured
+    pub fn events<T: Clone + Debug + 'static>(&self) -> &[T] {
+        self.world
+            .get_resource::<CapturedEvents<T>>()
+            .map_or(&[], |captured| captured.events.as_slice())
+    }
+
+    pub fn clear_events<T: Clone + Debug + 'static>(&mut self) {
+        if let Some(captured) = self.world.get_resource_mut::<CapturedEvents<T>>() {
+            captured.events.clear();
+        }
+    }
+
+    #[track_caller]
+    pub fn component<T: Component + 'static>(&self, entity: Entity) -> &T {
+        match self.world.get_component::<T>(entity) {
+            Some(component) => component,
+            None => panic!("{:?} has no {}", entity, std::any::type_name::<T>()),
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_component<T: Component + PartialEq + 'static>(
+        &self,
+        entity: Entity,
+        expected: &T,
+    ) {
+        let component = self.component::<T>(entity);
+        assert_eq!(
+            component,
+            expected,
+            "{} on {:?}",
+            std::any::type_name::<T>(),
+            entity
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_component_matches<T: Component + 'static>(
+        &self,
+        entity: Entity,
+        predicate: impl FnOnce(&T) -> bool,
+    ) {
+        let component = self.component::<T>(entity);
+        if !predicate(component) {
+            panic!(
+                "{} on {:?} doesn't match: {:?}",
+                std::any::type_name::<T>(),
+                entity,
+                component
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_no_component<T: Component + 'static>(&self, entity: Entity) {
+        if let Some(component) = self.world.get_component::<T>(entity) {
+            panic!("{:?} still has {:?}", entity, component);
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_event_sent<T: Clone + Debug + 'static>(&self, predicate: impl Fn(&T) -> bool) {
+        let events = self.events::<T>();
+        if !events.iter().any(predicate) {
+            panic!(
+                "No matching {} was sent, got {:?}",
+                std::any::type_name::<T>(),
+                events
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_event_count<T: Clone + Debug + 'static>(&self, count: usize) {
+        let events = self.events::<T>();
+        assert_eq!(
+            events.len(),
+            count,
+            "{} sent: {:?}",
+            std::any::type_name::<T>(),
+            events
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::KeyCode;
+
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Log(Vec<&'static str>);
+
+    impl Component for Log {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Ping(u32);
+
+    fn log(world: &mut World, entry: &'static str) {
+        world.resource_mut::<Log>().0.push(entry);
+    }
+
+    #[test]
+    fn ticks_run_the_schedules_in_frame_order() {
+        let mut test = TestWorld::empty()
+            .with_resource(Log::default())
+            .with_system(Startup, |world: &mut World| log(world, "startup"))
+            .with_system(Last, |world: &mut World| log(world, "last"))
+            .with_system(Ui, |world: &mut World| log(world, "ui"))
+            .with_system(FixedUpdate, |world: &mut World| log(world, "fixed"))
+            .with_system(Update, |world: &mut World| log(world, "update"))
+            .with_delta(1.0 / 30.0);
+        test.ticks(2);
+        let frame = ["update", "fixed", "fixed", "ui", "last"];
+        let expected: Vec<_> = ["startup"].into_iter().chain(frame).chain(frame).collect();
+        assert_eq!(test.world.resource::<Log>().0, expected);
+    }
+
+    #[test]
+    fn input_is_seen_by_one_tick() {
+        let mut test = TestWorld::empty()
+            .with_resource(Log::default())
+            .with_system(Update, |world: &mut World| {
+                if world.resource::<Input>().just_pressed(KeyCode::Space) {
+                    log(world, "jump");
+                }
+            });
+        test.input().press_key(KeyCode::Space);
+        test.ticks(2);
+        assert_eq!(test.world.resource::<Log>().0, ["jump"]);
+    }
+
+    #[test]
+    fn captured_events_outlive_their_frame() {
+        let mut frame = 0;
+        let mut test = TestWorld::empty().with_system(Update, move |world: &mut World| {
+            world.send_event(Ping(frame));
+            frame += 1;
+        });
+        test.capture_events::<Ping>();
+        test.ticks(3);
+        test.assert_event_count::<Ping>(3);
+        test.assert_event_sent::<Ping>(|ping| ping.0 == 2);
+        test.clear_events::<Ping>();
+        test.assert_event_count::<Ping>(0);
+    }
+
+    #[test]
+    fn components_are_asserted_by_value() {
+        let mut test = TestWorld::empty();
+        let entity = test.spawn().insert(Log(vec!["spawned"])).id();
+        test.assert_component(entity, &Log(vec!["spawned"]));
+        test.assert_component_matches::<Log>(entity, |log| log.0.len() == 1);
+        test.world.remove_component::<Log>(entity);
+        test.assert_no_component::<Log>(entity);
+    }
+
+    #[test]
+    fn the_engine_world_ticks_without_a_window() {
+        let mut test = TestWorld::new();
+        test.ticks(3);
+        assert!(test.exit_requested().is_none());
+    }
+}
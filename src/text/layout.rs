@@ -0,0 +1,323 @@
+use ab_glyph::{Font as _, GlyphId, PxScale, ScaleFont as _};
+use glam::Vec2;
+
+use crate::text::{Font, LaidGlyph, Text, TextAlign, TextLayout, TextStyle};
+
+#[derive(Debug, Clone, Copy)]
+struct Item {
+    id: GlyphId,
+    style: TextStyle,
+    // the font's kerning with the glyph before, added before this one is placed
+    kern: f32,
+    advance: f32,
+    space: bool,
+}
+
+enum Token {
+    Word(Vec<Item>),
+    Space(Item),
+    Newline,
+}
+
+fn item(font: &Font, c: char, style: TextStyle, previous: Option<&Item>) -> Item {
+    let face = font.face(style.bold).as_scaled(PxScale::from(style.size));
+    let id = face.glyph_id(c);
+    // kerning only between letters of the same face and size
+    let kern = previous
+        .filter(|previous| previous.style.size == style.size && previous.style.bold == style.bold)
+        .map_or(0.0, |previous| face.kern(previous.id, id));
+    Item {
+        id,
+        style,
+        kern,
+        advance: face.h_advance(id),
+        space: c.is_whitespace(),
+    }
+}
+
+fn tokens(text: &Text, font: &Font) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word: Vec<Item> = Vec::new();
+    for span in &text.spans {
+        for c in span.text.chars() {
+            match c {
+                '\r' => continue,
+                '\n' | ' ' | '\t' => {
+                    if !word.is_empty() {
+                        tokens.push(Token::Word(std::mem::take(&mut word)));
+                    }
+                    match c {
+                        '\n' => tokens.push(Token::Newline),
+                        '\t' => {
+                            let mut tab = item(font, ' ', span.style, None);
+                            tab.advance *= 4.0;
+                            tokens.push(Token::Space(tab));
+                        }
+                        _ => tokens.push(Token::Space(item(font, ' ', span.style, None))),
+                    }
+                }
+                _ => {
+                    let item = item(font, c, span.style, word.last());
+                    word.push(item);
+                }
+            }
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    tokens
+}
+
+fn width(line: &[Item]) -> f32 {
+    line.iter().map(|item| item.kern + item.advance).sum()
+}
+
+// greedy, a word goes on the next line when it doesn't fit and is split
+// between letters when it doesn't fit on a line of its own
+fn wrap(tokens: Vec<Token>, max_width: f32) -> Vec<Vec<Item>> {
+    let mut lines = vec![Vec::new()];
+    let mut spaces: Vec<Item> = Vec::new();
+    for token in tokens {
+        let line = lines.last_mut().unwrap();
+        match token {
+            Token::Newline => {
+                spaces.clear();
+                lines.push(Vec::new());
+            }
+            Token::Space(item) => spaces.push(item),
+            Token::Word(items) => {
+                let current = width(line);
+                if !line.is_empty() && current + width(&spaces) + width(&items) > max_width {
+                    // spaces at a wrap are dropped
+                    lines.push(Vec::new());
+                } else {
+                    line.extend(spaces.iter().copied());
+                }
+                spaces.clear();
+                for item in items {
+                    let line = lines.last_mut().unwrap();
+                    if !line.is_empty() && width(line) + item.kern + item.advance > max_width {
+                        // nothing left to kern against at the start of a line
+                        lines.push(vec![Item { kern: 0.0, ..item }]);
+                    } else {
+                        line.push(item);
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+// the last line ends in an ellipsis, dropping letters until it fits
+fn ellipsize(line: &mut Vec<Item>, font: &Font, style: TextStyle, max_width: f32) {
+    let face = font.face(style.bold).as_scaled(PxScale::from(style.size));
+    let dots = if face.glyph_id('…').0 != 0 {
+        vec![item(font, '…', style, None)]
+    } else {
+        vec![item(font, '.', style, None); 3]
+    };
+    let dots_width = width(&dots);
+    while line
+        .last()
+        .is_some_and(|last| last.space || width(line) + dots_width > max_width)
+    {
+        line.pop();
+    }
+    line.extend(dots);
+}
+
+// lays text out without caching, for measuring text that isn't on an entity
+pub fn layout(text: &Text, font: &Font) -> TextLayout {
+    let first_style = text
+        .spans
+        .first()
+        .map_or_else(TextStyle::default, |span| span.style);
+    let max_width = text.max_width.unwrap_or(f32::INFINITY);
+    let mut lines = wrap(tokens(text, font), max_width);
+
+    let truncated = text.max_lines.is_some_and(|max| lines.len() > max);
+    if let Some(max) = text.max_lines.filter(|_| truncated) {
+        lines.truncate(max);
+        if text.ellipsis
+            && let Some(line) = lines.last_mut()
+        {
+            let style = line.last().map_or(first_style, |item| item.style);
+            ellipsize(line, font, style, max_width);
+        }
+    }
+
+    let widths: Vec<f32> = lines.iter().map(|line| width(line)).collect();
+    let widest = widths.iter().copied().fold(0.0, f32::max);
+    let frame = text.max_width.unwrap_or(widest);
+
+    let mut glyphs = Vec::new();
+    let mut top = 0.0;
+    for (line, line_width) in lines.iter().zip(widths) {
+        // the tallest style on the line sets its height, empty lines use the first span's
+        let styles = line.iter().map(|item| item.style);
+        let (ascent, descent, gap) = styles
+            .chain(line.is_empty().then_some(first_style))
+            .map(|style| {
+                let face = font.face(style.bold).as_scaled(PxScale::from(style.size));
+                (face.ascent(), face.descent(), face.line_gap())
+            })
+            .fold((0.0f32, 0.0f32, 0.0f32), |a, b| {
+                (a.0.max(b.0), a.1.min(b.1), a.2.max(b.2))
+            });
+
+        let mut x = match text.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (frame - line_width) * 0.5,
+            TextAlign::Right => frame - line_width,
+        };
+        let baseline = top + ascent;
+        for item in line {
+            x += item.kern;
+            if !item.space {
+                glyphs.push(LaidGlyph {
+                    id: item.id,
+                    bold: item.style.bold,
+                    size: item.style.size,
+                    position: Vec2::new(x, baseline),
+                    color: item.style.color,
+                });
+            }
+            x += item.advance;
+        }
+        top += (ascent - descent + gap) * text.line_spacing;
+    }
+
+    TextLayout {
+        size: Vec2::new(widest, top),
+        lines: lines.len(),
+        truncated,
+        glyphs,
+        source: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::Assets;
+
+    // monospaced, so every letter has the same advance
+    fn font() -> Font {
+        Font::from_path(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/fonts/DejaVuSansMono.ttf"
+        ))
+        .unwrap()
+    }
+
+    fn text(font: &Font, string: &str) -> Text {
+        let handle = Assets::default().add(font.clone());
+        Text::new(handle, string, TextStyle::default())
+    }
+
+    fn advance(font: &Font) -> f32 {
+        font.face(false)
+            .as_scaled(PxScale::from(16.0))
+            .h_advance(font.face(false).glyph_id('a'))
+    }
+
+    fn line_height(font: &Font) -> f32 {
+        let face = font.face(false).as_scaled(PxScale::from(16.0));
+        face.ascent() - face.descent() + face.line_gap()
+    }
+
+    fn xs(layout: &TextLayout) -> Vec<f32> {
+        layout.glyphs.iter().map(|glyph| glyph.position.x).collect()
+    }
+
+    #[test]
+    fn glyphs_advance_along_the_baseline() {
+        let font = font();
+        let a = advance(&font);
+        let laid = layout(&text(&font, "ab c"), &font);
+        assert_eq!(xs(&laid), [0.0, a, a * 3.0]);
+        let ascent = font.face(false).as_scaled(PxScale::from(16.0)).ascent();
+        assert!(laid.glyphs.iter().all(|glyph| glyph.position.y == ascent));
+        assert_eq!(laid.size, Vec2::new(a * 4.0, line_height(&font)));
+        assert_eq!((laid.lines, laid.truncated), (1, false));
+
+        // bigger spans take more room and make the line taller
+        let big = TextStyle {
+            size: 32.0,
+            ..TextStyle::default()
+        };
+        let laid = layout(&text(&font, "a").with_span("b", big), &font);
+        assert_eq!(xs(&laid), [0.0, a]);
+        assert!((laid.size.x - a * 3.0).abs() < 1e-3);
+        assert!((laid.size.y - line_height(&font) * 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn words_wrap_at_the_max_width() {
+        let font = font();
+        let a = advance(&font);
+        let mut wrapped = text(&font, "aa bb cc");
+        wrapped.max_width = Some(a * 5.5);
+        let laid = layout(&wrapped, &font);
+        assert_eq!(laid.lines, 2);
+        // the space at the wrap is dropped
+        assert_eq!(xs(&laid), [0.0, a, a * 3.0, a * 4.0, 0.0, a]);
+        let below = laid.glyphs[4].position.y - laid.glyphs[0].position.y;
+        assert!((below - line_height(&font)).abs() < 1e-3);
+
+        // a word longer than a line is split between letters
+        let mut long = text(&font, "abcdefgh");
+        long.max_width = Some(a * 3.5);
+        let laid = layout(&long, &font);
+        assert_eq!(laid.lines, 3);
+        assert_eq!(laid.size.x, a * 3.0);
+    }
+
+    #[test]
+    fn line_breaks_and_tabs() {
+        let font = font();
+        let a = advance(&font);
+        let laid = layout(&text(&font, "a\r\n\nb\tc"), &font);
+        assert_eq!(laid.lines, 3);
+        assert_eq!(laid.glyphs.len(), 3);
+        let height = line_height(&font);
+        assert!((laid.size.y - height * 3.0).abs() < 1e-3);
+        assert!(
+            (laid.glyphs[1].position.y - laid.glyphs[0].position.y - height * 2.0).abs() < 1e-3
+        );
+        // a tab is four spaces
+        assert_eq!(xs(&laid), [0.0, 0.0, a * 5.0]);
+
+        let mut spaced = text(&font, "a\nb");
+        spaced.line_spacing = 2.0;
+        assert!((layout(&spaced, &font).size.y - height * 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn alignment_and_ellipsis() {
+        let font = font();
+        let a = advance(&font);
+        let mut aligned = text(&font, "abcd\nab");
+        aligned.max_width = Some(a * 6.0);
+        aligned.align = TextAlign::Right;
+        assert_eq!(xs(&layout(&aligned, &font))[4..], [a * 4.0, a * 5.0]);
+        aligned.align = TextAlign::Center;
+        assert_eq!(xs(&layout(&aligned, &font))[..1], [a]);
+
+        let mut cut = text(&font, "aaaa bbbb cccc");
+        cut.max_width = Some(a * 4.5);
+        cut.max_lines = Some(2);
+        let laid = layout(&cut, &font);
+        assert_eq!((laid.lines, laid.truncated), (2, true));
+        // "bbbb" loses letters until the ellipsis fits
+        assert_eq!(laid.glyphs.len(), 8);
+        assert!(laid.size.x <= a * 4.5);
+        let ellipsis = font.face(false).glyph_id('…');
+        assert_eq!(laid.glyphs.last().unwrap().id, ellipsis);
+
+        cut.ellipsis = false;
+        assert_eq!(layout(&cut, &font).glyphs.len(), 8);
+    }
+}
@@ -0,0 +1,268 @@
+// Text. A Text entity is a block of styled spans drawn over the frame at a
+// position in logical pixels. Layout wraps words to a max width, aligns the
+// lines and cuts to a max line count with an ellipsis, and the result's size
+// is on the entity's TextLayout for ui code to place things around it.
+// Text is drawn from bitmaps rasterized at its exact size, or from signed
+// distance fields that stay sharp at any scale and can be outlined and shadowed.
+// A WorldText next to the Text puts it in the scene at the entity's Transform
+// instead, for nameplates and damage numbers.
+
+mod layout;
+
+pub use layout::layout;
+
+use std::path::Path;
+
+use ab_glyph::FontArc;
+use glam::Vec2;
+
+use crate::{
+    assets::{Assets, Handle},
+    color::Color,
+    ecs::{component::Component, entity::Entity, world::World},
+};
+
+#[derive(Clone, Debug)]
+pub struct Font {
+    pub(crate) regular: FontArc,
+    pub(crate) bold: Option<FontArc>,
+}
+
+impl Font {
+    pub fn from_bytes(bytes: Vec<u8>) -> anyhow::Result<Self> {
+        Ok(Self {
+            regular: FontArc::try_from_vec(bytes)?,
+            bold: None,
+        })
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        crate::error::ensure_exists(path.as_ref())?;
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    // without a bold face bold spans are drawn twice, slightly apart
+    pub fn with_bold(mut self, bytes: Vec<u8>) -> anyhow::Result<Self> {
+        self.bold = Some(FontArc::try_from_vec(bytes)?);
+        Ok(self)
+    }
+
+    pub(crate) fn face(&self, bold: bool) -> &FontArc {
+        match &self.bold {
+            Some(face) if bold => face,
+            _ => &self.regular,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    // logical pixels from the top of the tallest letters to the bottom of the lowest
+    pub size: f32,
+    pub color: Color,
+    pub bold: bool,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            color: Color::WHITE,
+            bold: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+impl TextSpan {
+    pub fn new(text: impl Into<String>, style: TextStyle) -> Self {
+        Self {
+            text: text.into(),
+            style,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextOutline {
+    // logical pixels, at most a few before the distance field runs out
+    pub width: f32,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextShadow {
+    // logical pixels, +y down
+    pub offset: Vec2,
+    pub color: Color,
+    // 0 is a hard edge, up to 1 for a blurry one
+    pub softness: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextMaterial {
+    // one distance field per glyph scaled to every size, instead of a bitmap per size
+    pub sdf: bool,
+    // outlines and shadows come from the distance field, so they turn `sdf` on
+    pub outline: Option<TextOutline>,
+    pub shadow: Option<TextShadow>,
+}
+
+impl TextMaterial {
+    pub fn uses_sdf(&self) -> bool {
+        self.sdf || self.outline.is_some() || self.shadow.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text {
+    pub font: Handle<Font>,
+    pub spans: Vec<TextSpan>,
+    // top left corner in logical pixels from the top left of the window, or
+    // with a WorldText how far the block's center is moved from the entity
+    pub position: Vec2,
+    // words wrap onto the next line past this, and lines align within it
+    pub max_width: Option<f32>,
+    // lines past this are cut, the last one kept ending in an ellipsis if `ellipsis` is set
+    pub max_lines: Option<usize>,
+    pub ellipsis: bool,
+    pub align: TextAlign,
+    // multiplied with the font's own line height
+    pub line_spacing: f32,
+    pub material: TextMaterial,
+}
+
+impl Component for Text {}
+
+impl Text {
+    pub fn new(font: Handle<Font>, text: impl Into<String>, style: TextStyle) -> Self {
+        Self {
+            font,
+            spans: vec![TextSpan::new(text, style)],
+            position: Vec2::ZERO,
+            max_width: None,
+            max_lines: None,
+            ellipsis: true,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+            material: TextMaterial::default(),
+        }
+    }
+
+    pub fn with_span(mut self, text: impl Into<String>, style: TextStyle) -> Self {
+        self.spans.push(TextSpan::new(text, style));
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldTextScale {
+    // world units per logical pixel, shrinking with distance like the rest of the scene
+    World(f32),
+    // the same size on screen at any distance
+    Screen,
+}
+
+// the entity's Text is drawn in the scene, always from distance fields
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldText {
+    // faces the camera, otherwise lies in the transform's xy plane
+    pub billboard: bool,
+    pub scale: WorldTextScale,
+    // skips the depth test, drawn over the scene but under screen text
+    pub always_on_top: bool,
+}
+
+impl Component for WorldText {}
+
+impl Default for WorldText {
+    fn default() -> Self {
+        Self {
+            billboard: true,
+            scale: WorldTextScale::World(0.01),
+            always_on_top: false,
+        }
+    }
+}
+
+// in logical pixels, baseline origin from the text's top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LaidGlyph {
+    pub(crate) id: ab_glyph::GlyphId,
+    pub(crate) bold: bool,
+    pub(crate) size: f32,
+    pub(crate) position: Vec2,
+    pub(crate) color: Color,
+}
+
+// kept up to date on every Text entity at the end of the frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextLayout {
+    // logical pixels
+    pub size: Vec2,
+    pub lines: usize,
+    // whether lines were cut by max_lines
+    pub truncated: bool,
+    pub(crate) glyphs: Vec<LaidGlyph>,
+    // what this was laid out from, to skip unchanged text
+    source: Option<Text>,
+}
+
+impl Component for TextLayout {}
+
+// `font`, or the first one loaded when that's None, for overlays that work
+// without being given one
+pub(crate) fn font_or_first(world: &World, font: Option<Handle<Font>>) -> Option<Handle<Font>> {
+    font.or_else(|| {
+        world
+            .get_resource::<Assets<Font>>()?
+            .iter()
+            .next()
+            .map(|(font, _)| font)
+    })
+}
+
+pub fn layout_text(world: &mut World) {
+    let texts: Vec<(Entity, Text)> = world
+        .query::<Text>()
+        .into_iter()
+        .filter(|(entity, text)| {
+            world
+                .get_component::<TextLayout>(*entity)
+                .is_none_or(|layout| layout.source.as_ref() != Some(*text))
+        })
+        .map(|(entity, text)| (entity, text.clone()))
+        .collect();
+    if texts.is_empty() {
+        return;
+    }
+
+    let Some(fonts) = world.get_resource::<Assets<Font>>() else {
+        return;
+    };
+    let layouts: Vec<(Entity, TextLayout)> = texts
+        .into_iter()
+        .filter_map(|(entity, text)| {
+            let font = fonts.get(text.font)?;
+            let mut layout = layout(&text, font);
+            layout.source = Some(text);
+            Some((entity, layout))
+        })
+        .collect();
+    for (entity, layout) in layouts {
+        world.add_component(entity, layout);
+    }
+}
@@ -1,23 +1,180 @@
 use image::GenericImageView;
 
+use crate::ecs::component::Component;
+
+// what the texels mean, which decides whether the gpu decodes them from sRGB
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureUsage {
+    // albedo, emissive, ui - authored in sRGB
+    #[default]
+    Color,
+    // roughness, metallic, occlusion, masks - sampled as stored
+    Data,
+    // tangent space normals, linear like Data
+    NormalMap,
+}
+
+impl TextureUsage {
+    pub fn format(self) -> wgpu::TextureFormat {
+        match self {
+            TextureUsage::Color => wgpu::TextureFormat::Rgba8UnormSrgb,
+            TextureUsage::Data | TextureUsage::NormalMap => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Texture {
     #[allow(unused)]
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
+    pub usage: TextureUsage,
 }
 
+impl Component for Texture {}
+
 impl Texture {
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        label: &str,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width.max(1),
+            height: config.height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            usage: TextureUsage::Data,
+        }
+    }
+
+    // drawn into by the renderer and sampleable by materials, `format` has to
+    // match the pipelines drawing into it, usually RenderDevice::surface_format
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            usage: TextureUsage::Color,
+        }
+    }
+
+    // writable from compute shaders and sampleable by materials afterwards
+    pub fn create_storage_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            usage: TextureUsage::Data,
+        }
+    }
+
     pub fn from_path(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         path: impl Into<std::path::PathBuf> + Copy,
+        usage: TextureUsage,
     ) -> anyhow::Result<Self> {
+        crate::error::ensure_exists(path)?;
         let img = image::open(path.into())?;
         Self::from_image(
             device,
             queue,
             &img,
+            usage,
             Some(
                 path.into()
                     .file_name()
@@ -32,16 +189,40 @@ impl Texture {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         bytes: &[u8],
+        usage: TextureUsage,
         label: &str,
     ) -> anyhow::Result<Self> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, Some(label))
+        Self::from_image(device, queue, &img, usage, Some(label))
+    }
+
+    // squares of two colors, `cells` across each side
+    pub fn checkerboard(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        colors: [[u8; 4]; 2],
+        cells: u32,
+        usage: TextureUsage,
+    ) -> anyhow::Result<Self> {
+        let cell_size = 8;
+        let size = cells.max(1) * cell_size;
+        let image = image::RgbaImage::from_fn(size, size, |x, y| {
+            image::Rgba(colors[((x / cell_size + y / cell_size) % 2) as usize])
+        });
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(image),
+            usage,
+            Some("checkerboard_texture"),
+        )
     }
 
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
+        usage: TextureUsage,
         label: Option<&str>,
     ) -> anyhow::Result<Self> {
         let rgba = img.to_rgba8();
@@ -58,7 +239,7 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format: usage.format(),
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
@@ -94,6 +275,7 @@ impl Texture {
             texture,
             view,
             sampler,
+            usage,
         })
     }
 }
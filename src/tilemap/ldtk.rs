@@ -0,0 +1,204 @@
+// LDtk projects. Reads one level's tile, auto and IntGrid layers, loading the
+// tilesets they use. LDtk has no per-tile collision shapes, instead non-zero
+// IntGrid cells are solid. Every layer is assumed to share one grid size.
+
+use std::{collections::HashMap, path::Path};
+
+use glam::{UVec2, Vec2};
+use serde_json::Value;
+
+use crate::{
+    assets::Handle,
+    sprite::TextureAtlas,
+    texture::Texture,
+    tilemap::{Tile, TileLayer, Tilemap, Tileset},
+};
+
+const FLIP_X: u64 = 1;
+const FLIP_Y: u64 = 2;
+
+// `level` by identifier, the first level without one
+pub fn load(
+    path: &Path,
+    level: Option<&str>,
+    tile_size: Vec2,
+    load_texture: &mut impl FnMut(&Path) -> anyhow::Result<Handle<Texture>>,
+) -> anyhow::Result<Tilemap> {
+    crate::error::ensure_exists(path)?;
+    let project: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let levels = array(&project, "levels")?;
+    let level = match level {
+        Some(name) => levels
+            .iter()
+            .find(|level| level["identifier"] == name)
+            .ok_or(anyhow::anyhow!("No level named {name}"))?,
+        None => levels
+            .first()
+            .ok_or(anyhow::anyhow!("{} has no levels", path.display()))?,
+    };
+    // levels saved in their own files
+    let external;
+    let level = match level["externalRelPath"].as_str() {
+        Some(relative) if level["layerInstances"].is_null() => {
+            external =
+                serde_json::from_str::<Value>(&std::fs::read_to_string(directory.join(relative))?)?;
+            &external
+        }
+        _ => level,
+    };
+
+    let mut tilemap = Tilemap::new(tile_size);
+    // tileset uid to index in tilemap.tilesets
+    let mut tilesets = HashMap::new();
+    for definition in array(&project["defs"], "tilesets")? {
+        // embedded atlases have no image on disk
+        let Some(relative) = definition["relPath"].as_str() else {
+            continue;
+        };
+        let texture_size = UVec2::new(uint(definition, "pxWid")?, uint(definition, "pxHei")?);
+        let grid = uint(definition, "tileGridSize")?;
+        tilesets.insert(uint(definition, "uid")?, tilemap.tilesets.len() as u32);
+        tilemap.tilesets.push(Tileset {
+            name: definition["identifier"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            texture: load_texture(&directory.join(relative))?,
+            atlas: TextureAtlas::from_pixel_grid(
+                texture_size,
+                UVec2::splat(grid),
+                uint(definition, "spacing")?,
+                uint(definition, "padding")?,
+            ),
+            shapes: HashMap::new(),
+        });
+    }
+
+    // ldtk lists layers top first
+    for instance in array(level, "layerInstances")?.iter().rev() {
+        if instance["__type"] == "Entities" {
+            continue;
+        }
+        let size = UVec2::new(uint(instance, "__cWid")?, uint(instance, "__cHei")?);
+        let grid = uint(instance, "__gridSize")?.max(1);
+        let mut layer = TileLayer::new(instance["__identifier"].as_str().unwrap_or_default(), size);
+        layer.visible = instance["visible"].as_bool().unwrap_or(true);
+        layer.opacity = instance["__opacity"].as_f64().unwrap_or(1.0) as f32;
+        layer.int_grid = instance["intGridCsv"]
+            .as_array()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|cell| cell.as_i64().unwrap_or_default() as i32)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let tileset = instance["__tilesetDefUid"]
+            .as_u64()
+            .and_then(|uid| tilesets.get(&(uid as u32)).copied());
+        let tiles = ["gridTiles", "autoLayerTiles"]
+            .iter()
+            .filter_map(|key| instance[key].as_array())
+            .flatten();
+        for tile in tiles {
+            let Some(tileset) = tileset else {
+                break;
+            };
+            let (Some(x), Some(y)) = (tile["px"][0].as_u64(), tile["px"][1].as_u64()) else {
+                continue;
+            };
+            let flip = tile["f"].as_u64().unwrap_or_default();
+            layer.set(
+                UVec2::new(x as u32, y as u32) / grid,
+                Some(Tile {
+                    tileset,
+                    index: uint(tile, "t")?,
+                    flip_x: flip & FLIP_X != 0,
+                    flip_y: flip & FLIP_Y != 0,
+                }),
+            );
+        }
+        tilemap.layers.push(layer);
+    }
+    Ok(tilemap)
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> anyhow::Result<&'a Vec<Value>> {
+    value[key]
+        .as_array()
+        .ok_or(anyhow::anyhow!("LDtk project is missing {key}"))
+}
+
+fn uint(value: &Value, key: &str) -> anyhow::Result<u32> {
+    value[key]
+        .as_u64()
+        .map(|value| value as u32)
+        .ok_or(anyhow::anyhow!("LDtk project is missing {key}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_layers_and_int_grids() {
+        let path = std::env::temp_dir().join("whirlwind_level.ldtk");
+        let project = serde_json::json!({
+            "defs": { "tilesets": [
+                { "uid": 7, "identifier": "Cavern", "relPath": "cavern.png", "pxWid": 48,
+                  "pxHei": 16, "tileGridSize": 16, "spacing": 0, "padding": 0 },
+                { "uid": 8, "identifier": "Embedded" }
+            ] },
+            "levels": [
+                { "identifier": "Intro", "layerInstances": [] },
+                { "identifier": "Cave", "layerInstances": [
+                    { "__type": "Entities", "__identifier": "Spawns" },
+                    { "__type": "Tiles", "__identifier": "Walls", "__cWid": 2, "__cHei": 2,
+                      "__gridSize": 16, "__opacity": 0.5, "__tilesetDefUid": 7,
+                      "gridTiles": [
+                          { "px": [16, 0], "t": 2, "f": 1 },
+                          { "px": [0, 16], "t": 1, "f": 2 }
+                      ] },
+                    { "__type": "IntGrid", "__identifier": "Collision", "__cWid": 2,
+                      "__cHei": 2, "__gridSize": 16, "visible": false,
+                      "intGridCsv": [0, 1, 1, 0] }
+                ] }
+            ]
+        });
+        std::fs::write(&path, project.to_string()).unwrap();
+        let mut textures = Vec::new();
+        let mut load_texture = |texture: &Path| {
+            textures.push(texture.file_name().unwrap().to_owned());
+            Ok(Handle::dangling(0))
+        };
+        let tilemap = load(&path, Some("Cave"), Vec2::ONE, &mut load_texture).unwrap();
+        let first = load(&path, None, Vec2::ONE, &mut load_texture).unwrap();
+        let missing = load(&path, Some("Boss"), Vec2::ONE, &mut load_texture);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(textures, ["cavern.png"; 2]);
+        assert_eq!(tilemap.tilesets.len(), 1);
+        assert_eq!(tilemap.tilesets[0].atlas.frames.len(), 3);
+        // bottom layer first, entity layers skipped
+        let names: Vec<&str> = tilemap
+            .layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect();
+        assert_eq!(names, ["Collision", "Walls"]);
+        let walls = &tilemap.layers[1];
+        assert_eq!(walls.opacity, 0.5);
+        let tile = walls.get(UVec2::new(1, 0)).unwrap();
+        assert!(tile.index == 2 && tile.flip_x && !tile.flip_y);
+        let tile = walls.get(UVec2::new(0, 1)).unwrap();
+        assert!(tile.index == 1 && !tile.flip_x && tile.flip_y);
+        assert!(!tilemap.layers[0].visible);
+        assert_eq!(tilemap.collision_shapes(0).len(), 2);
+
+        assert!(first.layers.is_empty());
+        assert!(missing.is_err());
+    }
+}
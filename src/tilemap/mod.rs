@@ -0,0 +1,321 @@
+// Tilemaps. A Tilemap asset holds tilesets and layers of tiles, and an
+// entity with a Handle<Tilemap> draws it in its xy plane: tile (0, 0) is at
+// the origin and the map grows along +x and down -y, like the map in the
+// editor it came from. Layers are drawn in order in chunks of CHUNK_SIZE
+// tiles, each rebuilt on the gpu only when one of its tiles changes.
+
+pub mod ldtk;
+pub mod tmx;
+
+use std::{collections::HashMap, path::Path};
+
+use glam::{UVec2, Vec2};
+
+use crate::{
+    assets::{Assets, Handle},
+    ecs::world::World,
+    render::RenderDevice,
+    sprite::TextureAtlas,
+    texture::{Texture, TextureUsage},
+};
+
+// tiles per chunk side
+pub const CHUNK_SIZE: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    // into the map's tilesets
+    pub tileset: u32,
+    // into the tileset's atlas
+    pub index: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Tile {
+    pub fn new(tileset: u32, index: u32) -> Self {
+        Self {
+            tileset,
+            index,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+}
+
+// in tile units from the tile's top left corner, y down
+#[derive(Debug, Clone, PartialEq)]
+pub enum TileShape {
+    Rect { min: Vec2, max: Vec2 },
+    Polygon(Vec<Vec2>),
+}
+
+impl TileShape {
+    pub const FULL: Self = Self::Rect {
+        min: Vec2::ZERO,
+        max: Vec2::ONE,
+    };
+
+    fn offset(&self, by: Vec2) -> Self {
+        match self {
+            Self::Rect { min, max } => Self::Rect {
+                min: *min + by,
+                max: *max + by,
+            },
+            Self::Polygon(points) => {
+                Self::Polygon(points.iter().map(|point| *point + by).collect())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tileset {
+    pub name: String,
+    pub texture: Handle<Texture>,
+    pub atlas: TextureAtlas,
+    // collision shapes of the tiles that have any, by index
+    pub shapes: HashMap<u32, Vec<TileShape>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    size: UVec2,
+    tiles: Vec<Option<Tile>>,
+    // ldtk int grid values, empty when the layer has none. non-zero cells are solid
+    pub int_grid: Vec<i32>,
+    // bumped whenever a tile in the chunk changes, so the renderer knows to rebuild it
+    revisions: Vec<u32>,
+}
+
+impl TileLayer {
+    pub fn new(name: impl Into<String>, size: UVec2) -> Self {
+        let chunks = (size + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        Self {
+            name: name.into(),
+            visible: true,
+            opacity: 1.0,
+            size,
+            tiles: vec![None; (size.x * size.y) as usize],
+            int_grid: Vec::new(),
+            revisions: vec![0; (chunks.x * chunks.y) as usize],
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn chunks(&self) -> UVec2 {
+        (self.size + CHUNK_SIZE - 1) / CHUNK_SIZE
+    }
+
+    fn index(&self, position: UVec2) -> Option<usize> {
+        (position.x < self.size.x && position.y < self.size.y)
+            .then(|| (position.y * self.size.x + position.x) as usize)
+    }
+
+    pub fn get(&self, position: UVec2) -> Option<Tile> {
+        self.tiles[self.index(position)?]
+    }
+
+    // false when the position is outside the layer
+    pub fn set(&mut self, position: UVec2, tile: Option<Tile>) -> bool {
+        let Some(index) = self.index(position) else {
+            return false;
+        };
+        if self.tiles[index] != tile {
+            self.tiles[index] = tile;
+            let (chunk, columns) = (position / CHUNK_SIZE, self.chunks().x);
+            self.revisions[(chunk.y * columns + chunk.x) as usize] += 1;
+        }
+        true
+    }
+
+    pub(crate) fn chunk_revision(&self, chunk: UVec2) -> u32 {
+        self.revisions[(chunk.y * self.chunks().x + chunk.x) as usize]
+    }
+
+    pub(crate) fn chunk_tiles(&self, chunk: UVec2) -> impl Iterator<Item = (UVec2, Tile)> + '_ {
+        let min = chunk * CHUNK_SIZE;
+        let max = (min + CHUNK_SIZE).min(self.size);
+        (min.y..max.y)
+            .flat_map(move |y| (min.x..max.x).map(move |x| UVec2::new(x, y)))
+            .filter_map(|position| Some((position, self.get(position)?)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tilemap {
+    // world units per tile
+    pub tile_size: Vec2,
+    pub tilesets: Vec<Tileset>,
+    // drawn first to last
+    pub layers: Vec<TileLayer>,
+}
+
+impl Tilemap {
+    pub fn new(tile_size: Vec2) -> Self {
+        Self {
+            tile_size,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn layer(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    pub fn get_tile(&self, layer: usize, position: UVec2) -> Option<Tile> {
+        self.layers.get(layer)?.get(position)
+    }
+
+    // false when the layer or position doesn't exist
+    pub fn set_tile(&mut self, layer: usize, position: UVec2, tile: Option<Tile>) -> bool {
+        self.layers
+            .get_mut(layer)
+            .is_some_and(|layer| layer.set(position, tile))
+    }
+
+    // the tile under a point in the map entity's local space
+    pub fn tile_at(&self, local: Vec2) -> Option<UVec2> {
+        let tile = (Vec2::new(local.x, -local.y) / self.tile_size).floor();
+        (tile.x >= 0.0 && tile.y >= 0.0).then(|| tile.as_uvec2())
+    }
+
+    // the local position of a tile's top left corner
+    pub fn tile_position(&self, tile: UVec2) -> Vec2 {
+        Vec2::new(tile.x as f32, -(tile.y as f32)) * self.tile_size
+    }
+
+    // every collision shape of a layer in tile units from the map's top left, y down.
+    // shapes come from the tilesets and from solid int grid cells
+    pub fn collision_shapes(&self, layer: usize) -> Vec<TileShape> {
+        let Some(layer) = self.layers.get(layer) else {
+            return Vec::new();
+        };
+        let mut shapes = Vec::new();
+        for (i, tile) in layer.tiles.iter().enumerate() {
+            let position = UVec2::new(i as u32 % layer.size.x, i as u32 / layer.size.x).as_vec2();
+            if let Some(tile) = tile
+                && let Some(tile_shapes) = self
+                    .tilesets
+                    .get(tile.tileset as usize)
+                    .and_then(|tileset| tileset.shapes.get(&tile.index))
+            {
+                shapes.extend(tile_shapes.iter().map(|shape| shape.offset(position)));
+            }
+            if layer.int_grid.get(i).is_some_and(|value| *value != 0) {
+                shapes.push(TileShape::FULL.offset(position));
+            }
+        }
+        shapes
+    }
+}
+
+// loads a tileset image through the world's render device
+pub fn load_tileset_texture(world: &mut World, path: &Path) -> anyhow::Result<Handle<Texture>> {
+    let RenderDevice { device, queue, .. } = world
+        .get_resource::<RenderDevice>()
+        .ok_or(anyhow::anyhow!("Render device is not ready"))?
+        .clone();
+    let texture = Texture::from_path(&device, &queue, path, TextureUsage::Color)?;
+    Ok(world.resource_mut::<Assets<Texture>>().add(texture))
+}
+
+// a .tmx or .ldtk file, the first level of an ldtk project
+pub fn load_tilemap(
+    world: &mut World,
+    path: impl AsRef<Path>,
+    tile_size: Vec2,
+) -> anyhow::Result<Handle<Tilemap>> {
+    let path = path.as_ref();
+    let mut load_texture = |path: &Path| load_tileset_texture(world, path);
+    let tilemap = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tmx") => tmx::load(path, tile_size, &mut load_texture)?,
+        Some("ldtk") => ldtk::load(path, None, tile_size, &mut load_texture)?,
+        _ => anyhow::bail!("Unknown tilemap format {}", path.display()),
+    };
+    Ok(world.resource_mut::<Assets<Tilemap>>().add(tilemap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changed_chunks_are_rebuilt() {
+        let mut layer = TileLayer::new("ground", UVec2::new(20, 5));
+        assert_eq!(layer.chunks(), UVec2::new(2, 1));
+        assert!(layer.set(UVec2::new(17, 4), Some(Tile::new(0, 3))));
+        assert!(!layer.set(UVec2::new(20, 0), Some(Tile::new(0, 3))));
+        assert_eq!(layer.get(UVec2::new(17, 4)), Some(Tile::new(0, 3)));
+        assert_eq!(layer.get(UVec2::new(0, 5)), None);
+        assert_eq!(layer.chunk_revision(UVec2::ZERO), 0);
+        assert_eq!(layer.chunk_revision(UVec2::new(1, 0)), 1);
+        // setting the same tile again changes nothing
+        layer.set(UVec2::new(17, 4), Some(Tile::new(0, 3)));
+        assert_eq!(layer.chunk_revision(UVec2::new(1, 0)), 1);
+        let tiles: Vec<_> = layer.chunk_tiles(UVec2::new(1, 0)).collect();
+        assert_eq!(tiles, [(UVec2::new(17, 4), Tile::new(0, 3))]);
+    }
+
+    #[test]
+    fn tiles_grow_down_from_the_origin() {
+        let mut tilemap = Tilemap::new(Vec2::new(2.0, 1.0));
+        tilemap
+            .layers
+            .push(TileLayer::new("ground", UVec2::splat(4)));
+        assert_eq!(
+            tilemap.tile_position(UVec2::new(3, 2)),
+            Vec2::new(6.0, -2.0)
+        );
+        assert_eq!(
+            tilemap.tile_at(Vec2::new(6.5, -2.5)),
+            Some(UVec2::new(3, 2))
+        );
+        assert_eq!(tilemap.tile_at(Vec2::new(-0.5, -1.0)), None);
+        assert_eq!(tilemap.tile_at(Vec2::new(1.0, 0.5)), None);
+        assert!(tilemap.set_tile(0, UVec2::new(1, 1), Some(Tile::new(0, 1))));
+        assert!(!tilemap.set_tile(1, UVec2::new(1, 1), Some(Tile::new(0, 1))));
+        assert_eq!(tilemap.get_tile(0, UVec2::new(1, 1)), Some(Tile::new(0, 1)));
+        assert_eq!(tilemap.layer("ground"), Some(0));
+    }
+
+    #[test]
+    fn collision_shapes_from_tiles_and_int_grids() {
+        let mut tilemap = Tilemap::new(Vec2::ONE);
+        let slope = TileShape::Polygon(vec![Vec2::Y, Vec2::ONE, Vec2::X]);
+        tilemap.tilesets.push(Tileset {
+            name: "terrain".into(),
+            texture: Handle::dangling(0),
+            atlas: TextureAtlas::from_grid(2, 1),
+            shapes: HashMap::from([(1, vec![slope])]),
+        });
+        let mut layer = TileLayer::new("ground", UVec2::new(3, 2));
+        layer.set(UVec2::new(2, 0), Some(Tile::new(0, 1)));
+        layer.set(UVec2::new(0, 0), Some(Tile::new(0, 0)));
+        layer.int_grid = vec![0, 0, 0, 1, 0, 0];
+        tilemap.layers.push(layer);
+
+        assert_eq!(
+            tilemap.collision_shapes(0),
+            [
+                TileShape::Polygon(vec![
+                    Vec2::new(2.0, 1.0),
+                    Vec2::new(3.0, 1.0),
+                    Vec2::new(3.0, 0.0)
+                ]),
+                TileShape::Rect {
+                    min: Vec2::new(0.0, 1.0),
+                    max: Vec2::new(1.0, 2.0),
+                },
+            ]
+        );
+        assert!(tilemap.collision_shapes(1).is_empty());
+    }
+}
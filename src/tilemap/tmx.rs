@@ -0,0 +1,345 @@
+// Tiled maps. Reads orthogonal .tmx files with inline or external .tsx
+// tilesets, csv or xml layer data, and the per-tile collision rectangles and
+// polygons drawn in Tiled's collision editor. Base64 and compressed layer
+// data and infinite maps aren't supported, save as csv instead.
+
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use glam::{UVec2, Vec2};
+use roxmltree::Node;
+
+use crate::{
+    assets::Handle,
+    error::WhirlwindError,
+    sprite::TextureAtlas,
+    texture::Texture,
+    tilemap::{Tile, TileLayer, TileShape, Tilemap, Tileset},
+};
+
+const FLIP_X: u32 = 0x8000_0000;
+const FLIP_Y: u32 = 0x4000_0000;
+// diagonal flips and hexagonal rotations aren't supported, only masked out
+const FLAGS: u32 = 0xf000_0000;
+
+pub fn load(
+    path: &Path,
+    tile_size: Vec2,
+    load_texture: &mut impl FnMut(&Path) -> anyhow::Result<Handle<Texture>>,
+) -> anyhow::Result<Tilemap> {
+    crate::error::ensure_exists(path)?;
+    let text = std::fs::read_to_string(path)?;
+    let document = roxmltree::Document::parse(&text)?;
+    let map = document.root_element();
+    if !map.has_tag_name("map") {
+        anyhow::bail!("{} is not a Tiled map", path.display());
+    }
+    if attribute_or(map, "infinite", 0)? != 0 {
+        anyhow::bail!("Infinite Tiled maps are not supported");
+    }
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut tilemap = Tilemap::new(tile_size);
+    // first gid of each tileset, in the same order as tilemap.tilesets
+    let mut first_gids = Vec::new();
+    for node in map.children().filter(|node| node.has_tag_name("tileset")) {
+        first_gids.push(attribute::<u32>(node, "firstgid")?);
+        let tileset = match node.attribute("source") {
+            Some(source) => {
+                let path = directory.join(source);
+                let text = std::fs::read_to_string(&path)?;
+                let document = roxmltree::Document::parse(&text)?;
+                let directory = path.parent().unwrap_or(Path::new(""));
+                load_tileset(document.root_element(), directory, load_texture)?
+            }
+            None => load_tileset(node, directory, load_texture)?,
+        };
+        tilemap.tilesets.push(tileset);
+    }
+
+    let size = UVec2::new(attribute(map, "width")?, attribute(map, "height")?);
+    load_layers(path, map, size, &first_gids, &mut tilemap)?;
+    Ok(tilemap)
+}
+
+fn load_tileset(
+    node: Node,
+    directory: &Path,
+    load_texture: &mut impl FnMut(&Path) -> anyhow::Result<Handle<Texture>>,
+) -> anyhow::Result<Tileset> {
+    let name = node.attribute("name").unwrap_or_default().to_string();
+    let Some(image) = node.children().find(|node| node.has_tag_name("image")) else {
+        anyhow::bail!("Tileset {name} has no single image, image collections are not supported");
+    };
+    let tile_size = UVec2::new(
+        attribute(node, "tilewidth")?,
+        attribute(node, "tileheight")?,
+    );
+    let texture_size = UVec2::new(attribute(image, "width")?, attribute(image, "height")?);
+    let atlas = TextureAtlas::from_pixel_grid(
+        texture_size,
+        tile_size,
+        attribute_or(node, "spacing", 0)?,
+        attribute_or(node, "margin", 0)?,
+    );
+    let texture = load_texture(&directory.join(attribute::<String>(image, "source")?))?;
+
+    let pixel = Vec2::ONE / tile_size.max(UVec2::ONE).as_vec2();
+    let mut shapes = HashMap::new();
+    for tile in node.children().filter(|node| node.has_tag_name("tile")) {
+        let objects = tile
+            .children()
+            .filter(|node| node.has_tag_name("objectgroup"))
+            .flat_map(|group| group.children())
+            .filter(|node| node.has_tag_name("object"));
+        let mut tile_shapes = Vec::new();
+        for object in objects {
+            let position = Vec2::new(
+                attribute_or(object, "x", 0.0)?,
+                attribute_or(object, "y", 0.0)?,
+            );
+            if let Some(polygon) = object.children().find(|node| node.has_tag_name("polygon")) {
+                let points = attribute::<String>(polygon, "points")?
+                    .split_whitespace()
+                    .map(|point| {
+                        let (x, y) = point
+                            .split_once(',')
+                            .ok_or(anyhow::anyhow!("Invalid polygon point {point}"))?;
+                        Ok((position + Vec2::new(x.parse()?, y.parse()?)) * pixel)
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                tile_shapes.push(TileShape::Polygon(points));
+            } else if object.has_attribute("width") {
+                // ellipses keep their bounds
+                let size = Vec2::new(attribute(object, "width")?, attribute(object, "height")?);
+                tile_shapes.push(TileShape::Rect {
+                    min: position * pixel,
+                    max: (position + size) * pixel,
+                });
+            }
+        }
+        if !tile_shapes.is_empty() {
+            shapes.insert(attribute(tile, "id")?, tile_shapes);
+        }
+    }
+
+    Ok(Tileset {
+        name,
+        texture,
+        atlas,
+        shapes,
+    })
+}
+
+// groups are flattened, their layers drawn in place
+fn load_layers(
+    path: &Path,
+    node: Node,
+    size: UVec2,
+    first_gids: &[u32],
+    tilemap: &mut Tilemap,
+) -> anyhow::Result<()> {
+    for child in node.children() {
+        if child.has_tag_name("group") {
+            load_layers(path, child, size, first_gids, tilemap)?;
+            continue;
+        }
+        if !child.has_tag_name("layer") {
+            continue;
+        }
+        let name = child.attribute("name").unwrap_or_default();
+        let size = UVec2::new(
+            attribute_or(child, "width", size.x)?,
+            attribute_or(child, "height", size.y)?,
+        );
+        if size.min_element() == 0 {
+            return Err(WhirlwindError::InvalidAsset {
+                path: path.into(),
+                reason: format!("layer {name} is {}x{} tiles", size.x, size.y),
+            }
+            .into());
+        }
+        let mut layer = TileLayer::new(name, size);
+        layer.visible = attribute_or(child, "visible", 1)? != 0;
+        layer.opacity = attribute_or(child, "opacity", 1.0)?;
+
+        let Some(data) = child.children().find(|node| node.has_tag_name("data")) else {
+            continue;
+        };
+        let gids: Vec<u32> = match data.attribute("encoding") {
+            Some("csv") => data
+                .text()
+                .unwrap_or_default()
+                .split(',')
+                .map(|gid| gid.trim().parse())
+                .collect::<Result<_, _>>()?,
+            None => data
+                .children()
+                .filter(|node| node.has_tag_name("tile"))
+                .map(|tile| attribute_or(tile, "gid", 0))
+                .collect::<anyhow::Result<_>>()?,
+            Some(encoding) => {
+                anyhow::bail!("Layer {name} uses {encoding} data, only csv and xml are supported")
+            }
+        };
+
+        for (i, gid) in gids.into_iter().enumerate() {
+            let id = gid & !FLAGS;
+            if id == 0 {
+                continue;
+            }
+            // the tileset with the highest first gid not above this one
+            let Some(tileset) = first_gids
+                .iter()
+                .enumerate()
+                .filter(|(_, first)| **first <= id)
+                .max_by_key(|(_, first)| **first)
+                .map(|(tileset, _)| tileset)
+            else {
+                continue;
+            };
+            let position = UVec2::new(i as u32 % size.x, i as u32 / size.x);
+            layer.set(
+                position,
+                Some(Tile {
+                    tileset: tileset as u32,
+                    index: id - first_gids[tileset],
+                    flip_x: gid & FLIP_X != 0,
+                    flip_y: gid & FLIP_Y != 0,
+                }),
+            );
+        }
+        tilemap.layers.push(layer);
+    }
+    Ok(())
+}
+
+fn attribute<T: FromStr>(node: Node, name: &str) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    Ok(node
+        .attribute(name)
+        .ok_or(anyhow::anyhow!(
+            "<{}> is missing {name}",
+            node.tag_name().name()
+        ))?
+        .parse()?)
+}
+
+fn attribute_or<T: FromStr>(node: Node, name: &str, default: T) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match node.attribute(name) {
+        Some(value) => Ok(value.parse()?),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the map in a file of its own, loaded with a handle per texture path
+    fn load_str(name: &str, map: &str) -> anyhow::Result<(Tilemap, Vec<String>)> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, map).unwrap();
+        let mut textures = Vec::new();
+        let result = load(&path, Vec2::ONE, &mut |texture| {
+            textures.push(texture.file_name().unwrap().to_string_lossy().into_owned());
+            Ok(Handle::dangling(textures.len()))
+        });
+        std::fs::remove_file(&path).unwrap();
+        result.map(|tilemap| (tilemap, textures))
+    }
+
+    #[test]
+    fn tilesets_layers_and_collision_shapes() {
+        let (tilemap, textures) = load_str(
+            "whirlwind_tilesets.tmx",
+            r#"<map width="3" height="2" tilewidth="16" tileheight="16">
+                <tileset firstgid="1" name="terrain" tilewidth="16" tileheight="16">
+                    <image source="terrain.png" width="32" height="16"/>
+                    <tile id="1">
+                        <objectgroup>
+                            <object x="0" y="8" width="16" height="8"/>
+                            <object x="0" y="0"><polygon points="0,16 16,16 16,0"/></object>
+                        </objectgroup>
+                    </tile>
+                </tileset>
+                <tileset firstgid="3" name="props" tilewidth="16" tileheight="16">
+                    <image source="props.png" width="64" height="16"/>
+                </tileset>
+                <group>
+                    <layer name="ground" opacity="0.5">
+                        <data encoding="csv">1,2,0,
+                            3,6,2147483650</data>
+                    </layer>
+                </group>
+                <layer name="hidden" visible="0">
+                    <data><tile gid="3"/><tile/></data>
+                </layer>
+            </map>"#,
+        )
+        .unwrap();
+        assert_eq!(textures, ["terrain.png", "props.png"]);
+        assert_eq!(tilemap.tilesets[1].atlas.frames.len(), 4);
+
+        let ground = &tilemap.layers[0];
+        assert_eq!(ground.name, "ground");
+        assert_eq!(ground.opacity, 0.5);
+        assert_eq!(ground.get(UVec2::new(1, 0)), Some(Tile::new(0, 1)));
+        assert_eq!(ground.get(UVec2::new(2, 0)), None);
+        assert_eq!(ground.get(UVec2::new(0, 1)), Some(Tile::new(1, 0)));
+        assert_eq!(ground.get(UVec2::new(1, 1)), Some(Tile::new(1, 3)));
+        let flipped = ground.get(UVec2::new(2, 1)).unwrap();
+        assert!(flipped.flip_x && !flipped.flip_y && flipped.index == 1);
+        let hidden = &tilemap.layers[1];
+        assert!(!hidden.visible);
+        assert_eq!(hidden.get(UVec2::ZERO), Some(Tile::new(1, 0)));
+
+        assert_eq!(
+            tilemap.tilesets[0].shapes[&1],
+            [
+                TileShape::Rect {
+                    min: Vec2::new(0.0, 0.5),
+                    max: Vec2::ONE,
+                },
+                TileShape::Polygon(vec![Vec2::Y, Vec2::ONE, Vec2::X]),
+            ]
+        );
+        // both tiles with the shapes on the ground layer
+        assert_eq!(tilemap.collision_shapes(0).len(), 4);
+    }
+
+    #[test]
+    fn unsupported_maps_are_errors() {
+        let base64 = load_str(
+            "whirlwind_base64.tmx",
+            r#"<map width="1" height="1"><layer name="a"><data encoding="base64">AQAAAA==</data></layer></map>"#,
+        );
+        assert!(base64.unwrap_err().to_string().contains("base64"));
+        let infinite = load_str(
+            "whirlwind_infinite.tmx",
+            r#"<map width="1" height="1" infinite="1"></map>"#,
+        );
+        assert!(infinite.is_err());
+        assert!(load_str("whirlwind_not_a_map.tmx", "<tileset/>").is_err());
+    }
+
+    #[test]
+    fn zero_sized_layers_are_rejected() {
+        let path = std::env::temp_dir().join("whirlwind_empty_layer.tmx");
+        std::fs::write(
+            &path,
+            r#"<map width="0" height="2"><layer name="ground"><data encoding="csv">1,1</data></layer></map>"#,
+        )
+        .unwrap();
+        let error = load(&path, Vec2::ONE, &mut |_| anyhow::bail!("no tilesets")).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            error.downcast_ref::<WhirlwindError>(),
+            Some(WhirlwindError::InvalidAsset { .. })
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::ecs::component::Component;
+
+// never run more fixed steps than this per frame, so a long hitch can't snowball
+const MAX_FIXED_STEPS: u32 = 8;
+
+// `delta`/`elapsed` are virtual time: scaled, and frozen while paused.
+// `real_delta`/`real_elapsed` always follow the wall clock.
+#[derive(Debug)]
+pub struct Time {
+    delta: f32,
+    elapsed: f32,
+    real_delta: f32,
+    real_elapsed: f32,
+    scale: f32,
+    // slow motion from the debug toggles, on top of `scale`
+    pub(crate) debug_scale: f32,
+    paused: bool,
+    fixed_delta: f32,
+    fixed_accumulator: f32,
+    fixed_steps: u32,
+    last_update: Option<web_time::Instant>,
+}
+
+impl Component for Time {}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            delta: 0.0,
+            elapsed: 0.0,
+            real_delta: 0.0,
+            real_elapsed: 0.0,
+            scale: 1.0,
+            debug_scale: 1.0,
+            paused: false,
+            fixed_delta: 1.0 / 60.0,
+            fixed_accumulator: 0.0,
+            fixed_steps: 0,
+            last_update: None,
+        }
+    }
+}
+
+impl Time {
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn real_delta(&self) -> f32 {
+        self.real_delta
+    }
+
+    pub fn real_elapsed(&self) -> f32 {
+        self.real_elapsed
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // halts "update" and "fixed_update", "ui" keeps running
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // the step "fixed_update" systems should advance by
+    pub fn fixed_delta(&self) -> f32 {
+        self.fixed_delta
+    }
+
+    pub fn set_fixed_delta(&mut self, fixed_delta: f32) {
+        self.fixed_delta = fixed_delta.max(1e-4);
+    }
+
+    // how far the next fixed step is, 0..1, for interpolating between steps
+    pub fn fixed_overstep(&self) -> f32 {
+        self.fixed_accumulator / self.fixed_delta
+    }
+
+    pub(crate) fn update(&mut self) {
+        let now = web_time::Instant::now();
+        let real_delta = self
+            .last_update
+            .map_or(0.0, |last| (now - last).as_secs_f32());
+        self.last_update = Some(now);
+        self.advance(real_delta);
+    }
+
+    // the next update's delta is zero, for when frames stopped for a while
+    pub(crate) fn reset_clock(&mut self) {
+        self.last_update = None;
+    }
+
+    // steps by a made up wall clock delta instead of the real one, for
+    // headless runs that have to do the same work every time
+    pub(crate) fn advance(&mut self, real_delta: f32) {
+        self.real_delta = real_delta;
+        self.real_elapsed += self.real_delta;
+
+        self.delta = if self.paused {
+            0.0
+        } else {
+            self.real_delta * self.scale * self.debug_scale
+        };
+        self.elapsed += self.delta;
+        self.fixed_accumulator += self.delta;
+        self.fixed_steps = 0;
+    }
+
+    // true while another fixed step is due this frame
+    pub(crate) fn expend_fixed(&mut self) -> bool {
+        if self.fixed_accumulator < self.fixed_delta {
+            return false;
+        }
+        if self.fixed_steps >= MAX_FIXED_STEPS {
+            self.fixed_accumulator %= self.fixed_delta;
+            return false;
+        }
+        self.fixed_accumulator -= self.fixed_delta;
+        self.fixed_steps += 1;
+        true
+    }
+}
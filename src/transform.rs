@@ -0,0 +1,448 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::ecs::{component::Component, entity::Entity, world::World};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Component for Transform {}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::from_translation(Vec3::new(x, y, z))
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    // shear can't be kept, a matrix with any is only approximated
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn with_translation(self, translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..self
+        }
+    }
+
+    pub fn with_rotation(self, rotation: Quat) -> Self {
+        Self { rotation, ..self }
+    }
+
+    pub fn with_scale(self, scale: Vec3) -> Self {
+        Self { scale, ..self }
+    }
+
+    // turned so forward points at `target`
+    pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Self {
+        self.look_at(target, up);
+        self
+    }
+
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        self.look_to(target - self.translation, up);
+    }
+
+    // unchanged if `direction` is zero or parallel to `up`
+    pub fn look_to(&mut self, direction: Vec3, up: Vec3) {
+        let forward = direction.normalize_or_zero();
+        let right = forward.cross(up).normalize_or_zero();
+        if forward == Vec3::ZERO || right == Vec3::ZERO {
+            return;
+        }
+        let up = right.cross(forward);
+        self.rotation = Quat::from_mat3(&glam::Mat3::from_cols(right, up, -forward));
+    }
+
+    // -z, like the camera
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn back(&self) -> Vec3 {
+        -self.forward()
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn left(&self) -> Vec3 {
+        -self.right()
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn down(&self) -> Vec3 {
+        -self.up()
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        self.translation += offset;
+    }
+
+    // applied after the current rotation, in world space
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.rotation = rotation * self.rotation;
+    }
+
+    pub fn rotate_local(&mut self, rotation: Quat) {
+        self.rotation *= rotation;
+    }
+
+    pub fn rotate_around(&mut self, point: Vec3, rotation: Quat) {
+        self.translation = point + rotation * (self.translation - point);
+        self.rotate(rotation);
+    }
+
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    // `other` placed inside this transform, like a child under its parent
+    pub fn mul_transform(&self, other: &Transform) -> Transform {
+        Transform {
+            translation: self.transform_point(other.translation),
+            rotation: self.rotation * other.rotation,
+            scale: self.scale * other.scale,
+        }
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.translation + self.rotation * (self.scale * point)
+    }
+}
+
+impl From<Transform> for Mat4 {
+    fn from(transform: Transform) -> Self {
+        transform.compute_matrix()
+    }
+}
+
+// where an entity ends up after its parents' transforms, written by
+// `propagate_transforms` at the end of every frame. a matrix, since a rotated
+// child of a non-uniformly scaled parent can be sheared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub Mat4);
+
+impl Component for GlobalTransform {}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self(Mat4::IDENTITY)
+    }
+}
+
+impl GlobalTransform {
+    pub fn translation(&self) -> Vec3 {
+        self.0.w_axis.truncate()
+    }
+
+    pub fn compute_transform(&self) -> Transform {
+        Transform::from_matrix(self.0)
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.0.transform_point3(point)
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.0.transform_vector3(Vec3::NEG_Z).normalize_or_zero()
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.0.transform_vector3(Vec3::X).normalize_or_zero()
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.0.transform_vector3(Vec3::Y).normalize_or_zero()
+    }
+}
+
+// the matrix to draw an entity with. falls back to its own Transform before
+// the first propagation, and identity without either
+pub fn model_matrix(world: &World, entity: Entity) -> Mat4 {
+    match world.get_component::<GlobalTransform>(entity) {
+        Some(global) => global.0,
+        None => world
+            .get_component::<Transform>(entity)
+            .map_or(Mat4::IDENTITY, Transform::compute_matrix),
+    }
+}
+
+// like model_matrix, but with the entity's Transform as it is now rather than
+// as of the last propagation, for systems that move things before it runs
+pub fn current_model_matrix(world: &World, entity: Entity) -> Mat4 {
+    match world.get_component::<Transform>(entity) {
+        Some(transform) => parent_matrix(world, entity) * transform.compute_matrix(),
+        None => model_matrix(world, entity),
+    }
+}
+
+// the space an entity's Transform is in, its parent's model matrix, identity
+// at the root
+pub fn parent_matrix(world: &World, entity: Entity) -> Mat4 {
+    crate::hierarchy::parent(world, entity)
+        .map_or(Mat4::IDENTITY, |parent| model_matrix(world, parent))
+}
+
+// the entity's Transform in world space, for code that wants a translation,
+// rotation and scale rather than a matrix. current like current_model_matrix,
+// and only approximated under a parent that shears it
+pub fn world_transform(world: &World, entity: Entity) -> Option<Transform> {
+    let transform = world.get_component::<Transform>(entity)?;
+    Some(match crate::hierarchy::parent(world, entity) {
+        Some(_) => {
+            Transform::from_matrix(parent_matrix(world, entity) * transform.compute_matrix())
+        }
+        None => *transform,
+    })
+}
+
+// sets the entity's Transform so it ends up at `transform` in world space
+pub fn set_world_transform(world: &mut World, entity: Entity, transform: Transform) {
+    let local = match crate::hierarchy::parent(world, entity) {
+        Some(_) => Transform::from_matrix(
+            parent_matrix(world, entity).inverse() * transform.compute_matrix(),
+        ),
+        None => transform,
+    };
+    if let Some(current) = world.get_component_mut::<Transform>(entity) {
+        *current = local;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::hierarchy::{propagate_transforms, set_parent};
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{} isn't {}", a, b);
+    }
+
+    #[test]
+    fn directions_follow_the_rotation() {
+        let transform = Transform::IDENTITY;
+        assert_eq!(transform.forward(), Vec3::NEG_Z);
+        assert_eq!(transform.right(), Vec3::X);
+        assert_eq!(transform.up(), Vec3::Y);
+        assert_eq!(transform.back(), Vec3::Z);
+
+        // a quarter turn left around y
+        let turned = Transform::from_rotation(Quat::from_rotation_y(FRAC_PI_2));
+        assert_near(turned.forward(), Vec3::NEG_X);
+        assert_near(turned.right(), Vec3::NEG_Z);
+        assert_near(turned.up(), Vec3::Y);
+        assert_near(turned.left(), Vec3::Z);
+    }
+
+    #[test]
+    fn looking_at_a_target() {
+        let transform =
+            Transform::from_xyz(1.0, 2.0, 3.0).looking_at(Vec3::new(1.0, 2.0, -7.0), Vec3::Y);
+        assert_near(transform.forward(), Vec3::NEG_Z);
+        assert_near(transform.up(), Vec3::Y);
+
+        let transform = Transform::IDENTITY.looking_at(Vec3::new(5.0, 0.0, 0.0), Vec3::Y);
+        assert_near(transform.forward(), Vec3::X);
+        assert_near(transform.right(), Vec3::Z);
+        // straight up along `up`, or at itself, leaves the rotation alone
+        let rotated = Transform::from_rotation(Quat::from_rotation_x(0.3));
+        assert_eq!(rotated.looking_at(Vec3::Y, Vec3::Y), rotated);
+        assert_eq!(rotated.looking_at(Vec3::ZERO, Vec3::Y), rotated);
+    }
+
+    #[test]
+    fn rotating_around_a_point() {
+        let mut transform = Transform::from_xyz(2.0, 0.0, 0.0);
+        transform.rotate_around(Vec3::new(1.0, 0.0, 0.0), Quat::from_rotation_y(FRAC_PI_2));
+        assert_near(transform.translation, Vec3::new(1.0, 0.0, -1.0));
+        // it turns too, so it keeps facing the same way relative to the point
+        assert_near(transform.forward(), Vec3::NEG_X);
+        transform.rotate_around(Vec3::new(1.0, 0.0, 0.0), Quat::from_rotation_y(-FRAC_PI_2));
+        assert_near(transform.translation, Vec3::new(2.0, 0.0, 0.0));
+        assert_near(transform.forward(), Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        let transform = Transform {
+            translation: Vec3::new(1.0, -2.0, 3.0),
+            rotation: Quat::from_euler(glam::EulerRot::YXZ, 0.4, -1.1, 0.25),
+            scale: Vec3::new(2.0, 0.5, 3.0),
+        };
+        let matrix = Mat4::from(transform);
+        let back = Transform::from_matrix(matrix);
+        assert_near(back.translation, transform.translation);
+        assert_near(back.scale, transform.scale);
+        assert!(back.rotation.abs_diff_eq(transform.rotation, 1e-5));
+        let point = Vec3::new(0.5, 1.0, -4.0);
+        assert_near(
+            matrix.transform_point3(point),
+            transform.transform_point(point),
+        );
+        assert_near(
+            back.transform_point(point),
+            transform.transform_point(point),
+        );
+    }
+
+    #[test]
+    fn children_are_placed_in_their_parents_space() {
+        let mut world = World::new();
+        let parent = world
+            .spawn()
+            .insert(
+                Transform::from_xyz(10.0, 0.0, 0.0)
+                    .with_rotation(Quat::from_rotation_y(FRAC_PI_2))
+                    .with_scale(Vec3::splat(2.0)),
+            )
+            .id();
+        let child = world
+            .spawn()
+            .insert(Transform::from_xyz(0.0, 0.0, -1.0))
+            .id();
+        let grandchild = world
+            .spawn()
+            .insert(Transform::from_xyz(0.0, 1.0, 0.0))
+            .id();
+        set_parent(&mut world, child, Some(parent));
+        set_parent(&mut world, grandchild, Some(child));
+
+        // before propagation model_matrix only has the entity's own transform,
+        // so the grandchild is only placed under the child
+        assert_near(
+            model_matrix(&world, child).w_axis.truncate(),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        assert_near(
+            current_model_matrix(&world, grandchild).w_axis.truncate(),
+            Vec3::new(0.0, 1.0, -1.0),
+        );
+
+        propagate_transforms(&mut world);
+        // one forward from a parent turned to face -x, at twice the size
+        let expected_child = Vec3::new(8.0, 0.0, 0.0);
+        assert_near(
+            model_matrix(&world, child).w_axis.truncate(),
+            expected_child,
+        );
+        assert_near(
+            model_matrix(&world, grandchild).w_axis.truncate(),
+            expected_child + Vec3::new(0.0, 2.0, 0.0),
+        );
+        let global = world.get_component::<GlobalTransform>(child).unwrap();
+        assert_near(global.forward(), Vec3::NEG_X);
+        assert_near(
+            global.transform_point(Vec3::new(0.0, 1.0, 0.0)),
+            expected_child + Vec3::new(0.0, 2.0, 0.0),
+        );
+
+        // the entity's own changes show in current_model_matrix before propagation
+        world
+            .get_component_mut::<Transform>(child)
+            .unwrap()
+            .translation
+            .y = 1.0;
+        assert_near(
+            model_matrix(&world, child).w_axis.truncate(),
+            expected_child,
+        );
+        assert_near(
+            current_model_matrix(&world, child).w_axis.truncate(),
+            expected_child + Vec3::new(0.0, 2.0, 0.0),
+        );
+        world
+            .get_component_mut::<Transform>(parent)
+            .unwrap()
+            .translation
+            .y = 5.0;
+        propagate_transforms(&mut world);
+        assert_near(
+            model_matrix(&world, grandchild).w_axis.truncate(),
+            Vec3::new(8.0, 9.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn world_transforms_go_through_the_parent() {
+        let mut world = World::new();
+        let parent = world
+            .spawn()
+            .insert(
+                Transform::from_xyz(0.0, 3.0, 0.0).with_rotation(Quat::from_rotation_y(FRAC_PI_2)),
+            )
+            .id();
+        let child = world
+            .spawn()
+            .insert(Transform::from_xyz(0.0, 0.0, -2.0))
+            .id();
+        set_parent(&mut world, child, Some(parent));
+        propagate_transforms(&mut world);
+
+        let global = world_transform(&world, child).unwrap();
+        assert_near(global.translation, Vec3::new(-2.0, 3.0, 0.0));
+        assert_near(global.forward(), Vec3::NEG_X);
+        // roots are their own transform
+        assert_eq!(
+            world_transform(&world, parent),
+            world.get_component::<Transform>(parent).copied()
+        );
+        let loose = world.spawn().id();
+        assert_eq!(world_transform(&world, loose), None);
+
+        set_world_transform(&mut world, child, Transform::from_xyz(1.0, 3.0, 0.0));
+        let local = world.get_component::<Transform>(child).unwrap();
+        assert_near(local.translation, Vec3::new(0.0, 0.0, 1.0));
+        assert_near(
+            world_transform(&world, child).unwrap().translation,
+            Vec3::new(1.0, 3.0, 0.0),
+        );
+    }
+}
@@ -0,0 +1,49 @@
+// UI is laid out in logical pixels and multiplied by `UiScale::factor` when
+// it's turned into physical ones, so it stays the same size on high-DPI screens.
+
+use glam::Vec2;
+
+use crate::ecs::component::Component;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleFactorChanged {
+    pub scale_factor: f64,
+}
+
+#[derive(Debug)]
+pub struct UiScale {
+    // user preference on top of the window's scale factor
+    pub multiplier: f32,
+    pub(crate) window_scale_factor: f64,
+}
+
+impl Component for UiScale {}
+
+impl Default for UiScale {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.0,
+            window_scale_factor: 1.0,
+        }
+    }
+}
+
+impl UiScale {
+    // what the os reports for the monitor the window is on
+    pub fn window_scale_factor(&self) -> f64 {
+        self.window_scale_factor
+    }
+
+    // physical pixels per logical ui pixel, also the size text should be rasterized at
+    pub fn factor(&self) -> f32 {
+        self.window_scale_factor as f32 * self.multiplier
+    }
+
+    pub fn to_physical(&self, logical: Vec2) -> Vec2 {
+        logical * self.factor()
+    }
+
+    pub fn to_logical(&self, physical: Vec2) -> Vec2 {
+        physical / self.factor()
+    }
+}
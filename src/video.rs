@@ -0,0 +1,431 @@
+// Image-sequence playback into a texture asset. Frames are decoded ahead on a
+// worker thread and uploaded once they're due; a frame that isn't ready in
+// time is shown late rather than waited for. There's no VP9 or AV1 decoding,
+// that needs dav1d or libvpx, but anything that produces RGBA frames can be
+// played by implementing VideoDecoder.
+
+use std::{path::PathBuf, sync::mpsc};
+
+use crate::{
+    assets::{Assets, Handle},
+    audio::{AudioOutput, AudioSource},
+    ecs::{component::Component, entity::Entity, world::World},
+    render::RenderDevice,
+    texture::{Texture, TextureUsage},
+    time::Time,
+};
+
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    // presentation time in seconds from the start of the stream
+    pub timestamp: f32,
+}
+
+// runs on the player's worker thread
+pub trait VideoDecoder: Send {
+    fn dimensions(&self) -> (u32, u32);
+    fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>>;
+    fn rewind(&mut self) -> anyhow::Result<()>;
+}
+
+pub struct ImageSequenceDecoder {
+    frames: Vec<PathBuf>,
+    frame_rate: f32,
+    index: usize,
+    dimensions: (u32, u32),
+}
+
+impl ImageSequenceDecoder {
+    // every image in `dir`, played in file name order
+    pub fn from_dir(dir: impl Into<PathBuf>, frame_rate: f32) -> anyhow::Result<Self> {
+        let mut frames = std::fs::read_dir(dir.into())?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| image::ImageFormat::from_path(path).is_ok())
+            .collect::<Vec<_>>();
+        frames.sort();
+
+        let first = frames
+            .first()
+            .ok_or(anyhow::anyhow!("Image sequence has no frames"))?;
+        let dimensions = image::image_dimensions(first)?;
+
+        Ok(Self {
+            frames,
+            frame_rate,
+            index: 0,
+            dimensions,
+        })
+    }
+}
+
+impl VideoDecoder for ImageSequenceDecoder {
+    fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>> {
+        let Some(path) = self.frames.get(self.index) else {
+            return Ok(None);
+        };
+        let image = image::open(path)?.to_rgba8();
+        let frame = VideoFrame {
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_raw(),
+            timestamp: self.index as f32 / self.frame_rate,
+        };
+        self.index += 1;
+        Ok(Some(frame))
+    }
+
+    fn rewind(&mut self) -> anyhow::Result<()> {
+        self.index = 0;
+        Ok(())
+    }
+}
+
+// frames decoded this far ahead of playback
+const FRAMES_AHEAD: usize = 4;
+
+enum Decoded {
+    // tagged with the rewinds before it, so frames from before one are dropped
+    Frame(u64, VideoFrame),
+    End(u64),
+    Failed(anyhow::Error),
+}
+
+// the player's side of the worker thread. dropping it stops the thread
+struct FrameQueue {
+    frames: mpsc::Receiver<Decoded>,
+    rewind: mpsc::Sender<()>,
+    rewinds: u64,
+    pending: Option<VideoFrame>,
+}
+
+impl FrameQueue {
+    fn spawn(mut decoder: Box<dyn VideoDecoder>) -> anyhow::Result<Self> {
+        let (sender, frames) = mpsc::sync_channel(FRAMES_AHEAD);
+        let (rewind, rewinds) = mpsc::channel::<()>();
+        std::thread::Builder::new()
+            .name("video_decoder".to_string())
+            .spawn(move || {
+                let mut generation = 0;
+                let mut waiting = false;
+                loop {
+                    // after the end or an error, nothing more until a rewind
+                    let rewound = if waiting {
+                        rewinds.recv().is_ok()
+                    } else {
+                        match rewinds.try_recv() {
+                            Err(mpsc::TryRecvError::Empty) => false,
+                            Ok(()) => true,
+                            Err(mpsc::TryRecvError::Disconnected) => return,
+                        }
+                    };
+                    if waiting && !rewound {
+                        return;
+                    }
+                    let decoded = if rewound {
+                        generation += 1;
+                        match decoder.rewind() {
+                            Ok(()) => decoder.next_frame(),
+                            Err(e) => Err(e),
+                        }
+                    } else {
+                        decoder.next_frame()
+                    };
+                    let decoded = match decoded {
+                        Ok(Some(frame)) => Decoded::Frame(generation, frame),
+                        Ok(None) => Decoded::End(generation),
+                        Err(e) => Decoded::Failed(e),
+                    };
+                    waiting = !matches!(decoded, Decoded::Frame(..));
+                    if sender.send(decoded).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        Ok(Self {
+            frames,
+            rewind,
+            rewinds: 0,
+            pending: None,
+        })
+    }
+
+    fn rewind(&mut self) -> anyhow::Result<()> {
+        self.rewind
+            .send(())
+            .map_err(|_| anyhow::anyhow!("The video decoder has stopped"))?;
+        self.rewinds += 1;
+        self.pending = None;
+        Ok(())
+    }
+
+    // the newest frame due at `time`, and whether the stream ended
+    fn due(&mut self, time: f32) -> anyhow::Result<(Option<VideoFrame>, bool)> {
+        let mut latest = None;
+        loop {
+            let frame = match self.pending.take() {
+                Some(frame) => frame,
+                None => match self.frames.try_recv() {
+                    Ok(Decoded::Frame(generation, frame)) if generation == self.rewinds => frame,
+                    Ok(Decoded::End(generation)) if generation == self.rewinds => {
+                        return Ok((latest, true));
+                    }
+                    // from before a rewind
+                    Ok(Decoded::Frame(..) | Decoded::End(_)) => continue,
+                    Ok(Decoded::Failed(e)) => return Err(e),
+                    Err(mpsc::TryRecvError::Empty) => return Ok((latest, false)),
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        anyhow::bail!("The video decoder has stopped")
+                    }
+                },
+            };
+            if frame.timestamp > time {
+                self.pending = Some(frame);
+                return Ok((latest, false));
+            }
+            latest = Some(frame);
+        }
+    }
+}
+
+pub struct VideoPlayer {
+    frames: FrameQueue,
+    pub texture: Handle<Texture>,
+    pub playing: bool,
+    pub looping: bool,
+    // follow the playback position of an AudioSource on the same entity, while
+    // an audio output is playing it
+    pub sync_to_audio: bool,
+    time: f32,
+}
+
+impl std::fmt::Debug for VideoPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoPlayer")
+            .field("texture", &self.texture)
+            .field("playing", &self.playing)
+            .field("looping", &self.looping)
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+impl Component for VideoPlayer {}
+
+impl VideoPlayer {
+    // creates the target texture, so the render device must already exist
+    pub fn new(world: &mut World, decoder: impl VideoDecoder + 'static) -> anyhow::Result<Self> {
+        let RenderDevice { device, queue, .. } = world
+            .get_resource::<RenderDevice>()
+            .ok_or(anyhow::anyhow!("Render device is not ready"))?
+            .clone();
+        let (width, height) = decoder.dimensions();
+        let texture = Texture::from_image(
+            &device,
+            &queue,
+            &image::DynamicImage::new_rgba8(width, height),
+            TextureUsage::Color,
+            Some("video_texture"),
+        )?;
+        let texture = world.resource_mut::<Assets<Texture>>().add(texture);
+
+        Ok(Self {
+            frames: FrameQueue::spawn(Box::new(decoder))?,
+            texture,
+            playing: true,
+            looping: false,
+            sync_to_audio: true,
+            time: 0.0,
+        })
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn restart(&mut self) -> anyhow::Result<()> {
+        self.frames.rewind()?;
+        self.time = 0.0;
+        Ok(())
+    }
+
+    // newest frame due at the current time, if it changed
+    fn advance(&mut self) -> anyhow::Result<Option<VideoFrame>> {
+        let (frame, ended) = self.frames.due(self.time)?;
+        if ended {
+            if self.looping {
+                self.restart()?;
+            } else {
+                self.playing = false;
+            }
+        }
+        Ok(frame)
+    }
+}
+
+pub fn update_video_players(world: &mut World) {
+    let delta = world.get_resource::<Time>().map_or(0.0, Time::delta);
+    let Some(RenderDevice { queue, .. }) = world.get_resource::<RenderDevice>().cloned() else {
+        return;
+    };
+
+    let entities: Vec<Entity> = world
+        .query::<VideoPlayer>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect();
+
+    // a source's position stands still without an output to play it
+    let audio_out = world
+        .get_resource::<AudioOutput>()
+        .is_some_and(AudioOutput::is_playing_out);
+    for entity in entities {
+        let audio_time = world
+            .get_component::<AudioSource>(entity)
+            .filter(|source| audio_out && source.playing)
+            .map(AudioSource::position);
+        let Some(player) = world.get_component_mut::<VideoPlayer>(entity) else {
+            continue;
+        };
+        if !player.playing {
+            continue;
+        }
+
+        player.time = match audio_time {
+            Some(time) if player.sync_to_audio => time,
+            _ => player.time + delta,
+        };
+        let texture = player.texture;
+        let frame = match player.advance() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("Unable to decode video frame: {}", e);
+                player.playing = false;
+                continue;
+            }
+        };
+
+        let Some(texture) = world
+            .get_resource::<Assets<Texture>>()
+            .and_then(|textures| textures.get(texture))
+        else {
+            continue;
+        };
+        let size = texture.texture.size();
+        if size.width != frame.width || size.height != frame.height {
+            log::warn!("Video frame size does not match its texture, skipping");
+            continue;
+        }
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &frame.rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * frame.width),
+                rows_per_image: Some(frame.height),
+            },
+            size,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one pixel frames holding their index, ten a second
+    struct Counter {
+        frames: u8,
+        index: u8,
+    }
+
+    impl VideoDecoder for Counter {
+        fn dimensions(&self) -> (u32, u32) {
+            (1, 1)
+        }
+
+        fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>> {
+            if self.index == self.frames {
+                return Ok(None);
+            }
+            self.index += 1;
+            Ok(Some(VideoFrame {
+                width: 1,
+                height: 1,
+                rgba: vec![self.index - 1; 4],
+                timestamp: (self.index - 1) as f32 / 10.0,
+            }))
+        }
+
+        fn rewind(&mut self) -> anyhow::Result<()> {
+            self.index = 0;
+            Ok(())
+        }
+    }
+
+    // frames come from another thread, so wait until one past `time` or the end is in
+    fn wait_for(queue: &mut FrameQueue, time: f32) -> (Option<u8>, bool) {
+        let mut latest = None;
+        for _ in 0..1000 {
+            let (frame, ended) = queue.due(time).unwrap();
+            latest = frame.map(|frame| frame.rgba[0]).or(latest);
+            if ended || queue.pending.is_some() {
+                return (latest, ended);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("nothing was decoded");
+    }
+
+    #[test]
+    fn frames_are_decoded_ahead_and_shown_when_due() {
+        let mut queue = FrameQueue::spawn(Box::new(Counter {
+            frames: 5,
+            index: 0,
+        }))
+        .unwrap();
+        assert_eq!(wait_for(&mut queue, 0.0), (Some(0), false));
+        // the frames in between are skipped
+        assert_eq!(wait_for(&mut queue, 0.25), (Some(2), false));
+        assert_eq!(queue.due(0.25).unwrap().0.map(|frame| frame.rgba[0]), None);
+        assert_eq!(wait_for(&mut queue, 1.0), (Some(4), true));
+
+        queue.rewind().unwrap();
+        assert_eq!(wait_for(&mut queue, 0.0), (Some(0), false));
+        // frames decoded before a rewind don't show up after it
+        queue.rewind().unwrap();
+        assert_eq!(wait_for(&mut queue, 0.15), (Some(1), false));
+    }
+
+    #[test]
+    fn image_sequences_play_in_file_name_order() {
+        let dir = std::env::temp_dir().join(format!("whirlwind_video_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, shade) in [("b.png", 20), ("a.png", 10)] {
+            image::RgbaImage::from_pixel(2, 1, image::Rgba([shade; 4]))
+                .save(dir.join(name))
+                .unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not a frame").unwrap();
+        let mut decoder = ImageSequenceDecoder::from_dir(&dir, 24.0).unwrap();
+        assert_eq!(decoder.dimensions(), (2, 1));
+        let first = decoder.next_frame().unwrap().unwrap();
+        let second = decoder.next_frame().unwrap().unwrap();
+        assert_eq!((first.rgba[0], second.rgba[0]), (10, 20));
+        assert_eq!(second.timestamp, 1.0 / 24.0);
+        assert!(decoder.next_frame().unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{
+    ecs::{component::Component, entity::Entity, world::World},
+    hierarchy,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Visibility {
+    // follows the parent, visible at the root
+    #[default]
+    Inherited,
+    Visible,
+    Hidden,
+}
+
+impl Component for Visibility {}
+
+// entities without this component are on layer 0 only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderLayers(pub u32);
+
+impl Component for RenderLayers {}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
+
+impl RenderLayers {
+    pub const ALL: Self = Self(u32::MAX);
+    pub const NONE: Self = Self(0);
+
+    pub const fn layer(layer: u8) -> Self {
+        Self(1 << (layer % 32))
+    }
+
+    pub const fn with(self, layer: u8) -> Self {
+        Self(self.0 | Self::layer(layer).0)
+    }
+
+    pub const fn without(self, layer: u8) -> Self {
+        Self(self.0 & !Self::layer(layer).0)
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+pub fn is_visible(world: &World, entity: Entity) -> bool {
+    std::iter::once(entity)
+        .chain(hierarchy::ancestors(world, entity))
+        .find_map(|entity| match world.get_component::<Visibility>(entity) {
+            Some(Visibility::Visible) => Some(true),
+            Some(Visibility::Hidden) => Some(false),
+            _ => None,
+        })
+        .unwrap_or(true)
+}
+
+// visible, and on at least one of the layers the camera renders
+pub fn is_visible_to(world: &World, entity: Entity, layers: RenderLayers) -> bool {
+    world
+        .get_component::<RenderLayers>(entity)
+        .copied()
+        .unwrap_or_default()
+        .intersects(layers)
+        && is_visible(world, entity)
+}
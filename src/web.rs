@@ -0,0 +1,89 @@
+// Where the web build draws. The canvas is looked up by a css selector, or made
+// and appended to a parent element, when the window is created. With
+// `fit_to_parent` it's kept the size of its parent's box every frame, so the
+// page's layout decides how big the game is. None of this does anything on
+// native. Fullscreen is WindowSettings::set_fullscreen, like everywhere else.
+
+use crate::ecs::component::Component;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebCanvas {
+    // an existing canvas element
+    Selector(String),
+    // a new canvas added to the element matching the selector, or to the body
+    Append { parent: Option<String> },
+}
+
+// read when the window is created, except `fit_to_parent`
+#[derive(Debug, Clone)]
+pub struct WebSettings {
+    pub canvas: WebCanvas,
+    pub fit_to_parent: bool,
+}
+
+impl Component for WebSettings {}
+
+impl Default for WebSettings {
+    fn default() -> Self {
+        Self {
+            canvas: WebCanvas::Selector("#canvas".to_string()),
+            fit_to_parent: false,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn canvas(settings: &WebSettings) -> anyhow::Result<wgpu::web_sys::HtmlCanvasElement> {
+    use wasm_bindgen::JsCast;
+
+    let document = wgpu::web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or(anyhow::anyhow!("No document"))?;
+    let query = |selector: &str| {
+        document
+            .query_selector(selector)
+            .map_err(|e| anyhow::anyhow!("Invalid selector '{}': {:?}", selector, e))?
+            .ok_or_else(|| anyhow::anyhow!("Nothing matches '{}'", selector))
+    };
+    match &settings.canvas {
+        WebCanvas::Selector(selector) => query(selector)?
+            .dyn_into()
+            .map_err(|_| anyhow::anyhow!("'{}' isn't a canvas", selector)),
+        WebCanvas::Append { parent } => {
+            let parent = match parent {
+                Some(selector) => query(selector)?,
+                None => document
+                    .body()
+                    .ok_or(anyhow::anyhow!("The page has no body"))?
+                    .into(),
+            };
+            let canvas = document
+                .create_element("canvas")
+                .map_err(|e| anyhow::anyhow!("Unable to create a canvas: {:?}", e))?;
+            parent
+                .append_child(&canvas)
+                .map_err(|e| anyhow::anyhow!("Unable to add the canvas: {:?}", e))?;
+            canvas
+                .dyn_into()
+                .map_err(|_| anyhow::anyhow!("Created element isn't a canvas"))
+        }
+    }
+}
+
+// the resize comes back as a Resized event once the browser has laid it out
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn fit_to_parent(window: &winit::window::Window) {
+    use winit::platform::web::WindowExtWebSys;
+
+    let Some(parent) = window.canvas().and_then(|canvas| canvas.parent_element()) else {
+        return;
+    };
+    let rect = parent.get_bounding_client_rect();
+    let size = winit::dpi::LogicalSize::new(rect.width(), rect.height());
+    if size.width > 0.0
+        && size.height > 0.0
+        && size.to_physical::<u32>(window.scale_factor()) != window.inner_size()
+    {
+        let _ = window.request_inner_size(size);
+    }
+}
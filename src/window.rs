@@ -0,0 +1,859 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use glam::{IVec2, UVec2, Vec2};
+use web_time::{Duration, Instant};
+use wgpu::naga::FastHashMap;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::{ActiveEventLoop, ControlFlow},
+    monitor::{MonitorHandle, VideoModeHandle},
+    window::{
+        CursorGrabMode, CustomCursor, Fullscreen, Icon, Window, WindowAttributes, WindowLevel,
+    },
+};
+
+pub use winit::window::CursorIcon;
+
+use crate::{
+    ecs::{component::Component, world::World},
+    time::Time,
+};
+
+#[derive(Clone)]
+pub struct CursorImage {
+    id: usize,
+    rgba: Arc<[u8]>,
+    width: u16,
+    height: u16,
+    hotspot: (u16, u16),
+}
+
+impl std::fmt::Debug for CursorImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CursorImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("hotspot", &self.hotspot)
+            .finish()
+    }
+}
+
+impl PartialEq for CursorImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl CursorImage {
+    // hotspot is the clicking point in pixels from the top left
+    pub fn from_image(image: &image::DynamicImage, hotspot: (u16, u16)) -> anyhow::Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            width: width.try_into()?,
+            height: height.try_into()?,
+            rgba: rgba.into_raw().into(),
+            hotspot,
+        })
+    }
+
+    pub fn from_path(path: &str, hotspot: (u16, u16)) -> anyhow::Result<Self> {
+        crate::error::ensure_exists(path)?;
+        Self::from_image(&image::open(path)?, hotspot)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cursor {
+    Icon(CursorIcon),
+    Custom(CursorImage),
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::Icon(CursorIcon::Default)
+    }
+}
+
+impl From<CursorIcon> for Cursor {
+    fn from(icon: CursorIcon) -> Self {
+        Self::Icon(icon)
+    }
+}
+
+impl From<CursorImage> for Cursor {
+    fn from(image: CursorImage) -> Self {
+        Self::Custom(image)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoMode {
+    pub size: UVec2,
+    pub bit_depth: u16,
+    pub refresh_rate_millihertz: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub name: Option<String>,
+    pub size: UVec2,
+    pub position: IVec2,
+    pub scale_factor: f64,
+    pub refresh_rate_millihertz: Option<u32>,
+    pub primary: bool,
+    pub video_modes: Vec<VideoMode>,
+}
+
+// monitor and video mode are indices into WindowSettings::monitors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    // None picks the monitor the window is on
+    BorderlessFullscreen {
+        monitor: Option<usize>,
+    },
+    ExclusiveFullscreen {
+        monitor: usize,
+        video_mode: usize,
+    },
+}
+
+// sent when the window gains or loses keyboard focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowFocused(pub bool);
+
+// sent when the window is hidden or shown again: minimized, fully covered, or
+// on the web a background tab or a canvas scrolled out of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowVisibilityChanged {
+    pub visible: bool,
+}
+
+// mobile platforms take the window's surface away while the app is in the
+// background and may kill it without warning then, save anything worth keeping
+// on Suspended. desktop apps only see Resumed once, before startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    Suspended,
+    Resumed,
+}
+
+// when frames run. reactive suits tools that sit idle most of the time: a frame
+// runs after window events and input, a WindowSettings::request_redraw from the
+// last one, and every `wait` when it's set. delta is the time since the last
+// frame however long that was
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpdateMode {
+    #[default]
+    Continuous,
+    Reactive {
+        wait: Option<Duration>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+// what happens while the window can't be seen. frames stop while it's hidden,
+// and the time that passed doesn't land in the first delta after
+#[derive(Debug, Clone)]
+pub struct BackgroundSettings {
+    // freezes virtual time, unless it was already paused
+    pub pause_when_hidden: bool,
+    pub mute_when_hidden: bool,
+    // undone when the window is shown again
+    paused: bool,
+    #[cfg(feature = "audio")]
+    muted: bool,
+}
+
+impl Component for BackgroundSettings {}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            pause_when_hidden: true,
+            mute_when_hidden: true,
+            paused: false,
+            #[cfg(feature = "audio")]
+            muted: false,
+        }
+    }
+}
+
+// changes are queued and applied to the window after the frame's update
+#[derive(Debug)]
+pub struct WindowSettings {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    focused: bool,
+    visible: bool,
+    // position and size, where the platform reports one
+    pub(crate) safe_area: Option<(Vec2, Vec2)>,
+    title: String,
+    cursor: Cursor,
+    cursor_visible: bool,
+    cursor_captured: bool,
+    ime_allowed: bool,
+    // physical pixels, where the candidate window should avoid covering
+    ime_area: Option<(Vec2, Vec2)>,
+    pending_title: bool,
+    pending_cursor: bool,
+    pending_cursor_visible: bool,
+    pending_cursor_grab: bool,
+    pending_ime: bool,
+    pending_icon: Option<Option<Icon>>,
+    // for overlays, see set_transparent
+    transparent: bool,
+    decorations: bool,
+    always_on_top: bool,
+    click_through: bool,
+    pending_overlay: bool,
+    update_mode: UpdateMode,
+    redraw_requested: bool,
+    last_frame: Option<Instant>,
+    mode: WindowMode,
+    pending_mode: bool,
+    // mode to fall back to if the current one isn't confirmed in time
+    revert: Option<(WindowMode, Instant)>,
+    monitors: Vec<Monitor>,
+    monitor_handles: Vec<(MonitorHandle, Vec<VideoModeHandle>)>,
+    pending_monitor_refresh: bool,
+    custom_cursors: FastHashMap<usize, CustomCursor>,
+}
+
+impl Component for WindowSettings {}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            focused: true,
+            visible: true,
+            safe_area: None,
+            title: "Whirlwind Engine".to_string(),
+            cursor: Cursor::default(),
+            cursor_visible: true,
+            cursor_captured: false,
+            ime_allowed: false,
+            ime_area: None,
+            pending_title: false,
+            pending_cursor: false,
+            pending_cursor_visible: false,
+            pending_cursor_grab: false,
+            pending_ime: false,
+            pending_icon: None,
+            transparent: false,
+            decorations: true,
+            always_on_top: false,
+            click_through: false,
+            pending_overlay: false,
+            update_mode: UpdateMode::default(),
+            redraw_requested: false,
+            last_frame: None,
+            mode: WindowMode::Windowed,
+            pending_mode: false,
+            revert: None,
+            monitors: Vec::new(),
+            monitor_handles: Vec::new(),
+            pending_monitor_refresh: true,
+            custom_cursors: FastHashMap::default(),
+        }
+    }
+}
+
+impl WindowSettings {
+    // physical size of the surface
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32)
+    }
+
+    // the part of the window not under notches, rounded corners or system
+    // bars, in physical pixels from the top left. the whole window on desktop
+    pub fn safe_area(&self) -> (Vec2, Vec2) {
+        self.safe_area.unwrap_or((Vec2::ZERO, self.size()))
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        if self.height > self.width {
+            Orientation::Portrait
+        } else {
+            Orientation::Landscape
+        }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    // false while minimized or in a background tab, nothing is rendered then
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+        self.pending_title = true;
+    }
+
+    // ignored on platforms without window icons, like the web
+    pub fn set_icon(&mut self, image: &image::DynamicImage) -> anyhow::Result<()> {
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        self.pending_icon = Some(Some(Icon::from_rgba(rgba.into_raw(), width, height)?));
+        Ok(())
+    }
+
+    pub fn set_icon_from_path(&mut self, path: &str) -> anyhow::Result<()> {
+        crate::error::ensure_exists(path)?;
+        self.set_icon(&image::open(path)?)
+    }
+
+    pub fn clear_icon(&mut self) {
+        self.pending_icon = Some(None);
+    }
+
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
+    // lets the desktop show through wherever the frame's alpha is below 1, clear
+    // with a transparent ClearColor. only read when the window is created, so
+    // set it before the app runs. surfaces without a blending alpha mode stay opaque
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+
+    pub fn decorations(&self) -> bool {
+        self.decorations
+    }
+
+    // the title bar and borders
+    pub fn set_decorations(&mut self, decorations: bool) {
+        if decorations != self.decorations {
+            self.decorations = decorations;
+            self.pending_overlay = true;
+        }
+    }
+
+    pub fn always_on_top(&self) -> bool {
+        self.always_on_top
+    }
+
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        if always_on_top != self.always_on_top {
+            self.always_on_top = always_on_top;
+            self.pending_overlay = true;
+        }
+    }
+
+    pub fn click_through(&self) -> bool {
+        self.click_through
+    }
+
+    // clicks go to whatever is under the window, it gets no mouse input
+    pub fn set_click_through(&mut self, click_through: bool) {
+        if click_through != self.click_through {
+            self.click_through = click_through;
+            self.pending_overlay = true;
+        }
+    }
+
+    pub fn update_mode(&self) -> UpdateMode {
+        self.update_mode
+    }
+
+    pub fn set_update_mode(&mut self, update_mode: UpdateMode) {
+        self.update_mode = update_mode;
+    }
+
+    // runs another frame after this one in reactive mode, for animations and
+    // work spread over frames. continuous mode always does
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    // at the start of the frame, requests only carry over one frame
+    pub(crate) fn begin_frame(&mut self) {
+        self.redraw_requested = false;
+        self.last_frame = Some(Instant::now());
+    }
+
+    // whether the frame that just ran should be followed by another right away
+    pub(crate) fn redraw_next(&self) -> bool {
+        self.visible && (self.update_mode == UpdateMode::Continuous || self.redraw_requested)
+    }
+
+    // events wake reactive mode, except while hidden
+    pub(crate) fn redraw_on_event(&self) -> bool {
+        self.visible && self.update_mode != UpdateMode::Continuous
+    }
+
+    // before the loop sleeps, reactive mode with a wait is woken when it's up
+    pub(crate) fn schedule_wake(&self, window: &Window, event_loop: &ActiveEventLoop) {
+        let UpdateMode::Reactive { wait: Some(wait) } = self.update_mode else {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        };
+        let due = self.last_frame.map_or(Instant::now(), |last| last + wait);
+        if !self.visible {
+            event_loop.set_control_flow(ControlFlow::Wait);
+        } else if Instant::now() >= due {
+            window.request_redraw();
+        } else {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(due));
+        }
+    }
+
+    fn window_level(&self) -> WindowLevel {
+        if self.always_on_top {
+            WindowLevel::AlwaysOnTop
+        } else {
+            WindowLevel::Normal
+        }
+    }
+
+    // what the window is created with, the rest is applied once it exists
+    pub(crate) fn attributes(&mut self) -> WindowAttributes {
+        self.pending_overlay |= self.click_through;
+        Window::default_attributes()
+            .with_title(&self.title)
+            .with_transparent(self.transparent)
+            .with_decorations(self.decorations)
+            .with_window_level(self.window_level())
+    }
+
+    pub fn cursor(&self) -> &Cursor {
+        &self.cursor
+    }
+
+    pub fn set_cursor(&mut self, cursor: impl Into<Cursor>) {
+        let cursor = cursor.into();
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.pending_cursor = true;
+        }
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        if visible != self.cursor_visible {
+            self.cursor_visible = visible;
+            self.pending_cursor_visible = true;
+        }
+    }
+
+    pub fn cursor_captured(&self) -> bool {
+        self.cursor_captured
+    }
+
+    // locks and hides the cursor for mouse look, read motion from Input::mouse_motion.
+    // browsers only grant pointer lock in response to a click or key press
+    pub fn set_cursor_captured(&mut self, captured: bool) {
+        if captured != self.cursor_captured {
+            self.cursor_captured = captured;
+            self.pending_cursor_grab = true;
+        }
+    }
+
+    pub fn ime_allowed(&self) -> bool {
+        self.ime_allowed
+    }
+
+    // turn on while a text field has focus, otherwise keys go to the input method
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        if allowed != self.ime_allowed {
+            self.ime_allowed = allowed;
+            self.pending_ime = true;
+        }
+    }
+
+    // the focused text field, in physical pixels from the top left
+    pub fn set_ime_area(&mut self, position: Vec2, size: Vec2) {
+        if self.ime_area != Some((position, size)) {
+            self.ime_area = Some((position, size));
+            self.pending_ime = true;
+        }
+    }
+
+    // filled in once the window exists, see refresh_monitors for hotplugging
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.monitors
+    }
+
+    pub fn refresh_monitors(&mut self) {
+        self.pending_monitor_refresh = true;
+    }
+
+    pub fn mode(&self) -> WindowMode {
+        self.mode
+    }
+
+    // on the web only borderless fullscreen on the current monitor works, and
+    // browsers only grant it in response to a click or key press
+    pub fn set_mode(&mut self, mode: WindowMode) {
+        self.revert = None;
+        if mode != self.mode {
+            self.mode = mode;
+            self.pending_mode = true;
+        }
+    }
+
+    // borderless on the current monitor, or back to windowed
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.set_mode(if fullscreen {
+            WindowMode::BorderlessFullscreen { monitor: None }
+        } else {
+            WindowMode::Windowed
+        });
+    }
+
+    // switches now and reverts after `timeout` seconds unless confirm_mode is called,
+    // for "keep these display settings?" prompts
+    pub fn set_mode_with_confirmation(&mut self, mode: WindowMode, timeout: f32) {
+        let previous = self.revert.map_or(self.mode, |(previous, _)| previous);
+        self.set_mode(mode);
+        self.revert = Some((
+            previous,
+            Instant::now() + Duration::from_secs_f32(timeout.max(0.0)),
+        ));
+    }
+
+    pub fn confirm_mode(&mut self) {
+        self.revert = None;
+    }
+
+    // seconds left before an unconfirmed mode reverts
+    pub fn confirmation_remaining(&self) -> Option<f32> {
+        self.revert.map(|(_, deadline)| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs_f32()
+        })
+    }
+
+    fn fullscreen(&self) -> Option<Option<Fullscreen>> {
+        match self.mode {
+            WindowMode::Windowed => Some(None),
+            WindowMode::BorderlessFullscreen { monitor: None } => {
+                Some(Some(Fullscreen::Borderless(None)))
+            }
+            WindowMode::BorderlessFullscreen {
+                monitor: Some(monitor),
+            } => {
+                let (handle, _) = self.monitor_handles.get(monitor)?;
+                Some(Some(Fullscreen::Borderless(Some(handle.clone()))))
+            }
+            WindowMode::ExclusiveFullscreen {
+                monitor,
+                video_mode,
+            } => {
+                let (_, modes) = self.monitor_handles.get(monitor)?;
+                Some(Some(Fullscreen::Exclusive(modes.get(video_mode)?.clone())))
+            }
+        }
+    }
+
+    fn enumerate_monitors(&mut self, window: &Window) {
+        let primary = window.primary_monitor();
+        self.monitor_handles = window
+            .available_monitors()
+            .map(|monitor| {
+                let modes = monitor.video_modes().collect();
+                (monitor, modes)
+            })
+            .collect();
+        self.monitors = self
+            .monitor_handles
+            .iter()
+            .map(|(monitor, modes)| Monitor {
+                name: monitor.name(),
+                size: UVec2::new(monitor.size().width, monitor.size().height),
+                position: IVec2::new(monitor.position().x, monitor.position().y),
+                scale_factor: monitor.scale_factor(),
+                refresh_rate_millihertz: monitor.refresh_rate_millihertz(),
+                primary: primary.as_ref() == Some(monitor),
+                video_modes: modes
+                    .iter()
+                    .map(|mode| VideoMode {
+                        size: UVec2::new(mode.size().width, mode.size().height),
+                        bit_depth: mode.bit_depth(),
+                        refresh_rate_millihertz: mode.refresh_rate_millihertz(),
+                    })
+                    .collect(),
+            })
+            .collect();
+    }
+
+    // some platforms drop the grab when focus is lost
+    pub(crate) fn refresh_cursor_grab(&mut self) {
+        self.pending_cursor_grab |= self.cursor_captured;
+    }
+
+    pub(crate) fn apply(&mut self, window: &Window, event_loop: &ActiveEventLoop) {
+        if std::mem::take(&mut self.pending_monitor_refresh) {
+            self.enumerate_monitors(window);
+        }
+        if let Some((previous, deadline)) = self.revert
+            && Instant::now() >= deadline
+        {
+            log::info!("Display mode wasn't confirmed, reverting");
+            self.set_mode(previous);
+        }
+        if std::mem::take(&mut self.pending_mode) {
+            match self.fullscreen() {
+                Some(fullscreen) => window.set_fullscreen(fullscreen),
+                None => log::warn!("No monitor or video mode for {:?}", self.mode),
+            }
+        }
+        if std::mem::take(&mut self.pending_title) {
+            window.set_title(&self.title);
+        }
+        if let Some(icon) = self.pending_icon.take() {
+            window.set_window_icon(icon);
+        }
+        if std::mem::take(&mut self.pending_overlay) {
+            window.set_decorations(self.decorations);
+            window.set_window_level(self.window_level());
+            if let Err(e) = window.set_cursor_hittest(!self.click_through) {
+                log::warn!("Unable to change click through: {}", e);
+            }
+        }
+        if std::mem::take(&mut self.pending_ime) {
+            window.set_ime_allowed(self.ime_allowed);
+            if self.ime_allowed
+                && let Some((position, size)) = self.ime_area
+            {
+                window.set_ime_cursor_area(
+                    PhysicalPosition::new(position.x, position.y),
+                    PhysicalSize::new(size.x, size.y),
+                );
+            }
+        }
+        if std::mem::take(&mut self.pending_cursor_grab) {
+            let result = if self.cursor_captured {
+                // not every platform can lock, confining is the next best thing
+                window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+            } else {
+                window.set_cursor_grab(CursorGrabMode::None)
+            };
+            if let Err(e) = result {
+                log::warn!("Unable to change cursor grab: {}", e);
+            }
+            self.pending_cursor_visible = true;
+        }
+        if std::mem::take(&mut self.pending_cursor_visible) {
+            window.set_cursor_visible(self.cursor_visible && !self.cursor_captured);
+        }
+        if std::mem::take(&mut self.pending_cursor) {
+            match &self.cursor {
+                Cursor::Icon(icon) => window.set_cursor(*icon),
+                Cursor::Custom(image) => {
+                    // custom cursors are uploaded once and reused by id
+                    let cursor = match self.custom_cursors.get(&image.id) {
+                        Some(cursor) => cursor.clone(),
+                        None => {
+                            let source = match CustomCursor::from_rgba(
+                                image.rgba.to_vec(),
+                                image.width,
+                                image.height,
+                                image.hotspot.0,
+                                image.hotspot.1,
+                            ) {
+                                Ok(source) => source,
+                                Err(e) => {
+                                    log::error!("Invalid custom cursor: {}", e);
+                                    return;
+                                }
+                            };
+                            let cursor = event_loop.create_custom_cursor(source);
+                            self.custom_cursors.insert(image.id, cursor.clone());
+                            cursor
+                        }
+                    };
+                    window.set_cursor(cursor);
+                }
+            }
+        }
+    }
+}
+
+#[allow(unused_variables)]
+pub(crate) fn update_safe_area(
+    world: &mut World,
+    window: &Window,
+    #[cfg(target_os = "android")] android_app: Option<
+        &winit::platform::android::activity::AndroidApp,
+    >,
+) {
+    #[cfg(target_os = "android")]
+    if let Some(app) = android_app {
+        let rect = app.content_rect();
+        world.resource_mut::<WindowSettings>().safe_area = Some((
+            Vec2::new(rect.left as f32, rect.top as f32),
+            Vec2::new(
+                (rect.right - rect.left) as f32,
+                (rect.bottom - rect.top) as f32,
+            ),
+        ));
+    }
+    // winit's inner rect is the safe area on ios
+    #[cfg(target_os = "ios")]
+    if let (Ok(inner), Ok(outer)) = (window.inner_position(), window.outer_position()) {
+        let size = window.inner_size();
+        world.resource_mut::<WindowSettings>().safe_area = Some((
+            Vec2::new((inner.x - outer.x) as f32, (inner.y - outer.y) as f32),
+            Vec2::new(size.width as f32, size.height as f32),
+        ));
+    }
+}
+
+pub(crate) fn set_focused(world: &mut World, focused: bool) {
+    let settings = world.resource_mut::<WindowSettings>();
+    if settings.focused == focused {
+        return;
+    }
+    settings.focused = focused;
+    if focused {
+        settings.refresh_cursor_grab();
+    }
+    world.send_event(WindowFocused(focused));
+}
+
+pub(crate) fn set_visible(world: &mut World, visible: bool) {
+    let settings = world.resource_mut::<WindowSettings>();
+    if settings.visible == visible {
+        return;
+    }
+    settings.visible = visible;
+    world.send_event(WindowVisibilityChanged { visible });
+
+    let time = world.resource_mut::<Time>();
+    time.reset_clock();
+    let already_paused = time.is_paused();
+    let background = world.resource_mut::<BackgroundSettings>();
+    let pause = if visible {
+        std::mem::take(&mut background.paused).then_some(false)
+    } else if background.pause_when_hidden && !already_paused {
+        background.paused = true;
+        Some(true)
+    } else {
+        None
+    };
+    match pause {
+        Some(true) => world.resource_mut::<Time>().pause(),
+        Some(false) => world.resource_mut::<Time>().resume(),
+        None => {}
+    }
+
+    #[cfg(feature = "audio")]
+    {
+        let already_muted = world.resource::<crate::audio::Mixer>().master.muted;
+        let background = world.resource_mut::<BackgroundSettings>();
+        let mute = if visible {
+            std::mem::take(&mut background.muted).then_some(false)
+        } else if background.mute_when_hidden && !already_muted {
+            background.muted = true;
+            Some(true)
+        } else {
+            None
+        };
+        if let Some(mute) = mute {
+            world.resource_mut::<crate::audio::Mixer>().master.muted = mute;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::event::Events;
+
+    fn paused(world: &World) -> bool {
+        world.resource::<Time>().is_paused()
+    }
+
+    #[cfg(feature = "audio")]
+    fn muted(world: &World) -> bool {
+        world.resource::<crate::audio::Mixer>().master.muted
+    }
+
+    #[test]
+    fn hiding_pauses_and_showing_undoes_only_what_hiding_did() {
+        let mut world = crate::create_world();
+        set_visible(&mut world, false);
+        assert!(paused(&world));
+        #[cfg(feature = "audio")]
+        assert!(muted(&world));
+        set_visible(&mut world, true);
+        assert!(!paused(&world));
+        #[cfg(feature = "audio")]
+        assert!(!muted(&world));
+        assert_eq!(
+            world
+                .resource::<Events<WindowVisibilityChanged>>()
+                .iter()
+                .map(|event| event.visible)
+                .collect::<Vec<_>>(),
+            [false, true]
+        );
+
+        // a game that was already paused and muted stays that way
+        world.resource_mut::<Time>().pause();
+        #[cfg(feature = "audio")]
+        {
+            world.resource_mut::<crate::audio::Mixer>().master.muted = true;
+        }
+        set_visible(&mut world, false);
+        set_visible(&mut world, true);
+        assert!(paused(&world));
+        #[cfg(feature = "audio")]
+        assert!(muted(&world));
+    }
+
+    #[test]
+    fn hiding_leaves_time_and_sound_alone_when_turned_off() {
+        let mut world = crate::create_world();
+        let background = world.resource_mut::<BackgroundSettings>();
+        background.pause_when_hidden = false;
+        background.mute_when_hidden = false;
+        set_visible(&mut world, false);
+        assert!(!paused(&world));
+        #[cfg(feature = "audio")]
+        assert!(!muted(&world));
+        // showing twice doesn't resume a pause from while it was hidden
+        world.resource_mut::<Time>().pause();
+        set_visible(&mut world, true);
+        set_visible(&mut world, true);
+        assert!(paused(&world));
+    }
+}